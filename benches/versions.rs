@@ -0,0 +1,71 @@
+use std::hint::black_box;
+use std::process::{Command, Stdio};
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use rvm_core::parsing::version_parser::{sort_by_cached_precedence_key, Range, Version};
+
+// timings of the paths that have to stay fast, so a change made for speed (or one that wasn't) can be checked
+// every case runs on the same deterministic input, throughput is in versions (processes for the cold start)
+// `cargo bench -- --save-baseline base`, change things, then `cargo bench -- --baseline base` says what got slower
+
+const POOL: usize = 100_000;
+
+fn versions(c: &mut Criterion) {
+  let texts = version_texts(POOL);
+  let pool: Vec<Version> = texts.iter().filter_map(|t| Version::parse(t.as_str()).ok()).collect();
+  let nightlies: Vec<Version> = nightly_texts(POOL).iter().filter_map(|t| Version::parse(t.as_str()).ok()).collect();
+  let range = Range::parse(">=4.1.0, <4.4.0, !=4.2.3").unwrap_or_else(|err| panic!("Failed to parse range: {:?}", err));
+  let mut group = c.benchmark_group("versions");
+  group.sample_size(10);
+  group.throughput(Throughput::Elements(texts.len() as u64));
+  group.bench_function("parse", |b| b.iter(|| {
+    for text in &texts {
+      let _ = black_box(Version::parse(text.as_str()));
+    }
+  }));
+  for (name, input) in [("sort", &pool), ("sort-nightlies", &nightlies)] {
+    group.throughput(Throughput::Elements(input.len() as u64));
+    group.bench_function(name, |b| b.iter_batched_ref(|| input.clone(), |versions| versions.sort(), BatchSize::LargeInput));
+    group.bench_function(format!("{}-cached-key", name), |b| {
+      b.iter_batched_ref(|| input.clone(), |versions| sort_by_cached_precedence_key(versions, |v| v), BatchSize::LargeInput)
+    });
+  }
+  group.throughput(Throughput::Elements(pool.len() as u64));
+  group.bench_function("range-contains", |b| b.iter(|| black_box(pool.iter().filter(|v| range.contains(v)).count())));
+  group.finish();
+}
+
+fn cold_start(c: &mut Criterion) {
+  let exe = env!("CARGO_BIN_EXE_rvm");
+  let mut group = c.benchmark_group("process");
+  group.sample_size(10);
+  // whether anything resolves doesn't matter, only how long a fresh process takes to say so
+  group.bench_function("resolve-cold-start", |b| b.iter(|| {
+    let _ = Command::new(exe).arg("resolve").stdout(Stdio::null()).stderr(Stdio::null()).status();
+  }));
+  group.finish();
+}
+
+criterion_group!(benches, versions, cold_start);
+criterion_main!(benches);
+
+// n version strings shaped like an index's: releases, nightlies, release candidates and builds
+fn version_texts(n: usize) -> Vec<String> {
+  (0..n)
+    .map(|i| {
+      let (major, minor, patch) = (i % 5 + 1, i / 5 % 10, i / 50 % 20);
+      match i % 7 {
+        0 => format!("{}.{}.{}-nightly.{}", major, minor, patch, 20240000 + i % 10_000),
+        1 => format!("{}.{}.{}-rc.{}", major, minor, patch, i % 3 + 1),
+        2 => format!("{}.{}.{}+win", major, minor, patch),
+        _ => format!("{}.{}.{}", major, minor, patch),
+      }
+    })
+    .collect()
+}
+
+// n snapshots of a few development series, a channel's index where every comparison gets to the pre-release
+fn nightly_texts(n: usize) -> Vec<String> {
+  (0..n)
+    .map(|i| format!("4.{}.0-nightly.{}", i % 3 + 3, 20200000 + (i * 7919) % n))
+    .collect()
+}
@@ -0,0 +1,20 @@
+use std::path::Path;
+use crate::actions::lock_installs;
+use crate::args::Cli;
+use crate::exit::OrExit;
+use crate::install::install_dir;
+use crate::install::local::adopt;
+use crate::parsing::version_parser::Version;
+use crate::theme;
+
+// `rvm adopt <dir> --as <version>`, for the unmanaged dirs list and doctor point out
+pub fn main(dir: &Path, version: &str, options: &Cli) {
+  let _lock = lock_installs(options);
+  let version = Version::parse(version).or_exit("Failed to parse version");
+  if options.dry_run {
+    println!("Would move {} to {}", dir.display(), install_dir(&version).display());
+    return;
+  }
+  let dest = adopt(dir, &version).or_exit(format!("Failed to adopt {}", dir.display()));
+  println!("R {} {} ({})", theme::version(&version), theme::success("adopted"), dest.display());
+}
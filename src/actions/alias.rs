@@ -0,0 +1,44 @@
+use crate::actions::lock_installs;
+use crate::alias::Aliases;
+use crate::args::{AliasAction, Cli};
+use crate::audit::{self, Change};
+use crate::exit::{fail, Exit};
+use crate::utils::today;
+
+pub fn main(action: &AliasAction, options: &Cli) {
+  // held from before loading, so a change made meanwhile by another rvm isn't saved over
+  let _lock = (!matches!(action, AliasAction::List)).then(|| lock_installs(options));
+  let mut aliases = Aliases::load().unwrap_or_else(|err| panic!("Failed to read {}: {}", Aliases::path().display(), err));
+  match action {
+    AliasAction::List => {
+      let today = today();
+      for (name, target) in &aliases.aliases {
+        match aliases.expires.get(name) {
+          Some(day) if day.as_str() <= today.as_str() => println!("{} -> {} (expired {})", name, target, day),
+          Some(day) => println!("{} -> {} (expires {})", name, target, day),
+          None => println!("{} -> {}", name, target),
+        }
+      }
+    },
+    AliasAction::Set {name, target, expires} => {
+      let old = aliases.set(name, target).unwrap_or_else(|err| panic!("Failed to set alias: {}", err));
+      aliases.set_expiry(name, expires.as_deref());
+      aliases.save().unwrap_or_else(|err| panic!("Failed to save aliases: {}", err));
+      audit::record(Change::Alias { name: name.clone(), target: Some(target.clone()), was: old.clone() });
+      match old {
+        Some(old) => println!("{} -> {} (was {})", name, target, old),
+        None => println!("{} -> {}", name, target),
+      }
+    },
+    AliasAction::Remove {name} => {
+      match aliases.remove(name) {
+        Some(old) => {
+          aliases.save().unwrap_or_else(|err| panic!("Failed to save aliases: {}", err));
+          println!("Removed {} (was {})", name, old);
+          audit::record(Change::Alias { name: name.clone(), target: None, was: Some(old) });
+        },
+        None => fail(Exit::NoMatch, format!("No alias called {}", name))
+      }
+    },
+  }
+}
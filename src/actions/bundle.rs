@@ -0,0 +1,69 @@
+use std::path::{Path, PathBuf};
+use crate::activate::bin_dirs;
+use crate::args::Cli;
+use crate::exit::{fail, Exit, OrExit};
+use crate::install::{install_dir, installed_versions};
+use crate::install::meta::InstallMeta;
+use crate::install::relocate::rebase;
+use crate::parsing::version_parser::Version;
+use crate::resolve::request_range;
+use crate::shell::Shell;
+use crate::theme;
+use crate::utils::{copy_dir, ToAbsolute};
+
+// a copy of an install that works without rvm: its files rewritten to point into prefix, where it's going to be used,
+// and an env.sh at its top that puts it first on PATH. what a Dockerfile copies in, e.g.
+//   rvm bundle 4.3 -o r --prefix /opt/R/4.3.1
+//   COPY r /opt/R/4.3.1
+//   ENV PATH=/opt/R/4.3.1/bin:$PATH
+// packages aren't in it, they're in rvm's library dir and not the install
+
+pub const ENV_SCRIPT: &str = "env.sh";
+
+pub fn main(request: &str, output: &Path, prefix: Option<&Path>, options: &Cli) {
+  let range = request_range(request).or_exit("Failed to parse version");
+  let version = range.max_satisfying(&installed_versions())
+    .unwrap_or_else(|| fail(Exit::NoMatch, format!("No installed version matches {}", request)))
+    .clone();
+  let output = output.to_path_buf().to_absolute();
+  let prefix = match prefix {
+    Some(prefix) if !prefix.is_absolute() => fail(Exit::Usage, format!("--prefix {} has to be an absolute path", prefix.display())),
+    Some(prefix) => prefix.to_path_buf(),
+    None => output.clone(),
+  };
+  if output.read_dir().is_ok_and(|mut entries| entries.next().is_some()) {
+    fail(Exit::Usage, format!("{} isn't empty, bundle into a new dir", output.display()));
+  }
+  let install = install_dir(&version);
+  if options.dry_run {
+    println!("Would copy R {} from {} to {}", version, install.display(), output.display());
+    println!("Would rewrite its files to point into {}", prefix.display());
+    println!("Would write {}", output.join(ENV_SCRIPT).display());
+    return;
+  }
+  // an install that moved and wasn't used since still points where it was
+  let from = InstallMeta::load(&version).prefix.unwrap_or_else(|| install.clone());
+  copy_dir(&install, &output).unwrap_or_else(|err| panic!("Failed to copy R {} to {}: {}", version, output.display(), err));
+  let changed = rebase(&version, &output, &from, &prefix).or_exit(format!("Failed to rebase the copy of R {}", version));
+  if options.verbose > 0 {
+    eprintln!("Rewrote {} file(s) to point into {}", changed.len(), prefix.display());
+  }
+  let bins: Vec<PathBuf> = bin_dirs(&output).iter()
+    .map(|dir| prefix.join(dir.strip_prefix(&output).unwrap_or(dir)))
+    .collect();
+  std::fs::write(output.join(ENV_SCRIPT), env_script(&version, &prefix, &bins))
+    .unwrap_or_else(|err| panic!("Failed to write {}: {}", output.join(ENV_SCRIPT).display(), err));
+  println!("{}", theme::success(format!("Bundled R {} in {}, `. {}` to use it", version, output.display(), prefix.join(ENV_SCRIPT).display())));
+}
+
+/// The sh script making the bundle of version at prefix the R that runs
+pub fn env_script(version: &Version, prefix: &Path, bins: &[PathBuf]) -> String {
+  let path = bins.iter().map(|dir| dir.display().to_string()).collect::<Vec<_>>().join(":");
+  format!(
+    "# R {} bundled by rvm, `. {}` in a shell to use it\n{}\n{}:\"$PATH\"\n",
+    version,
+    prefix.join(ENV_SCRIPT).display(),
+    Shell::Bash.set_var("R_HOME", &prefix.display().to_string()),
+    Shell::Bash.set_var("PATH", &path),
+  )
+}
@@ -0,0 +1,83 @@
+use serde::Serialize;
+use crate::args::Cli;
+use crate::exit::{fail, Exit, OrExit};
+use crate::index::{newest_matching, remote_versions, ArtifactMeta, CachePolicy, CranSource, VersionSource};
+use crate::install::installed_versions;
+use crate::local_utils::curr_dir;
+use crate::output::{opt_field, print_json, print_porcelain};
+use crate::parsing::version_parser::Version;
+use crate::resolve::{active_range, request_range};
+
+#[derive(Serialize)]
+struct Notes<'a> {
+  version: &'a Version,
+  released: Option<&'a str>,
+  url: Option<&'a str>,
+  notes: Option<String>,
+  /// why there are none, when getting them failed
+  error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Changes<'a> {
+  /// the newest installed in the range, the notes start after it
+  installed: Option<&'a Version>,
+  newest: &'a Version,
+  /// newest first
+  versions: Vec<Notes<'a>>,
+}
+
+// `rvm changes [range]`, the release notes of what an upgrade within range would go through: every version after the
+// newest installed one up to the newest match, the newest match alone when none is installed
+pub fn main(request: Option<&str>, options: &Cli) {
+  let range = match request {
+    Some(request) => request_range(request).or_exit(format!("Failed to resolve {}", request)),
+    None => active_range(&curr_dir()).or_exit("Failed to resolve version").0,
+  };
+  let source = CranSource::default();
+  let entries = remote_versions(&source, CachePolicy::Default).or_exit("Failed to list remote versions");
+  let Some(newest) = newest_matching(&range, &entries) else {
+    fail(Exit::NoMatch, format!("No remote version matches {}", range))
+  };
+  let installed_versions = installed_versions();
+  let installed = range.max_satisfying(&installed_versions);
+  let mut between: Vec<&ArtifactMeta> = entries.iter()
+    .filter(|m| installed.map_or(m.version == newest.version, |installed| &m.version > installed) && m.version <= newest.version)
+    .filter(|m| range.contains(&m.version) && (m.version.pre_release_tag().is_none() || range.names_pre_release()))
+    .collect();
+  between.sort_by(|a, b| b.version.cmp(&a.version));
+  // the index has an entry per platform, one is enough for the notes
+  between.dedup_by(|a, b| a.version == b.version);
+  let versions: Vec<Notes> = between.iter().map(|meta| {
+    let (notes, error) = match source.release_notes(meta) {
+      Ok(notes) => (notes, None),
+      Err(err) => (None, Some(err.to_string())),
+    };
+    Notes { version: &meta.version, released: meta.released.as_deref(), url: meta.notes_url.as_deref(), notes, error }
+  }).collect();
+  let changes = Changes { installed, newest: &newest.version, versions };
+  if options.json {
+    return print_json("changes", &changes);
+  }
+  if options.porcelain {
+    let rows: Vec<Vec<String>> = changes.versions.iter()
+      .map(|n| vec![n.version.to_string(), opt_field(&n.released), opt_field(&n.url), n.notes.is_some().to_string()])
+      .collect();
+    return print_porcelain(&rows);
+  }
+  if changes.versions.is_empty() {
+    return println!("R {} is the newest match of {} and it's installed, nothing to review", newest.version, range);
+  }
+  for notes in &changes.versions {
+    match notes.released {
+      Some(day) => println!("R {} ({})", notes.version, day),
+      None => println!("R {}", notes.version),
+    }
+    match (&notes.notes, &notes.error) {
+      (Some(text), _) => println!("{}", text),
+      (None, Some(err)) => println!("Failed to get the release notes from {}: {}", opt_field(&notes.url), err),
+      (None, None) => println!("No release notes"),
+    }
+    println!();
+  }
+}
@@ -0,0 +1,164 @@
+use std::cmp::Ordering;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use duct::cmd;
+use serde::Serialize;
+use crate::activate::{env_for, find_binary};
+use crate::args::Cli;
+use crate::exit::{fail, Exit, OrExit};
+use crate::config::{Config, SmokeTest};
+use crate::install::{install_dir, installed_versions};
+use crate::output::{opt_field, print_json, print_porcelain};
+use crate::parsing::version_parser::Version;
+use crate::resolve::request_range;
+use crate::theme;
+
+// `rvm check [version]`, whether installs actually run: verify says the files are the ones installed,
+// this says they still work (a system library went away, the wrong build got registered as a version...)
+
+#[derive(Serialize)]
+struct Checked {
+  version: Version,
+  /// ok, broken (a command failed) or mismatch (it says it's another version)
+  status: &'static str,
+  /// what `R --version` said, none when it didn't say
+  reported: Option<Version>,
+  /// the command that failed and why
+  failed: Option<String>,
+  #[serde(skip)]
+  output: String,
+}
+
+pub fn main(request: Option<&str>, options: &Cli) {
+  let installed = installed_versions();
+  let versions = match request {
+    Some(request) => {
+      let range = request_range(request).or_exit("Failed to parse version");
+      vec![range.max_satisfying(&installed).unwrap_or_else(|| fail(Exit::NoMatch, format!("No installed version matches {}", request))).clone()]
+    },
+    None => installed,
+  };
+  let test = Config::get().check;
+  if options.dry_run {
+    for version in &versions {
+      for step in test_steps(&test) {
+        println!("Would run {} for R {}", expand(step, version).join(" "), version);
+      }
+    }
+    return;
+  }
+  let results: Vec<Checked> = versions.into_iter().map(|v| check(v, &test)).collect();
+  if options.json {
+    print_json("check", &results);
+  } else if options.porcelain {
+    let rows: Vec<Vec<String>> = results.iter()
+      .map(|r| vec![r.version.to_string(), r.status.to_owned(), opt_field(&r.reported), opt_field(&r.failed)])
+      .collect();
+    print_porcelain(&rows);
+  } else {
+    for result in &results {
+      print_human(result, options);
+    }
+  }
+  if results.iter().any(|r| r.status != "ok") {
+    std::process::exit(Exit::Verification.code());
+  }
+}
+
+fn print_human(result: &Checked, options: &Cli) {
+  let version = theme::version(&result.version);
+  match (result.status, &result.reported, &result.failed) {
+    ("ok", _, _) => println!("R {} {}", version, theme::success("ok")),
+    ("mismatch", Some(reported), _) => println!("R {} {}, it says it's R {}", version, theme::error("mismatch"), reported),
+    ("mismatch", None, _) => println!("R {} {}, it doesn't say its version", version, theme::error("mismatch")),
+    (_, _, failed) => println!("R {} {}, {}", version, theme::error("broken"), failed.as_deref().unwrap_or_default()),
+  }
+  if options.verbose > 0 && !result.output.is_empty() {
+    for line in result.output.lines() {
+      println!("  {}", line);
+    }
+  }
+}
+
+fn test_steps(test: &SmokeTest) -> impl Iterator<Item = &Vec<String>> {
+  std::iter::once(&test.version).filter(|v| !v.is_empty()).chain(&test.steps)
+}
+
+fn expand(step: &[String], version: &Version) -> Vec<String> {
+  let binary = |name: &str| find_binary(version, name).unwrap_or_else(|| PathBuf::from(name)).display().to_string();
+  step.iter()
+    .map(|arg| {
+      let mut arg = arg.replace("{version}", &version.to_string()).replace("{home}", &install_dir(version).display().to_string());
+      // only looked up when used, an install without Rscript can still have its R checked
+      for (placeholder, name) in [("{binary}", "R"), ("{rscript}", "Rscript")] {
+        if arg.contains(placeholder) {
+          arg = arg.replace(placeholder, &binary(name));
+        }
+      }
+      arg
+    })
+    .collect()
+}
+
+fn check(version: Version, test: &SmokeTest) -> Checked {
+  let mut checked = Checked { version, status: "ok", reported: None, failed: None, output: String::new() };
+  let timeout = Duration::from_secs(test.timeout);
+  if !test.version.is_empty() {
+    let step = expand(&test.version, &checked.version);
+    match run(&step, &checked.version, timeout) {
+      Ok(output) => {
+        checked.reported = reported_version(&output);
+        // R says only the release, 4.4.0 for 4.4.0-rc1 or a build of it
+        if checked.reported.as_ref().map_or(true, |reported| reported.cmp_release(&checked.version) != Ordering::Equal) {
+          checked.status = "mismatch";
+        }
+        checked.output = output;
+      },
+      Err(err) => return Checked { status: "broken", failed: Some(format!("{}: {}", step.join(" "), err)), ..checked },
+    }
+  }
+  for step in &test.steps {
+    let step = expand(step, &checked.version);
+    if let Err(err) = run(&step, &checked.version, timeout) {
+      return Checked { status: "broken", failed: Some(format!("{}: {}", step.join(" "), err)), ..checked };
+    }
+  }
+  checked
+}
+
+// stdout and stderr of command with version active, an error when it can't run, exits non zero or outlives timeout
+fn run(command: &[String], version: &Version, timeout: Duration) -> Result<String, String> {
+  let (program, args) = command.split_first().ok_or_else(|| "empty command".to_owned())?;
+  let mut expression = cmd(program, args).stderr_to_stdout().stdout_capture().unchecked();
+  for (name, value) in env_for(version) {
+    expression = expression.env(name, value);
+  }
+  let handle = expression.start().map_err(|err| err.to_string())?;
+  let start = Instant::now();
+  let output = loop {
+    if let Some(output) = handle.try_wait().map_err(|err| err.to_string())? {
+      break output.clone();
+    }
+    if start.elapsed() > timeout {
+      let _ = handle.kill();
+      return Err(format!("still running after {}s", timeout.as_secs()));
+    }
+    std::thread::sleep(Duration::from_millis(50));
+  };
+  let text = String::from_utf8_lossy(&output.stdout).trim_end().to_owned();
+  match output.status.code() {
+    Some(0) => Ok(text),
+    Some(code) => Err(format!("exited with {}", code)),
+    None => Err("killed by a signal".to_owned()),
+  }
+}
+
+/// The version a `--version` output says: the word after "version", else the first word that parses as one
+pub fn reported_version(output: &str) -> Option<Version> {
+  let words: Vec<&str> = output.split_whitespace().collect();
+  let parse = |word: &str| Version::parse(word.trim_matches(|c: char| !c.is_ascii_alphanumeric())).ok().filter(|_| word.contains('.'));
+  words.windows(2)
+    .find(|pair| pair[0].eq_ignore_ascii_case("version"))
+    .and_then(|pair| parse(pair[1]))
+    .or_else(|| words.iter().find_map(|w| parse(w)))
+}
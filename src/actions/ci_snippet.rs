@@ -0,0 +1,51 @@
+use crate::args::SnippetFormat;
+use crate::exit::{fail, Exit, OrExit};
+use crate::lockfile::{LOCKED_VAR, LOCK_FILE};
+use crate::local_utils::curr_dir;
+use crate::parsing::version_parser::Version;
+use crate::resolve::{find_project_file, project_lock, PROJECT_FILE};
+
+// what CI needs to get the R the project's lockfile has, and nothing else: rvm itself, that exact version and
+// RVM_LOCKED so every later rvm fails instead of quietly resolving something different when the lockfile goes stale.
+// the project's Renv.yaml and rvm.lock are expected at the root of the repository (the build context for docker)
+
+const RVM_INSTALL: &str = "cargo install --git https://github.com/notPlancha/rvm rvm";
+
+pub fn main(format: SnippetFormat) {
+  let project = find_project_file(&curr_dir()).unwrap_or_else(|| fail(Exit::NoMatch, format!("No {} here or above, `rvm init` makes one", PROJECT_FILE)));
+  let lock = project_lock(&project).or_exit("Failed to read the project's lockfile");
+  print!("{}", snippet(format, &lock.version));
+}
+
+/// The snippet installing version in format
+pub fn snippet(format: SnippetFormat, version: &Version) -> String {
+  let header = |flag: &str| format!("# R {} as {} locks it, from `rvm ci-snippet --format {}`\n", version, LOCK_FILE, flag);
+  match format {
+    SnippetFormat::Dockerfile => format!(
+      "{}ENV {}=1 RVM_NO_INPUT=1\nRUN {} \\\n && rvm install ={}\nCOPY {} {} ./\nRUN rvm exec -- R --version\n",
+      header("dockerfile"), LOCKED_VAR, RVM_INSTALL, version, PROJECT_FILE, LOCK_FILE,
+    ),
+    SnippetFormat::GithubActions => format!(
+      "{}env:\n  {}: \"1\"\n  RVM_NO_INPUT: \"1\"\n  RVM_HOME: ${{{{ github.workspace }}}}/.rvm\nsteps:\n  - uses: actions/checkout@v4\n  - uses: actions/cache@v4\n    with:\n      path: .rvm/versions\n      key: rvm-${{{{ runner.os }}}}-${{{{ hashFiles('{}') }}}}\n  - run: {}\n  - run: rvm install ={}\n  - run: rvm env --porcelain | tr '\\t' '=' >> \"$GITHUB_ENV\"\n",
+      header("github-actions"), LOCKED_VAR, LOCK_FILE, RVM_INSTALL, version,
+    ),
+    SnippetFormat::Gitlab => format!(
+      "{}variables:\n  {}: \"1\"\n  RVM_NO_INPUT: \"1\"\n  RVM_HOME: \"$CI_PROJECT_DIR/.rvm\"\ncache:\n  key:\n    files: [{}]\n  paths: [.rvm/versions]\nbefore_script:\n  - {}\n  - rvm install ={}\n  - eval \"$(rvm env --shell bash)\"\n",
+      header("gitlab"), LOCKED_VAR, LOCK_FILE, RVM_INSTALL, version,
+    ),
+  }
+}
+
+/// Where a new project keeps its CI config in format, and the snippet wrapped in what it needs to run on its own
+pub fn ci_file(format: SnippetFormat, version: &Version) -> (&'static str, String) {
+  let snippet = snippet(format, version);
+  match format {
+    // rvm is installed with cargo
+    SnippetFormat::Dockerfile => ("Dockerfile", format!("FROM rust:1\n{}", snippet)),
+    SnippetFormat::GithubActions => {
+      let job: String = snippet.lines().map(|line| format!("    {}\n", line)).collect();
+      (".github/workflows/r.yml", format!("name: R\non: [push, pull_request]\njobs:\n  check:\n    runs-on: ubuntu-latest\n{}      - run: rvm exec -- R --version\n", job))
+    },
+    SnippetFormat::Gitlab => (".gitlab-ci.yml", format!("{}check:\n  script:\n    - rvm exec -- R --version\n", snippet)),
+  }
+}
@@ -0,0 +1,33 @@
+use std::cmp::Ordering;
+use serde_json::json;
+use crate::args::Cli;
+use crate::exit::{fail, Exit};
+use crate::output::print_json;
+use crate::parsing::version_parser::{Matching, Version};
+
+// = exits 0 so `rvm compare a b` alone answers "are they the same", < and > get 1 and 2
+pub fn main(a: &str, b: &str, explain: bool, ignore_case: bool, options: &Cli) {
+  let parse = |v: &str| Version::parse(v).unwrap_or_else(|err| fail(Exit::Usage, format!("Failed to parse version {}: {:?}", v, err)));
+  let (a, b) = (parse(a), parse(b));
+  let matching = Matching { caseless_pre_release: ignore_case };
+  let (symbol, code) = match a.cmp_in(&b, matching) {
+    Ordering::Less => ("<", 1),
+    Ordering::Equal => ("=", 0),
+    Ordering::Greater => (">", 2),
+  };
+  let explanation = explain.then(|| a.matched_as(matching).explain_cmp(&b.matched_as(matching)));
+  if options.json {
+    let mut out = json!({"a": a, "b": b, "order": symbol});
+    if let Some(explanation) = &explanation {
+      out["component"] = json!(explanation.component);
+      out["explain"] = json!(explanation.to_string());
+    }
+    print_json("compare", &out);
+  } else {
+    println!("{}", symbol);
+    if let Some(explanation) = explanation {
+      println!("{}", explanation);
+    }
+  }
+  std::process::exit(code);
+}
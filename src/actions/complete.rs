@@ -0,0 +1,49 @@
+use clap::CommandFactory;
+use crate::alias::Aliases;
+use crate::args::Cli;
+use crate::exit::{fail, Exit};
+use crate::index::{completion_versions, CranSource};
+use crate::install::installed_versions;
+use crate::shell::Shell;
+
+// what the completions (see Shell::completion) run on every tab: the subcommands, and the versions a subcommand's first
+// argument can be. it's on every keypress, so nothing here touches the network or takes the install lock,
+// remote versions are the list the last index refresh saved
+
+/// subcommands whose first argument is an installed version (or an alias)
+const INSTALLED: [&str; 10] = ["uninstall", "exec", "pin", "unpin", "lock", "unlock", "shell", "global", "local", "verify"];
+
+pub fn script(shell: Shell) {
+  match shell.completion() {
+    Some(script) => println!("{}", script),
+    None => fail(Exit::Usage, format!("{:?} has no programmable completion", shell)),
+  }
+}
+
+pub fn main(words: &[String]) {
+  for candidate in candidates(words) {
+    println!("{}", candidate);
+  }
+}
+
+/// What completes the last of words, the ones starting with it
+pub fn candidates(words: &[String]) -> Vec<String> {
+  let (current, before) = match words.split_last() {
+    // powershell can't pass an empty argument
+    Some((current, before)) => (current.trim_matches('"'), before),
+    None => ("", &[][..]),
+  };
+  let options = match before {
+    [] => Cli::command().get_subcommands().filter(|c| !c.is_hide_set()).map(|c| c.get_name().to_owned()).collect(),
+    [command] if command == "install" || command == "try" => {
+      let keywords = ["latest", "stable", "lts"].map(str::to_owned);
+      keywords.into_iter().chain(completion_versions(&CranSource::default())).collect()
+    },
+    [command] if INSTALLED.contains(&command.as_str()) => {
+      let aliases = Aliases::load().map(|a| a.aliases.into_keys().collect()).unwrap_or_else(|_| vec![]);
+      installed_versions().into_iter().rev().map(|v| v.to_string()).chain(aliases).collect()
+    },
+    _ => vec![],
+  };
+  options.into_iter().filter(|o: &String| o.starts_with(current)).collect()
+}
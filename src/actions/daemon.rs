@@ -0,0 +1,52 @@
+use std::time::Duration;
+use serde::Serialize;
+use crate::args::Cli;
+use crate::daemon::{ask, serve, socket_path, Answer, Query};
+use crate::exit::{fail, Exit};
+use crate::output::{print_json, print_porcelain};
+
+#[derive(Serialize)]
+struct Status {
+  running: bool,
+  pid: Option<u32>,
+  /// project files it prefetches for
+  projects: usize,
+  socket: String,
+}
+
+// `rvm daemon`, in the foreground until `rvm daemon --stop`, logging with -v like any command
+pub fn main(stop: bool, status: bool, interval: u64, options: &Cli) {
+  if stop {
+    return match ask(&Query::Stop) {
+      Some(_) => println!("Stopped the daemon"),
+      None => fail(Exit::NoMatch, "No daemon is running"),
+    };
+  }
+  if status {
+    return print_status(options);
+  }
+  if options.verbose > 0 {
+    eprintln!("Listening on {}", socket_path().display());
+  }
+  serve(Duration::from_secs(interval)).unwrap_or_else(|err| fail(Exit::Failure, format!("Failed to run the daemon: {}", err)));
+}
+
+fn print_status(options: &Cli) {
+  let (pid, projects) = match ask(&Query::Ping) {
+    Some(Answer::Pong { pid, projects }) => (Some(pid), projects),
+    _ => (None, 0),
+  };
+  let status = Status { running: pid.is_some(), pid, projects, socket: socket_path().display().to_string() };
+  if options.json {
+    print_json("daemon", &status);
+  } else if options.porcelain {
+    print_porcelain(&[vec![status.running.to_string(), pid.map(|p| p.to_string()).unwrap_or_default(), projects.to_string()]]);
+  } else if let Some(pid) = pid {
+    println!("Running (pid {}), prefetching for {} project(s), on {}", pid, projects, status.socket);
+  } else {
+    println!("Not running");
+  }
+  if pid.is_none() {
+    std::process::exit(Exit::NoMatch.code());
+  }
+}
@@ -0,0 +1,74 @@
+use std::collections::{BTreeMap, HashMap};
+use crate::actions::confirm;
+use crate::args::Cli;
+use crate::audit::{self, Change};
+use crate::detect::{detect, Detected};
+use crate::local_utils::curr_dir;
+use crate::output::{opt_field, print_json, print_porcelain};
+use crate::parsing::version_parser::Range;
+use crate::parsing::yaml_ser::{read_yaml, write_yaml, Env};
+use crate::resolve::PROJECT_FILE;
+
+// `rvm detect`, the constraints the manifests in the current dir have, then offers to write them to its Renv.yaml:
+// R's as rversion and the rest as tools, what's already in the file and wasn't detected stays
+pub fn main(options: &Cli) {
+  let detected = detect(&curr_dir());
+  if options.json {
+    return print_json("detect", &detected);
+  }
+  if options.porcelain {
+    let rows: Vec<Vec<String>> = detected.iter()
+      .map(|d| vec![d.file.display().to_string(), d.tool.clone(), d.constraint.clone(), opt_field(&d.range), d.error.clone().unwrap_or_default()])
+      .collect();
+    return print_porcelain(&rows);
+  }
+  if detected.is_empty() {
+    return println!("No manifest here has a runtime constraint");
+  }
+  for d in &detected {
+    match (&d.range, &d.error) {
+      (Some(range), _) => println!("{} {}: {} -> {}", d.file.display(), d.tool, d.constraint, range),
+      (None, err) => eprintln!("{} {}: {}, skipped: {}", d.file.display(), d.tool, d.constraint, err.as_deref().unwrap_or_default()),
+    }
+  }
+  let usable: Vec<&Detected> = detected.iter().filter(|d| d.range.is_some()).collect();
+  let path = curr_dir().join(PROJECT_FILE);
+  let rversion = usable.iter().find(|d| d.tool == "R").and_then(|d| d.range.clone());
+  // a new project file needs an rversion, an existing one can take just tools
+  if usable.is_empty() || (rversion.is_none() && !path.exists()) {
+    return println!("Nothing to write to {}, no R constraint was found", PROJECT_FILE);
+  }
+  let tools: BTreeMap<String, Range> = usable.iter()
+    .filter(|d| d.tool != "R")
+    .filter_map(|d| Some((d.tool.clone(), d.range.clone()?)))
+    .collect();
+  let summary: Vec<String> = rversion.iter().map(|r| format!("rversion: {}", r))
+    .chain(tools.iter().map(|(tool, range)| format!("{}: {}", tool, range)))
+    .collect();
+  if options.dry_run {
+    return println!("Would write {} {}", path.display(), summary.join(", "));
+  }
+  if !confirm(options, &format!("Write {} to {}?", summary.join(", "), PROJECT_FILE), true) {
+    return;
+  }
+  let (mut env, old) = if path.exists() {
+    let env = read_yaml(path.clone()).unwrap_or_else(|err| panic!("Failed to read {}: {}", path.display(), err));
+    let old = env.rversion.clone();
+    (env, Some(old))
+  } else {
+    // not Env::default(), that asks CRAN for the latest version
+    let rversion = rversion.clone().unwrap_or_default();
+    let env = Env { name: None, description: None, r#type: None, version: None, rversion, rversion_expires: None, dependencies: HashMap::new(), tools: BTreeMap::new(), commands: BTreeMap::new(), env: BTreeMap::new() };
+    (env, None)
+  };
+  if let Some(range) = &rversion {
+    env.rversion = range.clone();
+  }
+  env.tools.extend(tools);
+  let new = env.rversion.to_string();
+  write_yaml(env, path.clone()).unwrap_or_else(|err| panic!("Failed to write {}: {}", path.display(), err));
+  if old.as_ref().map(Range::to_string).as_ref() != Some(&new) {
+    audit::record(Change::Local { path: path.clone(), rversion: new, was: old.as_ref().map(Range::to_string) });
+  }
+  println!("{} {}", path.display(), summary.join(", "));
+}
@@ -0,0 +1,70 @@
+use std::path::Path;
+use crate::args::Cli;
+use crate::exit::{fail, Exit};
+use crate::manifest::{Difference, Manifest, ManifestDiff};
+use crate::output::{print_json, print_porcelain};
+
+// `rvm diff old.toml [new.toml]`, what changed between two manifests from `rvm export`, or from one to this machine.
+// like diff(1) it exits 1 when there's a difference, so CI can fail on drift
+
+pub fn main(old: &Path, new: Option<&Path>, options: &Cli) {
+  let load = |path: &Path| Manifest::load(path).unwrap_or_else(|err| fail(Exit::Failure, format!("Failed to read {}: {}", path.display(), err)));
+  let old_manifest = load(old);
+  let new_manifest = match new {
+    Some(new) => load(new),
+    None => Manifest::current().unwrap_or_else(|err| fail(Exit::Failure, format!("Failed to read what's installed: {}", err))),
+  };
+  let diff = old_manifest.diff(&new_manifest);
+  if options.json {
+    print_json("diff", &diff);
+  } else if options.porcelain {
+    print_porcelain(&rows(&diff));
+  } else {
+    print_human(&diff);
+  }
+  if !diff.is_empty() {
+    std::process::exit(1);
+  }
+}
+
+fn rows(diff: &ManifestDiff) -> Vec<Vec<String>> {
+  fn row<T: ToString>(kind: &str, difference: &Difference<T>) -> Vec<String> {
+    let (change, old, new) = match difference {
+      Difference::Added { new, .. } => ("added", String::new(), new.to_string()),
+      Difference::Removed { old, .. } => ("removed", old.to_string(), String::new()),
+      Difference::Changed { old, new, .. } => ("changed", old.to_string(), new.to_string()),
+    };
+    vec![kind.to_owned(), change.to_owned(), difference.key().to_owned(), old, new]
+  }
+  let installs = diff.installs.iter().map(|d| row("install", d));
+  let aliases = diff.aliases.iter().map(|d| row("alias", d));
+  let tools = diff.tools.iter().map(|d| row("tool", d));
+  installs.chain(aliases).chain(tools).collect()
+}
+
+fn print_human(diff: &ManifestDiff) {
+  if diff.is_empty() {
+    return println!("No differences");
+  }
+  for difference in &diff.installs {
+    match difference {
+      Difference::Added { new, .. } => println!("+ R {}", new),
+      Difference::Removed { old, .. } => println!("- R {}", old),
+      Difference::Changed { old, new, .. } => println!("~ R {} -> {}", old, new),
+    }
+  }
+  for difference in &diff.aliases {
+    match difference {
+      Difference::Added { key, new } => println!("+ alias {} -> {}", key, new),
+      Difference::Removed { key, old } => println!("- alias {} -> {}", key, old),
+      Difference::Changed { key, old, new } => println!("~ alias {} -> {} (was {})", key, new, old),
+    }
+  }
+  for difference in &diff.tools {
+    match difference {
+      Difference::Added { key, .. } => println!("+ {}", key),
+      Difference::Removed { key, .. } => println!("- {}", key),
+      Difference::Changed { key, .. } => println!("~ {}", key),
+    }
+  }
+}
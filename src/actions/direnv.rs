@@ -0,0 +1,27 @@
+use crate::activate::env_for_active;
+use crate::args::{Cli, DirenvAction};
+use crate::local_utils::curr_dir;
+use crate::resolve::active_version;
+use crate::shell::Shell;
+use crate::exit::OrExit;
+
+// direnv runs .envrc with bash and restores everything when leaving the dir, so no shims or hooks needed
+pub fn main(action: &DirenvAction, options: &Cli) {
+  match action {
+    DirenvAction::Export {version} => {
+      let active = active_version(version.as_deref(), &curr_dir()).or_exit("Failed to resolve version");
+      println!("{}", Shell::Bash.set_vars(&env_for_active(&active)));
+      if options.verbose > 0 {
+        eprintln!("rvm: R {} ({})", active.version, active.origin);
+      }
+    },
+    DirenvAction::Envrc {version} => {
+      println!("# R from rvm, the layout can also go in ~/.config/direnv/direnvrc with `rvm init direnv`");
+      println!("type use_rvm &>/dev/null || eval \"$(rvm init direnv)\"");
+      match version {
+        Some(version) => println!("use rvm {}", version),
+        None => println!("use rvm"),
+      }
+    },
+  }
+}
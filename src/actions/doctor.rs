@@ -0,0 +1,281 @@
+use std::time::Duration;
+use crate::alias::Aliases;
+use crate::args::Cli;
+use crate::config::Config;
+use crate::filelock::{FileLock, LockError};
+use crate::index::{CranSource, IndexCache, VersionSource};
+use crate::local_utils::curr_dir;
+use crate::resolve::active_version;
+use crate::utils::today;
+use crate::install::transaction::{verify_tree, JournalEntry};
+use crate::install::{install_dir, installed_versions, rebase_moved, versions_dir};
+use crate::install::scan::{scan, SkipReason, Skipped};
+use crate::install::meta::InstallMeta;
+use crate::install::mirrors::{self, Health, MirrorHealth};
+use crate::layout::state_dir;
+use crate::output::{opt_field, print_json, print_porcelain};
+use crate::theme;
+use serde::Serialize;
+
+#[derive(Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Severity {
+  Problem,
+  Warning,
+}
+
+impl Severity {
+  pub(crate) fn label(&self) -> &'static str {
+    match self {
+      Severity::Problem => "problem",
+      Severity::Warning => "warning",
+    }
+  }
+}
+
+#[derive(Serialize)]
+pub(crate) struct Finding {
+  pub(crate) severity: Severity,
+  pub(crate) message: String,
+  pub(crate) fix: Option<String>,
+}
+
+impl Finding {
+  fn problem(message: String, fix: Option<String>) -> Self {
+    Self { severity: Severity::Problem, message, fix }
+  }
+  fn warning(message: String, fix: Option<String>) -> Self {
+    Self { severity: Severity::Warning, message, fix }
+  }
+}
+
+#[derive(Serialize)]
+struct MirrorStatus<'a> {
+  from: &'a str,
+  to: &'a str,
+  #[serde(flatten)]
+  health: Health,
+  /// whether recent failures put it behind the others
+  degraded: bool,
+}
+
+/// `rvm doctor --mirrors`, what downloads measured of each mirror, in the order the next download tries them
+pub fn mirrors(options: &Cli) {
+  let config = Config::get();
+  let health = MirrorHealth::load();
+  let now = mirrors::now();
+  let mut statuses: Vec<MirrorStatus> = config.mirrors.iter().map(|m| {
+    let measured = health.mirrors.get(&m.to).cloned().unwrap_or_default();
+    MirrorStatus { from: &m.from, to: &m.to, degraded: measured.penalty(now).0 > 0, health: measured }
+  }).collect();
+  statuses.sort_by_key(|s| s.health.penalty(now));
+  if options.json {
+    return print_json("doctor-mirrors", &statuses);
+  }
+  if options.porcelain {
+    let rows: Vec<Vec<String>> = statuses.iter()
+      .map(|s| vec![s.to.to_owned(), s.from.to_owned(), s.health.successes.to_string(), s.health.failures.to_string(), s.health.failing.to_string(), opt_field(&s.health.latency_ms)])
+      .collect();
+    return print_porcelain(&rows);
+  }
+  if statuses.is_empty() {
+    return println!("No mirrors configured, downloads go where the index points");
+  }
+  for status in &statuses {
+    let health = &status.health;
+    let latency = health.latency_ms.map(|ms| format!("{} ms", ms)).unwrap_or_else(|| "not measured".to_owned());
+    let failures = match health.last_failure {
+      Some(at) if status.degraded => theme::warn(format!("{} failures in a row, last {} ago", health.failing, format_duration(now.saturating_sub(at)))).to_string(),
+      _ => format!("{} failed", health.failures),
+    };
+    println!("{}  {} ok, {}, {}  (for {})", status.to, health.successes, failures, latency, status.from);
+  }
+}
+
+fn format_duration(seconds: u64) -> String {
+  match seconds {
+    s if s < 60 => format!("{}s", s),
+    s if s < 60 * 60 => format!("{}m", s / 60),
+    s => format!("{}h", s / 60 / 60),
+  }
+}
+
+/// How far the checks go
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Mode {
+  /// nothing is changed, not even by looking: the lock isn't taken to see whether it's stale
+  ReadOnly,
+  Check,
+  /// fixing what can be fixed safely
+  Fix,
+}
+
+/// What every check found, fixing what it can in Mode::Fix
+pub(crate) fn findings(mode: Mode) -> Vec<Finding> {
+  let mut findings: Vec<Finding> = vec![];
+  findings.extend(check_config());
+  findings.extend(check_path());
+  findings.extend(check_installs(mode == Mode::Fix));
+  // getting the lock clears the pid a dead rvm left in it
+  if mode != Mode::ReadOnly {
+    findings.extend(check_lock());
+  }
+  findings.extend(check_index_cache());
+  findings.extend(check_support());
+  findings
+}
+
+pub fn main(fix: bool, options: &Cli) {
+  let findings = findings(if fix { Mode::Fix } else { Mode::Check });
+  let problems = findings.iter().filter(|f| matches!(f.severity, Severity::Problem)).count();
+  if options.json || options.porcelain {
+    if options.json {
+      print_json("doctor", &findings);
+    } else {
+      let rows: Vec<Vec<String>> = findings.iter()
+        .map(|f| vec![f.severity.label().to_owned(), f.message.clone(), opt_field(&f.fix)])
+        .collect();
+      print_porcelain(&rows);
+    }
+    if problems > 0 {
+      std::process::exit(1);
+    }
+    return;
+  }
+  for finding in &findings {
+    let label = match finding.severity {
+      Severity::Problem => theme::error(finding.severity.label()),
+      Severity::Warning => theme::warn(finding.severity.label()),
+    };
+    println!("[{}] {}", label, finding.message);
+    if let Some(fix) = &finding.fix {
+      println!("    fix: {}", fix);
+    }
+  }
+  if findings.is_empty() {
+    println!("{}", theme::success("No problems found"));
+  } else if options.verbose > 0 {
+    println!("{} problem(s), {} warning(s)", problems, findings.len() - problems);
+  }
+  if problems > 0 {
+    std::process::exit(1);
+  }
+}
+
+fn check_config() -> Vec<Finding> {
+  let mut ret = vec![];
+  if let Err(err) = Config::load() {
+    ret.push(Finding::problem(format!("config {} can't be read: {}", Config::layers().iter().map(|p| p.display().to_string()).collect::<Vec<_>>().join(" or "), err), Some("fix or delete the file, defaults are being used".to_owned())));
+  }
+  if let Err(err) = Aliases::load() {
+    ret.push(Finding::problem(format!("aliases {} can't be read: {}", Aliases::path().display(), err), Some("fix or delete the file, aliases are being ignored".to_owned())));
+  }
+  ret
+}
+
+fn check_path() -> Vec<Finding> {
+  // the first R on PATH should be one of ours, if not plain `R` runs something else
+  let Some(path) = std::env::var_os("PATH") else { return vec![] };
+  let first_r = std::env::split_paths(&path)
+    .flat_map(|dir| ["R.exe", "R"].map(|name| dir.join(name)))
+    .find(|p| p.is_file());
+  match first_r {
+    Some(r) if !installed_versions().is_empty() && !r.starts_with(versions_dir()) => vec![Finding::warning(
+      format!("the first R on PATH is {}, which isn't managed by rvm", r.display()),
+      Some("use `rvm exec -- R` or move it after rvm's versions in PATH".to_owned()),
+    )],
+    _ => vec![],
+  }
+}
+
+fn check_installs(fix: bool) -> Vec<Finding> {
+  let mut ret = vec![];
+  for (journal_file, entry) in JournalEntry::pending() {
+    let message = format!("unfinished install of R {} (pid {}) left {}", entry.version, entry.pid, entry.staging.display());
+    if fix {
+      match JournalEntry::roll_back(&journal_file, &entry) {
+        Ok(()) => ret.push(Finding::warning(format!("{}, cleaned", message), None)),
+        Err(err) => ret.push(Finding::problem(format!("{}, failed to clean: {}", message, err), None)),
+      }
+    } else {
+      ret.push(Finding::problem(message, Some("run `rvm doctor --fix` when no other rvm is installing".to_owned())));
+    }
+  }
+  let scan = scan(&versions_dir());
+  for skipped in &scan.skipped {
+    let Skipped { path, reason } = skipped;
+    let message = format!("{} {}", path.display(), reason);
+    ret.push(match reason {
+      SkipReason::DanglingLink => Finding::problem(message, Some(format!("delete {}", path.display()))),
+      SkipReason::NotADir => Finding::warning(format!("{}, rvm ignores it", message), None),
+      SkipReason::NotAVersion | SkipReason::Misnamed(_) => Finding::warning(format!("{}, it's unmanaged", message), Some(format!("`{}`", skipped.adopt_command()))),
+      SkipReason::Empty(version) => Finding::problem(format!("{} is an orphaned or broken install (empty)", path.display()), Some(format!("delete {} and run `rvm install {}`", path.display(), version))),
+    });
+  }
+  for version in &scan.pool {
+    let path = install_dir(version);
+    if let Err(err) = verify_tree(&path) {
+      ret.push(Finding::problem(format!("{} is an orphaned or broken install ({})", path.display(), err), Some(format!("delete {} and run `rvm install {}`", path.display(), version))));
+    } else if let Some(from) = InstallMeta::load(version).prefix.filter(|p| p != &path) {
+      let message = format!("R {} moved from {} and its files still point there", version, from.display());
+      if fix {
+        // rebasing rewrites the install, not while another rvm is changing installs
+        let rebased = match FileLock::acquire(Duration::ZERO) {
+          Ok(_lock) => rebase_moved(version).map_err(|err| err.to_string()),
+          Err(err) => Err(err.to_string()),
+        };
+        match rebased {
+          Ok(_) => ret.push(Finding::warning(format!("{}, rebased", message), None)),
+          Err(err) => ret.push(Finding::problem(format!("{}, failed to rebase: {}", message, err), None)),
+        }
+      } else {
+        ret.push(Finding::warning(message, Some("run `rvm doctor --fix` when no other rvm is installing".to_owned())));
+      }
+    } else if InstallMeta::load(version).trial {
+      ret.push(Finding::warning(format!("R {} was installed by an `rvm try` that didn't finish", version), Some(format!("`rvm uninstall {}`, or `rvm pin {}` to keep it", version, version))));
+    }
+  }
+  ret
+}
+
+fn check_lock() -> Vec<Finding> {
+  let leftover = std::fs::read_to_string(FileLock::path()).unwrap_or_default();
+  match FileLock::acquire(Duration::ZERO) {
+    // getting it clears whatever pid was left in it
+    Ok(_lock) if !leftover.trim().is_empty() => vec![Finding::warning(format!("lock was left by process {} that's no longer running, cleared", leftover.trim()), None)],
+    Ok(_) => vec![],
+    Err(LockError::Timeout(_, pid)) => vec![Finding::warning(format!("another rvm (pid {}) is changing installs right now", pid), None)],
+    Err(err) => vec![Finding::problem(format!("lock {} can't be used: {}", FileLock::path().display(), err), Some(format!("check permissions of {}", state_dir().display())))],
+  }
+}
+
+fn check_index_cache() -> Vec<Finding> {
+  let source = CranSource::default();
+  let path = IndexCache::path(source.name());
+  if !path.exists() {
+    return vec![];
+  }
+  match IndexCache::load_named(source.name()) {
+    None => vec![Finding::problem(format!("index cache {} is corrupt", path.display()), Some("run `rvm list-remote --refresh`".to_owned()))],
+    Some(cache) if cache.entries.is_empty() => vec![Finding::warning(format!("index cache {} has no versions", path.display()), Some("run `rvm list-remote --refresh`".to_owned()))],
+    Some(cache) if !cache.is_fresh(Config::get().index_ttl) => vec![Finding::warning(format!("index cache {} is {} hour(s) old", path.display(), cache.age() / 3600), Some("run `rvm list-remote --refresh`".to_owned()))],
+    Some(_) => vec![],
+  }
+}
+
+fn check_support() -> Vec<Finding> {
+  // only from the cache, doctor doesn't go online
+  let Some(cache) = IndexCache::load_named(CranSource::default().name()) else { return vec![] };
+  let Ok(active) = active_version(None, &curr_dir()) else { return vec![] };
+  let today = today();
+  match cache.entries.iter().find(|m| m.version == active.version) {
+    Some(meta) if meta.is_eol_on(&today) => vec![Finding::warning(
+      match &meta.eol {
+        Some(eol) => format!("the active R {} reached its end of life on {}", active.version, eol),
+        None => format!("the active R {} reached its end of life", active.version),
+      },
+      Some("run `rvm upgrade`, or `rvm install supported`".to_owned()),
+    )],
+    _ => vec![],
+  }
+}
@@ -0,0 +1,78 @@
+use serde::Serialize;
+use crate::activate::libs_dir;
+use crate::args::Cli;
+use crate::install::{install_dir, installed_versions};
+use crate::install::meta::InstallMeta;
+use crate::install::source::builds_dir;
+use crate::layout::cache_dir;
+use crate::plugin::tools_root;
+use crate::output::{print_json, print_porcelain};
+use crate::parsing::version_parser::Version;
+use crate::utils::{dir_size, format_bytes};
+
+#[derive(Serialize)]
+struct VersionUsage {
+  version: Version,
+  install: u64,
+  /// its package library
+  libs: u64,
+  /// of install, what shares its blocks with another installed version's files, see install/dedup.rs
+  shared: u64,
+}
+
+#[derive(Serialize)]
+struct Usage {
+  versions: Vec<VersionUsage>,
+  /// the remote index caches and the artifacts kept for deltas
+  cache: u64,
+  /// unpacked sources kept for rebuilding
+  build: u64,
+  /// installs of plugin tools
+  tools: u64,
+  /// what sharing files between versions saves, already taken out of total
+  saved: u64,
+  total: u64,
+}
+
+pub fn main(options: &Cli) {
+  let installed = installed_versions();
+  let metas = InstallMeta::all();
+  let versions: Vec<VersionUsage> = installed.iter().rev()
+    .map(|version| {
+      // only with versions that are still there, the other side of what's shared with a removed one is gone
+      let shared = metas.get(version).map_or(0, |meta| meta.shared.iter().filter(|(v, _)| installed.contains(v)).map(|(_, bytes)| bytes).sum());
+      VersionUsage { install: dir_size(&install_dir(version)), libs: dir_size(&libs_dir(version)), shared, version: version.clone() }
+    })
+    .collect();
+  // the build dirs are in the cache dir, only counted once
+  let build = dir_size(&builds_dir());
+  let (cache, tools) = (dir_size(&cache_dir()).saturating_sub(build), dir_size(&tools_root()));
+  let saved = versions.iter().map(|v| v.shared).sum::<u64>();
+  let total = (versions.iter().map(|v| v.install + v.libs).sum::<u64>() + cache + build + tools).saturating_sub(saved);
+  let usage = Usage { versions, cache, build, tools, saved, total };
+  if options.json {
+    return print_json("du", &usage);
+  }
+  if options.porcelain {
+    let mut rows: Vec<Vec<String>> = usage.versions.iter()
+      .map(|v| vec!["version".to_owned(), v.version.to_string(), (v.install + v.libs).to_string()])
+      .collect();
+    for (kind, bytes) in [("cache", usage.cache), ("build", usage.build), ("tools", usage.tools), ("saved", usage.saved), ("total", usage.total)] {
+      rows.push(vec![kind.to_owned(), String::new(), bytes.to_string()]);
+    }
+    return print_porcelain(&rows);
+  }
+  for v in &usage.versions {
+    let shared = if v.shared > 0 { format!(", {} shared", format_bytes(v.shared)) } else { String::new() };
+    println!("{:>10}  R {} (packages {}{})", format_bytes(v.install + v.libs), v.version, format_bytes(v.libs), shared);
+  }
+  for (name, bytes) in [("cache", usage.cache), ("build dirs", usage.build), ("tools", usage.tools)] {
+    if bytes > 0 {
+      println!("{:>10}  {}", format_bytes(bytes), name);
+    }
+  }
+  if usage.saved > 0 {
+    println!("{:>10}  saved by sharing files between versions", format!("-{}", format_bytes(usage.saved)));
+  }
+  println!("{:>10}  total", format_bytes(usage.total));
+}
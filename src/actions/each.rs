@@ -0,0 +1,83 @@
+use std::time::Instant;
+use duct::cmd;
+use serde::Serialize;
+use crate::activate::{env_for, record_use};
+use crate::args::Cli;
+use crate::exit::{fail, Exit, OrExit};
+use crate::install::installed_versions;
+use crate::output::{opt_field, print_json, print_json_line, print_porcelain};
+use crate::parsing::version_parser::Version;
+use crate::resolve::request_range;
+use crate::stats;
+
+#[derive(Serialize)]
+struct RunResult {
+  version: Version,
+  /// None when it couldn't be started or was killed by a signal
+  exit_code: Option<i32>,
+  seconds: f64,
+}
+
+pub fn main(range: String, command: Vec<String>, fail_fast: bool, options: &Cli) {
+  let range = request_range(&range).or_exit("Failed to parse range");
+  let versions: Vec<Version> = installed_versions().into_iter().filter(|v| range.contains(v)).collect();
+  if versions.is_empty() {
+    eprintln!("No installed version matches {}", range);
+    std::process::exit(Exit::NoMatch.code());
+  }
+  let (program, args) = command.split_first().unwrap_or_else(|| fail(Exit::Usage, "No command given"));
+  let mut results: Vec<RunResult> = vec![];
+  for version in versions {
+    eprintln!("== R {} ==", version);
+    stats::count_run("r", &version);
+    record_use(&version);
+    let start = Instant::now();
+    let mut expression = cmd(program, args).unchecked();
+    if options.json || options.porcelain {
+      // stdout is for the summary
+      expression = expression.stdout_to_stderr();
+    }
+    for (key, value) in env_for(&version) {
+      expression = expression.env(key, value);
+    }
+    let exit_code = match expression.run() {
+      Ok(output) => output.status.code(),
+      Err(err) => {
+        eprintln!("Failed to run {}: {}", program, err);
+        None
+      }
+    };
+    let failed = exit_code != Some(0);
+    let result = RunResult { version, exit_code, seconds: start.elapsed().as_secs_f64() };
+    if options.json_lines {
+      print_json_line("each", &result);
+    }
+    results.push(result);
+    if failed && fail_fast {
+      break;
+    }
+  }
+  if options.json_lines {
+    // every result is out already
+  } else if options.json {
+    print_json("each", &results);
+  } else if options.porcelain {
+    let rows: Vec<Vec<String>> = results.iter()
+      .map(|r| vec![r.version.to_string(), opt_field(&r.exit_code), format!("{:.3}", r.seconds)])
+      .collect();
+    print_porcelain(&rows);
+  } else {
+    println!("{:<12} {:<6} {:>8}", "version", "exit", "time");
+    for result in &results {
+      let exit = result.exit_code.map_or("-".to_owned(), |c| c.to_string());
+      println!("{:<12} {:<6} {:>7.1}s", result.version.to_string(), exit, result.seconds);
+    }
+    if options.verbose > 0 {
+      let passed = results.iter().filter(|r| r.exit_code == Some(0)).count();
+      println!("{}/{} passed", passed, results.len());
+    }
+  }
+  if results.iter().any(|r| r.exit_code != Some(0)) {
+    std::process::exit(1);
+  }
+}
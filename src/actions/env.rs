@@ -0,0 +1,36 @@
+use std::collections::BTreeMap;
+use serde::Serialize;
+use crate::activate::env_for_active;
+use crate::args::Cli;
+use crate::exit::OrExit;
+use crate::local_utils::curr_dir;
+use crate::output::{print_json, print_porcelain};
+use crate::resolve::{active_version, Active};
+use crate::shell::Shell;
+
+#[derive(Serialize)]
+struct EnvExport {
+  #[serde(flatten)]
+  active: Active,
+  env: BTreeMap<String, String>,
+}
+
+// the same variables exec runs with and the hooks set, for whatever else wants them
+pub fn main(request: Option<&str>, shell: Option<Shell>, options: &Cli) {
+  let active = active_version(request, &curr_dir()).or_exit("Failed to resolve version");
+  let env = env_for_active(&active);
+  if options.json {
+    let env = env.into_iter().map(|(name, value)| (name, value.to_string_lossy().to_string())).collect();
+    print_json("env", &EnvExport { active, env });
+  } else if options.porcelain {
+    let rows: Vec<Vec<String>> = env.iter()
+      .map(|(name, value)| vec![name.clone(), value.to_string_lossy().to_string()])
+      .collect();
+    print_porcelain(&rows);
+  } else {
+    println!("{}", shell.unwrap_or_else(Shell::detect).set_vars(&env));
+    if options.verbose > 0 {
+      eprintln!("rvm: R {} ({})", active.version, active.origin);
+    }
+  }
+}
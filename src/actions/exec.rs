@@ -0,0 +1,53 @@
+use duct::cmd;
+use crate::activate::{env_for, record_use, system_path, VERSION_VARS};
+use crate::args::Cli;
+use crate::exit::{fail, Exit, OrExit};
+use crate::local_utils::curr_dir;
+use crate::parsing::version_parser::Version;
+use crate::resolve::{active_version, command_version, ResolveError};
+use crate::stats;
+
+pub fn main(version: Option<String>, command: Vec<String>, options: &Cli) {
+  // without a version the command's mapping (`commands` in Renv.yaml or the config) picks it, that's what the command shims rely on
+  let active = match (&version, command.first()) {
+    (None, Some(program)) => command_version(program, &curr_dir()),
+    _ => active_version(version.as_deref(), &curr_dir()),
+  };
+  let active = match active {
+    Err(ResolveError::System) => {
+      if options.verbose > 0 {
+        eprintln!("Running with the system R");
+      }
+      std::process::exit(run_system(&command));
+    },
+    active => active.or_exit("Failed to resolve version"),
+  };
+  if options.verbose > 0 {
+    eprintln!("Running with R {} (matches {}, {})", active.version, active.range, active.origin);
+  }
+  std::process::exit(run_with(&active.version, &command));
+}
+
+/// Runs command with version's environment and returns its exit code
+pub fn run_with(version: &Version, command: &[String]) -> i32 {
+  let (program, args) = command.split_first().unwrap_or_else(|| fail(Exit::Usage, "No command given"));
+  stats::count_run("r", version);
+  record_use(version);
+  let mut expression = cmd(program, args).unchecked();
+  for (key, value) in env_for(version) {
+    expression = expression.env(key, value);
+  }
+  let output = expression.run().unwrap_or_else(|err| panic!("Failed to run {}: {}", program, err));
+  output.status.code().unwrap_or(1)
+}
+
+/// Runs command with the system R, rvm's dirs and variables taken out, and returns its exit code
+pub fn run_system(command: &[String]) -> i32 {
+  let (program, args) = command.split_first().unwrap_or_else(|| fail(Exit::Usage, "No command given"));
+  let mut expression = cmd(program, args).unchecked().env("PATH", system_path());
+  for name in VERSION_VARS {
+    expression = expression.env_remove(name);
+  }
+  let output = expression.run().unwrap_or_else(|err| panic!("Failed to run {}: {}", program, err));
+  output.status.code().unwrap_or(1)
+}
@@ -0,0 +1,11 @@
+use crate::args::Cli;
+use crate::manifest::Manifest;
+use crate::output::print_json;
+
+pub fn main(options: &Cli) {
+  let manifest = Manifest::current().unwrap_or_else(|err| panic!("Failed to read what's installed: {}", err));
+  if options.json {
+    return print_json("export", &manifest);
+  }
+  print!("{}", manifest.to_toml().unwrap_or_else(|err| panic!("Failed to write manifest: {}", err)));
+}
@@ -0,0 +1,177 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use serde::Serialize;
+use crate::actions::{confirm, lock_installs};
+use crate::args::Cli;
+use crate::exit::{fail, Exit};
+use crate::install::{install_dir, installed_versions, versions_dir};
+use crate::install::delta::artifacts_dir;
+use crate::install::meta::InstallMeta;
+use crate::install::transaction::JournalEntry;
+use crate::output::{print_json, print_porcelain};
+use crate::parsing::version_parser::Version;
+use crate::plugin::{find_plugin, shims_dir, tool_install_dir, tool_versions, tools_root};
+use crate::utils::{dir_size, format_bytes};
+
+// `rvm gc`, what's left of things that are gone:
+//   staging    staging dirs and journal entries of installs that never finished, dirs set aside by `rvm verify --repair`
+//   meta       metadata and checksums of versions that aren't installed, temp files of interrupted writes
+//   artifact   artifacts kept for deltas (see install/delta.rs) of a series nothing is installed from anymore
+//   shim       shims of tools or executables that aren't installed
+// it holds the install lock, every install that could still be writing is done, so anything staged is abandoned
+
+#[derive(Serialize)]
+struct Orphan {
+  kind: &'static str,
+  path: PathBuf,
+  bytes: u64,
+  /// why it's an orphan
+  reason: String,
+}
+
+impl Orphan {
+  fn new(kind: &'static str, path: PathBuf, reason: impl Into<String>) -> Self {
+    Self { kind, bytes: dir_size(&path), path, reason: reason.into() }
+  }
+}
+
+pub fn main(options: &Cli) {
+  let _lock = lock_installs(options);
+  let installed = installed_versions();
+  let mut orphans = staging();
+  orphans.extend(meta(&installed));
+  orphans.extend(artifacts(&installed));
+  orphans.extend(shims());
+  if options.json {
+    print_json("gc", &orphans);
+  } else if options.porcelain {
+    let rows: Vec<Vec<String>> = orphans.iter()
+      .map(|o| vec![o.kind.to_owned(), o.path.display().to_string(), o.bytes.to_string()])
+      .collect();
+    print_porcelain(&rows);
+  } else if orphans.is_empty() {
+    println!("Nothing to clean");
+  } else {
+    for orphan in &orphans {
+      let verb = if options.dry_run { "Would remove" } else { "Found" };
+      println!("{} {} ({}, {})", verb, orphan.path.display(), orphan.reason, format_bytes(orphan.bytes));
+    }
+  }
+  if orphans.is_empty() || options.dry_run || options.json || options.porcelain {
+    return;
+  }
+  let total: u64 = orphans.iter().map(|o| o.bytes).sum();
+  if !confirm(options, &format!("Remove {} orphaned file(s), {}?", orphans.len(), format_bytes(total)), false) {
+    fail(Exit::Aborted, "Aborted: User chose not to clean")
+  }
+  let mut failed = false;
+  for orphan in &orphans {
+    let removed = match std::fs::symlink_metadata(&orphan.path) {
+      Ok(m) if m.is_dir() => std::fs::remove_dir_all(&orphan.path),
+      Ok(_) => std::fs::remove_file(&orphan.path),
+      // gone already, e.g. a journal's staging dir
+      Err(_) => Ok(()),
+    };
+    if let Err(err) = removed {
+      eprintln!("Failed to remove {}: {}", orphan.path.display(), err);
+      failed = true;
+    }
+  }
+  if orphans.iter().any(|o| o.kind == "meta") {
+    if let Err(err) = InstallMeta::rebuild_index() {
+      eprintln!("Failed to reindex {}: {}", InstallMeta::dir().display(), err);
+      failed = true;
+    }
+  }
+  println!("{} reclaimed", format_bytes(total));
+  if failed {
+    std::process::exit(1);
+  }
+}
+
+fn entries(dir: &Path) -> Vec<PathBuf> {
+  let Ok(entries) = dir.read_dir() else { return vec![] };
+  let mut ret: Vec<PathBuf> = entries.filter_map(|e| e.ok()).map(|e| e.path()).collect();
+  ret.sort();
+  ret
+}
+
+fn staging() -> Vec<Orphan> {
+  let mut ret = vec![];
+  let mut journaled = HashSet::new();
+  for (journal_file, entry) in JournalEntry::pending() {
+    journaled.insert(entry.staging.clone());
+    if entry.staging.exists() {
+      ret.push(Orphan::new("staging", entry.staging, format!("unfinished install of R {} by pid {}", entry.version, entry.pid)));
+    }
+    ret.push(Orphan::new("staging", journal_file, format!("journal of an unfinished install of R {}", entry.version)));
+  }
+  for path in entries(&versions_dir().join(".staging")) {
+    if !journaled.contains(&path) {
+      ret.push(Orphan::new("staging", path, "staging dir nothing is installing into"));
+    }
+  }
+  for tool in entries(&tools_root()) {
+    for path in entries(&tool).into_iter().filter(|p| p.file_name().is_some_and(|n| n.to_string_lossy().starts_with(".staging-"))) {
+      ret.push(Orphan::new("staging", path, "unfinished plugin install"));
+    }
+  }
+  ret
+}
+
+fn meta(installed: &[Version]) -> Vec<Orphan> {
+  let mut ret = vec![];
+  for path in entries(&InstallMeta::dir()) {
+    let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+    if name.contains(".tmp-") {
+      ret.push(Orphan::new("meta", path, "left by an interrupted write"));
+      continue;
+    }
+    let stem = [".json", ".yaml", ".sums", ".receipt"].iter().find_map(|ext| name.strip_suffix(ext));
+    let Some(version) = stem.and_then(|s| Version::parse(s).ok()) else { continue };
+    if !installed.contains(&version) && !install_dir(&version).exists() {
+      ret.push(Orphan::new("meta", path, format!("R {} isn't installed", version)));
+    }
+  }
+  ret
+}
+
+fn artifacts(installed: &[Version]) -> Vec<Orphan> {
+  let series: HashSet<String> = installed.iter().map(|v| {
+    let (major, minor, _) = v.parts();
+    format!("{}.{}", major, minor)
+  }).collect();
+  entries(&artifacts_dir()).into_iter()
+    .filter(|dir| !dir.file_name().is_some_and(|n| series.contains(n.to_string_lossy().as_ref())))
+    .map(|dir| {
+      let name = dir.file_name().unwrap_or_default().to_string_lossy().to_string();
+      Orphan::new("artifact", dir, format!("no R {} is installed", name))
+    })
+    .collect()
+}
+
+fn shims() -> Vec<Orphan> {
+  let mut ret = vec![];
+  for path in entries(&shims_dir()) {
+    let Ok(text) = std::fs::read_to_string(&path) else { continue };
+    // what write_shim writes: rvm plugin exec <tool> -- <name>
+    let Some((tool, name)) = text.split_once("rvm plugin exec ")
+      .and_then(|(_, rest)| rest.split_once(" -- "))
+      .map(|(tool, rest)| (tool.trim().to_owned(), rest.split_whitespace().next().unwrap_or_default().to_owned())) else { continue };
+    let reason = match find_plugin(&tool) {
+      Err(_) => format!("there's no plugin {}", tool),
+      Ok(plugin) => {
+        let provides = tool_versions(&tool).iter()
+          .flat_map(|v| plugin.bin_dirs(&tool_install_dir(&tool, v)))
+          .flat_map(|dir| entries(&dir))
+          .any(|exe| exe.is_file() && exe.file_stem().is_some_and(|s| s.to_string_lossy() == name));
+        if provides {
+          continue;
+        }
+        format!("no installed {} has {}", tool, name)
+      },
+    };
+    ret.push(Orphan::new("shim", path, reason));
+  }
+  ret
+}
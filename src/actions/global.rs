@@ -0,0 +1,35 @@
+use crate::actions::{lock_installs, run_hooks};
+use crate::alias::Aliases;
+use crate::args::Cli;
+use crate::audit::{self, Change};
+use crate::hooks::Event;
+use crate::local_utils::curr_dir;
+use crate::observer;
+use crate::resolve::active_version;
+
+// the machine default is just the "default" alias, `rvm alias set default` does the same
+pub fn main(request: &str, options: &Cli) {
+  let _lock = lock_installs(options);
+  let mut aliases = Aliases::load().unwrap_or_else(|err| panic!("Failed to read {}: {}", Aliases::path().display(), err));
+  let old = aliases.set("default", request).unwrap_or_else(|err| panic!("Failed to set default: {}", err));
+  if options.dry_run {
+    let was = old.map(|old| format!(" (was {})", old)).unwrap_or_default();
+    return println!("Would set default -> {}{} in {}", request, was, Aliases::path().display());
+  }
+  // use hooks only run when a version that's installed is picked
+  let version = active_version(Some(request), &curr_dir()).ok().map(|active| active.version);
+  let previous = active_version(None, &curr_dir()).ok().map(|active| active.version);
+  if let Some(version) = &version {
+    run_hooks(Event::PreUse, version);
+  }
+  aliases.save().unwrap_or_else(|err| panic!("Failed to save aliases: {}", err));
+  audit::record(Change::Alias { name: "default".to_owned(), target: Some(request.to_owned()), was: old.clone() });
+  match old {
+    Some(old) => println!("default -> {} (was {})", request, old),
+    None => println!("default -> {}", request),
+  }
+  if let Some(version) = &version {
+    run_hooks(Event::PostUse, version);
+    observer::notify(|o| o.on_switch(previous.as_ref(), version));
+  }
+}
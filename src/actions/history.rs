@@ -0,0 +1,79 @@
+use crate::actions::lock_installs;
+use crate::alias::Aliases;
+use crate::args::Cli;
+use crate::audit::{self, Change, Entry};
+use crate::constraint_file::ConstraintFile;
+use crate::exit::{fail, Exit, OrExit};
+use crate::output::{print_json, print_porcelain};
+use crate::utils::date_of;
+
+// `rvm history` shows the audit log (see audit.rs), `rvm undo` reverts its last switch
+
+pub fn main(limit: Option<usize>, options: &Cli) {
+  let entries = audit::load();
+  let entries = &entries[entries.len().saturating_sub(limit.unwrap_or(usize::MAX))..];
+  if options.json {
+    print_json("history", &entries);
+  } else if options.porcelain {
+    let rows: Vec<Vec<String>> = entries.iter()
+      .map(|e| vec![e.at.to_string(), e.user.clone(), kind(&e.change).to_owned(), e.change.to_string()])
+      .collect();
+    print_porcelain(&rows);
+  } else if entries.is_empty() {
+    println!("Nothing was changed yet{}", if audit::enabled() { "" } else { ", `audit: false` in the config turns the log off" });
+  } else {
+    for entry in entries {
+      println!("{} {} {}", date_of(entry.at), entry.user, entry.change);
+    }
+  }
+}
+
+fn kind(change: &Change) -> &'static str {
+  match change {
+    Change::Install { .. } => "install",
+    Change::Uninstall { .. } => "uninstall",
+    Change::Local { .. } => "local",
+    Change::Alias { .. } => "alias",
+  }
+}
+
+/// Makes the opposite of the last switch, a project's rversion or the default alias back to what it was
+pub fn undo(options: &Cli) {
+  let Some(Entry { change, .. }) = audit::last_switch() else { fail(Exit::NoMatch, "No switch to undo in the history") };
+  match change {
+    Change::Local { path, was: None, .. } =>
+      fail(Exit::Failure, format!("{} was created by that switch, remove it to undo it", path.display())),
+    Change::Local { path, rversion, was: Some(was) } => {
+      let mut file = ConstraintFile::load(&path).or_exit(format!("Failed to read {}", path.display()));
+      if file.constraint() != rversion {
+        fail(Exit::Failure, format!("{} has rversion {} now, not the {} that switch wrote", path.display(), file.constraint(), rversion));
+      }
+      if options.dry_run {
+        return println!("Would write {} rversion: {} (was {})", path.display(), was, rversion);
+      }
+      file.rewrite(&was);
+      file.save().or_exit(format!("Failed to write {}", path.display()));
+      audit::record(Change::Local { path: path.clone(), rversion: was.clone(), was: Some(rversion.clone()) });
+      println!("{} rversion: {} (was {})", path.display(), was, rversion);
+    },
+    Change::Alias { name, target, was } => {
+      let _lock = lock_installs(options);
+      let mut aliases = Aliases::load().unwrap_or_else(|err| panic!("Failed to read {}: {}", Aliases::path().display(), err));
+      if aliases.aliases.get(&name) != target.as_ref() {
+        fail(Exit::Failure, format!("{} changed since that switch, it's {} now", name, aliases.aliases.get(&name).map_or("unset", String::as_str)));
+      }
+      let now = target.as_deref().unwrap_or("unset");
+      if options.dry_run {
+        return println!("Would set {} -> {} (was {}) in {}", name, was.as_deref().unwrap_or("unset"), now, Aliases::path().display());
+      }
+      match &was {
+        Some(was) => { aliases.set(&name, was).unwrap_or_else(|err| panic!("Failed to set {}: {}", name, err)); },
+        None => { aliases.remove(&name); },
+      }
+      aliases.save().unwrap_or_else(|err| panic!("Failed to save aliases: {}", err));
+      audit::record(Change::Alias { name: name.clone(), target: was.clone(), was: target.clone() });
+      println!("{} -> {} (was {})", name, was.as_deref().unwrap_or("unset"), now);
+    },
+    Change::Install { .. } | Change::Uninstall { .. } => unreachable!("last_switch only finds switches"),
+  }
+}
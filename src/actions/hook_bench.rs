@@ -0,0 +1,80 @@
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+use clap::ValueEnum;
+use serde::Serialize;
+use crate::args::Cli;
+use crate::config::Config;
+use crate::daemon::{ask, Query};
+use crate::hook_cache::entry_path;
+use crate::output::{print_json, print_porcelain};
+use crate::shell::Shell;
+
+// `rvm hook-bench`, what a cd into dir costs: `rvm hook-env` spawned the way the hooks do, resolving every time
+// (its cache entry removed before each run) and answered by the cache. a bash, zsh or powershell hook whose entry
+// is fresh and already set doesn't spawn anything, that costs no more than a few stats
+
+#[derive(Serialize)]
+struct Timing {
+  name: &'static str,
+  runs: usize,
+  median_ms: f64,
+  p95_ms: f64,
+  max_ms: f64,
+}
+
+#[derive(Serialize)]
+struct HookBench {
+  budget_ms: u64,
+  daemon: bool,
+  timings: Vec<Timing>,
+}
+
+pub fn main(runs: usize, shell: Option<Shell>, dir: &Path, options: &Cli) {
+  let dir = dir.canonicalize().unwrap_or_else(|err| panic!("Failed to find {}: {}", dir.display(), err));
+  let shell = shell.unwrap_or_else(Shell::detect);
+  let exe = std::env::current_exe().unwrap_or_else(|err| panic!("Failed to find rvm itself: {}", err));
+  let shell_name = shell.to_possible_value().map(|v| v.get_name().to_owned()).unwrap_or_default();
+  let hook = || {
+    let start = Instant::now();
+    let _ = Command::new(&exe).args(["hook-env", "--shell", &shell_name]).current_dir(&dir).env("PWD", &dir)
+      .stdout(Stdio::null()).stderr(Stdio::null()).status();
+    start.elapsed()
+  };
+  let entry = entry_path(&dir);
+  let uncached = timing("resolving", runs, || {
+    let _ = std::fs::remove_file(&entry);
+    hook()
+  });
+  // the last uncached run left a fresh entry
+  let cached = timing("cached", runs, hook);
+  let bench = HookBench { budget_ms: Config::get().hook_budget_ms, daemon: ask(&Query::Ping).is_some(), timings: vec![uncached, cached] };
+  let over = bench.budget_ms > 0 && bench.timings.iter().any(|t| t.median_ms > bench.budget_ms as f64);
+  if options.json {
+    print_json("hook-bench", &bench);
+  } else if options.porcelain {
+    let rows: Vec<Vec<String>> = bench.timings.iter()
+      .map(|t| vec![t.name.to_owned(), t.runs.to_string(), format!("{:.1}", t.median_ms), format!("{:.1}", t.p95_ms), format!("{:.1}", t.max_ms)])
+      .collect();
+    print_porcelain(&rows);
+  } else {
+    for t in &bench.timings {
+      println!("{:<10} median {:>7.1}ms  p95 {:>7.1}ms  max {:>7.1}ms ({} runs)", t.name, t.median_ms, t.p95_ms, t.max_ms, t.runs);
+    }
+    match (bench.budget_ms, over) {
+      (0, _) => println!("No budget (hook_budget_ms is 0)"),
+      (budget, true) => println!("Over the {}ms budget{}", budget, if bench.daemon { "" } else { ", `rvm daemon` answers hooks without resolving from scratch" }),
+      (budget, false) => println!("Within the {}ms budget", budget),
+    }
+  }
+  if over {
+    std::process::exit(1);
+  }
+}
+
+fn timing(name: &'static str, runs: usize, mut run: impl FnMut() -> Duration) -> Timing {
+  let mut times: Vec<Duration> = (0..runs.max(1)).map(|_| run()).collect();
+  times.sort();
+  let ms = |at: usize| times[at.min(times.len() - 1)].as_secs_f64() * 1000.0;
+  Timing { name, runs: times.len(), median_ms: ms(times.len() / 2), p95_ms: ms(times.len() * 95 / 100), max_ms: ms(times.len() - 1) }
+}
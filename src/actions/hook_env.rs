@@ -0,0 +1,91 @@
+use crate::activate::{env_for_active, extra_vars, system_path, with_command_shims, write_command_shims, EXTRA_VARS, VERSION_VARS};
+use crate::args::Cli;
+use crate::config::Config;
+use crate::daemon;
+use crate::hook_cache;
+use crate::local_utils::curr_dir;
+use crate::resolve::{active_version, mapped_commands, none_marker, ResolveError};
+use crate::shell::Shell;
+use std::time::Instant;
+
+// run by the cd hooks, prints what to set for the version of the current dir
+// errors go to stderr as warnings, a broken project shouldn't break the prompt
+pub fn main(shell: Option<Shell>, options: &Cli) {
+  if !Config::get().auto_switch {
+    tracing::debug!("auto_switch is off");
+    return;
+  }
+  let started = Instant::now();
+  let shell = shell.unwrap_or_else(Shell::detect);
+  let dir = curr_dir();
+  // opted out, what was set stays
+  if let Some(marker) = none_marker(&dir) {
+    tracing::debug!(marker = %marker.display(), "not switching under rvm local --none");
+    return;
+  }
+  let named = hook_cache::shell_dir().unwrap_or_else(|| dir.clone());
+  // what this dir resolved to last time if nothing changed since, else the daemon's answer when one is running
+  let cached = hook_cache::applies().then(|| hook_cache::load(&named)).flatten();
+  let fresh = cached.is_none();
+  let resolved = cached
+    .or_else(|| daemon::resolve(&dir))
+    .unwrap_or_else(|| active_version(None, &dir));
+  if fresh && hook_cache::applies() {
+    if let Err(err) = hook_cache::store(&named, &dir, &resolved) {
+      tracing::debug!(error = %err, "can't write the hook cache");
+    }
+  }
+  if let Some(key) = hook_cache::key(&resolved) {
+    println!("{}", shell.set_vars(&[(hook_cache::KEY_VAR, key), (hook_cache::DIR_VAR, hook_cache::cache_root().display().to_string())]));
+  }
+  match resolved {
+    Ok(active) => {
+      tracing::debug!(version = %active.version, origin = %active.origin, ?shell, "switching");
+      let mut env = env_for_active(&active);
+      // executables mapped to another version go through their shims
+      let commands = mapped_commands(&dir);
+      if let Err(err) = write_command_shims(&commands) {
+        eprintln!("rvm: failed to write the command shims: {}", err);
+      }
+      if let Some((_, path)) = env.iter_mut().find(|(name, _)| name == "PATH") {
+        *path = with_command_shims(path, !commands.is_empty());
+      }
+      println!("{}", shell.set_vars(&env));
+      // what the last dir's version or project set and this one doesn't goes, the version's own variables are all set again
+      let previous = std::env::var(EXTRA_VARS).unwrap_or_default();
+      for name in previous.split(':').filter(|n| !n.is_empty() && !env.iter().any(|(set, _)| set == n)) {
+        println!("{}", shell.unset_var(name));
+      }
+      match extra_vars(&active).join(":") {
+        names if names.is_empty() && previous.is_empty() => {},
+        names if names.is_empty() => println!("{}", shell.unset_var(EXTRA_VARS)),
+        names => println!("{}", shell.set_var(EXTRA_VARS, &names)),
+      }
+      if options.verbose > 0 {
+        eprintln!("rvm: R {} ({})", active.version, active.origin);
+      }
+    },
+    // the system R, none of rvm's dirs or variables, nor the extra ones the last dir had
+    Err(ResolveError::System) => {
+      tracing::debug!(?shell, "switching to the system R");
+      println!("{}", shell.set_vars(&[("PATH", system_path())]));
+      let previous = std::env::var(EXTRA_VARS).unwrap_or_default();
+      for name in VERSION_VARS.into_iter().chain(previous.split(':')).filter(|n| !n.is_empty()) {
+        println!("{}", shell.unset_var(name));
+      }
+      if !previous.is_empty() {
+        println!("{}", shell.unset_var(EXTRA_VARS));
+      }
+      if options.verbose > 0 {
+        eprintln!("rvm: system R");
+      }
+    },
+    Err(ResolveError::NothingInstalled | ResolveError::NoProject(_)) => {},
+    Err(err) => eprintln!("rvm: {}", err),
+  }
+  let budget = Config::get().hook_budget_ms;
+  let took = started.elapsed().as_millis() as u64;
+  if budget > 0 && took > budget {
+    eprintln!("rvm: the hook took {}ms, over hook_budget_ms ({}ms), `rvm hook-bench` measures it and `rvm daemon` can help", took, budget);
+  }
+}
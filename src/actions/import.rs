@@ -0,0 +1,141 @@
+use std::path::Path;
+use crate::actions::{install, local, lock_installs, plugin};
+use crate::alias::Aliases;
+use crate::args::Cli;
+use crate::exit::OrExit;
+use crate::import::{link_dir, r_from_tool_versions, Found, Manager};
+use crate::install::{install_dir, is_installed, NoReporter};
+use crate::install::local::install_local;
+use crate::install::meta::InstallMeta;
+use crate::local_utils::curr_dir;
+use crate::manifest::Manifest;
+use crate::plugin::{find_plugin, tool_install_dir, tool_versions, write_shims};
+use crate::resolve::PROJECT_FILE;
+use crate::utils::copy_dir;
+
+pub fn main(manager: Manager, copy: bool, options: &Cli) {
+  let root = manager.root().unwrap_or_else(|| panic!("Failed to find where {:?} keeps its installs, set its root variable", manager));
+  let found = manager.installs();
+  if found.is_empty() {
+    println!("No installs found in {}", root.display());
+  }
+  {
+    let _lock = lock_installs(options);
+    for install in &found {
+      import(install, copy, options);
+    }
+  }
+  // shims so the imported tools run like ones installed through their plugin
+  let mut tools: Vec<&str> = found.iter().filter_map(|f| f.tool.as_deref()).collect();
+  tools.dedup();
+  for tool in tools {
+    if let (Ok(plugin), false) = (find_plugin(tool), options.dry_run) {
+      write_shims(plugin.as_ref()).or_exit(format!("Failed to write shims for {}", tool));
+    }
+  }
+  translate_project_file(manager, options);
+}
+
+/// `rvm import env.toml`, gets this machine to have at least what the manifest has
+/// nothing is removed, and aliases in the manifest replace the ones with the same name
+pub fn manifest(path: &Path, options: &Cli) {
+  let manifest = Manifest::load(path).unwrap_or_else(|err| panic!("Failed to read {}: {}", path.display(), err));
+  {
+    let _lock = lock_installs(options);
+    let mut aliases = Aliases::load().unwrap_or_else(|err| panic!("Failed to read {}: {}", Aliases::path().display(), err));
+    let default = manifest.default.iter().map(|target| ("default", target));
+    for (name, target) in manifest.aliases.iter().map(|(n, t)| (n.as_str(), t)).chain(default) {
+      match aliases.set(name, target) {
+        Ok(old) if old.as_ref() != Some(target) => println!("{}{} -> {}", if options.dry_run { "Would set " } else { "" }, name, target),
+        Ok(_) => {},
+        Err(err) => eprintln!("Failed to set alias {}: {}", name, err),
+      }
+    }
+    if !options.dry_run {
+      aliases.save().unwrap_or_else(|err| panic!("Failed to save aliases: {}", err));
+    }
+  }
+  // the ones rvm can't download get built or registered the way they were, the rest in one batch
+  let mut from_index = vec![];
+  for entry in manifest.installs.iter().filter(|entry| !is_installed(&entry.version)) {
+    let version = entry.version.to_string();
+    match (&entry.git, &entry.path) {
+      (Some(git), _) => install::git(&git.url, Some(&git.reference), Some(&version), options),
+      (None, Some(path)) if path.exists() => install::local(path, &version, options),
+      (None, Some(path)) => eprintln!("R {} was registered from {}, which isn't here, skipped", version, path.display()),
+      (None, None) => from_index.push(format!("={}", version)),
+    }
+  }
+  if !from_index.is_empty() {
+    install::main(from_index, 4, None, None, false, options);
+  }
+  for entry in manifest.installs.iter().filter(|entry| entry.pinned) {
+    if !InstallMeta::load(&entry.version).pinned && is_installed(&entry.version) && !options.dry_run {
+      InstallMeta::update(&entry.version, |meta| meta.pinned = true)
+        .or_exit(format!("Failed to pin {}", entry.version));
+    }
+  }
+  for (name, versions) in &manifest.tools {
+    let plugin = match find_plugin(name) {
+      Ok(plugin) => plugin,
+      Err(err) => {
+        eprintln!("Skipped {} {:?}: {}", name, versions.iter().map(|v| v.to_string()).collect::<Vec<_>>(), err);
+        continue;
+      }
+    };
+    let installed = tool_versions(name);
+    for version in versions.iter().filter(|v| !installed.contains(v)) {
+      plugin::install(plugin.as_ref(), &format!("={}", version), options);
+    }
+  }
+}
+
+fn import(install: &Found, copy: bool, options: &Cli) {
+  let (name, dest) = match &install.tool {
+    None => ("R".to_owned(), install_dir(&install.version)),
+    Some(tool) => (tool.clone(), tool_install_dir(tool, &install.version)),
+  };
+  let exists = match &install.tool {
+    None => is_installed(&install.version),
+    Some(_) => dest.exists(),
+  };
+  if exists {
+    return println!("{} {} is already installed, skipped", name, install.version);
+  }
+  let how = if copy { "copy" } else { "link" };
+  if options.dry_run {
+    return println!("Would {} {} {} from {} to {}", how, name, install.version, install.path.display(), dest.display());
+  }
+  let result = match (&install.tool, copy) {
+    // R copies go through the same staging and checks as `rvm install --path`
+    (None, true) => install_local(&install.path, &install.version, &NoReporter).map(|_| ()).map_err(|err| err.to_string()),
+    (None, false) => link_dir(&install.path, &dest)
+      .map_err(|err| err.to_string())
+      .and_then(|_| InstallMeta::update(&install.version, |meta| meta.registered_from = Some(install.path.clone()))
+        .map(|_| ())
+        .map_err(|err| err.to_string())),
+    (Some(_), true) => copy_dir(&install.path, &dest).map_err(|err| err.to_string()),
+    (Some(_), false) => link_dir(&install.path, &dest).map_err(|err| err.to_string()),
+  };
+  match result {
+    Ok(()) => println!("{} {} imported from {}", name, install.version, install.path.display()),
+    Err(err) => eprintln!("Failed to {} {} {}: {}", how, name, install.version, err),
+  }
+}
+
+// asdf's .tool-versions can pin R, the others' files pin their own runtime and stay as they are
+fn translate_project_file(manager: Manager, options: &Cli) {
+  let path = curr_dir().join(manager.project_file());
+  if !path.exists() {
+    return;
+  }
+  if manager != Manager::Asdf {
+    return println!("{} pins a runtime that isn't R, left as it is", path.display());
+  }
+  let contents = std::fs::read_to_string(&path).unwrap_or_else(|err| panic!("Failed to read {}: {}", path.display(), err));
+  let Some(version) = r_from_tool_versions(&contents) else { return };
+  if curr_dir().join(PROJECT_FILE).exists() && !options.yes {
+    return println!("{} already exists, `rvm local {}` sets it to what {} has", PROJECT_FILE, version, path.display());
+  }
+  local::main(&version, None, options);
+}
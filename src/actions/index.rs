@@ -0,0 +1,210 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use crate::args::{Cli, IndexAction, IndexFormat};
+use crate::exit::{fail, Exit};
+use crate::index::{artifact_name, ArtifactMeta, ArtifactName, Delta, IndexDocument};
+use crate::install::delta;
+use crate::parsing::version_parser::Version;
+use crate::trust::{payload, Signed};
+use crate::utils::{date_of, format_bytes, sha256_file};
+
+// a registry is a dir of artifacts served somewhere and the index describing them,
+// `rvm index generate releases/ --base-url https://r.example.com/releases > index.json` writes the index.
+// versions and platforms come from the file names (see artifact_name), sources are attached to every binary of their version,
+// and <artifact>.from-<version>.delta files from `rvm index delta` to the artifact they patch into.
+// a version's release day (for released-before and released-after) is the day its oldest artifact was last modified.
+// with --signing-key the versions are signed, and rvm holds the registry to that key from the first listing on (trust.rs)
+
+pub fn main(action: &IndexAction, options: &Cli) {
+  match action {
+    IndexAction::Generate {dir, base_url, format, output, signing_key} => generate(dir, base_url, *format, output.as_deref(), signing_key.as_deref(), options),
+    IndexAction::Delta {base, target, output} => make_delta(base, target, output.as_deref(), options),
+  }
+}
+
+fn make_delta(base: &Path, target: &Path, output: Option<&Path>, options: &Cli) {
+  let version = |path: &Path| path.file_name().and_then(|n| n.to_str()).and_then(artifact_name).map(|a| a.version);
+  let output = match output {
+    Some(output) => output.to_path_buf(),
+    None => {
+      let from = version(base).unwrap_or_else(|| panic!("Failed to read a version from {}, give --output", base.display()));
+      let mut name = target.as_os_str().to_owned();
+      name.push(format!(".from-{}.delta", from));
+      PathBuf::from(name)
+    },
+  };
+  if options.dry_run {
+    return println!("Would write {}", output.display());
+  }
+  let read = |path: &Path| std::fs::read(path).unwrap_or_else(|err| panic!("Failed to read {}: {}", path.display(), err));
+  let (base_bytes, target_bytes) = (read(base), read(target));
+  let patch = delta::diff(&base_bytes, &target_bytes).unwrap_or_else(|err| panic!("Failed to make delta: {}", err));
+  std::fs::write(&output, &patch).unwrap_or_else(|err| panic!("Failed to write {}: {}", output.display(), err));
+  let percent = patch.len() as f64 * 100.0 / target_bytes.len().max(1) as f64;
+  println!("Wrote {}, {} instead of {} ({:.0}%)", output.display(), format_bytes(patch.len() as u64), format_bytes(target_bytes.len() as u64), percent);
+}
+
+// <artifact>.from-<version>.delta, what the artifact is and which version it patches from
+fn delta_name(file_name: &str) -> Option<(ArtifactName, Version)> {
+  let (artifact, from) = file_name.strip_suffix(".delta")?.rsplit_once(".from-")?;
+  Some((artifact_name(artifact)?, Version::parse(from).ok()?))
+}
+
+// the day path was last modified, YYYY-MM-DD
+fn modified_day(path: &Path) -> Option<String> {
+  let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok()?;
+  Some(date_of(modified.duration_since(std::time::UNIX_EPOCH).ok()?.as_secs()))
+}
+
+fn generate(dir: &Path, base_url: &str, format: IndexFormat, output: Option<&Path>, signing_key: Option<&Path>, options: &Cli) {
+  let mut binaries: Vec<ArtifactMeta> = vec![];
+  let mut sources: BTreeMap<Version, (String, String)> = BTreeMap::new();
+  let mut deltas: Vec<(ArtifactName, Delta, PathBuf)> = vec![];
+  let mut released: BTreeMap<Version, String> = BTreeMap::new();
+  for path in files_under(dir) {
+    let relative = path.strip_prefix(dir).unwrap_or(&path);
+    let url = format!("{}/{}", base_url.trim_end_matches('/'), relative.iter().map(|c| c.to_string_lossy()).collect::<Vec<_>>().join("/"));
+    let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+    if let Some((name, from)) = delta_name(file_name) {
+      let sha256 = sha256_file(&path).unwrap_or_else(|err| panic!("Failed to hash {}: {}", path.display(), err));
+      deltas.push((name, Delta { from, url, sha256: Some(sha256) }, relative.to_path_buf()));
+      continue;
+    }
+    let Some(name) = artifact_name(file_name) else {
+      eprintln!("Skipped {}, its name isn't an artifact's", relative.display());
+      continue;
+    };
+    let sha256 = sha256_file(&path).unwrap_or_else(|err| panic!("Failed to hash {}: {}", path.display(), err));
+    if let Some(day) = modified_day(&path) {
+      let first = released.entry(name.version.clone()).or_insert_with(|| day.clone());
+      if &day < first {
+        *first = day;
+      }
+    }
+    if options.verbose > 0 {
+      eprintln!("{} R {} {}", relative.display(), name.version, name.platform.map(|p| p.to_string()).unwrap_or_else(|| "source".to_owned()));
+    }
+    if name.source {
+      if let Some((other, _)) = sources.get(&name.version) {
+        panic!("Failed to generate index: {} and {} are both the source of R {}", other, url, name.version);
+      }
+      sources.insert(name.version, (url, sha256));
+      continue;
+    }
+    if let Some(other) = binaries.iter().find(|m| m.version == name.version && m.platform == name.platform) {
+      panic!("Failed to generate index: {} and {} are both R {} for the same platform", other.url, url, name.version);
+    }
+    binaries.push(ArtifactMeta {
+      version: name.version,
+      url,
+      source_url: None,
+      build_script: None,
+      sha256: Some(sha256),
+      source_sha256: None,
+      source_archive: None,
+      platform: name.platform,
+      eol: None,
+      released: None,
+      yanked: false,
+      support: None,
+      deltas: vec![],
+      source_deltas: vec![],
+      notes_url: None,
+      notes: None,
+      relocate: None,
+      env: BTreeMap::new(),
+    });
+  }
+  for meta in &mut binaries {
+    if let Some((url, sha256)) = sources.get(&meta.version) {
+      meta.source_url = Some(url.clone());
+      meta.source_sha256 = Some(sha256.clone());
+    }
+  }
+  // versions with only a source can still be built
+  for (version, (url, sha256)) in &sources {
+    if !binaries.iter().any(|m| &m.version == version) {
+      binaries.push(ArtifactMeta {
+        version: version.clone(),
+        url: url.clone(),
+        source_url: Some(url.clone()),
+        build_script: None,
+        sha256: Some(sha256.clone()),
+        source_sha256: Some(sha256.clone()),
+        source_archive: None,
+        platform: None,
+        eol: None,
+        released: None,
+        yanked: false,
+        support: None,
+        deltas: vec![],
+        source_deltas: vec![],
+        notes_url: None,
+        notes: None,
+        relocate: None,
+        env: BTreeMap::new(),
+      });
+    }
+  }
+  for meta in &mut binaries {
+    meta.released = released.get(&meta.version).cloned();
+  }
+  for (name, delta, relative) in deltas {
+    let patches: Vec<&mut ArtifactMeta> = binaries.iter_mut()
+      .filter(|m| m.version == name.version && (name.source || m.platform == name.platform))
+      .collect();
+    if patches.is_empty() {
+      eprintln!("Skipped {}, there's no artifact it patches into", relative.display());
+    }
+    for meta in patches {
+      if name.source { meta.source_deltas.push(delta.clone()) } else { meta.deltas.push(delta.clone()) }
+    }
+  }
+  // newest first like CRAN's, then by platform so the same dir always gives the same index
+  binaries.sort_by(|a, b| b.version.cmp(&a.version).then_with(|| a.platform.map(|p| p.to_string()).cmp(&b.platform.map(|p| p.to_string()))));
+  let count = binaries.len();
+  let signed = signing_key.map(|path| {
+    let secret = std::fs::read_to_string(path).unwrap_or_else(|err| panic!("Failed to read {}: {}", path.display(), err));
+    let versions = serde_json::to_value(&binaries).unwrap_or_else(|err| panic!("Failed to write index: {}", err));
+    Signed::sign(&secret, payload(&versions)).unwrap_or_else(|err| fail(Exit::Usage, format!("Failed to sign with {}: {}", path.display(), err)))
+  });
+  let document = IndexDocument {
+    signing_key: signed.as_ref().map(|s| s.key.clone()),
+    signature: signed.map(|s| s.signature),
+    ..IndexDocument::new(binaries)
+  };
+  let text = match format {
+    IndexFormat::Json => serde_json::to_string_pretty(&document).map(|t| t + "\n").map_err(|err| err.to_string()),
+    IndexFormat::Toml => toml::to_string_pretty(&document).map_err(|err| err.to_string()),
+  }.unwrap_or_else(|err| panic!("Failed to write index: {}", err));
+  match output {
+    Some(path) if options.dry_run => println!("Would write {} with {} artifact(s)", path.display(), count),
+    Some(path) => {
+      std::fs::write(path, text).unwrap_or_else(|err| panic!("Failed to write {}: {}", path.display(), err));
+      println!("Wrote {} with {} artifact(s)", path.display(), count);
+    },
+    None => print!("{}", text),
+  }
+}
+
+// every file under dir, sorted, without hidden ones
+fn files_under(dir: &Path) -> Vec<PathBuf> {
+  let mut ret = vec![];
+  let mut stack = vec![dir.to_path_buf()];
+  while let Some(dir) = stack.pop() {
+    let entries = dir.read_dir().unwrap_or_else(|err| panic!("Failed to read {}: {}", dir.display(), err));
+    for entry in entries.filter_map(|e| e.ok()) {
+      let path = entry.path();
+      if entry.file_name().to_string_lossy().starts_with('.') {
+        continue;
+      }
+      if path.is_dir() {
+        stack.push(path);
+      } else {
+        ret.push(path);
+      }
+    }
+  }
+  ret.sort();
+  ret
+}
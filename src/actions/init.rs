@@ -3,19 +3,21 @@ use std::hash::Hash;
 use std::path::Path;
 use std::str::FromStr;
 use std::string::String;
-use cli_prompts::DisplayPrompt;
-use cli_prompts::prompts::Confirmation;
 use serde::Serialize;
-use crate::actions::confirmation_style;
+use crate::actions::confirm;
 use crate::actions::lock::{get_current_packages, Package, Priority};
+use crate::alias::expand_alias;
 use crate::args::Cli;
+use crate::exit::{fail, Exit};
 use crate::local_utils::{get_latest_R, install_version};
 use crate::parsing::version_parser;
 use crate::parsing::version_parser::Range;
 use crate::parsing::yaml_ser::{Env, write_yaml};
+use crate::shell::{direnv_layout, Hook};
 use crate::utils::ToAbsolute;
 
 pub fn main(mut rversion: String, path: &Path, options : &Cli) {
+  rversion = expand_alias(&rversion);
   // TODO make verbose
   // TODO make dry run
   create_folder_if_needed(path);
@@ -27,15 +29,8 @@ pub fn main(mut rversion: String, path: &Path, options : &Cli) {
   if env_exists || yaml_exists {
     if options.yes {
       println!("The path seems to have a project started already, overwriting env and/or yaml");
-    } else {
-      let can_overwrite = Confirmation::new(format!("The path seems to have a project started already, do you want to overwrite env and/or yaml?"))
-        .default_positive(true)
-        .style(confirmation_style())
-        .display()
-        .unwrap_or_else(|err| panic!("Aborted: {:?}", err));
-      if !can_overwrite {
-        panic!("Aborted: User chose not to overwrite")
-      }
+    } else if !confirm(options, "The path seems to have a project started already, do you want to overwrite env and/or yaml?", true) {
+      fail(Exit::Aborted, "Aborted: User chose not to overwrite")
     }
     // delete the env folder and yaml file
     if env_exists {
@@ -52,7 +47,7 @@ pub fn main(mut rversion: String, path: &Path, options : &Cli) {
     rversion = get_latest_R().unwrap(); //TODO change unrwap here
   } else {
     // check if version is valid
-    version_parser::Version::parse(&rversion).unwrap_or_else(|err| panic!("Failed to parse version: {:?}", err));
+    version_parser::Version::parse(&rversion).unwrap_or_else(|err| fail(Exit::Usage, format!("Failed to parse version: {:?}", err)));
   }
   install_version(env_path.clone(), &rversion);
   //region build env and yaml file
@@ -76,6 +71,22 @@ pub fn main(mut rversion: String, path: &Path, options : &Cli) {
   write_yaml(env, yaml_path).unwrap_or_else(|err| panic!("Failed to write yaml: {:?}", err)); //TODO version recieving null
 }
 
+/// `rvm init <shell>`, the hook that switches versions on cd, meant to be eval'd from the shell's rc file
+/// `rvm init direnv` is the `use rvm` layout, meant for direnvrc
+pub fn hook(hook: Hook) {
+  let shell = match hook {
+    Hook::Shell(shell) => shell,
+    Hook::Direnv => return println!("{}", direnv_layout()),
+  };
+  match shell.cd_hook() {
+    Some(hook) => println!("{}", hook),
+    None => {
+      eprintln!("{:?} has no cd hook, use `rvm shell` or `rvm exec` instead", shell);
+      std::process::exit(Exit::Usage.code());
+    }
+  }
+}
+
 fn create_folder_if_needed(path: &Path) {
   if !path.exists() {
     std::fs::create_dir_all(path).unwrap_or_else(|err| panic!("Failed to create path {}: {:?}", path.to_absolute().display(), err));
@@ -0,0 +1,190 @@
+use crate::actions::{lock_installs, print_plan, run_hooks, target_platform};
+use crate::args::Cli;
+use crate::cancel::on_interrupt;
+use crate::exit::{fail, Classify, Exit, OrExit};
+use crate::actions::pick::{pick, remote_choices};
+use crate::index::{newest_on_channel, on_channel, remote_versions, ArtifactMeta, CachePolicy, CranSource};
+use crate::config::{Backend, Config};
+use crate::hooks::Event;
+use crate::install::{install_dir, install_many, plan, HttpDownloader, Progress};
+use crate::install::budget::{self, Budget};
+use crate::install::source::build_dir;
+use crate::install::transaction::{JournalEntry, Transaction};
+use crate::install::local::install_local;
+use crate::install::git::{install_from_git, version_from_ref};
+use crate::parsing::version_parser::Version;
+use crate::observer;
+use crate::platform::installable;
+use crate::policy::Policy;
+use crate::reporter::reporter_for;
+use crate::resolve::request_range_in;
+use crate::theme;
+use crate::utils::{date_of, format_bytes};
+use std::path::Path;
+
+pub fn main(requests: Vec<String>, jobs: usize, backend: Option<Backend>, channel: Option<&str>, strict_budget: bool, options: &Cli) {
+  let _lock = lock_installs(options);
+  for (_, entry) in JournalEntry::pending() {
+    eprintln!("Found an unfinished install of R {} (pid {}) in {}, `rvm doctor --fix` cleans it", entry.version, entry.pid, entry.staging.display());
+  }
+  let backend = backend.unwrap_or(Config::get().backend);
+  let platform = target_platform(options);
+  let available = remote_versions(&CranSource::default(), CachePolicy::Default)
+    .or_exit("Failed to list remote versions");
+  let available = installable(available, backend, &platform);
+  // resolve everything first, a typo shouldn't leave half the batch installed
+  // the first failure decides the exit code
+  let mut failure: Option<Exit> = None;
+  let mut to_install: Vec<ArtifactMeta> = vec![];
+  for request in &requests {
+    let meta = request_range_in(request, available.iter().map(|m| &m.version), "remote")
+      .map_err(|err| (err.exit(), err.to_string()))
+      .and_then(|range| newest_on_channel(&range, &available, channel).ok_or_else(|| {
+        let candidates = available.iter().map(|m| (&m.version, Some(m.url.as_str())));
+        match (Policy::get().denied_match(&range, candidates), channel) {
+          (Some(denied), _) => (Exit::Denied, denied.to_string()),
+          (None, Some(channel)) => (Exit::NoMatch, format!("no {} version matches {} for {}", channel, range, platform)),
+          (None, None) => (Exit::NoMatch, format!("no version matches {} for {}", range, platform)),
+        }
+      }));
+    match meta {
+      Ok(meta) => {
+        if !to_install.contains(meta) {
+          to_install.push(meta.clone());
+        }
+      },
+      Err((exit, err)) => {
+        eprintln!("[{}] {}", request, err);
+        failure = failure.or(Some(exit));
+      }
+    }
+  }
+  let versions: Vec<Version> = to_install.iter().map(|m| m.version.clone()).collect();
+  if let Some(budget) = budget::check(Config::get().disk_budget_mb, &versions) {
+    warn_budget(&budget, strict_budget && !options.dry_run);
+  }
+  if options.dry_run {
+    for meta in &to_install {
+      print_plan(&meta.version, &plan(meta, backend));
+    }
+    if let Some(exit) = failure {
+      std::process::exit(exit.code());
+    }
+    return;
+  }
+  for meta in &to_install {
+    run_hooks(Event::PreInstall, &meta.version);
+  }
+  let reporter = reporter_for(options);
+  let results = install_many(&to_install, jobs, backend, &HttpDownloader, reporter.as_ref(), &on_interrupt());
+  // summary
+  for (meta, result) in to_install.iter().zip(results) {
+    match result {
+      Ok(path) => {
+        println!("R {} {} in {}", theme::version(&meta.version), theme::success("installed"), path.display());
+        run_hooks(Event::PostInstall, &meta.version);
+      },
+      Err(err) => {
+        eprintln!("{} {}", theme::failure(format!("R {} failed:", meta.version)), err);
+        failure = failure.or(Some(err.exit()));
+      }
+    }
+  }
+  if let Some(exit) = failure {
+    std::process::exit(exit.code());
+  }
+}
+
+/// `rvm install --interactive`, one version picked from the ones that can be installed here
+pub fn interactive(backend: Option<Backend>, channel: Option<&str>, strict_budget: bool, options: &Cli) {
+  let available = remote_versions(&CranSource::default(), CachePolicy::Default)
+    .or_exit("Failed to list remote versions");
+  let mut available = installable(available, backend.unwrap_or(Config::get().backend), &target_platform(options));
+  if let Some(channel) = channel {
+    available.retain(|m| on_channel(&m.version, channel));
+  }
+  let version = pick(options, "Install R", &remote_choices(&available));
+  main(vec![version.to_string()], 1, backend, channel, strict_budget, options)
+}
+
+// what going over disk_budget_mb takes and what could make room, refused with strict
+fn warn_budget(budget: &Budget, strict: bool) {
+  let message = format!("Installing takes about {} more, {} of the {} budget is used", format_bytes(budget.adding), format_bytes(budget.used), format_bytes(budget.limit));
+  if budget.candidates.is_empty() {
+    theme::warning(message);
+  } else {
+    theme::warning(format!("{}, these went unused the longest:", message));
+    for candidate in &budget.candidates {
+      let since = candidate.unused_since.map_or("never used".to_owned(), |secs| format!("unused since {}", date_of(secs)));
+      eprintln!("  R {} ({}, {}), `rvm uninstall {}`", candidate.version, format_bytes(candidate.size), since, candidate.version);
+    }
+  }
+  if strict {
+    fail(Exit::Failure, "Refusing to go over the disk budget (--strict-budget)")
+  }
+}
+
+/// `rvm install --path <tarball|dir> --as <version>`
+pub fn local(path: &Path, version: &str, options: &Cli) {
+  let version = Version::parse(version).unwrap_or_else(|err| fail(Exit::Usage, format!("Failed to parse version {}: {:?}", version, err)));
+  if options.dry_run {
+    let staging = Transaction::staging_for(&version);
+    let what = if path.is_dir() { "copy" } else { "unpack" };
+    let steps = [
+      format!("{} {} into {}", what, path.display(), staging.display()),
+      format!("move {} to {}", staging.display(), install_dir(&version).display()),
+    ];
+    return print_plan(&version, &steps);
+  }
+  let _lock = lock_installs(options);
+  run_hooks(Event::PreInstall, &version);
+  let reporter = reporter_for(options);
+  let result = install_local(path, &version, reporter.as_ref());
+  reporter.report(Progress::Done(&version, &result));
+  observer::notify(|o| o.on_install_complete(&version, &result));
+  match result {
+    Ok(dest) => {
+      println!("R {} {} from {} in {}", theme::version(&version), theme::success("registered"), path.display(), dest.display());
+      run_hooks(Event::PostInstall, &version);
+    },
+    Err(err) => {
+      eprintln!("{} {}", theme::failure(format!("R {} failed:", version)), err);
+      std::process::exit(err.exit().code());
+    }
+  }
+}
+
+/// `rvm install --git <url> [--ref <ref>] [--as <version>]`
+pub fn git(url: &str, reference: Option<&str>, as_version: Option<&str>, options: &Cli) {
+  let version = match (as_version, reference) {
+    (Some(version), _) => Version::parse(version).unwrap_or_else(|err| fail(Exit::Usage, format!("Failed to parse version {}: {:?}", version, err))),
+    (None, Some(reference)) => version_from_ref(reference)
+      .unwrap_or_else(|| panic!("Failed to get a version from {}, pass one with --as", reference)),
+    (None, None) => panic!("Building HEAD needs a version, pass one with --as"),
+  };
+  if options.dry_run {
+    let staging = Transaction::staging_for(&version);
+    let steps = [
+      format!("fetch {} of {} into {}", reference.unwrap_or("HEAD"), url, build_dir(&version).join("git").display()),
+      format!("build it with the configured recipe into {}", staging.display()),
+      format!("move {} to {}", staging.display(), install_dir(&version).display()),
+    ];
+    return print_plan(&version, &steps);
+  }
+  let _lock = lock_installs(options);
+  run_hooks(Event::PreInstall, &version);
+  let reporter = reporter_for(options);
+  let result = install_from_git(url, reference, &version, reporter.as_ref(), &on_interrupt());
+  reporter.report(Progress::Done(&version, &result));
+  observer::notify(|o| o.on_install_complete(&version, &result));
+  match result {
+    Ok(dest) => {
+      println!("R {} {} from {} in {}", theme::version(&version), theme::success("built"), url, dest.display());
+      run_hooks(Event::PostInstall, &version);
+    },
+    Err(err) => {
+      eprintln!("{} {}", theme::failure(format!("R {} failed:", version)), err);
+      std::process::exit(err.exit().code());
+    }
+  }
+}
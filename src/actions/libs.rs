@@ -0,0 +1,58 @@
+use std::path::PathBuf;
+use crate::actions::{confirm, lock_installs};
+use crate::activate::{libs_dir, libs_root};
+use crate::args::{Cli, LibsAction};
+use crate::install::installed_versions;
+use crate::local_utils::curr_dir;
+use crate::parsing::version_parser::Version;
+use crate::resolve::active_version;
+use crate::exit::{fail, Exit, OrExit};
+
+pub fn main(action: &LibsAction, options: &Cli) {
+  match action {
+    LibsAction::Path {version} => {
+      let active = active_version(version.as_deref(), &curr_dir()).or_exit("Failed to resolve version");
+      println!("{}", libs_dir(&active.version).display());
+    },
+    LibsAction::Clean {version} => clean(version.as_deref(), options),
+  }
+}
+
+fn clean(request: Option<&str>, options: &Cli) {
+  let _lock = lock_installs(options);
+  let dirs: Vec<PathBuf> = match request {
+    Some(request) => {
+      let active = active_version(Some(request), &curr_dir()).or_exit("Failed to resolve version");
+      vec![libs_dir(&active.version)]
+    },
+    None => orphaned(),
+  };
+  let dirs: Vec<PathBuf> = dirs.into_iter().filter(|d| d.exists()).collect();
+  if dirs.is_empty() {
+    println!("No libraries to clean");
+    return;
+  }
+  if options.dry_run {
+    for dir in &dirs {
+      println!("Would remove {}", dir.display());
+    }
+    return;
+  }
+  if !confirm(options, &format!("Remove the packages in {} librar(y/ies)?", dirs.len()), false) {
+    fail(Exit::Aborted, "Aborted: User chose not to clean")
+  }
+  for dir in &dirs {
+    std::fs::remove_dir_all(dir).unwrap_or_else(|err| panic!("Failed to remove {}: {}", dir.display(), err));
+    println!("Removed {}", dir.display());
+  }
+}
+
+// libraries of versions that were uninstalled, left behind in case they get installed again
+fn orphaned() -> Vec<PathBuf> {
+  let installed = installed_versions();
+  let Ok(entries) = libs_root().read_dir() else { return vec![] };
+  entries.filter_map(|e| e.ok())
+    .filter(|e| Version::parse_os(&e.file_name()).is_ok_and(|v| !installed.contains(&v)))
+    .map(|e| e.path())
+    .collect()
+}
@@ -0,0 +1,74 @@
+use std::path::{Path, PathBuf};
+use serde::Serialize;
+use crate::args::Cli;
+use crate::constraint_file::ConstraintFile;
+use crate::exit::{fail, Exit, OrExit};
+use crate::local_utils::curr_dir;
+use crate::output::{print_json, print_porcelain};
+use crate::parsing::ast::RangeAst;
+use crate::parsing::lint::Diagnostic;
+use crate::resolve::PROJECT_FILE;
+use crate::theme;
+
+// `rvm lint [file]`, the R constraint of a Renv.yaml or .tool-versions checked for ranges that parse but can't be
+// what was meant (see lint.rs), printed like a compiler's warnings. exits 1 when there's any, for CI
+
+#[derive(Serialize)]
+struct Found {
+  path: PathBuf,
+  /// 1 based, like editors count them
+  line: usize,
+  column: usize,
+  #[serde(flatten)]
+  diagnostic: Diagnostic,
+}
+
+pub fn main(file: Option<&Path>, options: &Cli) {
+  let path = file.map(Path::to_path_buf).or_else(|| ConstraintFile::find(&curr_dir()))
+    .unwrap_or_else(|| fail(Exit::NoMatch, format!("No {} or .tool-versions here or above", PROJECT_FILE)));
+  let file = ConstraintFile::load(&path).or_exit(format!("Failed to read {}", path.display()));
+  let ast = RangeAst::parse(file.constraint());
+  let range = file.range().unwrap_or_else(|err| match ast.point_at_error() {
+    Some(pointed) => fail(Exit::Usage, format!("Failed to parse {} in {}: {:?}\n{}", file.constraint(), path.display(), err, pointed)),
+    None => fail(Exit::Usage, format!("Failed to parse {} in {}: {:?}", file.constraint(), path.display(), err)),
+  });
+  // the spans are in the constraint, moved to where it is in the file
+  let found: Vec<Found> = ast.locate(range.lint()).into_iter().map(|mut diagnostic| {
+    diagnostic.span = diagnostic.span.start + file.span().start..diagnostic.span.end + file.span().start;
+    let (line, column) = line_column(file.contents(), diagnostic.span.start);
+    Found { path: path.clone(), line, column, diagnostic }
+  }).collect();
+  if options.json {
+    print_json("lint", &found);
+  } else if options.porcelain {
+    let rows: Vec<Vec<String>> = found.iter()
+      .map(|f| vec![f.path.display().to_string(), f.line.to_string(), f.column.to_string(), f.diagnostic.lint.name().to_owned(), f.diagnostic.message.clone()])
+      .collect();
+    print_porcelain(&rows);
+  } else {
+    for f in &found {
+      print_human(f, file.contents());
+    }
+  }
+  if !found.is_empty() {
+    std::process::exit(Exit::Failure.code());
+  }
+}
+
+// the line of contents at offset and the column in it, counting chars
+fn line_column(contents: &str, offset: usize) -> (usize, usize) {
+  let before = &contents[..offset];
+  let line_start = before.rfind('\n').map_or(0, |at| at + 1);
+  (before.matches('\n').count() + 1, before[line_start..].chars().count() + 1)
+}
+
+fn print_human(found: &Found, contents: &str) {
+  let span = &found.diagnostic.span;
+  let line_start = contents[..span.start].rfind('\n').map_or(0, |at| at + 1);
+  let line = contents[line_start..].lines().next().unwrap_or_default();
+  // a span over more than the line (it can't be, but) is underlined to its end
+  let width = contents[span.clone()].lines().next().unwrap_or_default().chars().count().max(1);
+  println!("{}:{}:{}: {} {}", found.path.display(), found.line, found.column, theme::warn("warning:"), found.diagnostic.message);
+  println!("  {}", line);
+  println!("  {}{}", " ".repeat(found.column - 1), theme::warn("^".repeat(width)));
+}
@@ -0,0 +1,149 @@
+use std::path::PathBuf;
+use serde::Serialize;
+use crate::actions::ListFilter;
+use crate::alias::Aliases;
+use crate::args::Cli;
+use crate::install::versions_dir;
+use crate::install::scan::{scan, Granularity, Skipped};
+use crate::install::meta::{InstallMeta, InstallOrigin};
+use crate::local_utils::curr_dir;
+use crate::output::{bool_field, opt_field, print_json, print_porcelain};
+use crate::parsing::request::VersionRequest;
+use crate::parsing::version_parser::Version;
+use crate::resolve::{active_version, Origin};
+use crate::theme;
+use crate::utils::{date_of, format_bytes};
+
+// what --json prints, the unmanaged dirs alongside the installs since they're in versions too
+#[derive(Serialize)]
+struct Listed {
+  installed: Vec<Installed>,
+  unmanaged: Vec<Unmanaged>,
+}
+
+#[derive(Serialize)]
+struct Unmanaged {
+  path: PathBuf,
+  /// why rvm doesn't use it, like verbose's "Skipped"
+  reason: String,
+  /// the `rvm adopt` that takes it in
+  adopt: String,
+}
+
+#[derive(Serialize)]
+struct Installed {
+  version: Version,
+  active: bool,
+  pinned: bool,
+  /// left over by an interrupted `rvm try`
+  trial: bool,
+  /// aliases that resolve to this version
+  aliases: Vec<String>,
+  /// why it's active, only on the active one
+  #[serde(skip_serializing_if = "Option::is_none")]
+  origin: Option<Origin>,
+  /// bytes of the install, only with --size
+  #[serde(skip_serializing_if = "Option::is_none")]
+  size: Option<u64>,
+  /// YYYY-MM-DD it last ran through rvm, only with --last-used and when it did
+  #[serde(skip_serializing_if = "Option::is_none")]
+  last_used: Option<String>,
+  /// where it was installed from, in json always and otherwise only with --origin, none when that wasn't recorded
+  #[serde(skip_serializing_if = "Option::is_none")]
+  installed_from: Option<InstallOrigin>,
+}
+
+pub fn main(size: bool, last_used: bool, origin: bool, compact: bool, filter: &ListFilter, options: &Cli) {
+  let scan = scan(&versions_dir());
+  if options.verbose > 0 {
+    for skipped in &scan.skipped {
+      eprintln!("Skipped {}, it {}", skipped.path.display(), skipped.reason);
+    }
+  }
+  let shown: Vec<Version> = match compact {
+    true => scan.pool.latest_per(Granularity::Minor).into_iter().cloned().collect(),
+    false => scan.pool.versions().cloned().collect(),
+  };
+  let installed = scan.pool.into_vec();
+  let unmanaged: Vec<&Skipped> = scan.skipped.iter().filter(|s| s.is_unmanaged()).collect();
+  if installed.is_empty() && unmanaged.is_empty() && !options.json && !options.porcelain {
+    println!("No R version installed, try `rvm install latest`");
+    return;
+  }
+  let active = active_version(None, &curr_dir()).ok();
+  let aliases = Aliases::load().unwrap_or_default();
+  let metas = InstallMeta::all();
+  // newest first, like list-remote
+  let entries: Vec<Installed> = shown.iter().rev().filter(|v| filter.keeps(v)).map(|version| {
+    let is_active = active.as_ref().is_some_and(|a| &a.version == version);
+    let names = aliases.aliases.iter()
+      .filter(|(_, target)| VersionRequest::parse(target).ok()
+        .and_then(|r| r.resolve(&installed).cloned())
+        .is_some_and(|v| &v == version))
+      .map(|(name, _)| name.clone())
+      .collect();
+    let meta = metas.get(version).cloned().unwrap_or_default();
+    Installed {
+      version: version.clone(),
+      active: is_active,
+      pinned: meta.pinned,
+      trial: meta.trial,
+      aliases: names,
+      origin: active.as_ref().filter(|_| is_active).map(|a| a.origin.clone()),
+      size: size.then(|| meta.size_of(version)),
+      last_used: meta.last_used.filter(|_| last_used).map(date_of),
+      installed_from: meta.origin().filter(|_| origin || options.json),
+    }
+  }).collect();
+  if options.json {
+    let unmanaged = unmanaged.iter()
+      .map(|s| Unmanaged { path: s.path.clone(), reason: s.reason.to_string(), adopt: s.adopt_command() })
+      .collect();
+    return print_json("list", &Listed { installed: entries, unmanaged });
+  }
+  if options.porcelain {
+    let rows: Vec<Vec<String>> = entries.iter()
+      .map(|e| vec![e.version.to_string(), bool_field(e.active), bool_field(e.pinned), e.aliases.join(","), opt_field(&e.size), opt_field(&e.last_used), opt_field(&e.installed_from)])
+      .collect();
+    return print_porcelain(&rows);
+  }
+  for entry in entries {
+    let mut notes: Vec<String> = vec![];
+    if entry.pinned {
+      notes.push("pinned".to_owned());
+    }
+    if entry.trial {
+      notes.push("trial".to_owned());
+    }
+    notes.extend(entry.aliases);
+    if let (Some(origin), true) = (entry.origin, options.verbose > 0) {
+      notes.push(origin.to_string());
+    }
+    match (origin, entry.installed_from) {
+      (true, Some(from)) => notes.push(format!("from {}", from)),
+      (true, None) => notes.push("origin unknown".to_owned()),
+      (false, _) => {},
+    }
+    let (marker, version) = if entry.active {
+      (theme::success("*").to_string(), theme::version(&entry.version).to_string())
+    } else {
+      (" ".to_owned(), entry.version.to_string())
+    };
+    let size = entry.size.map(|s| format!("{:>10}  ", format_bytes(s))).unwrap_or_default();
+    let used = match (last_used, &entry.last_used) {
+      (true, Some(day)) => format!("{:>10}  ", day),
+      (true, None) => format!("{:>10}  ", "never"),
+      (false, _) => String::new(),
+    };
+    if notes.is_empty() {
+      println!("{} {}{}{}", marker, size, used, version);
+    } else {
+      println!("{} {}{}{} ({})", marker, size, used, version, notes.join(", "));
+    }
+  }
+  // dirs in versions that rvm can't tell the version of, it doesn't use them until they're adopted
+  for skipped in unmanaged {
+    let name = skipped.path.file_name().unwrap_or_default().to_string_lossy();
+    println!("? {} (unmanaged, `{}`)", name, skipped.adopt_command());
+  }
+}
@@ -0,0 +1,70 @@
+use crate::actions::ListFilter;
+use crate::args::Cli;
+use crate::config::Config;
+use crate::exit::OrExit;
+use crate::index::{on_channel, remote_versions, ArtifactMeta, CachePolicy, CranSource, Support};
+use crate::install::installed_versions;
+use crate::output::{opt_field, print_json, print_json_line, print_porcelain};
+use crate::utils::today;
+
+pub fn main(refresh: bool, channel: Option<&str>, filter: &ListFilter, all_builds: bool, options: &Cli) {
+  let policy = if refresh { CachePolicy::Refresh } else { CachePolicy::Default };
+  let source = CranSource::default();
+  let mut versions = remote_versions(&source, policy).or_exit("Failed to list remote versions");
+  if let Some(channel) = channel {
+    versions.retain(|m| on_channel(&m.version, channel));
+  }
+  versions.retain(|m| filter.keeps(&m.version));
+  // registries that publish several builds of a release show the one resolving would pick
+  if !all_builds {
+    let installed = installed_versions();
+    versions = Config::get().tie_break.dedup(&versions, |m| &m.version, &installed).into_iter().cloned().collect();
+  }
+  if options.json_lines {
+    return versions.iter().for_each(|meta| print_json_line("list-remote", meta));
+  }
+  if options.json {
+    return print_json("list-remote", &versions);
+  }
+  if options.porcelain {
+    let rows: Vec<Vec<String>> = versions.iter()
+      .map(|m| vec![m.version.to_string(), m.url.clone(), opt_field(&m.eol), opt_field(&m.support.map(support_label))])
+      .collect();
+    return print_porcelain(&rows);
+  }
+  let today = today();
+  for meta in versions {
+    let note = support_note(&meta, &today);
+    if options.verbose > 0 {
+      println!("{}\t{}{}", meta.version, meta.url, note);
+    } else {
+      println!("{}{}", meta.version, note);
+    }
+  }
+}
+
+fn support_label(support: Support) -> &'static str {
+  match support {
+    Support::Current => "current",
+    Support::Maintenance => "maintenance",
+    Support::Eol => "eol",
+  }
+}
+
+/// "eol 2025-04-01" once it's past it, "maintenance", none for current ones
+pub fn support_status(meta: &ArtifactMeta, today: &str) -> Option<String> {
+  if meta.is_eol_on(today) {
+    match &meta.eol {
+      Some(eol) => Some(format!("eol {}", eol)),
+      None => Some("eol".to_owned()),
+    }
+  } else if meta.support == Some(Support::Maintenance) {
+    Some("maintenance".to_owned())
+  } else {
+    None
+  }
+}
+
+fn support_note(meta: &ArtifactMeta, today: &str) -> String {
+  support_status(meta, today).map(|status| format!(" ({})", status)).unwrap_or_default()
+}
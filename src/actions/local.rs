@@ -0,0 +1,99 @@
+use std::collections::{BTreeMap, HashMap};
+use crate::actions::run_hooks;
+use crate::args::Cli;
+use crate::audit::{self, Change};
+use crate::config::Config;
+use crate::constraint_file::{Bump, ConstraintFile};
+use crate::exit::{fail, Exit, OrExit};
+use crate::hooks::Event;
+use crate::local_utils::curr_dir;
+use crate::observer;
+use crate::parsing::ast::RangeAst;
+use crate::parsing::version_parser::Range;
+use crate::parsing::yaml_ser::{read_yaml, write_yaml, Env};
+use crate::resolve::{active_version, NONE_FILE, PROJECT_FILE};
+
+/// expires is the day the new range is to be looked at again, an expiry the old one had goes with it
+pub fn main(request: &str, expires: Option<&str>, options: &Cli) {
+  // a range naming versions (>=lts) is written as it is, resolving looks the names up
+  let range = Range::from_request(request).or_else(|err| Range::named(request).map_err(|_| err)).unwrap_or_else(|err| match RangeAst::parse(request).point_at_error() {
+    Some(pointed) => fail(Exit::Usage, format!("Failed to parse range: {}\n{}", err, pointed)),
+    None => fail(Exit::Usage, format!("Failed to parse range {}: {}", request, err)),
+  });
+  let path = curr_dir().join(PROJECT_FILE);
+  // only rversion changes, the rest of an existing file is kept
+  let (env, old) = if path.exists() {
+    let mut env = read_yaml(path.clone()).unwrap_or_else(|err| panic!("Failed to read {}: {}", path.display(), err));
+    let old = std::mem::replace(&mut env.rversion, range.clone());
+    env.rversion_expires = expires.map(str::to_owned);
+    (env, Some(old))
+  } else {
+    // not Env::default(), that asks CRAN for the latest version
+    let env = Env { name: None, description: None, r#type: None, version: None, rversion: range.clone(), rversion_expires: expires.map(str::to_owned), dependencies: HashMap::new(), tools: BTreeMap::new(), commands: BTreeMap::new(), env: BTreeMap::new() };
+    (env, None)
+  };
+  if options.dry_run {
+    let was = old.map(|old| format!(" (was {})", old)).unwrap_or_default();
+    let expiry = expires.map(|day| format!(" expiring on {}", day)).unwrap_or_default();
+    return println!("Would write {} rversion: {}{}{}", path.display(), range, expiry, was);
+  }
+  // use hooks only run when a version that's installed is picked
+  let version = active_version(Some(request), &curr_dir()).ok().map(|active| active.version);
+  let previous = active_version(None, &curr_dir()).ok().map(|active| active.version);
+  if let Some(version) = &version {
+    run_hooks(Event::PreUse, version);
+  }
+  write_yaml(env, path.clone()).unwrap_or_else(|err| panic!("Failed to write {}: {}", path.display(), err));
+  // the project file would be ignored next to it
+  let marker = curr_dir().join(NONE_FILE);
+  if marker.is_file() {
+    std::fs::remove_file(&marker).unwrap_or_else(|err| panic!("Failed to remove {}: {}", marker.display(), err));
+    println!("Removed {}, this dir is a project again", marker.display());
+  }
+  audit::record(Change::Local { path: path.clone(), rversion: range.to_string(), was: old.as_ref().map(Range::to_string) });
+  match old {
+    Some(old) => println!("{} rversion: {} (was {})", path.display(), range, old),
+    None => println!("{} rversion: {}", path.display(), range),
+  }
+  if let Some(day) = expires {
+    println!("It expires on {}, resolving it warns from then on", day);
+  }
+  if let Some(version) = &version {
+    run_hooks(Event::PostUse, version);
+    observer::notify(|o| o.on_switch(previous.as_ref(), version));
+  }
+}
+
+/// `rvm local --none`, the marker that takes this dir and what's under it out of projects and the cd hooks
+pub fn none(options: &Cli) {
+  let path = curr_dir().join(NONE_FILE);
+  if path.is_file() {
+    return println!("{} is already there", path.display());
+  }
+  if options.dry_run {
+    return println!("Would write {}", path.display());
+  }
+  let text = "# written by `rvm local --none`: no project file here or below, and the cd hooks don't switch, delete it to undo\n";
+  std::fs::write(&path, text).unwrap_or_else(|err| panic!("Failed to write {}: {}", path.display(), err));
+  audit::record(Change::Local { path: path.clone(), rversion: "none".to_owned(), was: None });
+  println!("Wrote {}, resolving here falls back to no_project ({})", path.display(), serde_yaml::to_string(&Config::get().no_project).unwrap_or_default().trim());
+  if curr_dir().join(PROJECT_FILE).is_file() {
+    eprintln!("{} next to it is ignored now", PROJECT_FILE);
+  }
+}
+
+/// `rvm local --bump <part>`, only the constraint's text changes so comments and formatting stay
+pub fn bump(part: Bump, options: &Cli) {
+  let path = ConstraintFile::find(&curr_dir())
+    .unwrap_or_else(|| fail(Exit::NoMatch, format!("No {} or .tool-versions here or above", PROJECT_FILE)));
+  let mut file = ConstraintFile::load(&path).or_exit("Failed to read the project's constraint");
+  let old = file.constraint().to_owned();
+  let new = file.bumped(part).or_exit("Failed to bump");
+  if options.dry_run {
+    return println!("Would write {} {} (was {})", path.display(), new, old);
+  }
+  file.rewrite(&new);
+  file.save().or_exit(format!("Failed to write {}", path.display()));
+  audit::record(Change::Local { path: path.clone(), rversion: new.clone(), was: Some(old.clone()) });
+  println!("{} {} (was {})", path.display(), new, old);
+}
@@ -3,11 +3,76 @@ use cli_prompts::{
   DisplayPrompt,
   style::{Color, ConfirmationStyle, Formatting, InputStyle, LabelStyle},
 };
+use std::io::IsTerminal;
+use std::time::Duration;
+use cli_prompts::prompts::Confirmation;
+use crate::args::Cli;
+use crate::error_code::ErrorCode;
+use crate::exit::{fail, fail_coded, Classify, Exit};
+use crate::config::Config;
+use crate::filelock::FileLock;
+use crate::hooks::{self, Event};
+use crate::local_utils::{is_system, system_home};
+use crate::parsing::version_parser::{Range, Version};
+use crate::platform::Platform;
+use crate::theme;
 pub mod init;
 pub mod add;
 pub mod run;
+pub mod list_remote;
+pub mod alias;
+pub mod uninstall;
+pub mod which;
+pub mod exec;
+pub mod each;
+pub mod doctor;
+pub mod prune;
+pub mod pin;
+pub mod protect;
+pub mod list;
+pub mod outdated;
+pub mod upgrade;
+pub mod shell;
+pub mod hook_env;
+pub mod local;
+pub mod global;
+pub mod direnv;
+pub mod resolve;
+pub mod env;
+pub mod libs;
+pub mod plugin;
+pub mod import;
+pub mod export;
+pub mod diff;
+pub mod update;
+pub mod satisfies;
+pub mod lint;
+pub mod compare;
+pub mod complete;
+pub mod sort;
+pub mod du;
+pub mod trial;
+pub mod status;
+pub mod paths;
+pub mod verify;
+pub mod index;
+pub mod check;
+pub mod gc;
 mod lock;
-mod install;
+pub mod install;
+pub mod pick;
+pub mod bundle;
+pub mod ci_snippet;
+pub mod stats;
+pub mod history;
+pub mod adopt;
+pub mod workspace;
+pub mod daemon;
+pub mod hook_bench;
+pub mod query;
+pub mod changes;
+pub mod detect;
+pub mod new;
 
 
 // https://docs.rs/cli-prompts/0.1.0/src/styling/styling.rs.html#38
@@ -41,3 +106,93 @@ pub fn confirmation_style() -> ConfirmationStyle {
     .input_formatting(input_formatting())
     .submitted_formatting(submitted_formatting())
 }
+
+// taken by every action that changes installed versions, held until it's dropped
+pub fn lock_installs(options: &Cli) -> FileLock {
+  FileLock::acquire(Duration::from_secs(options.wait_timeout))
+    .unwrap_or_else(|err| {
+      if is_system() {
+        fail_coded(err.exit(), err.code(), format!("Failed to lock {}: {} (the system prefix {} is shared, changing it takes admin rights)", FileLock::path().display(), err, system_home().display()))
+      }
+      fail_coded(err.exit(), err.code(), format!("Failed to lock {}: {}", FileLock::path().display(), err))
+    })
+}
+
+/// Asks question unless the answer is already known: --yes (or assume_yes in the config) says yes,
+/// and without a way to ask (--no-input, no_input in the config, CI set or no terminal) it aborts
+pub fn confirm(options: &Cli, question: &str, default_positive: bool) -> bool {
+  let config = Config::get();
+  if options.yes || config.assume_yes {
+    return true;
+  }
+  let can_ask = !options.no_input && !config.no_input && std::env::var_os("CI").is_none() && std::io::stdin().is_terminal();
+  if !can_ask {
+    fail(Exit::Aborted, format!("Aborted: \"{}\" needs an answer and input is disabled, pass --yes to go ahead", question));
+  }
+  Confirmation::new(question)
+    .default_positive(default_positive)
+    .style(confirmation_style())
+    .display()
+    .unwrap_or_else(|err| fail(Exit::Aborted, format!("Aborted: {:?}", err)))
+}
+
+/// Runs the hooks of event, a failing pre hook stops the command while a post one only warns, it's done already
+pub fn run_hooks(event: Event, version: &Version) {
+  if let Err(err) = hooks::run(event, version) {
+    if event.is_pre() {
+      fail(Exit::Aborted, format!("Aborted: {}", err))
+    }
+    theme::warning(err);
+  }
+}
+
+/// Says where uninstalling version put what its install didn't create, when it had any
+pub fn report_leftovers(version: &Version, leftovers: Option<std::path::PathBuf>) {
+  if let Some(path) = leftovers {
+    theme::warning(format!("R {} had files rvm didn't install, they're in {}", version, path.display()));
+  }
+}
+
+/// What --dry-run prints for an install
+pub fn print_plan(version: &Version, steps: &[String]) {
+  println!("Would install R {}:", version);
+  for step in steps {
+    println!("  {}", step);
+  }
+}
+
+/// --platform, or the one rvm runs on
+pub fn target_platform(options: &Cli) -> Platform {
+  options.platform.unwrap_or_else(Platform::detect)
+}
+
+/// What `--filter`, `--exclude-prereleases` and `--only-prereleases` of list and list-remote keep
+/// a filter lets pre-releases through only when it names one, like install picks them
+pub struct ListFilter {
+  range: Option<Range>,
+  /// none to leave pre-releases to the filter
+  pre_releases: Option<bool>,
+}
+
+impl ListFilter {
+  pub fn new(filter: Option<&str>, exclude_prereleases: bool, only_prereleases: bool) -> Self {
+    let range = filter.map(|f| Range::from_request(f).unwrap_or_else(|err| fail(Exit::Usage, format!("Failed to parse range {}: {}", f, err))));
+    let pre_releases = match (exclude_prereleases, only_prereleases) {
+      (true, _) => Some(false),
+      (_, true) => Some(true),
+      _ => None,
+    };
+    Self { range, pre_releases }
+  }
+
+  pub fn keeps(&self, version: &Version) -> bool {
+    let is_pre_release = version.channel().is_some();
+    if self.pre_releases.is_some_and(|wanted| wanted != is_pre_release) {
+      return false;
+    }
+    match &self.range {
+      Some(range) => range.contains(version) && (!is_pre_release || self.pre_releases.is_some() || range.names_pre_release()),
+      None => true,
+    }
+  }
+}
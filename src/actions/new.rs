@@ -0,0 +1,69 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use crate::actions::ci_snippet::ci_file;
+use crate::actions::update::lock_for;
+use crate::args::{Cli, SnippetFormat};
+use crate::audit::{self, Change};
+use crate::exit::{fail, Exit};
+use crate::local_utils::curr_dir;
+use crate::lockfile::{ProjectLock, LOCK_FILE};
+use crate::parsing::ast::RangeAst;
+use crate::parsing::version_parser::Range;
+use crate::parsing::yaml_ser::{write_yaml, Env};
+use crate::resolve::PROJECT_FILE;
+
+// `rvm new <dir> --version <range>`, a project that starts out reproducible: its Renv.yaml, the rvm.lock `rvm update`
+// would write for it (unless --no-lock) and with --ci the config running `rvm ci-snippet`'s snippet for that lock.
+// dir can already exist, as long as it isn't a project yet
+pub fn main(dir: &Path, request: &str, no_lock: bool, ci: Option<SnippetFormat>, options: &Cli) {
+  let range = Range::from_request(request).or_else(|err| Range::named(request).map_err(|_| err)).unwrap_or_else(|err| match RangeAst::parse(request).point_at_error() {
+    Some(pointed) => fail(Exit::Usage, format!("Failed to parse range: {}\n{}", err, pointed)),
+    None => fail(Exit::Usage, format!("Failed to parse range {}: {}", request, err)),
+  });
+  let project = dir.join(PROJECT_FILE);
+  if project.exists() {
+    fail(Exit::Usage, format!("{} already exists, `rvm local` changes its range", project.display()));
+  }
+  let lock = (!no_lock).then(|| lock_for(&range, options)
+    .unwrap_or_else(|| fail(Exit::NoMatch, format!("No version matches {}, nothing to lock", range))));
+  let ci = ci.zip(lock.as_ref()).map(|(format, lock)| {
+    let (file, contents) = ci_file(format, &lock.version);
+    (dir.join(file), contents)
+  });
+  if options.dry_run {
+    println!("Would write {} rversion: {}", project.display(), range);
+    if let Some(lock) = &lock {
+      println!("Would lock R {} in {}", lock.version, dir.join(LOCK_FILE).display());
+    }
+    if let Some((path, _)) = &ci {
+      println!("Would write {}", path.display());
+    }
+    return;
+  }
+  if let Some((path, _)) = ci.as_ref().filter(|(path, _)| path.exists()) {
+    fail(Exit::Usage, format!("{} already exists, leave out --ci to keep it", path.display()));
+  }
+  std::fs::create_dir_all(dir).unwrap_or_else(|err| panic!("Failed to create {}: {}", dir.display(), err));
+  // not Env::default(), that asks CRAN for the latest version
+  let env = Env { name: dir_name(dir), description: None, r#type: None, version: None, rversion: range.clone(), rversion_expires: None, dependencies: HashMap::new(), tools: BTreeMap::new(), commands: BTreeMap::new(), env: BTreeMap::new() };
+  write_yaml(env, project.clone()).unwrap_or_else(|err| panic!("Failed to write {}: {}", project.display(), err));
+  audit::record(Change::Local { path: project.clone(), rversion: range.to_string(), was: None });
+  println!("{} rversion: {}", project.display(), range);
+  if let Some(lock) = &lock {
+    let path = ProjectLock::path_for(&project);
+    lock.save(&path).unwrap_or_else(|err| panic!("Failed to write {}: {}", path.display(), err));
+    println!("{} R {}", path.display(), lock.version);
+  }
+  if let Some((path, contents)) = ci {
+    if let Some(parent) = path.parent() {
+      std::fs::create_dir_all(parent).unwrap_or_else(|err| panic!("Failed to create {}: {}", parent.display(), err));
+    }
+    std::fs::write(&path, contents).unwrap_or_else(|err| panic!("Failed to write {}: {}", path.display(), err));
+    println!("{}", path.display());
+  }
+}
+
+// the project's name, what dir is called
+fn dir_name(dir: &Path) -> Option<String> {
+  curr_dir().join(dir).file_name().map(|name| name.to_string_lossy().into_owned())
+}
@@ -0,0 +1,158 @@
+use std::path::PathBuf;
+use serde::Serialize;
+use crate::args::Cli;
+use crate::config::Config;
+use crate::exit::OrExit;
+use crate::index::{remote_versions, CachePolicy, CranSource};
+use crate::install::installed_versions;
+use crate::output::{opt_field, print_json, print_porcelain};
+use crate::parsing::version_parser::{Distance, Range, Version};
+use crate::parsing::yaml_ser::read_yaml;
+use crate::resolve::{find_project_files, with_names};
+use crate::theme;
+
+#[derive(Serialize)]
+struct InstallStatus {
+  version: Version,
+  /// newest of its minor series, none if it's already the newest
+  newest: Option<Version>,
+  /// how far behind newest it is
+  #[serde(skip_serializing_if = "Option::is_none")]
+  behind: Option<Distance>,
+}
+
+#[derive(Serialize)]
+struct ProjectStatus {
+  path: PathBuf,
+  range: Range,
+  /// the installed version it uses now
+  current: Option<Version>,
+  /// the newest remote one it allows
+  newest: Option<Version>,
+  /// how far behind newest current is
+  #[serde(skip_serializing_if = "Option::is_none")]
+  behind: Option<Distance>,
+}
+
+impl ProjectStatus {
+  fn is_outdated(&self) -> bool {
+    match (&self.current, &self.newest) {
+      (Some(current), Some(newest)) => newest > current,
+      (None, Some(_)) => true,
+      _ => false,
+    }
+  }
+}
+
+#[derive(Serialize)]
+struct Outdated {
+  installs: Vec<InstallStatus>,
+  projects: Vec<ProjectStatus>,
+}
+
+// whether distance is past the config's max_minors_behind, a major behind always is
+fn too_far_behind(distance: &Distance) -> bool {
+  Config::get().max_minors_behind.is_some_and(|max| distance.majors > 0 || distance.minors > max)
+}
+
+fn staleness(behind: &Option<Distance>) -> f64 {
+  behind.as_ref().map_or(0.0, Distance::scalar)
+}
+
+// " (2 minors behind)", warned when it's too far
+fn behind_label(behind: &Option<Distance>) -> String {
+  match behind {
+    Some(distance) if too_far_behind(distance) => format!(" {}", theme::warn(format!("({} behind)", distance))),
+    Some(distance) => format!(" ({} behind)", distance),
+    None => String::new(),
+  }
+}
+
+pub fn main(projects: Vec<PathBuf>, sort_by_staleness: bool, options: &Cli) {
+  let remote: Vec<Version> = remote_versions(&CranSource::default(), CachePolicy::Default)
+    .or_exit("Failed to list remote versions")
+    .into_iter()
+    .map(|m| m.version)
+    .collect();
+  let installed = installed_versions();
+  // installed versions can move within their minor series, 4.2.1 -> 4.2.3
+  let mut installs: Vec<InstallStatus> = installed.iter().map(|version| {
+    let (major, minor, _) = version.parts();
+    let series = Range::parse(&format!("~{}.{}", major, minor)).unwrap();
+    let newest = series.max_satisfying(&remote).filter(|c| *c > version).cloned();
+    let behind = newest.as_ref().map(|newest| version.distance(newest));
+    InstallStatus { version: version.clone(), newest, behind }
+  }).collect();
+  // projects can move to whatever their range allows
+  let mut statuses: Vec<ProjectStatus> = vec![];
+  for root in &projects {
+    for project in find_project_files(root) {
+      let Ok(env) = read_yaml(project.clone()) else {
+        eprintln!("Failed to read {}, skipping it", project.display());
+        continue;
+      };
+      let range = match with_names(env.rversion.clone()) {
+        Ok(range) => range,
+        Err(err) => {
+          eprintln!("Failed to resolve the rversion of {}, skipping it: {}", project.display(), err);
+          continue;
+        },
+      };
+      let current = range.max_satisfying(&installed).cloned();
+      let newest = range.max_satisfying(&remote).cloned();
+      let behind = match (&current, &newest) {
+        (Some(current), Some(newest)) if newest > current => Some(current.distance(newest)),
+        _ => None,
+      };
+      statuses.push(ProjectStatus {
+        current,
+        newest,
+        behind,
+        range: env.rversion,
+        path: project,
+      });
+    }
+  }
+  if sort_by_staleness {
+    installs.sort_by(|a, b| staleness(&b.behind).total_cmp(&staleness(&a.behind)));
+    statuses.sort_by(|a, b| staleness(&b.behind).total_cmp(&staleness(&a.behind)));
+  }
+  let outdated = Outdated { installs, projects: statuses };
+  if options.json {
+    return print_json("outdated", &outdated);
+  }
+  if options.porcelain {
+    let installs = outdated.installs.iter()
+      .map(|i| vec!["install".to_owned(), i.version.to_string(), opt_field(&i.newest)]);
+    let projects = outdated.projects.iter()
+      .map(|p| vec!["project".to_owned(), p.path.display().to_string(), p.range.to_string(), opt_field(&p.current), opt_field(&p.newest)]);
+    return print_porcelain(&installs.chain(projects).collect::<Vec<_>>());
+  }
+  let mut found = false;
+  for install in &outdated.installs {
+    match &install.newest {
+      Some(newest) => {
+        println!("R {} → {}{}", install.version, theme::version(newest), behind_label(&install.behind));
+        found = true;
+      },
+      None => if options.verbose > 0 {
+        println!("R {} is up to date", install.version);
+      }
+    }
+  }
+  for project in &outdated.projects {
+    let (path, range) = (project.path.display(), &project.range);
+    match (&project.current, &project.newest) {
+      (_, None) => eprintln!("{} ({}): no remote version matches", path, range),
+      (Some(current), Some(newest)) if project.is_outdated() => println!("{} ({}): {} → {}{}", path, range, current, theme::version(newest), behind_label(&project.behind)),
+      (None, Some(newest)) => println!("{} ({}): not installed → {}", path, range, theme::version(newest)),
+      _ => if options.verbose > 0 {
+        println!("{} ({}) is up to date", path, range);
+      }
+    }
+    found |= project.is_outdated();
+  }
+  if !found {
+    println!("{}", theme::success("Everything is up to date"));
+  }
+}
@@ -0,0 +1,45 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use crate::activate::libs_root;
+use crate::alias::Aliases;
+use crate::args::Cli;
+use crate::config::Config;
+use crate::filelock::FileLock;
+use crate::install::source::builds_dir;
+use crate::install::versions_dir;
+use crate::layout::{config_dir, Layout};
+use crate::output::{print_json, print_porcelain};
+use crate::plugin::{shims_dir, tools_root};
+
+// `rvm paths`, the dirs rvm uses with every override applied, for packagers and for finding things
+pub fn main(options: &Cli) {
+  let layout = Layout::get();
+  let paths: Vec<(&str, PathBuf)> = vec![
+    ("config", layout.config),
+    ("data", layout.data),
+    ("cache", layout.cache),
+    ("state", layout.state),
+    ("config-file", Config::path()),
+    ("system-config-file", Config::system_path()),
+    ("aliases", Aliases::path()),
+    ("hooks", config_dir().join("hooks.d")),
+    ("versions", versions_dir()),
+    ("tools", tools_root()),
+    ("shims", shims_dir()),
+    ("libs", libs_root()),
+    ("build", builds_dir()),
+    ("lock", FileLock::path()),
+  ];
+  if options.json {
+    let map: BTreeMap<&str, &PathBuf> = paths.iter().map(|(name, path)| (*name, path)).collect();
+    return print_json("paths", &map);
+  }
+  if options.porcelain {
+    let rows: Vec<Vec<String>> = paths.iter().map(|(name, path)| vec![name.to_string(), path.display().to_string()]).collect();
+    return print_porcelain(&rows);
+  }
+  let width = paths.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+  for (name, path) in &paths {
+    println!("{:<width$}  {}", name, path.display());
+  }
+}
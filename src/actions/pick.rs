@@ -0,0 +1,200 @@
+use std::io::{IsTerminal, Write};
+use crossterm::{cursor, event, queue, terminal};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use crossterm::style::{Attribute, Print, SetAttribute};
+use crate::actions::list_remote::support_status;
+use crate::args::Cli;
+use crate::config::Config;
+use crate::exit::{fail, Exit};
+use crate::index::{remote_versions, ArtifactMeta, CachePolicy, CranSource};
+use crate::install::installed_versions;
+use crate::install::meta::InstallMeta;
+use crate::local_utils::curr_dir;
+use crate::parsing::version_parser::Version;
+use crate::resolve::active_version;
+use crate::utils::today;
+
+// the list --interactive shows: type to narrow it down (fuzzy, "432" finds 4.3.2), up and down to move, enter to pick, esc to give up.
+// it's drawn on stderr and read from the terminal, so stdout stays what the command prints, `eval "$(rvm use -i)"` works
+
+const SHOWN: usize = 10;
+
+pub struct Choice {
+  pub version: Version,
+  /// shown after it: active, installed, nightly, eol 2025-04-01...
+  pub notes: Vec<String>,
+}
+
+impl Choice {
+  fn label(&self) -> String {
+    match self.notes.is_empty() {
+      true => self.version.to_string(),
+      false => format!("{} ({})", self.version, self.notes.join(", ")),
+    }
+  }
+}
+
+/// Installed versions newest first, with whether they're active or pinned,
+/// and their channel and support when the cached index knows them
+pub fn installed_choices() -> Vec<Choice> {
+  let active = active_version(None, &curr_dir()).ok().map(|a| a.version);
+  let metas = InstallMeta::all();
+  // only what's cached, picking shouldn't wait on the network
+  let known = remote_versions(&CranSource::default(), CachePolicy::Offline).unwrap_or_default();
+  let today = today();
+  installed_versions().into_iter().rev().map(|version| {
+    let mut notes = vec![];
+    if active.as_ref() == Some(&version) {
+      notes.push("active".to_owned());
+    }
+    if metas.get(&version).is_some_and(|m| m.pinned) {
+      notes.push("pinned".to_owned());
+    }
+    notes.extend(version.channel().map(str::to_owned));
+    notes.extend(known.iter().find(|m| m.version == version).and_then(|m| support_status(m, &today)));
+    Choice { version, notes }
+  }).collect()
+}
+
+/// available newest first, with their channel and support and whether they're installed
+pub fn remote_choices(available: &[ArtifactMeta]) -> Vec<Choice> {
+  let installed = installed_versions();
+  let today = today();
+  let mut available: Vec<&ArtifactMeta> = available.iter().collect();
+  available.sort_by(|a, b| b.version.cmp(&a.version));
+  available.dedup_by(|a, b| a.version == b.version);
+  available.into_iter().map(|meta| {
+    let mut notes: Vec<String> = meta.version.channel().map(str::to_owned).into_iter().collect();
+    notes.extend(support_status(meta, &today));
+    if installed.contains(&meta.version) {
+      notes.push("installed".to_owned());
+    }
+    Choice { version: meta.version.clone(), notes }
+  }).collect()
+}
+
+/// How well query matches text, its chars in order but not necessarily together, none if it doesn't
+/// chars right after the previous match and at the start of a part score more, so "4.3" ranks 4.3.0 over 4.1.3
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i64> {
+  let text: Vec<char> = text.to_lowercase().chars().collect();
+  let mut score = 0;
+  let mut from = 0;
+  let mut previous: Option<usize> = None;
+  for wanted in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+    let at = from + text[from..].iter().position(|&c| c == wanted)?;
+    score += if previous.is_some_and(|p| p + 1 == at) { 3 } else { 1 };
+    if at == 0 || !text[at - 1].is_alphanumeric() {
+      score += 2;
+    }
+    previous = Some(at);
+    from = at + 1;
+  }
+  Some(score)
+}
+
+/// The choices matching query, best first and in their order among equals
+fn matching<'a>(choices: &'a [Choice], query: &str) -> Vec<&'a Choice> {
+  let mut scored: Vec<(i64, &Choice)> = choices.iter()
+    .filter_map(|choice| fuzzy_score(query, &choice.label()).map(|score| (score, choice)))
+    .collect();
+  scored.sort_by_key(|(score, _)| -score);
+  scored.into_iter().map(|(_, choice)| choice).collect()
+}
+
+/// Lets the user pick one of choices, newest first is the order they're shown in
+pub fn pick(options: &Cli, prompt: &str, choices: &[Choice]) -> Version {
+  if choices.is_empty() {
+    fail(Exit::NoMatch, format!("{}: there's nothing to pick from", prompt));
+  }
+  let config = Config::get();
+  if options.no_input || config.no_input || !std::io::stdin().is_terminal() || !std::io::stderr().is_terminal() {
+    fail(Exit::Usage, "--interactive needs a terminal, give the version instead");
+  }
+  match run(prompt, choices) {
+    Ok(Some(version)) => version,
+    Ok(None) => fail(Exit::Aborted, "Aborted: no version picked"),
+    Err(err) => fail(Exit::Failure, format!("Failed to show the version list: {}", err)),
+  }
+}
+
+// leaves raw mode however the picker ends
+struct RawMode;
+
+impl RawMode {
+  fn enable() -> std::io::Result<Self> {
+    terminal::enable_raw_mode()?;
+    Ok(Self)
+  }
+}
+
+impl Drop for RawMode {
+  fn drop(&mut self) {
+    let _ = terminal::disable_raw_mode();
+  }
+}
+
+fn run(prompt: &str, choices: &[Choice]) -> std::io::Result<Option<Version>> {
+  let _raw = RawMode::enable()?;
+  let mut out = std::io::stderr();
+  let mut query = String::new();
+  let mut selected = 0;
+  // lines below the prompt drawn last time, to go back up over them
+  let mut below: u16 = 0;
+  loop {
+    let shown = matching(choices, &query);
+    selected = selected.min(shown.len().saturating_sub(1));
+    let first = selected.saturating_sub(SHOWN - 1);
+    if below > 0 {
+      queue!(out, cursor::MoveToPreviousLine(below))?;
+    }
+    queue!(out, Print("\r"), terminal::Clear(terminal::ClearType::FromCursorDown))?;
+    queue!(out, Print(format!("{} {}/{} > {}", prompt, shown.len(), choices.len(), query)))?;
+    below = 0;
+    for (i, choice) in shown.iter().enumerate().skip(first).take(SHOWN) {
+      queue!(out, Print("\r\n"))?;
+      if i == selected {
+        queue!(out, SetAttribute(Attribute::Reverse), Print(format!("> {}", choice.label())), SetAttribute(Attribute::Reset))?;
+      } else {
+        queue!(out, Print(format!("  {}", choice.label())))?;
+      }
+      below += 1;
+    }
+    out.flush()?;
+    let Event::Key(KeyEvent { code, modifiers }) = event::read()? else { continue };
+    let picked = match code {
+      KeyCode::Enter => shown.get(selected).map(|choice| Some(choice.version.clone())),
+      KeyCode::Esc => Some(None),
+      KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => Some(None),
+      KeyCode::Up => {
+        selected = selected.saturating_sub(1);
+        None
+      },
+      KeyCode::Down => {
+        selected = (selected + 1).min(shown.len().saturating_sub(1));
+        None
+      },
+      KeyCode::Backspace => {
+        query.pop();
+        None
+      },
+      KeyCode::Char(c) => {
+        query.push(c);
+        selected = 0;
+        None
+      },
+      _ => None,
+    };
+    if let Some(picked) = picked {
+      // the list goes, the pick stays on the prompt line
+      if below > 0 {
+        queue!(out, cursor::MoveToPreviousLine(below))?;
+      }
+      queue!(out, Print("\r"), terminal::Clear(terminal::ClearType::FromCursorDown))?;
+      if let Some(version) = &picked {
+        queue!(out, Print(format!("{} {}\r\n", prompt, version)))?;
+      }
+      out.flush()?;
+      return Ok(picked);
+    }
+  }
+}
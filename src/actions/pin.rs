@@ -0,0 +1,20 @@
+use crate::actions::lock_installs;
+use crate::args::Cli;
+use crate::exit::{fail, Exit, OrExit};
+use crate::install::installed_versions;
+use crate::install::meta::InstallMeta;
+use crate::resolve::request_range;
+
+pub fn main(request: String, pinned: bool, options: &Cli) {
+  let _lock = lock_installs(options);
+  let range = request_range(&request).or_exit("Failed to parse version");
+  let installed = installed_versions();
+  let version = range.max_satisfying(&installed).unwrap_or_else(|| fail(Exit::NoMatch, format!("No installed version matches {}", request)));
+  if InstallMeta::load(version).pinned == pinned {
+    println!("R {} is already {}", version, if pinned { "pinned" } else { "unpinned" });
+    return;
+  }
+  InstallMeta::update(version, |meta| meta.pinned = pinned)
+    .or_exit(format!("Failed to save metadata of {}", version));
+  println!("R {} {}", version, if pinned { "pinned" } else { "unpinned" });
+}
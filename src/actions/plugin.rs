@@ -0,0 +1,137 @@
+use std::ffi::OsString;
+use duct::cmd;
+use serde::Serialize;
+use crate::actions::{confirm, lock_installs};
+use crate::args::{Cli, PluginAction};
+use crate::index::{newest_matching, remote_versions, CachePolicy};
+use crate::local_utils::curr_dir;
+use crate::output::{print_json, print_porcelain};
+use crate::parsing::version_parser::{Range, Version};
+use crate::resolve::project_tools;
+use crate::stats;
+use crate::plugin::{find_plugin, install_tool, plugins, shims_dir, tool_install_dir, tool_versions, uninstall_tool, Tool};
+use crate::exit::{fail, Exit, OrExit};
+
+#[derive(Serialize)]
+struct PluginStatus {
+  name: String,
+  installed: Vec<Version>,
+}
+
+pub fn main(action: &PluginAction, options: &Cli) {
+  match action {
+    PluginAction::List => list(options),
+    PluginAction::ListRemote {plugin, refresh} => {
+      let tool = get(plugin);
+      let policy = if *refresh { CachePolicy::Refresh } else { CachePolicy::Default };
+      let versions = remote_versions(tool.source().as_ref(), policy).or_exit(format!("Failed to list versions of {}", plugin));
+      if options.json {
+        return print_json("plugin list-remote", &versions);
+      }
+      if options.porcelain {
+        let rows: Vec<Vec<String>> = versions.iter().map(|m| vec![m.version.to_string(), m.url.clone()]).collect();
+        return print_porcelain(&rows);
+      }
+      for meta in versions {
+        println!("{}", meta.version);
+      }
+    },
+    PluginAction::Install {plugin, version} => install(get(plugin).as_ref(), version, options),
+    PluginAction::Uninstall {plugin, version} => {
+      let tool = get(plugin);
+      let _lock = lock_installs(options);
+      let version = installed_matching(tool.as_ref(), version);
+      if options.dry_run {
+        return println!("Would remove {}", tool_install_dir(plugin, &version).display());
+      }
+      if !confirm(options, &format!("Uninstall {} {}?", plugin, version), false) {
+        fail(Exit::Aborted, "Aborted: User chose not to uninstall")
+      }
+      uninstall_tool(tool.as_ref(), &version).or_exit(format!("Failed to uninstall {} {}", plugin, version));
+      println!("{} {} uninstalled", plugin, version);
+    },
+    PluginAction::Exec {plugin, version, command} => {
+      let tool = get(plugin);
+      let version = match version {
+        Some(request) => installed_matching(tool.as_ref(), request),
+        None => project_version(plugin),
+      };
+      let mut paths = tool.bin_dirs(&tool_install_dir(plugin, &version));
+      if let Some(current) = std::env::var_os("PATH") {
+        // the shims would find themselves again otherwise
+        let shims = shims_dir();
+        paths.extend(std::env::split_paths(&current).filter(|p| p != &shims));
+      }
+      let path: OsString = std::env::join_paths(paths).unwrap_or_else(|err| panic!("Failed to build PATH: {}", err));
+      let (program, args) = command.split_first().unwrap_or_else(|| fail(Exit::Usage, "No command given"));
+      stats::count_run(plugin, &version);
+      let output = cmd(program, args).env("PATH", path).unchecked().run()
+        .unwrap_or_else(|err| panic!("Failed to run {}: {}", program, err));
+      std::process::exit(output.status.code().unwrap_or(1));
+    },
+  }
+}
+
+// what the project file asks for, or the newest installed if it doesn't mention the tool
+fn project_version(tool: &str) -> Version {
+  let tools = project_tools(&curr_dir()).or_exit("Failed to resolve version");
+  match tools.into_iter().find(|t| t.tool == tool) {
+    Some(active) => active.version.unwrap_or_else(|| fail(Exit::NoMatch, format!("No installed version of {} matches {} ({}), try `rvm plugin install {} '{}'`", tool, active.range, active.project.display(), tool, active.range))),
+    None => tool_versions(tool).pop().unwrap_or_else(|| fail(Exit::NoMatch, format!("No version of {} is installed, try `rvm plugin install {} <version>`", tool, tool))),
+  }
+}
+
+fn get(name: &str) -> Box<dyn Tool> {
+  find_plugin(name).or_exit("Failed to find plugin")
+}
+
+fn installed_matching(tool: &dyn Tool, request: &str) -> Version {
+  let range = Range::from_request(request).unwrap_or_else(|err| fail(Exit::Usage, format!("Failed to parse version: {:?}", err)));
+  let installed = tool_versions(tool.name());
+  range.max_satisfying(&installed)
+    .unwrap_or_else(|| fail(Exit::NoMatch, format!("No installed version of {} matches {}", tool.name(), request)))
+    .clone()
+}
+
+fn list(options: &Cli) {
+  let statuses: Vec<PluginStatus> = plugins().iter()
+    .map(|p| PluginStatus { name: p.name().to_owned(), installed: tool_versions(p.name()) })
+    .collect();
+  if options.json {
+    return print_json("plugin list", &statuses);
+  }
+  if options.porcelain {
+    let rows: Vec<Vec<String>> = statuses.iter()
+      .map(|s| vec![s.name.clone(), s.installed.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(",")])
+      .collect();
+    return print_porcelain(&rows);
+  }
+  if statuses.is_empty() {
+    println!("No plugins found, they're rvm-plugin-<name> executables on PATH or `plugins` in the config");
+  }
+  for status in statuses {
+    let installed: Vec<String> = status.installed.iter().map(|v| v.to_string()).collect();
+    println!("{}\t{}", status.name, if installed.is_empty() { "-".to_owned() } else { installed.join(", ") });
+  }
+}
+
+/// Installs the newest version of tool matching request, unless it already is
+pub fn install(tool: &dyn Tool, request: &str, options: &Cli) {
+  let _lock = lock_installs(options);
+  let available = remote_versions(tool.source().as_ref(), CachePolicy::Default)
+    .or_exit(format!("Failed to list versions of {}", tool.name()));
+  let range = Range::from_request(request).unwrap_or_else(|err| fail(Exit::Usage, format!("Failed to parse version: {:?}", err)));
+  let meta = newest_matching(&range, &available).unwrap_or_else(|| fail(Exit::NoMatch, format!("No version of {} matches {}", tool.name(), range)));
+  let dest = tool_install_dir(tool.name(), &meta.version);
+  if dest.exists() {
+    return println!("{} {} is already installed", tool.name(), meta.version);
+  }
+  if options.dry_run {
+    return println!("Would install {} {} from {} into {}", tool.name(), meta.version, meta.url, dest.display());
+  }
+  let dest = install_tool(tool, meta).or_exit(format!("Failed to install {} {}", tool.name(), meta.version));
+  println!("{} {} installed in {}", tool.name(), meta.version, dest.display());
+  if options.verbose > 0 {
+    println!("Its executables are shimmed in {}", shims_dir().display());
+  }
+}
@@ -0,0 +1,36 @@
+use crate::actions::lock_installs;
+use crate::args::Cli;
+use crate::config::Config;
+use crate::exit::{fail, Exit, OrExit};
+use crate::install::{install_dir, installed_versions};
+use crate::install::meta::InstallMeta;
+use crate::install::protect::{self, Protect};
+use crate::resolve::request_range;
+
+// `rvm lock` and `rvm unlock`, an install's protection (see install/protect.rs) put back or taken off
+pub fn main(request: String, locked: bool, options: &Cli) {
+  let _lock = lock_installs(options);
+  let range = request_range(&request).or_exit("Failed to parse version");
+  let installed = installed_versions();
+  let version = range.max_satisfying(&installed).unwrap_or_else(|| fail(Exit::NoMatch, format!("No installed version matches {}", request)));
+  if (InstallMeta::load(version).protection != Protect::Off) == locked {
+    println!("R {} is already {}", version, if locked { "locked" } else { "unlocked" });
+    return;
+  }
+  if options.dry_run {
+    println!("Would make {} {}", install_dir(version).display(), if locked { "read-only" } else { "writable" });
+    return;
+  }
+  if locked {
+    // locking on demand is what the config is for, unless it says not to protect at all
+    let protect = match Config::get().protect {
+      Protect::Off => Protect::ReadOnly,
+      protect => protect,
+    };
+    let protect = protect::lock(version, &install_dir(version), protect).or_exit(format!("Failed to lock R {}", version));
+    println!("R {} locked ({})", version, if protect == Protect::Immutable { "immutable" } else { "read-only" });
+  } else {
+    protect::unlock(version).or_exit(format!("Failed to unlock R {}", version));
+    println!("R {} unlocked, `rvm lock {}` protects it again", version, version);
+  }
+}
@@ -0,0 +1,114 @@
+use std::collections::{BTreeMap, HashSet};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use crate::actions::{confirm, lock_installs, report_leftovers, run_hooks};
+use crate::alias::Aliases;
+use crate::args::Cli;
+use crate::exit::{fail, Exit, OrExit};
+use crate::hooks::Event;
+use crate::install::{install_dir, installed_versions, uninstall};
+use crate::install::meta::InstallMeta;
+use crate::parsing::request::VersionRequest;
+use crate::parsing::version_parser::Version;
+use crate::parsing::yaml_ser::read_yaml;
+use crate::resolve::{find_project_files, with_names};
+use crate::utils::format_bytes;
+
+pub fn main(projects: Vec<PathBuf>, keep_latest_per_minor: Option<usize>, older_than: Option<Duration>, unused_for: Option<Duration>, options: &Cli) {
+  let _lock = lock_installs(options);
+  let installed = installed_versions();
+  // everything aliases and projects would resolve to right now is kept
+  let mut keep: HashSet<Version> = HashSet::new();
+  let aliases = Aliases::load().unwrap_or_else(|err| panic!("Failed to read {}: {}", Aliases::path().display(), err));
+  for (name, target) in &aliases.aliases {
+    if let Some(v) = VersionRequest::parse(target).ok().and_then(|r| r.resolve(&installed).cloned()) {
+      if options.verbose > 0 {
+        println!("Keeping {} (alias {})", v, name);
+      }
+      keep.insert(v);
+    }
+  }
+  for root in &projects {
+    for project in find_project_files(root) {
+      let Ok(env) = read_yaml(project.clone()) else {
+        eprintln!("Failed to read {}, skipping it", project.display());
+        continue;
+      };
+      let range = match with_names(env.rversion) {
+        Ok(range) => range,
+        Err(err) => {
+          eprintln!("Failed to resolve the rversion of {}, skipping it: {}", project.display(), err);
+          continue;
+        },
+      };
+      if let Some(v) = range.max_satisfying(&installed) {
+        if options.verbose > 0 {
+          println!("Keeping {} ({})", v, project.display());
+        }
+        keep.insert(v.clone());
+      }
+    }
+  }
+  if let Some(n) = keep_latest_per_minor {
+    let mut per_minor: BTreeMap<(u32, u32), Vec<&Version>> = BTreeMap::new();
+    for v in &installed {
+      let (major, minor, _) = v.parts();
+      per_minor.entry((major, minor)).or_default().push(v);
+    }
+    for versions in per_minor.values() {
+      // installed_versions is sorted, so the newest are at the end
+      keep.extend(versions.iter().rev().take(n).map(|v| (*v).clone()));
+    }
+  }
+  let metas = InstallMeta::all();
+  let meta = |v: &Version| metas.get(v).cloned().unwrap_or_default();
+  let candidates: Vec<&Version> = installed.iter()
+    .filter(|v| !keep.contains(v))
+    .filter(|v| !meta(v).pinned)
+    .filter(|v| older_than.map_or(true, |age| is_older_than(v, &meta(v), age)))
+    .filter(|v| unused_for.map_or(true, |age| is_unused_for(v, &meta(v), age)))
+    .collect();
+  if candidates.is_empty() {
+    println!("Nothing to prune");
+    return;
+  }
+  let mut total = 0;
+  for v in &candidates {
+    let size = meta(v).size_of(v);
+    total += size;
+    println!("{} R {} ({})", if options.dry_run { "Would remove" } else { "Removing" }, v, format_bytes(size));
+  }
+  if options.dry_run {
+    println!("{} would be reclaimed", format_bytes(total));
+    return;
+  }
+  if !confirm(options, &format!("Remove {} version(s)?", candidates.len()), false) {
+    fail(Exit::Aborted, "Aborted: User chose not to prune")
+  }
+  for v in &candidates {
+    run_hooks(Event::PreUninstall, v);
+    let leftovers = uninstall(v).or_exit(format!("Failed to uninstall {}", v));
+    report_leftovers(v, leftovers);
+    run_hooks(Event::PostUninstall, v);
+  }
+  println!("{} reclaimed", format_bytes(total));
+}
+
+fn is_older_than(version: &Version, meta: &InstallMeta, age: Duration) -> bool {
+  is_before(version, meta.installed_at, age)
+}
+
+fn is_unused_for(version: &Version, meta: &InstallMeta, age: Duration) -> bool {
+  is_before(version, meta.unused_since(), age)
+}
+
+// whether secs is longer ago than age, installs from before the dates were recorded go by the dir's mtime
+fn is_before(version: &Version, secs: Option<u64>, age: Duration) -> bool {
+  let at = match secs {
+    Some(secs) => Some(UNIX_EPOCH + Duration::from_secs(secs)),
+    None => std::fs::metadata(install_dir(version)).and_then(|m| m.modified()).ok(),
+  };
+  at
+    .and_then(|at| SystemTime::now().duration_since(at).ok())
+    .is_some_and(|elapsed| elapsed > age)
+}
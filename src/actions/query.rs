@@ -0,0 +1,19 @@
+use std::io::{self, BufReader};
+use crate::exit::{fail, Exit};
+use crate::local_utils::curr_dir;
+use crate::query::{reply, serve, Answer};
+
+// `rvm query`, one request answered and exit 1 if it's an error, or with none every line of stdin until it ends.
+// the answers are the protocol (see query.rs), --json and --porcelain don't change them
+pub fn main(request: Option<&str>) {
+  let dir = curr_dir();
+  let Some(request) = request else {
+    return serve(BufReader::new(io::stdin().lock()), io::stdout().lock(), &dir)
+      .unwrap_or_else(|err| fail(Exit::Failure, format!("Failed to answer queries: {}", err)));
+  };
+  let reply = reply(request, &dir);
+  println!("{}", serde_json::to_string(&reply).unwrap_or_else(|err| panic!("Failed to serialize the answer: {}", err)));
+  if matches!(reply.answer, Answer::Error { .. }) {
+    std::process::exit(Exit::Failure.code());
+  }
+}
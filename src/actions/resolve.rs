@@ -0,0 +1,141 @@
+use serde::Serialize;
+use crate::actions::target_platform;
+use crate::alias::expand_alias;
+use crate::args::Cli;
+use crate::exit::{fail, Exit, OrExit};
+use crate::config::Config;
+use crate::filter;
+use crate::index::{newest_matching, remote_versions, CachePolicy, CranSource};
+use crate::install::installed_versions;
+use crate::parsing::version_parser::{Range, Version};
+use crate::platform::installable;
+use crate::local_utils::curr_dir;
+use crate::output::{print_json, print_porcelain};
+use crate::resolve::{active_version, request_range, request_range_in, Active, Origin, Verdict};
+use crate::resolve;
+
+#[derive(Serialize)]
+struct Best<'a> {
+  version: &'a Version,
+  range: &'a Range,
+  /// installed or remote
+  from: &'static str,
+}
+
+pub fn main(options: &Cli) {
+  let active = active_version(None, &curr_dir()).or_exit("Failed to resolve version");
+  if options.json {
+    print_json("resolve", &active);
+  } else if options.porcelain {
+    print_porcelain(&[active_row(&active)]);
+  } else {
+    println!("R {} (matches {}, {})", active.version, active.range, active.origin);
+  }
+}
+
+/// `rvm resolve <range>`, only the version so scripts can use it as is
+pub fn best(request: &str, remote: bool, options: &Cli) {
+  let (range, version) = if remote {
+    let backend = Config::get().backend;
+    let available = remote_versions(&CranSource::default(), CachePolicy::Default)
+      .or_exit("Failed to list remote versions");
+    let available = installable(available, backend, &target_platform(options));
+    let range = request_range_in(request, available.iter().map(|m| &m.version), "remote").or_exit(format!("Failed to resolve {}", request));
+    let version = newest_matching(&range, &available).map(|meta| meta.version.clone());
+    (range, version)
+  } else {
+    let range = request_range(request).or_exit(format!("Failed to resolve {}", request));
+    let installed = installed_versions();
+    let version = Config::get().tie_break.newest(&range, &filter::installed_candidates(&installed), &installed).cloned();
+    (range, version)
+  };
+  let Some(version) = version else {
+    eprintln!("No {} version matches {}", if remote { "remote" } else { "installed" }, range);
+    std::process::exit(Exit::NoMatch.code());
+  };
+  if options.json {
+    print_json("resolve", &Best { version: &version, range: &range, from: if remote { "remote" } else { "installed" } });
+  } else {
+    println!("{}", version);
+  }
+}
+
+#[derive(Serialize)]
+struct Minimal<'a> {
+  version: &'a Version,
+  range: &'a Range,
+  /// versions the range skips between its bounds
+  holes: Vec<&'a Version>,
+}
+
+/// `rvm resolve <range> --minimal`, the low end of the range without looking at what exists
+pub fn minimal(request: &str, options: &Cli) {
+  let range = Range::from_request(&expand_alias(request)).unwrap_or_else(|err| fail(Exit::Usage, format!("Failed to parse range {}: {:?}", request, err)));
+  let Some(version) = range.minimal_version() else {
+    eprintln!("No version can match {}", range);
+    std::process::exit(Exit::NoMatch.code());
+  };
+  if options.json {
+    print_json("resolve", &Minimal { version: &version, range: &range, holes: range.holes() });
+  } else {
+    println!("{}", version);
+    if options.verbose > 0 && range.has_hole() {
+      let holes: Vec<String> = range.holes().iter().map(|v| v.to_string()).collect();
+      eprintln!("{} skips {}", range, holes.join(", "));
+    }
+  }
+}
+
+fn active_row(active: &Active) -> Vec<String> {
+  let (kind, from) = match &active.origin {
+    Origin::Request(request) => ("request", request.clone()),
+    Origin::Shell(request) => ("shell", request.clone()),
+    Origin::Env(request) => ("env", request.clone()),
+    Origin::Project(path) => ("project", path.display().to_string()),
+    Origin::Lockfile(path) => ("lockfile", path.display().to_string()),
+    Origin::Command(command, path) => ("command", format!("{} {}", command, path.display())),
+    Origin::Default => ("default", String::new()),
+    Origin::Latest => ("latest", String::new()),
+  };
+  vec![active.version.to_string(), active.range.to_string(), kind.to_owned(), from]
+}
+
+/// `rvm resolve --explain`, the sources and then the installed versions as a tree under the range they were held to
+pub fn explain(request: Option<&str>, options: &Cli) {
+  let trace = resolve::explain(request, &curr_dir());
+  let failed = trace.error.is_some();
+  if options.json {
+    print_json("resolve", &trace);
+  } else if options.porcelain {
+    let rows: Vec<Vec<String>> = trace.steps.iter()
+      .map(|s| vec![format!("{:?}", s.source).to_lowercase(), if s.used { "1" } else { "0" }.to_owned(), s.reason.clone()])
+      .collect();
+    print_porcelain(&rows);
+  } else {
+    for step in &trace.steps {
+      let source = format!("{:?}", step.source).to_lowercase();
+      println!("{} {:<9} {}", if step.used { "*" } else { " " }, source, step.reason);
+    }
+    if let (Some(range), Some(origin)) = (&trace.range, &trace.origin) {
+      println!("{} ({})", range, origin);
+      for (i, candidate) in trace.candidates.iter().enumerate() {
+        let branch = if i + 1 == trace.candidates.len() { "`--" } else { "|--" };
+        let verdict = match &candidate.verdict {
+          Verdict::Selected => "selected".to_owned(),
+          Verdict::Older => "matches, but is older".to_owned(),
+          Verdict::Excluded(comparator) => format!("excluded by {}", comparator),
+          Verdict::Filtered(name) => format!("matches, but refused by {}", name),
+        };
+        println!("{} {:<10} {}", branch, candidate.version.to_string(), verdict);
+      }
+    }
+    match (&trace.selected, &trace.error) {
+      (_, Some(err)) => println!("Failed: {}", err),
+      (Some(version), None) => println!("R {}", version),
+      (None, None) => {},
+    }
+  }
+  if failed {
+    std::process::exit(1);
+  }
+}
@@ -0,0 +1,18 @@
+use serde_json::json;
+use crate::args::Cli;
+use crate::exit::{fail, Exit};
+use crate::output::print_json;
+use crate::parsing::version_parser::{Range, Version};
+
+// the answer is the exit code, so it works in `if rvm satisfies ...; then`
+pub fn main(version: &str, range: &str, options: &Cli) {
+  let parsed = Version::parse(version).unwrap_or_else(|err| fail(Exit::Usage, format!("Failed to parse version {}: {:?}", version, err)));
+  let range = Range::from_request(range).unwrap_or_else(|err| fail(Exit::Usage, format!("Failed to parse range {}: {:?}", range, err)));
+  let satisfies = range.contains(&parsed);
+  if options.json {
+    print_json("satisfies", &json!({"version": parsed, "range": range, "satisfies": satisfies}));
+  } else if options.verbose > 0 {
+    println!("{} {} {}", parsed, if satisfies { "satisfies" } else { "doesn't satisfy" }, range);
+  }
+  std::process::exit(if satisfies { 0 } else { 1 });
+}
@@ -0,0 +1,51 @@
+use std::io::IsTerminal;
+use crate::actions::pick::{installed_choices, pick};
+use crate::actions::run_hooks;
+use crate::args::Cli;
+use crate::exit::OrExit;
+use crate::hooks::Event;
+use crate::local_utils::curr_dir;
+use crate::observer;
+use crate::activate::find_system_binary;
+use crate::resolve::{active_version, selects_system, SHELL_VAR};
+use crate::shell::Shell;
+
+// prints the code to eval, anything meant for the user goes to stderr so it doesn't get eval'd
+pub fn main(request: Option<String>, unset: bool, shell: Option<Shell>, options: &Cli) {
+  let shell = shell.unwrap_or_else(Shell::detect);
+  let command = match &request {
+    // nothing to resolve, the hooks take rvm out of PATH
+    Some(request) if !unset && selects_system(request) => {
+      match find_system_binary("R") {
+        Some(r) if options.verbose > 0 => eprintln!("System R ({}) for this shell", r.display()),
+        Some(_) => {},
+        None => eprintln!("rvm: there's no R on PATH outside rvm, R won't be found in this shell"),
+      }
+      shell.set_var(SHELL_VAR, request)
+    },
+    Some(request) if !unset => {
+      // fail now rather than on every command in the session
+      let active = active_version(Some(request), &curr_dir()).or_exit("Failed to resolve version");
+      if options.verbose > 0 {
+        eprintln!("R {} for this shell", active.version);
+      }
+      let previous = active_version(None, &curr_dir()).ok().map(|active| active.version);
+      run_hooks(Event::PreUse, &active.version);
+      run_hooks(Event::PostUse, &active.version);
+      observer::notify(|o| o.on_switch(previous.as_ref(), &active.version));
+      shell.set_var(SHELL_VAR, request)
+    },
+    _ => shell.unset_var(SHELL_VAR),
+  };
+  println!("{}", command);
+  if std::io::stdout().is_terminal() {
+    let args: Vec<String> = std::env::args().collect();
+    eprintln!("To apply it, run: {}", shell.eval_hint(&args.join(" ")));
+  }
+}
+
+/// `rvm shell --interactive` (`rvm use -i`), the version picked from the installed ones
+pub fn interactive(shell: Option<Shell>, options: &Cli) {
+  let version = pick(options, "Use R", &installed_choices());
+  main(Some(version.to_string()), false, shell, options)
+}
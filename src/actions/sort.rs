@@ -0,0 +1,71 @@
+use std::collections::{BTreeMap, HashSet};
+use std::hash::Hash;
+use std::io::BufRead;
+use crate::args::{Cli, Equality};
+use crate::install::scan::Granularity;
+use crate::install::git::version_from_ref;
+use crate::parsing::version_parser::{sort_by_cached_precedence_key, PrecedenceKey, Range, RangeKey, StrictKey, Version, VersionFormatter};
+use crate::exit::{fail, Exit};
+
+// like `sort -V` but with rvm's ordering, lines are printed as they came in unless there's a format
+// anything that isn't a version (also tags like R-4-3-1 or refs/tags/v4.2.0) is dropped
+pub fn main(reverse: bool, filter: Option<&str>, latest_per: Option<Granularity>, unique: Option<Equality>, format: Option<&VersionFormatter>, options: &Cli) {
+  let range = filter.map(|f| Range::from_request(f).unwrap_or_else(|err| fail(Exit::Usage, format!("Failed to parse range {}: {:?}", f, err))));
+  let mut versions: Vec<(Version, String)> = vec![];
+  for line in std::io::stdin().lock().lines() {
+    let line = line.unwrap_or_else(|err| panic!("Failed to read stdin: {}", err));
+    let text = line.trim();
+    if text.is_empty() {
+      continue;
+    }
+    match Version::parse(text).ok().or_else(|| version_from_ref(text)) {
+      Some(version) => versions.push((version, text.to_owned())),
+      None if options.verbose > 0 => eprintln!("Skipped {}, not a version", text),
+      None => {},
+    }
+  }
+  if let Some(range) = &range {
+    versions.retain(|(v, _)| range.contains(v));
+  }
+  sort_by_cached_precedence_key(&mut versions, |(v, _)| v); // stable, equal versions keep their order
+  match unique {
+    Some(Equality::Strict) => keep_first(&mut versions, StrictKey),
+    Some(Equality::Precedence) => keep_first(&mut versions, PrecedenceKey),
+    Some(Equality::Range) => keep_first(&mut versions, RangeKey),
+    None => {},
+  }
+  if let Some(series) = latest_per {
+    let mut newest: BTreeMap<(u32, u32), (Version, String)> = BTreeMap::new();
+    for (version, text) in versions {
+      newest.insert(series.series(&version), (version, text)); // sorted, so the last one is the newest
+    }
+    versions = newest.into_values().collect();
+  }
+  if reverse {
+    versions.reverse();
+  }
+  for (version, text) in versions {
+    match format {
+      Some(format) => println!("{}", format.format(&version)),
+      None => println!("{}", text),
+    }
+  }
+}
+
+// the oldest of the versions with the same key, like `sort -u` keeps the first line
+fn keep_first<K: Hash + Eq>(versions: &mut Vec<(Version, String)>, key: impl Fn(Version) -> K) {
+  let mut seen = HashSet::new();
+  versions.retain(|(version, _)| seen.insert(key(version.clone())));
+}
+
+/// The format the flags ask for, none to print the lines as they were
+pub fn formatter(normalize: bool, pad: &[usize], v_prefix: bool, short: bool, keep_zeros: bool) -> Option<VersionFormatter> {
+  if !normalize && pad.is_empty() && !v_prefix && !short && !keep_zeros {
+    return None;
+  }
+  // one width is for all of them, a missing one is the last given
+  let width = |i: usize| pad.get(i).or(pad.last()).copied().unwrap_or(0);
+  let mut format = VersionFormatter::new();
+  format.pad(width(0), width(1), width(2)).v_prefix(v_prefix).hide_extra_version(short).hide_build(short).keep_zeros(keep_zeros);
+  Some(format)
+}
@@ -0,0 +1,90 @@
+use std::collections::BTreeMap;
+use serde::Serialize;
+use crate::args::Cli;
+use crate::install::meta::InstallMeta;
+use crate::output::{print_json, print_porcelain};
+use crate::stats::{enabled, DownloadCounts, IndexCounts, Stats};
+use crate::theme;
+use crate::utils::date_of;
+
+// what rvm can say about how it's used from what's on this machine: installs per month from the install metadata,
+// and with `stats: true` in the config, the counters stats.rs keeps. none of it leaves the machine
+
+#[derive(Serialize)]
+struct Report {
+  /// whether runs, index lookups and downloads are being counted
+  counting: bool,
+  /// YYYY-MM to how many of the current installs were installed then
+  installs_per_month: BTreeMap<String, u64>,
+  since: Option<String>,
+  runs: Vec<Run>,
+  index: IndexCounts,
+  index_hit_rate: Option<f64>,
+  downloads: DownloadCounts,
+}
+
+#[derive(Serialize)]
+struct Run {
+  tool: String,
+  version: String,
+  count: u64,
+}
+
+pub fn main(options: &Cli) {
+  let stats = Stats::load();
+  let mut installs_per_month: BTreeMap<String, u64> = BTreeMap::new();
+  for installed_at in InstallMeta::all().values().filter_map(|meta| meta.installed_at) {
+    *installs_per_month.entry(date_of(installed_at)[..7].to_owned()).or_default() += 1;
+  }
+  let report = Report {
+    counting: enabled(),
+    installs_per_month,
+    since: stats.since.map(date_of),
+    runs: stats.most_used().into_iter()
+      .map(|(tool, version, count)| Run { tool: tool.to_owned(), version: version.to_string(), count })
+      .collect(),
+    index: stats.index,
+    index_hit_rate: stats.index.hit_rate(),
+    downloads: stats.downloads,
+  };
+  if options.json {
+    return print_json("stats", &report);
+  }
+  if options.porcelain {
+    let mut rows: Vec<Vec<String>> = report.installs_per_month.iter()
+      .map(|(month, count)| vec!["installs".to_owned(), month.clone(), count.to_string()])
+      .collect();
+    rows.extend(report.runs.iter().map(|run| vec!["runs".to_owned(), format!("{} {}", run.tool, run.version), run.count.to_string()]));
+    let counts = [("index", "cached", report.index.cached), ("index", "revalidated", report.index.revalidated), ("index", "fetched", report.index.fetched),
+      ("downloads", "full", report.downloads.full), ("downloads", "delta", report.downloads.delta)];
+    rows.extend(counts.iter().map(|(kind, name, count)| vec![kind.to_string(), name.to_string(), count.to_string()]));
+    return print_porcelain(&rows);
+  }
+  println!("Installs per month:");
+  if report.installs_per_month.is_empty() {
+    println!("  none recorded");
+  }
+  for (month, count) in &report.installs_per_month {
+    println!("  {}  {}", month, count);
+  }
+  if !report.counting && report.since.is_none() {
+    println!("Runs, index lookups and downloads aren't counted, set `stats: true` in the config to count them (locally)");
+    return;
+  }
+  println!("Most used{}:", report.since.as_ref().map(|since| format!(" since {}", since)).unwrap_or_default());
+  if report.runs.is_empty() {
+    println!("  nothing ran through rvm yet");
+  }
+  for run in report.runs.iter().take(10) {
+    let name = if run.tool == "r" { "R".to_owned() } else { run.tool.clone() };
+    println!("  {} {}  {}", name, theme::version(&run.version), run.count);
+  }
+  match report.index_hit_rate {
+    Some(rate) => println!("Index cache: {:.0}% hits ({} cached, {} revalidated, {} fetched)", rate * 100.0, report.index.cached, report.index.revalidated, report.index.fetched),
+    None => println!("Index cache: not used yet"),
+  }
+  println!("Downloads: {} whole, {} patched from an older version", report.downloads.full, report.downloads.delta);
+  if !report.counting {
+    println!("Counting is off since `stats` was turned off in the config");
+  }
+}
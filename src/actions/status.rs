@@ -0,0 +1,156 @@
+use std::path::{Path, PathBuf};
+use serde::Serialize;
+use crate::actions::doctor::{findings, Finding, Mode, Severity};
+use crate::args::Cli;
+use crate::exit::OrExit;
+use crate::index::{newest_matching, CranSource, IndexCache, VersionSource};
+use crate::local_utils::curr_dir;
+use crate::lockfile::{ProjectLock, LOCK_FILE};
+use crate::output::{opt_field, print_json, print_porcelain};
+use crate::parsing::version_parser::{Range, Version};
+use crate::parsing::yaml_ser::{read_yaml, rversion_is_system};
+use crate::resolve::{active_version, find_project_file, project_tools, with_names, Active, ToolActive};
+use crate::theme;
+
+#[derive(Serialize)]
+struct Status {
+  /// none when R didn't resolve, error says why
+  r: Option<Active>,
+  error: Option<String>,
+  /// the project file found from here, none outside a project
+  project: Option<Project>,
+  /// the newest version the index cache has that the project's rversion (or the active range) allows, when it's
+  /// newer than the active one
+  upgrade: Option<Version>,
+  tools: Vec<ToolActive>,
+  /// what `rvm doctor` finds, without fixing or changing anything
+  findings: Vec<Finding>,
+}
+
+#[derive(Serialize)]
+struct Project {
+  path: PathBuf,
+  /// its rversion, none for the system R or when it can't be read
+  constraint: Option<Range>,
+  system: bool,
+  lock: Lock,
+  /// whether the active version is in constraint, none without either
+  satisfied: Option<bool>,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "state", rename_all = "kebab-case")]
+enum Lock {
+  /// no rvm.lock next to the project file
+  Missing,
+  /// it locks version for the project's rversion
+  Current { version: Version },
+  /// it's for range, not the project's rversion anymore
+  Stale { version: Version, range: Box<Range> },
+  Unreadable { error: String },
+}
+
+// `rvm status`, R and every tool of the project file with what each resolves to here, and what's off about them:
+// the project's constraint and lockfile, a newer version in reach and what doctor finds. only the index cache is
+// read, status doesn't go online so an editor's status bar can run it often
+pub fn main(options: &Cli) {
+  let dir = curr_dir();
+  let (r, error) = match active_version(None, &dir) {
+    Ok(active) => (Some(active), None),
+    Err(err) => (None, Some(err.to_string())),
+  };
+  let tools = project_tools(&dir).or_exit("Failed to resolve tools");
+  let project = find_project_file(&dir).map(|path| project(path, r.as_ref()));
+  let upgrade = r.as_ref().and_then(|active| upgrade(active, project.as_ref().and_then(|p| p.constraint.as_ref())));
+  let status = Status { r, error, project, upgrade, tools, findings: findings(Mode::ReadOnly) };
+  if options.json {
+    return print_json("status", &status);
+  }
+  let Status { r, error, project, upgrade, tools, findings } = status;
+  if options.porcelain {
+    let mut rows = vec![match &r {
+      Some(active) => vec!["R".to_owned(), active.range.to_string(), active.version.to_string()],
+      None => vec!["R".to_owned(), String::new(), String::new()],
+    }];
+    rows.extend(tools.iter().map(|t| vec![t.tool.clone(), t.range.to_string(), opt_field(&t.version)]));
+    return print_porcelain(&rows);
+  }
+  let width = tools.iter().map(|t| t.tool.len()).max().unwrap_or(0).max(1);
+  match (&r, &error) {
+    (Some(active), _) => println!("{:<width$}  {:<8} {} ({})", "R", active.version.to_string(), active.range, active.origin),
+    (None, Some(err)) => println!("{:<width$}  {:<8} {}", "R", "-", err),
+    (None, None) => unreachable!("resolving gives a version or an error"),
+  }
+  if let Some(project) = &project {
+    print_project(project, r.as_ref(), width);
+  }
+  if let Some(upgrade) = &upgrade {
+    println!("{:<width$}  {:<8} is out, `rvm upgrade` gets it", "", upgrade.to_string());
+  }
+  for tool in &tools {
+    match &tool.version {
+      Some(version) => println!("{:<width$}  {:<8} {} (set by {})", tool.tool, version.to_string(), tool.range, tool.project.display()),
+      None => println!("{:<width$}  {:<8} {} isn't installed, try `rvm plugin install {} '{}'`", tool.tool, "-", tool.range, tool.tool, tool.range),
+    }
+  }
+  for finding in &findings {
+    let label = match finding.severity {
+      Severity::Problem => theme::error(finding.severity.label()),
+      Severity::Warning => theme::warn(finding.severity.label()),
+    };
+    println!("[{}] {}", label, finding.message);
+  }
+}
+
+fn project(path: PathBuf, active: Option<&Active>) -> Project {
+  let (constraint, system) = match read_yaml(path.clone()) {
+    Ok(env) => (Some(env.rversion), false),
+    Err(_) => (None, rversion_is_system(&path)),
+  };
+  let lock = lock(&path, constraint.as_ref());
+  // a name it can't resolve isn't satisfied by anything
+  let satisfied = active.zip(constraint.as_ref())
+    .map(|(active, constraint)| with_names(constraint.clone()).is_ok_and(|range| range.contains(&active.version)));
+  Project { path, constraint, system, lock, satisfied }
+}
+
+fn lock(project: &Path, constraint: Option<&Range>) -> Lock {
+  match ProjectLock::load(&ProjectLock::path_for(project)) {
+    Ok(None) => Lock::Missing,
+    Ok(Some(lock)) if Some(&lock.range) == constraint => Lock::Current { version: lock.version },
+    Ok(Some(lock)) => Lock::Stale { version: lock.version, range: Box::new(lock.range) },
+    Err(err) => Lock::Unreadable { error: err.to_string() },
+  }
+}
+
+// the newest version in the index cache constraint (the active range without one) allows, if it's newer than active's
+fn upgrade(active: &Active, constraint: Option<&Range>) -> Option<Version> {
+  let cache = IndexCache::load_named(CranSource::default().name())?;
+  let constraint = constraint.map(|c| with_names(c.clone())).transpose().ok()?;
+  newest_matching(constraint.as_ref().unwrap_or(&active.range), &cache.entries)
+    .map(|meta| meta.version.clone())
+    .filter(|newest| newest > &active.version)
+}
+
+fn print_project(project: &Project, active: Option<&Active>, width: usize) {
+  let path = project.path.display();
+  if project.system {
+    return println!("{:<width$}  {} asks for the system R", "", path);
+  }
+  match (&project.constraint, project.satisfied, active) {
+    (Some(constraint), Some(true), Some(active)) => println!("{:<width$}  {} asks for {}, {} is in it", "", path, constraint, active.version),
+    (Some(constraint), Some(false), Some(active)) => {
+      println!("{:<width$}  {} asks for {}, {}", "", path, constraint, theme::warn(format!("{} isn't in it", active.version)))
+    },
+    (Some(constraint), _, _) => println!("{:<width$}  {} asks for {}", "", path, constraint),
+    (None, _, _) => println!("{:<width$}  {} {}", "", path, theme::warn("can't be read")),
+  }
+  match &project.lock {
+    Lock::Missing => println!("{:<width$}  no {}, `rvm update` writes one", "", LOCK_FILE),
+    Lock::Current { version } => println!("{:<width$}  {} locks {}", "", LOCK_FILE, version),
+    Lock::Stale { version, range } => {
+      println!("{:<width$}  {} {}, `rvm update` locks it again", "", LOCK_FILE, theme::warn(format!("locks {} for {}, the project asks for something else now", version, range)))
+    },
+    Lock::Unreadable { error } => println!("{:<width$}  {} {}", "", LOCK_FILE, theme::warn(format!("can't be read: {}", error))),
+  }
+}
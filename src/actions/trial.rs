@@ -0,0 +1,55 @@
+use crate::actions::exec::run_with;
+use crate::actions::{lock_installs, print_plan, report_leftovers, target_platform};
+use crate::alias::expand_alias;
+use crate::args::Cli;
+use crate::cancel::on_interrupt;
+use crate::exit::{fail, Exit, OrExit};
+use crate::config::Config;
+use crate::index::{newest_matching, remote_versions, CachePolicy, CranSource};
+use crate::install::{install_many, installed_versions, plan, uninstall, HttpDownloader};
+use crate::install::meta::InstallMeta;
+use crate::parsing::version_parser::Range;
+use crate::platform::installable;
+use crate::reporter::reporter_for;
+
+// `rvm try`, the version goes in the versions dir like any other: R (built from source especially)
+// hardcodes its prefix, so a temporary one elsewhere wouldn't run. it's marked as a trial until it's removed
+pub fn main(request: &str, keep: bool, command: &[String], options: &Cli) {
+  let range = Range::from_request(&expand_alias(request)).unwrap_or_else(|err| fail(Exit::Usage, format!("Failed to parse range {}: {:?}", request, err)));
+  if let Some(version) = range.max_satisfying(&installed_versions()) {
+    eprintln!("R {} is installed already, using it", version);
+    std::process::exit(run_with(version, command));
+  }
+  let backend = Config::get().backend;
+  let available = remote_versions(&CranSource::default(), CachePolicy::Default)
+    .or_exit("Failed to list remote versions");
+  let available = installable(available, backend, &target_platform(options));
+  let meta = newest_matching(&range, &available).unwrap_or_else(|| fail(Exit::NoMatch, format!("No version matches {}", range))).clone();
+  let version = meta.version.clone();
+  if options.dry_run {
+    let mut steps = plan(&meta, backend);
+    steps.push(format!("run {}", command.join(" ")));
+    if !keep {
+      steps.push(format!("uninstall R {}", version));
+    }
+    return print_plan(&version, &steps);
+  }
+  {
+    let _lock = lock_installs(options);
+    let reporter = reporter_for(options);
+    let result = install_many(std::slice::from_ref(&meta), 1, backend, &HttpDownloader, reporter.as_ref(), &on_interrupt()).remove(0);
+    result.or_exit(format!("Failed to install R {}", version));
+    InstallMeta::update(&version, |meta| meta.trial = !keep)
+      .or_exit(format!("Failed to save metadata of {}", version));
+  }
+  let code = run_with(&version, command);
+  if keep {
+    eprintln!("Kept R {}", version);
+  } else {
+    let _lock = lock_installs(options);
+    let leftovers = uninstall(&version).or_exit(format!("Failed to remove trial R {}", version));
+    eprintln!("Removed trial R {}", version);
+    report_leftovers(&version, leftovers);
+  }
+  std::process::exit(code);
+}
@@ -0,0 +1,53 @@
+use crate::actions::{confirm, lock_installs, report_leftovers, run_hooks};
+use crate::alias::Aliases;
+use crate::args::Cli;
+use crate::exit::{fail, Exit, OrExit};
+use crate::hooks::Event;
+use crate::install::{install_dir, installed_versions, uninstall};
+use crate::install::meta::InstallMeta;
+use crate::install::receipt;
+use crate::parsing::version_parser::Version;
+use crate::resolve::request_range;
+use crate::theme;
+
+pub fn main(request: String, options: &Cli) {
+  let _lock = lock_installs(options);
+  let range = request_range(&request).or_exit("Failed to parse version");
+  let installed = installed_versions();
+  let version = range.max_satisfying(&installed)
+    .unwrap_or_else(|| fail(Exit::NoMatch, format!("No installed version matches {}", request)))
+    .clone();
+  if options.dry_run {
+    println!("Would remove {} and {}", install_dir(&version).display(), InstallMeta::path(&version).display());
+    if let Some(listed) = receipt::load(&version) {
+      let (added, _) = receipt::diff(&listed, &install_dir(&version)).unwrap_or_default();
+      if !added.is_empty() {
+        println!("Would keep {} files rvm didn't install in {}", added.len(), receipt::leftovers_dir(&version).display());
+      }
+    }
+    let aliases = Aliases::load().unwrap_or_default();
+    let pinned: Vec<&String> = aliases.aliases.iter()
+      .filter(|(_, target)| Version::parse(target.as_str()).is_ok_and(|v| v == version))
+      .map(|(name, _)| name)
+      .collect();
+    for name in pinned {
+      println!("Would remove alias {}", name);
+    }
+    return;
+  }
+  if !confirm(options, &format!("Uninstall R {}?", version), false) {
+    fail(Exit::Aborted, "Aborted: User chose not to uninstall")
+  }
+  run_hooks(Event::PreUninstall, &version);
+  let leftovers = uninstall(&version).or_exit(format!("Failed to uninstall {}", version));
+  println!("R {} {}", theme::version(&version), theme::success("uninstalled"));
+  report_leftovers(&version, leftovers);
+  run_hooks(Event::PostUninstall, &version);
+  // keep aliases consistent, anything pinned to this exact version would be dangling
+  let mut aliases = Aliases::load().unwrap_or_else(|err| panic!("Failed to read {}: {}", Aliases::path().display(), err));
+  let removed = aliases.forget_version(&version);
+  if !removed.is_empty() {
+    aliases.save().unwrap_or_else(|err| panic!("Failed to save aliases: {}", err));
+    println!("Removed aliases pointing to it: {}", removed.join(", "));
+  }
+}
@@ -0,0 +1,67 @@
+use crate::actions::target_platform;
+use crate::args::Cli;
+use crate::exit::{fail, Exit};
+use crate::config::{Backend, Config};
+use crate::index::{newest_matching, remote_versions, CachePolicy, CranSource};
+use crate::install::installed_versions;
+use crate::install::meta::InstallMeta;
+use crate::local_utils::curr_dir;
+use crate::lockfile::{ProjectLock, FORMAT};
+use crate::parsing::version_parser::Range;
+use crate::parsing::yaml_ser::read_yaml;
+use crate::platform::installable;
+use crate::resolve::{find_project_file, with_names};
+
+// the newest version for the range that can be installed here, or the newest installed one if the index can't be read
+pub fn main(options: &Cli) {
+  let project = find_project_file(&curr_dir()).unwrap_or_else(|| panic!("No Renv.yaml here or in any parent dir, `rvm local <version>` makes one"));
+  let range = read_yaml(project.clone()).unwrap_or_else(|err| panic!("Failed to read {}: {}", project.display(), err)).rversion;
+  let path = ProjectLock::path_for(&project);
+  let old = ProjectLock::load(&path).unwrap_or_else(|err| panic!("Failed to read {}: {}", path.display(), err));
+  let installed = installed_versions();
+  let lock = lock_for(&range, options)
+    .unwrap_or_else(|| fail(Exit::NoMatch, format!("No version matches {} for {}", range, project.display())));
+  let version = lock.version.clone();
+  let was = match &old {
+    Some(old) if old == &lock => return println!("{} is up to date, R {} for {}", path.display(), version, range),
+    Some(old) => format!(" (was {})", old.version),
+    None => String::new(),
+  };
+  if options.dry_run {
+    return println!("Would lock R {} for {} in {}{}", version, range, path.display(), was);
+  }
+  lock.save(&path).unwrap_or_else(|err| panic!("Failed to write {}: {}", path.display(), err));
+  println!("{} R {} for {}{}", path.display(), version, range, was);
+  if !installed.contains(&version) {
+    println!("It isn't installed, `rvm install {}` installs it", version);
+  }
+}
+
+/// The lock for range: the newest version for it that can be installed here, or the newest installed one if the
+/// index can't be read, none when nothing matches
+pub fn lock_for(range: &Range, options: &Cli) -> Option<ProjectLock> {
+  let backend = Config::get().backend;
+  // the lock keeps range as written, names and all, so it stays current for the project file
+  let matching = with_names(range.clone()).map_err(|err| eprintln!("Failed to resolve {}: {}", range, err)).ok()?;
+  let available = remote_versions(&CranSource::default(), CachePolicy::Default)
+    .map(|entries| installable(entries, backend, &target_platform(options)))
+    .unwrap_or_else(|err| {
+      eprintln!("Failed to list remote versions ({}), locking to an installed one", err);
+      vec![]
+    });
+  let remote = newest_matching(&matching, &available);
+  let version = match remote {
+    Some(meta) => meta.version.clone(),
+    None => matching.max_satisfying(&installed_versions())?.clone(),
+  };
+  // an install knows exactly what it came from, the index only what it would download
+  let meta = InstallMeta::load(&version);
+  // without its sha256 (a build that reused its sources) the index's is what can be locked
+  let (url, sha256) = match (meta.url.filter(|_| meta.sha256.is_some()), remote) {
+    (Some(url), _) => (Some(url), meta.sha256),
+    (None, Some(remote)) if backend == Backend::Source => (remote.source_url.clone(), remote.source_sha256.clone()),
+    (None, Some(remote)) => (Some(remote.url.clone()), remote.sha256.clone()),
+    (None, None) => (None, None),
+  };
+  Some(ProjectLock { format: FORMAT, range: range.clone(), version, url, sha256 })
+}
@@ -0,0 +1,121 @@
+use crate::actions::{lock_installs, print_plan, report_leftovers, target_platform};
+use crate::alias::Aliases;
+use crate::args::Cli;
+use crate::cancel::on_interrupt;
+use crate::exit::{attempt, fail, Exit, OrExit};
+use crate::config::Config;
+use crate::index::{newest_matching, remote_versions, CachePolicy, CranSource};
+use crate::install::{install, installed_versions, plan, uninstall, versions_dir, HttpDownloader};
+use crate::install::scan::{scan, Granularity};
+use crate::local_utils::curr_dir;
+use crate::parsing::version_parser::{Range, Version};
+use crate::platform::installable;
+use crate::reporter::reporter_for;
+use crate::resolve::{active_range, Origin};
+
+pub fn main(request: Option<String>, remove_old: bool, options: &Cli) {
+  let _lock = lock_installs(options);
+  let mut aliases = Aliases::load().unwrap_or_else(|err| panic!("Failed to read {}: {}", Aliases::path().display(), err));
+  // which alias (if any) follows the upgrade, and the constraint to upgrade within
+  let (alias, target) = match &request {
+    Some(request) => match aliases.get(request) {
+      Some(target) => (Some(request.clone()), target.to_owned()),
+      None => (None, request.clone()),
+    },
+    None => match active_range(&curr_dir()).or_exit("Failed to resolve version") {
+      (_, Origin::Default) => (Some("default".to_owned()), aliases.get("default").unwrap().to_owned()),
+      (range, _) => (None, range.to_string()),
+    },
+  };
+  let range = upgrade_range(&target);
+  let installed = installed_versions();
+  // an exact target is what's being replaced, even if something newer in the series is installed
+  let old = match Version::parse(target.as_str()) {
+    Ok(exact) => installed.iter().find(|v| **v == exact).cloned(),
+    Err(_) => range.max_satisfying(&installed).cloned(),
+  };
+  let backend = Config::get().backend;
+  let platform = target_platform(options);
+  let available = remote_versions(&CranSource::default(), CachePolicy::Default)
+    .or_exit("Failed to list remote versions");
+  let available = installable(available, backend, &platform);
+  let newest = newest_matching(&range, &available).unwrap_or_else(|| fail(Exit::NoMatch, format!("No remote version matches {} for {}", range, platform)));
+  if old.as_ref().is_some_and(|old| old >= &newest.version) {
+    println!("R {} is already the newest matching {}", old.unwrap(), range);
+    return;
+  }
+  if options.dry_run {
+    print_plan(&newest.version, &plan(newest, backend));
+    if let (Some(alias), true) = (&alias, Version::parse(target.as_str()).is_ok()) {
+      println!("Would point {} to {}", alias, newest.version);
+    }
+    if let (true, Some(old)) = (remove_old, &old) {
+      println!("Would uninstall R {} unless an alias still uses it", old);
+    }
+    return;
+  }
+  let reporter = reporter_for(options);
+  install(newest, backend, &HttpDownloader, reporter.as_ref(), &on_interrupt()).or_exit(format!("Failed to install {}", newest.version));
+  match &old {
+    Some(old) => println!("R {} → {}", old, newest.version),
+    None => println!("R {} installed", newest.version),
+  }
+  // an alias pinned to the exact old version moves, one with a range already follows
+  if let Some(alias) = alias {
+    if Version::parse(target.as_str()).is_ok() {
+      aliases.set(&alias, &newest.version.to_string()).unwrap();
+      aliases.save().unwrap_or_else(|err| panic!("Failed to save aliases: {}", err));
+      println!("{} -> {}", alias, newest.version);
+    }
+  }
+  if let (true, Some(old)) = (remove_old, old) {
+    let still_used = aliases.aliases.values()
+      .filter_map(|t| Range::from_request(t).ok())
+      .any(|r| r.max_satisfying(&installed).is_some_and(|v| v == &old) && !r.contains(&newest.version));
+    if still_used {
+      println!("Keeping R {}, an alias still uses it", old);
+    } else {
+      match uninstall(&old) {
+        Ok(leftovers) => {
+          println!("R {} uninstalled", old);
+          report_leftovers(&old, leftovers);
+        },
+        Err(err) => eprintln!("Failed to remove R {}: {}", old, err),
+      }
+    }
+  }
+}
+
+/// `rvm upgrade --each-minor`, every minor series that's installed to its newest
+pub fn each_minor(remove_old: bool, options: &Cli) {
+  let pool = scan(&versions_dir()).pool;
+  if pool.is_empty() {
+    println!("No R version installed, try `rvm install latest`");
+    return;
+  }
+  // a series that fails doesn't keep the others from upgrading
+  let mut failed: Vec<(String, Exit)> = vec![];
+  for version in pool.latest_per(Granularity::Minor) {
+    // an exact version upgrades within its minor series
+    if let Err(exit) = attempt(|| main(Some(version.to_string()), remove_old, options)) {
+      let (major, minor, _) = version.parts();
+      failed.push((format!("{}.{}", major, minor), exit));
+    }
+  }
+  let Some((_, first)) = failed.first() else { return };
+  // the failures' exit when they agree on one
+  let exit = if failed.iter().all(|(_, exit)| exit == first) { *first } else { Exit::Failure };
+  let series: Vec<&str> = failed.iter().map(|(series, _)| series.as_str()).collect();
+  fail(exit, format!("Failed to upgrade the {} series", series.join(", ")));
+}
+
+// an exact version upgrades within its minor series, anything else within itself
+fn upgrade_range(target: &str) -> Range {
+  match Version::parse(target) {
+    Ok(version) => {
+      let (major, minor, _) = version.parts();
+      Range::parse(&format!("~{}.{}", major, minor)).unwrap()
+    },
+    Err(_) => Range::from_request(target).unwrap_or_else(|err| fail(Exit::Usage, format!("Failed to parse range: {:?}", err))),
+  }
+}
@@ -0,0 +1,148 @@
+use serde::Serialize;
+use crate::actions::{lock_installs, target_platform};
+use crate::args::Cli;
+use crate::cancel::on_interrupt;
+use crate::exit::{fail, Exit, OrExit};
+use crate::config::Config;
+use crate::index::{remote_versions, CachePolicy, CranSource};
+use crate::install::{install, install_dir, installed_versions, versions_dir, HttpDownloader, InstallError};
+use crate::install::git::install_from_git;
+use crate::install::local::install_local;
+use crate::install::meta::{InstallMeta, InstallOrigin};
+use crate::install::protect;
+use crate::install::verify::{check_many, Report};
+use crate::local_utils::curr_dir;
+use crate::output::{print_json, print_porcelain};
+use crate::parsing::version_parser::Version;
+use crate::resolve::{active_version, request_range};
+use crate::platform::installable;
+use crate::reporter::reporter_for;
+use crate::theme;
+
+#[derive(Serialize)]
+struct Checked {
+  version: Version,
+  /// ok, damaged, repaired or unrecorded (installed before rvm kept checksums)
+  status: &'static str,
+  #[serde(flatten)]
+  report: Option<Report>,
+}
+
+pub fn main(request: Option<&str>, all: bool, repair: bool, jobs: usize, options: &Cli) {
+  let installed = installed_versions();
+  let versions = match request {
+    _ if all => installed,
+    Some(request) => {
+      let range = request_range(request).or_exit("Failed to parse version");
+      vec![range.max_satisfying(&installed).unwrap_or_else(|| fail(Exit::NoMatch, format!("No installed version matches {}", request))).clone()]
+    },
+    None => vec![active_version(None, &curr_dir()).or_exit("Failed to resolve version").version],
+  };
+  // repairing swaps trees, nothing else should touch them meanwhile
+  let _lock = repair.then(|| lock_installs(options));
+  let metas = InstallMeta::all();
+  // hashing is most of the time, it's all done first with the bars, repairs one at a time after
+  let reports = check_many(&versions_dir(), &versions, jobs, reporter_for(options).as_ref(), &on_interrupt());
+  let mut results = vec![];
+  for (version, report) in versions.into_iter().zip(reports) {
+    let report = report.or_exit(format!("Failed to check R {}", version));
+    let status = match &report {
+      None => "unrecorded",
+      Some(report) if !report.is_damaged() => "ok",
+      Some(_) if !repair => "damaged",
+      Some(_) if options.dry_run => {
+        println!("Would reinstall R {} into {}", version, install_dir(&version).display());
+        "damaged"
+      },
+      Some(_) => match reinstall(&version, &metas.get(&version).cloned().unwrap_or_default(), options) {
+        Ok(()) => "repaired",
+        Err(err) => {
+          eprintln!("{} {}", theme::failure(format!("R {} can't be repaired:", version)), err);
+          "damaged"
+        },
+      },
+    };
+    results.push(Checked { version, status, report });
+  }
+  let damaged = results.iter().any(|r| r.status == "damaged");
+  if options.json {
+    print_json("verify", &results);
+  } else if options.porcelain {
+    let rows: Vec<Vec<String>> = results.iter()
+      .map(|r| {
+        let counts = r.report.as_ref().map(|r| [r.missing.len(), r.modified.len(), r.added.len()]).unwrap_or_default();
+        vec![r.version.to_string(), r.status.to_owned(), counts[0].to_string(), counts[1].to_string(), counts[2].to_string()]
+      })
+      .collect();
+    print_porcelain(&rows);
+  } else {
+    for result in &results {
+      print_human(result, options);
+    }
+  }
+  if damaged {
+    std::process::exit(Exit::Verification.code());
+  }
+}
+
+fn print_human(result: &Checked, options: &Cli) {
+  let version = theme::version(&result.version);
+  let Some(report) = &result.report else {
+    return println!("R {} has no recorded checksums, it was installed before rvm kept them", version);
+  };
+  match result.status {
+    "ok" => println!("R {} {} ({} files)", version, theme::success("ok"), report.checked),
+    "repaired" => println!("R {} {}, {} missing and {} modified file(s) reinstalled", version, theme::success("repaired"), report.missing.len(), report.modified.len()),
+    _ => println!("R {} {}, {} missing and {} modified file(s), `rvm verify --repair {}` reinstalls it", version, theme::error("damaged"), report.missing.len(), report.modified.len(), result.version),
+  }
+  if options.verbose > 0 {
+    for path in &report.missing {
+      println!("  missing   {}", path);
+    }
+    for path in &report.modified {
+      println!("  modified  {}", path);
+    }
+    for path in &report.added {
+      println!("  added     {}", path);
+    }
+  }
+}
+
+// installs version again the way it was installed the first time, the damaged tree is kept aside until that worked
+fn reinstall(version: &Version, meta: &InstallMeta, options: &Cli) -> Result<(), InstallError> {
+  let dest = install_dir(version);
+  if dest.is_symlink() {
+    return Err(InstallError::Verify(format!("{} is a link to {}, repair it where it points", dest.display(), std::fs::read_link(&dest)?.display())));
+  }
+  let aside = versions_dir().join(".staging").join(format!("{}-damaged-{}", version, std::process::id()));
+  std::fs::create_dir_all(versions_dir().join(".staging"))?;
+  // the reinstall protects the new tree, the damaged one has to be writable to go
+  let protection = protect::unlock(version)?;
+  std::fs::rename(&dest, &aside)?;
+  let reporter = reporter_for(options);
+  let result = match meta.origin() {
+    Some(InstallOrigin::Git { url, reference }) => install_from_git(&url, Some(&reference), version, reporter.as_ref(), &on_interrupt()),
+    Some(InstallOrigin::Local { path }) => install_local(&path, version, reporter.as_ref()),
+    registry => {
+      let backend = Config::get().backend;
+      let available = remote_versions(&CranSource::default(), CachePolicy::Default)
+        .map_err(|err| InstallError::Verify(format!("can't list remote versions: {}", err)))?;
+      match installable(available, backend, &target_platform(options)).into_iter().find(|m| &m.version == version) {
+        Some(artifact) => install(&artifact, backend, &HttpDownloader, reporter.as_ref(), &on_interrupt()),
+        None => match registry {
+          Some(from) => Err(InstallError::Verify(format!("the index has no R {} for this platform anymore, it came from {}", version, from))),
+          None => Err(InstallError::Verify(format!("the index has no R {} for this platform", version))),
+        },
+      }
+    },
+  };
+  match result {
+    Ok(_) => std::fs::remove_dir_all(&aside)?,
+    Err(err) => {
+      std::fs::rename(&aside, &dest)?;
+      protect::lock(version, &dest, protection)?;
+      return Err(err);
+    },
+  }
+  Ok(())
+}
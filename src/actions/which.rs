@@ -0,0 +1,76 @@
+use crate::activate::{find_binary, find_system_binary};
+use crate::args::Cli;
+use crate::exit::OrExit;
+use crate::local_utils::curr_dir;
+use crate::output::{print_json, print_porcelain};
+use crate::resolve::{active_version, Active, ResolveError};
+use serde::Serialize;
+use std::path::PathBuf;
+
+#[derive(Serialize)]
+struct Which<'a> {
+  binary: &'a str,
+  /// none when the version doesn't have it
+  path: Option<PathBuf>,
+  #[serde(flatten)]
+  active: &'a Active,
+}
+
+#[derive(Serialize)]
+struct SystemWhich<'a> {
+  binary: &'a str,
+  path: Option<PathBuf>,
+  system: bool,
+}
+
+pub fn main(binary: String, options: &Cli) {
+  let active = match active_version(None, &curr_dir()) {
+    Err(ResolveError::System) => return system(binary, options),
+    active => active.or_exit("Failed to resolve version"),
+  };
+  let path = find_binary(&active.version, &binary);
+  if options.json {
+    print_json("which", &Which { binary: &binary, path: path.clone(), active: &active });
+    if path.is_none() {
+      std::process::exit(1);
+    }
+    return;
+  }
+  if options.porcelain {
+    print_porcelain(&[vec![path.as_ref().map(|p| p.display().to_string()).unwrap_or_default(), active.version.to_string()]]);
+    if path.is_none() {
+      std::process::exit(1);
+    }
+    return;
+  }
+  match path {
+    Some(path) => {
+      println!("{}", path.display());
+      if options.verbose > 0 {
+        println!("R {} (matches {}, {})", active.version, active.range, active.origin);
+      }
+    },
+    None => {
+      eprintln!("{} not found in R {}", binary, active.version);
+      std::process::exit(1);
+    }
+  }
+}
+
+// the binary of the R on PATH outside rvm, "system" where the version would be
+fn system(binary: String, options: &Cli) {
+  let path = find_system_binary(&binary);
+  if options.json {
+    print_json("which", &SystemWhich { binary: &binary, path: path.clone(), system: true });
+  } else if options.porcelain {
+    print_porcelain(&[vec![path.as_ref().map(|p| p.display().to_string()).unwrap_or_default(), "system".to_owned()]]);
+  } else {
+    match &path {
+      Some(path) => println!("{}", path.display()),
+      None => eprintln!("{} not found on PATH outside rvm", binary),
+    }
+  }
+  if path.is_none() {
+    std::process::exit(1);
+  }
+}
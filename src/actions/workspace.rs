@@ -0,0 +1,158 @@
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use serde::Serialize;
+use crate::actions::install;
+use crate::args::{Cli, WorkspaceAction};
+use crate::exit::{fail, Exit};
+use crate::index::{newest_matching, remote_versions, ArtifactMeta, CachePolicy, CranSource};
+use crate::install::installed_versions;
+use crate::output::{bool_field, opt_field, print_json, print_json_line, print_porcelain};
+use crate::parsing::version_parser::{common_bounds, Comparator, Range, Version};
+use crate::parsing::yaml_ser::read_yaml;
+use crate::resolve::{find_project_files, project_lock, with_names};
+use crate::theme;
+
+// every project file under a root resolved on its own, for monorepos where projects pin different R
+
+#[derive(Serialize)]
+pub(crate) struct Project {
+  path: PathBuf,
+  range: Range,
+  /// the version the project's lockfile has, when it has a fresh one
+  #[serde(skip_serializing_if = "Option::is_none")]
+  locked: Option<Version>,
+  /// what it runs with (or would, once installed), none when nothing installed or remote fits
+  version: Option<Version>,
+  installed: bool,
+}
+
+#[derive(Serialize)]
+pub(crate) struct Workspace {
+  projects: Vec<Project>,
+  /// every version the projects need, oldest first
+  versions: Vec<Version>,
+  /// the ones of those that aren't installed
+  missing: Vec<Version>,
+  common: Common,
+}
+
+/// what every project's range allows at once, the floor is the oldest R they could all share
+#[derive(Serialize)]
+struct Common {
+  minimal: Option<Version>,
+  ceiling: Option<Version>,
+  /// the projects whose ranges set the floor and the ceiling, with the bound
+  floor_by: Option<(PathBuf, Comparator)>,
+  ceiling_by: Option<(PathBuf, Comparator)>,
+}
+
+pub fn main(action: &WorkspaceAction, options: &Cli) {
+  match action {
+    WorkspaceAction::Status {root} => status(root, options),
+    WorkspaceAction::Install {root, jobs} => {
+      let workspace = resolve(root, &mut |_| {});
+      if workspace.missing.is_empty() {
+        return println!("Every version the {} projects under {} need is installed", workspace.projects.len(), root.display());
+      }
+      let requests = workspace.missing.iter().map(|v| format!("={}", v)).collect();
+      install::main(requests, *jobs, None, None, false, options);
+    },
+  }
+}
+
+fn status(root: &Path, options: &Cli) {
+  if options.json_lines {
+    resolve(root, &mut |project| print_json_line("workspace", project));
+    return;
+  }
+  let workspace = resolve(root, &mut |_| {});
+  if options.json {
+    return print_json("workspace", &workspace);
+  }
+  if options.porcelain {
+    let rows: Vec<Vec<String>> = workspace.projects.iter()
+      .map(|p| vec![p.path.display().to_string(), p.range.to_string(), opt_field(&p.version), bool_field(p.installed), opt_field(&p.locked)])
+      .collect();
+    return print_porcelain(&rows);
+  }
+  let width = workspace.projects.iter().map(|p| p.path.display().to_string().len()).max().unwrap_or(0);
+  for project in &workspace.projects {
+    let path = project.path.display().to_string();
+    let locked = if project.locked.is_some() { " (locked)" } else { "" };
+    match &project.version {
+      Some(version) if project.installed => println!("{:<width$}  {:<10} {}{}", path, project.range.to_string(), theme::version(version), locked),
+      Some(version) => println!("{:<width$}  {:<10} {}{} {}", path, project.range.to_string(), version, locked, theme::warn("not installed")),
+      None => println!("{:<width$}  {:<10} {}", path, project.range.to_string(), theme::failure("no version matches")),
+    }
+  }
+  let versions: Vec<String> = workspace.versions.iter().map(Version::to_string).collect();
+  println!("{} projects need R {}", workspace.projects.len(), versions.join(", "));
+  if !workspace.missing.is_empty() {
+    println!("{} of them missing, `rvm workspace install` installs them", workspace.missing.len());
+  }
+  let common = &workspace.common;
+  match &common.minimal {
+    Some(minimal) => println!("The oldest R all of them allow is {}", theme::version(minimal)),
+    None => println!("{}", theme::warn("Their ranges have no version in common")),
+  }
+  for (what, by) in [("floor", &common.floor_by), ("ceiling", &common.ceiling_by)] {
+    if let Some((path, comparator)) = by {
+      println!("  {} {} set by {}", what, comparator, path.display());
+    }
+  }
+}
+
+// locked projects need their lock's version, the rest the newest installed match or else the newest remote one
+// the remote index is only asked when something isn't installed, found is told about each project as it's resolved
+pub(crate) fn resolve(root: &Path, found: &mut dyn FnMut(&Project)) -> Workspace {
+  let files = find_project_files(root);
+  if files.is_empty() {
+    fail(Exit::NoMatch, format!("No project files under {}", root.display()));
+  }
+  let installed = installed_versions();
+  let mut remote: Option<Vec<ArtifactMeta>> = None;
+  let mut projects = vec![];
+  for path in files {
+    let env = match read_yaml(path.clone()) {
+      Ok(env) => env,
+      Err(err) => {
+        eprintln!("Failed to read {}, skipping it: {}", path.display(), err);
+        continue;
+      },
+    };
+    let locked = project_lock(&path).ok().map(|lock| lock.version);
+    let range = match locked.clone().map(Range::exactly).map_or_else(|| with_names(env.rversion.clone()), Ok) {
+      Ok(range) => range,
+      Err(err) => {
+        eprintln!("Failed to resolve the rversion of {}, skipping it: {}", path.display(), err);
+        continue;
+      },
+    };
+    let version = match range.max_satisfying(&installed) {
+      Some(version) => Some(version.clone()),
+      None => {
+        let remote = remote.get_or_insert_with(|| remote_versions(&CranSource::default(), CachePolicy::Default).unwrap_or_else(|err| {
+          eprintln!("Failed to list remote versions: {}", err);
+          vec![]
+        }));
+        newest_matching(&range, remote).map(|m| m.version.clone())
+      },
+    };
+    let installed = version.as_ref().is_some_and(|v| installed.contains(v));
+    let project = Project { path, range: env.rversion, locked, version, installed };
+    found(&project);
+    projects.push(project);
+  }
+  let versions: BTreeSet<&Version> = projects.iter().filter_map(|p| p.version.as_ref()).collect();
+  let missing = projects.iter().filter(|p| !p.installed).filter_map(|p| p.version.clone()).collect::<BTreeSet<_>>();
+  let ranges: Vec<Range> = projects.iter().map(|p| p.range.clone()).collect();
+  let bounds = common_bounds(&ranges);
+  let by = |found: Option<(usize, Comparator)>| found.map(|(i, c)| (projects[i].path.clone(), c));
+  let common = Common { minimal: bounds.minimal, ceiling: bounds.ceiling, floor_by: by(bounds.floor_by), ceiling_by: by(bounds.ceiling_by) };
+  Workspace {
+    common,
+    versions: versions.into_iter().cloned().collect(),
+    missing: missing.into_iter().collect(),
+    projects,
+  }
+}
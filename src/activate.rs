@@ -0,0 +1,198 @@
+use std::collections::BTreeSet;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use crate::config::Config;
+use crate::install::meta::InstallMeta;
+use crate::install::{install_dir, versions_dir};
+use crate::layout::Layout;
+use crate::local_utils::is_system;
+use crate::parsing::version_parser::Version;
+use crate::parsing::yaml_ser::read_yaml;
+use crate::resolve::{with_names, Active};
+use crate::plugin::{shims_dir, write_shim};
+
+// what a version needs in the environment to be the one that runs
+
+/// Dirs with executables, most specific first (bin\x64 is where 64 bit windows builds keep theirs)
+pub fn bin_dirs(install: &Path) -> Vec<PathBuf> {
+  vec![install.join("bin").join("x64"), install.join("bin")]
+    .into_iter()
+    .filter(|d| d.is_dir())
+    .collect()
+}
+
+/// Absolute path of binary (R, Rscript...) for version, with or without .exe
+pub fn find_binary(version: &Version, binary: &str) -> Option<PathBuf> {
+  let names = [binary.to_owned(), format!("{}.exe", binary)];
+  bin_dirs(&install_dir(version))
+    .into_iter()
+    .flat_map(|dir| names.iter().map(move |name| dir.join(name)).collect::<Vec<_>>())
+    .find(|p| p.is_file())
+}
+
+/// PATH with version's bin dirs in front, and those of any other managed version taken out
+pub fn path_for(version: &Version) -> OsString {
+  let mut paths = bin_dirs(&install_dir(version));
+  if let Some(current) = std::env::var_os("PATH") {
+    let versions = versions_dir();
+    paths.extend(std::env::split_paths(&current).filter(|p| !p.starts_with(&versions)));
+  }
+  std::env::join_paths(paths).unwrap_or_else(|err| panic!("Failed to build PATH: {}", err))
+}
+
+/// The variables env_for sets that the system R doesn't get, unset when it's the one selected
+pub const VERSION_VARS: [&str; 3] = ["R_HOME", "R_LIBS_USER", "RVM_VERSION"];
+
+/// PATH without any managed version's bin dirs or the command shims, what the system R is found on
+pub fn system_path() -> OsString {
+  let (versions, shims) = (versions_dir(), command_shims_dir());
+  let current = std::env::var_os("PATH").unwrap_or_default();
+  let paths = std::env::split_paths(&current).filter(|p| !p.starts_with(&versions) && p != &shims);
+  std::env::join_paths(paths).unwrap_or_else(|err| panic!("Failed to build PATH: {}", err))
+}
+
+/// Absolute path of binary (R, Rscript...) of the system R, with or without .exe
+pub fn find_system_binary(binary: &str) -> Option<PathBuf> {
+  let names = [binary.to_owned(), format!("{}.exe", binary)];
+  std::env::split_paths(&system_path())
+    .flat_map(|dir| names.iter().map(move |name| dir.join(name)).collect::<Vec<_>>())
+    .find(|p| p.is_file())
+}
+
+/// Where the shims of mapped commands (`commands` in Renv.yaml or the config) are, the shell hooks put it
+/// ahead of the active version so each runs `rvm exec -- <command>` and gets its own version
+pub fn command_shims_dir() -> PathBuf {
+  shims_dir().join("commands")
+}
+
+/// Makes the command shims exactly commands, leaving the ones that are already there alone
+pub fn write_command_shims(commands: &[String]) -> std::io::Result<()> {
+  let dir = command_shims_dir();
+  std::fs::create_dir_all(&dir)?;
+  for entry in dir.read_dir()?.filter_map(|e| e.ok()) {
+    let name = entry.path().file_stem().unwrap_or_default().to_string_lossy().to_string();
+    if !commands.contains(&name) {
+      std::fs::remove_file(entry.path())?;
+    }
+  }
+  for command in commands {
+    let name = if cfg!(windows) { format!("{}.cmd", command) } else { command.clone() };
+    if !dir.join(name).is_file() {
+      write_shim(&dir, command, &format!("rvm exec -- {}", command))?;
+    }
+  }
+  Ok(())
+}
+
+/// PATH with the command shims in front when there are any, and not in it otherwise
+pub fn with_command_shims(path: &OsString, any: bool) -> OsString {
+  let dir = command_shims_dir();
+  let rest = std::env::split_paths(path).filter(|p| p != &dir);
+  let paths: Vec<PathBuf> = if any { std::iter::once(dir.clone()).chain(rest).collect() } else { rest.collect() };
+  std::env::join_paths(paths).unwrap_or_else(|err| panic!("Failed to build PATH: {}", err))
+}
+
+/// Where packages installed with version go, each version gets its own so they never mix
+pub fn libs_dir(version: &Version) -> PathBuf {
+  libs_root().join(version.to_string())
+}
+
+// always in the user's home, users of a system install can't write to it
+// the system versions' ones are kept apart, a user version can have the same number
+pub fn libs_root() -> PathBuf {
+  let root = Layout::user().data.join("libs");
+  if is_system() { root.join("system") } else { root }
+}
+
+/// Every variable to set for version to be the active one
+pub fn env_for(version: &Version) -> Vec<(String, OsString)> {
+  // bin/R has its R_HOME baked in, an install that moved with RVM_HOME needs rebasing, which takes the installs' lock
+  if let Some(from) = InstallMeta::load(version).prefix.filter(|p| p != &install_dir(version)) {
+    tracing::warn!(%version, from = %from.display(), "the install moved and still points where it was, run `rvm doctor --fix`");
+  }
+  let libs = libs_dir(version);
+  // R silently drops an R_LIBS_USER that doesn't exist and would install into the shared library
+  if let Err(err) = std::fs::create_dir_all(&libs) {
+    tracing::warn!(error = %err, dir = %libs.display(), "can't create library dir");
+  }
+  let mut ret = vec![
+    ("PATH".to_owned(), path_for(version)),
+    ("R_HOME".to_owned(), install_dir(version).into_os_string()),
+    ("R_LIBS_USER".to_owned(), libs.into_os_string()),
+    ("RVM_VERSION".to_owned(), OsString::from(version.to_string())),
+  ];
+  for (name, value) in extra_env(version) {
+    set(&mut ret, name, OsString::from(value));
+  }
+  ret
+}
+
+// replaces name's value in env, or adds it
+fn set(env: &mut Vec<(String, OsString)>, name: String, value: OsString) {
+  match env.iter_mut().find(|(existing, _)| existing == &name) {
+    Some((_, existing)) => *existing = value,
+    None => env.push((name, value)),
+  }
+}
+
+/// The variables the index (as recorded at install) and then the config's env add for version, expanded
+pub fn extra_env(version: &Version) -> Vec<(String, String)> {
+  let config = Config::get().env.into_iter().filter(|e| with_names(e.range.clone()).is_ok_and(|r| r.contains(version))).flat_map(|e| e.vars);
+  InstallMeta::load(version).env.into_iter()
+    .chain(config)
+    .map(|(name, value)| (name, expand_env(&value, version)))
+    .collect()
+}
+
+/// set by the hooks to the variables they set beyond VERSION_VARS, so the next one unsets those the new dir doesn't have
+pub const EXTRA_VARS: &str = "RVM_EXTRA_VARS";
+
+/// Keeps when version was last activated (run, or switched to by a hook, `rvm env` or direnv) for
+/// `rvm prune --unused-for` and `rvm list --last-used`, a store that can't be written (someone else's system install)
+/// doesn't stop it
+pub fn record_use(version: &Version) {
+  if let Err(err) = InstallMeta::used(version) {
+    tracing::debug!(%version, error = %err, "can't record the use");
+  }
+}
+
+/// env_for, and when a project file picked the version its `env` over that, {project_dir} being where it is
+/// what activates a version in a shell, which counts as using it
+pub fn env_for_active(active: &Active) -> Vec<(String, OsString)> {
+  record_use(&active.version);
+  let mut ret = env_for(&active.version);
+  let Some(project) = active.origin.project_file() else { return ret };
+  let env = match read_yaml(project.clone()) {
+    Ok(env) => env.env,
+    Err(err) => {
+      tracing::warn!(error = %err, project = %project.display(), "can't read the project's env");
+      return ret;
+    },
+  };
+  let dir = project.parent().map(Path::to_path_buf).unwrap_or_default();
+  for (name, value) in env {
+    let value = expand_env(&value, &active.version).replace("{project_dir}", &dir.display().to_string());
+    set(&mut ret, name, OsString::from(value));
+  }
+  ret
+}
+
+/// The names of the variables env_for_active sets beyond VERSION_VARS and PATH, extra_env's and the project's,
+/// what EXTRA_VARS has
+pub fn extra_vars(active: &Active) -> Vec<String> {
+  let mut names: Vec<String> = extra_env(&active.version).into_iter().map(|(name, _)| name).collect();
+  if let Some(project) = active.origin.project_file() {
+    names.extend(read_yaml(project).map(|env| env.env.into_keys().collect::<Vec<_>>()).unwrap_or_default());
+  }
+  let mut seen = BTreeSet::new();
+  names.retain(|name| seen.insert(name.clone()));
+  names
+}
+
+/// value with {install_dir}, {libs_dir} and {version} replaced by version's
+pub fn expand_env(value: &str, version: &Version) -> String {
+  value
+    .replace("{install_dir}", &install_dir(version).display().to_string())
+    .replace("{libs_dir}", &libs_dir(version).display().to_string())
+    .replace("{version}", &version.to_string())
+}
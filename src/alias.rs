@@ -0,0 +1,127 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use crate::layout::config_dir;
+use crate::parsing::request::VersionRequest;
+use crate::parsing::version_parser::{ParseError, Range, Version};
+use crate::parsing::warning::{report, Warning};
+use crate::utils::today;
+
+// names like default, lts or project-x pointing to a version or a range
+// stored in <config dir>\aliases.toml as `name = "target"`, with the days some are to be looked at again under [expires]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Aliases {
+  #[serde(flatten)]
+  pub aliases: BTreeMap<String, String>,
+  /// YYYY-MM-DD by alias, expanding one warns from that day on
+  #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+  pub expires: BTreeMap<String, String>,
+}
+
+#[derive(Error, Debug)]
+pub enum AliasError {
+  #[error("error in reading/writing aliases file")]
+  Io(#[from] std::io::Error),
+  #[error("error in parsing aliases file")]
+  De(#[from] toml::de::Error),
+  #[error("error in writing aliases file")]
+  Ser(#[from] toml::ser::Error),
+  #[error("{0} looks like a version, it can't be an alias")]
+  InvalidName(String),
+  #[error("error in parsing alias target")]
+  InvalidTarget(#[from] ParseError),
+}
+
+impl Aliases {
+  pub fn path() -> PathBuf {
+    config_dir().join("aliases.toml")
+  }
+
+  pub fn load() -> Result<Self, AliasError> {
+    let path = Self::path();
+    if !path.exists() {
+      return Ok(Self::default());
+    }
+    Ok(toml::from_str(&std::fs::read_to_string(path)?)?)
+  }
+
+  pub fn save(&self) -> Result<(), AliasError> {
+    std::fs::create_dir_all(config_dir())?;
+    std::fs::write(Self::path(), toml::to_string(self)?)?;
+    Ok(())
+  }
+
+  /// Adds or replaces an alias, returns the old target
+  /// a new target is a new decision, so an expiry the alias had is dropped
+  pub fn set(&mut self, name: &str, target: &str) -> Result<Option<String>, AliasError> {
+    let name = name.trim();
+    if name.is_empty() || name == "expires" || Range::from_request(name).is_ok() {
+      return Err(AliasError::InvalidName(name.to_owned()));
+    }
+    VersionRequest::parse(target)?; // only valid targets get stored, keywords too
+    self.expires.remove(name);
+    Ok(self.aliases.insert(name.to_owned(), target.trim().to_owned()))
+  }
+
+  /// Sets the day (YYYY-MM-DD) name is to be looked at again, or takes it away
+  pub fn set_expiry(&mut self, name: &str, day: Option<&str>) {
+    match day {
+      Some(day) => self.expires.insert(name.trim().to_owned(), day.to_owned()),
+      None => self.expires.remove(name.trim()),
+    };
+  }
+
+  /// The day name expired, none when it hasn't by today (YYYY-MM-DD) or has no expiry
+  pub fn expired(&self, name: &str, today: &str) -> Option<&str> {
+    self.expires.get(name.trim()).map(String::as_str).filter(|day| *day <= today)
+  }
+
+  /// Reports name's expiry when it's past, see expired
+  pub fn report_expired(&self, name: &str) {
+    if let (Some(day), Some(target)) = (self.expired(name, &today()), self.get(name)) {
+      report(Warning::Expired { what: format!("alias {} -> {}", name.trim(), target), day: day.to_owned() });
+    }
+  }
+
+  pub fn remove(&mut self, name: &str) -> Option<String> {
+    self.expires.remove(name);
+    self.aliases.remove(name)
+  }
+
+  pub fn get(&self, name: &str) -> Option<&str> {
+    self.aliases.get(name).map(String::as_str)
+  }
+
+  /// The alias target if request is an alias, the request itself if not
+  pub fn expand<'a>(&'a self, request: &'a str) -> &'a str {
+    self.get(request.trim()).unwrap_or(request)
+  }
+
+  /// Removes the aliases pinned to exactly this version, called when it's uninstalled
+  /// Aliases to ranges stay, they can still resolve to something else
+  pub fn forget_version(&mut self, version: &Version) -> Vec<String> {
+    let removed: Vec<String> = self.aliases.iter()
+      .filter(|(_, target)| Version::parse(target.as_str()).is_ok_and(|v| &v == version))
+      .map(|(name, _)| name.clone())
+      .collect();
+    for name in &removed {
+      self.remove(name);
+    }
+    removed
+  }
+}
+
+/// Expands request if it names an alias, a broken aliases file is reported and ignored
+pub fn expand_alias(request: &str) -> String {
+  match Aliases::load() {
+    Ok(aliases) => {
+      aliases.report_expired(request);
+      aliases.expand(request).to_owned()
+    },
+    Err(err) => {
+      eprintln!("Failed to read {}, ignoring aliases: {}", Aliases::path().display(), err);
+      request.to_owned()
+    }
+  }
+}
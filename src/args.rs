@@ -1,26 +1,123 @@
 use std::env;
+use std::ffi::OsString;
 use std::path::PathBuf;
-use clap:: {Parser, Subcommand};
+use clap:: {CommandFactory, FromArgMatches, Parser, Subcommand};
+use clap::builder::FalseyValueParser;
 use crate::local_utils::curr_dir;
+use std::time::Duration;
+use crate::utils::{parse_day, parse_duration};
+use crate::config::Backend;
+use crate::platform::Platform;
+use crate::import::Manager;
+use crate::index::OFFLINE_VAR;
+use crate::local_utils::SYSTEM_VAR;
+use crate::lockfile::LOCKED_VAR;
+use crate::install::digest::INSECURE_DIGEST_VAR;
+use crate::trust::TRUST_NEW_KEY_VAR;
+use crate::parsing::warning::DENY_WARNINGS_VAR;
+use crate::shell::{Hook, Shell};
+use crate::logging::LogFormat;
+use crate::theme::ColorChoice;
+use crate::install::scan::Granularity;
+use crate::constraint_file::Bump;
 
+// every global flag can also be set with its RVM_ variable (RVM_JSON=1, RVM_VERBOSE=2, RVM_COLOR=never...),
+// the flag wins over the variable and both win over the config, e.g. --color over RVM_COLOR over `color`.
+// a boolean variable is off when it's empty, 0, false, no or off and on otherwise
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, about, long_about = None, after_help = crate::exit::EXIT_CODES)]
 pub struct Cli {
   #[command(subcommand)]
   pub action: Action,
-  #[arg(short, long, global = true)] // https://docs.rs/clap/latest/clap/_derive/_tutorial/index.html#flags
-  pub verbose: bool, //TODO
-  /// if true, don't ask for confirmation
-  #[arg(short, long, global = true)]
-  pub yes: bool, //TODO
-  //TODO maybe add a dry run
+  /// more output, and logs: -v info, -vv debug, -vvv trace
+  #[arg(short, long, global = true, action = clap::ArgAction::Count, env = "RVM_VERBOSE")] // https://docs.rs/clap/latest/clap/_derive/_tutorial/index.html#flags
+  pub verbose: u8,
+  /// format of the logs -v enables
+  #[arg(long, global = true, value_enum, default_value_t = LogFormat::Text, env = "RVM_LOG_FORMAT")]
+  pub log_format: LogFormat,
+  /// answer yes to every confirmation
+  #[arg(short, long, global = true, env = "RVM_YES", value_parser = FalseyValueParser::new())]
+  pub yes: bool,
+  /// never prompt, anything that needs confirmation fails unless --yes is given (the default when CI is set)
+  #[arg(long, global = true, env = "RVM_NO_INPUT", value_parser = FalseyValueParser::new())]
+  pub no_input: bool,
+  /// how long to wait for another rvm process that's changing installs, in seconds
+  #[arg(long, global = true, default_value_t = 60, value_name = "SECONDS", env = "RVM_WAIT_TIMEOUT")]
+  pub wait_timeout: u64,
+  /// pick prebuilt artifacts for this platform instead of the detected one, e.g. aarch64-linux-musl
+  #[arg(long, global = true, value_name = "TRIPLE", env = "RVM_PLATFORM")]
+  pub platform: Option<Platform>,
+  /// print results as json (list, list-remote, which, outdated, doctor, resolve, each)
+  #[arg(long, global = true, env = "RVM_JSON", value_parser = FalseyValueParser::new())]
+  pub json: bool,
+  /// print results as stable tab separated lines, for shell scripts (same commands as --json)
+  #[arg(long, global = true, conflicts_with = "json", env = "RVM_PORCELAIN", value_parser = FalseyValueParser::new())]
+  pub porcelain: bool,
+  /// print json one object per line, list-remote, each and workspace status one per item as soon as it's known,
+  /// the other --json commands their whole result on one line
+  #[arg(long, global = true, conflicts_with_all = ["json", "porcelain"], env = "RVM_JSON_LINES", value_parser = FalseyValueParser::new())]
+  pub json_lines: bool,
+  /// print what install, uninstall, prune, upgrade, update, verify --repair, local, global and index generate -o would change without changing it
+  #[arg(long, global = true, env = "RVM_DRY_RUN", value_parser = FalseyValueParser::new())]
+  pub dry_run: bool,
+  /// never use the network: registries are answered from their cached index and downloads fail
+  #[arg(long, global = true, env = OFFLINE_VAR, value_parser = FalseyValueParser::new())]
+  pub offline: bool,
+  /// color human output: auto (terminals, unless NO_COLOR is set), always or never, `color` in the config sets the default
+  #[arg(long, global = true, value_enum, env = "RVM_COLOR")]
+  pub color: Option<ColorChoice>,
+  /// work on the shared install for every user (/opt/rvm, %ProgramData%\rvm on windows) instead of your own
+  #[arg(long, global = true, env = SYSTEM_VAR, value_parser = FalseyValueParser::new())]
+  pub system: bool,
+  /// fail instead of warning when a project's rvm.lock is missing or doesn't match Renv.yaml
+  #[arg(long, global = true, env = LOCKED_VAR, value_parser = FalseyValueParser::new())]
+  pub locked: bool,
+  /// accept downloads the index only checks with md5 or sha1, which can be forged
+  #[arg(long, global = true, env = INSECURE_DIGEST_VAR, value_parser = FalseyValueParser::new())]
+  pub insecure_digest: bool,
+  /// trust a registry's signing key when it isn't the one it was first seen with, for a registry that rotated its key
+  #[arg(long, global = true, env = TRUST_NEW_KEY_VAR, value_parser = FalseyValueParser::new())]
+  pub trust_new_key: bool,
+  /// fail instead of warning about a range or project that's likely not what was meant (an unreachable except, a stale rvm.lock)
+  #[arg(long, global = true, env = DENY_WARNINGS_VAR, value_parser = FalseyValueParser::new())]
+  pub deny_warnings: bool,
+}
+
+impl Cli {
+  /// Parses args with the variables looked up with lookup instead of read from the process, the cli's own
+  /// with env_var_os so it sees what with_rvm_vars sets like everything else
+  pub fn try_parse_with_env<I, T>(args: I, lookup: impl Fn(&str) -> Option<OsString>) -> Result<Self, clap::Error>
+  where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+  {
+    // clap reads the process's variable as it builds each arg, where lookup says otherwise its value becomes the
+    // default instead, which a flag still wins over the same way
+    let command = Self::command().mut_args(|arg| {
+      let Some(name) = arg.get_env().and_then(|name| name.to_str()).map(str::to_owned) else { return arg };
+      let value = lookup(&name);
+      if value == env::var_os(&name) {
+        return arg;
+      }
+      let arg = arg.env(None);
+      match value {
+        Some(value) => arg.default_value(value),
+        None => arg,
+      }
+    });
+    Self::from_arg_matches_mut(&mut command.try_get_matches_from(args)?)
+  }
 }
 
 
 #[derive(Subcommand, Debug)]
 pub enum Action {
-  /// create a new project in the current directory
+  /// create a new project in the current directory, or with a shell print its hook
+  /// e.g. `eval "$(rvm init bash)"` in .bashrc switches versions on cd
   Init { //flags
+    /// print the cd hook for this shell (or the direnv layout) instead of creating a project
+    #[arg(value_name = "SHELL")]
+    hook: Option<Hook>,
     #[arg(long, short = 'R', default_value = "latest")]
     rversion: String,
     #[arg(long, short, default_value = r".\")]
@@ -39,4 +136,629 @@ pub enum Action {
     #[arg(long, short, default_value = r".\")]
     path: PathBuf,
   },
+  /// list the R versions available to install
+  ListRemote {
+    /// ignore the cache ttl and ask the registry again
+    #[arg(long, conflicts_with = "offline")]
+    refresh: bool,
+    /// only versions of a channel: release, or what pre-releases start with, e.g. nightly for 4.4.0-nightly.20240315
+    #[arg(long)]
+    channel: Option<String>,
+    /// only the versions in this range, a partial version is its series (4.2 is ~4.2)
+    #[arg(long, value_name = "RANGE")]
+    filter: Option<String>,
+    /// leave out pre-releases, even ones the filter names
+    #[arg(long, conflicts_with = "only_prereleases")]
+    exclude_prereleases: bool,
+    /// only pre-releases, a filter doesn't have to name one
+    #[arg(long)]
+    only_prereleases: bool,
+    /// every build of a release, not just the one `tie_break` picks
+    #[arg(long)]
+    all_builds: bool,
+  },
+  /// install one or more R versions, e.g. `rvm install 3.6 4.2 4.3.1`
+  Install {
+    /// versions, ranges or latest, stable and lts, a partial version means the newest of its series
+    #[arg(required_unless_present_any = ["path", "git", "interactive"], conflicts_with_all = ["path", "git"])]
+    versions: Vec<String>,
+    /// pick it from the remote versions, type to search
+    #[arg(long, short, conflicts_with_all = ["versions", "path", "git"])]
+    interactive: bool,
+    /// how many versions to install at the same time
+    #[arg(long, short, default_value_t = 4)]
+    jobs: usize,
+    /// prebuilt installer or build from source, overrides the config
+    #[arg(long, value_enum)]
+    backend: Option<Backend>,
+    /// register an R you already have, a dir or a .tar.gz, .tar.xz, .tar.zst or .zip of one
+    #[arg(long, requires = "as_version", conflicts_with = "git")]
+    path: Option<PathBuf>,
+    /// build and register a git repository of R, e.g. https://github.com/wch/r-source
+    #[arg(long, value_name = "URL")]
+    git: Option<String>,
+    /// the tag, branch or sha to build with --git, HEAD by default
+    #[arg(long = "ref", value_name = "REF", requires = "git")]
+    reference: Option<String>,
+    /// the version to register --path or --git as, with --git it defaults to the one in the tag
+    #[arg(long = "as", value_name = "VERSION", conflicts_with = "versions")]
+    as_version: Option<String>,
+    /// pick from a channel instead of releases, e.g. `rvm install 4.4 --channel nightly` for the newest 4.4 snapshot
+    #[arg(long, conflicts_with_all = ["path", "git"])]
+    channel: Option<String>,
+    /// refuse to install past disk_budget_mb instead of warning
+    #[arg(long)]
+    strict_budget: bool,
+  },
+  /// list installed R versions, * marks the active one
+  List {
+    /// also show how much disk each one takes
+    #[arg(long)]
+    size: bool,
+    /// also show the day each one was last run or activated through rvm
+    #[arg(long)]
+    last_used: bool,
+    /// also show where each one came from: the url it was downloaded from, the git repository or the local path
+    #[arg(long)]
+    origin: bool,
+    /// only the newest of each minor series
+    #[arg(long)]
+    compact: bool,
+    /// only the versions in this range, a partial version is its series (4.2 is ~4.2)
+    #[arg(long, value_name = "RANGE")]
+    filter: Option<String>,
+    /// leave out pre-releases, even ones the filter names
+    #[arg(long, conflicts_with = "only_prereleases")]
+    exclude_prereleases: bool,
+    /// only pre-releases, a filter doesn't have to name one
+    #[arg(long)]
+    only_prereleases: bool,
+  },
+  /// uninstall an installed R version
+  Uninstall {
+    version: String,
+  },
+  /// print the absolute path of a binary (R, Rscript...) of the active version
+  Which {
+    binary: String,
+  },
+  /// run a command with a version's binaries first in PATH, without changing the active one
+  /// e.g. `rvm exec 4.2 -- Rscript script.R`
+  Exec {
+    /// version, range or alias, the active version if not given
+    version: Option<String>,
+    #[arg(last = true, required = true)]
+    command: Vec<String>,
+  },
+  /// run a command under every installed version matching a range, e.g. `rvm each ">=4.1" -- Rscript test.R`
+  Each {
+    range: String,
+    #[arg(last = true, required = true)]
+    command: Vec<String>,
+    /// stop at the first version where the command fails
+    /// with --json the summary is json and the command's own output goes to stderr
+    #[arg(long)]
+    fail_fast: bool,
+  },
+  /// check the setup for problems and say how to fix them, exits with 1 if there's any
+  Doctor {
+    /// also fix what can be fixed safely (leftovers of interrupted installs, installs that moved)
+    #[arg(long)]
+    fix: bool,
+    /// show how the configured mirrors have been answering downloads instead, in the order they're tried
+    #[arg(long, conflicts_with = "fix")]
+    mirrors: bool,
+  },
+  /// uninstall versions no alias or project uses
+  Prune {
+    /// where to look for project files (recursively), can be repeated
+    #[arg(long, default_value = r".\")]
+    projects: Vec<PathBuf>,
+    /// keep the newest N of every minor series even if unused
+    #[arg(long, value_name = "N")]
+    keep_latest_per_minor: Option<usize>,
+    /// only remove versions installed longer ago than this (e.g. 90d, 12w)
+    #[arg(long, value_parser = parse_duration)]
+    older_than: Option<Duration>,
+    /// only remove versions that weren't run or activated through rvm for this long (e.g. 180d), since they were installed if never
+    #[arg(long, value_parser = parse_duration)]
+    unused_for: Option<Duration>,
+  },
+  /// protect an installed version from prune and uninstall
+  Pin {
+    version: String,
+  },
+  /// remove the protection of rvm pin
+  Unpin {
+    version: String,
+  },
+  /// make an installed version read-only again after rvm unlock
+  Lock {
+    version: String,
+  },
+  /// make an installed version writable, every install is read-only once it's in place (`protect` in the config)
+  Unlock {
+    version: String,
+  },
+  /// show installed versions and project pins that have a newer remote version
+  Outdated {
+    /// where to look for project files (recursively), can be repeated
+    #[arg(long, default_value = r".\")]
+    projects: Vec<PathBuf>,
+    /// the ones furthest behind first, by Version::distance
+    #[arg(long)]
+    sort_by_staleness: bool,
+  },
+  /// install the newest version within a range or alias and move the alias to it
+  Upgrade {
+    /// range or alias, the active version's constraint if not given
+    target: Option<String>,
+    /// uninstall the version that was replaced
+    #[arg(long)]
+    remove_old: bool,
+    /// upgrade the newest installed of every minor series to the newest of that series, e.g. 4.1.0 and 4.2.2 to 4.1.3 and 4.2.3
+    #[arg(long, conflicts_with = "target")]
+    each_minor: bool,
+  },
+  /// print the release notes of every version after the newest installed match of a range up to its newest match,
+  /// what `rvm upgrade` would go through, e.g. `rvm changes ~4.3`
+  Changes {
+    /// range or alias, the active version's constraint if not given
+    range: Option<String>,
+  },
+  /// create a project in dir with its Renv.yaml and, unless --no-lock, the rvm.lock `rvm update` would write,
+  /// e.g. `rvm new analysis --version "~4.3" --ci github-actions`
+  New {
+    dir: PathBuf,
+    /// the project's R range
+    #[arg(long, value_name = "RANGE")]
+    version: String,
+    /// leave the version unlocked
+    #[arg(long)]
+    no_lock: bool,
+    /// also write the CI config installing the locked version, what `rvm ci-snippet` prints for it
+    #[arg(long, value_enum, conflicts_with = "no_lock")]
+    ci: Option<SnippetFormat>,
+  },
+  /// set the R range of the project in the current dir, creating its Renv.yaml if needed
+  Local {
+    #[arg(required_unless_present_any = ["bump", "none"])]
+    range: Option<String>,
+    /// raise the major, minor or patch of the nearest Renv.yaml's (or .tool-versions') constraint instead,
+    /// changing nothing else in the file
+    #[arg(long, value_enum, conflicts_with = "range")]
+    bump: Option<Bump>,
+    /// the day (YYYY-MM-DD, or a duration from now like 180d) to look at the range again, `rvm status` and the cd
+    /// hook warn from then on
+    #[arg(long, value_parser = parse_day, conflicts_with = "bump")]
+    expires: Option<String>,
+    /// opt this dir and everything under it out of projects (a project file above doesn't count here) and of the
+    /// cd hooks switching, resolving falls back to what no_project in the config says
+    #[arg(long, conflicts_with_all = ["range", "bump", "expires"])]
+    none: bool,
+  },
+  /// set the machine default, the version used outside projects (the "default" alias)
+  Global {
+    range: String,
+  },
+  /// print which version is active here and why, or with a range just the best version for it (exit 1 if none)
+  /// e.g. `rvm resolve "~4.2" --remote` in a Makefile
+  Resolve {
+    /// version, range or alias to resolve instead of the active one
+    version: Option<String>,
+    /// show every source consulted, in the order of `resolution` in the config, and why it was used or not,
+    /// then every installed version and the constraint that ruled it out
+    #[arg(long, conflicts_with_all = ["installed", "remote", "minimal"])]
+    explain: bool,
+    /// the newest installed version matching, what's picked without --remote too but scripts can say so
+    #[arg(long, requires = "version", conflicts_with = "remote")]
+    installed: bool,
+    /// the newest version matching that can be installed here, from the index
+    #[arg(long, requires = "version")]
+    remote: bool,
+    /// the smallest version the range allows, whether or not R ever had it, e.g. the minimum a package supports
+    #[arg(long, requires = "version", conflicts_with_all = ["installed", "remote"])]
+    minimal: bool,
+  },
+  /// print shell completions, e.g. `rvm completions bash >> ~/.bashrc`, they never wait on the network
+  Completions {
+    #[arg(value_enum)]
+    shell: Shell,
+  },
+  /// print what completes the last of words (the command line after rvm), run by the completions
+  #[command(hide = true)]
+  Complete {
+    #[arg(last = true)]
+    words: Vec<String>,
+  },
+  /// print the environment for the project in the current dir, run by the shell hooks
+  #[command(hide = true)]
+  HookEnv {
+    #[arg(long, value_enum)]
+    shell: Option<Shell>,
+  },
+  /// measure how long the cd hook takes in a dir, resolving and from its cache, against hook_budget_ms
+  HookBench {
+    #[arg(default_value = ".")]
+    dir: PathBuf,
+    /// runs of each, the median is what's held to the budget
+    #[arg(long, default_value_t = 20)]
+    runs: usize,
+    /// syntax the hook prints, detected from $SHELL if not given
+    #[arg(long, value_enum)]
+    shell: Option<Shell>,
+  },
+  /// answer the shell hooks from a long running process and download what their projects will need, e.g. `rvm daemon &`
+  Daemon {
+    /// stop the running one
+    #[arg(long, conflicts_with = "status")]
+    stop: bool,
+    /// whether one is running
+    #[arg(long)]
+    status: bool,
+    /// seconds between refreshing the index and prefetching for every project seen
+    #[arg(long, default_value_t = 3600)]
+    interval: u64,
+  },
+  /// answer json requests (parse, compare, satisfies, resolve) for an editor, e.g.
+  /// `rvm query '{"query":"satisfies","version":"4.3.1","range":"~4.3"}'`, or one per line until stdin ends with --stdin
+  Query {
+    /// one request, answered on one line
+    #[arg(required_unless_present = "stdin", conflicts_with = "stdin")]
+    request: Option<String>,
+    /// read requests from stdin, one per line
+    #[arg(long)]
+    stdin: bool,
+  },
+  /// direnv integration, e.g. `rvm direnv envrc >> .envrc`
+  Direnv {
+    #[command(subcommand)]
+    action: DirenvAction,
+  },
+  /// use a version in this shell session only, e.g. `eval "$(rvm shell 4.2)"`
+  #[command(visible_alias = "use")]
+  Shell {
+    /// version, range or alias
+    #[arg(required_unless_present_any = ["unset", "interactive"])]
+    version: Option<String>,
+    /// pick it from the installed versions, type to search
+    #[arg(long, short, conflicts_with_all = ["version", "unset"])]
+    interactive: bool,
+    /// go back to the project file or default alias
+    #[arg(long, conflicts_with = "version")]
+    unset: bool,
+    /// syntax to print, detected from $SHELL if not given
+    #[arg(long, value_enum)]
+    shell: Option<Shell>,
+  },
+  /// print the variables that make a version the active one, e.g. `eval "$(rvm env)"`, --json for other tools
+  Env {
+    /// version, range or alias, resolved like `rvm exec` does if not given
+    version: Option<String>,
+    /// syntax to print, detected from $SHELL if not given
+    #[arg(long, value_enum)]
+    shell: Option<Shell>,
+  },
+  /// manage the package library each version gets
+  Libs {
+    #[command(subcommand)]
+    action: LibsAction,
+  },
+  /// manage other tools through plugins, rvm-plugin-* executables on PATH or `plugins` in the config
+  Plugin {
+    #[command(subcommand)]
+    action: PluginAction,
+  },
+  /// print what's installed (versions, aliases, default, tools) as a manifest for `rvm import`, e.g. `rvm export > env.toml`
+  Export,
+  /// compare two manifests from `rvm export`, or one with what's on this machine, exit 1 when they differ
+  /// e.g. `rvm diff ci-image.toml` for what a developer's machine has that CI doesn't
+  Diff {
+    /// the manifest taken as before
+    old: PathBuf,
+    /// the manifest taken as after, this machine if not given
+    new: Option<PathBuf>,
+  },
+  /// print the runtime constraints of the manifests here (DESCRIPTION, Gemfile, pyproject.toml, package.json's
+  /// engines) and offer to write them to the project's Renv.yaml, R's as rversion and the others as tools
+  Detect,
+  /// install whatever a manifest from `rvm export` has that's missing, or take another version manager's installs with --from
+  Import {
+    /// manifest from `rvm export`
+    #[arg(required_unless_present = "from", conflicts_with = "from")]
+    file: Option<PathBuf>,
+    /// the manager to take installs from, and the project's pin from its file
+    #[arg(long, value_enum)]
+    from: Option<Manager>,
+    /// copy the installs instead of linking to them, so removing the other manager doesn't break them
+    #[arg(long, requires = "from")]
+    copy: bool,
+  },
+  /// resolve the project's rversion again and write the result to its rvm.lock
+  Update,
+  /// exit 0 if a version is in a range and 1 if not, e.g. `rvm satisfies 4.2.1 ">=4.1, <4.3"`
+  Satisfies {
+    version: String,
+    range: String,
+  },
+  /// point at rversion ranges that can't be what was meant (nothing matches, bounds the wrong way, an except that takes out nothing), exit 1 if there's any
+  Lint {
+    /// a Renv.yaml or .tool-versions, the nearest one by default
+    file: Option<PathBuf>,
+  },
+  /// print <, = or > for how a compares to b, and exit with 1, 0 or 2 accordingly
+  Compare {
+    a: String,
+    b: String,
+    /// also say which component decided it, e.g. "differs at pre_release: ..."
+    #[arg(long)]
+    explain: bool,
+    /// compare pre-releases without case, RC.1 = rc.1
+    #[arg(long, short)]
+    ignore_case: bool,
+  },
+  /// sort the versions read from stdin, one per line, e.g. `git tag | rvm sort --filter ">=4" --latest-per minor`
+  Sort {
+    /// newest first
+    #[arg(long, short)]
+    reverse: bool,
+    /// only the versions in this range
+    #[arg(long, value_name = "RANGE")]
+    filter: Option<String>,
+    /// only the newest of each major or minor series
+    #[arg(long, value_enum, value_name = "SERIES")]
+    latest_per: Option<Granularity>,
+    /// drop the versions that are the same as an earlier one, builds count only when strict and pre-releases only when not range
+    #[arg(long, value_enum, value_name = "EQUALITY", num_args = 0..=1, default_missing_value = "strict")]
+    unique: Option<Equality>,
+    /// print the versions the way rvm writes them instead of the lines as they came in
+    #[arg(long)]
+    normalize: bool,
+    /// zero-pad major, minor and patch (one width for all, or major,minor,patch) so text sorting agrees, implies --normalize
+    #[arg(long, value_name = "WIDTH", value_delimiter = ',', num_args = 1..=3)]
+    pad: Vec<usize>,
+    /// start every version with v, implies --normalize
+    #[arg(long)]
+    v_prefix: bool,
+    /// leave out extra versions and builds, implies --normalize
+    #[arg(long)]
+    short: bool,
+    /// keep the leading zeros versions were written with (1.02.003), implies --normalize
+    #[arg(long)]
+    keep_zeros: bool,
+  },
+  /// show the disk used by every version, its package library and rvm's caches
+  Du,
+  /// run a command with a version that's only installed for it, e.g. `rvm try 4.4 -- R CMD check .`
+  /// a version that's already installed is used as is and kept
+  Try {
+    range: String,
+    /// keep the install afterwards
+    #[arg(long)]
+    keep: bool,
+    #[arg(last = true, required = true)]
+    command: Vec<String>,
+  },
+  /// show what R and the project's tools resolve to here, with the project's constraint and lockfile, a newer version
+  /// in reach and what `rvm doctor` finds, --json for an editor's status bar
+  Status,
+  /// print where rvm keeps its config, data, cache and state, after every override
+  Paths,
+  /// copy an installed version into a dir that works without rvm, e.g. for a container image
+  Bundle {
+    /// installed version or range, the newest installed match is copied
+    version: String,
+    /// where the copy goes, a new or empty dir
+    #[arg(short, long)]
+    output: PathBuf,
+    /// where the copy is going to be used from (an absolute path in the image), the output dir by default
+    #[arg(long)]
+    prefix: Option<PathBuf>,
+  },
+  /// print what a CI config or Dockerfile needs to install the R the project's lockfile has, run it again after `rvm update`
+  CiSnippet {
+    #[arg(long, value_enum)]
+    format: SnippetFormat,
+  },
+  /// show installs per month and, with `stats: true` in the config, the most used versions and cache hit rates, all local
+  Stats,
+  /// show who installed, uninstalled and switched versions and when, oldest first
+  History {
+    /// only the last N changes
+    #[arg(long, short = 'n', value_name = "N")]
+    limit: Option<usize>,
+  },
+  /// revert the last switch (`rvm local`, `rvm global`) in the history
+  Undo,
+  /// take in an install rvm skips for its name (`rvm list` shows them as unmanaged) as version, moving it where versions go
+  Adopt {
+    dir: PathBuf,
+    /// the version it is, exactly
+    #[arg(long = "as", value_name = "VERSION")]
+    version: String,
+  },
+  /// check installed versions against the checksums recorded when they were installed
+  Verify {
+    /// the installed version to check, the active one otherwise
+    #[arg(conflicts_with = "all")]
+    version: Option<String>,
+    /// every installed version, hashed across versions at the same time
+    #[arg(long)]
+    all: bool,
+    /// reinstall damaged versions the same way they were installed
+    #[arg(long)]
+    repair: bool,
+    /// how many files to hash at the same time, across versions
+    #[arg(long, short, default_value_t = 4)]
+    jobs: usize,
+  },
+  /// find and remove what's left of unfinished installs, uninstalled versions and removed tools
+  Gc,
+  /// run the configured smoke test (`R --version` and a short script by default) against installed versions
+  Check {
+    /// only this one, every installed version otherwise
+    version: Option<String>,
+  },
+  /// manage names (default, lts, project-x...) that point to a version or range
+  Alias {
+    #[command(subcommand)]
+    action: AliasAction,
+  },
+  /// resolve every project file under a root at once, for monorepos where projects pin different versions
+  Workspace {
+    #[command(subcommand)]
+    action: WorkspaceAction,
+  },
+  /// tools for hosting a registry of your own
+  Index {
+    #[command(subcommand)]
+    action: IndexAction,
+  },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum DirenvAction {
+  /// print the environment of the project's version for direnv, what `use rvm` runs
+  Export {
+    /// version, range or alias, the project's if not given
+    version: Option<String>,
+  },
+  /// print the lines to add to a .envrc
+  Envrc {
+    /// pin the .envrc to this version instead of following Renv.yaml
+    version: Option<String>,
+  },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum LibsAction {
+  /// print where a version's packages are installed
+  Path {
+    /// version, range or alias, resolved like `rvm exec` does if not given
+    version: Option<String>,
+  },
+  /// delete the packages of a version, or of every version no longer installed
+  Clean {
+    /// installed version, range or alias
+    version: Option<String>,
+  },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum PluginAction {
+  /// show the plugins found and what's installed with each
+  List,
+  /// list the versions a plugin can install
+  ListRemote {
+    plugin: String,
+    /// ignore the cache ttl and ask the plugin again
+    #[arg(long)]
+    refresh: bool,
+  },
+  /// install the newest version of a tool matching a range
+  Install {
+    plugin: String,
+    version: String,
+  },
+  /// remove an installed version of a tool
+  Uninstall {
+    plugin: String,
+    version: String,
+  },
+  /// run a command with a tool's version on PATH, what the shims in <data dir>/shims run
+  Exec {
+    plugin: String,
+    /// installed version or range, the newest installed if not given
+    #[arg(long)]
+    version: Option<String>,
+    #[arg(last = true, required = true)]
+    command: Vec<String>,
+  },
+}
+
+/// When two versions are the same, see version_parser.rs StrictKey, PrecedenceKey and RangeKey
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum Equality {
+  Strict,
+  Precedence,
+  Range,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum IndexAction {
+  /// print an index of the artifacts in a dir, with checksums, and versions and platforms from the file names
+  /// e.g. `rvm index generate releases/ --base-url https://r.example.com/releases -o releases/index.json`
+  Generate {
+    dir: PathBuf,
+    /// where dir is served, the urls are this and the path under dir
+    #[arg(long)]
+    base_url: String,
+    #[arg(long, value_enum, default_value_t = IndexFormat::Json)]
+    format: IndexFormat,
+    /// write it to this file instead of printing it
+    #[arg(long, short)]
+    output: Option<PathBuf>,
+    /// sign the index with this ed25519 secret key (hex of 32 random bytes, e.g. `openssl rand -hex 32 > index.key`),
+    /// rvm trusts its public key on first use and fails when it changes or the index stops being signed
+    #[arg(long, value_name = "FILE")]
+    signing_key: Option<PathBuf>,
+  },
+  /// write a patch from an older version's artifact to a newer one's, for the index's deltas
+  /// it's named <target>.from-<base version>.delta next to target unless --output is given, which is how generate finds it
+  Delta {
+    base: PathBuf,
+    target: PathBuf,
+    #[arg(long, short)]
+    output: Option<PathBuf>,
+  },
+}
+
+/// Where `rvm ci-snippet` output goes
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum SnippetFormat {
+  Dockerfile,
+  GithubActions,
+  Gitlab,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum IndexFormat {
+  Json,
+  Toml,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum WorkspaceAction {
+  /// what each project resolves to and every version they need together
+  Status {
+    /// where to look for project files (recursively)
+    #[arg(default_value = ".")]
+    root: PathBuf,
+  },
+  /// install the versions projects need that aren't installed, in one go
+  Install {
+    #[arg(default_value = ".")]
+    root: PathBuf,
+    /// how many versions to install at the same time
+    #[arg(long, short, default_value_t = 4)]
+    jobs: usize,
+  },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum AliasAction {
+  /// point an alias to a version or range, e.g. `rvm alias set lts ~4.2`
+  Set {
+    name: String,
+    target: String,
+    /// the day (YYYY-MM-DD, or a duration from now like 180d) to look at the alias again, using it warns from then on
+    #[arg(long, value_parser = parse_day)]
+    expires: Option<String>,
+  },
+  /// show every alias
+  List,
+  /// delete an alias
+  Remove {
+    name: String,
+  },
 }
\ No newline at end of file
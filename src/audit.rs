@@ -0,0 +1,140 @@
+use std::fmt::{Display, Formatter};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+use crate::config::Config;
+use crate::install::meta::InstallMeta;
+use crate::layout::state_dir;
+use crate::local_utils::{is_system, system_home};
+use crate::parsing::version_parser::Version;
+use crate::utils::share_tree;
+
+// every change to what's installed and which R is used, for shared machines where "who changed R, and when" comes up:
+// one json object per line in <state dir>/audit.jsonl (next to the shared installs with --system), appended with an
+// flock held so two rvm at once don't interleave lines. `rvm history` shows it, `rvm undo` reverts the last switch
+// by making the opposite change, which is logged too, so undoing twice redoes.
+// with `audit: false` in the config nothing is logged, and logging never fails a command
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+  /// unix seconds
+  pub at: u64,
+  /// who ran rvm, by USER (USERNAME on windows)
+  pub user: String,
+  #[serde(flatten)]
+  pub change: Change,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "change", rename_all = "kebab-case")]
+pub enum Change {
+  /// source is the artifact's url, the path it was registered from or the git repository and ref it was built from
+  Install { version: Version, source: Option<String>, sha256: Option<String> },
+  Uninstall { version: Version, sha256: Option<String> },
+  /// rversion of a project file written, `rvm local`, was is none when the file was created
+  Local { path: PathBuf, rversion: String, was: Option<String> },
+  /// an alias set or removed (target none), `rvm global` is the default alias
+  Alias { name: String, target: Option<String>, was: Option<String> },
+}
+
+impl Change {
+  /// Whether it changed which version is used, what `rvm undo` reverts
+  pub fn is_switch(&self) -> bool {
+    match self {
+      Change::Local { .. } => true,
+      Change::Alias { name, .. } => name == "default",
+      Change::Install { .. } | Change::Uninstall { .. } => false,
+    }
+  }
+}
+
+impl Display for Change {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    let was = |was: &Option<String>| was.as_ref().map(|was| format!(" (was {})", was)).unwrap_or_default();
+    match self {
+      Change::Install { version, source, sha256 } => {
+        write!(f, "install {}", version)?;
+        if let Some(source) = source {
+          write!(f, " from {}", source)?;
+        }
+        match sha256 {
+          Some(sha256) => write!(f, " sha256 {}", sha256),
+          None => Ok(()),
+        }
+      },
+      Change::Uninstall { version, .. } => write!(f, "uninstall {}", version),
+      Change::Local { path, rversion, was: previous } => write!(f, "local {} rversion: {}{}", path.display(), rversion, was(previous)),
+      Change::Alias { name, target: Some(target), was: previous } => write!(f, "alias {} -> {}{}", name, target, was(previous)),
+      Change::Alias { name, target: None, was: previous } => write!(f, "unalias {}{}", name, was(previous)),
+    }
+  }
+}
+
+pub fn path() -> PathBuf {
+  if is_system() {
+    return system_home().join("audit.jsonl");
+  }
+  state_dir().join("audit.jsonl")
+}
+
+/// Whether the config asks for logging
+pub fn enabled() -> bool {
+  Config::get().audit
+}
+
+/// Appends change by the current user now, when logging is on
+pub fn record(change: Change) {
+  if !enabled() {
+    return;
+  }
+  let entry = Entry {
+    at: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs()),
+    user: user(),
+    change,
+  };
+  if let Err(err) = append(&entry) {
+    tracing::debug!(error = %err, "can't log the change");
+  }
+}
+
+/// Logs version's install with where it came from, after its metadata has it
+pub fn record_install(version: &Version) {
+  let meta = InstallMeta::load(version);
+  let source = meta.origin().map(|origin| origin.to_string());
+  record(Change::Install { version: version.clone(), source, sha256: meta.sha256 });
+}
+
+/// Every entry, oldest first, lines that don't read (from a newer rvm, cut short) are skipped
+pub fn load() -> Vec<Entry> {
+  let text = std::fs::read_to_string(path()).unwrap_or_default();
+  text.lines().filter_map(|line| serde_json::from_str(line).ok()).collect()
+}
+
+/// The newest switch, the one `rvm undo` reverts
+pub fn last_switch() -> Option<Entry> {
+  load().into_iter().rev().find(|entry| entry.change.is_switch())
+}
+
+fn user() -> String {
+  std::env::var("USER").or_else(|_| std::env::var("USERNAME")).unwrap_or_else(|_| "unknown".to_owned())
+}
+
+fn append(entry: &Entry) -> std::io::Result<()> {
+  let path = path();
+  if let Some(parent) = path.parent() {
+    std::fs::create_dir_all(parent)?;
+  }
+  let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+  file.lock_exclusive()?;
+  let line = serde_json::to_string(entry).map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+  writeln!(file, "{}", line)?;
+  file.flush()?;
+  FileExt::unlock(&file)?;
+  if is_system() {
+    share_tree(&system_home(), &path)?;
+  }
+  Ok(())
+}
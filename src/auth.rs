@@ -0,0 +1,173 @@
+use std::fmt;
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use crate::config::Config;
+use crate::utils::url_under;
+
+// credentials for private registries and artifact hosts, looked up for every request by its url, first that has some:
+//   RVM_TOKEN_<HOST>   a bearer token, HOST upper cased with everything but letters and digits as _ (RVM_TOKEN_R_CORP_EXAMPLE)
+//   `credentials` in the config, the longest url prefix that matches (as a url, not as text) and has a secret, e.g.
+//     {url: https://r.corp.example/, token_env: CORP_R_TOKEN} or {url: ..., username: ci, password_env: CORP_R_PASSWORD}
+//   netrc, NETRC or ~/.netrc (~/_netrc on windows), the machine of the host or default
+// secrets never get logged or printed, Debug shows where they come from but not what they are.
+// reqwest drops the Authorization header on redirects to another host, so a registry sending downloads to a cdn doesn't leak it
+
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Credential {
+  /// prefix of the urls it's for
+  pub url: String,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub token: Option<String>,
+  /// env var with the token, so it doesn't have to be in the config
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub token_env: Option<String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub username: Option<String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub password: Option<String>,
+  /// env var with the password
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub password_env: Option<String>,
+}
+
+impl fmt::Debug for Credential {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let redacted = |set: bool| if set { Some("***") } else { None };
+    f.debug_struct("Credential")
+      .field("url", &self.url)
+      .field("token", &redacted(self.token.is_some()))
+      .field("token_env", &self.token_env)
+      .field("username", &self.username)
+      .field("password", &redacted(self.password.is_some()))
+      .field("password_env", &self.password_env)
+      .finish()
+  }
+}
+
+impl Credential {
+  fn auth(&self) -> Option<Auth> {
+    let from_env = |name: &Option<String>| name.as_deref().and_then(|n| std::env::var(n).ok()).filter(|v| !v.is_empty());
+    if let Some(token) = from_env(&self.token_env).or_else(|| self.token.clone()) {
+      return Some(Auth::Bearer(token));
+    }
+    let username = self.username.clone()?;
+    Some(Auth::Basic(username, from_env(&self.password_env).or_else(|| self.password.clone())))
+  }
+}
+
+#[derive(Clone, PartialEq, Eq)]
+pub enum Auth {
+  Bearer(String),
+  Basic(String, Option<String>),
+}
+
+impl fmt::Debug for Auth {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Auth::Bearer(_) => write!(f, "Bearer(***)"),
+      Auth::Basic(username, _) => write!(f, "Basic({}, ***)", username),
+    }
+  }
+}
+
+/// The credentials to send to url, none for hosts nothing has credentials for
+pub fn auth_for(url: &str) -> Option<Auth> {
+  let host = reqwest::Url::parse(url).ok()?.host_str()?.to_owned();
+  if let Some(token) = std::env::var(token_var(&host)).ok().filter(|t| !t.is_empty()) {
+    tracing::debug!(%host, "using token from the environment");
+    return Some(Auth::Bearer(token));
+  }
+  if let Some(auth) = configured_auth(Config::get().credentials, url) {
+    tracing::debug!(%host, "using credentials from the config");
+    return Some(auth);
+  }
+  let netrc = std::fs::read_to_string(netrc_path()?).ok()?;
+  let (login, password) = netrc_login(&netrc, &host)?;
+  tracing::debug!(%host, "using credentials from netrc");
+  Some(Auth::Basic(login, password))
+}
+
+/// What the longest of credentials whose url url is under (see url_under, the same scheme, host and port) has
+pub fn configured_auth(credentials: Vec<Credential>, url: &str) -> Option<Auth> {
+  // one whose env var isn't set doesn't hide a shorter prefix
+  let mut configured: Vec<Credential> = credentials.into_iter().filter(|c| url_under(url, &c.url)).collect();
+  configured.sort_by_key(|c| std::cmp::Reverse(c.url.len()));
+  configured.iter().find_map(Credential::auth)
+}
+
+/// The env var with a bearer token for host
+pub fn token_var(host: &str) -> String {
+  let host: String = host.chars().map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' }).collect();
+  format!("RVM_TOKEN_{}", host)
+}
+
+fn netrc_path() -> Option<PathBuf> {
+  if let Some(path) = std::env::var_os("NETRC") {
+    return Some(PathBuf::from(path));
+  }
+  let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))?;
+  let name = if cfg!(windows) { "_netrc" } else { ".netrc" };
+  Some(PathBuf::from(home).join(name))
+}
+
+/// The login and password of host in a netrc, or of its default entry
+pub fn netrc_login(netrc: &str, host: &str) -> Option<(String, Option<String>)> {
+  // (machine, none for default, login, password)
+  let mut entries: Vec<(Option<&str>, Option<&str>, Option<&str>)> = vec![];
+  let mut tokens = netrc.split_whitespace();
+  while let Some(token) = tokens.next() {
+    match token {
+      "machine" => entries.push((Some(tokens.next().unwrap_or_default()), None, None)),
+      "default" => entries.push((None, None, None)),
+      "login" | "password" | "account" => {
+        let value = tokens.next();
+        match (entries.last_mut(), token) {
+          (Some(entry), "login") => entry.1 = value,
+          (Some(entry), "password") => entry.2 = value,
+          _ => {},
+        }
+      },
+      // a macro runs until an empty line, which split_whitespace can't see, and the file rarely goes on after one
+      "macdef" => break,
+      _ => {},
+    }
+  }
+  entries.iter().find(|e| e.0.is_some_and(|m| m.eq_ignore_ascii_case(host)))
+    .or_else(|| entries.iter().find(|e| e.0.is_none()))
+    .and_then(|&(_, login, password)| Some((login?.to_owned(), password.map(str::to_owned))))
+}
+
+/// Sends the credentials for url with a request, if there are some
+pub trait WithAuth: Sized {
+  fn bearer(self, token: &str) -> Self;
+  fn basic(self, username: &str, password: Option<&str>) -> Self;
+
+  fn with_auth(self, url: &str) -> Self {
+    match auth_for(url) {
+      Some(Auth::Bearer(token)) => self.bearer(&token),
+      Some(Auth::Basic(username, password)) => self.basic(&username, password.as_deref()),
+      None => self,
+    }
+  }
+}
+
+impl WithAuth for reqwest::blocking::RequestBuilder {
+  fn bearer(self, token: &str) -> Self {
+    self.bearer_auth(token)
+  }
+
+  fn basic(self, username: &str, password: Option<&str>) -> Self {
+    self.basic_auth(username, password)
+  }
+}
+
+#[cfg(feature = "async")]
+impl WithAuth for reqwest::RequestBuilder {
+  fn bearer(self, token: &str) -> Self {
+    self.bearer_auth(token)
+  }
+
+  fn basic(self, username: &str, password: Option<&str>) -> Self {
+    self.basic_auth(username, password)
+  }
+}
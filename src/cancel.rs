@@ -0,0 +1,94 @@
+use std::io::{self, Read};
+use std::process::Output;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Once};
+use std::time::Duration;
+use duct::Expression;
+use lazy_static::lazy_static;
+use signal_hook::consts::{SIGINT, SIGTERM};
+use crate::install::InstallError;
+
+// stopping downloads, builds and verification halfway: whatever does one takes a CancellationToken and checks it
+// between chunks, files and build steps, killing the command it runs when it's cancelled. the error it stops with
+// goes up through ? like any other, so what it set up is undone the usual way on the way out (the transaction
+// rolls back its staging dir, lock guards release) instead of being left for `rvm doctor --fix`.
+// the cli's token is cancelled by the first Ctrl-C, a second one exits right away. commands that don't take one
+// keep the default of dying on Ctrl-C, `rvm exec` and `rvm run` leave it to the command they run
+
+/// Shared between whatever cancels and what's cancelled, clones are the same token
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn cancel(&self) {
+    self.0.store(true, Ordering::SeqCst);
+  }
+
+  pub fn is_cancelled(&self) -> bool {
+    self.0.load(Ordering::SeqCst)
+  }
+
+  /// Err(Cancelled) once it's cancelled, for ? between steps
+  pub fn check(&self) -> Result<(), InstallError> {
+    if self.is_cancelled() { Err(InstallError::Cancelled) } else { Ok(()) }
+  }
+
+  /// Reads from inner until it's cancelled, then fails every read
+  pub fn reader<R: Read>(&self, inner: R) -> CancellableReader<R> {
+    CancellableReader { inner, token: self.clone() }
+  }
+}
+
+lazy_static! {
+  static ref INTERRUPT: CancellationToken = CancellationToken::new();
+}
+
+/// The token Ctrl-C (or SIGTERM) cancels, installing the handler the first time
+pub fn on_interrupt() -> CancellationToken {
+  static HANDLER: Once = Once::new();
+  HANDLER.call_once(|| {
+    for signal in [SIGINT, SIGTERM] {
+      // in this order the second signal finds the flag already set and exits, the first only sets it
+      let registered = signal_hook::flag::register_conditional_shutdown(signal, 130, INTERRUPT.0.clone())
+        .and_then(|_| signal_hook::flag::register(signal, INTERRUPT.0.clone()));
+      if let Err(err) = registered {
+        tracing::warn!(%err, signal, "can't handle the signal, it kills rvm without cleaning up");
+      }
+    }
+  });
+  INTERRUPT.clone()
+}
+
+pub struct CancellableReader<R> {
+  inner: R,
+  token: CancellationToken,
+}
+
+impl<R: Read> Read for CancellableReader<R> {
+  fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    if self.token.is_cancelled() {
+      // not Interrupted, io::copy retries those
+      return Err(io::Error::new(io::ErrorKind::Other, "cancelled"));
+    }
+    self.inner.read(buf)
+  }
+}
+
+/// Runs expression like duct's run, killing it when token is cancelled
+pub fn run(expression: Expression, token: &CancellationToken) -> io::Result<Output> {
+  let handle = expression.start()?;
+  loop {
+    if let Some(output) = handle.try_wait()? {
+      return Ok(output.clone());
+    }
+    if token.is_cancelled() {
+      let _ = handle.kill();
+      return Err(io::Error::new(io::ErrorKind::Other, "cancelled"));
+    }
+    std::thread::sleep(Duration::from_millis(50));
+  }
+}
@@ -0,0 +1,275 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use crate::auth::Credential;
+use crate::hooks::Hooks;
+use crate::layout::{config_dir, Dirs};
+use crate::local_utils::{is_system, system_home};
+use crate::parsing::version_parser::Range;
+use crate::plugin::PluginConfig;
+use crate::filter::Builtin;
+use crate::resolve::{NoProject, Source, TieBreak};
+use crate::install::dedup::Dedup;
+use crate::install::protect::Protect;
+use crate::theme::{ColorChoice, ThemeConfig};
+use crate::throttle::RequestLimits;
+
+// user config, lives in <config dir>\config.yaml, on top of the machine config in <system home>\config.yaml
+// the user one only overrides the keys it sets, system mode reads the machine one alone
+// every field has a default so an empty or partial file is fine
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+  /// how long (in seconds) the cached remote index is used before asking the registry again
+  pub index_ttl: u64,
+  /// an append-only changelog of CRAN's versions (see index.rs#Changes), with it a refresh only fetches what changed
+  pub index_changelog: Option<String>,
+  /// proxy for every request (http://, https:// or socks5://), overrides HTTP(S)_PROXY and ALL_PROXY
+  pub proxy: Option<String>,
+  /// hosts that skip the proxy, same format as NO_PROXY
+  pub no_proxy: Option<String>,
+  /// url prefixes to rewrite before downloading an artifact, first match wins
+  pub mirrors: Vec<Mirror>,
+  /// tokens or logins for private registries and artifact hosts, see auth.rs
+  pub credentials: Vec<Credential>,
+  /// how hard rvm may hit a host: connections, requests per second and how Retry-After is honored
+  pub requests: RequestLimits,
+  /// how versions get installed, prebuilt installers or building the sources
+  pub backend: Backend,
+  /// steps to build from source, used when the index has no build script for the version
+  pub build: BuildRecipe,
+  /// what `rvm check` runs against an install
+  pub check: SmokeTest,
+  /// whether the shell hooks from `rvm init <shell>` switch versions when entering a project
+  pub auto_switch: bool,
+  /// how long (in milliseconds) a shell hook can take before it says so, 0 never does
+  pub hook_budget_ms: u64,
+  /// same as always passing --yes
+  pub assume_yes: bool,
+  /// same as always passing --no-input
+  pub no_input: bool,
+  /// commands to run before/after install, uninstall and use, besides the files in hooks.d
+  pub hooks: Hooks,
+  /// plugins that aren't rvm-plugin-* executables on PATH
+  pub plugins: Vec<PluginConfig>,
+  /// where the active version comes from, first one that has something wins, see `rvm resolve --explain`
+  pub resolution: Vec<Source>,
+  /// what the default and latest sources of resolution do without a project file: default (both in their order),
+  /// latest (the default alias is passed over) or error
+  pub no_project: NoProject,
+  /// data, cache and state dirs instead of the default ones, see `rvm paths`
+  pub dirs: Dirs,
+  /// same as always passing --color
+  pub color: ColorChoice,
+  /// styles of success, warn, error and version in human output
+  pub theme: ThemeConfig,
+  /// count runs, index lookups and downloads locally for `rvm stats`, nothing is sent anywhere
+  pub stats: bool,
+  /// log installs, uninstalls and switches with who made them for `rvm history` and `rvm undo`, see audit.rs
+  pub audit: bool,
+  /// versions for single executables over the active one, e.g. `Rscript: "4.2"`, a project's `commands` win over these
+  pub commands: BTreeMap<String, String>,
+  /// which of the versions that only differ in build metadata resolving picks, prefer-latest-build by default
+  pub tie_break: TieBreak,
+  /// versions resolving never picks on top of the range, none by default, see filter.rs
+  pub filters: Vec<Builtin>,
+  /// extra variables for the versions in a range, on top of (and over) what the index sets, see activate::expand_env
+  pub env: Vec<VersionEnv>,
+  /// `rvm outdated` warns about what's more than this many minors behind the newest it could use, see Version::distance
+  pub max_minors_behind: Option<u32>,
+  /// how a new install shares the files it has in common with the installs next to it, see install/dedup.rs
+  pub dedup: Dedup,
+  /// how installs are protected from changes once they're in place, read-only by default, see install/protect.rs
+  pub protect: Protect,
+  /// how much (in megabytes) the installs may take together before install warns, or refuses with --strict-budget,
+  /// 0 has no budget, see install/budget.rs
+  pub disk_budget_mb: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+  Binary,
+  Source,
+}
+
+impl Default for Backend {
+  fn default() -> Self {
+    // CRAN only has installers for windows
+    if cfg!(windows) { Backend::Binary } else { Backend::Source }
+  }
+}
+
+// each step is a command and its args, run in the unpacked sources with DESTDIR set to where to install
+// {prefix} (the install dir it's configured for), {version} and {jobs} are replaced before running
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BuildRecipe {
+  pub steps: Vec<Vec<String>>,
+  /// parallel make jobs, 0 is one per cpu
+  pub jobs: usize,
+}
+
+impl Default for BuildRecipe {
+  fn default() -> Self {
+    let step = |args: &[&str]| args.iter().map(|a| a.to_string()).collect();
+    Self {
+      steps: vec![
+        step(&["./configure", "--prefix={prefix}", "--enable-R-shlib"]),
+        step(&["make", "-j{jobs}"]),
+        step(&["make", "install"]),
+      ],
+      jobs: 0,
+    }
+  }
+}
+
+// commands run with the version active, {binary} is its R, {rscript} its Rscript, {home} the install and {version} the version.
+// the output of `version` has to say the version the install is, the other steps only have to exit 0
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SmokeTest {
+  /// empty to not compare versions
+  pub version: Vec<String>,
+  pub steps: Vec<Vec<String>>,
+  /// seconds a command gets before it counts as hung
+  pub timeout: u64,
+}
+
+impl Default for SmokeTest {
+  fn default() -> Self {
+    let step = |args: &[&str]| args.iter().map(|a| a.to_string()).collect();
+    Self {
+      version: step(&["{binary}", "--version"]),
+      steps: vec![step(&["{rscript}", "-e", "invisible(sessionInfo())"])],
+      timeout: 60,
+    }
+  }
+}
+
+// e.g. range: ^4.3 vars: {R_PROFILE_USER: "{libs_dir}/Rprofile"}, every entry whose range has the version applies, later ones win
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VersionEnv {
+  pub range: Range,
+  pub vars: BTreeMap<String, String>,
+}
+
+// e.g. from: https://cran.r-project.org/ to: https://cran.corp.example/cran/
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Mirror {
+  pub from: String,
+  pub to: String,
+}
+
+impl Mirror {
+  pub fn rewrite(mirrors: &[Mirror], url: &str) -> String {
+    mirrors.iter()
+      .find_map(|m| url.strip_prefix(m.from.as_str()).map(|rest| format!("{}{}", m.to, rest)))
+      .unwrap_or_else(|| url.to_owned())
+  }
+}
+
+impl Default for Config {
+  fn default() -> Self {
+    Self {
+      index_ttl: 60 * 60 * 24, // a day, R doesn't release that often
+      index_changelog: None,
+      proxy: None,
+      no_proxy: None,
+      mirrors: vec![],
+      backend: Backend::default(),
+      build: BuildRecipe::default(),
+      check: SmokeTest::default(),
+      auto_switch: true,
+      hook_budget_ms: 100,
+      assume_yes: false,
+      no_input: false,
+      hooks: Hooks::default(),
+      plugins: vec![],
+      credentials: vec![],
+      requests: RequestLimits::default(),
+      resolution: Source::default_order(),
+      no_project: NoProject::default(),
+      dirs: Dirs::default(),
+      color: ColorChoice::default(),
+      theme: ThemeConfig::default(),
+      stats: false,
+      audit: true,
+      commands: BTreeMap::new(),
+      tie_break: TieBreak::default(),
+      filters: vec![],
+      env: vec![],
+      max_minors_behind: None,
+      dedup: Dedup::default(),
+      protect: Protect::default(),
+      disk_budget_mb: 0,
+    }
+  }
+}
+
+// keys of top replace the ones in base, maps are merged key by key and anything else (lists too) replaced whole
+pub fn overlay(base: &mut serde_yaml::Value, top: serde_yaml::Value) {
+  use serde_yaml::Value;
+  match (base, top) {
+    (_, Value::Null) => {},
+    (Value::Mapping(base), Value::Mapping(top)) => {
+      for (key, value) in top {
+        match base.get_mut(&key) {
+          Some(existing) => overlay(existing, value),
+          None => { base.insert(key, value); },
+        }
+      }
+    },
+    (base, top) => *base = top,
+  }
+}
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+  #[error("error in reading config file")]
+  Io(#[from] std::io::Error),
+  #[error("error in parsing config file")]
+  Serde(#[from] serde_yaml::Error),
+}
+
+impl Config {
+  pub fn path() -> PathBuf {
+    config_dir().join("config.yaml")
+  }
+
+  /// The machine layer, what admins set for everyone
+  pub fn system_path() -> PathBuf {
+    system_home().join("config.yaml")
+  }
+
+  /// The files that make up the config, machine first
+  pub fn layers() -> Vec<PathBuf> {
+    let mut ret = vec![Self::system_path()];
+    if !is_system() && Self::path() != Self::system_path() {
+      ret.push(Self::path());
+    }
+    ret
+  }
+
+  pub fn load() -> Result<Self, ConfigError> {
+    let mut merged = serde_yaml::Value::Null;
+    for path in Self::layers().into_iter().filter(|p| p.exists()) {
+      let layer: serde_yaml::Value = serde_yaml::from_reader(File::open(path)?)?;
+      overlay(&mut merged, layer);
+    }
+    // an empty file deserializes to null, so treat it as default
+    let config: Option<Self> = serde_yaml::from_value(merged)?;
+    Ok(config.unwrap_or_default())
+  }
+
+  /// Loads the config, falling back to the defaults (with a warning) if it can't be read
+  pub fn get() -> Self {
+    Self::load().unwrap_or_else(|err| {
+      let paths: Vec<String> = Self::layers().iter().map(|p| p.display().to_string()).collect();
+      eprintln!("Failed to read config {}, using defaults: {}", paths.join(" or "), err);
+      Self::default()
+    })
+  }
+}
@@ -0,0 +1,175 @@
+use std::ops::Range as Span;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+use crate::import::TOOL_VERSIONS;
+use crate::parsing::version_parser::{ParseError, Range};
+use crate::resolve::{NONE_FILE, PROJECT_FILE};
+
+// the R constraint of a project file found by position, without reading the file into a struct and writing it back,
+// so changing it (`rvm local --bump minor`) keeps comments, key order and quoting the way they were
+//   Renv.yaml       the value of the top level rversion key, inside its quotes if it has them
+//   .tool-versions  the first version on the R line, the fallbacks after it are left alone
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileKind {
+  Project,
+  ToolVersions,
+}
+
+impl FileKind {
+  /// .tool-versions by its name, anything else is read like a Renv.yaml
+  pub fn of(path: &Path) -> Self {
+    match path.file_name() {
+      Some(name) if name == TOOL_VERSIONS => FileKind::ToolVersions,
+      _ => FileKind::Project,
+    }
+  }
+}
+
+/// Which part of a constraint's version `rvm local --bump` raises, the parts after it go back to 0
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Bump {
+  Major,
+  Minor,
+  Patch,
+}
+
+#[derive(Error, Debug)]
+pub enum ConstraintFileError {
+  #[error("error in reading/writing the file")]
+  Io(#[from] std::io::Error),
+  #[error("{} doesn't say which R it wants", .0.display())]
+  Missing(PathBuf),
+  #[error("{0} isn't a single version, only those can be bumped")]
+  NotBumpable(String),
+  #[error("{0} can't be bumped, it's at the biggest version there is")]
+  Overflow(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConstraintFile {
+  pub path: PathBuf,
+  pub kind: FileKind,
+  contents: String,
+  span: Span<usize>,
+}
+
+impl ConstraintFile {
+  pub fn load(path: &Path) -> Result<Self, ConstraintFileError> {
+    Self::parse(path, std::fs::read_to_string(path)?)
+  }
+
+  /// contents as the file at path, what it is by its name
+  pub fn parse(path: &Path, contents: String) -> Result<Self, ConstraintFileError> {
+    let kind = FileKind::of(path);
+    let span = match kind {
+      FileKind::Project => rversion_span(&contents),
+      FileKind::ToolVersions => tool_versions_span(&contents),
+    }.ok_or_else(|| ConstraintFileError::Missing(path.to_path_buf()))?;
+    Ok(Self { path: path.to_path_buf(), kind, contents, span })
+  }
+
+  /// The nearest Renv.yaml or .tool-versions from dir up, a Renv.yaml wins in the same dir, none past `rvm local --none`
+  pub fn find(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+      .take_while(|d| !d.join(NONE_FILE).is_file())
+      .flat_map(|d| [d.join(PROJECT_FILE), d.join(TOOL_VERSIONS)])
+      .find(|p| p.is_file())
+  }
+
+  /// Where the constraint is in contents, in bytes
+  pub fn span(&self) -> Span<usize> {
+    self.span.clone()
+  }
+
+  /// The constraint as written
+  pub fn constraint(&self) -> &str {
+    &self.contents[self.span.clone()]
+  }
+
+  /// The constraint as a range, a bare partial version is its series like on the command line
+  pub fn range(&self) -> Result<Range, ParseError> {
+    Range::from_request(self.constraint())
+  }
+
+  pub fn contents(&self) -> &str {
+    &self.contents
+  }
+
+  /// Replaces the constraint with constraint, the rest of contents stays byte for byte
+  pub fn rewrite(&mut self, constraint: &str) {
+    self.contents.replace_range(self.span.clone(), constraint);
+    self.span = self.span.start..self.span.start + constraint.len();
+  }
+
+  /// The constraint with part of its version raised, keeping its operator and how many parts it's written with:
+  /// ~4.2.1 bumped minor is ~4.3.0 and 4.2 is 4.3
+  pub fn bumped(&self, part: Bump) -> Result<String, ConstraintFileError> {
+    let constraint = self.constraint();
+    let not_bumpable = || ConstraintFileError::NotBumpable(constraint.to_owned());
+    let version_at = constraint.find(|c: char| c.is_ascii_digit()).ok_or_else(not_bumpable)?;
+    let (op, version) = constraint.split_at(version_at);
+    if !["", "=", "==", "~", "^", ">="].contains(&op.trim_end()) {
+      return Err(not_bumpable());
+    }
+    let mut parts: Vec<u32> = version.split('.').map(|p| p.parse().ok()).collect::<Option<_>>().ok_or_else(not_bumpable)?;
+    if parts.len() > 3 {
+      return Err(not_bumpable());
+    }
+    let at = match part {
+      Bump::Major => 0,
+      Bump::Minor => 1,
+      Bump::Patch => 2,
+    };
+    // a part that isn't written is bumped all the same, 4 bumped minor is 4.1
+    parts.resize(parts.len().max(at + 1), 0);
+    parts[at] = parts[at].checked_add(1).ok_or_else(|| ConstraintFileError::Overflow(constraint.to_owned()))?;
+    parts[at + 1..].iter_mut().for_each(|p| *p = 0);
+    let version: Vec<String> = parts.iter().map(u32::to_string).collect();
+    Ok(format!("{}{}", op, version.join(".")))
+  }
+
+  pub fn save(&self) -> Result<(), ConstraintFileError> {
+    std::fs::write(&self.path, &self.contents)?;
+    Ok(())
+  }
+}
+
+// lines of contents with where each starts
+fn lines(contents: &str) -> impl Iterator<Item = (usize, &str)> {
+  contents.split_inclusive('\n').scan(0, |start, line| {
+    let at = *start;
+    *start += line.len();
+    Some((at, line.trim_end_matches(['\n', '\r'])))
+  })
+}
+
+fn rversion_span(contents: &str) -> Option<Span<usize>> {
+  lines(contents).find_map(|(start, line)| {
+    let rest = line.strip_prefix("rversion")?.trim_start();
+    let rest = rest.strip_prefix(':')?;
+    let value_at = start + line.len() - rest.trim_start().len();
+    let value = rest.trim_start();
+    // a comment needs a space before its #
+    let value = value.find(" #").map_or(value, |at| &value[..at]).trim_end();
+    match value.chars().next() {
+      Some(quote @ ('"' | '\'')) if value.len() > 1 && value.ends_with(quote) => Some(value_at + 1..value_at + value.len() - 1),
+      Some(_) => Some(value_at..value_at + value.len()),
+      None => None,
+    }
+  })
+}
+
+fn tool_versions_span(contents: &str) -> Option<Span<usize>> {
+  lines(contents).find_map(|(start, line)| {
+    let line = line.split('#').next().unwrap_or_default();
+    let (tool, rest) = line.trim_start().split_once(char::is_whitespace)?;
+    if !tool.eq_ignore_ascii_case("r") {
+      return None;
+    }
+    let rest = rest.trim_start();
+    let version = rest.split_whitespace().next()?;
+    let at = start + line.len() - rest.len();
+    Some(at..at + version.len())
+  })
+}
@@ -0,0 +1,261 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use crate::cancel::CancellationToken;
+use crate::config::{Backend, Config};
+use crate::index::{newest_matching, remote_versions, ArtifactMeta, CachePolicy, CranSource};
+use crate::install::delta::{self, Kind};
+use crate::install::{installer_name, is_installed, verify_download, HttpDownloader, InstallError};
+use crate::layout::state_dir;
+use crate::parsing::yaml_ser::read_yaml;
+use crate::policy::Policy;
+use crate::resolve::{active_version, with_names, Active, ResolveError};
+use crate::utils::with_rvm_vars;
+
+// `rvm daemon`: a long running rvm the shell hooks ask instead of resolving themselves, that meanwhile keeps the
+// index cache fresh and downloads what the projects it was asked about will need into the kept artifacts (see
+// delta.rs), where installing takes them from instead of the network when they hash to what the index says.
+// it listens on daemon.sock in the state dir, a json query per line answered by a json line:
+//   {"query":"resolve","dir":"/home/me/proj","vars":{"RVM_SHELL_VERSION":"4.2"}}
+//   {"answer":"active","version":"4.2.3","range":"~4.2.0","origin":{"kind":"shell","from":"4.2"}}
+// vars are the client's RVM_* variables, the daemon resolves with those instead of its own.
+// a client that can't reach it, or gets an answer it can't use, resolves itself, so a stale or stuck daemon only
+// costs the timeout. unix only, elsewhere there's never a daemon to ask
+
+// how long a client waits for an answer, and the daemon for a query
+const TIMEOUT: Duration = Duration::from_millis(500);
+// how often the daemon looks for a query when there's none
+const POLL: Duration = Duration::from_millis(20);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "query", rename_all = "kebab-case")]
+pub enum Query {
+  Ping,
+  /// the active version of dir
+  Resolve {
+    dir: PathBuf,
+    #[serde(default)]
+    vars: BTreeMap<String, String>,
+  },
+  Stop,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "answer", rename_all = "kebab-case")]
+pub enum Answer {
+  /// to a ping, with how many project files it prefetches for
+  Pong { pid: u32, projects: usize },
+  Active(Box<Active>),
+  /// the system R is selected
+  System,
+  /// resolving failed, the client resolves itself to get the error
+  Failed { error: String },
+  Stopping,
+}
+
+pub fn socket_path() -> PathBuf {
+  state_dir().join("daemon.sock")
+}
+
+/// What the daemon answers to query, none when there's no daemon or it didn't answer in time
+#[cfg(unix)]
+pub fn ask(query: &Query) -> Option<Answer> {
+  use std::os::unix::net::UnixStream;
+  let mut stream = UnixStream::connect(socket_path()).ok()?;
+  stream.set_read_timeout(Some(TIMEOUT)).ok()?;
+  stream.set_write_timeout(Some(TIMEOUT)).ok()?;
+  let mut line = serde_json::to_string(query).ok()?;
+  line.push('\n');
+  stream.write_all(line.as_bytes()).ok()?;
+  let mut answer = String::new();
+  BufReader::new(stream).read_line(&mut answer).ok()?;
+  serde_json::from_str(&answer)
+    .map_err(|err| tracing::debug!(error = %err, "can't read the daemon's answer"))
+    .ok()
+}
+
+#[cfg(not(unix))]
+pub fn ask(_query: &Query) -> Option<Answer> {
+  None
+}
+
+/// The active version of dir as the daemon resolves it with this process' RVM_* variables, none when it can't say
+pub fn resolve(dir: &Path) -> Option<Result<Active, ResolveError>> {
+  let vars = std::env::vars().filter(|(name, _)| name.starts_with("RVM_")).collect();
+  match ask(&Query::Resolve { dir: dir.to_path_buf(), vars })? {
+    Answer::Active(active) => Some(Ok(*active)),
+    Answer::System => Some(Err(ResolveError::System)),
+    answer => {
+      tracing::debug!(?answer, "resolving without the daemon");
+      None
+    },
+  }
+}
+
+/// Answers queries on socket_path until a stop query, prefetching for the project files it was asked about as
+/// they come and for all of them every interval
+#[cfg(unix)]
+pub fn serve(interval: Duration) -> io::Result<()> {
+  use std::os::unix::net::UnixListener;
+  use std::time::Instant;
+  if let Some(Answer::Pong { pid, .. }) = ask(&Query::Ping) {
+    return Err(io::Error::new(io::ErrorKind::AddrInUse, format!("another daemon (pid {}) is running", pid)));
+  }
+  let path = socket_path();
+  // one a daemon that died left
+  let _ = std::fs::remove_file(&path);
+  std::fs::create_dir_all(state_dir())?;
+  let listener = UnixListener::bind(&path)?;
+  listener.set_nonblocking(true)?;
+  tracing::info!(socket = %path.display(), pid = std::process::id(), "daemon listening");
+  let mut daemon = Daemon::default();
+  // downloads take long, they're done on a thread of their own so queries are answered meanwhile
+  let (jobs, queue) = mpsc::channel();
+  std::thread::spawn(move || prefetching(queue));
+  let mut next_refresh = Instant::now();
+  loop {
+    match listener.accept() {
+      Ok((stream, _)) => {
+        if !daemon.answer(stream) {
+          break;
+        }
+      },
+      Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+        if Instant::now() >= next_refresh {
+          daemon.pending.clear();
+          let _ = jobs.send((daemon.projects.clone(), CachePolicy::Refresh));
+          next_refresh = Instant::now() + interval;
+        } else if !daemon.pending.is_empty() {
+          let _ = jobs.send((std::mem::take(&mut daemon.pending), CachePolicy::Default));
+        }
+        std::thread::sleep(POLL);
+      },
+      Err(err) => tracing::warn!(error = %err, "can't accept a query"),
+    }
+  }
+  tracing::info!("daemon stopping");
+  std::fs::remove_file(&path)
+}
+
+#[cfg(not(unix))]
+pub fn serve(_interval: Duration) -> io::Result<()> {
+  Err(io::Error::new(io::ErrorKind::Unsupported, "the daemon only runs on unix"))
+}
+
+#[derive(Default)]
+struct Daemon {
+  /// every project file it was asked about
+  projects: BTreeSet<PathBuf>,
+  /// the ones not prefetched for yet
+  pending: BTreeSet<PathBuf>,
+}
+
+impl Daemon {
+  // false when it's to stop
+  #[cfg(unix)]
+  fn answer(&mut self, stream: std::os::unix::net::UnixStream) -> bool {
+    // the listener's non blocking, the connection isn't
+    if let Err(err) = stream.set_nonblocking(false).and_then(|_| stream.set_read_timeout(Some(TIMEOUT))) {
+      tracing::debug!(error = %err, "can't set up the connection");
+      return true;
+    }
+    let mut line = String::new();
+    if let Err(err) = BufReader::new(&stream).read_line(&mut line) {
+      tracing::debug!(error = %err, "can't read the query");
+      return true;
+    }
+    let (answer, go_on) = match serde_json::from_str::<Query>(&line) {
+      Ok(Query::Ping) => (Answer::Pong { pid: std::process::id(), projects: self.projects.len() }, true),
+      Ok(Query::Stop) => (Answer::Stopping, false),
+      Ok(Query::Resolve { dir, vars }) => (self.resolve(&dir, &vars), true),
+      Err(err) => (Answer::Failed { error: format!("not a query: {}", err) }, true),
+    };
+    let mut text = serde_json::to_string(&answer).unwrap_or_else(|err| panic!("Failed to serialize the answer: {}", err));
+    text.push('\n');
+    if let Err(err) = (&stream).write_all(text.as_bytes()) {
+      tracing::debug!(error = %err, "can't answer");
+    }
+    go_on
+  }
+
+  fn resolve(&mut self, dir: &Path, vars: &BTreeMap<String, String>) -> Answer {
+    // the client's variables for this thread only, the prefetching one keeps reading the daemon's
+    let resolved = with_rvm_vars(vars, || active_version(None, dir));
+    // a project whose range nothing installed matches is the one a download helps most
+    let project = match &resolved {
+      Ok(active) => active.origin.project_file(),
+      Err(ResolveError::NotInstalled(_, origin)) => origin.project_file(),
+      Err(_) => None,
+    };
+    if let Some(project) = project.filter(|p| !self.projects.contains(p)) {
+      self.projects.insert(project.clone());
+      self.pending.insert(project);
+    }
+    match resolved {
+      Ok(active) => Answer::Active(Box::new(active)),
+      Err(ResolveError::System) => Answer::System,
+      Err(err) => Answer::Failed { error: err.to_string() },
+    }
+  }
+}
+
+// prefetches what's sent until the daemon stops, what was sent while one ran is done together in the next
+fn prefetching(queue: Receiver<(BTreeSet<PathBuf>, CachePolicy)>) {
+  while let Ok((mut projects, mut policy)) = queue.recv() {
+    for (more, more_policy) in queue.try_iter() {
+      projects.extend(more);
+      if more_policy == CachePolicy::Refresh {
+        policy = CachePolicy::Refresh;
+      }
+    }
+    prefetch(&projects, policy);
+  }
+}
+
+// downloads the newest remote version each of projects' rversion matches when it isn't installed, the index
+// refreshed according to policy first
+fn prefetch(projects: &BTreeSet<PathBuf>, policy: CachePolicy) {
+  if projects.is_empty() {
+    return;
+  }
+  let entries = match remote_versions(&CranSource::configured(), policy) {
+    Ok(entries) => entries,
+    Err(err) => return tracing::warn!(error = %err, "can't refresh the index"),
+  };
+  for project in projects {
+    // a project on the system R doesn't read, there's nothing to get for it
+    let Ok(env) = read_yaml(project.clone()) else { continue };
+    let Ok(range) = with_names(env.rversion) else { continue };
+    let Some(meta) = newest_matching(&range, &entries).filter(|meta| !is_installed(&meta.version)) else { continue };
+    match download(meta) {
+      Ok(true) => tracing::info!(version = %meta.version, project = %project.display(), "prefetched"),
+      Ok(false) => {},
+      Err(err) => tracing::warn!(version = %meta.version, error = %err, "can't prefetch"),
+    }
+  }
+}
+
+// meta's artifact for the configured backend into the kept ones, false when it's there already or there's none
+fn download(meta: &ArtifactMeta) -> Result<bool, InstallError> {
+  let (kind, url, filename, expected) = match (Config::get().backend, &meta.source_url) {
+    (Backend::Binary, _) => (Kind::Installer, &meta.url, installer_name(&meta.version), meta.sha256.as_deref()),
+    (Backend::Source, Some(url)) => {
+      let filename = format!("R-{}.{}", meta.version, meta.source_format().extension());
+      (Kind::Source, url, filename, meta.source_sha256.as_deref())
+    },
+    (Backend::Source, None) => return Ok(false),
+  };
+  if delta::kept(&meta.version, kind).is_file() {
+    return Ok(false);
+  }
+  Policy::get().check(&meta.version, Some(url))?;
+  // stopping the daemon stops it, it has nothing to roll back
+  let path = delta::download(meta, kind, url, &filename, &HttpDownloader, &|_, _| {}, &CancellationToken::new())?;
+  verify_download(url, &path, expected)?;
+  delta::keep(&meta.version, kind, &path)?;
+  let _ = std::fs::remove_file(&path);
+  Ok(true)
+}
@@ -0,0 +1,273 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use lazy_static::lazy_static;
+use serde::Serialize;
+use crate::parsing::version_parser::{ParseError, Range, Syntax};
+
+// `rvm detect`, the runtime constraints other ecosystems' manifests already have: R's in a package's DESCRIPTION,
+// ruby's in a Gemfile, python's in pyproject.toml and node's in package.json's engines. each manifest has a
+// Detector that finds the constraint as written, which is then translated from its ecosystem's syntax into an rvm
+// range. R's becomes the project's rversion and the others its tools. code embedding rvm can register its own
+
+/// Finds one tool's constraint in one manifest
+pub trait Detector: Send + Sync {
+  /// the manifest, a file name in the project dir
+  fn file(&self) -> &str;
+  /// what the constraint is for, R or a plugin's tool
+  fn tool(&self) -> &str;
+  /// how the manifest writes constraints
+  fn syntax(&self) -> Syntax;
+  /// the constraint as the manifest has it, none when it doesn't have one
+  fn constraint(&self, contents: &str) -> Option<String>;
+}
+
+lazy_static!{
+  static ref DETECTORS: RwLock<Vec<Arc<dyn Detector>>> = RwLock::new(vec![
+    Arc::new(Description), Arc::new(Gemfile), Arc::new(PyProject), Arc::new(PackageJson),
+  ]);
+}
+
+/// Adds detector after the built in ones, it stays registered until the process ends
+pub fn register(detector: impl Detector + 'static) {
+  DETECTORS.write().unwrap_or_else(|e| e.into_inner()).push(Arc::new(detector));
+}
+
+/// A constraint found in a manifest
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Detected {
+  pub file: PathBuf,
+  pub tool: String,
+  /// as the manifest writes it
+  pub constraint: String,
+  /// as rvm writes it, none when it can't be translated, error says why
+  pub range: Option<Range>,
+  pub error: Option<String>,
+}
+
+/// What every detector finds in dir, in the order they're registered
+pub fn detect(dir: &Path) -> Vec<Detected> {
+  let detectors: Vec<Arc<dyn Detector>> = DETECTORS.read().unwrap_or_else(|e| e.into_inner()).clone();
+  detectors.iter()
+    .filter_map(|detector| {
+      let file = dir.join(detector.file());
+      let contents = std::fs::read_to_string(&file).ok()?;
+      let constraint = detector.constraint(&contents)?;
+      let (range, error) = match translate(&constraint, detector.syntax()) {
+        Ok(range) => (Some(range), None),
+        Err(err) => (None, Some(err)),
+      };
+      Some(Detected { file, tool: detector.tool().to_owned(), constraint, range, error })
+    })
+    .collect()
+}
+
+/// constraint, written in syntax, as an rvm range
+pub fn translate(constraint: &str, syntax: Syntax) -> Result<Range, String> {
+  if !syntax.is_supported() {
+    return Err(ParseError::Unsupported(syntax).to_string());
+  }
+  let comparators: Vec<String> = match syntax {
+    Syntax::Npm => {
+      if constraint.contains("||") {
+        return Err("rvm ranges can't have ||, only one of the alternatives can be used".to_owned());
+      }
+      match constraint.split_once(" - ") {
+        Some((from, to)) => std::iter::once(format!(">={}", from.trim())).chain(hyphen_upper(to.trim())).collect(),
+        None => npm_tokens(constraint).iter().map(|c| npm_comparator(c)).collect(),
+      }
+    },
+    Syntax::Pep440 | Syntax::Ruby | Syntax::Cargo => constraint.split(',')
+      .map(|c| c.split_whitespace().collect::<String>())
+      .filter(|c| !c.is_empty())
+      .map(|c| listed_comparator(&c))
+      .collect(),
+  };
+  let range = comparators.join(", ");
+  range.parse::<Range>().map_err(|err| format!("{} in {} (from {})", err, range, constraint))
+}
+
+// the comparators of an npm range, an operator written apart from its version (>= 18) goes with it
+fn npm_tokens(constraint: &str) -> Vec<String> {
+  let mut tokens: Vec<String> = vec![];
+  let mut pending = String::new();
+  for token in constraint.split_whitespace() {
+    pending.push_str(token);
+    if !token.chars().all(|c| matches!(c, '<' | '>' | '=' | '~' | '^')) {
+      tokens.push(std::mem::take(&mut pending));
+    }
+  }
+  if !pending.is_empty() {
+    tokens.push(pending);
+  }
+  tokens
+}
+
+// the upper end of an npm hyphen range, a partial one takes in all of what it leaves out: 16 - 18.2 is <18.3.0,
+// none for 16 - *
+fn hyphen_upper(to: &str) -> Option<String> {
+  let parts: Vec<&str> = to.split('.').take_while(|p| !matches!(*p, "*" | "x" | "X" | "")).collect();
+  match parts.as_slice() {
+    [] => None,
+    [major] => Some(format!("<{}", major.parse::<u64>().map_or(0, |n| n + 1))),
+    [major, minor] => Some(format!("<{}.{}", major, minor.parse::<u64>().map_or(0, |n| n + 1))),
+    _ => Some(format!("<={}", to)),
+  }
+}
+
+// npm's bare partial versions and x's are ranges, 18 is 18.x
+fn npm_comparator(comparator: &str) -> String {
+  let op_len = comparator.find(|c: char| c.is_ascii_digit() || c == 'x' || c == 'X' || c == '*').unwrap_or(comparator.len());
+  let (op, version) = comparator.split_at(op_len);
+  let op = op.trim_end_matches('v');
+  if op.is_empty() || op == "=" {
+    wildcard(version)
+  } else {
+    format!("{}{}", op, version)
+  }
+}
+
+// one comparator of a PEP 440, Gemfile, Cargo or DESCRIPTION list, spaces already taken out
+fn listed_comparator(comparator: &str) -> String {
+  if let Some(version) = comparator.strip_prefix("~>").or_else(|| comparator.strip_prefix("~=")) {
+    return compatible(version);
+  }
+  let version = comparator.trim_start_matches(['=', '<', '>', '!', '^', '~']);
+  let op = &comparator[..comparator.len() - version.len()];
+  match op {
+    "" | "=" | "==" | "===" if version.contains(['*', 'x', 'X']) => wildcard(version),
+    "" | "=" | "==" | "===" => format!("={}", version),
+    op => format!("{}{}", op, version),
+  }
+}
+
+// ~> 3.2 and ~= 3.2 allow what only changes the last part given and what's after it, >=3.2, <4
+fn compatible(version: &str) -> String {
+  let mut parts: Vec<&str> = version.split('.').collect();
+  if parts.len() > 1 {
+    parts.pop();
+  }
+  let last = parts.pop().unwrap_or_default();
+  let next = last.parse::<u64>().map_or_else(|_| last.to_owned(), |n| (n + 1).to_string());
+  parts.push(&next);
+  format!(">={}, <{}", version, parts.join("."))
+}
+
+// 3.11.*, 3.11.x and a bare 3.11 are every 3.11, * alone is anything
+fn wildcard(version: &str) -> String {
+  let parts: Vec<&str> = version.split('.').take_while(|p| !matches!(*p, "*" | "x" | "X" | "")).collect();
+  match parts.as_slice() {
+    [] => "*".to_owned(),
+    [major] => format!(">={}, <{}", major, major.parse::<u64>().map_or(0, |n| n + 1)),
+    [major, minor] => format!(">={}.{}, <{}.{}", major, minor, major, minor.parse::<u64>().map_or(0, |n| n + 1)),
+    _ => format!("={}", parts.join(".")),
+  }
+}
+
+/// A package's `Depends: R (>= 4.1.0)`
+pub struct Description;
+
+impl Detector for Description {
+  fn file(&self) -> &str {
+    "DESCRIPTION"
+  }
+
+  fn tool(&self) -> &str {
+    "R"
+  }
+
+  // Cargo's operators with a space after them, which translating takes out
+  fn syntax(&self) -> Syntax {
+    Syntax::Cargo
+  }
+
+  fn constraint(&self, contents: &str) -> Option<String> {
+    // a field goes on in the lines after it that start with a space
+    let mut depends = String::new();
+    let mut lines = contents.lines().skip_while(|line| !line.starts_with("Depends:"));
+    depends.push_str(lines.next()?.trim_start_matches("Depends:"));
+    for line in lines.take_while(|line| line.starts_with([' ', '\t'])) {
+      depends.push(' ');
+      depends.push_str(line.trim());
+    }
+    depends.split(',')
+      .map(str::trim)
+      .find(|dep| dep.strip_prefix('R').is_some_and(|rest| rest.is_empty() || rest.starts_with([' ', '('])))
+      .and_then(|dep| Some(dep.split_once('(')?.1.trim_end_matches(')').trim().to_owned()))
+  }
+}
+
+/// A Gemfile's `ruby "~> 3.2"`, not the ones reading .ruby-version
+pub struct Gemfile;
+
+impl Detector for Gemfile {
+  fn file(&self) -> &str {
+    "Gemfile"
+  }
+
+  fn tool(&self) -> &str {
+    "ruby"
+  }
+
+  fn syntax(&self) -> Syntax {
+    Syntax::Ruby
+  }
+
+  fn constraint(&self, contents: &str) -> Option<String> {
+    let line = contents.lines()
+      .map(|line| line.split('#').next().unwrap_or_default().trim())
+      .find(|line| line.strip_prefix("ruby").is_some_and(|rest| rest.starts_with([' ', '('])))?;
+    if line.contains("file:") {
+      return None;
+    }
+    // every quoted argument is a requirement
+    let quoted: Vec<&str> = line.split(['"', '\'']).skip(1).step_by(2).collect();
+    (!quoted.is_empty()).then(|| quoted.join(", "))
+  }
+}
+
+/// pyproject.toml's `requires-python`, or poetry's python dependency
+pub struct PyProject;
+
+impl Detector for PyProject {
+  fn file(&self) -> &str {
+    "pyproject.toml"
+  }
+
+  fn tool(&self) -> &str {
+    "python"
+  }
+
+  fn syntax(&self) -> Syntax {
+    Syntax::Pep440
+  }
+
+  fn constraint(&self, contents: &str) -> Option<String> {
+    let value: toml::Value = toml::from_str(contents).ok()?;
+    value.get("project").and_then(|p| p.get("requires-python"))
+      .or_else(|| value.get("tool")?.get("poetry")?.get("dependencies")?.get("python"))
+      .and_then(|v| v.as_str())
+      .map(str::to_owned)
+  }
+}
+
+/// package.json's `"engines": {"node": ">=18"}`
+pub struct PackageJson;
+
+impl Detector for PackageJson {
+  fn file(&self) -> &str {
+    "package.json"
+  }
+
+  fn tool(&self) -> &str {
+    "node"
+  }
+
+  fn syntax(&self) -> Syntax {
+    Syntax::Npm
+  }
+
+  fn constraint(&self, contents: &str) -> Option<String> {
+    let value: serde_json::Value = serde_json::from_str(contents).ok()?;
+    value.get("engines")?.get("node")?.as_str().map(str::to_owned)
+  }
+}
@@ -0,0 +1,243 @@
+use crate::alias::AliasError;
+use crate::config::ConfigError;
+use crate::constraint_file::ConstraintFileError;
+use crate::filelock::LockError;
+use crate::hooks::HookError;
+use crate::index::IndexError;
+use crate::install::InstallError;
+use crate::lockfile::LockfileError;
+use crate::manifest::ManifestError;
+use crate::parsing::operators::OperatorError;
+use crate::parsing::version_parser::{ParseError, RenderError};
+use crate::parsing::yaml_ser::YamlError;
+use crate::plugin::PluginError;
+use crate::policy::Denied;
+use crate::resolve::ResolveError;
+use crate::trust::TrustError;
+
+// stable names for what went wrong, so code using rvm matches on those instead of on messages, which change:
+// every error of the library has one (ErrorCode), and the cli puts it in the `code` of a --json failure.
+// a code is never renamed or given another meaning once it's out, new ones are only added.
+// an error wrapping another (an install's policy refusal, a resolve's broken lockfile) has the inner one's code
+// when that says more
+
+/// Every code and what it means, the cli's by exit code too (for failures that aren't one of these errors)
+pub const CODES: [(&str, &str); 46] = [
+  ("E_PARSE_VERSION", "a version doesn't parse"),
+  ("E_PARSE_RANGE", "a range or request doesn't parse"),
+  ("E_UNKNOWN_NAME", "a name in a range isn't a version, alias or channel"),
+  ("E_RENDER", "a range can't be written in the syntax asked for"),
+  ("E_UNSUPPORTED_SYNTAX", "a range is in a syntax rvm was built without"),
+  ("E_OPERATOR", "a custom operator can't be registered"),
+  ("E_NO_MATCH", "no installed or remote version matches"),
+  ("E_NOTHING_INSTALLED", "no version is installed at all"),
+  ("E_UNRESOLVED", "nothing in the resolution order set a version"),
+  ("E_SYSTEM", "the system R rvm doesn't manage is selected"),
+  ("E_NO_PROJECT", "there's no project file and no_project in the config is error"),
+  ("E_BAD_DAY", "a day in a range isn't YYYY-MM-DD"),
+  ("E_UNDATED", "a range has released-before or released-after and the index has no release days"),
+  ("E_WARNINGS", "resolving warned with --deny-warnings"),
+  ("E_DENIED", "the machine's policy refuses the version or registry"),
+  ("E_PROJECT", "a project file can't be read"),
+  ("E_LOCKFILE", "a lockfile can't be read or written"),
+  ("E_DRIFT", "a lockfile doesn't agree with its project or install"),
+  ("E_INDEX", "the index or its cache can't be read"),
+  ("E_KEY_CHANGED", "a registry's signing key isn't the one it was first seen with"),
+  ("E_SIGNATURE", "a registry's index isn't signed, or not by the key it has"),
+  ("E_INDEX_SCHEMA", "the index needs a newer rvm"),
+  ("E_NETWORK", "a request failed or answered with an error status"),
+  ("E_OFFLINE", "the network was needed while offline, or there's no cache to use"),
+  ("E_CHECKSUM", "a download doesn't hash to what the index says"),
+  ("E_DIGEST", "a digest's algorithm is unknown or too weak"),
+  ("E_PINNED", "the version is pinned"),
+  ("E_ALREADY_INSTALLED", "the version is already installed"),
+  ("E_INSTALL", "installing, unpacking or relocating failed"),
+  ("E_BUILD", "building from source failed"),
+  ("E_BUSY", "another rvm held the install lock too long"),
+  ("E_LOCK", "the install lock can't be taken"),
+  ("E_HOOK", "a hook failed"),
+  ("E_PLUGIN", "a plugin failed"),
+  ("E_PLUGIN_NOT_FOUND", "there's no such plugin"),
+  ("E_CONSTRAINT_FILE", "a project's constraint can't be read or changed"),
+  ("E_ALIAS", "the aliases can't be read, written or set"),
+  ("E_MANIFEST", "a manifest can't be read or written"),
+  ("E_CONFIG", "the config can't be read"),
+  ("E_IO", "a file can't be read or written"),
+  ("E_FAILURE", "anything else"),
+  ("E_USAGE", "the arguments are wrong"),
+  ("E_VERIFICATION", "an install isn't what was recorded"),
+  ("E_ABORTED", "a confirmation was answered no, or a pre hook failed"),
+  ("E_CANCELLED", "a download, build or verification was stopped with Ctrl-C"),
+  ("E_FORMAT", "a file is from a newer rvm"),
+];
+
+/// The stable code of an error, one of CODES
+pub trait ErrorCode {
+  fn code(&self) -> &'static str;
+}
+
+impl ErrorCode for ParseError {
+  fn code(&self) -> &'static str {
+    match self {
+      ParseError::InvalidVersion | ParseError::InvalidUtf8 => "E_PARSE_VERSION",
+      ParseError::UnknownName(_) => "E_UNKNOWN_NAME",
+      ParseError::Unsupported(_) => "E_UNSUPPORTED_SYNTAX",
+      ParseError::InvalidRange | ParseError::Overflow | ParseError::TooLong | ParseError::MissingOperator => "E_PARSE_RANGE",
+    }
+  }
+}
+
+impl ErrorCode for RenderError {
+  fn code(&self) -> &'static str {
+    match self {
+      RenderError::Lossy(..) => "E_RENDER",
+      RenderError::Unsupported(_) => "E_UNSUPPORTED_SYNTAX",
+    }
+  }
+}
+
+impl ErrorCode for OperatorError {
+  fn code(&self) -> &'static str {
+    "E_OPERATOR"
+  }
+}
+
+impl ErrorCode for IndexError {
+  fn code(&self) -> &'static str {
+    match self {
+      IndexError::Reqwest(_) | IndexError::Status(_) => "E_NETWORK",
+      IndexError::NoCache(_) => "E_OFFLINE",
+      IndexError::RequiresRvm(..) | IndexError::UnknownSchema(..) => "E_INDEX_SCHEMA",
+      IndexError::Io(_) | IndexError::Serde(_) | IndexError::Json(_) | IndexError::Toml(_) => "E_INDEX",
+      IndexError::Trust(err) => err.code(),
+    }
+  }
+}
+
+impl ErrorCode for TrustError {
+  fn code(&self) -> &'static str {
+    match self {
+      TrustError::Io(_) => "E_INDEX",
+      TrustError::KeyChanged { .. } => "E_KEY_CHANGED",
+      TrustError::Unsigned { .. } | TrustError::BadSignature { .. } => "E_SIGNATURE",
+    }
+  }
+}
+
+impl ErrorCode for ResolveError {
+  fn code(&self) -> &'static str {
+    match self {
+      ResolveError::Parse(err) => err.code(),
+      ResolveError::Project(..) => "E_PROJECT",
+      ResolveError::NotInstalled(..) | ResolveError::NoKeywordMatch(..) => "E_NO_MATCH",
+      ResolveError::NothingInstalled => "E_NOTHING_INSTALLED",
+      ResolveError::Lockfile(_, err) => err.code(),
+      ResolveError::Drift(_) => "E_DRIFT",
+      ResolveError::Unresolved => "E_UNRESOLVED",
+      ResolveError::Index(err) => err.code(),
+      ResolveError::BadDay(_) => "E_BAD_DAY",
+      ResolveError::Undated(_) => "E_UNDATED",
+      ResolveError::System => "E_SYSTEM",
+      ResolveError::NoProject(_) => "E_NO_PROJECT",
+      ResolveError::Warnings(_) => "E_WARNINGS",
+      ResolveError::Denied(err) => err.code(),
+    }
+  }
+}
+
+impl ErrorCode for InstallError {
+  fn code(&self) -> &'static str {
+    match self {
+      InstallError::Status(..) | InstallError::Reqwest(_) | InstallError::Download(_) => "E_NETWORK",
+      InstallError::Offline(_) => "E_OFFLINE",
+      InstallError::Io(_) | InstallError::Unpack(..) | InstallError::Relocate(_) | InstallError::Verify(_) | InstallError::Startup(..) => "E_INSTALL",
+      InstallError::Build(..) => "E_BUILD",
+      InstallError::Pinned(_) => "E_PINNED",
+      InstallError::AlreadyInstalled(..) => "E_ALREADY_INSTALLED",
+      InstallError::Checksum(..) => "E_CHECKSUM",
+      InstallError::UnsupportedDigest(..) | InstallError::WeakDigest(..) => "E_DIGEST",
+      InstallError::Denied(err) => err.code(),
+      InstallError::Hook(err) => err.code(),
+      InstallError::Cancelled => "E_CANCELLED",
+    }
+  }
+}
+
+impl ErrorCode for Denied {
+  fn code(&self) -> &'static str {
+    "E_DENIED"
+  }
+}
+
+impl ErrorCode for LockError {
+  fn code(&self) -> &'static str {
+    match self {
+      LockError::Timeout(..) => "E_BUSY",
+      LockError::Io(_) => "E_LOCK",
+    }
+  }
+}
+
+impl ErrorCode for LockfileError {
+  fn code(&self) -> &'static str {
+    match self {
+      LockfileError::Format(_) => "E_FORMAT",
+      LockfileError::Io(_) | LockfileError::De(_) | LockfileError::Ser(_) => "E_LOCKFILE",
+    }
+  }
+}
+
+impl ErrorCode for HookError {
+  fn code(&self) -> &'static str {
+    "E_HOOK"
+  }
+}
+
+impl ErrorCode for PluginError {
+  fn code(&self) -> &'static str {
+    match self {
+      PluginError::NotFound(_) => "E_PLUGIN_NOT_FOUND",
+      PluginError::Io(_) | PluginError::Failed(..) | PluginError::Json(_) => "E_PLUGIN",
+    }
+  }
+}
+
+impl ErrorCode for ConstraintFileError {
+  fn code(&self) -> &'static str {
+    match self {
+      ConstraintFileError::Io(_) => "E_IO",
+      ConstraintFileError::Missing(_) | ConstraintFileError::NotBumpable(_) | ConstraintFileError::Overflow(_) => "E_CONSTRAINT_FILE",
+    }
+  }
+}
+
+impl ErrorCode for AliasError {
+  fn code(&self) -> &'static str {
+    match self {
+      AliasError::InvalidTarget(err) => err.code(),
+      AliasError::Io(_) | AliasError::De(_) | AliasError::Ser(_) | AliasError::InvalidName(_) => "E_ALIAS",
+    }
+  }
+}
+
+impl ErrorCode for ManifestError {
+  fn code(&self) -> &'static str {
+    match self {
+      ManifestError::Aliases(err) => err.code(),
+      ManifestError::Format(_) => "E_FORMAT",
+      ManifestError::Io(_) | ManifestError::De(_) | ManifestError::Ser(_) => "E_MANIFEST",
+    }
+  }
+}
+
+impl ErrorCode for YamlError {
+  fn code(&self) -> &'static str {
+    "E_PROJECT"
+  }
+}
+
+impl ErrorCode for ConfigError {
+  fn code(&self) -> &'static str {
+    "E_CONFIG"
+  }
+}
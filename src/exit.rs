@@ -0,0 +1,207 @@
+use std::fmt::Display;
+use std::panic::{self, AssertUnwindSafe};
+use serde::Serialize;
+use crate::constraint_file::ConstraintFileError;
+use crate::error_code::ErrorCode;
+use crate::filelock::LockError;
+use crate::index::IndexError;
+use crate::install::InstallError;
+use crate::parsing::version_parser::ParseError;
+use crate::trust::TrustError;
+use crate::plugin::PluginError;
+use crate::output::print_json;
+use crate::resolve::ResolveError;
+
+/// What rvm exits with, so scripts can tell failures apart, the end of `rvm --help`
+pub const EXIT_CODES: &str = "\
+Exit codes:
+  0  it worked
+  1  anything not below (a file that can't be written, a build that failed...)
+  2  usage: arguments rvm rejects, or a version, range or alias that doesn't parse
+  3  no match: nothing installed or remote satisfies the request, or nothing sets a version
+  4  network: a registry or download failed or answered with an error status, or --offline needed one
+  5  verification: a checksum didn't match, `rvm verify` found changes or `rvm check` a broken install
+  6  aborted: a confirmation was answered no or couldn't be asked, or a pre hook failed
+  7  busy: another rvm held the install lock for longer than --wait-timeout
+  8  denied: the machine's policy refuses the version or where it would come from
+Commands that answer a question keep their own codes: satisfies and which exit 1 for no, diff 1 for a difference,
+compare with how the two versions order, exec and try with the command's code and each 1 when a command failed";
+
+// the codes above are Exit's, denied's policy is policy.rs'.
+// a failing command prints why on stderr and ends with the code, a failure that isn't one of these is a bug and exits 1.
+// with --json it also prints {"command": "error", "data": {"code", "exit", "message"}} on stdout, code being the
+// error's stable one (see error_code.rs) or the exit code's name when the failure isn't one of the library's errors
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Exit {
+  Failure = 1,
+  Usage = 2,
+  NoMatch = 3,
+  Network = 4,
+  Verification = 5,
+  Aborted = 6,
+  Busy = 7,
+  Denied = 8,
+}
+
+impl Exit {
+  pub fn code(self) -> i32 {
+    self as i32
+  }
+
+  /// The error code of a failure that only has an exit code, one of error_code::CODES
+  pub fn name(self) -> &'static str {
+    match self {
+      Exit::Failure => "E_FAILURE",
+      Exit::Usage => "E_USAGE",
+      Exit::NoMatch => "E_NO_MATCH",
+      Exit::Network => "E_NETWORK",
+      Exit::Verification => "E_VERIFICATION",
+      Exit::Aborted => "E_ABORTED",
+      Exit::Busy => "E_BUSY",
+      Exit::Denied => "E_DENIED",
+    }
+  }
+}
+
+/// What a fail() panics with, unwinding (and dropping locks and staging dirs) up to run
+#[derive(Serialize)]
+struct Failure {
+  code: &'static str,
+  #[serde(serialize_with = "exit_code")]
+  exit: Exit,
+  message: String,
+}
+
+fn exit_code<S: serde::Serializer>(exit: &Exit, serializer: S) -> Result<S::Ok, S::Error> {
+  serializer.serialize_i32(exit.code())
+}
+
+/// Ends the command with message and exit's code
+pub fn fail(exit: Exit, message: impl Display) -> ! {
+  fail_coded(exit, exit.name(), message)
+}
+
+/// Same as fail, with the error's own code for --json
+pub fn fail_coded(exit: Exit, code: &'static str, message: impl Display) -> ! {
+  panic::panic_any(Failure { code, exit, message: message.to_string() })
+}
+
+/// Which code an error ends rvm with
+pub trait Classify {
+  fn exit(&self) -> Exit;
+}
+
+/// `.or_exit("Failed to list remote versions")` in place of an unwrap_or_else(|err| panic!(...))
+pub trait OrExit<T> {
+  /// The value, or fail with the error's code and "<what>: <error>"
+  fn or_exit(self, what: impl Display) -> T;
+}
+
+impl<T, E: Classify + ErrorCode + Display> OrExit<T> for Result<T, E> {
+  fn or_exit(self, what: impl Display) -> T {
+    self.unwrap_or_else(|err| fail_coded(err.exit(), err.code(), format!("{}: {}", what, err)))
+  }
+}
+
+/// Runs a part of a command that goes on when it fails (one of the series --each-minor upgrades), the exit it failed
+/// with when it did. why was already printed, the command fails after the rest with fail()
+pub fn attempt(part: impl FnOnce()) -> Result<(), Exit> {
+  panic::catch_unwind(AssertUnwindSafe(part))
+    .map_err(|payload| payload.downcast_ref::<Failure>().map_or(Exit::Failure, |failure| failure.exit))
+}
+
+/// Runs the command and returns the code to exit with, see the top of this file
+pub fn run(json: bool, command: impl FnOnce()) -> i32 {
+  let default_hook = panic::take_hook();
+  panic::set_hook(Box::new(move |info| match info.payload().downcast_ref::<Failure>() {
+    Some(failure) => eprintln!("{}", failure.message),
+    None => default_hook(info),
+  }));
+  let payload = match panic::catch_unwind(AssertUnwindSafe(command)) {
+    Ok(()) => return 0,
+    Err(payload) => payload,
+  };
+  // a plain panic!("Failed to ...") is a failure without a code of its own
+  let failure = match payload.downcast::<Failure>() {
+    Ok(failure) => *failure,
+    Err(payload) => {
+      let message = payload.downcast_ref::<String>().cloned()
+        .or_else(|| payload.downcast_ref::<&str>().map(|m| m.to_string()))
+        .unwrap_or_default();
+      Failure { code: Exit::Failure.name(), exit: Exit::Failure, message }
+    },
+  };
+  if json {
+    print_json("error", &failure);
+  }
+  failure.exit.code()
+}
+
+impl Classify for ParseError {
+  fn exit(&self) -> Exit {
+    Exit::Usage
+  }
+}
+
+impl Classify for IndexError {
+  fn exit(&self) -> Exit {
+    match self {
+      IndexError::Reqwest(_) | IndexError::Status(_) | IndexError::NoCache(_) => Exit::Network,
+      IndexError::Trust(TrustError::KeyChanged { .. } | TrustError::Unsigned { .. } | TrustError::BadSignature { .. }) => Exit::Verification,
+      _ => Exit::Failure,
+    }
+  }
+}
+
+impl Classify for ResolveError {
+  fn exit(&self) -> Exit {
+    match self {
+      ResolveError::Parse(_) | ResolveError::BadDay(_) | ResolveError::Undated(_) => Exit::Usage,
+      ResolveError::NotInstalled(..) | ResolveError::NothingInstalled | ResolveError::Unresolved
+        | ResolveError::NoKeywordMatch(..) | ResolveError::System | ResolveError::NoProject(_) => Exit::NoMatch,
+      ResolveError::Index(err) => err.exit(),
+      ResolveError::Denied(_) => Exit::Denied,
+      ResolveError::Project(..) | ResolveError::Lockfile(..) | ResolveError::Drift(_) | ResolveError::Warnings(_) => Exit::Failure,
+    }
+  }
+}
+
+impl Classify for InstallError {
+  fn exit(&self) -> Exit {
+    match self {
+      InstallError::Status(..) | InstallError::Reqwest(_) | InstallError::Offline(_) => Exit::Network,
+      InstallError::Checksum(..) | InstallError::UnsupportedDigest(..) | InstallError::WeakDigest(..) => Exit::Verification,
+      InstallError::Startup(..) => Exit::Verification,
+      InstallError::Denied(_) => Exit::Denied,
+      InstallError::Hook(_) | InstallError::Cancelled => Exit::Aborted,
+      _ => Exit::Failure,
+    }
+  }
+}
+
+impl Classify for LockError {
+  fn exit(&self) -> Exit {
+    match self {
+      LockError::Timeout(..) => Exit::Busy,
+      LockError::Io(_) => Exit::Failure,
+    }
+  }
+}
+
+impl Classify for PluginError {
+  fn exit(&self) -> Exit {
+    match self {
+      PluginError::NotFound(_) => Exit::Usage,
+      _ => Exit::Failure,
+    }
+  }
+}
+
+impl Classify for ConstraintFileError {
+  fn exit(&self) -> Exit {
+    match self {
+      ConstraintFileError::Io(_) => Exit::Failure,
+      _ => Exit::Usage,
+    }
+  }
+}
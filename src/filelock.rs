@@ -0,0 +1,65 @@
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use fs2::FileExt;
+use thiserror::Error;
+use crate::layout::state_dir;
+
+// one advisory lock for everything that changes the versions dir (install, uninstall, aliases...)
+// so two rvm running at the same time (e.g. parallel CI jobs) wait for each other instead of racing
+// the lock is released by the os when the process dies, so it can't go stale, the pid inside is just informative
+pub struct FileLock {
+  file: File,
+}
+
+#[derive(Error, Debug)]
+pub enum LockError {
+  #[error("error in opening lock file")]
+  Io(#[from] std::io::Error),
+  #[error("timed out after {0:?} waiting for another rvm process (pid {1}) to finish")]
+  Timeout(Duration, String),
+}
+
+impl FileLock {
+  pub fn path() -> PathBuf {
+    state_dir().join("rvm.lock")
+  }
+
+  /// Blocks until the lock is free or timeout passes
+  pub fn acquire(timeout: Duration) -> Result<Self, LockError> {
+    std::fs::create_dir_all(state_dir())?;
+    let mut file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(Self::path())?;
+    let start = Instant::now();
+    let mut warned = false;
+    while file.try_lock_exclusive().is_err() {
+      let holder = Self::holder(&mut file);
+      if start.elapsed() >= timeout {
+        return Err(LockError::Timeout(timeout, holder));
+      }
+      if !warned {
+        eprintln!("Waiting for another rvm process (pid {}) to finish...", holder);
+        warned = true;
+      }
+      std::thread::sleep(Duration::from_millis(200));
+    }
+    file.set_len(0)?;
+    file.seek(SeekFrom::Start(0))?;
+    write!(file, "{}", std::process::id())?;
+    file.flush()?;
+    Ok(Self { file })
+  }
+
+  fn holder(file: &mut File) -> String {
+    let mut pid = String::new();
+    let _ = file.seek(SeekFrom::Start(0)).and_then(|_| file.read_to_string(&mut pid));
+    if pid.trim().is_empty() { "unknown".to_owned() } else { pid.trim().to_owned() }
+  }
+}
+
+impl Drop for FileLock {
+  fn drop(&mut self) {
+    let _ = self.file.set_len(0);
+    let _ = FileExt::unlock(&self.file);
+  }
+}
@@ -0,0 +1,128 @@
+use std::sync::{Arc, RwLock};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use crate::config::Config;
+use crate::index::ArtifactMeta;
+use crate::install::delta::{kept, Kind};
+use crate::install::is_installed;
+use crate::parsing::version_parser::Version;
+use crate::policy::Policy;
+use crate::utils::today;
+
+// policies on which versions resolving may pick, on top of the range: a version a filter refuses is skipped as if it
+// wasn't there, installed or in the index. the machine's policy (see policy.rs) and the ones in `filters` in the
+// config apply, and code embedding rvm can register its own (a company allowlist, a minimum age) without touching
+// the resolution code.
+// installed versions are looked at without their index entry (resolving them doesn't touch the network), so filters
+// that need one (yanked, eol) let them through
+
+/// What a version has to pass to be picked
+pub trait CandidateFilter: Send + Sync {
+  /// Says which filter it was when a version is left out, e.g. in `rvm resolve --explain`
+  fn name(&self) -> String;
+  /// Whether version can be picked, meta is its index entry, none for an installed version
+  fn allows(&self, version: &Version, meta: Option<&ArtifactMeta>) -> bool;
+}
+
+/// Pre-releases aren't picked even by ranges that name them
+pub struct ExcludePrereleases;
+
+impl CandidateFilter for ExcludePrereleases {
+  fn name(&self) -> String {
+    "exclude-prereleases".to_owned()
+  }
+
+  fn allows(&self, version: &Version, _meta: Option<&ArtifactMeta>) -> bool {
+    version.channel().is_none()
+  }
+}
+
+/// What the index has marked yanked is still listed (lockfiles can install it) but never picked
+pub struct ExcludeYanked;
+
+impl CandidateFilter for ExcludeYanked {
+  fn name(&self) -> String {
+    "exclude-yanked".to_owned()
+  }
+
+  fn allows(&self, _version: &Version, meta: Option<&ArtifactMeta>) -> bool {
+    !meta.is_some_and(|meta| meta.yanked)
+  }
+}
+
+/// What's past its end of life on day (YYYY-MM-DD)
+pub struct ExcludeEol {
+  pub day: String,
+}
+
+impl CandidateFilter for ExcludeEol {
+  fn name(&self) -> String {
+    format!("exclude-eol (on {})", self.day)
+  }
+
+  fn allows(&self, _version: &Version, meta: Option<&ArtifactMeta>) -> bool {
+    !meta.is_some_and(|meta| meta.is_eol_on(&self.day))
+  }
+}
+
+/// Only what can be had without downloading: what's installed, or has its installer or source tarball in the cache
+pub struct OnlyCached;
+
+impl CandidateFilter for OnlyCached {
+  fn name(&self) -> String {
+    "only-cached".to_owned()
+  }
+
+  fn allows(&self, version: &Version, _meta: Option<&ArtifactMeta>) -> bool {
+    is_installed(version) || kept(version, Kind::Installer).is_file() || kept(version, Kind::Source).is_file()
+  }
+}
+
+/// The filters that can be turned on in the config, `filters: [exclude-prereleases, only-cached]`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Builtin {
+  ExcludePrereleases,
+  ExcludeYanked,
+  /// by today's date
+  ExcludeEol,
+  OnlyCached,
+}
+
+impl Builtin {
+  pub fn filter(self) -> Arc<dyn CandidateFilter> {
+    match self {
+      Builtin::ExcludePrereleases => Arc::new(ExcludePrereleases),
+      Builtin::ExcludeYanked => Arc::new(ExcludeYanked),
+      Builtin::ExcludeEol => Arc::new(ExcludeEol { day: today() }),
+      Builtin::OnlyCached => Arc::new(OnlyCached),
+    }
+  }
+}
+
+lazy_static!{
+  static ref FILTERS: RwLock<Vec<Arc<dyn CandidateFilter>>> = RwLock::new(vec![]);
+}
+
+/// Adds filter for every resolve after, it stays registered until the process ends
+pub fn register(filter: impl CandidateFilter + 'static) {
+  FILTERS.write().unwrap_or_else(|e| e.into_inner()).push(Arc::new(filter));
+}
+
+/// The machine's policy when there's one, the configured filters, then the registered ones
+pub fn active() -> Vec<Arc<dyn CandidateFilter>> {
+  let policy = Some(Policy::get()).filter(|p| !p.is_open()).map(|p| Arc::new(p) as Arc<dyn CandidateFilter>);
+  let registered = FILTERS.read().unwrap_or_else(|e| e.into_inner()).clone();
+  policy.into_iter().chain(Config::get().filters.into_iter().map(Builtin::filter)).chain(registered).collect()
+}
+
+/// The name of the first of filters refusing version, none when they all allow it
+pub fn refused_by(filters: &[Arc<dyn CandidateFilter>], version: &Version, meta: Option<&ArtifactMeta>) -> Option<String> {
+  filters.iter().find(|f| !f.allows(version, meta)).map(|f| f.name())
+}
+
+/// versions the active filters allow, for resolving among installed ones
+pub fn installed_candidates(versions: &[Version]) -> Vec<Version> {
+  let filters = active();
+  versions.iter().filter(|v| refused_by(&filters, v, None).is_none()).cloned().collect()
+}
@@ -0,0 +1,134 @@
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use crate::alias::Aliases;
+use crate::config::Config;
+use crate::index::OFFLINE_VAR;
+use crate::install::versions_dir;
+use crate::layout::cache_dir;
+use crate::local_utils::SYSTEM_VAR;
+use crate::lockfile::{ProjectLock, LOCKED_VAR};
+use crate::policy::Policy;
+use crate::resolve::{find_project_file, Active, ResolveError, ENV_VAR, SHELL_VAR};
+
+// what the cd hooks resolved in each dir, so entering it again doesn't resolve again. an entry is lines:
+//   the key of what the hook set, the version and project file ("system" for the system R)
+//   the resolved Active as json ("system" for the system R)
+//   every file and dir resolving read, one per line
+// it's stale once any of those is as new as it or newer (a change in the same clock tick counts): a project file appearing above the dir changes that dir,
+// installs change the versions dir. the bash, zsh and powershell hooks read entries themselves, when the key is the
+// one already set (RVM_HOOK_KEY) nothing would change and rvm isn't run at all.
+// only for resolving by dir, with RVM_SHELL_VERSION or RVM_R_VERSION set every hook resolves. nor with RVM_LOCKED,
+// RVM_SYSTEM or RVM_OFFLINE set, they change what resolving gives without changing any file it read
+
+/// set by the hooks to the key of what they set
+pub const KEY_VAR: &str = "RVM_HOOK_KEY";
+/// set by the hooks to where the entries are, for the shell to find them
+pub const DIR_VAR: &str = "RVM_HOOK_CACHE";
+
+pub fn cache_root() -> PathBuf {
+  cache_dir().join("hook")
+}
+
+/// The entry of dir, named the way a shell can name it without running anything (each separator a %)
+pub fn entry_path(dir: &Path) -> PathBuf {
+  entry_path_in(&cache_root(), dir)
+}
+
+/// entry_path with the entries in root instead of cache_root()
+pub fn entry_path_in(root: &Path, dir: &Path) -> PathBuf {
+  let separators: &[char] = if cfg!(windows) { &['\\', '/', ':'] } else { &['/'] };
+  root.join(dir.to_string_lossy().replace(separators, "%"))
+}
+
+/// The dir the shell is in as it names it, which keeps the symlinks the current dir resolves
+pub fn shell_dir() -> Option<PathBuf> {
+  std::env::var_os("PWD").map(PathBuf::from).filter(|dir| dir.is_absolute())
+}
+
+/// Whether entries can be used, not with a version set by variable nor a variable changing how it resolves
+pub fn applies() -> bool {
+  [SHELL_VAR, ENV_VAR, LOCKED_VAR, SYSTEM_VAR, OFFLINE_VAR].iter().all(|var| std::env::var(var).map_or(true, |value| value.trim().is_empty()))
+}
+
+/// The key of what resolved sets, the same key for the same environment
+pub fn key(resolved: &Result<Active, ResolveError>) -> Option<String> {
+  match resolved {
+    Ok(active) => {
+      let project = active.origin.project_file().map(|p| p.display().to_string()).unwrap_or_else(|| "-".to_owned());
+      Some(format!("{} {}", active.version, project))
+    },
+    Err(ResolveError::System) => Some("system".to_owned()),
+    Err(_) => None,
+  }
+}
+
+/// What was resolved in the dir the shell calls dir when nothing it read changed since, only a version or the system R
+pub fn load(dir: &Path) -> Option<Result<Active, ResolveError>> {
+  load_in(&cache_root(), dir)
+}
+
+/// load with the entries in root instead of cache_root()
+pub fn load_in(root: &Path, dir: &Path) -> Option<Result<Active, ResolveError>> {
+  let path = entry_path_in(root, dir);
+  let written = modified(&path)?;
+  let text = std::fs::read_to_string(&path).ok()?;
+  let mut lines = text.lines();
+  let (_key, resolved) = (lines.next()?, lines.next()?);
+  if lines.any(|dependency| modified(Path::new(dependency)).is_some_and(|m| m >= written)) {
+    tracing::debug!(entry = %path.display(), "stale hook cache");
+    return None;
+  }
+  match resolved {
+    "system" => Some(Err(ResolveError::System)),
+    json => serde_json::from_str(json).ok().map(Ok),
+  }
+}
+
+/// Keeps what resolving in resolved_dir (dir as the shell calls it) gave, only a version or the system R
+pub fn store(dir: &Path, resolved_dir: &Path, resolved: &Result<Active, ResolveError>) -> std::io::Result<()> {
+  store_in(&cache_root(), dir, resolved_dir, resolved)
+}
+
+/// store with the entries in root instead of cache_root()
+pub fn store_in(root: &Path, dir: &Path, resolved_dir: &Path, resolved: &Result<Active, ResolveError>) -> std::io::Result<()> {
+  let Some(key) = key(resolved) else { return Ok(()) };
+  let (json, project) = match resolved {
+    Ok(active) => (serde_json::to_string(active)?, active.origin.project_file()),
+    _ => ("system".to_owned(), None),
+  };
+  // the system R's project (or one the resolution order passed over) still changes it by changing
+  let project = project.or_else(|| find_project_file(resolved_dir));
+  let mut lines = vec![key, json];
+  lines.extend(dependencies(resolved_dir, project.as_deref()).into_iter().map(|p| p.display().to_string()));
+  std::fs::create_dir_all(root)?;
+  std::fs::write(entry_path_in(root, dir), lines.join("\n") + "\n")
+}
+
+// what resolving in dir read, with project the project file it found: the dirs up to the project's (up to the root
+// without one), where a project file can appear, the project's files, and what every resolution reads
+fn dependencies(dir: &Path, project: Option<&Path>) -> Vec<PathBuf> {
+  let top = project.and_then(Path::parent);
+  let mut read = vec![];
+  for ancestor in dir.ancestors() {
+    read.push(ancestor.to_path_buf());
+    if Some(ancestor) == top {
+      break;
+    }
+  }
+  if let Some(project) = project {
+    read.extend([project.to_path_buf(), ProjectLock::path_for(project)]);
+  }
+  read.extend([versions_dir(), Aliases::path(), Policy::path()]);
+  read.extend(Config::layers());
+  // one that isn't there yet shows up as a change of its dir
+  read.into_iter()
+    .map(|p| match p.exists() {
+      true => p,
+      false => p.parent().map(Path::to_path_buf).unwrap_or(p),
+    })
+    .collect()
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+  std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
@@ -0,0 +1,116 @@
+use std::path::PathBuf;
+use duct::{cmd, Expression};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use crate::activate::libs_dir;
+use crate::config::Config;
+use crate::install::install_dir;
+use crate::layout::{config_dir, with_layout};
+use crate::parsing::version_parser::Version;
+
+// site specific steps around what rvm does, from the config or executables in <config dir>\hooks.d\<event>\
+// they get what they need in RVM_HOOK_* variables, and their stdout goes to stderr so an eval'd `rvm shell` stays clean
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+  PreInstall,
+  PostInstall,
+  PreUninstall,
+  PostUninstall,
+  PreUse,
+  PostUse,
+}
+
+impl Event {
+  /// Same as the config key and the hooks.d dir
+  pub fn name(&self) -> &'static str {
+    match self {
+      Event::PreInstall => "pre_install",
+      Event::PostInstall => "post_install",
+      Event::PreUninstall => "pre_uninstall",
+      Event::PostUninstall => "post_uninstall",
+      Event::PreUse => "pre_use",
+      Event::PostUse => "post_use",
+    }
+  }
+
+  pub fn is_pre(&self) -> bool {
+    matches!(self, Event::PreInstall | Event::PreUninstall | Event::PreUse)
+  }
+}
+
+// shell commands, run in order before the files in hooks.d
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Hooks {
+  pub pre_install: Vec<String>,
+  pub post_install: Vec<String>,
+  pub pre_uninstall: Vec<String>,
+  pub post_uninstall: Vec<String>,
+  pub pre_use: Vec<String>,
+  pub post_use: Vec<String>,
+}
+
+impl Hooks {
+  pub fn commands(&self, event: Event) -> &[String] {
+    match event {
+      Event::PreInstall => &self.pre_install,
+      Event::PostInstall => &self.post_install,
+      Event::PreUninstall => &self.pre_uninstall,
+      Event::PostUninstall => &self.post_uninstall,
+      Event::PreUse => &self.pre_use,
+      Event::PostUse => &self.post_use,
+    }
+  }
+}
+
+#[derive(Error, Debug)]
+pub enum HookError {
+  #[error("error in running {1} hook `{0}`")]
+  Io(String, &'static str, #[source] std::io::Error),
+  #[error("{1} hook `{0}` failed, {2}")]
+  Failed(String, &'static str, String),
+}
+
+pub fn hooks_dir(event: Event) -> PathBuf {
+  config_dir().join("hooks.d").join(event.name())
+}
+
+/// Runs every hook of event for version, stopping at the first that fails
+pub fn run(event: Event, version: &Version) -> Result<(), HookError> {
+  let mut hooks: Vec<(String, Expression)> = Config::get().hooks.commands(event).iter()
+    .map(|command| (command.clone(), shell_command(command)))
+    .collect();
+  if let Ok(entries) = hooks_dir(event).read_dir() {
+    let mut files: Vec<PathBuf> = entries.filter_map(|e| e.ok())
+      .filter(|e| !e.file_name().to_string_lossy().starts_with('.'))
+      .map(|e| e.path())
+      .filter(|p| p.is_file())
+      .collect();
+    files.sort();
+    hooks.extend(files.into_iter().map(|file| (file.display().to_string(), cmd!(file))));
+  }
+  for (name, expression) in hooks {
+    tracing::info!(hook = %name, event = event.name(), %version, "running hook");
+    let output = with_layout(expression)
+      .env("RVM_HOOK", event.name())
+      .env("RVM_HOOK_VERSION", version.to_string())
+      .env("RVM_HOOK_PREFIX", install_dir(version))
+      .env("RVM_HOOK_LIBS", libs_dir(version))
+      .stdout_to_stderr()
+      .unchecked()
+      .run()
+      .map_err(|err| HookError::Io(name.clone(), event.name(), err))?;
+    if !output.status.success() {
+      return Err(HookError::Failed(name, event.name(), output.status.to_string()));
+    }
+  }
+  Ok(())
+}
+
+fn shell_command(command: &str) -> Expression {
+  if cfg!(windows) {
+    cmd!("cmd", "/C", command)
+  } else {
+    cmd!("sh", "-c", command)
+  }
+}
@@ -0,0 +1,114 @@
+use std::path::{Path, PathBuf};
+use clap::ValueEnum;
+use crate::parsing::version_parser::Version;
+
+/// asdf's project file, `R 4.3.1` on a line of its own
+pub const TOOL_VERSIONS: &str = ".tool-versions";
+
+// other version managers rvm can take installs from, each keeps them in <root>/<some dir>/<version>/
+// only asdf manages R, the others' runtimes go where plugins keep theirs (tools/<name>/)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Manager {
+  Asdf,
+  Pyenv,
+  Rbenv,
+  Nvm,
+}
+
+// an install found in another manager, tool is none for R itself
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Found {
+  pub tool: Option<String>,
+  pub version: Version,
+  pub path: PathBuf,
+}
+
+impl Manager {
+  /// Where the manager keeps everything, from its env var or the default under home
+  pub fn root(&self) -> Option<PathBuf> {
+    let (var, default) = match self {
+      Manager::Asdf => ("ASDF_DATA_DIR", ".asdf"),
+      Manager::Pyenv => ("PYENV_ROOT", ".pyenv"),
+      Manager::Rbenv => ("RBENV_ROOT", ".rbenv"),
+      Manager::Nvm => ("NVM_DIR", ".nvm"),
+    };
+    std::env::var_os(var).map(PathBuf::from).or_else(|| {
+      let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))?;
+      Some(PathBuf::from(home).join(default))
+    })
+  }
+
+  /// Every version it has installed, names that aren't versions (pyenv's "miniconda3-latest"...) are skipped
+  pub fn installs(&self) -> Vec<Found> {
+    let Some(root) = self.root() else { return vec![] };
+    match self {
+      Manager::Asdf => {
+        let Ok(plugins) = root.join("installs").read_dir() else { return vec![] };
+        plugins.filter_map(|e| e.ok())
+          .flat_map(|plugin| {
+            let name = plugin.file_name().to_string_lossy().to_string();
+            let tool = if name.eq_ignore_ascii_case("r") { None } else { Some(tool_name(&name).to_owned()) };
+            versions_in(&plugin.path(), tool)
+          })
+          .collect()
+      },
+      Manager::Pyenv => versions_in(&root.join("versions"), Some("python".to_owned())),
+      Manager::Rbenv => versions_in(&root.join("versions"), Some("ruby".to_owned())),
+      Manager::Nvm => versions_in(&root.join("versions").join("node"), Some("node".to_owned())),
+    }
+  }
+
+  /// The file it pins versions in per project
+  pub fn project_file(&self) -> &'static str {
+    match self {
+      Manager::Asdf => TOOL_VERSIONS,
+      Manager::Pyenv => ".python-version",
+      Manager::Rbenv => ".ruby-version",
+      Manager::Nvm => ".nvmrc",
+    }
+  }
+}
+
+// asdf calls node nodejs, everything else is named the same as the plugins here
+fn tool_name(asdf_plugin: &str) -> &str {
+  match asdf_plugin {
+    "nodejs" => "node",
+    other => other,
+  }
+}
+
+fn versions_in(dir: &Path, tool: Option<String>) -> Vec<Found> {
+  let Ok(entries) = dir.read_dir() else { return vec![] };
+  let mut ret: Vec<Found> = entries.filter_map(|e| e.ok())
+    .filter(|e| e.path().is_dir())
+    .filter_map(|e| {
+      let name = e.file_name().to_string_lossy().to_string();
+      let version = Version::parse(name.strip_prefix('v').unwrap_or(&name)).ok()?;
+      Some(Found { tool: tool.clone(), version, path: e.path() })
+    })
+    .collect();
+  ret.sort_by(|a, b| a.version.cmp(&b.version));
+  ret
+}
+
+/// Points to with a link to from, so the install stays where the other manager has it
+pub fn link_dir(from: &Path, to: &Path) -> std::io::Result<()> {
+  if let Some(parent) = to.parent() {
+    std::fs::create_dir_all(parent)?;
+  }
+  #[cfg(windows)]
+  return std::os::windows::fs::symlink_dir(from, to);
+  #[cfg(not(windows))]
+  return std::os::unix::fs::symlink(from, to);
+}
+
+/// The R version asdf's .tool-versions asks for, its first one if it lists fallbacks
+pub fn r_from_tool_versions(contents: &str) -> Option<String> {
+  contents.lines()
+    .map(|line| line.split('#').next().unwrap_or_default())
+    .find_map(|line| {
+      let mut words = line.split_whitespace();
+      let tool = words.next()?;
+      if tool.eq_ignore_ascii_case("r") { words.next().map(str::to_owned) } else { None }
+    })
+}
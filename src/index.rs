@@ -0,0 +1,742 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use reqwest::header::{HeaderMap, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use crate::auth::WithAuth;
+use crate::config::{Config, Mirror};
+use crate::filter;
+use crate::install::extract::ArchiveFormat;
+use crate::install::installed_versions;
+use crate::install::relocate::Relocation;
+use crate::layout::cache_dir;
+use crate::local_utils::client_for;
+use crate::parsing::version_parser::{Range, Version};
+use std::str::FromStr;
+use crate::platform::{Arch, Os, Platform};
+use crate::resolve::TieBreak;
+use crate::stats;
+use crate::throttle;
+use crate::trust::{self, payload, Signed, TrustError};
+use crate::utils::env_flag;
+
+// an installable version as the registry describes it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArtifactMeta {
+  pub version: Version,
+  /// the prebuilt installer
+  pub url: String,
+  /// source tarball, for the build from source backend
+  #[serde(default)]
+  pub source_url: Option<String>,
+  /// shell script to build this version instead of the configured recipe
+  #[serde(default)]
+  pub build_script: Option<String>,
+  /// hex sha256 of what url points to, downloads that don't match are refused
+  /// or another algorithm's digest written <algorithm>:<hex>, e.g. sha512:9b71d2..., see install/digest.rs
+  #[serde(default)]
+  pub sha256: Option<String>,
+  /// same for source_url
+  #[serde(default)]
+  pub source_sha256: Option<String>,
+  /// what source_url is packed as, by default what its extension says
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub source_archive: Option<ArchiveFormat>,
+  /// what url runs on, none for artifacts that run anywhere
+  #[serde(default)]
+  pub platform: Option<Platform>,
+  /// the day it stops getting fixes, YYYY-MM-DD
+  #[serde(default)]
+  pub eol: Option<String>,
+  /// the day it came out, YYYY-MM-DD, for released-before and released-after in ranges
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub released: Option<String>,
+  /// taken back by the registry, still installable as is but never picked by a range with exclude-yanked, see filter.rs
+  #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+  pub yanked: bool,
+  /// how much it's looked after now, none when the index doesn't say
+  #[serde(default)]
+  pub support: Option<Support>,
+  /// patches from older versions' url to this one's, see install/delta.rs
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub deltas: Vec<Delta>,
+  /// same for source_url
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub source_deltas: Vec<Delta>,
+  /// how to fix the prefix baked into it, see install/relocate.rs
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub relocate: Option<Relocation>,
+  /// extra variables to set while it's active, e.g. R_SHARE_DIR: "{install_dir}/share", see activate::expand_env
+  #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+  pub env: BTreeMap<String, String>,
+  /// where its release notes are, a page or plain text, for `rvm changes`
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub notes_url: Option<String>,
+  /// the release notes themselves, for an index that carries them
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub notes: Option<String>,
+}
+
+/// A patch that turns the artifact of from into this version's
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Delta {
+  pub from: Version,
+  pub url: String,
+  /// of the patch itself
+  #[serde(default)]
+  pub sha256: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Support {
+  /// every fix
+  Current,
+  /// only security and critical fixes
+  Maintenance,
+  /// none anymore, whatever eol says
+  Eol,
+}
+
+impl ArtifactMeta {
+  /// Whether it's past its end of life on day (YYYY-MM-DD), by its tier or its eol date
+  pub fn is_eol_on(&self, day: &str) -> bool {
+    self.support == Some(Support::Eol) || self.eol.as_deref().is_some_and(|eol| eol <= day)
+  }
+
+  /// How source_url is packed, a .tar.gz when neither the index nor the url say
+  pub fn source_format(&self) -> ArchiveFormat {
+    self.source_archive
+      .or_else(|| self.source_url.as_deref().and_then(ArchiveFormat::from_name))
+      .unwrap_or(ArchiveFormat::TarGz)
+  }
+}
+
+/// `>=supported`: from the oldest release that isn't past its end of life on day, without the ones above it that are
+/// none when every release is
+pub fn supported_range(entries: &[ArtifactMeta], day: &str) -> Option<Range> {
+  let releases = || entries.iter().filter(|m| m.version.channel().is_none());
+  let floor = releases().filter(|m| !m.is_eol_on(day)).map(|m| &m.version).min()?;
+  let mut except: Vec<Version> = releases().filter(|m| m.is_eol_on(day) && &m.version > floor).map(|m| m.version.clone()).collect();
+  except.sort();
+  Some(Range { min: Some(floor.clone()), except, ..Range::default() })
+}
+
+/// The range of what came out in a window of days, keep says which release days (YYYY-MM-DD) are in it
+/// it only takes out the entries the index has a day for, a version it doesn't date is in
+pub fn released_range(entries: &[ArtifactMeta], keep: impl Fn(&str) -> bool) -> Range {
+  let mut except: Vec<Version> = entries.iter()
+    .filter(|m| m.released.as_deref().is_some_and(|day| !keep(day)))
+    .map(|m| m.version.clone())
+    .collect();
+  except.sort();
+  except.dedup();
+  Range { except, ..Range::default() }
+}
+
+/// The newest entry whose version is in range, pre-releases only when the range names one (like npm)
+pub fn newest_matching<'a>(range: &Range, entries: &'a [ArtifactMeta]) -> Option<&'a ArtifactMeta> {
+  newest_on_channel(range, entries, None)
+}
+
+/// Same as newest_matching, only looking at the entries of channel when there's one
+/// a channel is "release" or what pre-releases start with, e.g. nightly for 4.4.0-nightly.20240315
+pub fn newest_on_channel<'a>(range: &Range, entries: &'a [ArtifactMeta], channel: Option<&str>) -> Option<&'a ArtifactMeta> {
+  let filters = filter::active();
+  let considered = entries.iter().filter(|m| match channel {
+    Some(channel) => on_channel(&m.version, channel),
+    None => m.version.channel().is_none() || range.names_pre_release(),
+  }).filter(|m| filter::refused_by(&filters, &m.version, Some(m)).is_none());
+  let tie_break = Config::get().tie_break;
+  let installed = if tie_break == TieBreak::PreferInstalled { installed_versions() } else { vec![] };
+  tie_break.newest(range, considered.map(|m| &m.version), &installed)
+    .and_then(|v| entries.iter().find(|m| &m.version == v))
+}
+
+pub fn on_channel(version: &Version, channel: &str) -> bool {
+  match version.channel() {
+    None => channel == "release",
+    Some(name) => name == channel,
+  }
+}
+
+// cache validators sent back to the registry so it can answer 304
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Validators {
+  pub etag: Option<String>,
+  pub last_modified: Option<String>,
+  /// where in the source's changelog the cached entries are, for sources that have one
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub cursor: Option<String>,
+}
+
+// what a changelog endpoint answers for ?since=<cursor>, everything since the start without one:
+//   {"cursor": "1742", "added": [{"version": "4.3.2", "url": "..."}], "yanked": ["4.3.0"]}
+// it's append-only, so applying the answers in order to an empty list gives the whole index.
+// a cursor it doesn't know anymore is answered with 410, and the changelog is read again from the start
+
+/// What changed in a source since a cursor
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Changes {
+  /// where this leaves the changelog, sent back next time
+  pub cursor: String,
+  /// new versions, or new metadata for ones already there
+  #[serde(default)]
+  pub added: Vec<ArtifactMeta>,
+  /// versions taken out
+  #[serde(default)]
+  pub yanked: Vec<Version>,
+}
+
+impl Changes {
+  pub fn parse(text: &str) -> Result<Self, IndexError> {
+    Ok(serde_json::from_str(text)?)
+  }
+
+  /// entries with the changes applied, newest first
+  pub fn apply(self, mut entries: Vec<ArtifactMeta>) -> Vec<ArtifactMeta> {
+    entries.retain(|e| !self.yanked.contains(&e.version) && !self.added.iter().any(|a| a.version == e.version));
+    entries.extend(self.added);
+    entries.sort_by(|a, b| b.version.cmp(&a.version));
+    entries
+  }
+}
+
+pub enum Fetched {
+  NotModified,
+  /// the listing, and its signature when it's signed (see trust.rs)
+  Modified(Vec<ArtifactMeta>, Validators, Option<Signed>),
+}
+
+#[derive(Error, Debug)]
+pub enum IndexError {
+  #[error("error in reading/writing the index cache")]
+  Io(#[from] std::io::Error),
+  #[error("error in (de)serializing the index cache")]
+  Serde(#[from] serde_yaml::Error),
+  #[error("error in requesting the index")]
+  Reqwest(#[from] reqwest::Error),
+  #[error("registry answered with status {0}")]
+  Status(StatusCode),
+  #[error("no cached index for {0}, can't list versions offline")]
+  NoCache(String),
+  #[error("error in reading the index document")]
+  Json(#[from] serde_json::Error),
+  #[error("error in reading the index document")]
+  Toml(#[from] toml::de::Error),
+  #[error("index requires rvm >= {0}, this is rvm {1}")]
+  RequiresRvm(String, &'static str),
+  #[error("index has schema version {0} but rvm {1} reads up to {2}, try a newer rvm")]
+  UnknownSchema(u32, &'static str, u32),
+  #[error(transparent)]
+  Trust(#[from] TrustError),
+}
+
+// what registries publish, json or toml:
+//   {"schema_version": 1, "requires_rvm": "0.2.0", "versions": [{"version": "4.3.1", "url": "..."}]}
+// a field rvm doesn't know is ignored, so adding one doesn't take a new schema version, changing or removing one does.
+// before the first check, with what this binary knows, the document says which rvm reads it, so an older one
+// can tell the user to upgrade instead of failing on a field it never heard of
+// a bare array of versions is schema 0, what plugins printed before there was a schema
+
+/// The newest index schema this rvm reads
+pub const INDEX_SCHEMA: u32 = 1;
+const RVM_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+// only what tells whether the rest can be read, whatever the schema
+#[derive(Deserialize)]
+struct SchemaHeader {
+  schema_version: u32,
+  requires_rvm: Option<String>,
+}
+
+/// An index as registries publish it, what `rvm index generate` writes
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IndexDocument {
+  pub schema_version: u32,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub requires_rvm: Option<String>,
+  /// the public key the registry signs with, trusted on first use, see trust.rs
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub signing_key: Option<String>,
+  /// the signature of versions with signing_key
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub signature: Option<String>,
+  pub versions: Vec<ArtifactMeta>,
+  /// what's signed of versions as the document has them, filled in when it's read
+  #[serde(skip)]
+  pub payload: Vec<u8>,
+}
+
+impl IndexDocument {
+  /// In the current schema, readable by any rvm that knows it
+  pub fn new(versions: Vec<ArtifactMeta>) -> Self {
+    Self { schema_version: INDEX_SCHEMA, requires_rvm: None, signing_key: None, signature: None, versions, payload: vec![] }
+  }
+
+  /// Its signature, none when it has no key or no signature
+  pub fn signed(&self) -> Option<Signed> {
+    let (key, signature) = (self.signing_key.clone()?, self.signature.clone()?);
+    Some(Signed { key, signature, payload: self.payload.clone() })
+  }
+}
+
+/// Reads an index document in any schema up to INDEX_SCHEMA, toml if it isn't json
+pub fn parse_index(text: &str) -> Result<Vec<ArtifactMeta>, IndexError> {
+  Ok(parse_index_document(text)?.versions)
+}
+
+/// Same as parse_index, with what the document says besides its versions
+pub fn parse_index_document(text: &str) -> Result<IndexDocument, IndexError> {
+  let value: serde_json::Value = match text.trim_start().chars().next() {
+    Some('{' | '[') => serde_json::from_str(text)?,
+    _ => toml::from_str(text)?,
+  };
+  let value = if value.is_array() { serde_json::json!({ "schema_version": 0, "versions": value }) } else { value };
+  let header: SchemaHeader = serde_json::from_value(value.clone())?;
+  if let Some(requires) = &header.requires_rvm {
+    // a requirement that doesn't parse is for a future rvm too
+    if Version::parse(requires.as_str()).map_or(true, |v| v > Version::parse(RVM_VERSION).unwrap_or_default()) {
+      return Err(IndexError::RequiresRvm(requires.clone(), RVM_VERSION));
+    }
+  }
+  if header.schema_version > INDEX_SCHEMA {
+    return Err(IndexError::UnknownSchema(header.schema_version, RVM_VERSION, INDEX_SCHEMA));
+  }
+  let payload = payload(&value["versions"]);
+  let document = serde_json::from_value::<IndexDocument>(migrate(value, header.schema_version))?;
+  Ok(IndexDocument { payload, ..document })
+}
+
+// one step per schema bump, up to the current one
+fn migrate(value: serde_json::Value, from: u32) -> serde_json::Value {
+  match from {
+    // 0 to 1 only wrapped the array, which reading one already does
+    0 => migrate(value, 1),
+    _ => value,
+  }
+}
+
+// somewhere that knows which versions exist
+pub trait VersionSource {
+  /// used to name the cache file, so it should be unique and path friendly
+  fn name(&self) -> &str;
+  /// fetch the full list, unless validators show nothing changed
+  fn fetch(&self, validators: &Validators) -> Result<Fetched, IndexError>;
+  /// What changed since cursor (since the start without one) for sources with a changelog,
+  /// none when the source has none, then the full list is fetched
+  fn changes(&self, _cursor: Option<&str>) -> Result<Option<Changes>, IndexError> {
+    Ok(None)
+  }
+  /// The release notes of meta's version as text, the ones the index carries or else what notes_url has,
+  /// none when there are neither (or only a url while offline)
+  fn release_notes(&self, meta: &ArtifactMeta) -> Result<Option<String>, IndexError> {
+    if let Some(notes) = &meta.notes {
+      return Ok(Some(notes.clone()));
+    }
+    match &meta.notes_url {
+      Some(url) if !is_offline() => fetch_notes(url).map(Some),
+      _ => Ok(None),
+    }
+  }
+}
+
+// the text of the release notes at url, a page's without its markup
+fn fetch_notes(url: &str) -> Result<String, IndexError> {
+  let client = client_for(url);
+  let (_permit, response) = throttle::send(url, || client.get(url).with_auth(url))?;
+  if !response.status().is_success() {
+    return Err(IndexError::Status(response.status()));
+  }
+  Ok(notes_text(&response.text()?))
+}
+
+/// Release notes as text: plain text as it is, an html page's text with the tags and blank runs taken out
+pub fn notes_text(body: &str) -> String {
+  if !body.trim_start().starts_with('<') {
+    return body.trim().to_owned();
+  }
+  let Ok(dom) = tl::parse(body, tl::ParserOptions::default()) else { return body.trim().to_owned() };
+  let parser = dom.parser();
+  let page = dom.query_selector("body").and_then(|mut found| found.next()).and_then(|handle| handle.get(parser));
+  let text = match page {
+    Some(node) => node.inner_text(parser).into_owned(),
+    None => dom.children().iter().filter_map(|handle| handle.get(parser)).map(|node| node.inner_text(parser).into_owned()).collect(),
+  };
+  let text = [("&lt;", "<"), ("&gt;", ">"), ("&quot;", "\""), ("&#39;", "'"), ("&nbsp;", " "), ("&amp;", "&")].iter()
+    .fold(text, |text, (entity, c)| text.replace(entity, c));
+  let lines: Vec<&str> = text.lines().map(str::trim_end).collect();
+  // one blank line at most between paragraphs
+  lines.iter().enumerate()
+    .filter(|(i, line)| !line.trim().is_empty() || (*i > 0 && !lines[i - 1].trim().is_empty()))
+    .map(|(_, line)| *line)
+    .collect::<Vec<_>>()
+    .join("\n")
+    .trim()
+    .to_owned()
+}
+
+// CRAN's windows binaries, every release lives in bin/windows/base/old/<version>/
+pub struct CranSource {
+  pub base_url: String,
+}
+
+impl Default for CranSource {
+  fn default() -> Self {
+    Self {
+      base_url: "https://cran.r-project.org/bin/windows/base".to_owned(), //TODO change to be possible to change CRAN
+    }
+  }
+}
+
+impl CranSource {
+  /// CRAN, through the config's mirror for it when there's one
+  pub fn configured() -> Self {
+    let cran = Self::default();
+    Self { base_url: Mirror::rewrite(&Config::get().mirrors, &cran.base_url) }
+  }
+
+  pub fn installer_url(&self, version: &str) -> String {
+    format!("{}/old/{}/R-{}-win.exe", self.base_url, version, version)
+  }
+
+  /// CRAN keeps each release's NEWS next to its installer
+  pub fn notes_url(&self, version: &str) -> String {
+    format!("{}/old/{}/NEWS.R-{}.html", self.base_url, version, version)
+  }
+
+  pub fn source_url(&self, version: &Version) -> String {
+    // sources aren't under bin/windows but under src/base/R-<major>/
+    let cran = self.base_url.trim_end_matches("/bin/windows/base");
+    format!("{}/src/base/R-{}/R-{}.tar.gz", cran, version.parts().0, version)
+  }
+
+  pub fn parse_listing(&self, body: &str) -> Vec<ArtifactMeta> {
+    // the listing is a bunch of <a href="4.3.1/">R 4.3.1</a>, anything else is ignored
+    let dom = tl::parse(body, tl::ParserOptions::default()).expect("Failed to parse body");
+    let parser = dom.parser();
+    let mut ret: Vec<ArtifactMeta> = dom.query_selector("a")
+      .expect("Failed to do query")
+      .filter_map(|handle| handle.get(parser)?.as_tag())
+      .filter_map(|tag| tag.attributes().get("href").flatten()?.try_as_utf8_str().map(str::to_owned))
+      .filter_map(|href| {
+        let name = href.strip_suffix('/')?;
+        let version = Version::parse(name).ok()?;
+        Some(ArtifactMeta {
+          url: self.installer_url(name),
+          source_url: Some(self.source_url(&version)),
+          build_script: None,
+          sha256: None,
+          source_sha256: None,
+          source_archive: None,
+          platform: Some(Platform { arch: Arch::X86_64, os: Os::Windows, libc: None }),
+          eol: None,
+          released: None,
+          yanked: false,
+          support: None,
+          deltas: vec![],
+          source_deltas: vec![],
+          notes_url: Some(self.notes_url(name)),
+          notes: None,
+          relocate: None,
+          env: BTreeMap::new(),
+          version,
+        })
+      })
+      .collect();
+    ret.sort_by(|a, b| b.version.cmp(&a.version)); // newest first
+    ret.dedup();
+    ret
+  }
+}
+
+/// What an artifact's file name says, see artifact_name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArtifactName {
+  pub version: Version,
+  /// none for a source tarball or an artifact that runs anywhere
+  pub platform: Option<Platform>,
+  /// a tarball without a platform, what the build from source backend takes
+  pub source: bool,
+}
+
+const ARTIFACT_EXTENSIONS: &[&str] = &[".tar.gz", ".tar.xz", ".tgz", ".zip", ".exe", ".msi", ".pkg"];
+
+/// Reads names like CRAN's and the usual R-<version>-<platform>.<ext>:
+/// R-4.3.1-win.exe, R-4.3.1-arm64.pkg, R-4.3.1.tar.gz (source), R-4.4.0-nightly.20240315-x86_64-linux-gnu.tar.gz
+/// none when there's no version in it or it isn't an archive or installer
+pub fn artifact_name(file_name: &str) -> Option<ArtifactName> {
+  let lower = file_name.to_ascii_lowercase();
+  let ext = *ARTIFACT_EXTENSIONS.iter().find(|e| lower.ends_with(*e))?;
+  let stem = &file_name[..file_name.len() - ext.len()];
+  let stem = stem.strip_prefix("R-").or_else(|| stem.strip_prefix("r-")).unwrap_or(stem);
+  let stem = stem.strip_prefix('v').unwrap_or(stem);
+  let windows = Platform { arch: Arch::X86_64, os: Os::Windows, libc: None };
+  // the version ends at the first dash the platform follows, pre-releases have dashes too
+  for (i, _) in stem.match_indices('-') {
+    let rest = &stem[i + 1..];
+    let platform = match rest {
+      "win" | "win64" => Some(windows),
+      arch if ext == ".pkg" && !arch.contains('-') => Platform::from_str(&format!("{}-macos", arch)).ok(),
+      _ => Platform::from_str(rest).ok(),
+    };
+    if let (Some(platform), Ok(version)) = (platform, Version::parse(&stem[..i])) {
+      return Some(ArtifactName { version, platform: Some(platform), source: false });
+    }
+  }
+  let version = Version::parse(stem).ok()?;
+  let platform = match ext {
+    ".exe" | ".msi" => Some(windows),
+    // CRAN's intel builds from before there were arm ones
+    ".pkg" => Some(Platform { arch: Arch::X86_64, os: Os::Macos, libc: None }),
+    _ => None,
+  };
+  let source = platform.is_none() && matches!(ext, ".tar.gz" | ".tar.xz" | ".tgz");
+  Some(ArtifactName { version, platform, source })
+}
+
+impl VersionSource for CranSource {
+  fn name(&self) -> &str {
+    "cran"
+  }
+
+  fn fetch(&self, validators: &Validators) -> Result<Fetched, IndexError> {
+    let url = format!("{}/old/", self.base_url);
+    let client = client_for(&url);
+    let (_permit, response) = throttle::send(&url, || {
+      let mut request = client.get(&url).with_auth(&url);
+      if let Some(etag) = &validators.etag {
+        request = request.header(IF_NONE_MATCH, etag);
+      }
+      if let Some(last_modified) = &validators.last_modified {
+        request = request.header(IF_MODIFIED_SINCE, last_modified);
+      }
+      request
+    })?;
+    let status = response.status();
+    let validators = Validators::from_headers(response.headers());
+    self.read_answer(status, validators, || Ok(response.text()?))
+  }
+
+  // the config's index_changelog, if there's one
+  fn changes(&self, cursor: Option<&str>) -> Result<Option<Changes>, IndexError> {
+    let Some(url) = Config::get().index_changelog else { return Ok(None) };
+    let client = client_for(&url);
+    let (_permit, response) = throttle::send(&url, || {
+      let request = client.get(&url).with_auth(&url);
+      match cursor {
+        Some(cursor) => request.query(&[("since", cursor)]),
+        None => request,
+      }
+    })?;
+    if !response.status().is_success() {
+      return Err(IndexError::Status(response.status()));
+    }
+    Ok(Some(Changes::parse(&response.text()?)?))
+  }
+}
+
+impl CranSource {
+  // shared by the blocking and async fetch, body is only read when there's something new
+  pub(crate) fn read_answer(&self, status: StatusCode, validators: Validators, body: impl FnOnce() -> Result<String, IndexError>) -> Result<Fetched, IndexError> {
+    if status == StatusCode::NOT_MODIFIED {
+      return Ok(Fetched::NotModified);
+    }
+    if !status.is_success() {
+      return Err(IndexError::Status(status));
+    }
+    Ok(Fetched::Modified(self.parse_listing(&body()?), validators, None))
+  }
+}
+
+impl Validators {
+  pub fn from_headers(headers: &HeaderMap) -> Self {
+    let header = |name| headers.get(name).and_then(|v| v.to_str().ok()).map(str::to_owned);
+    Self {
+      etag: header(ETAG),
+      last_modified: header(LAST_MODIFIED),
+      cursor: None,
+    }
+  }
+}
+
+/// set by --offline (or by hand), every policy is Offline and nothing is downloaded
+pub const OFFLINE_VAR: &str = "RVM_OFFLINE";
+
+/// Whether rvm has to do without the network
+pub fn is_offline() -> bool {
+  env_flag(OFFLINE_VAR)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CachePolicy {
+  /// use the cache while it's younger than the ttl
+  #[default] Default,
+  /// always ask the registry (still conditionally)
+  Refresh,
+  /// never touch the network
+  Offline,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IndexCache {
+  pub fetched_at: u64, // seconds since epoch
+  pub validators: Validators,
+  pub entries: Vec<ArtifactMeta>,
+}
+
+impl IndexCache {
+  pub fn path(name: &str) -> PathBuf {
+    cache_dir().join(format!("index-{}.yaml", name))
+  }
+
+  pub fn load_named(name: &str) -> Option<Self> {
+    let file = File::open(Self::path(name)).ok()?;
+    serde_yaml::from_reader(file).ok() // a broken cache is the same as no cache
+  }
+
+  pub fn save_named(&self, name: &str) -> Result<(), IndexError> {
+    let path = Self::path(name);
+    std::fs::create_dir_all(path.parent().unwrap())?;
+    serde_yaml::to_writer(File::create(path)?, self)?;
+    // only costs completions their freshness
+    if let Err(err) = self.save_completions(name) {
+      tracing::debug!(error = %err, "can't save the completion list");
+    }
+    Ok(())
+  }
+
+  /// The versions the cached index named has, one per line newest first, what shell completion reads
+  /// written with every save of the index, so any command that refreshes it refreshes completions
+  pub fn completions_path(name: &str) -> PathBuf {
+    cache_dir().join(format!("completions-{}.txt", name))
+  }
+
+  fn save_completions(&self, name: &str) -> std::io::Result<()> {
+    let text: String = completion_list(&self.entries).into_iter().map(|v| v + "\n").collect();
+    std::fs::write(Self::completions_path(name), text)
+  }
+
+  pub fn age(&self) -> u64 {
+    now().saturating_sub(self.fetched_at)
+  }
+
+  pub fn is_fresh(&self, ttl: u64) -> bool {
+    self.age() < ttl
+  }
+}
+
+/// What completing `rvm install` offers, from the last saved index and never the network, empty before the first
+/// listing. Reads the cached index itself when the list isn't there (a cache from before it was written)
+pub fn completion_versions(source: &dyn VersionSource) -> Vec<String> {
+  match std::fs::read_to_string(IndexCache::completions_path(source.name())) {
+    Ok(text) => text.lines().map(str::to_owned).collect(),
+    Err(_) => IndexCache::load_named(source.name()).map(|cache| completion_list(&cache.entries)).unwrap_or_default(),
+  }
+}
+
+// newest first, each version once
+fn completion_list(entries: &[ArtifactMeta]) -> Vec<String> {
+  let mut versions: Vec<&Version> = entries.iter().map(|e| &e.version).collect();
+  versions.sort_by(|a, b| b.cmp(a));
+  versions.dedup();
+  versions.into_iter().map(Version::to_string).collect()
+}
+
+fn now() -> u64 {
+  SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+/// Lists the versions a source has, going through the on disk cache
+#[tracing::instrument(skip(source), fields(source = source.name()))]
+pub fn remote_versions(source: &dyn VersionSource, policy: CachePolicy) -> Result<Vec<ArtifactMeta>, IndexError> {
+  let cache = IndexCache::load_named(source.name());
+  if let Some(answer) = answer_from_cache(source.name(), &cache, policy) {
+    return answer;
+  }
+  let cursor = cache.as_ref().and_then(|c| c.validators.cursor.clone());
+  let changes = match source.changes(cursor.as_deref()) {
+    // the changelog forgot the cursor, it's read from the start
+    Err(IndexError::Status(StatusCode::GONE)) if cursor.is_some() => source.changes(None),
+    changes => changes,
+  };
+  match changes {
+    Ok(Some(changes)) => return store_changes(source.name(), cache, cursor.is_some(), changes),
+    Ok(None) => {},
+    Err(err) => return store_fetched(source.name(), cache, Err(err)),
+  }
+  let validators = cache.as_ref().map(|c| c.validators.clone()).unwrap_or_default();
+  tracing::info!(etag = ?validators.etag, "fetching index");
+  let fetched = source.fetch(&validators);
+  store_fetched(source.name(), cache, fetched)
+}
+
+// answers without the network when the policy allows it, None means a fetch is needed
+pub(crate) fn answer_from_cache(name: &str, cache: &Option<IndexCache>, policy: CachePolicy) -> Option<Result<Vec<ArtifactMeta>, IndexError>> {
+  if let Some(cache) = cache {
+    tracing::debug!(age = cache.age(), entries = cache.entries.len(), "index cache found");
+  }
+  let policy = if is_offline() { CachePolicy::Offline } else { policy };
+  let answer = match (cache, policy) {
+    (Some(cache), CachePolicy::Offline) => Some(Ok(cache.entries.clone())),
+    (None, CachePolicy::Offline) => Some(Err(IndexError::NoCache(name.to_owned()))),
+    (Some(cache), CachePolicy::Default) if cache.is_fresh(Config::get().index_ttl) => Some(Ok(cache.entries.clone())),
+    _ => None
+  };
+  if let Some(Ok(_)) = answer {
+    stats::count(|s| s.index.cached += 1);
+  }
+  answer
+}
+
+// applies a changelog answer to the old cache and saves it, since_cache is whether it's since the cache's cursor
+fn store_changes(name: &str, cache: Option<IndexCache>, since_cache: bool, changes: Changes) -> Result<Vec<ArtifactMeta>, IndexError> {
+  // a changelog isn't signed, a registry with a key can't be updated through one
+  trust::check(name, None)?;
+  stats::count(|s| s.index.fetched += 1);
+  tracing::info!(cursor = %changes.cursor, added = changes.added.len(), yanked = changes.yanked.len(), "index changes");
+  let (entries, validators) = match cache {
+    Some(cache) if since_cache => (cache.entries, cache.validators),
+    _ => (vec![], Validators::default()),
+  };
+  let validators = Validators { cursor: Some(changes.cursor.clone()), ..validators };
+  let new_cache = IndexCache { fetched_at: now(), validators, entries: changes.apply(entries) };
+  new_cache.save_named(name)?;
+  Ok(new_cache.entries)
+}
+
+// merges what the registry answered with the old cache and saves it
+pub(crate) fn store_fetched(name: &str, cache: Option<IndexCache>, fetched: Result<Fetched, IndexError>) -> Result<Vec<ArtifactMeta>, IndexError> {
+  let fetched = match fetched {
+    Ok(fetched) => fetched,
+    Err(err) => {
+      // a stale index is better than nothing
+      tracing::warn!(error = %err, "index refresh failed");
+      if let Some(cache) = cache {
+        eprintln!("Failed to refresh index ({}), using cache from {} seconds ago", err, cache.age());
+        return Ok(cache.entries);
+      }
+      return Err(err);
+    }
+  };
+  let new_cache = match (fetched, cache) {
+    (Fetched::NotModified, Some(cache)) => {
+      stats::count(|s| s.index.revalidated += 1);
+      IndexCache { fetched_at: now(), ..cache }
+    },
+    (Fetched::Modified(entries, validators, signed), _) => {
+      // not the cache when this fails, it came from the registry before its key changed and isn't a way around it
+      trust::check(name, signed.as_ref())?;
+      stats::count(|s| s.index.fetched += 1);
+      IndexCache { fetched_at: now(), validators, entries }
+    },
+    // 304 without having sent validators, shouldn't happen
+    (Fetched::NotModified, None) => return Err(IndexError::Status(StatusCode::NOT_MODIFIED)),
+  };
+  new_cache.save_named(name)?;
+  tracing::debug!(entries = new_cache.entries.len(), "index cache saved");
+  Ok(new_cache.entries)
+}
@@ -0,0 +1,88 @@
+use std::collections::BTreeMap;
+use serde::Serialize;
+use crate::alias::Aliases;
+use crate::install::installed_versions;
+use crate::install::meta::InstallMeta;
+use crate::parsing::request::VersionRequest;
+use crate::parsing::version_parser::Version;
+
+// disk_budget_mb in the config caps what the installs in versions\ take together. before installing, the new
+// versions are estimated to take what the closest installed one does (the index doesn't say how big an install
+// is) and when that goes over, install warns, or refuses with --strict-budget, with what could make room: the
+// versions unused the longest, pinned and aliased ones left out like prune leaves them
+
+/// What the installs take and what installing would add, against the budget
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Budget {
+  pub limit: u64,
+  pub used: u64,
+  /// the estimate for the versions about to be installed
+  pub adding: u64,
+  /// oldest unused first, just enough of them to be under the limit again (all of them when that's not enough)
+  pub candidates: Vec<Candidate>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Candidate {
+  pub version: Version,
+  pub size: u64,
+  /// when it last ran (or was installed), none if that's not known
+  pub unused_since: Option<u64>,
+}
+
+impl Budget {
+  pub fn is_over(&self) -> bool {
+    self.used + self.adding > self.limit
+  }
+}
+
+/// The budget with versions installed, none without a budget or when they fit in it
+pub fn check(limit_mb: u64, versions: &[Version]) -> Option<Budget> {
+  if limit_mb == 0 {
+    return None;
+  }
+  let installed = installed_versions();
+  let metas = InstallMeta::all();
+  let sizes: BTreeMap<Version, u64> = installed.iter()
+    .map(|v| (v.clone(), metas.get(v).cloned().unwrap_or_default().size_of(v)))
+    .collect();
+  let adding = versions.iter().filter(|v| !sizes.contains_key(v)).map(|v| estimate(v, &sizes)).sum();
+  let budget = Budget { limit: limit_mb * 1024 * 1024, used: sizes.values().sum(), adding, candidates: Vec::new() };
+  if !budget.is_over() {
+    return None;
+  }
+  let aliased: Vec<Version> = Aliases::load().map(|aliases| aliases.aliases.values()
+    .filter_map(|target| VersionRequest::parse(target).ok().and_then(|r| r.resolve(&installed).cloned()))
+    .collect())
+    .unwrap_or_default();
+  let unused: Vec<Candidate> = sizes.iter()
+    .filter(|(v, _)| !aliased.contains(v) && !versions.contains(v))
+    .filter_map(|(v, size)| {
+      let meta = metas.get(v).cloned().unwrap_or_default();
+      (!meta.pinned).then(|| Candidate { version: v.clone(), size: *size, unused_since: meta.unused_since() })
+    })
+    .collect();
+  let candidates = make_room(unused, budget.used + budget.adding - budget.limit);
+  Some(Budget { candidates, ..budget })
+}
+
+/// What an install of version is guessed to take: the installed one closest to it, the newest older one first
+pub fn estimate(version: &Version, sizes: &BTreeMap<Version, u64>) -> u64 {
+  sizes.range(..version).next_back()
+    .or_else(|| sizes.range(version..).next())
+    .map_or(0, |(_, size)| *size)
+}
+
+/// The ones of unused that went unused the longest, until they free over bytes
+pub fn make_room(mut unused: Vec<Candidate>, over: u64) -> Vec<Candidate> {
+  // the ones that never recorded when they were used are the oldest
+  unused.sort_by_key(|c| c.unused_since.unwrap_or(0));
+  let mut freed = 0;
+  unused.into_iter()
+    .take_while(|c| {
+      let needed = freed < over;
+      freed += c.size;
+      needed
+    })
+    .collect()
+}
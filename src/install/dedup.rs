@@ -0,0 +1,99 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::install::{install_dir, installed_versions, verify};
+use crate::parsing::version_parser::Version;
+use crate::utils::sha256_file;
+
+// files of a new install that are byte for byte the ones of the nearest older and newer installs share their blocks,
+// consecutive releases have most of them in common (docs, translations, base packages' R code).
+// candidates are found by the sums (see verify.rs) and hashed again on the other side, it could have changed since.
+// what's shared with which version is in the install's metadata, for `rvm du`
+
+/// How installs share the files they have in common with the versions next to them, `dedup` in the config
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Dedup {
+  /// every install has its own copies
+  Off,
+  /// copy on write clones (btrfs, xfs, apfs, refs), nothing is shared on a filesystem without them
+  #[default]
+  Reflink,
+  /// clones, else hard links, where an edit in place changes the file in every install that has it
+  Hardlink,
+}
+
+// files smaller than this aren't worth it, most of an install's size is in fewer bigger files
+const MIN_SIZE: u64 = 4096;
+
+/// Makes the files of version's install in dir share the ones its neighbours have, returning the bytes shared with each
+/// stops at the first file that can't be, what was shared until then stays
+pub fn run(version: &Version, dir: &Path, mode: Dedup) -> BTreeMap<Version, u64> {
+  let mut shared = BTreeMap::new();
+  let Some(sums) = verify::sums(version).filter(|_| mode != Dedup::Off) else { return shared };
+  let mut by_sha256: HashMap<String, (Version, String)> = HashMap::new();
+  for neighbour in neighbours(version) {
+    for (path, sha256, size) in verify::sums(&neighbour).unwrap_or_default() {
+      if size >= MIN_SIZE {
+        by_sha256.entry(sha256).or_insert_with(|| (neighbour.clone(), path));
+      }
+    }
+  }
+  let mut can_reflink = true;
+  for (path, sha256, size) in sums {
+    let Some((neighbour, theirs)) = by_sha256.get(&sha256) else { continue };
+    let theirs = install_dir(neighbour).join(theirs);
+    if std::fs::metadata(&theirs).map(|m| m.len()).ok() != Some(size) || sha256_file(&theirs).ok().as_ref() != Some(&sha256) {
+      continue;
+    }
+    match share(&theirs, &dir.join(&path), mode, &mut can_reflink) {
+      Ok(true) => *shared.entry(neighbour.clone()).or_insert(0) += size,
+      Ok(false) if mode == Dedup::Reflink && !can_reflink => break,
+      Ok(false) => {},
+      Err(err) => {
+        tracing::warn!(error = %err, file = %path, "can't share files with other installs");
+        break;
+      },
+    }
+  }
+  tracing::info!(%version, ?shared, "deduplicated");
+  shared
+}
+
+/// The installed versions right before and after version
+pub fn neighbours(version: &Version) -> Vec<Version> {
+  let installed = installed_versions();
+  let older = installed.iter().rev().find(|v| *v < version);
+  let newer = installed.iter().find(|v| *v > version);
+  older.into_iter().chain(newer).cloned().collect()
+}
+
+/// Replaces ours with a clone of theirs (or with Hardlink a link to it when there are no clones), through a temp file
+/// so ours is never half there. Whether it did, can_reflink goes false once a clone fails
+pub fn share(theirs: &Path, ours: &Path, mode: Dedup, can_reflink: &mut bool) -> io::Result<bool> {
+  let temp = ours.with_file_name(format!("{}.rvm-dedup", ours.file_name().unwrap_or_default().to_string_lossy()));
+  let permissions = std::fs::metadata(ours)?.permissions();
+  if *can_reflink {
+    match reflink_copy::reflink(theirs, &temp) {
+      Ok(()) => {
+        std::fs::set_permissions(&temp, permissions)?;
+        std::fs::rename(&temp, ours)?;
+        return Ok(true);
+      },
+      Err(err) => {
+        // it's the filesystem, the next file won't clone either
+        tracing::debug!(error = %err, "no reflinks");
+        let _ = std::fs::remove_file(&temp);
+        *can_reflink = false;
+      },
+    }
+  }
+  // a link has one set of permissions for both
+  if mode != Dedup::Hardlink || std::fs::metadata(theirs)?.permissions() != permissions {
+    return Ok(false);
+  }
+  std::fs::hard_link(theirs, &temp)?;
+  std::fs::rename(&temp, ours)?;
+  Ok(true)
+}
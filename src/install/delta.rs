@@ -0,0 +1,242 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use crate::cancel::CancellationToken;
+use crate::index::{ArtifactMeta, Delta};
+use crate::install::{verify_download, Downloader, InstallError};
+use crate::layout::cache_dir;
+use crate::observer;
+use crate::stats;
+use crate::parsing::version_parser::Version;
+
+// patches between the artifacts of two versions, so upgrading 4.3.1 to 4.3.2 downloads what changed instead of all of it.
+// the index lists them under deltas (for url) and source_deltas (for source_url), each from an older version.
+// the newest installer and source tarball of every series are kept in the cache after an install, those are the bases.
+// a delta is only used when the index has the sha256 of the whole artifact, the patched file has to match it,
+// anything going wrong on the way (no base, a bad patch, a mismatch) is the full download instead.
+//
+// a patch is gzip of "RVMDELTA1\n" then ops until E:
+//   C <offset u64 le> <len u64 le>   copy len bytes of the base from offset
+//   I <len u64 le> <bytes>           insert len bytes
+//   E
+
+const MAGIC: &[u8] = b"RVMDELTA1\n";
+// matches shorter than this aren't looked for
+const BLOCK: usize = 64;
+const HASH_BASE: u64 = 0x100000001b3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+  Installer,
+  Source,
+}
+
+impl Kind {
+  fn name(&self) -> &'static str {
+    match self {
+      Kind::Installer => "installer",
+      Kind::Source => "source",
+    }
+  }
+
+  // the hash and deltas of this kind of artifact of meta
+  fn of(self, meta: &ArtifactMeta) -> (Option<&str>, &[Delta]) {
+    match self {
+      Kind::Installer => (meta.sha256.as_deref(), &meta.deltas),
+      Kind::Source => (meta.source_sha256.as_deref(), &meta.source_deltas),
+    }
+  }
+}
+
+// <cache dir>\artifacts\<major>.<minor>\<kind>-<version>
+pub fn artifacts_dir() -> PathBuf {
+  cache_dir().join("artifacts")
+}
+
+fn series_dir(version: &Version) -> PathBuf {
+  let (major, minor, _) = version.parts();
+  artifacts_dir().join(format!("{}.{}", major, minor))
+}
+
+pub fn kept(version: &Version, kind: Kind) -> PathBuf {
+  series_dir(version).join(format!("{}-{}", kind.name(), version))
+}
+
+/// Keeps path as the base for deltas to later versions of its series, replacing the one of an older version
+/// nothing changes when a newer one of the series is kept already
+pub fn keep(version: &Version, kind: Kind, path: &Path) -> io::Result<()> {
+  let dir = series_dir(version);
+  std::fs::create_dir_all(&dir)?;
+  let prefix = format!("{}-", kind.name());
+  let mut newer_kept = false;
+  for entry in dir.read_dir()?.filter_map(|e| e.ok()) {
+    let name = entry.file_name().to_string_lossy().to_string();
+    match name.strip_prefix(&prefix).and_then(|v| Version::parse(v).ok()) {
+      Some(other) if &other < version => std::fs::remove_file(entry.path())?,
+      Some(other) if &other > version => newer_kept = true,
+      _ => {},
+    }
+  }
+  if !newer_kept {
+    std::fs::copy(path, kept(version, kind))?;
+  }
+  Ok(())
+}
+
+/// Downloads url, meta's artifact of kind, to filename in the temp dir, by patching a kept artifact when one of its deltas is from it
+pub fn download(meta: &ArtifactMeta, kind: Kind, url: &str, filename: &str, downloader: &dyn Downloader, on_bytes: &dyn Fn(u64, Option<u64>),
+                cancel: &CancellationToken) -> Result<PathBuf, InstallError> {
+  observer::notify(|o| o.on_download_start(&meta.version, url));
+  let path = download_patched(meta, kind, url, filename, downloader, on_bytes, cancel)?;
+  observer::notify(|o| o.on_download_finish(&meta.version, url, &path));
+  Ok(path)
+}
+
+fn download_patched(meta: &ArtifactMeta, kind: Kind, url: &str, filename: &str, downloader: &dyn Downloader, on_bytes: &dyn Fn(u64, Option<u64>),
+                    cancel: &CancellationToken) -> Result<PathBuf, InstallError> {
+  let (sha256, deltas) = kind.of(meta);
+  let fetch = |url: &str, filename: &str| downloader.download(url, filename, on_bytes, cancel);
+  if let Some(expected) = sha256 {
+    // what `rvm daemon` prefetched, as long as it's still what the index says
+    let own = kept(&meta.version, kind);
+    if own.is_file() {
+      let path = std::env::temp_dir().join(filename);
+      match std::fs::copy(&own, &path).map_err(InstallError::from).and_then(|_| verify_download(url, &path, Some(expected))) {
+        Ok(_) => {
+          tracing::info!(kept = %own.display(), "using the kept artifact");
+          return Ok(path);
+        },
+        Err(err) => tracing::warn!(kept = %own.display(), error = %err, "the kept artifact isn't the index's, downloading it"),
+      }
+    }
+    for delta in deltas.iter().filter(|d| kept(&d.from, kind).is_file()) {
+      match patched(delta, kind, url, expected, filename, &fetch) {
+        Ok(path) => {
+          stats::count(|s| s.downloads.delta += 1);
+          return Ok(path);
+        },
+        Err(InstallError::Cancelled) => return Err(InstallError::Cancelled),
+        Err(err) => {
+          tracing::warn!(error = %err, from = %delta.from, "delta failed");
+          eprintln!("Failed to patch R {} into {} ({}), downloading all of it", delta.from, meta.version, err);
+        },
+      }
+    }
+  }
+  let path = fetch(url, filename)?;
+  stats::count(|s| s.downloads.full += 1);
+  Ok(path)
+}
+
+fn patched(delta: &Delta, kind: Kind, url: &str, expected: &str, filename: &str,
+           fetch: &dyn Fn(&str, &str) -> Result<PathBuf, InstallError>) -> Result<PathBuf, InstallError> {
+  tracing::info!(from = %delta.from, url = %delta.url, "downloading delta");
+  let patch = fetch(&delta.url, &format!("{}.delta", filename))?;
+  verify_download(&delta.url, &patch, delta.sha256.as_deref())?;
+  let out = std::env::temp_dir().join(filename);
+  let applied = apply(&kept(&delta.from, kind), &patch, &out);
+  let _ = std::fs::remove_file(&patch);
+  applied?;
+  verify_download(url, &out, Some(expected))?;
+  Ok(out)
+}
+
+/// Writes what patch makes of base to out
+pub fn apply(base: &Path, patch: &Path, out: &Path) -> io::Result<()> {
+  let mut base = File::open(base)?;
+  let mut patch = BufReader::new(GzDecoder::new(File::open(patch)?));
+  let mut out = BufWriter::new(File::create(out)?);
+  let mut magic = [0; MAGIC.len()];
+  patch.read_exact(&mut magic)?;
+  if magic != MAGIC {
+    return Err(io::Error::new(io::ErrorKind::InvalidData, "not an rvm delta"));
+  }
+  let u64_le = |patch: &mut BufReader<_>| -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    patch.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+  };
+  loop {
+    let mut op = [0];
+    patch.read_exact(&mut op)?;
+    match op[0] {
+      b'C' => {
+        let (offset, len) = (u64_le(&mut patch)?, u64_le(&mut patch)?);
+        base.seek(SeekFrom::Start(offset))?;
+        if io::copy(&mut (&mut base).take(len), &mut out)? != len {
+          return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "delta copies past the end of its base"));
+        }
+      },
+      b'I' => {
+        let len = u64_le(&mut patch)?;
+        if io::copy(&mut (&mut patch).take(len), &mut out)? != len {
+          return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "delta ends in an insert"));
+        }
+      },
+      b'E' => break,
+      _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "unknown delta op")),
+    }
+  }
+  out.flush()
+}
+
+/// A patch from base to target, for `rvm index delta`
+/// runs of at least BLOCK bytes that are somewhere in base are copied from it, the rest is inserted
+pub fn diff(base: &[u8], target: &[u8]) -> io::Result<Vec<u8>> {
+  let mut ops: Vec<u8> = MAGIC.to_vec();
+  let insert = |ops: &mut Vec<u8>, bytes: &[u8]| {
+    if !bytes.is_empty() {
+      ops.push(b'I');
+      ops.extend((bytes.len() as u64).to_le_bytes());
+      ops.extend(bytes);
+    }
+  };
+  let pow = (1..BLOCK).fold(1u64, |p, _| p.wrapping_mul(HASH_BASE));
+  let hash = |block: &[u8]| block.iter().fold(0u64, |h, &b| h.wrapping_mul(HASH_BASE).wrapping_add(b as u64));
+  let mut blocks: HashMap<u64, Vec<usize>> = HashMap::new();
+  for offset in (0..base.len().saturating_sub(BLOCK - 1)).step_by(BLOCK) {
+    let offsets = blocks.entry(hash(&base[offset..offset + BLOCK])).or_default();
+    if offsets.len() < 8 {
+      offsets.push(offset);
+    }
+  }
+  let (mut i, mut pending) = (0, 0);
+  let mut h = if target.len() >= BLOCK { hash(&target[..BLOCK]) } else { 0 };
+  while i + BLOCK <= target.len() {
+    let found = blocks.get(&h).and_then(|offsets| offsets.iter().copied().find(|&o| base[o..o + BLOCK] == target[i..i + BLOCK]));
+    if let Some(offset) = found {
+      let mut len = BLOCK;
+      while offset + len < base.len() && i + len < target.len() && base[offset + len] == target[i + len] {
+        len += 1;
+      }
+      // the match may start before the block, in what would otherwise be inserted
+      let mut back = 0;
+      while back < i - pending && back < offset && base[offset - back - 1] == target[i - back - 1] {
+        back += 1;
+      }
+      insert(&mut ops, &target[pending..i - back]);
+      ops.push(b'C');
+      ops.extend(((offset - back) as u64).to_le_bytes());
+      ops.extend(((len + back) as u64).to_le_bytes());
+      i += len;
+      pending = i;
+      if i + BLOCK <= target.len() {
+        h = hash(&target[i..i + BLOCK]);
+      }
+      continue;
+    }
+    if i + BLOCK < target.len() {
+      h = h.wrapping_sub((target[i] as u64).wrapping_mul(pow)).wrapping_mul(HASH_BASE).wrapping_add(target[i + BLOCK] as u64);
+    }
+    i += 1;
+  }
+  insert(&mut ops, &target[pending..]);
+  ops.push(b'E');
+  let mut encoder = GzEncoder::new(vec![], Compression::best());
+  encoder.write_all(&ops)?;
+  encoder.finish()
+}
@@ -0,0 +1,127 @@
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+use crate::utils::env_flag;
+
+// what an index's sha256 fields hold: bare hex is a sha256 like it always was, any other algorithm is named first,
+// sha512:<hex> or blake3:<hex> (with the blake3 feature, much faster to check on multi GB artifacts).
+// md5 and sha1 are known but broken, a download only checked with one is refused unless --insecure-digest
+
+/// set by --insecure-digest (or by hand), downloads only checked with a broken algorithm are accepted
+pub const INSECURE_DIGEST_VAR: &str = "RVM_INSECURE_DIGEST";
+
+/// Whether downloads checked with md5 or sha1 are accepted
+pub fn insecure_allowed() -> bool {
+  env_flag(INSECURE_DIGEST_VAR)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+  Md5,
+  Sha1,
+  Sha256,
+  Sha512,
+  Blake3,
+}
+
+impl Algorithm {
+  pub const ALL: [Algorithm; 5] = [Algorithm::Md5, Algorithm::Sha1, Algorithm::Sha256, Algorithm::Sha512, Algorithm::Blake3];
+
+  pub fn name(self) -> &'static str {
+    match self {
+      Algorithm::Md5 => "md5",
+      Algorithm::Sha1 => "sha1",
+      Algorithm::Sha256 => "sha256",
+      Algorithm::Sha512 => "sha512",
+      Algorithm::Blake3 => "blake3",
+    }
+  }
+
+  /// Collisions can be made for it, so matching one proves nothing against a hostile mirror
+  pub fn is_weak(self) -> bool {
+    matches!(self, Algorithm::Md5 | Algorithm::Sha1)
+  }
+
+  /// Whether this build can compute it, blake3 needs its feature
+  pub fn is_supported(self) -> bool {
+    self != Algorithm::Blake3 || cfg!(feature = "blake3")
+  }
+
+  /// Hex digest of a file's contents
+  pub fn hash_file(self, path: &Path) -> io::Result<String> {
+    match self {
+      Algorithm::Md5 => hash_with::<md5::Md5>(path),
+      Algorithm::Sha1 => hash_with::<sha1::Sha1>(path),
+      Algorithm::Sha256 => hash_with::<sha2::Sha256>(path),
+      Algorithm::Sha512 => hash_with::<sha2::Sha512>(path),
+      #[cfg(feature = "blake3")]
+      Algorithm::Blake3 => {
+        let mut hasher = blake3::Hasher::new();
+        io::copy(&mut File::open(path)?, &mut hasher)?;
+        Ok(hasher.finalize().to_hex().to_string())
+      },
+      #[cfg(not(feature = "blake3"))]
+      Algorithm::Blake3 => Err(io::Error::new(io::ErrorKind::Unsupported, "rvm was built without the blake3 feature")),
+    }
+  }
+}
+
+fn hash_with<D: sha2::Digest + io::Write>(path: &Path) -> io::Result<String> {
+  let mut hasher = D::new();
+  io::copy(&mut File::open(path)?, &mut hasher)?;
+  Ok(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+impl FromStr for Algorithm {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    Algorithm::ALL.into_iter()
+      .find(|a| a.name().eq_ignore_ascii_case(s))
+      .ok_or_else(|| format!("unknown digest algorithm {}", s))
+  }
+}
+
+impl fmt::Display for Algorithm {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(self.name())
+  }
+}
+
+/// A digest as an index writes it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Digest {
+  pub algorithm: Algorithm,
+  pub hex: String,
+}
+
+impl Digest {
+  /// `<algorithm>:<hex>`, or bare hex for a sha256
+  pub fn parse(text: &str) -> Result<Self, String> {
+    match text.split_once(':') {
+      Some((algorithm, hex)) => Ok(Self { algorithm: algorithm.parse()?, hex: hex.to_owned() }),
+      None => Ok(Self { algorithm: Algorithm::Sha256, hex: text.to_owned() }),
+    }
+  }
+
+  pub fn of_file(algorithm: Algorithm, path: &Path) -> io::Result<Self> {
+    Ok(Self { algorithm, hex: algorithm.hash_file(path)? })
+  }
+
+  /// Same algorithm and same hex, whatever its case
+  pub fn matches(&self, other: &Digest) -> bool {
+    self.algorithm == other.algorithm && self.hex.eq_ignore_ascii_case(&other.hex)
+  }
+}
+
+// written back the way it's parsed, so lockfiles and install metadata keep a sha256 as bare hex
+impl fmt::Display for Digest {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self.algorithm {
+      Algorithm::Sha256 => f.write_str(&self.hex),
+      algorithm => write!(f, "{}:{}", algorithm, self.hex),
+    }
+  }
+}
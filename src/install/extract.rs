@@ -0,0 +1,359 @@
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Component, Path, PathBuf};
+use std::process::{Command, Stdio};
+use flate2::read::{DeflateDecoder, GzDecoder};
+use flate2::Crc;
+use serde::{Deserialize, Serialize};
+use crate::install::{InstallError, Progress, Reporter};
+use crate::parsing::version_parser::Version;
+use crate::utils::ProgressReader;
+
+// unpacking the archives sources and registered trees come in: .tar.gz, .tar.xz, .tar.zst and .zip.
+// the format is the index's source_archive when it has one, else the artifact's extension.
+// gzip is decoded in process, xz and zstd by piping through `xz -dc` and `zstd -dc`, zips are read here.
+// modes and symlinks are kept, and nothing gets written outside dest: entries with absolute paths or `..`,
+// and links pointing out of dest, fail the whole unpack instead of being skipped. where an entry or a link's
+// target ends up is found on disk, following the links unpacked before it, so a chain of links that each look
+// fine on their own (`a -> .` then `a/b -> ..`) can't lead out either
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ArchiveFormat {
+  TarGz,
+  TarXz,
+  TarZst,
+  Zip,
+}
+
+impl ArchiveFormat {
+  /// The format a file name's extension says, none for anything else
+  pub fn from_name(name: &str) -> Option<Self> {
+    let name = name.to_lowercase();
+    let name = name.split(['?', '#']).next().unwrap_or_default();
+    [(".tar.gz", Self::TarGz), (".tgz", Self::TarGz), (".tar.xz", Self::TarXz), (".txz", Self::TarXz),
+     (".tar.zst", Self::TarZst), (".tzst", Self::TarZst), (".zip", Self::Zip)]
+      .into_iter()
+      .find(|(ext, _)| name.ends_with(ext))
+      .map(|(_, format)| format)
+  }
+
+  pub fn extension(&self) -> &'static str {
+    match self {
+      Self::TarGz => "tar.gz",
+      Self::TarXz => "tar.xz",
+      Self::TarZst => "tar.zst",
+      Self::Zip => "zip",
+    }
+  }
+
+  // the program decompressing it into a tar, none for the ones done here
+  fn decompressor(&self) -> Option<&'static str> {
+    match self {
+      Self::TarXz => Some("xz"),
+      Self::TarZst => Some("zstd"),
+      Self::TarGz | Self::Zip => None,
+    }
+  }
+}
+
+/// Unpacks archive into dest, reporting how much of it was read
+pub fn unpack(version: &Version, archive: &Path, format: ArchiveFormat, dest: &Path, reporter: &dyn Reporter) -> Result<(), InstallError> {
+  let file = File::open(archive)?;
+  let size = file.metadata()?.len();
+  let on_read = |read| reporter.report(Progress::Unpacking(version, read, size));
+  let failed = |err: String| InstallError::Unpack(archive.to_path_buf(), err);
+  std::fs::create_dir_all(dest)?;
+  match (format, format.decompressor()) {
+    (ArchiveFormat::Zip, _) => unzip(file, dest, &on_read).map_err(|err| failed(err.to_string())),
+    (_, None) => untar(GzDecoder::new(ProgressReader::new(file, on_read)), dest).map_err(|err| failed(err.to_string())),
+    (_, Some(program)) => {
+      let mut child = Command::new(program).arg("-dc")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| failed(format!("can't run {}: {}", program, err)))?;
+      let (mut stdin, stdout) = (child.stdin.take(), child.stdout.take());
+      let untarred = std::thread::scope(|scope| {
+        // fed from another thread so a full stdout pipe can't block the stdin one
+        let feeding = scope.spawn(move || stdin.as_mut().map(|s| io::copy(&mut ProgressReader::new(file, on_read), s)));
+        // tar stops at its end marker, the padding after it still has to be read or the decompressor gets a broken pipe
+        let untarred = stdout.map(|mut out| untar(&mut out, dest).and_then(|_| io::copy(&mut out, &mut io::sink()))).unwrap_or(Ok(0));
+        let _ = feeding.join();
+        untarred
+      });
+      let output = child.wait_with_output()?;
+      if !output.status.success() {
+        return Err(failed(format!("{} failed: {}", program, String::from_utf8_lossy(&output.stderr).trim())));
+      }
+      untarred.map(|_| ()).map_err(|err| failed(err.to_string()))
+    },
+  }
+}
+
+/// The part of dest path is in, none when it's absolute or goes up out of it
+pub fn contained(path: &Path) -> Option<PathBuf> {
+  let mut ret = PathBuf::new();
+  for component in path.components() {
+    match component {
+      Component::Normal(part) => ret.push(part),
+      Component::CurDir => {},
+      Component::ParentDir if ret.pop() => {},
+      Component::ParentDir | Component::RootDir | Component::Prefix(_) => return None,
+    }
+  }
+  Some(ret)
+}
+
+// entries don't get `..` at all, following it through a link made earlier in the archive could leave dest
+fn entry_path(path: &Path) -> io::Result<PathBuf> {
+  match contained(path) {
+    Some(relative) if !path.components().any(|c| c == Component::ParentDir) => Ok(relative),
+    _ => Err(escapes("entry", path)),
+  }
+}
+
+fn escapes(what: &str, path: &Path) -> io::Error {
+  io::Error::new(io::ErrorKind::InvalidData, format!("{} {} is outside of where it's unpacked", what, path.display()))
+}
+
+// where path (relative to root, which is canonical) really is, following the links already on disk, none when that's
+// outside of root. parts that don't exist yet are taken as they're written
+fn resolve(root: &Path, path: &Path) -> Option<PathBuf> {
+  let mut at = root.to_path_buf();
+  // what's left to walk, last part first
+  let mut left: Vec<PathBuf> = path.components().rev().map(|c| PathBuf::from(c.as_os_str())).collect();
+  let mut hops = 0;
+  while let Some(part) = left.pop() {
+    match part.components().next() {
+      Some(Component::Normal(name)) => {
+        at.push(name);
+        if let Ok(target) = std::fs::read_link(&at) {
+          hops += 1;
+          if hops > 40 || target.is_absolute() {
+            return None;
+          }
+          at.pop();
+          left.extend(target.components().rev().map(|c| PathBuf::from(c.as_os_str())));
+        }
+      },
+      Some(Component::ParentDir) if at == root => return None,
+      Some(Component::ParentDir) => { at.pop(); },
+      Some(Component::CurDir) | None => {},
+      Some(Component::RootDir | Component::Prefix(_)) => return None,
+    }
+  }
+  Some(at)
+}
+
+// an entry at path (relative to dest) has to end up in dest, also when it's inside a link unpacked before it
+fn check_entry(root: &Path, path: &Path) -> io::Result<()> {
+  resolve(root, path).map(|_| ()).ok_or_else(|| escapes("entry", path))
+}
+
+// a link at path (relative to dest) to target has to end up in dest too
+fn check_link(root: &Path, path: &Path, target: &Path) -> io::Result<()> {
+  let parent = path.parent().unwrap_or(Path::new(""));
+  if target.is_absolute() || resolve(root, parent).and_then(|_| resolve(root, &parent.join(target))).is_none() {
+    return Err(escapes("link target", target));
+  }
+  Ok(())
+}
+
+fn untar(reader: impl Read, dest: &Path) -> io::Result<()> {
+  let mut archive = tar::Archive::new(reader);
+  archive.set_preserve_permissions(true);
+  archive.set_preserve_mtime(true);
+  let root = dest.canonicalize()?;
+  for entry in archive.entries()? {
+    let mut entry = entry?;
+    let path = entry.path()?.into_owned();
+    let relative = entry_path(&path)?;
+    let kind = entry.header().entry_type();
+    if kind.is_symlink() {
+      let target = entry.link_name()?.map(|t| t.into_owned()).unwrap_or_default();
+      check_link(&root, &relative, &target)?;
+    } else {
+      check_entry(&root, &relative)?;
+    }
+    if kind.is_hard_link() {
+      // hard links are relative to the archive root, not the link
+      let target = entry.link_name()?.map(|t| t.into_owned()).unwrap_or_default();
+      entry_path(&target).and_then(|target| check_entry(&root, &target)).map_err(|_| escapes("link target", &target))?;
+    }
+    entry.unpack_in(dest)?;
+  }
+  Ok(())
+}
+
+// the zip bits read here: stored and deflated entries, unix modes and symlinks, no zip64, encryption or multiple disks
+const END_OF_DIRECTORY: u32 = 0x06054b50;
+const DIRECTORY_ENTRY: u32 = 0x02014b50;
+const LOCAL_HEADER: u32 = 0x04034b50;
+const MADE_ON_UNIX: u8 = 3;
+const S_IFMT: u32 = 0o170000;
+const S_IFLNK: u32 = 0o120000;
+
+struct ZipEntry {
+  name: String,
+  method: u16,
+  crc: u32,
+  compressed: u64,
+  size: u64,
+  /// from the external attributes when it was zipped on unix
+  mode: Option<u32>,
+  offset: u64,
+}
+
+fn invalid(message: &str) -> io::Error {
+  io::Error::new(io::ErrorKind::InvalidData, message.to_owned())
+}
+
+fn u16_at(bytes: &[u8], at: usize) -> u16 {
+  u16::from_le_bytes([bytes[at], bytes[at + 1]])
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> u32 {
+  u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+}
+
+fn zip_entries(file: &mut File) -> io::Result<Vec<ZipEntry>> {
+  // the end of directory record is 22 bytes and a comment of up to 64k
+  let size = file.seek(SeekFrom::End(0))?;
+  let tail_len = size.min(22 + u16::MAX as u64);
+  file.seek(SeekFrom::Start(size - tail_len))?;
+  let mut tail = vec![0; tail_len as usize];
+  file.read_exact(&mut tail)?;
+  let end = (0..tail.len().saturating_sub(21)).rev()
+    .find(|&at| u32_at(&tail, at) == END_OF_DIRECTORY)
+    .ok_or_else(|| invalid("not a zip"))?;
+  let (count, directory_len, directory_at) = (u16_at(&tail, end + 10), u32_at(&tail, end + 12), u32_at(&tail, end + 16));
+  if count == u16::MAX || directory_at == u32::MAX || u16_at(&tail, end + 4) != 0 {
+    return Err(invalid("zip64 and multi disk zips aren't supported"));
+  }
+  file.seek(SeekFrom::Start(directory_at as u64))?;
+  let mut directory = vec![0; directory_len as usize];
+  file.read_exact(&mut directory)?;
+  let mut entries = vec![];
+  let mut at = 0;
+  for _ in 0..count {
+    if at + 46 > directory.len() || u32_at(&directory, at) != DIRECTORY_ENTRY {
+      return Err(invalid("broken zip directory"));
+    }
+    let (name_len, extra_len, comment_len) = (u16_at(&directory, at + 28) as usize, u16_at(&directory, at + 30) as usize, u16_at(&directory, at + 32) as usize);
+    let name = directory.get(at + 46..at + 46 + name_len).ok_or_else(|| invalid("broken zip directory"))?;
+    let (compressed, size, offset) = (u32_at(&directory, at + 20), u32_at(&directory, at + 24), u32_at(&directory, at + 42));
+    if [compressed, size, offset].contains(&u32::MAX) {
+      return Err(invalid("zip64 entries aren't supported"));
+    }
+    if u16_at(&directory, at + 8) & 1 != 0 {
+      return Err(invalid("encrypted zips aren't supported"));
+    }
+    let made_on = (u16_at(&directory, at + 4) >> 8) as u8;
+    let mode = Some(u32_at(&directory, at + 38) >> 16).filter(|m| made_on == MADE_ON_UNIX && *m != 0);
+    entries.push(ZipEntry {
+      name: String::from_utf8_lossy(name).replace('\\', "/"),
+      method: u16_at(&directory, at + 10),
+      crc: u32_at(&directory, at + 16),
+      compressed: compressed as u64,
+      size: size as u64,
+      mode,
+      offset: offset as u64,
+    });
+    at += 46 + name_len + extra_len + comment_len;
+  }
+  Ok(entries)
+}
+
+fn unzip(mut file: File, dest: &Path, on_read: &dyn Fn(u64)) -> io::Result<()> {
+  let entries = zip_entries(&mut file)?;
+  // checked before anything is written, a zip with one bad entry leaves nothing behind
+  let relatives = entries.iter().map(|e| entry_path(Path::new(&e.name))).collect::<io::Result<Vec<_>>>()?;
+  let root = dest.canonicalize()?;
+  let mut links = vec![];
+  for (entry, relative) in entries.iter().zip(relatives) {
+    let path = dest.join(&relative);
+    check_entry(&root, &relative)?;
+    file.seek(SeekFrom::Start(entry.offset))?;
+    let mut header = [0; 30];
+    file.read_exact(&mut header)?;
+    if u32_at(&header, 0) != LOCAL_HEADER {
+      return Err(invalid("broken zip entry"));
+    }
+    file.seek(SeekFrom::Current(u16_at(&header, 26) as i64 + u16_at(&header, 28) as i64))?;
+    let raw = (&mut file).take(entry.compressed);
+    let mut data: Box<dyn Read + '_> = match entry.method {
+      0 => Box::new(raw),
+      8 => Box::new(DeflateDecoder::new(BufReader::new(raw))),
+      method => return Err(invalid(&format!("zip compression method {} isn't supported", method))),
+    };
+    if entry.name.ends_with('/') {
+      std::fs::create_dir_all(&path)?;
+      continue;
+    }
+    if let Some(parent) = path.parent() {
+      std::fs::create_dir_all(parent)?;
+    }
+    let mut crc = Crc::new();
+    let mut bytes = vec![];
+    let is_link = entry.mode.is_some_and(|m| m & S_IFMT == S_IFLNK);
+    let mut out: Box<dyn Write> = if is_link { Box::new(&mut bytes) } else { Box::new(File::create(&path)?) };
+    let mut buf = [0; 64 * 1024];
+    let mut written = 0;
+    loop {
+      let n = data.read(&mut buf)?;
+      if n == 0 {
+        break;
+      }
+      crc.update(&buf[..n]);
+      out.write_all(&buf[..n])?;
+      written += n as u64;
+    }
+    out.flush()?;
+    drop(out);
+    if crc.sum() != entry.crc || written != entry.size {
+      return Err(invalid(&format!("{} is corrupt", entry.name)));
+    }
+    if is_link {
+      let target = PathBuf::from(String::from_utf8_lossy(&bytes).into_owned());
+      links.push((relative, path, target));
+    } else if let Some(mode) = entry.mode {
+      set_mode(&path, mode)?;
+    }
+    on_read(entry.offset + entry.compressed);
+  }
+  // made last so no entry gets written through one, each checked against the ones made before it
+  for (relative, path, target) in links {
+    check_link(&root, &relative, &target)?;
+    symlink(&target, &path)?;
+  }
+  Ok(())
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: u32) -> io::Result<()> {
+  use std::os::unix::fs::PermissionsExt;
+  std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode & 0o7777))
+}
+
+#[cfg(not(unix))]
+fn set_mode(_path: &Path, _mode: u32) -> io::Result<()> {
+  Ok(())
+}
+
+#[cfg(unix)]
+fn symlink(target: &Path, path: &Path) -> io::Result<()> {
+  std::os::unix::fs::symlink(target, path)
+}
+
+// windows needs to know what the link points to, and the rest of the unpack may not have made it yet
+#[cfg(windows)]
+fn symlink(target: &Path, path: &Path) -> io::Result<()> {
+  let resolved = path.parent().unwrap_or(Path::new("")).join(target);
+  if resolved.is_dir() {
+    std::os::windows::fs::symlink_dir(target, path)
+  } else {
+    std::os::windows::fs::symlink_file(target, path)
+  }
+}
@@ -0,0 +1,72 @@
+use std::fs::OpenOptions;
+use std::path::PathBuf;
+use duct::cmd;
+use serde::{Deserialize, Serialize};
+use crate::audit;
+use crate::cancel::{self, CancellationToken};
+use crate::install::{install_dir, is_installed, InstallError, Progress, Reporter};
+use crate::install::meta::InstallMeta;
+use crate::install::source::{build_dir, build_error, build_log, run_build};
+use crate::install::transaction::Transaction;
+use crate::parsing::version_parser::Version;
+use crate::policy::Policy;
+
+// building an unreleased R straight from a repository, e.g. to try a fix before it ships
+// the checkout is kept in the build dir like the source backend, so rebuilding the same ref is cheap
+
+/// Where a version built from git came from, kept in its install metadata
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GitOrigin {
+  pub url: String,
+  pub reference: String,
+}
+
+/// The version a tag names, R's own tags look like R-4-3-1 (R-4-3-1-rc1 for a pre-release), others like v4.3.1 or 4.3.1
+pub fn version_from_ref(reference: &str) -> Option<Version> {
+  let name = reference.trim().trim_start_matches("refs/tags/");
+  let name = name.strip_prefix("R-").or_else(|| name.strip_prefix('v')).unwrap_or(name);
+  if name.contains('.') {
+    return Version::parse(name).ok();
+  }
+  // the dashes between numbers are dots, the first one after them starts the pre-release and the rest split it
+  let parts: Vec<&str> = name.split('-').collect();
+  let numbers = parts.iter().take_while(|p| !p.is_empty() && p.chars().all(|c| c.is_ascii_digit())).count();
+  let release = parts[..numbers].join(".");
+  match &parts[numbers..] {
+    [] => Version::parse(release).ok(),
+    pre_release => Version::parse(format!("{}-{}", release, pre_release.join("."))).ok(),
+  }
+}
+
+/// Fetches reference (HEAD without one) of the repository at url, builds it and installs it as version
+pub fn install_from_git(url: &str, reference: Option<&str>, version: &Version, reporter: &dyn Reporter, cancel: &CancellationToken) -> Result<PathBuf, InstallError> {
+  let dest = install_dir(version);
+  if is_installed(version) {
+    reporter.report(Progress::Skipped(version));
+    return Ok(dest);
+  }
+  Policy::get().check(version, Some(url))?;
+  let sources = build_dir(version).join("git");
+  std::fs::create_dir_all(&sources)?;
+  reporter.report(Progress::Downloading(version, url));
+  // init + fetch instead of clone, so a sha works as well as a tag or a branch
+  let reference = reference.unwrap_or("HEAD");
+  let steps: [&[&str]; 3] = [
+    &["init", "--quiet"],
+    &["fetch", "--depth", "1", url, reference],
+    &["checkout", "--force", "--quiet", "FETCH_HEAD"],
+  ];
+  for args in steps {
+    let log = OpenOptions::new().create(true).append(true).open(build_log(version))?;
+    let git = cmd("git", args).dir(&sources).stderr_to_stdout().stdout_file(log);
+    cancel::run(git, cancel).map_err(|err| build_error(&format!("git {}", args[0]), version, err, cancel))?;
+  }
+  reporter.report(Progress::Installing(version));
+  let transaction = Transaction::begin(version, &dest)?;
+  run_build(version, None, &sources, &transaction, reporter, cancel)?;
+  let dest = transaction.commit()?;
+  let origin = GitOrigin { url: url.to_owned(), reference: reference.to_owned() };
+  InstallMeta::update(version, |meta| meta.git = Some(origin))?;
+  audit::record_install(version);
+  Ok(dest)
+}
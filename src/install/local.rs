@@ -0,0 +1,81 @@
+use std::path::{Path, PathBuf};
+use crate::audit;
+use crate::install::{install_dir, is_installed, rebase_moved, versions_dir, InstallError, Progress, Reporter};
+use crate::install::meta::InstallMeta;
+use crate::install::extract::{unpack, ArchiveFormat};
+use crate::install::transaction::{verify_tree, Transaction};
+use crate::parsing::version_parser::Version;
+use crate::policy::Policy;
+use crate::utils::copy_dir;
+
+// registering an R that rvm didn't download: something built by hand or copied from another machine
+// it goes through the same staging and verification as any other install
+
+/// Installs the tree in path (a dir or an archive of one, see extract) as version
+pub fn install_local(path: &Path, version: &Version, reporter: &dyn Reporter) -> Result<PathBuf, InstallError> {
+  let dest = install_dir(version);
+  if is_installed(version) {
+    reporter.report(Progress::Skipped(version));
+    return Ok(dest);
+  }
+  Policy::get().check(version, None)?;
+  let path = std::fs::canonicalize(path)?;
+  reporter.report(Progress::Installing(version));
+  let transaction = Transaction::begin(version, &dest)?;
+  if path.is_dir() {
+    copy_dir(&path, transaction.staging())?;
+  } else if let Some(format) = path.file_name().and_then(|n| ArchiveFormat::from_name(&n.to_string_lossy())) {
+    unpack(version, &path, format, transaction.staging(), reporter)?;
+    hoist_single_dir(transaction.staging())?;
+  } else {
+    return Err(InstallError::Verify(format!("{} is neither a dir nor a .tar.gz, .tar.xz, .tar.zst or .zip", path.display())));
+  }
+  let dest = transaction.commit()?;
+  InstallMeta::update(version, |meta| meta.registered_from = Some(path))?;
+  audit::record_install(version);
+  Ok(dest)
+}
+
+/// Moves dir, an install rvm skipped for its name (see scan), to where version's go and records it
+/// its files still point into dir, so they're rebased like a moved install's before it's used. Callers hold the lock
+pub fn adopt(dir: &Path, version: &Version) -> Result<PathBuf, InstallError> {
+  let dest = install_dir(version);
+  if is_installed(version) {
+    return Err(InstallError::AlreadyInstalled(version.clone(), dest));
+  }
+  Policy::get().check(version, None)?;
+  let dir = std::fs::canonicalize(dir)?;
+  verify_tree(&dir)?;
+  // an empty dir named after version is what an interrupted install leaves
+  if dest.exists() {
+    std::fs::remove_dir(&dest)?;
+  }
+  std::fs::create_dir_all(versions_dir())?;
+  std::fs::rename(&dir, &dest)?;
+  InstallMeta::installed(version, &dest)?;
+  InstallMeta::update(version, |meta| {
+    meta.registered_from = Some(dir.clone());
+    meta.prefix = Some(dir);
+  })?;
+  rebase_moved(version)?;
+  audit::record_install(version);
+  Ok(dest)
+}
+
+// tarballs usually wrap everything in one R-x.y.z\ dir, the install should be its contents
+fn hoist_single_dir(staging: &Path) -> Result<(), InstallError> {
+  if verify_tree(staging).is_ok() {
+    return Ok(());
+  }
+  let entries: Vec<PathBuf> = staging.read_dir()?.filter_map(|e| e.ok()).map(|e| e.path()).collect();
+  let [inner] = entries.as_slice() else { return Ok(()) };
+  if !inner.is_dir() {
+    return Ok(());
+  }
+  for entry in inner.read_dir()? {
+    let entry = entry?;
+    std::fs::rename(entry.path(), staging.join(entry.file_name()))?;
+  }
+  std::fs::remove_dir(inner)?;
+  Ok(())
+}
@@ -0,0 +1,245 @@
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+use crate::install::{install_dir, versions_dir, InstallError};
+use crate::local_utils::{is_system, system_home};
+use crate::utils::{dir_size, share_tree};
+use crate::install::git::GitOrigin;
+use crate::install::protect::Protect;
+use crate::parsing::version_parser::Version;
+
+// what rvm knows about an install, kept out of the install tree in versions\.meta\:
+//   <version>.json   one per install, the source of truth (<version>.yaml before, read and replaced on the next change)
+//   index.json       all of them in one file, so list and prune read one file instead of one per install
+// every change goes through update(), which holds .meta\.lock while it reads, writes and reindexes,
+// so parallel installs (install --jobs, two rvm at once) can't lose each other's changes.
+// files are written to a temp file and renamed into place, a reader never sees half of one
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InstallMeta {
+  /// pinned versions are skipped by prune and refused by uninstall
+  pub pinned: bool,
+  /// the tarball or dir it was registered from with `rvm install --path`, none if rvm downloaded it
+  pub registered_from: Option<PathBuf>,
+  /// the repository and ref it was built from with `rvm install --git`
+  pub git: Option<GitOrigin>,
+  /// the artifact rvm downloaded for it and its sha256, what project lockfiles record
+  /// no sha256 when the download wasn't hashed this time (a build that reused its sources)
+  pub url: Option<String>,
+  pub sha256: Option<String>,
+  /// installed by `rvm try` and removed once its command is done, left over if rvm was killed in between
+  pub trial: bool,
+  /// unix seconds of when it was moved into place, none for installs from before it was recorded
+  pub installed_at: Option<u64>,
+  /// bytes of the install then
+  pub size: Option<u64>,
+  /// unix seconds of when it last ran through rvm (exec, each, try), within the hour, none if it never did
+  pub last_used: Option<u64>,
+  /// the dir its files point into (bin/R, shebangs, .pc files), where it was installed unless it was rebased since
+  pub prefix: Option<PathBuf>,
+  /// the index's env for it when it was installed, activating doesn't look at the index
+  #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+  pub env: BTreeMap<String, String>,
+  /// how it's protected from changes now, see protect.rs
+  pub protection: Protect,
+  /// bytes of its files that share their blocks with another install's, by version, see dedup.rs
+  #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+  pub shared: BTreeMap<Version, u64>,
+}
+
+/// Where an install came from, what `rvm list --origin` shows and `rvm verify --repair` fetches again
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum InstallOrigin {
+  /// downloaded from the index's url, the installer or the source tarball
+  Registry { url: String, sha256: Option<String> },
+  Git { url: String, reference: String },
+  /// registered or adopted from path
+  Local { path: PathBuf },
+}
+
+impl std::fmt::Display for InstallOrigin {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      InstallOrigin::Registry { url, .. } => write!(f, "{}", url),
+      InstallOrigin::Git { url, reference } => write!(f, "{}@{}", url, reference),
+      InstallOrigin::Local { path } => write!(f, "{}", path.display()),
+    }
+  }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct MetaIndex {
+  installs: BTreeMap<Version, InstallMeta>,
+}
+
+// held while changing anything in dir(), released when dropped
+struct StoreLock(File);
+
+impl StoreLock {
+  fn acquire() -> std::io::Result<Self> {
+    std::fs::create_dir_all(InstallMeta::dir())?;
+    let file = OpenOptions::new().write(true).create(true).truncate(false).open(InstallMeta::dir().join(".lock"))?;
+    file.lock_exclusive()?;
+    Ok(Self(file))
+  }
+}
+
+impl Drop for StoreLock {
+  fn drop(&mut self) {
+    let _ = FileExt::unlock(&self.0);
+  }
+}
+
+impl InstallMeta {
+  pub fn dir() -> PathBuf {
+    versions_dir().join(".meta")
+  }
+
+  pub fn path(version: &Version) -> PathBuf {
+    Self::dir().join(format!("{}.json", version))
+  }
+
+  fn legacy_path(version: &Version) -> PathBuf {
+    Self::dir().join(format!("{}.yaml", version))
+  }
+
+  fn index_path() -> PathBuf {
+    Self::dir().join("index.json")
+  }
+
+  /// Missing or unreadable metadata is the default, an install from before it existed
+  pub fn load(version: &Version) -> Self {
+    let json = || std::fs::read(Self::path(version)).ok().and_then(|bytes| serde_json::from_slice(&bytes).ok());
+    let yaml = || File::open(Self::legacy_path(version)).ok().and_then(|file| serde_yaml::from_reader(file).ok());
+    json().or_else(yaml).unwrap_or_default()
+  }
+
+  /// Changes the metadata of version with change, returning what it is now
+  pub fn update(version: &Version, change: impl FnOnce(&mut InstallMeta)) -> Result<Self, InstallError> {
+    let _lock = StoreLock::acquire()?;
+    let mut meta = Self::load(version);
+    change(&mut meta);
+    write_atomic(&Self::path(version), &serde_json::to_vec_pretty(&meta).map_err(invalid_data)?)?;
+    if Self::legacy_path(version).exists() {
+      std::fs::remove_file(Self::legacy_path(version))?;
+    }
+    Self::reindex()?;
+    Ok(meta)
+  }
+
+  /// Records that version just got installed into dest
+  pub fn installed(version: &Version, dest: &Path) -> Result<Self, InstallError> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    Self::update(version, |meta| {
+      meta.installed_at = Some(now);
+      meta.size = Some(dir_size(dest));
+      meta.prefix = Some(dest.to_path_buf());
+    })
+  }
+
+  /// Records that version is running now, at most once an hour so a script calling Rscript in a loop
+  /// doesn't rewrite the store every time
+  pub fn used(version: &Version) -> Result<(), InstallError> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    if Self::load(version).last_used.is_some_and(|at| now.saturating_sub(at) < 60 * 60) {
+      return Ok(());
+    }
+    Self::update(version, |meta| meta.last_used = Some(now)).map(|_| ())
+  }
+
+  /// Where it came from, none for installs from before rvm recorded it
+  pub fn origin(&self) -> Option<InstallOrigin> {
+    if let Some(git) = &self.git {
+      return Some(InstallOrigin::Git { url: git.url.clone(), reference: git.reference.clone() });
+    }
+    if let Some(path) = &self.registered_from {
+      return Some(InstallOrigin::Local { path: path.clone() });
+    }
+    self.url.as_ref().map(|url| InstallOrigin::Registry { url: url.clone(), sha256: self.sha256.clone() })
+  }
+
+  /// When version was last used, or installed if it never ran since, none if neither is known
+  pub fn unused_since(&self) -> Option<u64> {
+    self.last_used.or(self.installed_at)
+  }
+
+  pub fn remove(version: &Version) -> Result<(), InstallError> {
+    let _lock = StoreLock::acquire()?;
+    for path in [Self::path(version), Self::legacy_path(version)] {
+      if path.exists() {
+        std::fs::remove_file(path)?;
+      }
+    }
+    Self::reindex()
+  }
+
+  /// The metadata of every install that has some, from the index (rebuilt when it's missing or broken)
+  pub fn all() -> BTreeMap<Version, InstallMeta> {
+    let index = std::fs::read(Self::index_path()).ok().and_then(|bytes| serde_json::from_slice::<MetaIndex>(&bytes).ok());
+    match index {
+      Some(index) => index.installs,
+      None => {
+        let rebuilt = Self::scan();
+        // whoever changes something next writes it, this only reads
+        if let Ok(_lock) = StoreLock::acquire() {
+          let _ = Self::reindex();
+        }
+        rebuilt
+      },
+    }
+  }
+
+  /// Rewrites the index from the per install files, after something removed some of them
+  pub fn rebuild_index() -> Result<(), InstallError> {
+    let _lock = StoreLock::acquire()?;
+    Self::reindex()
+  }
+
+  /// The size of the install, recorded when it was installed or measured now for older ones
+  pub fn size_of(&self, version: &Version) -> u64 {
+    self.size.unwrap_or_else(|| dir_size(&install_dir(version)))
+  }
+
+  // every per install file in dir(), with the lock held or not
+  fn scan() -> BTreeMap<Version, InstallMeta> {
+    let Ok(entries) = Self::dir().read_dir() else { return BTreeMap::new() };
+    entries.filter_map(|e| e.ok())
+      .filter_map(|entry| {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let stem = name.strip_suffix(".json").or_else(|| name.strip_suffix(".yaml"))?;
+        Version::parse(stem).ok()
+      })
+      .map(|version| (version.clone(), Self::load(&version)))
+      .collect()
+  }
+
+  // with the lock held
+  fn reindex() -> Result<(), InstallError> {
+    let index = MetaIndex { installs: Self::scan() };
+    write_atomic(&Self::index_path(), &serde_json::to_vec_pretty(&index).map_err(invalid_data)?)?;
+    if is_system() {
+      share_tree(&system_home(), &Self::dir())?;
+    }
+    Ok(())
+  }
+}
+
+fn invalid_data(err: serde_json::Error) -> std::io::Error {
+  std::io::Error::new(std::io::ErrorKind::InvalidData, err)
+}
+
+// through a temp file in the same dir, rename replaces the old one at once
+fn write_atomic(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+  let mut temp = path.as_os_str().to_owned();
+  temp.push(format!(".tmp-{}", std::process::id()));
+  let temp = PathBuf::from(temp);
+  let mut file = File::create(&temp)?;
+  file.write_all(bytes)?;
+  file.sync_all()?;
+  std::fs::rename(&temp, path)
+}
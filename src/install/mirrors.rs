@@ -0,0 +1,118 @@
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+use crate::config::Mirror;
+use crate::layout::cache_dir;
+
+// how the configured mirrors have been answering, so downloads try the healthy ones first:
+//   a mirror that failed lately goes after the ones that didn't, more so the more times in a row it failed,
+//   and among the rest the one that answered fastest goes first
+// in <cache dir>/mirror-health.json, keyed by the mirror's `to`, changed with an flock on it like stats.json.
+// a failure is forgotten after FAILURE_MEMORY, so a mirror that was down gets tried again first in line
+
+/// How long a failure counts against a mirror, in seconds
+pub const FAILURE_MEMORY: u64 = 60 * 60 * 6;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Health {
+  pub successes: u64,
+  pub failures: u64,
+  /// failures since the last success
+  pub failing: u32,
+  /// unix seconds
+  pub last_failure: Option<u64>,
+  /// time to the response, averaged over the recent answers
+  pub latency_ms: Option<u64>,
+}
+
+impl Health {
+  /// What orders attempts, lower goes first: the failures in a row while they're recent, then the latency
+  pub fn penalty(&self, now: u64) -> (u32, u64) {
+    let failing = match self.last_failure {
+      Some(at) if now.saturating_sub(at) < FAILURE_MEMORY => self.failing,
+      _ => 0,
+    };
+    (failing, self.latency_ms.unwrap_or(0))
+  }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MirrorHealth {
+  pub mirrors: BTreeMap<String, Health>,
+}
+
+impl MirrorHealth {
+  pub fn path() -> PathBuf {
+    cache_dir().join("mirror-health.json")
+  }
+
+  /// What's been measured, nothing when there's no (readable) file
+  pub fn load() -> Self {
+    std::fs::read(Self::path()).ok().and_then(|bytes| serde_json::from_slice(&bytes).ok()).unwrap_or_default()
+  }
+
+  /// The mirrors that rewrite url, healthiest first (config order between equally healthy ones), with what they make of it
+  /// just url when none does
+  pub fn attempts<'a>(&self, mirrors: &'a [Mirror], url: &str) -> Vec<(Option<&'a Mirror>, String)> {
+    let mut attempts: Vec<(Option<&Mirror>, String)> = mirrors.iter()
+      .filter_map(|m| url.strip_prefix(m.from.as_str()).map(|rest| (Some(m), format!("{}{}", m.to, rest))))
+      .collect();
+    if attempts.is_empty() {
+      return vec![(None, url.to_owned())];
+    }
+    let now = now();
+    attempts.sort_by_key(|(m, _)| m.and_then(|m| self.mirrors.get(&m.to)).map(|h| h.penalty(now)).unwrap_or_default());
+    attempts
+  }
+}
+
+/// Records an answer from mirror after latency_ms
+pub fn record_success(mirror: &Mirror, latency_ms: u64) {
+  record(mirror, |health| {
+    health.successes += 1;
+    health.failing = 0;
+    // the old average weighs 3 to 1, so one slow answer doesn't push a mirror to the back
+    health.latency_ms = Some(health.latency_ms.map_or(latency_ms, |avg| (avg * 3 + latency_ms) / 4));
+  });
+}
+
+/// Records that mirror didn't answer, or answered with an error
+pub fn record_failure(mirror: &Mirror) {
+  record(mirror, |health| {
+    health.failures += 1;
+    health.failing += 1;
+    health.last_failure = Some(now());
+  });
+}
+
+// recording never fails a download, a file that can't be written only loses that measurement
+fn record(mirror: &Mirror, change: impl FnOnce(&mut Health)) {
+  if let Err(err) = update(|health| change(health.mirrors.entry(mirror.to.clone()).or_default())) {
+    tracing::debug!(error = %err, "can't record mirror health");
+  }
+}
+
+fn update(change: impl FnOnce(&mut MirrorHealth)) -> std::io::Result<()> {
+  std::fs::create_dir_all(cache_dir())?;
+  let mut file: File = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(MirrorHealth::path())?;
+  file.lock_exclusive()?;
+  let mut text = String::new();
+  file.read_to_string(&mut text)?;
+  let mut health: MirrorHealth = serde_json::from_str(&text).unwrap_or_default();
+  change(&mut health);
+  file.set_len(0)?;
+  file.seek(SeekFrom::Start(0))?;
+  file.write_all(&serde_json::to_vec_pretty(&health).map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?)?;
+  file.flush()?;
+  FileExt::unlock(&file)
+}
+
+pub fn now() -> u64 {
+  SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
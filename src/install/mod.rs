@@ -0,0 +1,412 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use duct::cmd;
+use reqwest::StatusCode;
+use thiserror::Error;
+use crate::config::{Backend, Config, Mirror};
+use crate::index::{is_offline, ArtifactMeta};
+use crate::layout::{data_dir, version_names};
+use crate::auth::WithAuth;
+use crate::local_utils::client_for;
+use crate::audit::{self, Change};
+use crate::cancel::{self, CancellationToken};
+use crate::observer;
+use crate::throttle;
+use crate::parsing::version_parser::Version;
+use crate::policy::{Denied, Policy};
+use crate::hooks::HookError;
+use crate::utils::{ProgressReader, ResponseToFileError};
+use meta::InstallMeta;
+use transaction::Transaction;
+use mirrors::MirrorHealth;
+use digest::{Algorithm, Digest};
+use delta::Kind;
+
+pub mod transaction;
+pub mod meta;
+pub mod source;
+pub mod local;
+pub mod git;
+pub mod verify;
+pub mod scan;
+pub mod delta;
+pub mod extract;
+pub mod relocate;
+pub mod digest;
+pub mod receipt;
+pub mod dedup;
+pub mod protect;
+pub mod mirrors;
+pub mod startup;
+pub mod budget;
+
+#[derive(Error, Debug)]
+pub enum InstallError {
+  #[error("error in downloading {0}, status {1}")]
+  Status(String, StatusCode),
+  #[error("error in downloading")]
+  Reqwest(#[from] reqwest::Error),
+  #[error("error in downloading {0}, rvm is offline")]
+  Offline(String),
+  #[error("error in saving the download")]
+  Download(#[from] ResponseToFileError),
+  #[error("error in running the installer")]
+  Io(#[from] io::Error),
+  #[error("error in unpacking {}: {1}", .0.display())]
+  Unpack(PathBuf, String),
+  #[error("error in relocating the install, {0}")]
+  Relocate(String),
+  #[error("error in verifying the install: {0}")]
+  Verify(String),
+  #[error("{} won't start, {1}", .0.display())]
+  Startup(PathBuf, String),
+  #[error("error in building, {0} failed: {2} (log in {1})")]
+  Build(String, PathBuf, #[source] io::Error),
+  #[error("R {0} is pinned, `rvm unpin {0}` first")]
+  Pinned(Version),
+  #[error("R {0} is already installed in {}", .1.display())]
+  AlreadyInstalled(Version, PathBuf),
+  #[error("{0} hashes to {2}, expected {1}")]
+  Checksum(String, String, String),
+  #[error("error in verifying {0}, {1}")]
+  UnsupportedDigest(String, String),
+  #[error("{0} is only checked with {1}, which is broken, --insecure-digest accepts it anyway")]
+  WeakDigest(String, Algorithm),
+  #[error(transparent)]
+  Denied(#[from] Denied),
+  #[error(transparent)]
+  Hook(#[from] HookError),
+  #[error("cancelled")]
+  Cancelled,
+}
+
+// every managed version lives in <data dir>\versions\<version>\, or under the name dirs.version_names gives it
+pub fn versions_dir() -> PathBuf {
+  data_dir().join("versions")
+}
+
+pub fn install_dir(version: &Version) -> PathBuf {
+  versions_dir().join(version_names().name(version))
+}
+
+pub fn is_installed(version: &Version) -> bool {
+  let dir = install_dir(version);
+  dir.exists() && dir.read_dir().map(|mut d| d.next().is_some()).unwrap_or(false)
+}
+
+/// Versions in versions_dir, oldest first, see scan for what's skipped
+pub fn installed_versions() -> Vec<Version> {
+  scan::scan(&versions_dir()).pool.into_vec()
+}
+
+/// Removes what version's install created (see receipt.rs), returning where what it didn't create was moved to
+/// installs from before receipts are removed whole
+pub fn uninstall(version: &Version) -> Result<Option<PathBuf>, InstallError> {
+  if InstallMeta::load(version).pinned {
+    return Err(InstallError::Pinned(version.clone()));
+  }
+  protect::unlock(version)?;
+  let sha256 = InstallMeta::load(version).sha256;
+  let leftovers = match receipt::load(version) {
+    Some(listed) => receipt::remove(version, &listed, &install_dir(version))?,
+    None => {
+      std::fs::remove_dir_all(install_dir(version))?;
+      None
+    },
+  };
+  for path in [verify::sums_path(version), receipt::receipt_path(version)] {
+    if path.exists() {
+      std::fs::remove_file(path)?;
+    }
+  }
+  InstallMeta::remove(version)?;
+  audit::record(Change::Uninstall { version: version.clone(), sha256 });
+  Ok(leftovers)
+}
+
+pub enum Progress<'a> {
+  Downloading(&'a Version, &'a str),
+  /// bytes downloaded so far and the total if the server said
+  Downloaded(&'a Version, u64, Option<u64>),
+  /// bytes of the archive read so far and its size
+  Unpacking(&'a Version, u64, u64),
+  Installing(&'a Version),
+  /// a build from source step started, e.g. `make -j8`
+  Building(&'a Version, &'a str),
+  Skipped(&'a Version),
+  /// bytes of the install hashed so far by `rvm verify` and how many it has
+  Verifying(&'a Version, u64, u64),
+  Done(&'a Version, &'a Result<PathBuf, InstallError>),
+}
+
+// where long operations tell how they're going, the cli shows bars but anything embedding rvm can listen
+pub trait Reporter: Sync {
+  fn report(&self, progress: Progress);
+}
+
+impl<F: Fn(Progress) + Sync> Reporter for F {
+  fn report(&self, progress: Progress) {
+    self(progress)
+  }
+}
+
+/// Ignores everything
+pub struct NoReporter;
+
+impl Reporter for NoReporter {
+  fn report(&self, _progress: Progress) {}
+}
+
+// how artifacts get to disk, so tests and embedders can swap the http client out
+pub trait Downloader: Sync {
+  /// Downloads url (after mirror rewriting) to a file called filename in the temp dir
+  /// on_bytes gets the bytes downloaded so far and the total, when known
+  /// a cancelled download stops and leaves nothing in the temp dir
+  fn download(&self, url: &str, filename: &str, on_bytes: &dyn Fn(u64, Option<u64>), cancel: &CancellationToken) -> Result<PathBuf, InstallError>;
+}
+
+pub struct HttpDownloader;
+
+impl Downloader for HttpDownloader {
+  #[tracing::instrument(skip(self, on_bytes, cancel))]
+  fn download(&self, url: &str, filename: &str, on_bytes: &dyn Fn(u64, Option<u64>), cancel: &CancellationToken) -> Result<PathBuf, InstallError> {
+    cancel.check()?;
+    let (_permit, response) = send_to_healthiest(url)?;
+    let total = response.content_length();
+    tracing::debug!(status = %response.status(), ?total, "response");
+    let path = std::env::temp_dir().join(filename);
+    let mut file = std::fs::File::create(&path).map_err(ResponseToFileError::from)?;
+    let mut reader = ProgressReader::new(cancel.reader(response), |read| on_bytes(read, total));
+    if let Err(err) = io::copy(&mut reader, &mut file) {
+      drop(file);
+      let _ = std::fs::remove_file(&path);
+      cancel.check()?;
+      return Err(ResponseToFileError::from(err).into());
+    }
+    Ok(path)
+  }
+}
+
+// the answer of the first of url's mirrors that gives one, healthiest first, each answer recorded against its mirror
+// a mirror that fails before sending anything is skipped for the next, the last one's error is the download's
+// a mirror the policy refuses is skipped too, without counting against its health
+fn send_to_healthiest(url: &str) -> Result<(throttle::Permit, reqwest::blocking::Response), InstallError> {
+  let policy = Policy::get();
+  let mut failed = None;
+  for (mirror, url) in MirrorHealth::load().attempts(&Config::get().mirrors, url) {
+    if is_offline() {
+      return Err(InstallError::Offline(url));
+    }
+    if let Some(err) = &failed {
+      tracing::warn!(error = %err, "mirror failed, trying the next one");
+    }
+    if let Err(denied) = policy.check_url(&url) {
+      failed = Some(InstallError::from(denied));
+      continue;
+    }
+    tracing::info!(%url, "downloading");
+    let started = std::time::Instant::now();
+    let client = client_for(&url);
+    let err = match throttle::send(&url, || client.get(&url).with_auth(&url)) {
+      Ok((permit, response)) if response.status() == StatusCode::OK => {
+        if let Some(mirror) = mirror {
+          mirrors::record_success(mirror, started.elapsed().as_millis() as u64);
+        }
+        return Ok((permit, response));
+      },
+      Ok((_, response)) => InstallError::Status(url.clone(), response.status()),
+      Err(err) => InstallError::from(err),
+    };
+    if let Some(mirror) = mirror {
+      mirrors::record_failure(mirror);
+    }
+    failed = Some(err);
+  }
+  Err(failed.expect("there's always an attempt, the url itself without mirrors"))
+}
+
+pub fn installer_name(version: &Version) -> String {
+  format!("R-{}-win.exe", version)
+}
+
+// the installer flags, shared with the async installer
+pub fn installer_args(dest: &Path) -> Vec<String> {
+  vec![
+    format!(r"/dir={}\", dest.display()),
+    "/verysilent".to_owned(),
+    "/mergetasks=!desktopicon".to_owned(),
+    "/currentuser".to_owned(),
+  ]
+}
+
+/// Installs one version into install_dir, with the prebuilt installer or by building it
+#[tracing::instrument(skip_all, fields(version = %meta.version, ?backend))]
+/// cancel stops it between steps and kills the installer or build, the staged install is rolled back
+pub fn install(meta: &ArtifactMeta, backend: Backend, downloader: &dyn Downloader, reporter: &dyn Reporter, cancel: &CancellationToken) -> Result<PathBuf, InstallError> {
+  if let Some(dest) = skip_installed(meta, reporter) {
+    return Ok(dest);
+  }
+  if backend == Backend::Source {
+    cancel.check()?;
+    return source::install_from_source(meta, &install_dir(&meta.version), downloader, reporter, cancel);
+  }
+  before_download(meta, reporter, cancel)?;
+  let on_bytes = |read, total| reporter.report(Progress::Downloaded(&meta.version, read, total));
+  let installer = delta::download(meta, Kind::Installer, &meta.url, &installer_name(&meta.version), downloader, &on_bytes, cancel)?;
+  let (transaction, sha256) = stage(meta, &installer, reporter)?;
+  tracing::info!(installer = %installer.display(), "running installer");
+  cancel::run(cmd(&installer, installer_args(transaction.staging())), cancel)?;
+  finish(meta, transaction, sha256, &installer)
+}
+
+// the steps install shares with nonblocking::install, so both refuse, stop and roll back the same way:
+// where it already is when there's nothing to do
+pub(crate) fn skip_installed(meta: &ArtifactMeta, reporter: &dyn Reporter) -> Option<PathBuf> {
+  let dest = install_dir(&meta.version);
+  if !is_installed(&meta.version) {
+    return None;
+  }
+  tracing::info!(dest = %dest.display(), "already installed");
+  reporter.report(Progress::Skipped(&meta.version));
+  Some(dest)
+}
+
+// whether the installer can be downloaded at all
+pub(crate) fn before_download(meta: &ArtifactMeta, reporter: &dyn Reporter, cancel: &CancellationToken) -> Result<(), InstallError> {
+  cancel.check()?;
+  Policy::get().check(&meta.version, Some(&meta.url))?;
+  reporter.report(Progress::Downloading(&meta.version, &meta.url));
+  Ok(())
+}
+
+// the downloaded installer checked and a transaction for it to install into, rolled back when it's dropped
+pub(crate) fn stage(meta: &ArtifactMeta, installer: &Path, reporter: &dyn Reporter) -> Result<(Transaction, String), InstallError> {
+  let sha256 = verify_download(&meta.url, installer, meta.sha256.as_deref())?;
+  reporter.report(Progress::Installing(&meta.version));
+  Ok((Transaction::begin(&meta.version, &install_dir(&meta.version))?, sha256))
+}
+
+// what the installer left in transaction put into place and recorded
+pub(crate) fn finish(meta: &ArtifactMeta, transaction: Transaction, sha256: String, installer: &Path) -> Result<PathBuf, InstallError> {
+  relocate(meta, &transaction, transaction.dest())?;
+  let dest = transaction.commit()?;
+  record_artifact(meta, &meta.url, Some(sha256))?;
+  audit::record_install(&meta.version);
+  keep_artifact(&meta.version, Kind::Installer, installer);
+  Ok(dest)
+}
+
+// the index's relocation recipe, run on the staged tree so verification and checksums see the fixed files
+pub(crate) fn relocate(meta: &ArtifactMeta, transaction: &Transaction, dest: &Path) -> Result<(), InstallError> {
+  if let Some(relocation) = &meta.relocate {
+    relocation.run(&meta.version, transaction.staging(), dest)?;
+  }
+  Ok(())
+}
+
+/// Rebases version's install when its files still point where it was installed, after RVM_HOME (or the data dir)
+/// moved the tree, returning where that was. Installs from before rvm recorded it are taken as where they are
+pub fn rebase_moved(version: &Version) -> Result<Option<PathBuf>, InstallError> {
+  let dest = install_dir(version);
+  let Some(from) = InstallMeta::load(version).prefix.filter(|p| p != &dest) else { return Ok(None) };
+  let changed = protect::unlocked(version, || relocate::rebase(version, &dest, &from, &dest))?;
+  verify::rehash(version, &dest, &changed)?;
+  InstallMeta::update(version, |meta| meta.prefix = Some(dest.clone()))?;
+  tracing::info!(%version, from = %from.display(), files = changed.len(), "rebased moved install");
+  Ok(Some(from))
+}
+
+// a base for deltas to the next release, not having one only makes that one a full download
+pub(crate) fn keep_artifact(version: &Version, kind: Kind, path: &Path) {
+  if let Err(err) = delta::keep(version, kind, path) {
+    tracing::warn!(error = %err, "failed to keep the artifact for deltas");
+  }
+}
+
+/// Hashes a download with the algorithm expected is in (sha256 without one), refusing it if the registry said it
+/// should hash to something else or only gave a broken algorithm (see digest.rs). The digest, for recording
+pub fn verify_download(url: &str, path: &Path, expected: Option<&str>) -> Result<String, InstallError> {
+  verify_download_with(url, path, expected, digest::insecure_allowed())
+}
+
+/// verify_download accepting a broken algorithm when insecure is, whatever --insecure-digest says
+pub fn verify_download_with(url: &str, path: &Path, expected: Option<&str>, insecure: bool) -> Result<String, InstallError> {
+  let refuse = |err: InstallError| {
+    let _ = std::fs::remove_file(path);
+    Err(err)
+  };
+  let expected = match expected.map(Digest::parse).transpose() {
+    Ok(expected) => expected,
+    Err(err) => return refuse(InstallError::UnsupportedDigest(url.to_owned(), err)),
+  };
+  let algorithm = expected.as_ref().map_or(Algorithm::Sha256, |d| d.algorithm);
+  if !algorithm.is_supported() {
+    return refuse(InstallError::UnsupportedDigest(url.to_owned(), format!("rvm was built without the {} feature", algorithm)));
+  }
+  if algorithm.is_weak() {
+    if !insecure {
+      return refuse(InstallError::WeakDigest(url.to_owned(), algorithm));
+    }
+    tracing::warn!(%url, %algorithm, "accepting a download checked with a broken digest");
+  }
+  let digest = Digest::of_file(algorithm, path)?;
+  tracing::debug!(%url, %digest, "downloaded");
+  match expected {
+    Some(expected) if !expected.matches(&digest) => refuse(InstallError::Checksum(url.to_owned(), expected.to_string(), digest.to_string())),
+    _ => Ok(digest.to_string()),
+  }
+}
+
+// keeps what was downloaded in the metadata, so lockfiles can pin it, and the index's env for activating it
+pub(crate) fn record_artifact(artifact: &ArtifactMeta, url: &str, sha256: Option<String>) -> Result<(), InstallError> {
+  InstallMeta::update(&artifact.version, |meta| {
+    meta.url = Some(url.to_owned());
+    meta.sha256 = sha256;
+    meta.env = artifact.env.clone();
+  }).map(|_| ())
+}
+
+/// What install would do for meta, step by step, for --dry-run
+pub fn plan(meta: &ArtifactMeta, backend: Backend) -> Vec<String> {
+  let dest = install_dir(&meta.version);
+  if is_installed(&meta.version) {
+    return vec![format!("nothing, already installed in {}", dest.display())];
+  }
+  let staging = Transaction::staging_for(&meta.version);
+  let mirrors = Config::get().mirrors;
+  let mut steps = vec![];
+  if backend == Backend::Source {
+    steps.extend(source::plan(meta, &staging, &dest, &mirrors));
+  } else {
+    let installer = std::env::temp_dir().join(installer_name(&meta.version));
+    steps.push(format!("download {} to {}", Mirror::rewrite(&mirrors, &meta.url), installer.display()));
+    steps.push(format!("run {} {}", installer.display(), installer_args(&staging).join(" ")));
+  }
+  if let Some(relocation) = &meta.relocate {
+    steps.push(format!("relocate it from {} to {}", relocation.prefix_for(&meta.version), dest.display()));
+  }
+  steps.push(format!("move {} to {}", staging.display(), dest.display()));
+  steps
+}
+
+/// Installs several versions at once with at most `jobs` running at the same time
+/// A failed version doesn't stop the others, every result is returned in the same order as metas
+pub fn install_many(metas: &[ArtifactMeta], jobs: usize, backend: Backend, downloader: &dyn Downloader, reporter: &dyn Reporter, cancel: &CancellationToken) -> Vec<Result<PathBuf, InstallError>> {
+  let queue = Mutex::new(metas.iter().enumerate());
+  let results: Mutex<Vec<Option<Result<PathBuf, InstallError>>>> = Mutex::new(metas.iter().map(|_| None).collect());
+  std::thread::scope(|scope| {
+    for _ in 0..jobs.clamp(1, metas.len().max(1)) {
+      scope.spawn(|| loop {
+        let next = queue.lock().unwrap().next(); // lock is dropped right away
+        let Some((i, meta)) = next else { break };
+        // whatever failed once it was cancelled failed because of it (a killed build, a cut download)
+        let result = install(meta, backend, downloader, reporter, cancel).map_err(|err| if cancel.is_cancelled() { InstallError::Cancelled } else { err });
+        reporter.report(Progress::Done(&meta.version, &result));
+        observer::notify(|o| o.on_install_complete(&meta.version, &result));
+        results.lock().unwrap()[i] = Some(result);
+      });
+    }
+  });
+  results.into_inner().unwrap().into_iter().map(|r| r.expect("every version is handled by a worker")).collect()
+}
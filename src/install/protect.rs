@@ -0,0 +1,125 @@
+use std::io;
+use std::path::Path;
+use duct::cmd;
+use serde::{Deserialize, Serialize};
+use crate::install::meta::InstallMeta;
+use crate::install::{install_dir, InstallError};
+use crate::parsing::version_parser::Version;
+
+// an install is made read-only once it's committed and its sums are recorded, so nothing edits it in place by accident
+// (a script writing into R_HOME, a package installed into the tree's own library), which `rvm verify` would flag.
+// `protect: immutable` also sets the filesystem's immutable flag (chattr +i, chflags uchg, a deny entry in the ACL on
+// windows), which even root has to take off before changing anything, setting it usually needs root too.
+// `rvm unlock` makes an install writable again and `rvm lock` protects it again, what's applied is in its metadata.
+// rvm unlocks what it changes itself (uninstall, rebasing, repair) and locks it again after.
+// files deduplicated with hard links (see dedup.rs) are one file, unlocking one install unlocks them in the other too
+
+/// How installs are protected, `protect` in the config
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Protect {
+  /// writable like any other dir
+  Off,
+  /// no write permission on its files and dirs
+  #[default]
+  ReadOnly,
+  /// read-only and immutable, falls back to read-only where the flag can't be set
+  Immutable,
+}
+
+/// Protects version's install in dir, returning the protection it got
+pub fn lock(version: &Version, dir: &Path, protect: Protect) -> Result<Protect, InstallError> {
+  if protect == Protect::Off {
+    return Ok(protect);
+  }
+  set_writable(dir, false)?;
+  let protect = match protect {
+    Protect::Immutable => match set_immutable(dir, true) {
+      Ok(()) => Protect::Immutable,
+      Err(err) => {
+        tracing::warn!(error = %err, "can't make the install immutable, it's only read-only");
+        Protect::ReadOnly
+      },
+    },
+    protect => protect,
+  };
+  InstallMeta::update(version, |meta| meta.protection = protect)?;
+  tracing::info!(%version, ?protect, "install protected");
+  Ok(protect)
+}
+
+/// Makes version's install writable again, returning what it was protected with
+pub fn unlock(version: &Version) -> Result<Protect, InstallError> {
+  let protect = InstallMeta::load(version).protection;
+  if protect == Protect::Off {
+    return Ok(protect);
+  }
+  let dir = install_dir(version);
+  if protect == Protect::Immutable {
+    set_immutable(&dir, false)?;
+  }
+  set_writable(&dir, true)?;
+  InstallMeta::update(version, |meta| meta.protection = Protect::Off)?;
+  tracing::info!(%version, "install unlocked");
+  Ok(protect)
+}
+
+/// Runs change with version's install unlocked, protecting it the way it was after, whether change worked or not
+pub fn unlocked<T>(version: &Version, change: impl FnOnce() -> Result<T, InstallError>) -> Result<T, InstallError> {
+  let protect = unlock(version)?;
+  let ret = change();
+  if install_dir(version).exists() {
+    lock(version, &install_dir(version), protect)?;
+  }
+  ret
+}
+
+/// Takes the write permission of everything under path away or gives it back (to the owner), symlinks are left alone
+pub fn set_writable(path: &Path, writable: bool) -> io::Result<()> {
+  let metadata = std::fs::symlink_metadata(path)?;
+  if metadata.file_type().is_symlink() {
+    return Ok(());
+  }
+  if metadata.is_dir() {
+    for entry in path.read_dir()? {
+      set_writable(&entry?.path(), writable)?;
+    }
+  }
+  let mut permissions = metadata.permissions();
+  #[cfg(unix)]
+  {
+    use std::os::unix::fs::PermissionsExt;
+    let mode = permissions.mode();
+    permissions.set_mode(if writable { mode | 0o200 } else { mode & !0o222 });
+  }
+  // windows has no read-only dirs, only files
+  #[cfg(not(unix))]
+  {
+    if metadata.is_dir() {
+      return Ok(());
+    }
+    permissions.set_readonly(!writable);
+  }
+  std::fs::set_permissions(path, permissions)
+}
+
+fn set_immutable(dir: &Path, immutable: bool) -> io::Result<()> {
+  #[cfg(target_os = "linux")]
+  let command = cmd!("chattr", "-R", if immutable { "+i" } else { "-i" }, dir);
+  #[cfg(target_os = "macos")]
+  let command = cmd!("chflags", "-R", if immutable { "uchg" } else { "nouchg" }, dir);
+  // everyone is denied writing, deleting and adding, in the dir and what it has
+  #[cfg(windows)]
+  let command = if immutable {
+    cmd!("icacls", dir, "/deny", "*S-1-1-0:(OI)(CI)(W,D,DC)", "/T", "/Q")
+  } else {
+    cmd!("icacls", dir, "/remove:d", "*S-1-1-0", "/T", "/Q")
+  };
+  #[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+  {
+    let _ = immutable;
+    return Err(io::Error::new(io::ErrorKind::Unsupported, format!("no immutable flag on this platform for {}", dir.display())));
+  }
+  #[cfg(any(target_os = "linux", target_os = "macos", windows))]
+  command.stdout_null().stderr_capture().run().map(|_| ())
+}
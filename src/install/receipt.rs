@@ -0,0 +1,118 @@
+use std::collections::BTreeSet;
+use std::io;
+use std::path::{Path, PathBuf};
+use crate::install::meta::InstallMeta;
+use crate::install::{versions_dir, InstallError};
+use crate::parsing::version_parser::Version;
+use crate::local_utils::{is_system, system_home};
+use crate::utils::share_tree;
+
+// everything an install created, in versions\.meta\<version>.receipt, recorded with the sums when it's committed
+// one path per line relative to the install with / separators, dirs end with a /, symlinks are there as themselves.
+// uninstall removes what's in it and nothing else, so files that came after (a package installed into the tree,
+// something of the user's) aren't lost: they're moved to versions\.leftovers\<version> instead.
+// the sums (see verify.rs) only have regular files, the receipt is what has the dirs and symlinks `rvm verify` compares
+
+pub fn receipt_path(version: &Version) -> PathBuf {
+  receipt_path_in(&InstallMeta::dir(), version)
+}
+
+// in the .meta dir of some versions dir
+fn receipt_path_in(meta: &Path, version: &Version) -> PathBuf {
+  meta.join(format!("{}.receipt", version))
+}
+
+/// Where uninstall moves what's in an install that its receipt doesn't have (with .2, .3... after the first time)
+pub fn leftovers_dir(version: &Version) -> PathBuf {
+  versions_dir().join(".leftovers").join(version.to_string())
+}
+
+/// Every path under dir, sorted, so a dir comes before what's in it
+pub fn list(dir: &Path) -> io::Result<BTreeSet<String>> {
+  let mut ret = BTreeSet::new();
+  walk(dir, "", &mut ret)?;
+  Ok(ret)
+}
+
+fn walk(dir: &Path, prefix: &str, into: &mut BTreeSet<String>) -> io::Result<()> {
+  for entry in dir.read_dir()? {
+    let entry = entry?;
+    let name = format!("{}{}", prefix, entry.file_name().to_string_lossy());
+    // file_type doesn't follow symlinks, a link to a dir is a path like any file
+    if entry.file_type()?.is_dir() {
+      let name = format!("{}/", name);
+      walk(&entry.path(), &name, into)?;
+      into.insert(name);
+    } else {
+      into.insert(name);
+    }
+  }
+  Ok(())
+}
+
+/// Records what's in dir as version's receipt
+pub fn record(version: &Version, dir: &Path) -> Result<(), InstallError> {
+  let text: String = list(dir)?.into_iter().map(|path| path + "\n").collect();
+  std::fs::create_dir_all(InstallMeta::dir())?;
+  std::fs::write(receipt_path(version), text)?;
+  if is_system() {
+    share_tree(&system_home(), &receipt_path(version))?;
+  }
+  Ok(())
+}
+
+/// version's receipt, none if it was installed before rvm kept them
+pub fn load(version: &Version) -> Option<BTreeSet<String>> {
+  load_in(&InstallMeta::dir(), version)
+}
+
+/// load from the .meta dir meta instead of the versions dir's
+pub fn load_in(meta: &Path, version: &Version) -> Option<BTreeSet<String>> {
+  let text = std::fs::read_to_string(receipt_path_in(meta, version)).ok()?;
+  Some(text.lines().filter(|line| !line.is_empty()).map(str::to_owned).collect())
+}
+
+/// What's in dir that receipt doesn't have and the other way around
+pub fn diff(receipt: &BTreeSet<String>, dir: &Path) -> io::Result<(Vec<String>, Vec<String>)> {
+  let found = list(dir)?;
+  let added = found.difference(receipt).cloned().collect();
+  let missing = receipt.difference(&found).cloned().collect();
+  Ok((added, missing))
+}
+
+/// Removes what receipt lists from dir, deepest first, and dir itself once it's empty
+/// what's left (a dir that still has something the receipt doesn't list keeps it) is moved next to leftovers_dir,
+/// where to is returned when there was something
+pub fn remove(version: &Version, receipt: &BTreeSet<String>, dir: &Path) -> io::Result<Option<PathBuf>> {
+  for path in receipt.iter().rev() {
+    let full = dir.join(path.trim_end_matches('/'));
+    let removed = if path.ends_with('/') {
+      // only when empty, anything the receipt doesn't have in it stays
+      if full.read_dir().is_ok_and(|mut entries| entries.next().is_some()) {
+        continue;
+      }
+      std::fs::remove_dir(&full)
+    } else {
+      match std::fs::symlink_metadata(&full) {
+        // a windows symlink to a dir is removed like a dir
+        Ok(meta) if meta.is_dir() => std::fs::remove_dir(&full),
+        _ => std::fs::remove_file(&full),
+      }
+    };
+    // gone already is what was wanted
+    removed.or_else(|err| if err.kind() == io::ErrorKind::NotFound { Ok(()) } else { Err(err) })?;
+  }
+  if dir.read_dir()?.next().is_none() {
+    std::fs::remove_dir(dir)?;
+    return Ok(None);
+  }
+  // an earlier uninstall's leftovers of the same version aren't overwritten, these go next to them
+  let leftovers = (1..).map(|n| match n {
+    1 => leftovers_dir(version),
+    n => leftovers_dir(version).with_file_name(format!("{}.{}", version, n)),
+  }).find(|path| !path.exists()).unwrap_or_else(|| leftovers_dir(version));
+  std::fs::create_dir_all(versions_dir().join(".leftovers"))?;
+  std::fs::rename(dir, &leftovers)?;
+  tracing::info!(%version, leftovers = %leftovers.display(), "kept what the install didn't create");
+  Ok(Some(leftovers))
+}
@@ -0,0 +1,171 @@
+use std::path::{Path, PathBuf};
+use duct::cmd;
+use serde::{Deserialize, Serialize};
+use crate::install::InstallError;
+use crate::parsing::version_parser::Version;
+
+// builds bake the prefix they were configured with into a few places: shebangs, bin/R's R_HOME_DIR, Renviron, Makeconf, .pc files.
+// an index entry with a relocation recipe gets those rewritten to its rvm install dir while it's still staged, e.g.
+//   relocate:
+//     prefix: /opt/R/{version}
+//     fixups:
+//       - {kind: shebang, files: ["bin/*", "lib/R/bin/*"]}
+//       - {kind: replace, files: ["bin/R", "lib/R/etc/*"]}
+//       - {kind: pkg-config, files: ["lib/pkgconfig/*.pc"]}
+//       - {kind: script, script: "sed -i \"s|$OLD_PREFIX|$PREFIX|\" lib/R/etc/ldpaths"}
+// without fixups it's the defaults below. files are globs relative to the install, * within a dir and ** across them.
+// only text files are touched, a prefix inside a binary can't change length without breaking it
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Relocation {
+  /// where the artifact expects to live, {version} is the version
+  pub prefix: String,
+  #[serde(default = "default_fixups")]
+  pub fixups: Vec<Fixup>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum Fixup {
+  /// the interpreter in the first line, when it's under the prefix
+  Shebang { files: Vec<String> },
+  /// every mention of the prefix
+  Replace { files: Vec<String> },
+  /// the prefix= variable, other mentions become ${prefix}
+  PkgConfig { files: Vec<String> },
+  /// sh -c script in the install, with OLD_PREFIX and PREFIX set
+  Script { script: String },
+}
+
+fn default_fixups() -> Vec<Fixup> {
+  let globs = |globs: &[&str]| globs.iter().map(|g| g.to_string()).collect();
+  vec![
+    Fixup::Shebang { files: globs(&["bin/*", "lib/R/bin/*", "lib64/R/bin/*"]) },
+    Fixup::Replace { files: globs(&["bin/R", "lib/R/bin/R", "lib64/R/bin/R", "lib/R/etc/*", "lib64/R/etc/*"]) },
+    Fixup::PkgConfig { files: globs(&["lib/pkgconfig/*.pc", "lib64/pkgconfig/*.pc"]) },
+  ]
+}
+
+impl Fixup {
+  fn name(&self) -> &'static str {
+    match self {
+      Fixup::Shebang {..} => "shebang",
+      Fixup::Replace {..} => "replace",
+      Fixup::PkgConfig {..} => "pkg-config",
+      Fixup::Script {..} => "script",
+    }
+  }
+
+  /// What the file's text becomes, none when this doesn't change it
+  pub fn apply(&self, text: &str, from: &str, to: &str) -> Option<String> {
+    let ret = match self {
+      Fixup::Shebang {..} => {
+        let (first, rest) = text.split_once('\n').unwrap_or((text, ""));
+        let interpreter = first.strip_prefix("#!")?.trim_start();
+        let relative = interpreter.strip_prefix(from).filter(|r| r.is_empty() || r.starts_with(['/', ' ']))?;
+        let newline = if text.contains('\n') { "\n" } else { "" };
+        format!("#!{}{}{}{}", to, relative, newline, rest)
+      },
+      Fixup::Replace {..} => text.replace(from, to),
+      Fixup::PkgConfig {..} => text.lines().map(|line| match line.strip_prefix("prefix=") {
+        Some(_) => format!("prefix={}\n", to),
+        None => format!("{}\n", line.replace(from, "${prefix}")),
+      }).collect(),
+      Fixup::Script {..} => return None,
+    };
+    Some(ret).filter(|r| r != text)
+  }
+}
+
+impl Relocation {
+  pub fn prefix_for(&self, version: &Version) -> String {
+    self.prefix.replace("{version}", &version.to_string()).trim_end_matches(['/', '\\']).to_owned()
+  }
+
+  /// Rewrites the staged install of version from the recipe's prefix to dest, returning the files it changed
+  pub fn run(&self, version: &Version, staging: &Path, dest: &Path) -> Result<Vec<PathBuf>, InstallError> {
+    let (from, to) = (self.prefix_for(version), dest.display().to_string());
+    let mut changed = vec![];
+    if from == to {
+      return Ok(changed);
+    }
+    let files = files_under(staging)?;
+    for fixup in &self.fixups {
+      let globs = match fixup {
+        Fixup::Script { script } => {
+          cmd!("sh", "-c", script).dir(staging).env("OLD_PREFIX", &from).env("PREFIX", &to).stdout_to_stderr().run()
+            .map_err(|err| InstallError::Relocate(format!("the script failed: {}", err)))?;
+          continue;
+        },
+        Fixup::Shebang { files } | Fixup::Replace { files } | Fixup::PkgConfig { files } => files,
+      };
+      for relative in files.iter().filter(|f| globs.iter().any(|g| glob_match(g, f))) {
+        let path = staging.join(relative);
+        let bytes = std::fs::read(&path)?;
+        // binaries, see above
+        let Ok(text) = String::from_utf8(bytes) else { continue };
+        if text.contains('\0') {
+          continue;
+        }
+        if let Some(fixed) = fixup.apply(&text, &from, &to) {
+          tracing::debug!(file = %relative, fixup = fixup.name(), "relocated");
+          std::fs::write(&path, fixed)?;
+          changed.push(PathBuf::from(relative));
+        }
+      }
+    }
+    changed.sort();
+    changed.dedup();
+    tracing::info!(%from, %to, files = changed.len(), "relocated install");
+    Ok(changed)
+  }
+}
+
+/// Rewrites the installed tree of version from pointing into from to pointing into to, with the default fixups,
+/// for an install that moved with RVM_HOME or a copy `rvm bundle` made (the index's recipe was for its artifact)
+pub fn rebase(version: &Version, tree: &Path, from: &Path, to: &Path) -> Result<Vec<PathBuf>, InstallError> {
+  Relocation { prefix: from.display().to_string(), fixups: default_fixups() }.run(version, tree, to)
+}
+
+// the regular files under dir, relative to it with / between dirs, links aren't followed
+fn files_under(dir: &Path) -> std::io::Result<Vec<String>> {
+  let mut ret = vec![];
+  let mut stack = vec![PathBuf::new()];
+  while let Some(relative) = stack.pop() {
+    for entry in dir.join(&relative).read_dir()? {
+      let entry = entry?;
+      let path = relative.join(entry.file_name());
+      let kind = entry.file_type()?;
+      if kind.is_dir() {
+        stack.push(path);
+      } else if kind.is_file() {
+        ret.push(path.iter().map(|c| c.to_string_lossy()).collect::<Vec<_>>().join("/"));
+      }
+    }
+  }
+  Ok(ret)
+}
+
+/// Whether path (with / between dirs) matches glob, * and ? within a dir and ** for any number of them
+pub fn glob_match(glob: &str, path: &str) -> bool {
+  let glob: Vec<&str> = glob.split('/').filter(|s| !s.is_empty()).collect();
+  let path: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+  fn segments(glob: &[&str], path: &[&str]) -> bool {
+    match (glob.split_first(), path.split_first()) {
+      (None, None) => true,
+      (Some((&"**", rest)), _) => segments(rest, path) || (!path.is_empty() && segments(glob, &path[1..])),
+      (Some((g, glob)), Some((p, path))) => segment(g.as_bytes(), p.as_bytes()) && segments(glob, path),
+      _ => false,
+    }
+  }
+  fn segment(glob: &[u8], name: &[u8]) -> bool {
+    match (glob.split_first(), name.split_first()) {
+      (None, None) => true,
+      (Some((b'*', rest)), _) => segment(rest, name) || (!name.is_empty() && segment(glob, &name[1..])),
+      (Some((b'?', glob)), Some((_, name))) => segment(glob, name),
+      (Some((g, glob)), Some((n, name))) => g == n && segment(glob, name),
+      _ => false,
+    }
+  }
+  segments(&glob, &path)
+}
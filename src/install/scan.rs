@@ -0,0 +1,185 @@
+use std::collections::BTreeSet;
+use std::fmt::{Display, Formatter};
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
+use crate::install::git::version_from_ref;
+use crate::layout::version_names;
+use crate::parsing::version_parser::{Range, RangeBound, Version};
+
+// one walk of a versions dir for everything that needs what's installed (list, doctor, resolving)
+// names are parsed leniently (v4.2.1, R-4-2-1) so a hand-made dir is reported as misnamed instead of unknown,
+// but only dirs named exactly like install_dir makes them are in the pool, the rest is skipped with why
+
+/// Versions sorted oldest first, without duplicates
+/// keyed by RangeBound, so the ones in a range are found without going through the rest
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VersionPool {
+  versions: BTreeSet<RangeBound>,
+}
+
+impl VersionPool {
+  pub fn new(versions: impl IntoIterator<Item = Version>) -> Self {
+    Self { versions: versions.into_iter().map(RangeBound::At).collect() }
+  }
+
+  pub fn versions(&self) -> impl DoubleEndedIterator<Item = &Version> {
+    self.versions.iter().map(RangeBound::version)
+  }
+
+  pub fn into_vec(self) -> Vec<Version> {
+    self.versions.into_iter().map(|key| match key {
+      RangeBound::At(version) | RangeBound::Below(version) => version,
+    }).collect()
+  }
+
+  pub fn newest(&self) -> Option<&Version> {
+    self.versions.last().map(RangeBound::version)
+  }
+
+  pub fn contains(&self, version: &Version) -> bool {
+    self.versions.contains(&RangeBound::At(version.clone()))
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.versions.is_empty()
+  }
+
+  pub fn len(&self) -> usize {
+    self.versions.len()
+  }
+
+  /// The versions in range oldest first, only the ones between its bounds are looked at
+  /// (all of them when it includes versions on top of its bounds or has none)
+  pub fn range_iter<'a>(&'a self, range: &'a Range) -> impl DoubleEndedIterator<Item = &'a Version> + 'a {
+    let scan = range.include.is_empty() && (range.min.is_some() || range.max.is_some());
+    let bound = |version: &Option<Version>, to: fn(RangeBound) -> Bound<RangeBound>| match version {
+      Some(version) if scan => to(RangeBound::Below(version.clone())),
+      _ => Bound::Unbounded,
+    };
+    self.versions.range((bound(&range.min, Bound::Included), bound(&range.max, Bound::Excluded)))
+      .map(RangeBound::version)
+      .filter(|version| range.contains(version))
+  }
+
+  /// The newest of each series, oldest series first
+  pub fn latest_per(&self, granularity: Granularity) -> Vec<&Version> {
+    let mut ret: Vec<&Version> = vec![];
+    for version in self.versions() {
+      match ret.last_mut() {
+        // sorted, so a version of the same series as the last one is newer
+        Some(last) if granularity.series(last) == granularity.series(version) => *last = version,
+        _ => ret.push(version),
+      }
+    }
+    ret
+  }
+}
+
+/// How wide a series is, 4 or 4.2
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Granularity {
+  Major,
+  Minor,
+}
+
+impl Granularity {
+  /// (major, minor) of the series version is in, minor is 0 for Major
+  pub fn series(&self, version: &Version) -> (u32, u32) {
+    let (major, minor, _) = version.parts();
+    match self {
+      Granularity::Major => (major, 0),
+      Granularity::Minor => (major, minor),
+    }
+  }
+}
+
+impl<'a> IntoIterator for &'a VersionPool {
+  type Item = &'a Version;
+  type IntoIter = std::iter::Map<std::collections::btree_set::Iter<'a, RangeBound>, fn(&RangeBound) -> &Version>;
+
+  fn into_iter(self) -> Self::IntoIter {
+    self.versions.iter().map(RangeBound::version)
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SkipReason {
+  DanglingLink,
+  NotADir,
+  NotAVersion,
+  /// looks like this version, but rvm only finds it under its own name
+  Misnamed(Version),
+  /// named after a version and empty, what an interrupted install or uninstall leaves
+  Empty(Version),
+}
+
+impl Display for SkipReason {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      SkipReason::DanglingLink => write!(f, "is a dangling link"),
+      SkipReason::NotADir => write!(f, "isn't a dir"),
+      SkipReason::NotAVersion => write!(f, "isn't named after a version"),
+      SkipReason::Misnamed(version) => write!(f, "looks like R {} but isn't named {}", version, version_names().name(version)),
+      SkipReason::Empty(version) => write!(f, "is empty, R {} isn't installed in it", version),
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Skipped {
+  pub path: PathBuf,
+  pub reason: SkipReason,
+}
+
+impl Skipped {
+  /// Whether it's a dir with something rvm doesn't manage in it, what `rvm adopt` takes in
+  pub fn is_unmanaged(&self) -> bool {
+    matches!(self.reason, SkipReason::NotAVersion | SkipReason::Misnamed(_))
+  }
+
+  /// The `rvm adopt` that takes it in, with the version it looks like when there's one
+  pub fn adopt_command(&self) -> String {
+    let version = match &self.reason {
+      SkipReason::Misnamed(version) => version.to_string(),
+      _ => "<version>".to_owned(),
+    };
+    format!("rvm adopt {} --as {}", self.path.display(), version)
+  }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Scan {
+  pub pool: VersionPool,
+  pub skipped: Vec<Skipped>,
+}
+
+/// Reads dir, rvm's own dot dirs (.staging, .journal, .meta) are neither in the pool nor skipped
+/// a dir that can't be read is an empty scan
+pub fn scan(dir: &Path) -> Scan {
+  let Ok(entries) = dir.read_dir() else { return Scan::default() };
+  let names = version_names();
+  let mut versions = vec![];
+  let mut skipped = vec![];
+  for entry in entries.filter_map(|e| e.ok()) {
+    let path = entry.path();
+    let name = entry.file_name();
+    if name.to_string_lossy().starts_with('.') {
+      continue;
+    }
+    let mut skip = |reason| skipped.push(Skipped { path: path.clone(), reason });
+    let strict = name.to_str().and_then(|name| names.parse(name));
+    let lenient = || name.to_str().and_then(|name| Version::parse(name).ok().or_else(|| version_from_ref(name)));
+    match strict {
+      _ if path.is_symlink() && !path.exists() => skip(SkipReason::DanglingLink),
+      _ if !path.is_dir() => skip(SkipReason::NotADir),
+      Some(version) if path.read_dir().is_ok_and(|mut d| d.next().is_some()) => versions.push(version),
+      Some(version) => skip(SkipReason::Empty(version)),
+      None => match lenient() {
+        Some(version) => skip(SkipReason::Misnamed(version)),
+        None => skip(SkipReason::NotAVersion),
+      },
+    }
+  }
+  skipped.sort_by(|a, b| a.path.cmp(&b.path));
+  Scan { pool: VersionPool::new(versions), skipped }
+}
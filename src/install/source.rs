@@ -0,0 +1,176 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+use duct::cmd;
+use crate::audit;
+use crate::cancel::{self, CancellationToken};
+use crate::config::{BuildRecipe, Config, Mirror};
+use crate::index::ArtifactMeta;
+use crate::install::{keep_artifact, record_artifact, relocate, verify_download, Downloader, InstallError, Progress, Reporter};
+use crate::install::delta::{self, Kind};
+use crate::install::extract::unpack;
+use crate::install::transaction::Transaction;
+use crate::layout::cache_dir;
+use crate::parsing::version_parser::Version;
+use crate::policy::Policy;
+
+// build from source backend: download the tarball, unpack it in a build dir that's kept between attempts
+// (so a failed build resumes and ccache sees the same paths), run the recipe and install into the staging dir.
+// R bakes its prefix into bin/R and the rest, so the configured recipe is for where it ends up ({prefix} is the install
+// dir) and installs with make's DESTDIR under the staging dir, what lands there is then moved up into staging
+
+// <cache dir>\build\<version>\
+pub fn builds_dir() -> PathBuf {
+  cache_dir().join("build")
+}
+
+pub fn build_dir(version: &Version) -> PathBuf {
+  builds_dir().join(version.to_string())
+}
+
+/// Where the output of every build step of version goes
+pub fn build_log(version: &Version) -> PathBuf {
+  build_dir(version).join("build.log")
+}
+
+#[tracing::instrument(skip_all, fields(version = %meta.version))]
+pub fn install_from_source(meta: &ArtifactMeta, dest: &Path, downloader: &dyn Downloader, reporter: &dyn Reporter, cancel: &CancellationToken) -> Result<PathBuf, InstallError> {
+  let url = meta.source_url.as_ref().ok_or_else(|| InstallError::Verify(format!("no source tarball for {}", meta.version)))?;
+  Policy::get().check(&meta.version, Some(url))?;
+  let build = build_dir(&meta.version);
+  let sources = build.join(format!("R-{}", meta.version));
+  // reused sources were checked when they were downloaded, there's just no hash to record this time
+  let mut sha256 = None;
+  if sources.join("configure").exists() {
+    tracing::info!(sources = %sources.display(), "reusing unpacked sources");
+  } else {
+    reporter.report(Progress::Downloading(&meta.version, url));
+    let on_bytes = |read, total| reporter.report(Progress::Downloaded(&meta.version, read, total));
+    let format = meta.source_format();
+    let tarball = delta::download(meta, Kind::Source, url, &format!("R-{}.{}", meta.version, format.extension()), downloader, &on_bytes, cancel)?;
+    sha256 = Some(verify_download(url, &tarball, meta.source_sha256.as_deref())?);
+    keep_artifact(&meta.version, Kind::Source, &tarball);
+    std::fs::create_dir_all(&build)?;
+    unpack(&meta.version, &tarball, format, &build, reporter)?;
+  }
+  reporter.report(Progress::Installing(&meta.version));
+  let transaction = Transaction::begin(&meta.version, dest)?;
+  run_build(&meta.version, meta.build_script.as_deref(), &sources, &transaction, reporter, cancel)?;
+  relocate(meta, &transaction, dest)?;
+  let dest = transaction.commit()?;
+  record_artifact(meta, url, sha256)?;
+  audit::record_install(&meta.version);
+  Ok(dest)
+}
+
+/// The download and build steps of install_from_source, for --dry-run
+pub fn plan(meta: &ArtifactMeta, staging: &Path, dest: &Path, mirrors: &[Mirror]) -> Vec<String> {
+  let build = build_dir(&meta.version);
+  let sources = build.join(format!("R-{}", meta.version));
+  let mut steps = vec![];
+  match &meta.source_url {
+    _ if sources.join("configure").exists() => steps.push(format!("reuse the sources in {}", sources.display())),
+    Some(url) => {
+      let tarball = std::env::temp_dir().join(format!("R-{}.{}", meta.version, meta.source_format().extension()));
+      steps.push(format!("download {} to {}", Mirror::rewrite(mirrors, url), tarball.display()));
+      steps.push(format!("unpack it into {}", build.display()));
+    },
+    None => steps.push(format!("fail, there's no source tarball for {}", meta.version)),
+  }
+  let config = Config::get();
+  match &meta.build_script {
+    Some(_) => steps.push(format!("run the index's build script in {} with PREFIX={}", sources.display(), staging.display())),
+    None => {
+      for step in &config.build.steps {
+        let args: Vec<String> = step.iter().map(|a| expand(a, &meta.version, dest, &config.build)).collect();
+        steps.push(format!("run {} in {} with DESTDIR={}", args.join(" "), sources.display(), staging.join(DESTDIR).display()));
+      }
+      steps.push(format!("move what it installed into {}", staging.display()));
+    },
+  }
+  steps
+}
+
+/// Runs the build script (or the configured recipe without one) in sources, installing into the staging dir
+/// (the recipe through DESTDIR, see above). a cancelled build stops at the next step, the one running is killed
+pub fn run_build(version: &Version, script: Option<&str>, sources: &Path, transaction: &Transaction, reporter: &dyn Reporter,
+                 cancel: &CancellationToken) -> Result<(), InstallError> {
+  let config = Config::get();
+  std::fs::create_dir_all(build_dir(version))?;
+  let mut log = OpenOptions::new().create(true).append(true).open(build_log(version))?;
+  match script {
+    Some(script) => {
+      writeln!(log, "== build script from index")?;
+      reporter.report(Progress::Building(version, "build script"));
+      let script = cmd!("sh", "-c", script)
+        .dir(sources)
+        .env("PREFIX", transaction.staging())
+        .env("JOBS", jobs(&config.build).to_string())
+        .stderr_to_stdout()
+        .stdout_file(log.try_clone()?);
+      cancel::run(script, cancel).map_err(|err| build_error("build script", version, err, cancel))?;
+    },
+    None => {
+      let destdir = transaction.staging().join(DESTDIR);
+      for step in &config.build.steps {
+        let args: Vec<String> = step.iter().map(|a| expand(a, version, transaction.dest(), &config.build)).collect();
+        let Some((program, args)) = args.split_first() else { continue };
+        cancel.check()?;
+        let step = format!("{} {}", program, args.join(" "));
+        writeln!(log, "== {}", step)?;
+        tracing::info!(%step, "build step");
+        reporter.report(Progress::Building(version, &step));
+        // duct resolves relative programs against our cwd, not dir(), and ./configure is relative to the sources
+        let executable = if program.contains('/') && Path::new(program).is_relative() {
+          sources.join(program).into_os_string()
+        } else {
+          program.into()
+        };
+        let step = cmd(executable, args).dir(sources).env("DESTDIR", &destdir).stderr_to_stdout().stdout_file(log.try_clone()?);
+        cancel::run(step, cancel).map_err(|err| build_error(program, version, err, cancel))?;
+      }
+      take_installed(&destdir, transaction)?;
+    },
+  }
+  Ok(())
+}
+
+// the dir in staging the recipe installs into as DESTDIR
+const DESTDIR: &str = ".destdir";
+
+// moves what the recipe installed under destdir, at the install dir's path, up into the staging dir
+fn take_installed(destdir: &Path, transaction: &Transaction) -> Result<(), InstallError> {
+  let installed = transaction.dest().components()
+    .filter(|c| matches!(c, Component::Normal(_)))
+    .fold(destdir.to_path_buf(), |path, c| path.join(c));
+  if !installed.is_dir() {
+    return Err(InstallError::Verify(format!("the build recipe installed nothing into {}, does it install into {{prefix}}?", installed.display())));
+  }
+  for entry in installed.read_dir()? {
+    let entry = entry?;
+    std::fs::rename(entry.path(), transaction.staging().join(entry.file_name()))?;
+  }
+  std::fs::remove_dir_all(destdir)?;
+  Ok(())
+}
+
+// a step that failed because it was killed for cancel is the cancellation, not a failed build
+pub(crate) fn build_error(step: &str, version: &Version, err: std::io::Error, cancel: &CancellationToken) -> InstallError {
+  if cancel.is_cancelled() {
+    return InstallError::Cancelled;
+  }
+  InstallError::Build(step.to_owned(), build_log(version), err)
+}
+
+fn jobs(recipe: &BuildRecipe) -> usize {
+  if recipe.jobs > 0 {
+    return recipe.jobs;
+  }
+  std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+}
+
+fn expand(arg: &str, version: &Version, prefix: &Path, recipe: &BuildRecipe) -> String {
+  arg.replace("{prefix}", &prefix.display().to_string())
+    .replace("{version}", &version.to_string())
+    .replace("{jobs}", &jobs(recipe).to_string())
+}
@@ -0,0 +1,104 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use duct::cmd;
+use crate::config::Config;
+use crate::install::InstallError;
+
+// with the startup-check feature, a staged install has to start before it's committed: the libraries its
+// executable links have to be on this system (`ldd` on linux, `otool -L` on macos, nothing to ask on windows)
+// and `--version` has to exit 0. an R built for another glibc or missing libgfortran then fails the install,
+// saying what's missing, instead of installing and failing the first time it's used
+
+// the executable bin/R ends up running, in the layout R_HOME has on unix and on windows
+const EXECUTABLES: [&str; 4] = ["bin/exec/R", "bin/x64/Rterm.exe", "bin/Rterm.exe", "bin/R.exe"];
+
+/// The executable of the install in dir, none when it has none of the known ones
+pub fn executable(dir: &Path) -> Option<PathBuf> {
+  EXECUTABLES.iter().map(|exe| dir.join(exe)).find(|exe| exe.is_file())
+}
+
+/// Checks the install staged in dir starts, see above
+pub fn check(dir: &Path) -> Result<(), InstallError> {
+  let Some(exe) = executable(dir) else { return Ok(()) };
+  let missing = missing_libraries(&exe, dir)?;
+  if !missing.is_empty() {
+    let reason = format!("this system doesn't have {}", missing.join(", "));
+    return Err(InstallError::Startup(exe, reason));
+  }
+  let timeout = Duration::from_secs(Config::get().check.timeout);
+  run(&exe, dir, timeout).map_err(|reason| InstallError::Startup(exe.clone(), reason))?;
+  tracing::info!(exe = %exe.display(), "install starts");
+  Ok(())
+}
+
+/// The libraries exe links that can't be found, with dir's lib on the search path like bin/R puts it
+pub fn missing_libraries(exe: &Path, dir: &Path) -> Result<Vec<String>, InstallError> {
+  if cfg!(target_os = "linux") {
+    let output = cmd!("ldd", exe).env("LD_LIBRARY_PATH", dir.join("lib")).stderr_to_stdout().stdout_capture().unchecked().run()?;
+    Ok(ldd_missing(&String::from_utf8_lossy(&output.stdout)))
+  } else if cfg!(target_os = "macos") {
+    let output = cmd!("otool", "-L", exe).stderr_to_stdout().stdout_capture().unchecked().run()?;
+    let dirs = [exe.parent().unwrap_or(dir).to_path_buf(), dir.join("lib")];
+    Ok(otool_missing(&String::from_utf8_lossy(&output.stdout), &dirs))
+  } else {
+    Ok(Vec::new())
+  }
+}
+
+/// The libraries ldd's output says are "not found"
+pub fn ldd_missing(output: &str) -> Vec<String> {
+  output.lines()
+    .filter(|line| line.contains("not found"))
+    .filter_map(|line| line.split_whitespace().next())
+    .map(str::to_owned)
+    .collect()
+}
+
+/// The libraries otool -L's output lists that aren't there, @-relative ones are looked for in dirs
+/// the system's own (/usr/lib and /System) are in the dyld cache and not on disk, they're taken as there
+pub fn otool_missing(output: &str, dirs: &[PathBuf]) -> Vec<String> {
+  // the first line is the executable itself, each one after it a tab, the path and its versions in parentheses
+  output.lines()
+    .skip(1)
+    .filter_map(|line| line.split_whitespace().next())
+    .filter(|lib| !lib.starts_with("/usr/lib/") && !lib.starts_with("/System/"))
+    .filter(|lib| match lib.split_once('/') {
+      Some((prefix, rest)) if prefix.starts_with('@') => {
+        let name = Path::new(rest).file_name().unwrap_or_default();
+        !dirs.iter().any(|dir| dir.join(name).exists())
+      },
+      _ => !Path::new(lib).exists(),
+    })
+    .map(str::to_owned)
+    .collect()
+}
+
+// exe --version with dir as its R_HOME, what it said when it can't run, exits non zero or outlives timeout
+fn run(exe: &Path, dir: &Path, timeout: Duration) -> Result<(), String> {
+  let handle = cmd!(exe, "--version")
+    .env("R_HOME", dir)
+    .env("LD_LIBRARY_PATH", dir.join("lib"))
+    .stderr_to_stdout()
+    .stdout_capture()
+    .unchecked()
+    .start()
+    .map_err(|err| err.to_string())?;
+  let start = Instant::now();
+  let output = loop {
+    if let Some(output) = handle.try_wait().map_err(|err| err.to_string())? {
+      break output.clone();
+    }
+    if start.elapsed() > timeout {
+      let _ = handle.kill();
+      return Err(format!("--version is still running after {}s", timeout.as_secs()));
+    }
+    std::thread::sleep(Duration::from_millis(50));
+  };
+  let said = String::from_utf8_lossy(&output.stdout).trim().lines().last().unwrap_or_default().to_owned();
+  match output.status.code() {
+    Some(0) => Ok(()),
+    Some(code) if said.is_empty() => Err(format!("--version exited with {}", code)),
+    Some(code) => Err(format!("--version exited with {}: {}", code, said)),
+    None => Err("--version was killed by a signal".to_owned()),
+  }
+}
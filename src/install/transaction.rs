@@ -0,0 +1,158 @@
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+use crate::config::Config;
+use crate::install::{dedup, protect, receipt, verify, versions_dir, InstallError};
+use crate::install::meta::InstallMeta;
+use crate::local_utils::{is_system, system_home};
+use crate::utils::share_tree;
+use crate::parsing::version_parser::Version;
+
+// an install is staged in versions\.staging\ and only renamed into place once it's verified
+// each in flight install leaves an entry in versions\.journal\ until it commits or rolls back,
+// so whatever a killed process left behind can be found and cleaned later (rvm doctor)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+  pub version: Version,
+  pub staging: PathBuf,
+  pub dest: PathBuf,
+  pub pid: u32,
+  pub started_at: u64,
+}
+
+impl JournalEntry {
+  pub fn dir() -> PathBuf {
+    versions_dir().join(".journal")
+  }
+
+  fn path(&self) -> PathBuf {
+    Self::dir().join(format!("{}-{}.yaml", self.version, self.pid))
+  }
+
+  /// Every install that started and never committed or rolled back
+  pub fn pending() -> Vec<(PathBuf, JournalEntry)> {
+    let Ok(entries) = Self::dir().read_dir() else { return vec![] };
+    entries
+      .filter_map(|e| e.ok())
+      .filter_map(|e| {
+        let file = File::open(e.path()).ok()?;
+        let entry: JournalEntry = serde_yaml::from_reader(file).ok()?;
+        Some((e.path(), entry))
+      })
+      .collect()
+  }
+
+  /// Removes the staging dir and journal file of an interrupted install
+  pub fn roll_back(journal_file: &Path, entry: &JournalEntry) -> std::io::Result<()> {
+    if entry.staging.exists() {
+      std::fs::remove_dir_all(&entry.staging)?;
+    }
+    std::fs::remove_file(journal_file)
+  }
+}
+
+pub struct Transaction {
+  entry: JournalEntry,
+  committed: bool,
+}
+
+impl Transaction {
+  /// Where this process stages version
+  pub fn staging_for(version: &Version) -> PathBuf {
+    versions_dir().join(".staging").join(format!("{}-{}", version, std::process::id()))
+  }
+
+  pub fn begin(version: &Version, dest: &Path) -> Result<Self, InstallError> {
+    let pid = std::process::id();
+    let entry = JournalEntry {
+      version: version.clone(),
+      staging: Self::staging_for(version),
+      dest: dest.to_path_buf(),
+      pid,
+      started_at: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+    };
+    std::fs::create_dir_all(JournalEntry::dir())?;
+    // journal first, so there's never a staging dir nobody knows about
+    serde_yaml::to_writer(File::create(entry.path())?, &entry)
+      .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err))?;
+    if entry.staging.exists() {
+      std::fs::remove_dir_all(&entry.staging)?;
+    }
+    std::fs::create_dir_all(&entry.staging)?;
+    tracing::debug!(staging = %entry.staging.display(), "transaction started");
+    Ok(Self { entry, committed: false })
+  }
+
+  pub fn staging(&self) -> &Path {
+    &self.entry.staging
+  }
+
+  /// Where the staged tree goes on commit
+  pub fn dest(&self) -> &Path {
+    &self.entry.dest
+  }
+
+  /// Checks the staged tree looks like an R install and moves it into place
+  pub fn commit(mut self) -> Result<PathBuf, InstallError> {
+    verify_tree(&self.entry.staging)?;
+    #[cfg(feature = "startup-check")]
+    crate::install::startup::check(&self.entry.staging)?;
+    if let Some(parent) = self.entry.dest.parent() {
+      std::fs::create_dir_all(parent)?;
+    }
+    if self.entry.dest.exists() {
+      // an empty leftover dir, is_installed already said it's not a real install
+      std::fs::remove_dir_all(&self.entry.dest)?;
+    }
+    std::fs::rename(&self.entry.staging, &self.entry.dest)?;
+    if is_system() {
+      share_tree(&system_home(), &self.entry.dest)?;
+    }
+    tracing::info!(dest = %self.entry.dest.display(), "install committed");
+    self.committed = true;
+    let _ = std::fs::remove_file(self.entry.path());
+    if let Err(err) = InstallMeta::installed(&self.entry.version, &self.entry.dest) {
+      tracing::warn!(%err, "can't record the install");
+    }
+    // it's in place already, without sums `rvm verify` only can't check it
+    if let Err(err) = verify::record(&self.entry.version, &self.entry.dest) {
+      tracing::warn!(%err, "can't record checksums");
+    }
+    // without a receipt uninstall removes the whole tree, like before rvm kept them
+    if let Err(err) = receipt::record(&self.entry.version, &self.entry.dest) {
+      tracing::warn!(%err, "can't record the receipt");
+    }
+    // sharing only saves space, an install that doesn't keeps its own copies
+    let shared = dedup::run(&self.entry.version, &self.entry.dest, Config::get().dedup);
+    if !shared.is_empty() {
+      if let Err(err) = InstallMeta::update(&self.entry.version, |meta| meta.shared = shared) {
+        tracing::warn!(%err, "can't record what the install shares");
+      }
+    }
+    // after the sums, they're what it's protected as
+    if let Err(err) = protect::lock(&self.entry.version, &self.entry.dest, Config::get().protect) {
+      tracing::warn!(%err, "can't protect the install");
+    }
+    Ok(self.entry.dest.clone())
+  }
+}
+
+impl Drop for Transaction {
+  // anything that didn't commit gets rolled back, including on early return with ? or a panic
+  fn drop(&mut self) {
+    if !self.committed {
+      tracing::warn!(version = %self.entry.version, "install rolled back");
+      let _ = JournalEntry::roll_back(&self.entry.path(), &self.entry);
+    }
+  }
+}
+
+pub fn verify_tree(dir: &Path) -> Result<(), InstallError> {
+  // the installer can exit 0 and still leave nothing, at least the executables should be there
+  let has_r = ["R.exe", "R"].iter().any(|bin| dir.join("bin").join(bin).exists());
+  if !has_r {
+    return Err(InstallError::Verify(format!("no bin/R in {}", dir.display())));
+  }
+  Ok(())
+}
@@ -0,0 +1,245 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use serde::Serialize;
+use crate::cancel::CancellationToken;
+use crate::install::meta::InstallMeta;
+use crate::install::{receipt, InstallError, NoReporter, Progress, Reporter};
+use crate::layout::version_names;
+use crate::parsing::version_parser::Version;
+use crate::local_utils::{is_system, system_home};
+use crate::utils::{sha256_file, share_tree};
+
+// what every file of an install hashed to when it was committed, in versions\.meta\<version>.sums
+// one "<sha256>  <size>  <path>" line per file, paths relative to the install with / separators, like sha256sum
+// symlinks aren't followed, the tree they point to isn't rvm's, dirs and symlinks themselves are compared with the receipt
+
+pub fn sums_path(version: &Version) -> PathBuf {
+  sums_path_in(&InstallMeta::dir(), version)
+}
+
+// in the .meta dir of some versions dir
+fn sums_path_in(meta: &Path, version: &Version) -> PathBuf {
+  meta.join(format!("{}.sums", version))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Entry {
+  sha256: String,
+  size: u64,
+}
+
+/// What changed in an install since its sums were recorded
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Report {
+  pub checked: usize,
+  pub missing: Vec<String>,
+  pub modified: Vec<String>,
+  /// files that weren't there at install time, not damage (a package installed into the tree is one)
+  pub added: Vec<String>,
+}
+
+impl Report {
+  pub fn is_damaged(&self) -> bool {
+    !self.missing.is_empty() || !self.modified.is_empty()
+  }
+}
+
+/// Hashes the tree in dir and records it as version's sums
+pub fn record(version: &Version, dir: &Path) -> Result<(), InstallError> {
+  record_in(&InstallMeta::dir(), version, dir)
+}
+
+/// record into the .meta dir meta instead of the versions dir's
+pub fn record_in(meta: &Path, version: &Version, dir: &Path) -> Result<(), InstallError> {
+  let mut text = String::new();
+  for (path, entry) in hash_tree(dir)? {
+    text.push_str(&format!("{}  {}  {}\n", entry.sha256, entry.size, path));
+  }
+  std::fs::create_dir_all(meta)?;
+  std::fs::write(sums_path_in(meta, version), text)?;
+  if is_system() {
+    share_tree(&system_home(), &sums_path_in(meta, version))?;
+  }
+  Ok(())
+}
+
+/// Records the new hashes of files (relative to dir) rvm itself changed, the others keep what they were installed with
+pub fn rehash(version: &Version, dir: &Path, files: &[PathBuf]) -> Result<(), InstallError> {
+  let Ok(text) = std::fs::read_to_string(sums_path(version)) else { return Ok(()) };
+  let mut recorded: BTreeMap<String, Entry> = text.lines().filter_map(parse_line).collect();
+  for file in files {
+    let relative = file.iter().map(|c| c.to_string_lossy()).collect::<Vec<_>>().join("/");
+    if let Some(entry) = recorded.get_mut(&relative) {
+      let path = dir.join(file);
+      *entry = Entry { sha256: sha256_file(&path)?, size: std::fs::metadata(&path)?.len() };
+    }
+  }
+  let text: String = recorded.iter().map(|(path, entry)| format!("{}  {}  {}\n", entry.sha256, entry.size, path)).collect();
+  std::fs::write(sums_path(version), text)?;
+  Ok(())
+}
+
+/// Compares what's in dir with version's recorded sums, none if there aren't any (it was installed before rvm recorded them)
+pub fn check(version: &Version, dir: &Path) -> Result<Option<Report>, InstallError> {
+  check_all(&InstallMeta::dir(), &[(version, dir)], 1, &NoReporter, &CancellationToken::new()).pop().unwrap_or(Ok(None))
+}
+
+/// check for several versions installed in root (a versions dir, versions_dir() for rvm's own), hashing with at
+/// most jobs threads over the files of all of them, so one big install doesn't leave the others' threads idle.
+/// Results are in the order of versions, the ones cancel stopped before they were done are Cancelled
+pub fn check_many(root: &Path, versions: &[Version], jobs: usize, reporter: &dyn Reporter, cancel: &CancellationToken) -> Vec<Result<Option<Report>, InstallError>> {
+  let names = version_names();
+  let dirs: Vec<PathBuf> = versions.iter().map(|v| root.join(names.name(v))).collect();
+  let installs: Vec<(&Version, &Path)> = versions.iter().zip(&dirs).map(|(v, d)| (v, d.as_path())).collect();
+  check_all(&root.join(".meta"), &installs, jobs, reporter, cancel)
+}
+
+// what an install's check has left once its tree was walked: the files whose size matched, to hash
+struct Pending {
+  report: Report,
+  to_hash: Vec<ToHash>,
+  bytes: u64,
+}
+
+struct ToHash {
+  /// relative to the install
+  path: String,
+  file: PathBuf,
+  /// what was recorded
+  sha256: String,
+  size: u64,
+}
+
+// an install's files hashed so far: the ones that hashed to something else, their bytes and the first error
+#[derive(Default)]
+struct Hashed {
+  modified: Vec<String>,
+  bytes: u64,
+  error: Option<std::io::Error>,
+}
+
+// meta is the .meta dir with the installs' sums and receipts
+fn check_all(meta: &Path, installs: &[(&Version, &Path)], jobs: usize, reporter: &dyn Reporter, cancel: &CancellationToken) -> Vec<Result<Option<Report>, InstallError>> {
+  let prepared: Vec<Result<Option<Pending>, InstallError>> = installs.iter().map(|(version, dir)| prepare(meta, version, dir)).collect();
+  let totals: Vec<u64> = prepared.iter().map(|p| p.as_ref().ok().and_then(Option::as_ref).map_or(0, |p| p.bytes)).collect();
+  // every file of every install in one queue, progress is per install in bytes
+  let files: Vec<(usize, &ToHash)> = prepared.iter().enumerate()
+    .filter_map(|(i, p)| Some((i, p.as_ref().ok()?.as_ref()?)))
+    .flat_map(|(i, pending)| pending.to_hash.iter().map(move |file| (i, file)))
+    .collect();
+  let queue = Mutex::new(files.iter());
+  let hashed: Mutex<Vec<Hashed>> = Mutex::new(installs.iter().map(|_| Hashed::default()).collect());
+  std::thread::scope(|scope| {
+    for _ in 0..jobs.clamp(1, files.len().max(1)) {
+      scope.spawn(|| loop {
+        let next = queue.lock().unwrap().next(); // lock is dropped right away
+        let Some(&(i, file)) = next else { break };
+        if cancel.is_cancelled() {
+          break;
+        }
+        let result = sha256_file(&file.file);
+        // reported with the lock held, so an install's progress never goes back
+        let mut hashed = hashed.lock().unwrap();
+        let hashed = &mut hashed[i];
+        match result {
+          Ok(found) if found != file.sha256 => hashed.modified.push(file.path.clone()),
+          Ok(_) => {},
+          Err(err) => {
+            hashed.error.get_or_insert(err);
+          },
+        }
+        hashed.bytes += file.size;
+        reporter.report(Progress::Verifying(installs[i].0, hashed.bytes, totals[i]));
+      });
+    }
+  });
+  prepared.into_iter().zip(hashed.into_inner().unwrap()).map(|(prepared, hashed)| {
+    let Some(mut pending) = prepared? else { return Ok(None) };
+    // the files it didn't get to weren't checked, its report would say they're fine
+    if hashed.bytes < pending.bytes {
+      cancel.check()?;
+    }
+    if let Some(err) = hashed.error {
+      return Err(err.into());
+    }
+    pending.report.modified.extend(hashed.modified);
+    pending.report.modified.sort();
+    Ok(Some(pending.report))
+  }).collect()
+}
+
+// everything but the hashing: the files that are missing, added or have another size, and the receipt's diff
+fn prepare(meta: &Path, version: &Version, dir: &Path) -> Result<Option<Pending>, InstallError> {
+  let Ok(text) = std::fs::read_to_string(sums_path_in(meta, version)) else { return Ok(None) };
+  let recorded: BTreeMap<String, Entry> = text.lines().filter_map(parse_line).collect();
+  let mut pending = Pending { report: Report { checked: recorded.len(), ..Report::default() }, to_hash: vec![], bytes: 0 };
+  let mut seen = 0;
+  walk(dir, "", &mut |path, file| {
+    match recorded.get(path) {
+      Some(entry) => {
+        seen += 1;
+        // same size first, hashing is what takes time
+        if std::fs::metadata(file)?.len() != entry.size {
+          pending.report.modified.push(path.to_owned());
+        } else {
+          pending.to_hash.push(ToHash { path: path.to_owned(), file: file.to_path_buf(), sha256: entry.sha256.clone(), size: entry.size });
+          pending.bytes += entry.size;
+        }
+      },
+      None => pending.report.added.push(path.to_owned()),
+    }
+    Ok(())
+  })?;
+  let report = &mut pending.report;
+  if seen < recorded.len() {
+    report.missing = recorded.keys().filter(|path| !dir.join(path).is_file()).cloned().collect();
+  }
+  if let Some(listed) = receipt::load_in(meta, version) {
+    let (added, missing) = receipt::diff(&listed, dir)?;
+    for (found, into) in [(added, &mut report.added), (missing, &mut report.missing)] {
+      let new: Vec<String> = found.into_iter().filter(|path| !into.contains(path)).collect();
+      into.extend(new);
+      into.sort();
+    }
+  }
+  Ok(Some(pending))
+}
+
+/// version's recorded sums as (path, sha256, size), none if there aren't any
+pub fn sums(version: &Version) -> Option<Vec<(String, String, u64)>> {
+  let text = std::fs::read_to_string(sums_path(version)).ok()?;
+  Some(text.lines().filter_map(parse_line).map(|(path, entry)| (path, entry.sha256, entry.size)).collect())
+}
+
+fn parse_line(line: &str) -> Option<(String, Entry)> {
+  let mut parts = line.splitn(3, "  ");
+  let sha256 = parts.next()?.to_owned();
+  let size = parts.next()?.parse().ok()?;
+  Some((parts.next()?.to_owned(), Entry { sha256, size }))
+}
+
+fn hash_tree(dir: &Path) -> Result<BTreeMap<String, Entry>, InstallError> {
+  let mut ret = BTreeMap::new();
+  walk(dir, "", &mut |path, file| {
+    let entry = Entry { sha256: sha256_file(file)?, size: std::fs::metadata(file)?.len() };
+    ret.insert(path.to_owned(), entry);
+    Ok(())
+  })?;
+  Ok(ret)
+}
+
+// calls on_file with the relative path and full path of every regular file under dir
+fn walk(dir: &Path, prefix: &str, on_file: &mut dyn FnMut(&str, &Path) -> std::io::Result<()>) -> std::io::Result<()> {
+  for entry in dir.read_dir()? {
+    let entry = entry?;
+    let name = format!("{}{}", prefix, entry.file_name().to_string_lossy());
+    let kind = entry.file_type()?;
+    if kind.is_dir() {
+      walk(&entry.path(), &format!("{}/", name), on_file)?;
+    } else if kind.is_file() {
+      on_file(&name, &entry.path())?;
+    }
+  }
+  Ok(())
+}
@@ -0,0 +1,309 @@
+use std::ffi::OsString;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use duct::Expression;
+use lazy_static::lazy_static;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use serde_with::{DeserializeFromStr, SerializeDisplay};
+use crate::config::Config;
+use crate::local_utils::{is_system, system_home};
+use crate::parsing::version_parser::Version;
+use crate::utils::{env_var_os, ToAbsolute};
+
+// where rvm keeps everything, split like the XDG base dirs
+//   config  config.yaml, aliases.toml, hooks.d
+//   data    versions, tools, shims, libs
+//   cache   remote indexes and build dirs, safe to delete
+//   state   the install lock, usage counters
+// each is, first that applies: RVM_<KIND>_DIR, `dirs` in the config (not for config itself), RVM_HOME for all of them,
+// ~/.rvm (%LOCALAPPDATA%\rvm on windows) when it's already there, then XDG_<KIND>_HOME/rvm or its default
+// windows only has %LOCALAPPDATA%\rvm, system mode everything in system_home
+// nothing rvm writes holds these paths (shims run `rvm plugin exec`, metadata is per version), so moving the tree and
+// pointing RVM_HOME at it is all a move takes: installs remember their prefix and get rebased the next time they're used
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Layout {
+  pub config: PathBuf,
+  pub data: PathBuf,
+  pub cache: PathBuf,
+  pub state: PathBuf,
+}
+
+// `dirs` in the config, for packagers moving data somewhere else
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Dirs {
+  pub data: Option<PathBuf>,
+  pub cache: Option<PathBuf>,
+  pub state: Option<PathBuf>,
+  /// how the dirs of installed versions are named, e.g. "R-{major}.{minor}.{patch}{-pre}" to share another tool's
+  pub version_names: NameTemplate,
+}
+
+pub const CONFIG_VAR: &str = "RVM_CONFIG_DIR";
+pub const DATA_VAR: &str = "RVM_DATA_DIR";
+pub const CACHE_VAR: &str = "RVM_CACHE_DIR";
+pub const STATE_VAR: &str = "RVM_STATE_DIR";
+
+impl Layout {
+  /// Everything in one dir, what RVM_HOME and the old ~/.rvm are
+  pub fn rooted(home: &Path) -> Self {
+    Self { config: home.to_path_buf(), data: home.to_path_buf(), cache: home.join("cache"), state: home.to_path_buf() }
+  }
+
+  /// The layout of the mode rvm runs in
+  pub fn get() -> Self {
+    if is_system() {
+      return Self::rooted(&system_home());
+    }
+    Self::user()
+  }
+
+  /// The user's layout, even in system mode (their libraries stay theirs)
+  pub fn user() -> Self {
+    // Config::load only needs config_dir, no loop
+    let dirs = Config::load().map(|c| c.dirs).unwrap_or_default();
+    Self::from(&|name| env_var_os(name).filter(|v| !v.is_empty()), &dirs)
+  }
+
+  // only the config dir, the config file can't move itself (and loading it needs this)
+  fn config_dir_from(env: &dyn Fn(&str) -> Option<OsString>) -> PathBuf {
+    match env(CONFIG_VAR) {
+      Some(dir) => PathBuf::from(dir).to_absolute(),
+      None => Self::default_from(env).config,
+    }
+  }
+
+  /// The layout for an environment (env looks variables up) and the config's dirs
+  pub fn from(env: &dyn Fn(&str) -> Option<OsString>, dirs: &Dirs) -> Self {
+    let default = Self::default_from(env);
+    let pick = |var: &str, configured: &Option<PathBuf>, default: PathBuf| {
+      env(var).map(PathBuf::from).or_else(|| configured.clone()).map(|p| p.to_absolute()).unwrap_or(default)
+    };
+    Self {
+      config: Self::config_dir_from(env),
+      data: pick(DATA_VAR, &dirs.data, default.data),
+      cache: pick(CACHE_VAR, &dirs.cache, default.cache),
+      state: pick(STATE_VAR, &dirs.state, default.state),
+    }
+  }
+
+  fn default_from(env: &dyn Fn(&str) -> Option<OsString>) -> Self {
+    if let Some(home) = env("RVM_HOME") {
+      return Self::rooted(&PathBuf::from(home).to_absolute());
+    }
+    if let Some(local) = env("LOCALAPPDATA") {
+      return Self::rooted(&PathBuf::from(local).join("rvm"));
+    }
+    let home = PathBuf::from(env("USERPROFILE").or_else(|| env("HOME"))
+      .unwrap_or_else(|| panic!("Failed to find home dir, set RVM_HOME")));
+    // installs from before XDG stay where they are
+    let legacy = home.join(".rvm");
+    if legacy.is_dir() {
+      return Self::rooted(&legacy);
+    }
+    let xdg = |var: &str, default: &str| env(var).map(PathBuf::from).filter(|p| p.is_absolute()).unwrap_or_else(|| home.join(default)).join("rvm");
+    Self {
+      config: xdg("XDG_CONFIG_HOME", ".config"),
+      data: xdg("XDG_DATA_HOME", ".local/share"),
+      cache: xdg("XDG_CACHE_HOME", ".cache"),
+      state: xdg("XDG_STATE_HOME", ".local/state"),
+    }
+  }
+}
+
+/// Gives what rvm runs (hooks, plugins, rvm again) the variables for this same layout
+pub fn with_layout(expression: Expression) -> Expression {
+  let layout = Layout::get();
+  [(CONFIG_VAR, layout.config), (DATA_VAR, layout.data), (CACHE_VAR, layout.cache), (STATE_VAR, layout.state)]
+    .into_iter()
+    .fold(expression, |expression, (var, dir)| expression.env(var, dir))
+}
+
+pub fn config_dir() -> PathBuf {
+  if is_system() {
+    return system_home();
+  }
+  Layout::config_dir_from(&|name| env_var_os(name).filter(|v| !v.is_empty()))
+}
+
+pub fn data_dir() -> PathBuf {
+  Layout::get().data
+}
+
+pub fn cache_dir() -> PathBuf {
+  Layout::get().cache
+}
+
+pub fn state_dir() -> PathBuf {
+  Layout::get().state
+}
+
+lazy_static!{
+  // read once, every install_dir goes through it
+  static ref VERSION_NAMES: NameTemplate = Config::get().dirs.version_names;
+}
+
+/// How install dirs are named, `dirs.version_names` in the config
+pub fn version_names() -> NameTemplate {
+  VERSION_NAMES.clone()
+}
+
+// the name of a version's install dir, made from a template and read back through it:
+//   {version}                the whole version, 4.3.1-rc.1+b2, the default
+//   {major} {minor} {patch}  one number each
+//   {-pre} {+build}          the pre-release and build with their - and +, nothing when there's none
+// a version the template can't say (its fourth part, a pre-release without {-pre}) is named {version} instead,
+// and a name is only a version's when naming that version gives it back, so no two versions share a dir
+#[derive(Debug, Clone, PartialEq, Eq, DeserializeFromStr, SerializeDisplay)]
+pub struct NameTemplate {
+  template: String,
+  pieces: Vec<Piece>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Piece {
+  Text(String),
+  Version,
+  Major,
+  Minor,
+  Patch,
+  Pre,
+  Build,
+}
+
+// what a name matched each piece with
+#[derive(Debug, Clone, Default)]
+struct Captures {
+  version: Option<String>,
+  parts: [Option<String>; 3],
+  pre: Option<String>,
+  build: Option<String>,
+}
+
+impl Default for NameTemplate {
+  fn default() -> Self {
+    "{version}".parse().expect("the default template parses")
+  }
+}
+
+impl Display for NameTemplate {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}", self.template)
+  }
+}
+
+impl FromStr for NameTemplate {
+  type Err = String;
+
+  fn from_str(template: &str) -> Result<Self, Self::Err> {
+    let mut pieces = vec![];
+    let mut rest = template;
+    while !rest.is_empty() {
+      let Some(open) = rest.find('{') else {
+        pieces.push(Piece::Text(rest.to_owned()));
+        break;
+      };
+      if open > 0 {
+        pieces.push(Piece::Text(rest[..open].to_owned()));
+      }
+      let close = rest[open..].find('}').ok_or_else(|| format!("{} has a {{ without a }}", template))? + open;
+      pieces.push(match &rest[open + 1..close] {
+        "version" => Piece::Version,
+        "major" => Piece::Major,
+        "minor" => Piece::Minor,
+        "patch" => Piece::Patch,
+        "-pre" => Piece::Pre,
+        "+build" => Piece::Build,
+        other => return Err(format!("{{{}}} isn't one of {{version}}, {{major}}, {{minor}}, {{patch}}, {{-pre}} or {{+build}}", other)),
+      });
+      rest = &rest[close + 1..];
+    }
+    if pieces.iter().any(|p| matches!(p, Piece::Text(text) if text.contains(['/', '\\', '}']))) {
+      return Err(format!("{} has a path separator or a stray }}, it names a single dir", template));
+    }
+    let has = |piece: Piece| pieces.contains(&piece);
+    if !(has(Piece::Version) || has(Piece::Major) && has(Piece::Minor) && has(Piece::Patch)) {
+      return Err(format!("{} needs {{version}}, or {{major}}, {{minor}} and {{patch}}, to tell versions apart", template));
+    }
+    Ok(Self { template: template.to_owned(), pieces })
+  }
+}
+
+impl NameTemplate {
+  /// The name of version's install dir
+  pub fn name(&self, version: &Version) -> String {
+    let name = self.fill(version);
+    match self.matched(&name) {
+      Some(matched) if &matched == version => name,
+      _ => version.to_string(),
+    }
+  }
+
+  /// The version a dir named name is the install dir of, none when it isn't one's
+  pub fn parse(&self, name: &str) -> Option<Version> {
+    self.matched(name).into_iter().chain(Version::parse(name).ok()).find(|version| self.name(version) == name)
+  }
+
+  fn fill(&self, version: &Version) -> String {
+    let (major, minor, patch) = version.parts();
+    self.pieces.iter().map(|piece| match piece {
+      Piece::Text(text) => text.clone(),
+      Piece::Version => version.to_string(),
+      Piece::Major => major.to_string(),
+      Piece::Minor => minor.to_string(),
+      Piece::Patch => patch.to_string(),
+      Piece::Pre => version.pre_release_tag().map(|pre| format!("-{}", pre)).unwrap_or_default(),
+      Piece::Build => version.build_metadata().map(|build| format!("+{}", build)).unwrap_or_default(),
+    }).collect()
+  }
+
+  // the version name matches the pieces with, the first way that makes one
+  fn matched(&self, name: &str) -> Option<Version> {
+    let captures = match_pieces(&self.pieces, name, Captures::default())?;
+    match captures.version {
+      Some(version) => Version::parse(&version).ok(),
+      None => {
+        let [major, minor, patch] = captures.parts.map(Option::unwrap_or_default);
+        let pre = captures.pre.map(|pre| format!("-{}", pre)).unwrap_or_default();
+        let build = captures.build.map(|build| format!("+{}", build)).unwrap_or_default();
+        Version::parse(format!("{}.{}.{}{}{}", major, minor, patch, pre, build)).ok()
+      },
+    }
+  }
+}
+
+// backtracks over where each piece ends, numbers as long as they can be first
+fn match_pieces(pieces: &[Piece], rest: &str, captures: Captures) -> Option<Captures> {
+  let Some((piece, others)) = pieces.split_first() else {
+    return rest.is_empty().then_some(captures);
+  };
+  let ends = |of: &str| (1..=of.len()).filter(|&end| of.is_char_boundary(end)).collect::<Vec<_>>();
+  match piece {
+    Piece::Text(text) => match_pieces(others, rest.strip_prefix(text.as_str())?, captures),
+    Piece::Major | Piece::Minor | Piece::Patch => {
+      let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+      let at = match piece { Piece::Major => 0, Piece::Minor => 1, _ => 2 };
+      (1..=digits).rev().find_map(|end| {
+        let mut captures = captures.clone();
+        captures.parts[at] = Some(rest[..end].to_owned());
+        match_pieces(others, &rest[end..], captures)
+      })
+    },
+    Piece::Version => ends(rest).into_iter().find_map(|end| {
+      let mut captures = captures.clone();
+      captures.version = Some(rest[..end].to_owned());
+      match_pieces(others, &rest[end..], captures)
+    }),
+    Piece::Pre | Piece::Build => {
+      let mark = if piece == &Piece::Pre { '-' } else { '+' };
+      let with = rest.strip_prefix(mark).and_then(|after| ends(after).into_iter().find_map(|end| {
+        let mut captures = captures.clone();
+        let value = Some(after[..end].to_owned());
+        if piece == &Piece::Pre { captures.pre = value } else { captures.build = value }
+        match_pieces(others, &after[end..], captures)
+      }));
+      with.or_else(|| match_pieces(others, rest, captures.clone()))
+    },
+  }
+}
@@ -0,0 +1,93 @@
+//! rvm's version management without its cli: resolving which R a project uses, the registries versions come from,
+//! installing and removing them, and the environment that makes one the active R.
+//! The `rvm` binary is a clap frontend over this, anything it does an IDE plugin or a bot can do too:
+//!
+//! ```no_run
+//! use rvm_core::cancel::CancellationToken;
+//! use rvm_core::index::{remote_versions, CachePolicy, CranSource};
+//! use rvm_core::install::{install, HttpDownloader, NoReporter};
+//! use rvm_core::resolve::{active_version, request_range};
+//!
+//! let available = remote_versions(&CranSource::default(), CachePolicy::Default).unwrap();
+//! let range = request_range("4.3").unwrap();
+//! let meta = available.iter().find(|m| range.contains(&m.version)).unwrap();
+//! install(meta, rvm_core::config::Config::get().backend, &HttpDownloader, &NoReporter, &CancellationToken::new()).unwrap();
+//! let active = active_version(None, std::path::Path::new(".")).unwrap();
+//! for (name, value) in rvm_core::activate::env_for(&active.version) {
+//!   println!("{}={:?}", name, value);
+//! }
+//! ```
+//!
+//! Long operations report progress through [`install::Reporter`], lifecycle events go to every registered
+//! [`observer::Observer`], and errors are each module's own thiserror enum. With the `async` feature
+//! `nonblocking` has async versions of the registry and download apis.
+
+/// Version, range and request parsing, and the project file format
+pub mod parsing;
+/// The merged user and machine config
+pub mod config;
+/// Registries: the remote index, its cache and formats
+pub mod index;
+/// Installing, verifying and removing versions
+pub mod install;
+/// Which installed version is active and why
+pub mod resolve;
+/// Policies on which versions resolving can pick
+pub mod filter;
+/// The admin's allowed and denied versions and registries
+pub mod policy;
+/// The environment (PATH, R_HOME, R_LIBS_USER) of a version
+pub mod activate;
+/// Names for versions and ranges
+pub mod alias;
+/// The lock held while installs change
+pub mod filelock;
+/// Where rvm keeps its config, data, cache and state
+pub mod layout;
+/// Targets artifacts are built for
+pub mod platform;
+/// Shell specific code for hooks and `rvm shell`
+pub mod shell;
+/// Shell hooks around installs and switches
+pub mod hooks;
+/// In process callbacks for the same events
+pub mod observer;
+/// Other tools managed like R
+pub mod plugin;
+/// Versions other tools (asdf, rig...) installed
+pub mod import;
+/// Exported lists of installs
+pub mod manifest;
+/// Project lockfiles
+pub mod lockfile;
+/// Rewriting the R constraint of a project file in place
+pub mod constraint_file;
+/// Credentials for private registries
+pub mod auth;
+/// Per host request limits
+pub mod throttle;
+/// Styles of human output
+pub mod theme;
+/// Local usage counters, with `stats: true`
+pub mod stats;
+/// The log of installs, uninstalls and switches
+pub mod audit;
+/// The background process shell hooks ask instead of resolving
+pub mod daemon;
+/// What the shell hooks resolved in each dir
+pub mod hook_cache;
+/// Parse, compare, satisfies and resolve for editors, a json request per line
+pub mod query;
+/// Stable codes of every error, for matching on them
+pub mod error_code;
+/// Ctrl-C during downloads, builds and verification
+pub mod cancel;
+/// Runtime constraints in other ecosystems' manifests
+pub mod detect;
+/// Registries' signing keys, trusted on first use
+pub mod trust;
+/// Tracing setup
+pub mod logging;
+#[macro_use] pub mod utils;
+pub mod local_utils;
+#[cfg(feature = "async")] pub mod nonblocking;
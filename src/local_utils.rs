@@ -1,9 +1,15 @@
+use std::collections::HashMap;
 use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
 use duct::cmd;
 use lazy_static::lazy_static;
 use reqwest::blocking::Response;
-use reqwest::StatusCode;
-use crate::utils::{response_to_file_path, ToAbsolute};
+use reqwest::{NoProxy, Proxy, StatusCode};
+use crate::auth::WithAuth;
+use crate::config::{Config, Mirror};
+use crate::index::CranSource;
+use crate::utils::{env_flag, env_var_os, response_to_file_path, ToAbsolute};
 use cli_prompts::{
   DisplayPrompt,
   prompts::{Confirmation, Input},
@@ -27,7 +33,7 @@ pub fn download_R(version: &String, dest: Option<PathBuf>) -> Result<PathBuf, St
   // returns the path for the  exe installer
   // dest is the Windows temp folder if is None
   //TODO change to be possible to change CRAN
-  let url: &String = &format!("https://cran.r-project.org/bin/windows/base/old/{}/R-{}-win.exe", version, version); //TODO change it to toggle between archived and not (20 versions behind thye change url)
+  let url: &String = &Mirror::rewrite(&Config::get().mirrors, &CranSource::default().installer_url(version)); //TODO change it to toggle between archived and not (20 versions behind thye change url)
   request!(head, url); //head request to check if the file exists
   let response: Response = request!(get, url); //get request to download the file
   let destination = dest.unwrap_or(std::env::temp_dir());
@@ -76,10 +82,84 @@ pub fn get_latest_R() -> Result<String, StatusCode> {
 // lazy static is here for singleton-like patterns
 lazy_static!{
   // http client
-  static ref CLIENT: reqwest::blocking::Client = reqwest::blocking::Client::new();
+  pub static ref CLIENT: reqwest::blocking::Client = build_client(&Config::get());
+}
+
+// a client's connect and read timeouts
+type Timeouts = (Option<Duration>, Option<Duration>);
+
+lazy_static!{
+  // clients for the registries with timeouts of their own, by those timeouts
+  static ref CLIENTS: Mutex<HashMap<Timeouts, reqwest::blocking::Client>> = Mutex::new(HashMap::new());
+}
+
+fn build_client(config: &Config) -> reqwest::blocking::Client {
+  let network = config.requests.defaults();
+  build_client_with(config, network.connect_timeout, network.read_timeout)
+}
+
+/// A client with config's proxy and these timeouts. read_timeout is the blocking client's own timeout, which is how long
+/// each wait may take (for the answer, then for each read of its body), unlike a request's timeout which is for all of
+/// it and would cut off a big download taking longer
+pub fn build_client_with(config: &Config, connect_timeout: Option<Duration>, read_timeout: Option<Duration>) -> reqwest::blocking::Client {
+  let mut builder = reqwest::blocking::Client::builder();
+  if let Some(proxy) = configured_proxy(config) {
+    builder = builder.proxy(proxy);
+  }
+  if let Some(timeout) = connect_timeout {
+    builder = builder.connect_timeout(timeout);
+  }
+  if let Some(timeout) = read_timeout {
+    builder = builder.timeout(timeout);
+  }
+  builder.build().unwrap_or_else(|err| panic!("Failed to build http client: {}", err))
+}
+
+/// The client for url, CLIENT unless its registry has timeouts of its own in the config
+pub fn client_for(url: &str) -> reqwest::blocking::Client {
+  let config = Config::get();
+  let network = config.requests.for_url(url);
+  let timeouts = (network.connect_timeout, network.read_timeout);
+  let default = config.requests.defaults();
+  if timeouts == (default.connect_timeout, default.read_timeout) {
+    return CLIENT.clone();
+  }
+  let mut clients = CLIENTS.lock().unwrap_or_else(|e| e.into_inner());
+  clients.entry(timeouts).or_insert_with(|| build_client_with(&config, timeouts.0, timeouts.1)).clone()
+}
+
+pub fn configured_proxy(config: &Config) -> Option<Proxy> {
+  // without a configured proxy reqwest already honors HTTP_PROXY, HTTPS_PROXY, ALL_PROXY and NO_PROXY
+  let proxy = config.proxy.as_ref()?;
+  let no_proxy = config.no_proxy.as_deref().map(NoProxy::from_string).unwrap_or_else(NoProxy::from_env);
+  let proxy = Proxy::all(proxy)
+    .unwrap_or_else(|err| panic!("Invalid proxy {} in config: {}", proxy, err))
+    .no_proxy(no_proxy);
+  Some(proxy)
 }
 
 pub fn curr_dir() -> PathBuf {
   // Here it can panic because it should be getting anywhere
   std::env::current_dir().unwrap_or_else(|err| panic!("Failed to get current dir: {:?}", err))
-}
\ No newline at end of file
+}
+
+/// set by --system, for rvm itself and whatever it runs
+pub const SYSTEM_VAR: &str = "RVM_SYSTEM";
+
+/// Whether rvm works on the shared prefix instead of the user's home
+pub fn is_system() -> bool {
+  env_flag(SYSTEM_VAR)
+}
+
+// the shared prefix for every user of the machine, RVM_SYSTEM_HOME overrides
+// %ProgramData%\rvm on windows, /opt/rvm elsewhere
+pub fn system_home() -> PathBuf {
+  if let Some(home) = env_var_os("RVM_SYSTEM_HOME") {
+    return PathBuf::from(home).to_absolute();
+  }
+  if cfg!(windows) {
+    let data = std::env::var_os("ProgramData").unwrap_or_else(|| r"C:\ProgramData".into());
+    return PathBuf::from(data).join("rvm");
+  }
+  PathBuf::from("/opt/rvm")
+}
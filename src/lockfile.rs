@@ -0,0 +1,67 @@
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use crate::parsing::version_parser::{Range, Version};
+use crate::utils::env_flag;
+
+// <project>\rvm.lock, the exact version the project's range resolved to when `rvm update` last ran
+// so everyone on the project (and CI) gets the same R, not just one matching the range
+pub const LOCK_FILE: &str = "rvm.lock";
+/// set by --locked (or by hand, e.g. in CI), a missing or stale lockfile is an error instead of a warning
+pub const LOCKED_VAR: &str = "RVM_LOCKED";
+pub const FORMAT: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProjectLock {
+  pub format: u32,
+  /// the project's rversion when it was locked, if it changed since the lock is stale
+  pub range: Range,
+  pub version: Version,
+  /// the artifact it was installed from and its sha256, when rvm knows them
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub url: Option<String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub sha256: Option<String>,
+}
+
+#[derive(Error, Debug)]
+pub enum LockfileError {
+  #[error("error in reading/writing lockfile")]
+  Io(#[from] std::io::Error),
+  #[error("error in parsing lockfile")]
+  De(#[from] toml::de::Error),
+  #[error("error in writing lockfile")]
+  Ser(#[from] toml::ser::Error),
+  #[error("lockfile format {0} is newer than this rvm understands ({FORMAT}), update rvm")]
+  Format(u32),
+}
+
+impl ProjectLock {
+  /// The lockfile next to a project file
+  pub fn path_for(project_file: &Path) -> PathBuf {
+    project_file.with_file_name(LOCK_FILE)
+  }
+
+  /// None if there's no lockfile
+  pub fn load(path: &Path) -> Result<Option<Self>, LockfileError> {
+    if !path.exists() {
+      return Ok(None);
+    }
+    let lock: Self = toml::from_str(&std::fs::read_to_string(path)?)?;
+    if lock.format > FORMAT {
+      return Err(LockfileError::Format(lock.format));
+    }
+    Ok(Some(lock))
+  }
+
+  pub fn save(&self, path: &Path) -> Result<(), LockfileError> {
+    let header = "# written by `rvm update`, commit it next to Renv.yaml\n";
+    std::fs::write(path, format!("{}{}", header, toml::to_string(self)?))?;
+    Ok(())
+  }
+}
+
+/// Whether drift from the lockfile should fail
+pub fn is_locked() -> bool {
+  env_flag(LOCKED_VAR)
+}
@@ -0,0 +1,31 @@
+use std::io::IsTerminal;
+use tracing_subscriber::EnvFilter;
+
+// diagnostics go through tracing to stderr, off unless asked for so the normal output stays clean
+// -v is info, -vv debug, -vvv trace, RVM_LOG (same syntax as RUST_LOG) overrides them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum LogFormat {
+  Text,
+  Json,
+}
+
+pub fn init(verbosity: u8, format: LogFormat) {
+  let level = match verbosity {
+    0 => "warn",
+    1 => "info",
+    2 => "debug",
+    _ => "trace",
+  };
+  // only rvm's own events, the http stack is too chatty at debug
+  let filter = EnvFilter::try_from_env("RVM_LOG").unwrap_or_else(|_| EnvFilter::new(format!("rvm={}", level)));
+  let builder = tracing_subscriber::fmt().with_env_filter(filter).with_writer(std::io::stderr)
+    .with_ansi(std::io::stderr().is_terminal())
+    .with_target(false);
+  let result = match format {
+    LogFormat::Text => builder.try_init(),
+    LogFormat::Json => builder.json().try_init(),
+  };
+  if let Err(err) = result {
+    eprintln!("Failed to set up logging: {}", err);
+  }
+}
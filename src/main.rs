@@ -1,19 +1,56 @@
 mod args;
 mod actions;
-#[macro_use] mod utils;
-mod local_utils;
-mod parsing;
+mod output;
+mod reporter;
+mod exit;
+#[cfg(test)] mod property;
+// the cli over rvm_core, so crate:: paths in the cli stay what they were
+use rvm_core::{utils, local_utils, parsing, config, index, install, filelock, alias, resolve, activate, platform, shell,
+  logging, hooks, plugin, import, manifest, lockfile, layout, theme, observer, stats, constraint_file, audit, filter, policy, error_code, daemon, hook_cache, query, cancel, detect, trust};
+#[cfg(test)] use rvm_core::{auth, throttle};
 
 use std::path::Path;
-use clap::Parser;
 use args::{Cli, Action};
 use crate::utils::ToAbsolute;
+use crate::exit::{fail, Exit};
 
 fn main() {
-  let args = &Cli::parse();
-  // switch functions based on command
-  match &args.action {
-    Action::Init {rversion, path} =>
+  let mut args = Cli::try_parse_with_env(std::env::args_os(), utils::env_var_os).unwrap_or_else(|err| err.exit());
+  if args.json_lines {
+    // what doesn't stream prints its --json result, on one line
+    args.json = true;
+    output::set_json_lines();
+  }
+  let args = &args;
+  logging::init(args.verbose, args.log_format);
+  theme::init(args.color);
+  observer::register(observer::LogObserver);
+  observer::register(observer::WarningObserver);
+  if args.locked {
+    // through the environment so rvm run by hooks and `rvm exec` children is locked too
+    std::env::set_var(lockfile::LOCKED_VAR, "1");
+  }
+  if args.system {
+    std::env::set_var(local_utils::SYSTEM_VAR, "1");
+  }
+  if args.offline {
+    std::env::set_var(index::OFFLINE_VAR, "1");
+  }
+  if args.insecure_digest {
+    std::env::set_var(install::digest::INSECURE_DIGEST_VAR, "1");
+  }
+  if args.trust_new_key {
+    std::env::set_var(trust::TRUST_NEW_KEY_VAR, "1");
+  }
+  if args.deny_warnings {
+    std::env::set_var(parsing::warning::DENY_WARNINGS_VAR, "1");
+  }
+  // switch functions based on command, exit.rs has what each failure exits with
+  let code = exit::run(args.json, || match &args.action {
+    Action::Init {hook: Some(hook), ..} => {
+      actions::init::hook(*hook)
+    },
+    Action::Init {rversion, path, ..} =>
       actions::init::main(
         String::from(rversion),
         Path::new(&path),
@@ -30,8 +67,220 @@ fn main() {
       dbg!(command);
       dbg!(path);
       todo!()
+    },
+    Action::ListRemote {refresh, channel, filter, exclude_prereleases, only_prereleases, all_builds} => {
+      let filter = actions::ListFilter::new(filter.as_deref(), *exclude_prereleases, *only_prereleases);
+      actions::list_remote::main(*refresh, channel.as_deref(), &filter, *all_builds, args)
+    },
+    Action::Install {path: Some(path), as_version: Some(as_version), ..} => {
+      actions::install::local(path, as_version, args)
+    },
+    Action::Install {git: Some(url), reference, as_version, ..} => {
+      actions::install::git(url, reference.as_deref(), as_version.as_deref(), args)
+    },
+    Action::Install {interactive: true, backend, channel, strict_budget, ..} => {
+      actions::install::interactive(*backend, channel.as_deref(), *strict_budget, args)
+    },
+    Action::Install {versions, jobs, backend, channel, strict_budget, ..} => {
+      actions::install::main(versions.to_owned(), *jobs, *backend, channel.as_deref(), *strict_budget, args)
+    },
+    Action::Uninstall {version} => {
+      actions::uninstall::main(version.to_owned(), args)
+    },
+    Action::Which {binary} => {
+      actions::which::main(binary.to_owned(), args)
+    },
+    Action::Exec {version, command} => {
+      actions::exec::main(version.to_owned(), command.to_owned(), args)
+    },
+    Action::Each {range, command, fail_fast} => {
+      actions::each::main(range.to_owned(), command.to_owned(), *fail_fast, args)
+    },
+    Action::Doctor {mirrors: true, ..} => {
+      actions::doctor::mirrors(args)
+    },
+    Action::Doctor {fix, ..} => {
+      actions::doctor::main(*fix, args)
+    },
+    Action::Prune {projects, keep_latest_per_minor, older_than, unused_for} => {
+      actions::prune::main(projects.to_owned(), *keep_latest_per_minor, *older_than, *unused_for, args)
+    },
+    Action::Pin {version} => {
+      actions::pin::main(version.to_owned(), true, args)
+    },
+    Action::Unpin {version} => {
+      actions::pin::main(version.to_owned(), false, args)
+    },
+    Action::Lock {version} => {
+      actions::protect::main(version.to_owned(), true, args)
+    },
+    Action::Unlock {version} => {
+      actions::protect::main(version.to_owned(), false, args)
+    },
+    Action::List {size, last_used, origin, compact, filter, exclude_prereleases, only_prereleases} => {
+      let filter = actions::ListFilter::new(filter.as_deref(), *exclude_prereleases, *only_prereleases);
+      actions::list::main(*size, *last_used, *origin, *compact, &filter, args)
+    },
+    Action::Outdated {projects, sort_by_staleness} => {
+      actions::outdated::main(projects.to_owned(), *sort_by_staleness, args)
+    },
+    Action::Upgrade {each_minor: true, remove_old, ..} => {
+      actions::upgrade::each_minor(*remove_old, args)
+    },
+    Action::Upgrade {target, remove_old, ..} => {
+      actions::upgrade::main(target.to_owned(), *remove_old, args)
+    },
+    Action::Changes {range} => {
+      actions::changes::main(range.as_deref(), args)
+    },
+    Action::New {dir, version, no_lock, ci} => {
+      actions::new::main(dir, version, *no_lock, *ci, args)
+    },
+    Action::Local {bump: Some(part), ..} => {
+      actions::local::bump(*part, args)
+    },
+    Action::Local {none: true, ..} => {
+      actions::local::none(args)
+    },
+    Action::Local {range, expires, ..} => {
+      actions::local::main(range.as_deref().unwrap_or_default(), expires.as_deref(), args)
+    },
+    Action::Global {range} => {
+      actions::global::main(range, args)
+    },
+    Action::Resolve {version, explain: true, ..} => {
+      actions::resolve::explain(version.as_deref(), args)
+    },
+    Action::Resolve {version: Some(range), minimal: true, ..} => {
+      actions::resolve::minimal(range, args)
+    },
+    Action::Resolve {version: Some(range), installed: true, ..} => {
+      actions::resolve::best(range, false, args)
+    },
+    Action::Resolve {version: Some(range), remote, ..} => {
+      actions::resolve::best(range, *remote, args)
+    },
+    Action::Resolve {..} => {
+      actions::resolve::main(args)
+    },
+    Action::Completions {shell} => {
+      actions::complete::script(*shell)
+    },
+    Action::Complete {words} => {
+      actions::complete::main(words)
+    },
+    Action::HookEnv {shell} => {
+      actions::hook_env::main(*shell, args)
+    },
+    Action::HookBench {dir, runs, shell} => {
+      actions::hook_bench::main(*runs, *shell, dir, args)
+    },
+    Action::Daemon {stop, status, interval} => {
+      actions::daemon::main(*stop, *status, *interval, args)
+    },
+    Action::Query {request, stdin: _} => {
+      actions::query::main(request.as_deref())
+    },
+    Action::Direnv {action} => {
+      actions::direnv::main(action, args)
+    },
+    Action::Shell {interactive: true, shell, ..} => {
+      actions::shell::interactive(*shell, args)
+    },
+    Action::Shell {version, unset, shell, ..} => {
+      actions::shell::main(version.to_owned(), *unset, *shell, args)
+    },
+    Action::Env {version, shell} => {
+      actions::env::main(version.as_deref(), *shell, args)
+    },
+    Action::Libs {action} => {
+      actions::libs::main(action, args)
+    },
+    Action::Plugin {action} => {
+      actions::plugin::main(action, args)
+    },
+    Action::Index {action} => {
+      actions::index::main(action, args)
+    },
+    Action::Export => {
+      actions::export::main(args)
+    },
+    Action::Diff {old, new} => {
+      actions::diff::main(old, new.as_deref(), args)
+    },
+    Action::Detect => {
+      actions::detect::main(args)
+    },
+    Action::Import {file: Some(file), ..} => {
+      actions::import::manifest(file, args)
+    },
+    Action::Import {from, copy, ..} => {
+      let from = from.unwrap_or_else(|| fail(Exit::Usage, "Nothing to import, pass a manifest or --from"));
+      actions::import::main(from, *copy, args)
+    },
+    Action::Update => {
+      actions::update::main(args)
+    },
+    Action::Satisfies {version, range} => {
+      actions::satisfies::main(version, range, args)
+    },
+    Action::Lint {file} => {
+      actions::lint::main(file.as_deref(), args)
+    },
+    Action::Compare {a, b, explain, ignore_case} => {
+      actions::compare::main(a, b, *explain, *ignore_case, args)
+    },
+    Action::Sort {reverse, filter, latest_per, unique, normalize, pad, v_prefix, short, keep_zeros} => {
+      let format = actions::sort::formatter(*normalize, pad, *v_prefix, *short, *keep_zeros);
+      actions::sort::main(*reverse, filter.as_deref(), *latest_per, *unique, format.as_ref(), args)
+    },
+    Action::Du => {
+      actions::du::main(args)
+    },
+    Action::Try {range, keep, command} => {
+      actions::trial::main(range, *keep, command, args)
+    },
+    Action::Status => {
+      actions::status::main(args)
+    },
+    Action::Stats => {
+      actions::stats::main(args)
+    },
+    Action::History {limit} => {
+      actions::history::main(*limit, args)
+    },
+    Action::Undo => {
+      actions::history::undo(args)
+    },
+    Action::Adopt {dir, version} => {
+      actions::adopt::main(dir, version, args)
+    },
+    Action::CiSnippet {format} => {
+      actions::ci_snippet::main(*format)
+    },
+    Action::Bundle {version, output, prefix} => {
+      actions::bundle::main(version, output, prefix.as_deref(), args)
+    },
+    Action::Paths => {
+      actions::paths::main(args)
+    },
+    Action::Verify {version, all, repair, jobs} => {
+      actions::verify::main(version.as_deref(), *all, *repair, *jobs, args)
+    },
+    Action::Gc => {
+      actions::gc::main(args)
+    },
+    Action::Check {version} => {
+      actions::check::main(version.as_deref(), args)
+    },
+    Action::Workspace {action} => {
+      actions::workspace::main(action, args)
+    },
+    Action::Alias {action} => {
+      actions::alias::main(action, args)
     }
-  }
+  });
+  std::process::exit(code);
 }
 
 #[allow(non_upper_case_globals)]
@@ -43,6 +292,16 @@ mod tests {
   use crate::parsing::grammer::the_parser::{parse_dependencies, parse_dependency};
   use crate::parsing::version_parser::{ParseError, Range};
   use crate::parsing::version_parser::Version;
+  use crate::index::CranSource;
+  use crate::config::Mirror;
+  use crate::import::r_from_tool_versions;
+  use crate::lockfile::ProjectLock;
+  use crate::alias::Aliases;
+  use crate::utils::{format_bytes, parse_duration};
+  use std::time::Duration;
+  use crate::install::git::version_from_ref;
+  use crate::platform::{Arch, Libc, Os, Platform};
+  use crate::shell::Shell;
   #[test]
   fn parse_ver() {
     let m = Version::new(1, 2, 3);
@@ -150,7 +409,7 @@ mod tests {
       max: Some(Version::new(1, 2, 5).to_owned()),
       ..Default::default()
     });
-    assert_eq!(r("1.2.3 1.2.4"), Range {
+    assert_eq!(r("1.2.3, 1.2.4"), Range {
       include: vec![Version::new(1, 2, 3).to_owned(), Version::new(1, 2, 4).to_owned()],
       ..Default::default()
     });
@@ -276,6 +535,2281 @@ mod tests {
     ]));
   }
 
+  #[test]
+  fn parse_cran_listing() {
+    let source = CranSource::default();
+    let body = r#"<html><body><ul>
+      <li><a href="../">Parent Directory</a></li>
+      <li><a href="4.2.3/">R 4.2.3</a> (March, 2023)</li>
+      <li><a href="4.3.1/">R 4.3.1</a> (June, 2023)</li>
+      <li><a href="index.html">not a version</a></li>
+    </ul></body></html>"#;
+    let entries = source.parse_listing(body);
+    assert_eq!(entries.iter().map(|e| e.version.clone()).collect::<Vec<_>>(), vec![Version::new(4, 3, 1), Version::new(4, 2, 3)]);
+    assert_eq!(entries[0].url, source.installer_url("4.3.1"));
+  }
+
+  #[test]
+  fn rewrite_mirrors() {
+    let mirrors = vec![
+      Mirror { from: "https://cran.r-project.org/".to_owned(), to: "https://mirror.example/cran/".to_owned() },
+      Mirror { from: "https://cran.r-project.org/bin/".to_owned(), to: "https://unused.example/".to_owned() },
+    ];
+    assert_eq!(Mirror::rewrite(&mirrors, "https://cran.r-project.org/bin/windows/base/old/4.3.1/R-4.3.1-win.exe"),
+      "https://mirror.example/cran/bin/windows/base/old/4.3.1/R-4.3.1-win.exe");
+    assert_eq!(Mirror::rewrite(&mirrors, "https://github.com/x"), "https://github.com/x");
+  }
+
+  #[test]
+  fn range_contains() {
+    assert!(r(">=1.2.3, <2").contains(&Version::new(1, 9, 0)));
+    assert!(!r(">=1.2.3, <2").contains(&Version::new(2, 0, 0)));
+    assert!(!r(">=1.2.3, <2, !=1.5.0").contains(&Version::new(1, 5, 0)));
+    assert!(r(">=1.2.3, <=1.2.5, 1.2.7").contains(&Version::new(1, 2, 7)));
+    assert!(!r("1.2.3").contains(&Version::new(1, 2, 4)));
+    assert!(Range::default().contains(&Version::new(0, 1, 0)));
+    let pool = [Version::new(4, 2, 3), Version::new(4, 3, 1), Version::new(4, 2, 1), Version::new(3, 6, 3)];
+    assert_eq!(Range::from_request("4.2").unwrap().max_satisfying(&pool), Some(&Version::new(4, 2, 3)));
+    assert_eq!(Range::from_request("4").unwrap().max_satisfying(&pool), Some(&Version::new(4, 3, 1)));
+    assert_eq!(Range::from_request("4.2.1").unwrap().max_satisfying(&pool), Some(&Version::new(4, 2, 1)));
+    assert_eq!(Range::from_request("<4").unwrap().max_satisfying(&pool), Some(&Version::new(3, 6, 3)));
+    assert_eq!(Range::from_request("5").unwrap().max_satisfying(&pool), None);
+  }
+
+  #[test]
+  fn snapshot_order() {
+    let (older, newer) = (p("4.4.0-nightly.20240315"), p("4.4.0-nightly.20240401"));
+    assert!(older < newer);
+    assert!(newer < p("4.4.0"));
+    assert!(p("4.4.0-nightly.9") < p("4.4.0-nightly.10"));
+    assert!(p("4.4.0-rc.1") < p("4.4.0-rc.1.1"));
+    assert_eq!(newer.channel(), Some("nightly"));
+    let entry = |v: &str| crate::index::ArtifactMeta { version: p(v), url: String::new(), source_url: None, build_script: None, sha256: None, source_sha256: None, source_archive: None, platform: None, eol: None, released: None, yanked: false, support: None, deltas: vec![], source_deltas: vec![], notes_url: None, notes: None, relocate: None, env: Default::default() };
+    let entries = [entry("4.3.3"), entry("4.4.0-nightly.20240315"), entry("4.4.0-nightly.20240401"), entry("4.4.0-rc.1")];
+    let newest = |request: &str, channel| crate::index::newest_on_channel(&Range::from_request(request).unwrap(), &entries, channel).map(|m| m.version.to_string());
+    assert_eq!(newest("4", None).as_deref(), Some("4.3.3"));
+    assert_eq!(newest("4.4", Some("nightly")).as_deref(), Some("4.4.0-nightly.20240401"));
+    assert_eq!(newest("4", Some("release")).as_deref(), Some("4.3.3"));
+    assert_eq!(newest("=4.4.0-nightly.20240315", None).as_deref(), Some("4.4.0-nightly.20240315"));
+  }
+
+  #[test]
+  fn range_holes() {
+    let minimal = |range: &str| Range::parse(range).unwrap().minimal_version().map(|v| v.to_string());
+    assert_eq!(minimal("~4.2.1").as_deref(), Some("4.2.1"));
+    assert_eq!(minimal("<4.0.0").as_deref(), Some("0.0.0"));
+    assert_eq!(minimal(">=4.2.1, !=4.2.1, !=4.2.2").as_deref(), Some("4.2.3"));
+    assert_eq!(minimal(">=4.2.1, <4.2.2, !=4.2.1"), None);
+    assert_eq!(minimal(">=4.4.0-rc.1").as_deref(), Some("4.4.0-rc.1"));
+    let range = Range::parse(">=4.2.0, <4.3.0, !=4.2.1, !=4.3.0").unwrap();
+    assert_eq!(range.holes(), vec![&p("4.2.1")]);
+    assert!(range.has_hole());
+    assert!(!Range::parse("^4").unwrap().has_hole());
+  }
+
+  #[test]
+  fn comparators_round_trip() {
+    use crate::parsing::version_parser::{Comparator, Op};
+    let built = vec![
+      Comparator { op: Op::Ge, version: p("4.1.0") },
+      Comparator { op: Op::Lt, version: p("4.3.0") },
+      Comparator { op: Op::Ne, version: p("4.2.1") },
+      Comparator { op: Op::Eq, version: p("4.4.0-rc.1") },
+    ];
+    let range = Range::from_comparators(built.clone()).unwrap();
+    assert_eq!(range.comparators(), built);
+    assert_eq!(range.to_string(), ">=4.1.0,<4.3.0,!=4.2.1,=4.4.0-rc.1");
+    assert_eq!(Range::parse(&range.to_string()).unwrap(), range);
+    let tilde = Range::from_comparators([Comparator { op: Op::Tilde, version: p("4.2.0") }]).unwrap();
+    assert_eq!(tilde.comparators(), vec![Comparator { op: Op::Ge, version: p("4.2.0") }, Comparator { op: Op::Lt, version: p("4.3.0") }]);
+    assert_eq!(Range::from_comparators(tilde.comparators()).unwrap(), tilde);
+  }
+
+  #[test]
+  fn tightest_bounds() {
+    let range = Range::parse(">=4.1.0, <4.4.0, >=4.2.0, <4.3.0").unwrap();
+    assert_eq!((range.min, range.max), (Some(p("4.2.0")), Some(p("4.3.0"))));
+    assert_eq!(Range::parse(">=1.0, >=2.0").unwrap().min, Some(p("2.0.0")));
+    assert_eq!(Range::parse("<3.0, <2.5").unwrap().max, Some(p("2.5.0")));
+    assert_eq!(Range::parse("<4.3.0 >=4.2.0 ~4.2.1").unwrap(), Range::parse("~4.2.1 >=4.2.0 <4.3.0").unwrap());
+    assert!(!Range::parse("^4 <4.2.0").unwrap().contains(&p("4.3.1")));
+  }
+
+  #[test]
+  fn version_requests() {
+    use crate::parsing::request::{Keyword, VersionRequest};
+    let request = |r: &str| VersionRequest::parse(r).unwrap();
+    assert_eq!(request("4.2.1"), VersionRequest::Exact(p("4.2.1")));
+    assert_eq!(request("4.2"), VersionRequest::Range(Range::parse("~4.2").unwrap()));
+    assert_eq!(request("Release"), VersionRequest::Keyword(Keyword::Latest));
+    assert!(VersionRequest::parse("newest").is_err());
+    let pool = [p("4.2.0"), p("4.2.3"), p("4.3.0"), p("4.4.0-rc.1")];
+    let resolve = |r: &str| request(r).resolve(&pool).map(|v| v.to_string());
+    assert_eq!(resolve("latest").as_deref(), Some("4.3.0"));
+    assert_eq!(resolve("stable").as_deref(), Some("4.2.3"));
+    assert_eq!(resolve("lts").as_deref(), Some("4.2.3"));
+    assert_eq!(resolve("system"), None);
+    assert_eq!(resolve("4.2.1"), None);
+    assert_eq!(request("latest").to_range(&pool), Some(Range::exactly(p("4.3.0"))));
+  }
+
+  #[test]
+  fn range_ast() {
+    use crate::parsing::ast::{RangeAst, TokenKind};
+    use crate::parsing::version_parser::Op;
+    let source = " >= 4.2.0-RC.1 ;<4.3  ";
+    let ast = RangeAst::parse(source);
+    assert_eq!(ast.source(), source);
+    let kinds: Vec<&TokenKind> = ast.tokens.iter().map(|t| &t.kind).collect();
+    assert_eq!(kinds, vec![&TokenKind::Space, &TokenKind::Op(Op::Ge), &TokenKind::Space, &TokenKind::Version(p("4.2.0-RC.1")), &TokenKind::Space, &TokenKind::Separator, &TokenKind::Op(Op::Lt), &TokenKind::Version(p("4.3")), &TokenKind::Space]);
+    assert_eq!(ast.tokens[3].span, 4..14);
+    assert_eq!(ast.range, Range::parse(source));
+    assert_eq!(ast.error_span(), None);
+    assert_eq!(RangeAst::parse(">=4.2, <4.x.0").error_span(), Some(8..13));
+    assert_eq!(RangeAst::parse(">=4.2 <=<").point_at_error().unwrap(), ">=4.2 <=<\n      ^^^");
+  }
+
+  #[test]
+  fn range_conjunctions() {
+    use crate::parsing::ast::RangeAst;
+    use crate::parsing::version_parser::Dialect;
+    let expected = Range::parse(">=4.1, <5").unwrap();
+    for source in [">=4.1 && <5", ">=4.1&&<5", ">=4.1 and <5", ">= 4.1 AND < 5", ">=4.1-rc and <5"] {
+      assert_eq!(Range::parse(source).map(|r| r.max), Ok(expected.max.clone()), "{}", source);
+    }
+    assert_eq!(Range::parse(">=4.1 and <5").unwrap(), expected);
+    assert!(Range::parse(">=4.1and <5").is_err());
+    assert!(Range::parse(">=4.1 & <5").is_err());
+    assert_eq!(Range::parse_in(">=4.1 && <5", Dialect::STRICT), Err(ParseError::InvalidRange));
+    assert_eq!(Range::parse_in(">=4.1 and <5", Dialect { and: true, ..Dialect::STRICT }), Ok(expected));
+    assert_eq!(RangeAst::parse(">=4.1 and <5").error_span(), None);
+    assert_eq!(RangeAst::parse_in(">=4.1 && <5", Dialect::STRICT).error_span(), Some(6..8));
+  }
+
+  #[test]
+  fn range_spacing() {
+    let expected = r(">=1.2, <2");
+    for source in [">= 1.2 , <2", "  >=  1.2  ,  <  2  ", ">=1.2\t<2", "\t>= 1.2 ;\t< 2 ", ">=1.2 <2", ">=1.2,<2"] {
+      assert_eq!(Range::parse(source), Ok(expected.clone()), "{:?}", source);
+    }
+    let rejected = [
+      ("1.2 3.4", ParseError::MissingOperator),
+      ("1.2.3 1.2.4", ParseError::MissingOperator),
+      (">=1.2 <2 3", ParseError::MissingOperator),
+      ("1.2 <2", ParseError::MissingOperator),
+      ("> = 1.2", ParseError::InvalidRange),
+      ("=>1.2", ParseError::InvalidRange),
+      (">=", ParseError::InvalidRange),
+      (">=1.2 <", ParseError::InvalidRange),
+      (">=1.2,, <", ParseError::InvalidRange),
+      ("1.2 || 1.4", ParseError::InvalidRange),
+      ("4.x", ParseError::InvalidRange),
+      ("~>1.2", ParseError::InvalidRange),
+    ];
+    for (source, err) in rejected {
+      assert_eq!(Range::parse(source), Err(err), "{:?}", source);
+    }
+    assert!(Range::parse("1.2, 3.4").is_ok());
+    assert!(Range::parse(">=1.2, <2, 1.5.1").is_ok());
+    use crate::parsing::ast::RangeAst;
+    assert_eq!(RangeAst::parse("1.2 3.4").error_span(), Some(0..3));
+    assert_eq!(RangeAst::parse(">=1.2 <2 3").point_at_error().unwrap(), ">=1.2 <2 3\n         ^");
+  }
+
+  #[test]
+  fn parse_limits() {
+    use crate::parsing::version_parser::{Dialect, Limits};
+    assert_eq!(Version::parse("4.2.1-".to_owned() + &"a".repeat(200)), Err(ParseError::TooLong));
+    assert_eq!(Version::parse(format!("4.2.1-{}", vec!["1"; 100].join("."))), Err(ParseError::TooLong));
+    assert_eq!(Range::parse(&">=1.0 ".repeat(300)), Err(ParseError::TooLong));
+    assert_eq!(Range::parse(&vec![">=1.0"; 65].join(",")), Err(ParseError::TooLong));
+    assert!(Range::parse(&vec![">=1.0"; 64].join(",")).is_ok());
+    assert!(Version::parse("4.4.0-nightly.20240315+0123456789abcdef0123456789abcdef01234567").is_ok());
+    let tight = Limits { max_input: 8, ..Limits::default() };
+    assert_eq!(Range::parse_limited(">=4.2, <5", Dialect::default(), tight), Err(ParseError::TooLong));
+    assert_eq!(Version::parse_limited("4.2.1", tight), Ok(p("4.2.1")));
+  }
+
+  #[test]
+  fn list_filters() {
+    use crate::actions::ListFilter;
+    let pool = ["4.4.0-nightly.20240315", "4.4.0-rc.1", "4.3.1", "4.2.3", "4.2.0"].map(p);
+    let kept = |filter: ListFilter| pool.iter().filter(|v| filter.keeps(v)).map(|v| v.to_string()).collect::<Vec<_>>();
+    assert_eq!(kept(ListFilter::new(None, false, false)).len(), 5);
+    assert_eq!(kept(ListFilter::new(Some(">=4.3"), false, false)), vec!["4.3.1"]);
+    assert_eq!(kept(ListFilter::new(Some(">=4.4.0-a"), false, false)), vec!["4.4.0-nightly.20240315", "4.4.0-rc.1"]);
+    assert_eq!(kept(ListFilter::new(Some(">=4.3"), false, true)), vec!["4.4.0-nightly.20240315", "4.4.0-rc.1"]);
+    assert_eq!(kept(ListFilter::new(None, true, false)), vec!["4.3.1", "4.2.3", "4.2.0"]);
+    assert_eq!(kept(ListFilter::new(Some("4.2"), false, false)), vec!["4.2.3", "4.2.0"]);
+  }
+
+  #[test]
+  fn latest_per_series() {
+    use crate::install::scan::{Granularity, VersionPool};
+    let pool = VersionPool::new(["4.2.3", "3.6.3", "4.2.1", "4.3.0", "4.1.0", "3.6.0"].map(p));
+    let latest = |g| pool.latest_per(g).into_iter().map(|v| v.to_string()).collect::<Vec<_>>();
+    assert_eq!(latest(Granularity::Minor), vec!["3.6.3", "4.1.0", "4.2.3", "4.3.0"]);
+    assert_eq!(latest(Granularity::Major), vec!["3.6.3", "4.3.0"]);
+    assert!(VersionPool::default().latest_per(Granularity::Minor).is_empty());
+  }
+
+  #[test]
+  fn support_window() {
+    use crate::index::{supported_range, ArtifactMeta, Support};
+    let meta = |v: &str, eol: Option<&str>, support: Option<Support>| ArtifactMeta {
+      version: p(v), url: String::new(), source_url: None, build_script: None, sha256: None, source_sha256: None, source_archive: None, platform: None,
+      eol: eol.map(str::to_owned), released: None, yanked: false, support, deltas: vec![], source_deltas: vec![], notes_url: None, notes: None, relocate: None, env: Default::default(),
+    };
+    let entries = vec![
+      meta("4.1.3", Some("2023-04-01"), None),
+      meta("4.2.3", Some("2025-04-01"), Some(Support::Maintenance)),
+      meta("4.3.0", None, Some(Support::Eol)),
+      meta("4.3.2", None, Some(Support::Current)),
+      meta("4.4.0-nightly.1", None, None),
+    ];
+    assert!(entries[0].is_eol_on("2024-01-01"));
+    assert!(!entries[1].is_eol_on("2024-01-01"));
+    assert!(entries[1].is_eol_on("2025-04-01"));
+    assert!(entries[2].is_eol_on("2000-01-01"));
+    assert_eq!(supported_range(&entries, "2024-01-01"), Some(Range::parse(">=4.2.3, !=4.3.0").unwrap()));
+    assert_eq!(supported_range(&entries, "2025-06-01"), Some(Range::parse(">=4.3.2").unwrap()));
+    assert_eq!(supported_range(&entries[..3], "2025-06-01"), None);
+  }
+
+  #[test]
+  fn index_schemas() {
+    use crate::index::{parse_index, IndexError};
+    let versions = |text: &str| parse_index(text).unwrap().into_iter().map(|m| m.version.to_string()).collect::<Vec<_>>();
+    assert_eq!(versions(r#"[{"version": "4.3.1", "url": "u"}]"#), vec!["4.3.1"]);
+    assert_eq!(versions(r#"{"schema_version": 1, "versions": [{"version": "4.3.1", "url": "u", "checksum_kind": "new"}]}"#), vec!["4.3.1"]);
+    assert_eq!(versions("schema_version = 1\nrequires_rvm = \"0.0.1\"\n[[versions]]\nversion = \"4.2.3\"\nurl = \"u\"\n"), vec!["4.2.3"]);
+    let future = parse_index(r#"{"schema_version": 7, "requires_rvm": "2.0.0", "artifacts": {}}"#);
+    assert!(matches!(future, Err(IndexError::RequiresRvm(v, _)) if v == "2.0.0"));
+    assert!(matches!(parse_index(r#"{"schema_version": 7, "artifacts": {}}"#), Err(IndexError::UnknownSchema(7, _, 1))));
+    assert!(matches!(parse_index(r#"{"versions": []}"#), Err(IndexError::Json(_))));
+  }
+
+  #[test]
+  fn artifact_names() {
+    use crate::index::{artifact_name, parse_index, IndexDocument};
+    let name = |n: &str| artifact_name(n).map(|a| (a.version.to_string(), a.platform.map(|p| p.to_string()), a.source));
+    let some = |v: &str, platform: Option<&str>, source| Some((v.to_owned(), platform.map(str::to_owned), source));
+    assert_eq!(name("R-4.3.1-win.exe"), some("4.3.1", Some("x86_64-windows"), false));
+    assert_eq!(name("R-4.3.1-arm64.pkg"), some("4.3.1", Some("aarch64-macos"), false));
+    assert_eq!(name("R-4.0.0.pkg"), some("4.0.0", Some("x86_64-macos"), false));
+    assert_eq!(name("R-4.3.1.tar.gz"), some("4.3.1", None, true));
+    assert_eq!(name("v4.3.1-x86_64-linux-gnu.tar.xz"), some("4.3.1", Some("x86_64-linux-gnu"), false));
+    assert_eq!(name("R-4.4.0-nightly.20240315-aarch64-linux-musl.tgz"), some("4.4.0-nightly.20240315", Some("aarch64-linux-musl"), false));
+    assert_eq!(name("R-4.4.0-rc.1.tar.gz"), some("4.4.0-rc.1", None, true));
+    assert_eq!(name("README.md"), None);
+    assert_eq!(name("R-latest.tar.gz"), None);
+    let meta = crate::index::ArtifactMeta {
+      version: p("4.3.1"), url: "u".to_owned(), source_url: None, build_script: None, sha256: Some("ab".to_owned()), source_sha256: None, source_archive: None,
+      platform: "x86_64-windows".parse().ok(), eol: None, released: None, yanked: false, support: None, deltas: vec![], source_deltas: vec![], notes_url: None, notes: None, relocate: None, env: Default::default(),
+    };
+    let document = IndexDocument::new(vec![meta.clone()]);
+    assert_eq!(parse_index(&serde_json::to_string(&document).unwrap()).unwrap(), vec![meta.clone()]);
+    assert_eq!(parse_index(&toml::to_string(&document).unwrap()).unwrap(), vec![meta]);
+  }
+
+  #[test]
+  fn registry_credentials() {
+    use crate::auth::{netrc_login, token_var, Auth, Credential};
+    let netrc = "machine example.org login x password y\nmachine R.Corp.Example\n  login ci\n  password pw\ndefault login anon\nmacdef init\n  machine z login z\n";
+    assert_eq!(netrc_login(netrc, "r.corp.example"), Some(("ci".to_owned(), Some("pw".to_owned()))));
+    assert_eq!(netrc_login(netrc, "cran.r-project.org"), Some(("anon".to_owned(), None)));
+    assert_eq!(netrc_login("machine a password p", "a"), None);
+    assert_eq!(token_var("r.corp-example.com"), "RVM_TOKEN_R_CORP_EXAMPLE_COM");
+    let credential = Credential { url: "https://r.corp.example/".to_owned(), token: Some("s3cret".to_owned()), token_env: None, username: None, password: Some("hunter2".to_owned()), password_env: None };
+    assert!(!format!("{:?}", credential).contains("s3cret") && !format!("{:?}", credential).contains("hunter2"));
+    assert_eq!(format!("{:?}", Auth::Basic("ci".to_owned(), Some("pw".to_owned()))), "Basic(ci, ***)");
+    // the token doesn't go to hosts that only start like the registry's
+    let credentials = vec![credential.clone(), Credential { url: "https://r.corp.example/team/".to_owned(), token: Some("team".to_owned()), ..credential }];
+    let auth = |url: &str| crate::auth::configured_auth(credentials.clone(), url);
+    assert_eq!(auth("https://r.corp.example/src/R-4.3.1.tar.gz"), Some(Auth::Bearer("s3cret".to_owned())));
+    assert_eq!(auth("https://r.corp.example/team/R-4.3.1.tar.gz"), Some(Auth::Bearer("team".to_owned())));
+    assert_eq!(auth("https://r.corp.example/teams/R-4.3.1.tar.gz"), Some(Auth::Bearer("s3cret".to_owned())));
+    assert_eq!(auth("https://r.corp.example.evil.test/R-4.3.1.tar.gz"), None);
+    assert_eq!(auth("https://r.corp.example@evil.test/R-4.3.1.tar.gz"), None);
+    assert_eq!(auth("http://r.corp.example/R-4.3.1.tar.gz"), None);
+  }
+
+  #[test]
+  fn retry_after_header() {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    use crate::throttle::retry_after;
+    use crate::utils::parse_http_date;
+    assert_eq!(parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT"), Some(UNIX_EPOCH + Duration::from_secs(784111777)));
+    assert_eq!(parse_http_date("Thu, 29 Feb 2024 00:00:00 GMT"), Some(UNIX_EPOCH + Duration::from_secs(1709164800)));
+    assert_eq!(parse_http_date("Sunday, 06-Nov-94 08:49:37 GMT"), None);
+    let now = UNIX_EPOCH + Duration::from_secs(784111770);
+    assert_eq!(retry_after("120", now), Some(Duration::from_secs(120)));
+    assert_eq!(retry_after("Sun, 06 Nov 1994 08:49:37 GMT", now), Some(Duration::from_secs(7)));
+    assert_eq!(retry_after("Sun, 06 Nov 1994 08:49:37 GMT", SystemTime::now()), Some(Duration::ZERO));
+    assert_eq!(retry_after("soon", now), None);
+  }
+
+  #[test]
+  fn delta_patches() {
+    use crate::install::delta::{apply, diff};
+    let dir = std::env::temp_dir().join(format!("rvm-delta-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    // something that doesn't repeat, with a change in the middle, one at the end and one the length of a block
+    let base: Vec<u8> = (0..200_000u32).map(|i| (i.wrapping_mul(2654435761) >> 13) as u8).collect();
+    let mut target = base.clone();
+    target[100_000..100_010].copy_from_slice(b"0123456789");
+    target.splice(150_000..150_000, vec![7; 64]);
+    target.extend(b"appended");
+    for (base, target) in [(&base, &target), (&base, &vec![]), (&vec![], &target), (&target, &base)] {
+      let patch = diff(base, target).unwrap();
+      std::fs::write(dir.join("base"), base).unwrap();
+      std::fs::write(dir.join("patch"), &patch).unwrap();
+      apply(&dir.join("base"), &dir.join("patch"), &dir.join("out")).unwrap();
+      assert_eq!(&std::fs::read(dir.join("out")).unwrap(), target);
+    }
+    assert!(diff(&base, &target).unwrap().len() < 2_000);
+    std::fs::write(dir.join("patch"), b"not a patch").unwrap();
+    assert!(apply(&dir.join("base"), &dir.join("patch"), &dir.join("out")).is_err());
+    std::fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn archive_unpacking() {
+    use crate::install::extract::{contained, unpack, ArchiveFormat};
+    use crate::install::NoReporter;
+    assert_eq!(ArchiveFormat::from_name("R-4.3.1.tar.XZ?raw=1"), Some(ArchiveFormat::TarXz));
+    assert_eq!(ArchiveFormat::from_name("R-4.3.1.tgz"), Some(ArchiveFormat::TarGz));
+    assert_eq!(ArchiveFormat::from_name("R-4.3.1.exe"), None);
+    assert_eq!(contained(std::path::Path::new("a/./b/../c")), Some(std::path::PathBuf::from("a/c")));
+    assert_eq!(contained(std::path::Path::new("a/../../c")), None);
+    assert_eq!(contained(std::path::Path::new("/etc/passwd")), None);
+    let root = tempfile::tempdir().unwrap();
+    let dir = root.path();
+    let tarball = |name: &str, link: &str| {
+      let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(vec![], flate2::Compression::fast()));
+      let mut header = tar::Header::new_gnu();
+      header.set_size(3);
+      header.set_mode(0o755);
+      builder.append_data(&mut header, "R-4.3.1/bin/R", &b"#!\n"[..]).unwrap();
+      let mut header = tar::Header::new_gnu();
+      header.set_entry_type(tar::EntryType::Symlink);
+      header.set_size(0);
+      builder.append_link(&mut header, "R-4.3.1/lib/R", link).unwrap();
+      std::fs::write(dir.join(name), builder.into_inner().unwrap().finish().unwrap()).unwrap();
+      dir.join(name)
+    };
+    // a zip of stored entries, each (name, unix mode, contents)
+    let zip = |name: &str, entries: &[(&str, u32, &[u8])]| {
+      let (mut out, mut directory) = (vec![], vec![]);
+      for (path, mode, data) in entries {
+        let mut crc = flate2::Crc::new();
+        crc.update(data);
+        let fields = |out: &mut Vec<u8>| {
+          out.extend(0u16.to_le_bytes()); // flags
+          out.extend(0u16.to_le_bytes()); // stored
+          out.extend(0u32.to_le_bytes()); // time and date
+          out.extend(crc.sum().to_le_bytes());
+          out.extend((data.len() as u32).to_le_bytes());
+          out.extend((data.len() as u32).to_le_bytes());
+          out.extend((path.len() as u16).to_le_bytes());
+          out.extend(0u16.to_le_bytes()); // extra
+        };
+        directory.extend(0x02014b50u32.to_le_bytes());
+        directory.extend((3u16 << 8 | 20).to_le_bytes());
+        directory.extend(20u16.to_le_bytes());
+        fields(&mut directory);
+        directory.extend([0; 6]); // comment, disk and internal attributes
+        directory.extend((mode << 16).to_le_bytes());
+        directory.extend((out.len() as u32).to_le_bytes());
+        directory.extend(path.as_bytes());
+        out.extend(0x04034b50u32.to_le_bytes());
+        out.extend(20u16.to_le_bytes());
+        fields(&mut out);
+        out.extend(path.as_bytes());
+        out.extend(*data);
+      }
+      let at = out.len() as u32;
+      out.extend(&directory);
+      out.extend(0x06054b50u32.to_le_bytes());
+      out.extend([0; 4]);
+      out.extend((entries.len() as u16).to_le_bytes());
+      out.extend((entries.len() as u16).to_le_bytes());
+      out.extend((directory.len() as u32).to_le_bytes());
+      out.extend(at.to_le_bytes());
+      out.extend([0; 2]);
+      std::fs::write(dir.join(name), out).unwrap();
+      dir.join(name)
+    };
+    let v = p("4.3.1");
+    let good_tar = tarball("good.tar.gz", "../bin");
+    let good_zip = zip("good.zip", &[("R-4.3.1/bin/R", 0o100755, b"#!\n"), ("R-4.3.1/lib/R", 0o120777, b"../bin")]);
+    for (archive, format) in [(&good_tar, ArchiveFormat::TarGz), (&good_zip, ArchiveFormat::Zip)] {
+      let dest = dir.join(format.extension());
+      unpack(&v, archive, format, &dest, &NoReporter).unwrap();
+      assert_eq!(std::fs::read(dest.join("R-4.3.1/lib/R/R")).unwrap(), b"#!\n");
+      assert_eq!(std::fs::read_link(dest.join("R-4.3.1/lib/R")).unwrap(), std::path::PathBuf::from("../bin"));
+      #[cfg(unix)] {
+        use std::os::unix::fs::PermissionsExt;
+        assert_eq!(std::fs::metadata(dest.join("R-4.3.1/bin/R")).unwrap().permissions().mode() & 0o777, 0o755);
+      }
+    }
+    let bad_tar = tarball("bad.tar.gz", "../../../outside");
+    let slip_zip = zip("slip.zip", &[("R-4.3.1/ok", 0o100644, b""), ("R-4.3.1/../../evil", 0o100644, b"x")]);
+    let link_zip = zip("link.zip", &[("R-4.3.1/lib", 0o120777, b"/etc")]);
+    for (archive, format) in [(&bad_tar, ArchiveFormat::TarGz), (&slip_zip, ArchiveFormat::Zip), (&link_zip, ArchiveFormat::Zip)] {
+      let dest = dir.join("bad");
+      assert!(matches!(unpack(&v, archive, format, &dest, &NoReporter), Err(crate::install::InstallError::Unpack(..))));
+    }
+    assert!(!dir.join("evil").exists() && !dir.join("bad/R-4.3.1/ok").exists());
+    // each link is fine on its own, together they lead out of dest
+    let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(vec![], flate2::Compression::fast()));
+    for (path, link) in [("a", "."), ("a/b", ".."), ("b/c", "..")] {
+      let mut header = tar::Header::new_gnu();
+      header.set_entry_type(tar::EntryType::Symlink);
+      header.set_size(0);
+      builder.append_link(&mut header, path, link).unwrap();
+    }
+    let mut header = tar::Header::new_gnu();
+    header.set_size(1);
+    builder.append_data(&mut header, "b/c/chained-evil", &b"x"[..]).unwrap();
+    std::fs::write(dir.join("chained.tar.gz"), builder.into_inner().unwrap().finish().unwrap()).unwrap();
+    let chained_zip = zip("chained.zip", &[("a", 0o120777, b"."), ("a/b", 0o120777, b"..")]);
+    for (archive, format) in [(&dir.join("chained.tar.gz"), ArchiveFormat::TarGz), (&chained_zip, ArchiveFormat::Zip)] {
+      let dest = dir.join("chained").join(format.extension());
+      assert!(matches!(unpack(&v, archive, format, &dest, &NoReporter), Err(crate::install::InstallError::Unpack(..))));
+      assert!(std::fs::symlink_metadata(dest.join("b")).is_err());
+    }
+    assert!(!dir.join("chained-evil").exists());
+  }
+
+  #[test]
+  fn relocation_fixups() {
+    use crate::install::relocate::{glob_match, Relocation};
+    assert!(glob_match("bin/*", "bin/R"));
+    assert!(!glob_match("bin/*", "bin/exec/R"));
+    assert!(glob_match("lib/**/*.pc", "lib/pkgconfig/libR.pc"));
+    assert!(glob_match("lib/**/*.pc", "lib/libR.pc"));
+    assert!(glob_match("lib/R/etc/Ma?econf", "lib/R/etc/Makeconf"));
+    let relocation: Relocation = serde_yaml::from_str("prefix: /opt/R/{version}/").unwrap();
+    assert_eq!(relocation.prefix_for(&p("4.3.1")), "/opt/R/4.3.1");
+    let dir = std::env::temp_dir().join(format!("rvm-relocate-{}", std::process::id()));
+    let files = [
+      ("bin/Rscript", "#!/opt/R/4.3.1/bin/R --vanilla\nx\n", "#!/new/R/bin/R --vanilla\nx\n"),
+      ("bin/R", "R_HOME_DIR=/opt/R/4.3.1/lib/R\n", "R_HOME_DIR=/new/R/lib/R\n"),
+      ("bin/other", "#!/opt/R/4.3.10/bin/R\n", "#!/opt/R/4.3.10/bin/R\n"),
+      ("lib/pkgconfig/libR.pc", "prefix=/opt/R/4.3.1\nlibdir=/opt/R/4.3.1/lib\n", "prefix=/new/R\nlibdir=${prefix}/lib\n"),
+      ("lib/R/etc/Makeconf", "LIBR = -L/opt/R/4.3.1/lib\0", "LIBR = -L/opt/R/4.3.1/lib\0"),
+      ("share/README", "see /opt/R/4.3.1\n", "see /opt/R/4.3.1\n"),
+    ];
+    for (file, before, _) in files {
+      std::fs::create_dir_all(dir.join(file).parent().unwrap()).unwrap();
+      std::fs::write(dir.join(file), before).unwrap();
+    }
+    let changed = relocation.run(&p("4.3.1"), &dir, std::path::Path::new("/new/R")).unwrap();
+    for (file, _, after) in files {
+      assert_eq!(std::fs::read_to_string(dir.join(file)).unwrap(), after, "{}", file);
+    }
+    let changed: Vec<String> = changed.iter().map(|c| c.display().to_string()).collect();
+    assert_eq!(changed, ["bin/R", "bin/Rscript", "lib/pkgconfig/libR.pc"]);
+    std::fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn reported_versions() {
+    use crate::actions::check::reported_version;
+    let r = "R version 4.3.1 (2023-06-16) -- \"Beagle Scouts\"\nCopyright (C) 2023 The R Foundation for Statistical Computing";
+    assert_eq!(reported_version(r), Some(p("4.3.1")));
+    assert_eq!(reported_version("Rscript (R) version 4.2.0 (2022-04-22)"), Some(p("4.2.0")));
+    assert_eq!(reported_version("R 4.4.0 built from source"), Some(p("4.4.0")));
+    assert_eq!(reported_version("R Under development (unstable) (2024-01-10 r85795)"), None);
+  }
+
+  #[test]
+  fn install_meta_formats() {
+    use crate::install::meta::InstallMeta;
+    // what versions\.meta\<version>.yaml had before installs recorded their date and size
+    let legacy: InstallMeta = serde_yaml::from_str("pinned: true\nurl: https://x/R-4.3.1.tar.gz\nsha256: ab\n").unwrap();
+    assert!(legacy.pinned && legacy.installed_at.is_none() && legacy.size.is_none());
+    assert_eq!(legacy.size_of(&p("0.0.1")), 0);
+    let meta = InstallMeta { installed_at: Some(1_700_000_000), size: Some(123), ..legacy };
+    let json = serde_json::to_string(&meta).unwrap();
+    assert_eq!(serde_json::from_str::<InstallMeta>(&json).unwrap(), meta);
+    assert_eq!(meta.size_of(&p("0.0.1")), 123);
+  }
+
+  #[test]
+  fn observers_notified() {
+    use std::sync::{Arc, Mutex};
+    use crate::observer::{notify, register, Observer};
+    // only switches, everything else is the default and does nothing
+    struct Switches(Arc<Mutex<Vec<String>>>);
+    impl Observer for Switches {
+      fn on_switch(&self, from: Option<&Version>, to: &Version) {
+        self.0.lock().unwrap().push(format!("{:?} -> {}", from.map(|v| v.to_string()), to));
+      }
+    }
+    let seen = Arc::new(Mutex::new(vec![]));
+    register(Switches(seen.clone()));
+    notify(|o| o.on_download_start(&p("0.0.42"), "u"));
+    notify(|o| o.on_switch(Some(&p("0.0.41")), &p("0.0.42")));
+    notify(|o| o.on_switch(None, &p("0.0.43")));
+    // other tests running at the same time may switch too
+    let seen: Vec<String> = seen.lock().unwrap().iter().filter(|s| s.contains("0.0.4")).cloned().collect();
+    assert_eq!(seen, ["Some(\"0.0.41\") -> 0.0.42", "None -> 0.0.43"]);
+  }
+
+  #[test]
+  fn parse_os_and_bytes() {
+    assert_eq!(Version::parse_os(std::ffi::OsStr::new("4.2.1")), Ok(p("4.2.1")));
+    assert_eq!(Version::parse_bytes(b"4.3.0-rc.1"), Ok(p("4.3.0-rc.1")));
+    assert_eq!(Version::parse_bytes(b"4.\xff.0"), Err(ParseError::InvalidUtf8));
+    assert_eq!(Version::parse_bytes(b".staging"), Err(ParseError::InvalidVersion));
+    #[cfg(unix)] {
+      use std::os::unix::ffi::OsStrExt;
+      assert_eq!(Version::parse_os(std::ffi::OsStr::from_bytes(b"4.\xff.0")), Err(ParseError::InvalidUtf8));
+    }
+  }
+
+  #[test]
+  fn installed_scan() {
+    use crate::install::scan::{scan, SkipReason};
+    let dir = std::env::temp_dir().join(format!("rvm-scan-{}", std::process::id()));
+    for sub in ["4.2.1/bin", "v4.3.0/bin", "4.1.0", "notes/x", ".staging/4.4.0"] {
+      std::fs::create_dir_all(dir.join(sub)).unwrap();
+    }
+    std::fs::write(dir.join("4.2.1/bin/R"), "").unwrap();
+    let found = scan(&dir);
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(found.pool.versions().collect::<Vec<_>>(), [&p("4.2.1")]);
+    let skipped: Vec<(String, SkipReason)> = found.skipped.into_iter()
+      .map(|s| (s.path.file_name().unwrap().to_string_lossy().into_owned(), s.reason))
+      .collect();
+    assert_eq!(skipped, vec![
+      ("4.1.0".to_owned(), SkipReason::Empty(p("4.1.0"))),
+      ("notes".to_owned(), SkipReason::NotAVersion),
+      ("v4.3.0".to_owned(), SkipReason::Misnamed(p("4.3.0"))),
+    ]);
+  }
+
+  #[test]
+  fn range_excluded_by() {
+    let range = Range::parse(">=4.1, <5, !=4.2.3").unwrap();
+    let by = |v: &str| range.excluded_by(&p(v)).map(|c| c.to_string());
+    assert_eq!(by("4.0.5"), Some(">=4.1.0".to_owned()));
+    assert_eq!(by("5.0.0"), Some("<5.0.0".to_owned()));
+    assert_eq!(by("4.2.3"), Some("!=4.2.3".to_owned()));
+    assert_eq!(by("4.2.2"), None);
+    assert_eq!(Range::parse("=4.2.3").unwrap().excluded_by(&p("4.2.2")).map(|c| c.to_string()), Some("=4.2.3".to_owned()));
+  }
+
+  #[test]
+  fn version_keys() {
+    use std::collections::{BTreeSet, HashSet};
+    use crate::parsing::version_parser::{PrecedenceKey, RangeKey, StrictKey};
+    let versions = || ["4.3.0", "4.3.0+b1", "4.3.0+b2", "4.3.0-rc.1", "4.3.0-rc.01", "4.2.1"].map(p);
+    assert_eq!(versions().into_iter().map(StrictKey).collect::<HashSet<_>>().len(), 5);
+    assert_eq!(versions().into_iter().map(PrecedenceKey).collect::<HashSet<_>>().len(), 3);
+    assert_eq!(versions().into_iter().map(RangeKey).collect::<HashSet<_>>().len(), 2);
+    let ordered: Vec<PrecedenceKey> = versions().into_iter().map(PrecedenceKey).collect::<BTreeSet<_>>().into_iter().collect();
+    assert_eq!(ordered, ["4.2.1", "4.3.0-rc.1", "4.3.0"].map(|v| PrecedenceKey(p(v))));
+    assert_eq!(RangeKey(p("4.3.0-rc.1")), RangeKey(p("4.3.0+b1")));
+  }
+
+  #[cfg(feature = "intern")]
+  #[test]
+  fn version_interner() {
+    use crate::parsing::intern::VersionInterner;
+    let versions = ["4.4.0-nightly.20240315", "4.3.0-nightly.20240315", "4.3.0+win", "4.2.1+win", "4.2.1"].map(p);
+    let mut interner = VersionInterner::new();
+    let interned = interner.intern_all(&versions);
+    assert_eq!(interner.len(), 2);
+    for (version, interned) in versions.iter().zip(&interned) {
+      assert_eq!(&interned.to_version(), version);
+      assert_eq!(interned.to_string(), version.to_string());
+    }
+    let mut sorted = interned.clone();
+    sorted.sort();
+    assert_eq!(sorted.iter().map(|v| v.to_version()).collect::<Vec<_>>(), { let mut v = versions.to_vec(); v.sort(); v });
+  }
+
+  #[test]
+  fn cached_sort_keys() {
+    use crate::parsing::version_parser::sort_by_cached_precedence_key;
+    let texts = ["4.3.0", "4.3.0-rc.2", "4.3.0-rc.10", "4.3.0-rc", "4.3.0+b", "4.2.1", "4.3.0-nightly.20240315", "4.3.0-rc.2", "4.2.1.1", "4.3.0-beta"];
+    let mut sorted: Vec<(Version, usize)> = texts.iter().enumerate().map(|(i, t)| (p(t), i)).collect();
+    let mut cached = sorted.clone();
+    sorted.sort_by(|a, b| a.0.cmp(&b.0));
+    sort_by_cached_precedence_key(&mut cached, |(v, _)| v);
+    assert_eq!(cached, sorted);
+    for a in texts.map(p) {
+      for b in texts.map(p) {
+        assert_eq!(a.sort_key().cmp(&b.sort_key()), a.cmp(&b), "{} vs {}", a, b);
+      }
+    }
+  }
+
+  #[test]
+  fn range_overflow() {
+    let max = u32::MAX;
+    let range = |r: String| Range::parse(&r);
+    assert_eq!(range(format!("~4.{}.0", max)).unwrap().max, Some(Version::new(5, 0, 0)));
+    assert_eq!(range(format!("<=4.2.{}", max)).unwrap().max, Some(Version::new(4, 3, 0)));
+    assert_eq!(range(format!(">4.{}.{}", max, max)).unwrap().min, Some(Version::new(5, 0, 0)));
+    let top = range(format!("^{}.0.0", max)).unwrap();
+    assert_eq!((top.min, top.max), (Some(Version::new(max, 0, 0)), None));
+    assert!(range(format!("<={}.{}.{}", max, max, max)).unwrap().contains(&Version::new(max, max, max)));
+    assert_eq!(range(format!(">{}.{}.{}", max, max, max)), Err(ParseError::Overflow));
+    assert_eq!(range(format!(">={}.0.0, !={}.0.0", max, max)).unwrap().minimal_version(), Some(Version::new(max, 0, 1)));
+  }
+
+  #[test]
+  fn version_formatter() {
+    use crate::parsing::version_parser::VersionFormatter;
+    let version = p("3.1.2.7-rc.1+win");
+    assert_eq!(VersionFormatter::new().format(&version), version.to_string());
+    assert_eq!(VersionFormatter::new().pad(2, 2, 3).hide_extra_version(true).hide_build(true).format(&version), "03.01.002-rc.1");
+    assert_eq!(VersionFormatter::new().pad(1, 1, 1).v_prefix(true).hide_build(true).format(&p("4.10.0")), "v4.10.0");
+    assert_eq!(crate::actions::sort::formatter(false, &[3], false, false, false).unwrap().format(&p("4.2.1")), "004.002.001");
+    assert!(crate::actions::sort::formatter(false, &[], false, false, false).is_none());
+  }
+
+  #[test]
+  fn aliases() {
+    let mut aliases = Aliases::default();
+    assert!(aliases.set("lts", "~4.2").unwrap().is_none());
+    assert_eq!(aliases.set("default", "4.3.1").unwrap(), None);
+    assert_eq!(aliases.set("default", "4.3.2").unwrap(), Some("4.3.1".to_owned()));
+    assert!(aliases.set("4.1", "4.2").is_err());
+    assert!(aliases.set("broken", ">=>1").is_err());
+    assert_eq!(aliases.expand("lts"), "~4.2");
+    assert_eq!(aliases.expand("4.1"), "4.1");
+    assert_eq!(aliases.forget_version(&Version::new(4, 3, 2)), vec!["default".to_owned()]);
+    assert_eq!(aliases.get("lts"), Some("~4.2"));
+    let toml = toml::to_string(&aliases).unwrap();
+    assert_eq!(toml::from_str::<Aliases>(&toml).unwrap(), aliases);
+  }
+
+  #[test]
+  fn durations_and_sizes() {
+    assert_eq!(parse_duration("90d"), Ok(Duration::from_secs(90 * 24 * 60 * 60)));
+    assert_eq!(parse_duration("12h"), Ok(Duration::from_secs(12 * 60 * 60)));
+    assert_eq!(parse_duration("30"), Ok(Duration::from_secs(30)));
+    assert!(parse_duration("d").is_err());
+    assert!(parse_duration("3y").is_err());
+    assert!(parse_duration("18446744073709551615w").is_err());
+    assert_eq!(format_bytes(512), "512 B");
+    assert_eq!(format_bytes(1536), "1.5 KB");
+    assert_eq!(format_bytes(3 * 1024 * 1024 * 1024), "3.0 GB");
+  }
+
+  #[test]
+  fn versions_from_git_refs() {
+    assert_eq!(version_from_ref("R-4-3-1"), Some(Version::new(4, 3, 1)));
+    assert_eq!(version_from_ref("refs/tags/v4.2.0"), Some(Version::new(4, 2, 0)));
+    assert_eq!(version_from_ref("4.1.3"), Some(Version::new(4, 1, 3)));
+    assert_eq!(version_from_ref("trunk"), None);
+    // the pre-release isn't part of the release
+    assert_eq!(version_from_ref("R-4-3-1-rc1"), Some(p("4.3.1-rc1")));
+    assert_eq!(version_from_ref("refs/tags/R-4-4-0-alpha-2"), Some(p("4.4.0-alpha.2")));
+    assert_eq!(version_from_ref("v4.3.1-rc.1"), Some(p("4.3.1-rc.1")));
+    assert_eq!(version_from_ref("R-4-3-1-1"), Some(p("4.3.1.1")));
+    assert_eq!(version_from_ref("R-rc1"), None);
+  }
+
+  #[test]
+  fn platforms() {
+    let musl: Platform = "aarch64-linux-musl".parse().unwrap();
+    assert_eq!(musl, Platform { arch: Arch::Aarch64, os: Os::Linux, libc: Some(Libc::Musl) });
+    assert_eq!("amd64-win".parse::<Platform>().unwrap().to_string(), "x86_64-windows");
+    assert!("x86_64-macos-gnu".parse::<Platform>().is_err());
+    assert!("riscv-linux".parse::<Platform>().is_err());
+    let any_linux: Platform = "aarch64-linux".parse().unwrap();
+    assert!(musl.runs(&any_linux));
+    assert!(!musl.runs(&"aarch64-linux-gnu".parse().unwrap()));
+    assert!(!musl.runs(&"x86_64-linux-musl".parse().unwrap()));
+  }
+
+  #[test]
+  fn shell_syntax() {
+    assert_eq!(Shell::Bash.set_var("RVM_SHELL_VERSION", "it's"), r"export RVM_SHELL_VERSION='it'\''s'");
+    assert_eq!(Shell::Fish.set_var("A", "4.2"), "set -gx A '4.2'");
+    assert_eq!(Shell::Powershell.set_var("A", "it's"), "$env:A = 'it''s'");
+    assert_eq!(Shell::Zsh.unset_var("A"), "unset A");
+    assert_eq!(Shell::Fish.set_vars(&[("A", "1"), ("B", "2")]), "set -gx A '1'\nset -gx B '2'");
+  }
+
+  #[test]
+  fn asdf_tool_versions() {
+    assert_eq!(r_from_tool_versions("nodejs 18.17.0\nR 4.2.1 4.1.0 # fallback\n"), Some("4.2.1".to_owned()));
+    assert_eq!(r_from_tool_versions("# R 4.0.0\npython 3.11.4"), None);
+  }
+
+  #[test]
+  fn project_lockfile() {
+    let lock = ProjectLock { format: 1, range: Range::parse("~4.1.0").unwrap(), version: Version::parse("4.1.3").unwrap(), url: None, sha256: Some("ab12".to_owned()) };
+    let text = toml::to_string(&lock).unwrap();
+    assert_eq!(text, "format = 1\nrange = \"~4.1.0\"\nversion = \"4.1.3\"\nsha256 = \"ab12\"\n");
+    assert_eq!(toml::from_str::<ProjectLock>(&text).unwrap(), lock);
+  }
+
+  #[test]
+  fn config_layers() {
+    let mut machine: serde_yaml::Value = serde_yaml::from_str("backend: source\nbuild:\n  jobs: 4\nmirrors: [{from: a, to: b}]\n").unwrap();
+    let user: serde_yaml::Value = serde_yaml::from_str("build:\n  jobs: 2\nmirrors: []\n").unwrap();
+    crate::config::overlay(&mut machine, user);
+    crate::config::overlay(&mut machine, serde_yaml::Value::Null);
+    let merged: crate::config::Config = serde_yaml::from_value(machine).unwrap();
+    assert_eq!(merged.backend, crate::config::Backend::Source);
+    assert_eq!(merged.build.jobs, 2);
+    assert_eq!(merged.build.steps, crate::config::BuildRecipe::default().steps);
+    assert!(merged.mirrors.is_empty());
+  }
+
+  #[test]
+  fn layout_overrides() {
+    use std::ffi::OsString;
+    use crate::layout::{Dirs, Layout};
+    let vars = |pairs: &'static [(&'static str, &'static str)]| move |name: &str| pairs.iter().find(|(k, _)| *k == name).map(|(_, v)| OsString::from(v));
+    let xdg = Layout::from(&vars(&[("HOME", "/nonexistent"), ("XDG_DATA_HOME", "/xdg/data"), ("XDG_CACHE_HOME", "relative")]), &Dirs::default());
+    assert_eq!(xdg.config, std::path::Path::new("/nonexistent/.config/rvm"));
+    assert_eq!(xdg.data, std::path::Path::new("/xdg/data/rvm"));
+    assert_eq!(xdg.cache, std::path::Path::new("/nonexistent/.cache/rvm"));
+    let dirs = Dirs { data: Some("/srv/rvm".into()), cache: None, state: Some("/var/lib/rvm".into()), ..Dirs::default() };
+    let overridden = Layout::from(&vars(&[("RVM_HOME", "/home"), ("RVM_STATE_DIR", "/run/rvm")]), &dirs);
+    assert_eq!(overridden, Layout { config: "/home".into(), data: "/srv/rvm".into(), cache: "/home/cache".into(), state: "/run/rvm".into() });
+  }
+
+  #[test]
+  fn project_tools() {
+    let env: crate::parsing::yaml_ser::Env = serde_yaml::from_str("rversion: ~4.1.0\ndependencies: {}\ntools:\n  quarto: ^1.4\n").unwrap();
+    assert_eq!(env.tools["quarto"], Range::parse("^1.4.0").unwrap());
+    let env: crate::parsing::yaml_ser::Env = serde_yaml::from_str("rversion: ~4.1.0\ndependencies: {}\n").unwrap();
+    assert!(env.tools.is_empty());
+  }
+
+  #[test]
+  fn global_flags_from_env() {
+    use std::collections::HashMap;
+    use std::ffi::OsString;
+    use crate::args::Cli;
+    use crate::theme::ColorChoice;
+    let vars: HashMap<&str, &str> = [("RVM_PORCELAIN", "1"), ("RVM_VERBOSE", "2"), ("RVM_COLOR", "always"), ("RVM_DRY_RUN", "0")].into();
+    let lookup = |name: &str| vars.get(name).map(OsString::from);
+    let cli = Cli::try_parse_with_env(["rvm", "list"], lookup).unwrap();
+    assert!(cli.porcelain && !cli.dry_run);
+    assert_eq!(cli.verbose, 2);
+    assert_eq!(cli.color, Some(ColorChoice::Always));
+    let cli = Cli::try_parse_with_env(["rvm", "list", "-v", "--color", "never", "--dry-run"], lookup).unwrap();
+    assert_eq!(cli.verbose, 1);
+    assert_eq!(cli.color, Some(ColorChoice::Never));
+    assert!(cli.dry_run);
+    let cli = Cli::try_parse_with_env(["rvm", "list"], |_| None).unwrap();
+    assert!(!cli.porcelain && cli.verbose == 0 && cli.color.is_none());
+  }
+
+  #[test]
+  fn exit_codes() {
+    use crate::exit::{fail, run, Classify, Exit, OrExit};
+    use crate::install::InstallError;
+    use crate::resolve::ResolveError;
+    assert_eq!(run(false, || {}), 0);
+    assert_eq!(run(false, || fail(Exit::NoMatch, "No installed version matches 3.0")), 3);
+    assert_eq!(run(false, || { Version::parse("four").or_exit("Failed to parse version"); }), 2);
+    assert_eq!(run(false, || panic!("not a classified failure")), 1);
+    assert_eq!(ResolveError::NothingInstalled.exit(), Exit::NoMatch);
+    assert_eq!(ResolveError::Parse(ParseError::InvalidRange).exit(), Exit::Usage);
+    assert_eq!(InstallError::Offline("https://cran.r-project.org".to_owned()).exit(), Exit::Network);
+    assert_eq!(InstallError::Checksum("R.exe".to_owned(), "a".to_owned(), "b".to_owned()).exit(), Exit::Verification);
+    use clap::CommandFactory;
+    let help = crate::args::Cli::command().render_help().to_string();
+    for exit in [Exit::Failure, Exit::Usage, Exit::NoMatch, Exit::Network, Exit::Verification, Exit::Aborted, Exit::Busy, Exit::Denied] {
+      assert!(help.contains(&format!("\n  {}  ", exit.code())), "--help doesn't say what {} is", exit.code());
+    }
+  }
+
+  #[test]
+  fn fuzzy_picking() {
+    use crate::actions::pick::fuzzy_score;
+    assert_eq!(fuzzy_score("", "4.2.3"), Some(0));
+    assert!(fuzzy_score("432", "4.3.2").is_some());
+    assert_eq!(fuzzy_score("5", "4.3.2"), None);
+    assert_eq!(fuzzy_score("23", "4.3.2"), None);
+    assert!(fuzzy_score("4.3", "4.3.0").unwrap() > fuzzy_score("4.3", "4.1.3").unwrap());
+    assert!(fuzzy_score("NIGHT", "4.4.0-nightly.20240315 (nightly)").is_some());
+  }
+
+  #[test]
+  fn bundle_rebase() {
+    use std::path::{Path, PathBuf};
+    use crate::actions::bundle::env_script;
+    use crate::install::relocate::rebase;
+    let dir = std::env::temp_dir().join(format!("rvm-bundle-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("bin")).unwrap();
+    std::fs::write(dir.join("bin/R"), "#!/bin/sh\nR_HOME_DIR=/home/me/.rvm/versions/4.3.1\n").unwrap();
+    std::fs::write(dir.join("bin/Rscript"), "#!/home/me/.rvm/versions/4.3.1/bin/R\n").unwrap();
+    let changed = rebase(&p("4.3.1"), &dir, Path::new("/home/me/.rvm/versions/4.3.1"), Path::new("/opt/R/4.3.1")).unwrap();
+    assert_eq!(changed, [PathBuf::from("bin/R"), PathBuf::from("bin/Rscript")]);
+    assert_eq!(std::fs::read_to_string(dir.join("bin/R")).unwrap(), "#!/bin/sh\nR_HOME_DIR=/opt/R/4.3.1\n");
+    assert_eq!(std::fs::read_to_string(dir.join("bin/Rscript")).unwrap(), "#!/opt/R/4.3.1/bin/R\n");
+    std::fs::remove_dir_all(&dir).unwrap();
+    let script = env_script(&p("4.3.1"), Path::new("/opt/R/4.3.1"), &[PathBuf::from("/opt/R/4.3.1/bin")]);
+    assert!(script.contains("export R_HOME='/opt/R/4.3.1'\n"));
+    assert!(script.ends_with("export PATH='/opt/R/4.3.1/bin':\"$PATH\"\n"));
+  }
+
+  #[test]
+  fn ci_snippets() {
+    use crate::actions::ci_snippet::snippet;
+    use crate::args::SnippetFormat;
+    use crate::lockfile::FORMAT;
+    use crate::resolve::project_lock;
+    let dir = std::env::temp_dir().join(format!("rvm-ci-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let project = dir.join("Renv.yaml");
+    std::fs::write(&project, "rversion: ~4.3.0\ndependencies: {}\n").unwrap();
+    assert!(project_lock(&project).is_err());
+    let lock = ProjectLock { format: FORMAT, range: Range::parse("~4.3.0").unwrap(), version: p("4.3.1"), url: None, sha256: None };
+    lock.save(&ProjectLock::path_for(&project)).unwrap();
+    assert_eq!(project_lock(&project).unwrap().version, p("4.3.1"));
+    std::fs::write(&project, "rversion: ~4.4.0\ndependencies: {}\n").unwrap();
+    assert!(project_lock(&project).unwrap_err().to_string().contains("asks for"));
+    std::fs::remove_dir_all(&dir).unwrap();
+    let docker = snippet(SnippetFormat::Dockerfile, &p("4.3.1"));
+    assert!(docker.contains("ENV RVM_LOCKED=1") && docker.contains("rvm install =4.3.1") && docker.contains("COPY Renv.yaml rvm.lock ./"));
+    let github = snippet(SnippetFormat::GithubActions, &p("4.3.1"));
+    assert!(github.contains("RVM_LOCKED: \"1\"") && github.contains("hashFiles('rvm.lock')") && github.contains("${{ runner.os }}"));
+    assert!(snippet(SnippetFormat::Gitlab, &p("4.3.1")).contains("files: [rvm.lock]"));
+  }
+
+  #[test]
+  fn usage_stats() {
+    use crate::stats::{IndexCounts, Stats};
+    use crate::utils::date_of;
+    assert_eq!(IndexCounts::default().hit_rate(), None);
+    assert_eq!(IndexCounts { cached: 2, revalidated: 1, fetched: 1 }.hit_rate(), Some(0.75));
+    let stats: Stats = serde_json::from_str(r#"{"runs": {"r": {"4.2.3": 2, "4.3.1": 5}, "quarto": {"1.4.5": 2}}}"#).unwrap();
+    let used: Vec<(&str, String, u64)> = stats.most_used().into_iter().map(|(t, v, c)| (t, v.to_string(), c)).collect();
+    assert_eq!(used, [("r", "4.3.1".to_owned(), 5), ("r", "4.2.3".to_owned(), 2), ("quarto", "1.4.5".to_owned(), 2)]);
+    assert_eq!(date_of(1_700_000_000), "2023-11-14");
+  }
+
+  #[test]
+  fn last_used() {
+    use clap::Parser;
+    use crate::args::{Action, Cli};
+    use crate::install::meta::InstallMeta;
+    let meta: InstallMeta = serde_json::from_str(r#"{"installed_at": 1700000000}"#).unwrap();
+    assert_eq!((meta.last_used, meta.unused_since()), (None, Some(1_700_000_000)));
+    let meta = InstallMeta { last_used: Some(1_710_000_000), ..meta };
+    assert_eq!(meta.unused_since(), Some(1_710_000_000));
+    assert_eq!(InstallMeta::default().unused_since(), None);
+    let cli = Cli::try_parse_from(["rvm", "prune", "--unused-for", "180d"]).unwrap();
+    assert!(matches!(cli.action, Action::Prune { unused_for: Some(d), .. } if d.as_secs() == 180 * 86400));
+  }
+
+  #[test]
+  fn command_mapping() {
+    use crate::activate::{command_shims_dir, with_command_shims};
+    use crate::resolve::command_name;
+    let env: crate::parsing::yaml_ser::Env = serde_yaml::from_str("rversion: ~4.3.0\ndependencies: {}\ncommands:\n  Rscript: ~4.2.0\n").unwrap();
+    assert_eq!(env.commands["Rscript"], Range::parse("~4.2.0").unwrap());
+    assert_eq!(command_name("/opt/R/bin/Rscript"), "Rscript");
+    assert_eq!(command_name("R.exe"), "R");
+    let path = std::env::join_paths(["/usr/bin", "/bin"]).unwrap();
+    let with = with_command_shims(&path, true);
+    assert_eq!(std::env::split_paths(&with).next(), Some(command_shims_dir()));
+    assert_eq!(with_command_shims(&with, true), with);
+    assert_eq!(with_command_shims(&with, false), path);
+  }
+
+  #[test]
+  fn version_env() {
+    use crate::activate::expand_env;
+    use crate::config::Config;
+    use crate::install::install_dir;
+    let config: Config = serde_yaml::from_str("env:\n  - range: ^4.3\n    vars: {R_SHARE_DIR: \"{install_dir}/share\"}\n").unwrap();
+    assert!(config.env[0].range.contains(&p("4.3.1")) && !config.env[0].range.contains(&p("4.2.3")));
+    let expanded = expand_env(&config.env[0].vars["R_SHARE_DIR"], &p("4.3.1"));
+    assert_eq!(expanded, format!("{}/share", install_dir(&p("4.3.1")).display()));
+    assert_eq!(expand_env("R-{version}", &p("4.3.1")), "R-4.3.1");
+  }
+
+  #[test]
+  fn unmanaged_installs() {
+    use std::path::PathBuf;
+    use clap::Parser;
+    use crate::args::{Action, Cli};
+    use crate::install::scan::{SkipReason, Skipped};
+    let misnamed = Skipped { path: PathBuf::from("/v/v4.3.0"), reason: SkipReason::Misnamed(p("4.3.0")) };
+    assert!(misnamed.is_unmanaged());
+    assert_eq!(misnamed.adopt_command(), "rvm adopt /v/v4.3.0 --as 4.3.0");
+    let unknown = Skipped { path: PathBuf::from("/v/custom"), reason: SkipReason::NotAVersion };
+    assert_eq!(unknown.adopt_command(), "rvm adopt /v/custom --as <version>");
+    assert!(!Skipped { path: PathBuf::from("/v/4.1.0"), reason: SkipReason::Empty(p("4.1.0")) }.is_unmanaged());
+    let cli = Cli::try_parse_from(["rvm", "adopt", "/v/custom", "--as", "4.2.3"]).unwrap();
+    assert!(matches!(cli.action, Action::Adopt { version, .. } if version == "4.2.3"));
+  }
+
+  #[test]
+  fn explained_comparisons() {
+    let explain = |a: &str, b: &str| p(a).explain_cmp(&p(b)).to_string();
+    assert_eq!(explain("4.2.0", "4.5.0"), "differs at minor: 2 < 5");
+    assert_eq!(explain("4.3.0", "4.3.0-rc.1"), "differs at pre_release: release > rc.1, a release comes after its pre-releases");
+    assert_eq!(explain("4.3.0-rc.2", "4.3.0-rc.10"), "differs at pre_release: identifier 2 of rc.2 and rc.10: 2 < 10, numbers compare by value");
+    assert_eq!(explain("4.3.0-1", "4.3.0-beta"), "differs at pre_release: identifier 1 of 1 and beta: 1 < beta, numbers come before words");
+    assert_eq!(explain("4.3.0-rc", "4.3.0-rc.1"), "differs at pre_release: rc < rc.1, with the same identifiers first fewer of them comes first");
+    assert!(explain("4.3.0+a", "4.3.0+b").starts_with("differs at build: a < b"));
+    assert_eq!(explain("4.3.0", "4.3.0"), "equal in every component");
+    assert_eq!(p("4.1.3").explain_cmp(&p("4.1.0")).ordering, std::cmp::Ordering::Greater);
+  }
+
+  #[test]
+  fn range_syntaxes() {
+    use crate::parsing::version_parser::{RenderError, Syntax};
+    let as_ = |range: &str, syntax| r(range).display_as(syntax);
+    assert_eq!(as_("^4.2", Syntax::Cargo).unwrap(), ">=4.2.0, <5.0.0");
+    assert_eq!(as_(">=4.2.0, <5.0.0, !=4.2.1", Syntax::Cargo), Err(RenderError::Lossy(Syntax::Cargo, ">=4.2.0,<5.0.0,!=4.2.1, it would need ||".to_owned())));
+    assert_eq!(Range::default().display_as(Syntax::Cargo).unwrap(), "*");
+    #[cfg(feature = "npm")] {
+      assert_eq!(as_("^4.2", Syntax::Npm).unwrap(), ">=4.2.0 <5.0.0");
+      assert_eq!(as_(">=4.2.0, <5.0.0, !=4.2.1", Syntax::Npm).unwrap(), ">=4.2.0 <4.2.1 || >4.2.1 <5.0.0");
+      assert_eq!(as_("~4.1.0, =4.3.1", Syntax::Npm).unwrap(), ">=4.1.0 <4.2.0 || =4.3.1");
+      assert!(as_(">=4.2.1.1", Syntax::Npm).is_err());
+    }
+    #[cfg(not(feature = "npm"))]
+    assert_eq!(as_("^4.2", Syntax::Npm), Err(RenderError::Unsupported(Syntax::Npm)));
+    #[cfg(feature = "ruby")] {
+      assert_eq!(as_("^4.2", Syntax::Ruby).unwrap(), ">= 4.2.0, < 5.0.0");
+      assert!(as_("~4.1.0, =4.3.1", Syntax::Ruby).is_err());
+      assert_eq!(Range::default().display_as(Syntax::Ruby).unwrap(), ">= 0.0.0");
+      assert_eq!(as_(">=4.3.0-rc.1", Syntax::Ruby).unwrap(), ">= 4.3.0.rc.1");
+    }
+    #[cfg(not(feature = "ruby"))]
+    assert_eq!(as_("^4.2", Syntax::Ruby), Err(RenderError::Unsupported(Syntax::Ruby)));
+    #[cfg(feature = "pep440")] {
+      assert_eq!(as_(">=4.2.0, <5.0.0, !=4.2.1", Syntax::Pep440).unwrap(), ">=4.2.0, <5.0.0, !=4.2.1");
+      assert_eq!(as_("=4.3.1", Syntax::Pep440).unwrap(), "==4.3.1");
+      assert_eq!(as_(">=4.3.0-rc.1", Syntax::Pep440).unwrap(), ">=4.3.0rc1");
+      assert!(as_(">=4.4.0-nightly.20240315", Syntax::Pep440).is_err());
+      assert_eq!(as_(">=4.2.1.1", Syntax::Pep440).unwrap(), ">=4.2.1.1");
+    }
+    #[cfg(not(feature = "pep440"))]
+    assert_eq!(as_("=4.3.1", Syntax::Pep440), Err(RenderError::Unsupported(Syntax::Pep440)));
+  }
+
+  #[test]
+  fn constraint_rewriting() {
+    use std::path::Path;
+    use crate::constraint_file::{Bump, ConstraintFile, FileKind};
+    let yaml = "# the project\nname: x\nrversion: \"~4.2.1\" # keep in sync with CI\ndependencies: {}\n";
+    let mut file = ConstraintFile::parse(Path::new("Renv.yaml"), yaml.to_owned()).unwrap();
+    assert_eq!((file.kind, file.constraint()), (FileKind::Project, "~4.2.1"));
+    assert_eq!(file.range().unwrap(), r("~4.2.1"));
+    assert_eq!(file.bumped(Bump::Minor).unwrap(), "~4.3.0");
+    assert_eq!(file.bumped(Bump::Major).unwrap(), "~5.0.0");
+    file.rewrite(&file.bumped(Bump::Patch).unwrap());
+    assert_eq!(file.contents(), yaml.replace("~4.2.1", "~4.2.2"));
+    let tools = "nodejs 20.1.0\n  R   4.2 4.1  # fallback\n";
+    let mut file = ConstraintFile::parse(Path::new("/p/.tool-versions"), tools.to_owned()).unwrap();
+    assert_eq!(file.constraint(), "4.2");
+    file.rewrite(&file.bumped(Bump::Minor).unwrap());
+    assert_eq!(file.contents(), "nodejs 20.1.0\n  R   4.3 4.1  # fallback\n");
+    let ranged = ConstraintFile::parse(Path::new("Renv.yaml"), "rversion: '>=4.1, <5'\n".to_owned()).unwrap();
+    assert_eq!(ranged.constraint(), ">=4.1, <5");
+    assert!(ranged.bumped(Bump::Minor).is_err());
+    assert!(ConstraintFile::parse(Path::new("Renv.yaml"), "  rversion: 4.2\n".to_owned()).is_err());
+  }
+
+  #[test]
+  fn workspace_commands() {
+    use clap::Parser;
+    use crate::args::{Action, Cli, WorkspaceAction};
+    let cli = Cli::try_parse_from(["rvm", "workspace", "status"]).unwrap();
+    assert!(matches!(cli.action, Action::Workspace { action: WorkspaceAction::Status { root } } if root == std::path::Path::new(".")));
+    let cli = Cli::try_parse_from(["rvm", "workspace", "install", "monorepo", "-j", "2"]).unwrap();
+    assert!(matches!(cli.action, Action::Workspace { action: WorkspaceAction::Install { jobs: 2, .. } }));
+    use crate::actions::workspace::resolve;
+    use crate::install::install_dir;
+    use crate::lockfile::FORMAT;
+    use crate::utils::with_rvm_vars;
+    // a layout of its own, with both versions installed so it's answered without the index
+    let home = tempfile::tempdir().unwrap();
+    let root = tempfile::tempdir().unwrap();
+    let root = root.path();
+    let vars = std::collections::BTreeMap::from([("RVM_HOME".to_owned(), home.path().to_string_lossy().into_owned())]);
+    let workspace = with_rvm_vars(&vars, || {
+      for version in [p("4.3.1"), p("4.2.3")] {
+        std::fs::create_dir_all(install_dir(&version).join("bin")).unwrap();
+        std::fs::write(install_dir(&version).join("bin/R"), "").unwrap();
+      }
+      for (name, range, version) in [("a", "~4.3.0", "4.3.1"), ("b", ">=4.1.0", "4.2.3"), ("c", ">=4.2.0", "4.2.3")] {
+        let project = root.join(name).join("Renv.yaml");
+        std::fs::create_dir_all(project.parent().unwrap()).unwrap();
+        std::fs::write(&project, format!("rversion: '{}'\ndependencies: {{}}\n", range)).unwrap();
+        let lock = ProjectLock { format: FORMAT, range: r(range), version: p(version), url: None, sha256: None };
+        lock.save(&ProjectLock::path_for(&project)).unwrap();
+      }
+      let mut found = 0;
+      let workspace = serde_json::to_value(resolve(root, &mut |_| found += 1)).unwrap();
+      assert_eq!(found, 3);
+      workspace
+    });
+    let projects = workspace["projects"].as_array().unwrap();
+    assert_eq!(projects.iter().map(|p| p["version"].as_str().unwrap()).collect::<Vec<_>>(), ["4.3.1", "4.2.3", "4.2.3"]);
+    assert!(projects.iter().all(|p| p["installed"] == true));
+    assert_eq!(workspace["versions"], serde_json::json!(["4.2.3", "4.3.1"]));
+    assert_eq!(workspace["missing"], serde_json::json!([]));
+    assert_eq!(workspace["common"]["minimal"], "4.3.0");
+    assert_eq!(workspace["common"]["floor_by"][0], serde_json::json!(root.join("a").join("Renv.yaml")));
+  }
+
+  #[test]
+  fn common_range_bounds() {
+    use crate::parsing::version_parser::{common_bounds, CommonBounds, Comparator, Op};
+    let ranges = vec![r(">=4.1.0"), r("^4.2.0"), r(">=4.0.0, <4.3.0, !=4.2.0")];
+    let bounds = common_bounds(&ranges);
+    assert_eq!(bounds.minimal, Some(p("4.2.1")));
+    assert_eq!(bounds.ceiling, Some(p("4.3.0")));
+    assert_eq!(bounds.floor_by, Some((1, Comparator { op: Op::Ge, version: p("4.2.0") })));
+    assert_eq!(bounds.ceiling_by, Some((2, Comparator { op: Op::Lt, version: p("4.3.0") })));
+    let installed = [p("4.1.3"), p("4.2.0"), p("4.2.3"), p("4.3.1")];
+    assert_eq!(CommonBounds::newest(&ranges, &installed), Some(&p("4.2.3")));
+    assert_eq!(common_bounds(&[r("~4.1.0"), r("~4.2.0")]).minimal, None);
+    assert_eq!(common_bounds(&[r("=4.3.1"), r(">=4.2.0")]).minimal, Some(p("4.3.1")));
+    assert_eq!(common_bounds(&[]), CommonBounds { minimal: Some(p("0.0.0")), ..CommonBounds::default() });
+  }
+
+  #[test]
+  fn tie_breaking() {
+    use crate::config::Config;
+    use crate::resolve::TieBreak;
+    let versions = [p("4.2.3"), p("4.3.1+b"), p("4.3.1"), p("4.3.1+a")];
+    let range = r("^4.2.0");
+    let newest = |policy: TieBreak, installed: &[Version]| policy.newest(&range, &versions, installed).map(|v| v.to_string());
+    assert_eq!(newest(TieBreak::PreferLatestBuild, &[]).as_deref(), Some("4.3.1+b"));
+    assert_eq!(newest(TieBreak::PreferNoBuild, &[]).as_deref(), Some("4.3.1"));
+    assert_eq!(newest(TieBreak::PreferInstalled, &[p("4.3.1+a")]).as_deref(), Some("4.3.1+a"));
+    assert_eq!(newest(TieBreak::PreferInstalled, &[p("4.2.3")]).as_deref(), Some("4.3.1+b"));
+    let config: Config = serde_yaml::from_str("tie_break: prefer-no-build\n").unwrap();
+    assert_eq!(config.tie_break, TieBreak::PreferNoBuild);
+  }
+
+  #[test]
+  fn names_in_ranges() {
+    let pool = [Version::parse("4.2.3").unwrap(), Version::parse("4.3.1").unwrap()];
+    let names = |name: &str| (name == "lts").then(|| pool[0].clone());
+    assert_eq!(Range::parse_with(">=lts, <5", names).unwrap(), Range::parse(">=4.2.3, <5").unwrap());
+    assert_eq!(Range::parse_with("=lts", names).unwrap(), Range::parse("=4.2.3").unwrap());
+    assert_eq!(Range::parse_with(">=nightly", names), Err(ParseError::UnknownName("nightly".to_owned())));
+    assert_eq!(Range::parse_with("~4.2", names).unwrap(), Range::parse("~4.2").unwrap());
+    assert_eq!(crate::resolve::request_range_in(">=latest", &pool, "installed").unwrap(), Range::parse(">=4.3.1").unwrap());
+    assert!(crate::resolve::request_range_in(">=nightly", &pool, "installed").is_err());
+    // read from a file the names stay as written, nothing is looked up until with_names
+    let named: Range = ">=lts, <5".parse().unwrap();
+    assert!(named.is_named() && !named.contains(&pool[0]) && !named.contains(&Version::new(0, 0, 0)));
+    assert_eq!(named.to_string(), ">=lts, <5");
+    assert_eq!(serde_yaml::from_str::<Range>(&serde_yaml::to_string(&named).unwrap()).unwrap(), named);
+    assert_eq!(named.with_names(names).unwrap(), r(">=4.2.3, <5"));
+    assert_eq!(r("~4.2").with_names(|_| None).unwrap(), r("~4.2"));
+    assert_eq!(">=nightly".parse::<Range>().unwrap().with_names(names), Err(ParseError::UnknownName("nightly".to_owned())));
+    assert!(Range::named("~4.2").is_err() && Range::named(">=lts <<").is_err());
+  }
+
+  #[test]
+  fn version_distance() {
+    let v = |v: &str| Version::parse(v).unwrap();
+    let distance = v("4.1.3").distance(&v("4.3.1"));
+    assert_eq!((distance.majors, distance.minors, distance.patches), (0, 2, 1));
+    assert_eq!(distance, v("4.3.1").distance(&v("4.1.3")));
+    assert_eq!(distance.to_string(), "2 minors, 1 patch");
+    assert_eq!(v("4.2.1").distance(&v("4.2.3")).to_string(), "2 patches");
+    assert_eq!(v("3.6.3").distance(&v("4.0.0")).majors, 1);
+    assert!(v("4.3.0-rc").distance(&v("4.3.0")).is_zero());
+    assert!(v("3.6.3").distance(&v("4.0.0")).scalar() > v("4.1.0").distance(&v("4.3.2")).scalar());
+  }
+
+  #[test]
+  fn pre_release_promotion() {
+    let v = |v: &str| Version::parse(v).unwrap();
+    assert_eq!(v("2.0.0-rc.3").promote(), v("2.0.0"));
+    assert_eq!(v("2.0.0-rc.3+build.5").promote(), v("2.0.0"));
+    assert_eq!(v("4.3.1").promote(), v("4.3.1"));
+    assert!(v("2.0.0").is_promotion_of(&v("2.0.0-rc.3")));
+    assert!(v("2.0.0+build.1").is_promotion_of(&v("2.0.0-beta")));
+    assert!(!v("2.0.0").is_promotion_of(&v("2.0.1-rc.1")));
+    assert!(!v("2.0.0").is_promotion_of(&v("2.0.0")));
+    assert!(!v("2.0.0-rc.4").is_promotion_of(&v("2.0.0-rc.3")));
+  }
+
+  #[test]
+  fn ordering_agrees_with_eq() {
+    let texts = ["4.3.0-01", "4.3.0-1", "4.3.0-rc.1", "4.3.0-rc.01", "4.3.0+b", "4.3.0", "4.3.0.1"];
+    for a in texts.map(p) {
+      for b in texts.map(p) {
+        assert_eq!(a == b, a.cmp(&b) == std::cmp::Ordering::Equal, "{} vs {}", a, b);
+        assert_eq!(a.sort_key().cmp(&b.sort_key()), a.cmp(&b), "{} vs {}", a, b);
+      }
+    }
+  }
+
+  #[test]
+  fn leading_zeros() {
+    use crate::parsing::version_parser::VersionFormatter;
+    let version = p("1.02.003");
+    assert_eq!(version, p("1.2.3"));
+    assert_eq!(version.to_string(), "1.2.3");
+    assert!(p("1.02.003") < p("1.10.0"));
+    assert_eq!(VersionFormatter::new().keep_zeros(true).format(&version), "1.02.003");
+    assert_eq!(VersionFormatter::new().keep_zeros(true).format(&p("1.2.3-rc.01")), "1.2.3-rc.01");
+    assert_eq!(VersionFormatter::new().keep_zeros(true).pad(3, 1, 1).format(&version), "001.02.003");
+    assert_eq!(VersionFormatter::new().keep_zeros(true).format(&version.with_minor(5)), "1.05.003");
+  }
+
+  #[test]
+  fn caseless_pre_releases() {
+    use crate::parsing::version_parser::Matching;
+    let caseless = Matching { caseless_pre_release: true };
+    assert_ne!(p("4.3.0-RC.1").cmp(&p("4.3.0-rc.1")), std::cmp::Ordering::Equal);
+    assert_eq!(p("4.3.0-RC.1").cmp_in(&p("4.3.0-rc.1"), caseless), std::cmp::Ordering::Equal);
+    assert_eq!(p("4.3.0-RC.1").cmp_in(&p("4.3.0-rc.2"), caseless), std::cmp::Ordering::Less);
+    let range = Range::parse("=4.3.0-rc.1").unwrap();
+    assert!(!range.contains(&p("4.3.0-RC.1")));
+    assert!(range.contains_in(&p("4.3.0-RC.1"), caseless));
+    assert!(!Range::parse("!=4.3.0-RC.1, >=4.2").unwrap().contains_in(&p("4.3.0-rc.1"), caseless));
+  }
+
+  #[test]
+  fn batch_contains() {
+    let pool = ["4.1.3", "4.2.0", "4.2.3", "4.3.0-rc.1", "4.3.1"].map(p);
+    let range = Range::parse("~4.2").unwrap();
+    assert!(range.contains_any(&pool));
+    assert!(!range.contains_all(&pool));
+    assert!(range.contains_all(&pool[1..3]));
+    assert!(range.contains_all(&[]) && !range.contains_any(&[]));
+    let (matching, rest) = range.partition(&pool);
+    assert_eq!(matching, [&pool[1], &pool[2]]);
+    assert_eq!(rest, [&pool[0], &pool[3], &pool[4]]);
+    assert_eq!(Range::default().partition(&pool).0.len(), pool.len());
+  }
+
+  #[test]
+  fn build_deduplication() {
+    use crate::parsing::version_parser::precedence_classes;
+    use crate::resolve::TieBreak;
+    let listing = ["4.3.1+b2", "4.2.3", "4.3.1", "4.3.1+b1", "4.3.0-rc+b1", "4.3.0-rc"].map(p);
+    let classes = precedence_classes(&listing, |v| v);
+    assert_eq!(classes.len(), 3);
+    assert_eq!(classes[2], [&listing[0], &listing[2], &listing[3]]);
+    let kept = |policy: TieBreak, installed: &[Version]| policy.dedup(&listing, |v| v, installed).into_iter().map(Version::to_string).collect::<Vec<_>>();
+    assert_eq!(kept(TieBreak::PreferLatestBuild, &[]), ["4.3.1+b2", "4.2.3", "4.3.0-rc+b1"]);
+    assert_eq!(kept(TieBreak::PreferNoBuild, &[]), ["4.2.3", "4.3.1", "4.3.0-rc"]);
+    assert_eq!(kept(TieBreak::PreferInstalled, &[p("4.3.1+b1")]), ["4.2.3", "4.3.1+b1", "4.3.0-rc+b1"]);
+  }
+
+  #[test]
+  fn pool_range_scans() {
+    use crate::install::scan::VersionPool;
+    use crate::parsing::version_parser::RangeBound;
+    let pool = VersionPool::new(["4.3.0", "4.2.3", "4.3.0-rc.1", "4.1.0", "4.2.0+b", "4.2.0", "4.4.0"].map(p));
+    let scanned = |range: &str| { let range = Range::parse(range).unwrap(); pool.range_iter(&range).map(Version::to_string).collect::<Vec<_>>() };
+    assert_eq!(scanned("~4.2"), ["4.2.0", "4.2.0+b", "4.2.3"]);
+    assert_eq!(scanned(">=4.2.3, <4.4, !=4.3.0"), ["4.2.3", "4.3.0-rc.1"]);
+    assert_eq!(scanned("=4.1.0"), ["4.1.0"]);
+    assert_eq!(scanned("<4.2"), ["4.1.0"]);
+    assert_eq!(pool.versions().count(), 7);
+    assert!(RangeBound::Below(p("4.3.0")) < RangeBound::At(p("4.3.0-rc.1")));
+    assert!(RangeBound::At(p("4.2.9")) < RangeBound::Below(p("4.3.0-rc.1")));
+    assert_eq!(pool.into_vec(), { let mut v = ["4.3.0", "4.2.3", "4.3.0-rc.1", "4.1.0", "4.2.0+b", "4.2.0", "4.4.0"].map(p).to_vec(); v.sort(); v });
+  }
+
+  #[test]
+  fn index_changelog() {
+    use crate::index::Changes;
+    let meta = |v: &str, url: &str| serde_json::from_value::<crate::index::ArtifactMeta>(serde_json::json!({"version": v, "url": url})).unwrap();
+    let snapshot = vec![meta("4.3.0", "a"), meta("4.2.3", "b"), meta("4.2.2", "c")];
+    let changes = Changes::parse(r#"{"cursor": "17", "added": [{"version": "4.3.1", "url": "d"}, {"version": "4.2.3", "url": "e"}], "yanked": ["4.2.2"]}"#).unwrap();
+    assert_eq!(changes.cursor, "17");
+    let entries = changes.apply(snapshot);
+    assert_eq!(entries.iter().map(|m| (m.version.to_string(), m.url.as_str())).collect::<Vec<_>>(),
+      [("4.3.1".to_owned(), "d"), ("4.3.0".to_owned(), "a"), ("4.2.3".to_owned(), "e")]);
+    let empty = Changes::parse(r#"{"cursor": "18"}"#).unwrap();
+    assert_eq!(empty.clone().apply(entries.clone()), entries);
+  }
+
+  #[test]
+  fn mirror_ordering() {
+    use crate::install::mirrors::{now, Health, MirrorHealth, FAILURE_MEMORY};
+    let mirror = |to: &str| Mirror { from: "https://cran.r-project.org/".to_owned(), to: to.to_owned() };
+    let mirrors = [mirror("https://a/"), mirror("https://b/"), mirror("https://c/")];
+    let url = "https://cran.r-project.org/bin/R.exe";
+    let order = |health: &MirrorHealth| health.attempts(&mirrors, url).into_iter().map(|(_, url)| url).collect::<Vec<_>>();
+    assert_eq!(order(&MirrorHealth::default()), ["https://a/bin/R.exe", "https://b/bin/R.exe", "https://c/bin/R.exe"]);
+    let mut health = MirrorHealth::default();
+    health.mirrors.insert("https://a/".to_owned(), Health { failing: 2, last_failure: Some(now()), ..Health::default() });
+    health.mirrors.insert("https://b/".to_owned(), Health { latency_ms: Some(900), ..Health::default() });
+    health.mirrors.insert("https://c/".to_owned(), Health { latency_ms: Some(100), ..Health::default() });
+    assert_eq!(order(&health), ["https://c/bin/R.exe", "https://b/bin/R.exe", "https://a/bin/R.exe"]);
+    // an old failure is forgiven
+    health.mirrors.get_mut("https://a/").unwrap().last_failure = Some(now() - FAILURE_MEMORY - 1);
+    assert_eq!(order(&health)[0], "https://a/bin/R.exe");
+    assert_eq!(health.attempts(&mirrors, "https://github.com/x"), [(None, "https://github.com/x".to_owned())]);
+  }
+
+  #[test]
+  fn digest_algorithms() {
+    use std::collections::BTreeMap;
+    use crate::install::digest::{insecure_allowed, Algorithm, Digest, INSECURE_DIGEST_VAR};
+    use crate::install::{verify_download_with, InstallError};
+    use crate::utils::with_rvm_vars;
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("artifact");
+    std::fs::write(&file, b"abc").unwrap();
+    let sha256 = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+    let sha512 = "sha512:DDAF35A193617ABACC417349AE20413112E6FA4E89A97EA20A9EEEE64B55D39A2192992A274FC1A836BA3C23A3FEEBBD454D4423643CE80E2A9AC94FA54CA49F";
+    assert_eq!(Digest::parse(sha256).unwrap().algorithm, Algorithm::Sha256);
+    assert_eq!(Digest::parse(sha512).unwrap().algorithm, Algorithm::Sha512);
+    assert!(Digest::parse("crc32:352441c2").is_err());
+    assert_eq!(verify_download_with("abc", &file, Some(sha256), false).unwrap(), sha256);
+    assert_eq!(verify_download_with("abc", &file, Some(sha512), false).unwrap(), sha512.to_lowercase());
+    assert_eq!(verify_download_with("abc", &file, None, false).unwrap(), sha256);
+    assert_eq!(Algorithm::Sha1.hash_file(&file).unwrap(), "a9993e364706816aba3e25717850c26c9cd0d89d");
+    // a broken algorithm is checked only when it's allowed, otherwise refused before it's even checked, and the download with it
+    let md5 = "md5:900150983cd24fb0d6963f7d28e17f72";
+    assert_eq!(verify_download_with("abc", &file, Some(md5), true).unwrap(), md5);
+    assert!(matches!(verify_download_with("abc", &file, Some(md5), false), Err(InstallError::WeakDigest(_, Algorithm::Md5))));
+    assert!(!file.exists());
+    // writing false is off, like it is for the flag
+    let vars = BTreeMap::from([(INSECURE_DIGEST_VAR.to_owned(), "false".to_owned())]);
+    assert!(!with_rvm_vars(&vars, insecure_allowed));
+    let vars = BTreeMap::from([(INSECURE_DIGEST_VAR.to_owned(), "1".to_owned())]);
+    assert!(with_rvm_vars(&vars, insecure_allowed));
+    std::fs::write(&file, b"abd").unwrap();
+    assert!(matches!(verify_download_with("abd", &file, Some(sha512), false), Err(InstallError::Checksum(..))));
+  }
+
+  #[test]
+  fn install_receipts() {
+    use crate::install::receipt::{diff, list, remove};
+    let dir = std::env::temp_dir().join(format!("rvm-receipt-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("bin")).unwrap();
+    std::fs::create_dir_all(dir.join("library/base")).unwrap();
+    std::fs::write(dir.join("bin/R"), "R").unwrap();
+    std::fs::write(dir.join("library/base/DESCRIPTION"), "base").unwrap();
+    let receipt = list(&dir).unwrap();
+    assert_eq!(receipt.iter().map(String::as_str).collect::<Vec<_>>(), ["bin/", "bin/R", "library/", "library/base/", "library/base/DESCRIPTION"]);
+    std::fs::create_dir_all(dir.join("library/dplyr")).unwrap();
+    std::fs::remove_file(dir.join("bin/R")).unwrap();
+    let (added, missing) = diff(&receipt, &dir).unwrap();
+    assert_eq!(added, ["library/dplyr/"]);
+    assert_eq!(missing, ["bin/R"]);
+    // what the receipt has goes, a missing file isn't an error, and the tree with it once it's empty
+    std::fs::remove_dir(dir.join("library/dplyr")).unwrap();
+    assert_eq!(remove(&p("4.3.1"), &receipt, &dir).unwrap(), None);
+    assert!(!dir.exists());
+  }
+
+  #[test]
+  fn file_dedup() {
+    use crate::install::dedup::{share, Dedup};
+    let dir = std::env::temp_dir().join(format!("rvm-dedup-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let (theirs, ours) = (dir.join("theirs"), dir.join("ours"));
+    std::fs::write(&theirs, vec![7u8; 8192]).unwrap();
+    std::fs::write(&ours, vec![7u8; 8192]).unwrap();
+    let mut can_reflink = true;
+    if share(&theirs, &ours, Dedup::Reflink, &mut can_reflink).unwrap() {
+      assert!(can_reflink);
+    } else {
+      // no clones on this filesystem, only a hard link shares it
+      assert!(!can_reflink);
+      assert!(share(&theirs, &ours, Dedup::Hardlink, &mut can_reflink).unwrap());
+    }
+    assert_eq!(std::fs::read(&ours).unwrap(), vec![7u8; 8192]);
+    assert!(!dir.join("ours.rvm-dedup").exists());
+    std::fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn read_only_installs() {
+    use crate::install::protect::set_writable;
+    let dir = std::env::temp_dir().join(format!("rvm-protect-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("bin")).unwrap();
+    std::fs::write(dir.join("bin/R"), "R").unwrap();
+    set_writable(&dir, false).unwrap();
+    assert!(std::fs::metadata(dir.join("bin/R")).unwrap().permissions().readonly());
+    #[cfg(unix)]
+    assert!(std::fs::metadata(dir.join("bin")).unwrap().permissions().readonly());
+    set_writable(&dir, true).unwrap();
+    std::fs::write(dir.join("bin/R"), "changed").unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn project_env_overlay() {
+    use crate::resolve::{Active, Origin};
+    use crate::activate::extra_vars;
+    let env: crate::parsing::yaml_ser::Env = serde_yaml::from_str("rversion: ~4.3.0\ndependencies: {}\nenv:\n  R_PROFILE_USER: \"{project_dir}/.Rprofile\"\n").unwrap();
+    assert_eq!(env.env.get("R_PROFILE_USER").map(String::as_str), Some("{project_dir}/.Rprofile"));
+    let dir = std::env::temp_dir().join(format!("rvm-project-env-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("Renv.yaml"), "rversion: ~4.3.0\ndependencies: {}\nenv:\n  A: '1'\n  B: '{version}'\n").unwrap();
+    // a lockfile picking the version is the project picking it
+    let locked = Active { version: p("4.3.1"), range: r("=4.3.1"), origin: Origin::Lockfile(dir.join("rvm.lock")), warnings: vec![] };
+    assert_eq!(locked.origin.project_file(), Some(dir.join("Renv.yaml")));
+    assert_eq!(extra_vars(&locked), ["A", "B"]);
+    let requested = Active { origin: Origin::Request("4.3".to_owned()), ..locked };
+    assert!(extra_vars(&requested).is_empty());
+    std::fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn shell_completions() {
+    use crate::actions::complete::candidates;
+    let words = |w: &[&str]| w.iter().map(|w| w.to_string()).collect::<Vec<_>>();
+    let commands = candidates(&words(&["un"]));
+    assert!(commands.contains(&"uninstall".to_owned()) && commands.contains(&"unpin".to_owned()));
+    assert!(commands.iter().all(|c| c.starts_with("un")));
+    // hidden ones are only for rvm itself
+    assert!(!candidates(&words(&[""])).contains(&"hook-env".to_owned()));
+    assert_eq!(candidates(&words(&["install", "lt"])), ["lts"]);
+    assert!(candidates(&words(&["compare", "4.2", ""])).is_empty());
+    assert!(Shell::Bash.completion().unwrap().contains("rvm complete --"));
+    assert!(Shell::Cmd.completion().is_none());
+  }
+
+  #[test]
+  fn range_warnings() {
+    use crate::parsing::warning::{collect, report, Warning};
+    assert!(r(">=4.1 <4.3").warnings().is_empty());
+    assert!(Range::default().warnings().is_empty());
+    let range = Range { except: vec![p("4.0.5"), p("4.1.2")], ..r(">=4.1 <4.3") };
+    assert_eq!(range.warnings(), vec![Warning::UnreachableExcept { version: p("4.0.5"), range: range.to_string() }]);
+    let range = Range { min: Some(p("4.1.0+build.7")), ..r(">=4.1 <4.3") };
+    assert!(matches!(&range.warnings()[..], [Warning::BuildIgnored { version, .. }] if version.build_metadata() == Some("build.7")));
+    // reported inside collect comes back with the result, nested ones don't leak out
+    let warned = collect(|| {
+      let inner = collect(|| report(Warning::StaleLock { message: "inner".to_owned() }));
+      assert_eq!(inner.warnings.len(), 1);
+      report(Warning::StaleLock { message: "outer".to_owned() });
+      7
+    });
+    assert_eq!(warned.value, 7);
+    assert_eq!(warned.warnings, vec![Warning::StaleLock { message: "outer".to_owned() }]);
+  }
+
+  #[test]
+  fn range_lints() {
+    use crate::parsing::ast::RangeAst;
+    use crate::parsing::lint::Lint;
+    assert!(r(">=4.1 <4.3").lint().is_empty());
+    assert!(r("=4.0.1 !=4.1.0").lint().iter().all(|l| !matches!(l, Lint::Empty)));
+    assert_eq!(r(">=4.3 <4.1").lint(), vec![Lint::Empty, Lint::Inverted { min: p("4.3.0"), max: p("4.1.0") }]);
+    assert_eq!(r(">=4.1 <4.3 !=4.0.5").lint(), vec![Lint::ExceptOutside { version: p("4.0.5") }]);
+    assert_eq!(r("=4.0.1 !=4.0.1").lint(), vec![Lint::Empty, Lint::IncludeExcepted { version: p("4.0.1") }]);
+    // placed at the comparator, the whole source for what's about all of it
+    let ast = RangeAst::parse(">=4.1 <4.3 !=4.0.5");
+    assert_eq!(ast.lint().iter().map(|d| d.span.clone()).collect::<Vec<_>>(), vec![11..18]);
+    let ast = RangeAst::parse(">=4.3, <4.1");
+    assert_eq!(ast.lint().iter().map(|d| d.span.clone()).collect::<Vec<_>>(), vec![0..11, 7..11]);
+  }
+
+  #[test]
+  fn quoted_identifiers() {
+    use crate::parsing::ast::{RangeAst, TokenKind};
+    let odd = Version::parse(r#""1.2.3+odd chars""#).unwrap();
+    assert_eq!(odd.build_metadata(), Some("odd chars"));
+    assert_eq!(odd.to_string(), r#""1.2.3+odd chars""#);
+    // an escaped + stays in the pre-release, a quote and a \ in the build
+    let escaped = Version::parse(r#""4.3.0-rc\+1+a\"b\\c""#).unwrap();
+    assert_eq!(escaped.channel(), Some("rc+1"));
+    assert_eq!(escaped.build_metadata(), Some(r#"a"b\c"#));
+    assert_eq!(Version::parse(escaped.to_string()).unwrap(), escaped);
+    // what needs no quotes is written without them, and parses the same with them
+    assert_eq!(Version::parse(r#""4.3.0-rc.1""#).unwrap().to_string(), "4.3.0-rc.1");
+    let range = Range::parse(r#">="1.2.3-a b" <2, !="1.5.0+x y""#).unwrap();
+    assert_eq!(Range::parse(&range.to_string()).unwrap(), range);
+    assert!(Version::parse(r#""1.2.3+unclosed"#).is_err());
+    let ast = RangeAst::parse(r#"="1.2.3+odd chars" <2"#);
+    assert!(matches!(&ast.tokens[1].kind, TokenKind::Version(v) if *v == odd));
+    assert_eq!(ast.source(), r#"="1.2.3+odd chars" <2"#);
+  }
+
+  #[test]
+  fn release_date_ranges() {
+    use crate::index::{released_range, ArtifactMeta};
+    use crate::parsing::ast::RangeAst;
+    use crate::resolve::{request_range, ResolveError};
+    let meta = |v: &str, released: Option<&str>| ArtifactMeta {
+      version: p(v), url: String::new(), source_url: None, build_script: None, sha256: None, source_sha256: None, source_archive: None, platform: None,
+      eol: None, released: released.map(str::to_owned), yanked: false, support: None, deltas: vec![], source_deltas: vec![], notes_url: None, notes: None, relocate: None, env: Default::default(),
+    };
+    let entries = vec![meta("4.2.3", Some("2023-03-15")), meta("4.3.2", Some("2023-10-31")), meta("4.3.3", Some("2024-02-29")), meta("4.1.0", None)];
+    let before = released_range(&entries, |day| day < "2024-01-01");
+    assert_eq!(before, Range::parse("!=4.3.3").unwrap());
+    // a version the index doesn't date stays in
+    assert_eq!(before.max_satisfying(&[p("4.1.0"), p("4.3.2"), p("4.3.3")]), Some(&p("4.3.2")));
+    assert_eq!(released_range(&entries, |day| day > "2023-06-01"), Range::parse("!=4.2.3").unwrap());
+    assert!(matches!(request_range("released-before:soon"), Err(ResolveError::BadDay(_))));
+    assert!(matches!(request_range(">=4.1, released-after:2024-1-1"), Err(ResolveError::BadDay(_))));
+    // what's left of a request once a window in the middle is taken out
+    let ast = RangeAst::parse(">=4.1, released-before:2024-01-01, <5");
+    let window: Vec<usize> = (0..ast.tokens.len()).filter(|&i| ast.tokens[i].text == "released-before").flat_map(|i| i..i + 3).collect();
+    assert_eq!(ast.source_without(|i| window.contains(&i)), ">=4.1, <5");
+  }
+
+  #[test]
+  fn audit_entries() {
+    use crate::audit::{Change, Entry};
+    let install = Entry { at: 1700000000, user: "ci".to_owned(), change: Change::Install { version: p("4.3.1"), source: Some("https://r/4.3.1.tgz".to_owned()), sha256: Some("ab".to_owned()) } };
+    let line = serde_json::to_string(&install).unwrap();
+    assert!(line.contains(r#""change":"install""#) && !line.contains('\n'));
+    assert_eq!(serde_json::from_str::<Entry>(&line).unwrap(), install);
+    assert_eq!(install.change.to_string(), "install 4.3.1 from https://r/4.3.1.tgz sha256 ab");
+    assert!(!install.change.is_switch());
+    let global = Change::Alias { name: "default".to_owned(), target: Some("4.3".to_owned()), was: None };
+    assert!(global.is_switch());
+    assert!(!Change::Alias { name: "work".to_owned(), target: None, was: Some("4.2".to_owned()) }.is_switch());
+    let local = Change::Local { path: std::path::PathBuf::from("/p/Renv.yaml"), rversion: ">=4.3".to_owned(), was: Some("4.2".to_owned()) };
+    assert!(local.is_switch());
+    assert_eq!(local.to_string(), "local /p/Renv.yaml rversion: >=4.3 (was 4.2)");
+  }
+
+  #[test]
+  fn candidate_filters() {
+    use std::sync::Arc;
+    use crate::filter::{refused_by, Builtin, CandidateFilter, ExcludeEol, ExcludePrereleases, ExcludeYanked};
+    use crate::index::ArtifactMeta;
+    struct Allowlist(Vec<Version>);
+    impl CandidateFilter for Allowlist {
+      fn name(&self) -> String {
+        "allowlist".to_owned()
+      }
+      fn allows(&self, version: &Version, _meta: Option<&ArtifactMeta>) -> bool {
+        self.0.contains(version)
+      }
+    }
+    let meta = |v: &str, yanked: bool, eol: Option<&str>| ArtifactMeta {
+      version: p(v), url: String::new(), source_url: None, build_script: None, sha256: None, source_sha256: None, source_archive: None, platform: None,
+      eol: eol.map(str::to_owned), released: None, yanked, support: None, deltas: vec![], source_deltas: vec![], notes_url: None, notes: None, relocate: None, env: Default::default(),
+    };
+    let filters: Vec<Arc<dyn CandidateFilter>> = vec![
+      Arc::new(ExcludePrereleases), Arc::new(ExcludeYanked), Arc::new(ExcludeEol { day: "2024-01-01".to_owned() }),
+      Arc::new(Allowlist(vec![p("4.3.1"), p("4.3.2"), p("4.2.3"), p("4.4.0-rc.1"), p("4.1.3")])),
+    ];
+    let refused = |m: &ArtifactMeta| refused_by(&filters, &m.version, Some(m));
+    assert_eq!(refused(&meta("4.3.1", false, None)), None);
+    assert_eq!(refused(&meta("4.4.0-rc.1", false, None)).as_deref(), Some("exclude-prereleases"));
+    assert_eq!(refused(&meta("4.3.2", true, None)).as_deref(), Some("exclude-yanked"));
+    assert_eq!(refused(&meta("4.1.3", false, Some("2023-04-01"))).as_deref(), Some("exclude-eol (on 2024-01-01)"));
+    assert_eq!(refused(&meta("4.0.0", false, None)).as_deref(), Some("allowlist"));
+    // installed versions have no entry, only what needs none applies
+    assert_eq!(refused_by(&filters, &p("4.2.3"), None), None);
+    assert_eq!(refused_by(&filters, &p("4.0.0"), None).as_deref(), Some("allowlist"));
+    let config: Vec<Builtin> = serde_yaml::from_str("[exclude-prereleases, only-cached]").unwrap();
+    assert_eq!(config, vec![Builtin::ExcludePrereleases, Builtin::OnlyCached]);
+    let yanked: ArtifactMeta = serde_json::from_str(r#"{"version": "4.3.0", "url": "u", "yanked": true}"#).unwrap();
+    assert!(yanked.yanked);
+  }
+
+  #[test]
+  fn policy_rules() {
+    use crate::policy::{Policy, Violation};
+    let policy: Policy = serde_yaml::from_str(r#"
+allow: [">=4.2"]
+deny: ["=4.3.0"]
+allow_registries: ["https://r.example.com/"]
+deny_registries: ["https://r.example.com/old/"]
+contact: it-help
+"#).unwrap();
+    let violation = |v: &str, url: Option<&str>| policy.check(&p(v), url).err().map(|d| *d.violation);
+    assert_eq!(violation("4.3.1", Some("https://r.example.com/R-4.3.1.exe")), None);
+    assert_eq!(violation("4.3.1", None), None);
+    assert!(matches!(violation("4.3.0", None), Some(Violation::DeniedVersion { .. })));
+    assert!(matches!(violation("4.1.3", None), Some(Violation::NotAllowed { .. })));
+    assert!(matches!(violation("4.3.1", Some("https://cran.r-project.org/R-4.3.1.exe")), Some(Violation::NotAllowedRegistry { .. })));
+    assert!(matches!(violation("4.3.1", Some("https://r.example.com/old/R-4.3.1.exe")), Some(Violation::DeniedRegistry { .. })));
+    // registries are matched as urls, not as text
+    for url in ["https://r.example.com.evil.test/R-4.3.1.exe", "https://r.example.com@evil.test/R-4.3.1.exe", "http://r.example.com/R-4.3.1.exe", "https://r.example.com:8443/R-4.3.1.exe"] {
+      assert!(matches!(violation("4.3.1", Some(url)), Some(Violation::NotAllowedRegistry { .. })), "{}", url);
+    }
+    assert_eq!(violation("4.3.1", Some("https://R.Example.com:443/older/R-4.3.1.exe")), None);
+    // every url fetched is checked on its own, a mirror the version's url is rewritten to included
+    assert!(policy.check_url("https://r.example.com/R-4.3.0.exe").is_ok());
+    assert!(matches!(policy.check_url("https://mirror.test/R-4.3.1.exe").map_err(|d| *d.violation), Err(Violation::NotAllowedRegistry { .. })));
+    assert!(matches!(policy.check_url("https://r.example.com/old/R-4.3.1.exe").map_err(|d| *d.violation), Err(Violation::DeniedRegistry { .. })));
+    let denied = policy.check(&p("4.3.0"), None).unwrap_err().to_string();
+    assert!(denied.starts_with("R 4.3.0 is denied (=4.3.0) by the policy in ") && denied.ends_with(", ask it-help for an exception"), "{}", denied);
+    // the newest in range says why nothing was found, when the policy is why
+    let versions = [p("4.1.3"), p("4.3.0")];
+    let denied = |range: &str| policy.denied_match(&r(range), versions.iter().map(|v| (v, None))).map(|d| *d.violation);
+    assert!(matches!(denied("4.3"), Some(Violation::DeniedVersion { .. })));
+    assert!(matches!(denied("<4.2"), Some(Violation::NotAllowed { .. })));
+    assert_eq!(denied("4.4"), None);
+    assert!(Policy::default().is_open() && !policy.is_open());
+  }
+
+  #[test]
+  fn expiring_pins() {
+    use crate::parsing::warning::Warning;
+    use crate::parsing::yaml_ser::Env;
+    use crate::utils::parse_day;
+    let mut aliases: Aliases = toml::from_str("default = \"4.2\"\nlts = \"~4.3\"\n\n[expires]\ndefault = \"2025-01-01\"\n").unwrap();
+    assert_eq!(aliases.get("default"), Some("4.2"));
+    assert_eq!(aliases.expired("default", "2024-12-31"), None);
+    assert_eq!(aliases.expired("default", "2025-01-01"), Some("2025-01-01"));
+    assert_eq!(aliases.expired("lts", "2030-01-01"), None);
+    assert_eq!(toml::from_str::<Aliases>(&toml::to_string(&aliases).unwrap()).unwrap(), aliases);
+    // a new target is a new decision
+    aliases.set("default", "4.3").unwrap();
+    assert_eq!(aliases.expired("default", "2030-01-01"), None);
+    aliases.set_expiry("lts", Some("2026-06-01"));
+    aliases.remove("lts");
+    assert!(aliases.expires.is_empty());
+    assert!(aliases.set("expires", "4.3").is_err());
+    let env: Env = serde_yaml::from_str("rversion: ~4.2\nrversion_expires: 2025-06-30\ndependencies: {}\n").unwrap();
+    assert_eq!(env.rversion_expires.as_deref(), Some("2025-06-30"));
+    let warning = Warning::Expired { what: "alias default -> 4.2".to_owned(), day: "2025-01-01".to_owned() };
+    assert_eq!(warning.to_string(), "alias default -> 4.2 expired on 2025-01-01, re-evaluate this constraint");
+    assert_eq!(parse_day("2025-06-30"), Ok("2025-06-30".to_owned()));
+    assert!(parse_day("0d").unwrap() == crate::utils::today());
+    assert!(parse_day("2025-6-30").is_err());
+    assert!(parse_day("18446744073709551615s").is_err());
+  }
+
+  #[test]
+  fn manifest_diff() {
+    use crate::manifest::{Difference, Manifest, ManifestInstall};
+    let manifest = |text: &str| -> Manifest { toml::from_str(text).unwrap() };
+    let old = manifest(r#"
+format = 1
+default = "4.2"
+aliases = { lts = "~4.2", old = "4.1" }
+tools = { quarto = ["1.4.5"] }
+installs = [{ version = "4.3.1" }, { version = "4.2.3", pinned = true }, { version = "4.1.3" }]
+"#);
+    let new = manifest(r#"
+format = 1
+default = "4.3"
+aliases = { lts = "~4.2", dev = "4.4" }
+tools = { quarto = ["1.5.0"] }
+installs = [{ version = "4.4.0" }, { version = "4.3.1" }, { version = "4.2.3" }]
+"#);
+    assert!(old.diff(&old).is_empty());
+    let diff = old.diff(&new);
+    let install = |v: &str, pinned: bool| ManifestInstall { version: p(v), pinned, git: None, path: None };
+    assert_eq!(diff.installs, vec![
+      Difference::Removed { key: "4.1.3".to_owned(), old: install("4.1.3", false) },
+      Difference::Changed { key: "4.2.3".to_owned(), old: install("4.2.3", true), new: install("4.2.3", false) },
+      Difference::Added { key: "4.4.0".to_owned(), new: install("4.4.0", false) },
+    ]);
+    let keys = |d: &[Difference<String>]| d.iter().map(|d| d.key().to_owned()).collect::<Vec<_>>();
+    assert_eq!(keys(&diff.aliases), ["default", "old", "dev"]);
+    assert!(matches!(&diff.aliases[0], Difference::Changed { old, new, .. } if old == "4.2" && new == "4.3"));
+    assert_eq!(diff.tools.iter().map(|d| d.key()).collect::<Vec<_>>(), ["quarto 1.4.5", "quarto 1.5.0"]);
+    assert_eq!(install("4.2.3", true).to_string(), "4.2.3 pinned");
+    let json = serde_json::to_value(&diff.installs[0]).unwrap();
+    assert_eq!(json["change"], "removed");
+  }
+
+  #[test]
+  fn error_codes() {
+    use crate::error_code::{ErrorCode, CODES};
+    use crate::exit::Exit;
+    use crate::install::InstallError;
+    use crate::resolve::{Origin, ResolveError};
+    let codes: Vec<&str> = CODES.iter().map(|(code, _)| *code).collect();
+    let mut unique = codes.clone();
+    unique.sort();
+    unique.dedup();
+    assert_eq!(unique.len(), codes.len());
+    let exits = [Exit::Failure, Exit::Usage, Exit::NoMatch, Exit::Network, Exit::Verification, Exit::Aborted, Exit::Busy, Exit::Denied];
+    assert!(exits.iter().all(|exit| codes.contains(&exit.name())));
+    let errors: Vec<&str> = vec![
+      ParseError::InvalidRange.code(),
+      ParseError::InvalidVersion.code(),
+      ResolveError::Parse(ParseError::UnknownName("x".to_owned())).code(),
+      ResolveError::NotInstalled("~4.2".to_owned(), Origin::Default).code(),
+      ResolveError::NothingInstalled.code(),
+      InstallError::Checksum("R.exe".to_owned(), "a".to_owned(), "b".to_owned()).code(),
+      InstallError::Offline("u".to_owned()).code(),
+    ];
+    assert_eq!(errors, ["E_PARSE_RANGE", "E_PARSE_VERSION", "E_UNKNOWN_NAME", "E_NO_MATCH", "E_NOTHING_INSTALLED", "E_CHECKSUM", "E_OFFLINE"]);
+    assert!(errors.iter().all(|code| codes.contains(code)));
+  }
+
+  #[test]
+  fn version_dir_template() {
+    use crate::layout::NameTemplate;
+    let default = NameTemplate::default();
+    assert_eq!(default.name(&p("4.3.1-rc.1")), "4.3.1-rc.1");
+    assert_eq!(default.parse("4.3.1"), Some(p("4.3.1")));
+    assert_eq!(default.parse("4.3"), None);
+    let other: NameTemplate = "R-{major}.{minor}.{patch}{-pre}".parse().unwrap();
+    assert_eq!(other.name(&p("4.3.1")), "R-4.3.1");
+    assert_eq!(other.name(&p("4.4.0-rc.1")), "R-4.4.0-rc.1");
+    assert_eq!(other.parse("R-4.3.1"), Some(p("4.3.1")));
+    assert_eq!(other.parse("R-4.4.0-rc.1"), Some(p("4.4.0-rc.1")));
+    assert_eq!(other.parse("4.3.1"), None);
+    assert_eq!(other.parse("R-4.3"), None);
+    // what it can't say keeps the plain name, and is found under it
+    assert_eq!(other.name(&p("4.3.1+b2")), "4.3.1+b2");
+    assert_eq!(other.parse("4.3.1+b2"), Some(p("4.3.1+b2")));
+    let joined: NameTemplate = "{major}{minor}{patch}".parse().unwrap();
+    assert_eq!(joined.name(&p("4.3.1")), "431");
+    assert_eq!(joined.name(&p("4.13.1")), "4.13.1");
+    assert!("R-{major}.{minor}".parse::<NameTemplate>().is_err());
+    assert!("{version}/R".parse::<NameTemplate>().is_err());
+    assert!("{name}".parse::<NameTemplate>().is_err());
+  }
+
+  #[test]
+  fn single_comparator() {
+    use crate::parsing::version_parser::{matches, Comparator, Op};
+    let ops = [Op::Eq, Op::Ne, Op::Gt, Op::Lt, Op::Ge, Op::Le, Op::Tilde, Op::Caret];
+    let versions = ["4.2.0", "4.2.3", "4.2.3-rc.1", "4.2.4", "4.3.0", "5.0.0", "4.2.3+b1"].map(p);
+    for op in ops {
+      for target in &versions[..6] {
+        let range = Range::from_ver_vec(vec![(op, target.clone())]).unwrap();
+        for candidate in &versions {
+          assert_eq!(matches(op, candidate, target), range.contains(candidate), "{}{} on {}", op, target, candidate);
+        }
+      }
+    }
+    assert!(matches(Op::Tilde, &p("4.2.9"), &p("4.2.3")));
+    assert!(!matches(Op::Tilde, &p("4.3.0"), &p("4.2.3")));
+    assert!(matches(Op::Caret, &p("4.9.0"), &p("4.2.3")));
+    assert!(!matches(Op::Gt, &p("4294967295.4294967295.4294967295"), &p("4294967295.4294967295.4294967295")));
+    assert!(Comparator { op: Op::Le, version: p("4.2") }.matches(&p("4.2.0")));
+  }
+
+  #[test]
+  fn custom_operators() {
+    use crate::parsing::operators::{register, OperatorError};
+    use crate::parsing::version_parser::{Comparator, Op};
+    // pep 440's compatible release, ~=4.2 is >=4.2, <5 and ~=4.2.1 is >=4.2.1, <4.3
+    register("~=", 0, |v, written| {
+      let (major, minor, _) = v.parts();
+      let max = if written.matches('.').count() < 2 { Version::new(major + 1, 0, 0) } else { Version::new(major, minor + 1, 0) };
+      vec![Comparator { op: Op::Ge, version: v.clone() }, Comparator { op: Op::Lt, version: max }]
+    }).unwrap();
+    assert_eq!(Range::parse("~=4.2").unwrap(), r(">=4.2, <5"));
+    assert_eq!(Range::parse("~= 4.2.1, !=4.2.3").unwrap(), r(">=4.2.1, <4.3, !=4.2.3"));
+    assert_eq!(Range::parse(">=4.1 && ~=4.2.1").unwrap(), r(">=4.2.1, <4.3"));
+    assert_eq!(Range::parse("~4.2").unwrap(), r(">=4.2, <4.3"));
+    assert_eq!(Range::parse("~=x"), Err(ParseError::InvalidRange));
+    // the longer one wins where both start
+    register("~==", 0, |v, _| vec![Comparator { op: Op::Eq, version: v.clone() }]).unwrap();
+    assert_eq!(Range::parse("~==4.2.1").unwrap(), r("=4.2.1"));
+    assert_eq!(register(">=", 0, |_, _| vec![]), Err(OperatorError::Builtin(">=".to_owned())));
+    assert!(matches!(register("a b", 0, |_, _| vec![]), Err(OperatorError::InvalidSymbol(_))));
+  }
+
+  #[test]
+  fn range_edits() {
+    use crate::parsing::ast::RangeAst;
+    use crate::parsing::edit::Side;
+    let edit = |source: &str, f: &dyn Fn(&RangeAst) -> Result<RangeAst, ParseError>| f(&RangeAst::parse(source)).unwrap().source();
+    assert_eq!(edit(">= 4.1, <5, !=4.2.0", &|a| a.replace_bound(Side::Lower, Some(&p("4.2")))), ">= 4.2.0, <5, !=4.2.0");
+    assert_eq!(edit(">=4.1 <5", &|a| a.replace_bound(Side::Upper, Some(&p("6")))), ">=4.1 <6.0.0");
+    assert_eq!(edit(">=4.1 <5", &|a| a.replace_bound(Side::Upper, None)), ">=4.1");
+    assert_eq!(edit("!=4.2.0", &|a| a.replace_bound(Side::Lower, Some(&p("4.1.0")))), "!=4.2.0, >=4.1.0");
+    let tilde = edit("~4.2, !=4.2.1", &|a| a.replace_bound(Side::Upper, Some(&p("4.4"))));
+    assert_eq!(r(&tilde), r(">=4.2, <4.4, !=4.2.1"));
+    assert!(tilde.ends_with(", !=4.2.1"));
+    assert_eq!(edit(">=4.1;<5", &|a| a.add_except(&p("4.2.0"))), ">=4.1;<5;!=4.2.0");
+    assert_eq!(edit(">=4.1, !=4.2.0", &|a| a.add_except(&p("4.2.0"))), ">=4.1, !=4.2.0");
+    assert_eq!(edit(">=4.1, !=4.2.0, <5", &|a| a.remove_except(&p("4.2.0"))), ">=4.1, <5");
+    assert_eq!(edit("!=4.2.0 && >=4.1", &|a| a.remove_except(&p("4.2.0"))), ">=4.1");
+    assert!(RangeAst::parse(">=x").add_except(&p("4.2.0")).is_err());
+    let mut range = r(">=4.1");
+    assert!(range.add_except(p("4.2.0")) && !range.add_except(p("4.2.0")));
+    range.replace_bound(Side::Upper, Some(p("5")));
+    assert_eq!(range, r(">=4.1, <5, !=4.2.0"));
+    assert!(range.remove_except(&p("4.2.0")) && !range.remove_except(&p("4.2.0")));
+  }
+
+  #[test]
+  fn ordering_axioms() {
+    use crate::property::{range_axioms, version_axioms};
+    for seed in [1, 2, 3, 42] {
+      if let Err(counterexample) = version_axioms(seed, 5000) {
+        panic!("{}", counterexample);
+      }
+      if let Err(counterexample) = range_axioms(seed, 5000) {
+        panic!("{}", counterexample);
+      }
+    }
+  }
+
+  #[test]
+  fn registry_network_settings() {
+    use std::time::Duration;
+    use crate::throttle::{Network, RequestLimits};
+    let limits: RequestLimits = serde_yaml::from_str(r#"
+      read_timeout: 120
+      registries:
+        - {prefix: "https://r.corp.example/", connect_timeout: 2, retries: 0}
+        - {prefix: "https://r.corp.example/slow/", read_timeout: 600}
+    "#).unwrap();
+    assert_eq!(limits.for_url("https://r.corp.example/bin/R-4.3.1.tar.gz"), Network {
+      connect_timeout: Some(Duration::from_secs(2)),
+      read_timeout: Some(Duration::from_secs(120)),
+      retries: 0,
+    });
+    // the first prefix that matches wins, even when a later one is longer
+    assert_eq!(limits.for_url("https://r.corp.example/slow/x").read_timeout, Some(Duration::from_secs(120)));
+    assert_eq!(limits.for_url("https://cloud.r-project.org/src/base/"), Network { connect_timeout: None, read_timeout: Some(Duration::from_secs(120)), retries: 3 });
+    assert_eq!(limits.defaults(), limits.for_url("https://cloud.r-project.org/src/base/"));
+    // the read timeout is for the host going quiet, a body taking longer than it in all still comes through
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/R-4.3.1.tar.gz", listener.local_addr().unwrap());
+    let server = std::thread::spawn(move || {
+      use std::io::{Read, Write};
+      let (mut stream, _) = listener.accept().unwrap();
+      let _ = stream.read(&mut [0; 1024]).unwrap();
+      stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 4\r\nConnection: close\r\n\r\n").unwrap();
+      for _ in 0..4 {
+        std::thread::sleep(Duration::from_millis(300));
+        stream.write_all(b"x").unwrap();
+        stream.flush().unwrap();
+      }
+    });
+    let client = crate::local_utils::build_client_with(&crate::config::Config::default(), None, Some(Duration::from_millis(800)));
+    // read as downloads are, bytes() waits for all of it at once
+    let mut body = vec![];
+    std::io::Read::read_to_end(&mut client.get(&url).send().unwrap(), &mut body).unwrap();
+    assert_eq!(body, b"xxxx");
+    server.join().unwrap();
+  }
+
+  #[test]
+  fn system_version() {
+    use crate::parsing::yaml_ser::rversion_is_system;
+    use crate::resolve::selects_system;
+    assert!(selects_system("system") && selects_system(" system "));
+    assert!(!selects_system("4.3") && !selects_system(">=4.2"));
+    let dir = std::env::temp_dir().join(format!("rvm-system-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let project = dir.join("Renv.yaml");
+    std::fs::write(&project, "name: legacy\nrversion: system\n").unwrap();
+    assert!(rversion_is_system(&project));
+    std::fs::write(&project, "rversion: ^4.3.1\n").unwrap();
+    assert!(!rversion_is_system(&project));
+    assert!(!rversion_is_system(&dir.join("missing.yaml")));
+    std::fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn daemon_protocol() {
+    use crate::daemon::{Answer, Query};
+    use crate::resolve::{Active, Origin};
+    let query: Query = serde_json::from_str(r#"{"query":"resolve","dir":"/home/me/proj","vars":{"RVM_SHELL_VERSION":"4.2"}}"#).unwrap();
+    let Query::Resolve { dir, vars } = query else { panic!("not a resolve query") };
+    assert_eq!((dir, vars.get("RVM_SHELL_VERSION").map(String::as_str)), (std::path::PathBuf::from("/home/me/proj"), Some("4.2")));
+    assert_eq!(serde_json::from_str::<Query>(r#"{"query":"ping"}"#).unwrap(), Query::Ping);
+    let active = Active { version: p("4.2.3"), range: r("~4.2"), origin: Origin::Shell("4.2".to_owned()), warnings: vec![] };
+    let text = serde_json::to_string(&Answer::Active(Box::new(active))).unwrap();
+    assert_eq!(text, r#"{"answer":"active","version":"4.2.3","range":"~4.2.0","origin":{"kind":"shell","from":"4.2"}}"#);
+    let Answer::Active(back) = serde_json::from_str(&text).unwrap() else { panic!("not an active answer") };
+    assert_eq!((back.version, back.range, back.origin), (p("4.2.3"), r("~4.2"), Origin::Shell("4.2".to_owned())));
+    // a client's variables are only seen by the thread answering it, and gone after
+    use crate::utils::{env_var, with_rvm_vars};
+    let own = std::env::var("RVM_SHELL_VERSION").ok();
+    let vars = std::collections::BTreeMap::from([("RVM_SHELL_VERSION".to_owned(), "4.2".to_owned())]);
+    with_rvm_vars(&vars, || {
+      assert_eq!(env_var("RVM_SHELL_VERSION").as_deref(), Some("4.2"));
+      assert_eq!(env_var("RVM_HOME"), None);
+      assert_eq!(std::thread::spawn(|| env_var("RVM_SHELL_VERSION")).join().unwrap(), own);
+    });
+    assert_eq!(env_var("RVM_SHELL_VERSION"), own);
+  }
+
+  #[test]
+  fn hook_cache_entries() {
+    use std::time::{Duration, SystemTime};
+    use crate::hook_cache::{entry_path, entry_path_in, key, load_in, store_in};
+    use crate::resolve::{Active, Origin, ResolveError};
+    let root = tempfile::tempdir().unwrap();
+    let cache = root.path().join("hook");
+    let project_dir = root.path().join("proj");
+    std::fs::create_dir_all(project_dir.join("sub")).unwrap();
+    std::fs::write(project_dir.join("Renv.yaml"), "rversion: ~4.3\n").unwrap();
+    let dir = project_dir.join("sub");
+    assert!(entry_path(&dir).ends_with(dir.to_string_lossy().replace(std::path::MAIN_SEPARATOR, "%")));
+    let active = Active { version: p("4.3.1"), range: r("~4.3"), origin: Origin::Project(project_dir.join("Renv.yaml")), warnings: vec![] };
+    let resolved = Ok(active);
+    assert_eq!(key(&resolved), Some(format!("4.3.1 {}", project_dir.join("Renv.yaml").display())));
+    assert_eq!(key(&Err(ResolveError::System)), Some("system".to_owned()));
+    assert_eq!(key(&Err(ResolveError::NothingInstalled)), None);
+    // mtimes set rather than waited for, the entry dated ahead so the real versions dir and config it also depends on changing meanwhile don't matter
+    let touch = |path: &std::path::Path, time: SystemTime| std::fs::File::open(path).unwrap().set_modified(time).unwrap();
+    let written = SystemTime::now() + Duration::from_secs(3600);
+    for path in [project_dir.join("Renv.yaml"), project_dir.clone(), dir.clone()] {
+      touch(&path, written - Duration::from_secs(7200));
+    }
+    store_in(&cache, &dir, &dir, &resolved).unwrap();
+    touch(&entry_path_in(&cache, &dir), written);
+    assert_eq!(load_in(&cache, &dir).unwrap().unwrap().version, p("4.3.1"));
+    // the project file changing after the entry was written makes it stale
+    std::fs::write(project_dir.join("Renv.yaml"), "rversion: ~4.2\n").unwrap();
+    touch(&project_dir.join("Renv.yaml"), written + Duration::from_secs(1));
+    assert!(load_in(&cache, &dir).is_none());
+  }
+
+  #[test]
+  fn four_part_versions() {
+    use crate::parsing::version_parser::RangeKey;
+    // the extra version counts, it doesn't compare as text
+    assert!(p("4.3.1.10") > p("4.3.1.9"));
+    assert!(p("4.3.1.2.1") > p("4.3.1.2") && p("4.3.1.2") > p("4.3.1"));
+    assert_eq!(RangeKey(p("4.3.1.01")), RangeKey(p("4.3.1.1")));
+    let contains = |range: &str, version: &str| r(range).contains(&p(version));
+    // a version stands for the ones that only add to it
+    assert!(contains("<=4.3.1.4", "4.3.1.4.2") && !contains("<=4.3.1.4", "4.3.1.5"));
+    assert!(contains(">4.3.1.4", "4.3.1.5") && !contains(">4.3.1.4", "4.3.1.4.2"));
+    assert!(contains("<=4.3.1", "4.3.1.4") && !contains(">4.3.1", "4.3.1.4"));
+    assert!(contains("~4.3.1.4", "4.3.9") && !contains("~4.3.1.4", "4.3.1.3") && !contains("~4.3.1.4", "4.4.0"));
+    assert!(contains("^4.3.1.4", "4.9.0") && !contains("^4.3.1.4", "4.3.1"));
+    assert_eq!(r(">4.3.1.9").to_string(), ">=4.3.1.10");
+    let versions = [p("4.3.1.9"), p("4.3.1.10"), p("4.3.1.11"), p("4.3.2")];
+    assert_eq!(r("<=4.3.1.10").max_satisfying(&versions), Some(&p("4.3.1.10")));
+    assert_eq!(r("~4.3.1.9").max_satisfying(&versions), Some(&p("4.3.2")));
+    assert_eq!(r(">=4.3.1.9, !=4.3.1.9").minimal_version(), Some(p("4.3.1.10")));
+    // a word can't be counted up
+    assert!(!contains(">4.3.1.a", "4.3.1.b") && contains(">4.3.1.a", "4.3.2"));
+  }
+
+  #[test]
+  fn query_protocol() {
+    use crate::query::serve;
+    let input = [
+      r#"{"id":1,"query":"parse","range":">=4.2, <4.x"}"#,
+      r#"{"id":"b","query":"satisfies","version":"4.3.1","range":"~4.3"}"#,
+      "",
+      r#"{"query":"compare","a":"4.3.1.10","b":"4.3.1.9"}"#,
+      r#"{"id":4,"query":"nope"}"#,
+    ].join("\n");
+    let mut output = vec![];
+    serve(input.as_bytes(), &mut output, std::path::Path::new(".")).unwrap();
+    let replies: Vec<serde_json::Value> = String::from_utf8(output).unwrap().lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+    assert_eq!(replies.len(), 4);
+    assert_eq!(replies[0], serde_json::json!({"id": 1, "answer": "error", "message": "error in parsing range", "span": [8, 11]}));
+    assert_eq!(replies[1], serde_json::json!({"id": "b", "answer": "satisfies", "satisfies": true}));
+    assert_eq!(replies[2], serde_json::json!({"answer": "compared", "order": ">"}));
+    assert_eq!((&replies[3]["id"], &replies[3]["answer"]), (&serde_json::json!(4), &serde_json::json!("error")));
+  }
+
+  #[test]
+  fn none_marker_opts_out() {
+    use crate::constraint_file::ConstraintFile;
+    use crate::resolve::{find_project_file, none_marker, NONE_FILE, PROJECT_FILE};
+    let root = std::env::temp_dir().join(format!("rvm-none-{}", std::process::id()));
+    let (out, deeper) = (root.join("out"), root.join("out").join("deeper"));
+    std::fs::create_dir_all(&deeper).unwrap();
+    std::fs::write(root.join(PROJECT_FILE), "rversion: ~4.3.0\ndependencies: {}\n").unwrap();
+    assert_eq!(find_project_file(&deeper), Some(root.join(PROJECT_FILE)));
+    assert_eq!(none_marker(&deeper), None);
+    std::fs::write(out.join(NONE_FILE), "").unwrap();
+    assert_eq!((find_project_file(&deeper), ConstraintFile::find(&deeper)), (None, None));
+    assert_eq!(none_marker(&deeper), Some(out.join(NONE_FILE)));
+    // a project under the marker is one again
+    std::fs::write(deeper.join(PROJECT_FILE), "rversion: ~4.2.0\ndependencies: {}\n").unwrap();
+    assert_eq!((find_project_file(&deeper), none_marker(&deeper)), (Some(deeper.join(PROJECT_FILE)), None));
+    assert_eq!(find_project_file(&root), Some(root.join(PROJECT_FILE)));
+    std::fs::remove_dir_all(&root).unwrap();
+  }
+
+  #[test]
+  fn release_notes_text() {
+    use crate::index::{notes_text, CranSource};
+    assert_eq!(notes_text("  BUG FIXES\n\n* one\n"), "BUG FIXES\n\n* one");
+    let page = "<html><head><title>NEWS</title></head><body>\n<h2>CHANGES IN R 4.3.3</h2>\n\n\n<ul>\n<li>Fixed &amp; <code>x &lt; y</code></li>\n</ul>\n</body></html>";
+    assert_eq!(notes_text(page), "CHANGES IN R 4.3.3\n\nFixed & x < y");
+    let listing = CranSource::default().parse_listing(r#"<a href="4.3.1/">R 4.3.1</a>"#);
+    assert_eq!(listing[0].notes_url.as_deref(), Some("https://cran.r-project.org/bin/windows/base/old/4.3.1/NEWS.R-4.3.1.html"));
+  }
+
+  #[test]
+  fn startup_check() {
+    use std::path::PathBuf;
+    use crate::install::startup::{ldd_missing, otool_missing};
+    let ldd = "\tlinux-vdso.so.1 (0x00007ffd)\n\tlibR.so => /opt/R/lib/libR.so (0x00007f)\n\tlibgfortran.so.5 => not found\n\tlibc.so.6 => /lib/x86_64-linux-gnu/libc.so.6 (0x00007f)\n";
+    assert_eq!(ldd_missing(ldd), vec!["libgfortran.so.5"]);
+    assert!(ldd_missing("\tnot a dynamic executable\n").is_empty());
+    let dir = std::env::temp_dir().join(format!("rvm-test-startup-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("lib")).unwrap();
+    std::fs::write(dir.join("lib/libR.dylib"), "").unwrap();
+    let otool = "/x/bin/exec/R:\n\t@rpath/libR.dylib (compatibility version 4.3.0)\n\t@rpath/libgfortran.5.dylib (compatibility version 6.0.0)\n\t/usr/lib/libSystem.B.dylib (compatibility version 1.0.0)\n\t/opt/gfortran/lib/libquadmath.0.dylib (compatibility version 1.0.0)\n";
+    assert_eq!(otool_missing(otool, &[dir.join("lib")]), vec!["@rpath/libgfortran.5.dylib", "/opt/gfortran/lib/libquadmath.0.dylib"]);
+    #[cfg(unix)]
+    {
+      use std::os::unix::fs::PermissionsExt;
+      use crate::install::startup::{check, executable};
+      use crate::install::InstallError;
+      assert_eq!(executable(&dir), None);
+      assert!(check(&dir).is_ok());
+      let exe = dir.join("bin/exec/R");
+      std::fs::create_dir_all(exe.parent().unwrap()).unwrap();
+      std::fs::write(&exe, "#!/bin/sh\necho \"R home is $R_HOME\"\nexit 0\n").unwrap();
+      std::fs::set_permissions(&exe, std::fs::Permissions::from_mode(0o755)).unwrap();
+      assert_eq!(executable(&dir), Some(exe.clone()));
+      assert!(check(&dir).is_ok());
+      std::fs::write(&exe, "#!/bin/sh\necho 'error while loading shared libraries: libRblas.so'\nexit 127\n").unwrap();
+      match check(&dir) {
+        Err(InstallError::Startup(path, reason)) => {
+          assert_eq!(path, exe);
+          assert_eq!(reason, "--version exited with 127: error while loading shared libraries: libRblas.so");
+        },
+        other => panic!("expected a startup error, got {:?}", other.map(|_| PathBuf::new())),
+      }
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn disk_budget() {
+    use std::collections::BTreeMap;
+    use crate::install::budget::{check, estimate, make_room, Budget, Candidate};
+    let sizes: BTreeMap<Version, u64> = [(p("4.2.0"), 200), (p("4.3.1"), 300)].into_iter().collect();
+    assert_eq!(estimate(&p("4.3.3"), &sizes), 300);
+    assert_eq!(estimate(&p("4.2.5"), &sizes), 200);
+    assert_eq!(estimate(&p("4.1.0"), &sizes), 200);
+    assert_eq!(estimate(&p("4.1.0"), &BTreeMap::new()), 0);
+    assert_eq!(check(0, &[p("4.4.0")]), None);
+    let budget = Budget { limit: 1000, used: 800, adding: 300, candidates: vec![] };
+    assert!(budget.is_over());
+    assert!(!Budget { adding: 200, ..budget }.is_over());
+    let candidate = |v: &str, size, unused_since| Candidate { version: p(v), size, unused_since };
+    let unused = vec![candidate("4.3.1", 300, Some(1_710_000_000)), candidate("4.1.0", 50, None), candidate("4.2.0", 200, Some(1_700_000_000))];
+    let picked: Vec<Version> = make_room(unused.clone(), 100).into_iter().map(|c| c.version).collect();
+    assert_eq!(picked, vec![p("4.1.0"), p("4.2.0")]);
+    assert_eq!(make_room(unused.clone(), 50).len(), 1);
+    assert_eq!(make_room(unused, 10_000).len(), 3);
+  }
+
+  #[test]
+  fn install_origin() {
+    use std::path::PathBuf;
+    use crate::install::git::GitOrigin;
+    use crate::install::meta::{InstallMeta, InstallOrigin};
+    assert_eq!(InstallMeta::default().origin(), None);
+    let downloaded = InstallMeta { url: Some("https://cran.r-project.org/R-4.3.1-win.exe".to_owned()), sha256: Some("ab".to_owned()), ..InstallMeta::default() };
+    let origin = downloaded.origin().unwrap();
+    assert_eq!(origin.to_string(), "https://cran.r-project.org/R-4.3.1-win.exe");
+    assert_eq!(serde_json::to_value(&origin).unwrap(), serde_json::json!({"kind": "registry", "url": "https://cran.r-project.org/R-4.3.1-win.exe", "sha256": "ab"}));
+    let git = GitOrigin { url: "https://github.com/wch/r-source".to_owned(), reference: "R-4-3-1".to_owned() };
+    let built = InstallMeta { git: Some(git), ..downloaded.clone() };
+    assert_eq!(built.origin().unwrap().to_string(), "https://github.com/wch/r-source@R-4-3-1");
+    let registered = InstallMeta { registered_from: Some(PathBuf::from("/opt/R-4.3.1.tar.gz")), ..downloaded };
+    assert_eq!(registered.origin(), Some(InstallOrigin::Local { path: PathBuf::from("/opt/R-4.3.1.tar.gz") }));
+  }
+
+  #[test]
+  fn cancellation() {
+    use std::io::Read;
+    use crate::cancel::CancellationToken;
+    use crate::install::{Downloader, HttpDownloader, InstallError};
+    let token = CancellationToken::new();
+    let clone = token.clone();
+    let mut reader = token.reader(&b"abc"[..]);
+    let mut buf = [0; 1];
+    assert_eq!(reader.read(&mut buf).unwrap(), 1);
+    assert!(token.check().is_ok());
+    clone.cancel();
+    assert!(token.is_cancelled());
+    assert!(reader.read(&mut buf).is_err());
+    assert!(matches!(token.check(), Err(InstallError::Cancelled)));
+    // a cancelled download doesn't even connect
+    let download = HttpDownloader.download("http://127.0.0.1:9/R.exe", "rvm-test-cancelled", &|_, _| {}, &token);
+    assert!(matches!(download, Err(InstallError::Cancelled)));
+    assert!(!std::env::temp_dir().join("rvm-test-cancelled").exists());
+    #[cfg(unix)]
+    {
+      let token = CancellationToken::new();
+      let canceller = token.clone();
+      let started = std::time::Instant::now();
+      std::thread::spawn(move || {
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        canceller.cancel();
+      });
+      assert!(crate::cancel::run(duct::cmd!("sleep", "10"), &token).is_err());
+      assert!(started.elapsed() < std::time::Duration::from_secs(5));
+      assert!(crate::cancel::run(duct::cmd!("true"), &CancellationToken::new()).is_ok());
+    }
+  }
+
+  #[test]
+  fn detect_manifests() {
+    use crate::detect::{detect, translate, Description, Detector, Gemfile, PackageJson, PyProject};
+    use crate::parsing::version_parser::Syntax;
+    let description = "Package: x\nDepends:\n    methods,\n    R (>= 4.1.0)\nImports: utils\n";
+    assert_eq!(Description.constraint(description).as_deref(), Some(">= 4.1.0"));
+    assert_eq!(Description.constraint("Depends: R, methods\n"), None);
+    assert_eq!(Gemfile.constraint("source \"https://rubygems.org\"\nruby '~> 3.2', '>= 3.2.1'\n").as_deref(), Some("~> 3.2, >= 3.2.1"));
+    assert_eq!(Gemfile.constraint("ruby file: \".ruby-version\"\n"), None);
+    assert_eq!(PyProject.constraint("[project]\nrequires-python = \">=3.9\"\n").as_deref(), Some(">=3.9"));
+    assert_eq!(PyProject.constraint("[tool.poetry.dependencies]\npython = \"^3.10\"\n").as_deref(), Some("^3.10"));
+    assert_eq!(PackageJson.constraint(r#"{"engines": {"node": ">=18 <21"}}"#).as_deref(), Some(">=18 <21"));
+    let same = |constraint: &str, syntax: Syntax, rvm: &str| assert_eq!(translate(constraint, syntax).unwrap(), rvm.parse::<Range>().unwrap(), "{}", constraint);
+    same(">= 4.1.0", Syntax::Cargo, ">=4.1.0");
+    #[cfg(feature = "ruby")] {
+      same("~> 3.2", Syntax::Ruby, ">=3.2.0, <4.0.0");
+      same("~> 3.2.1", Syntax::Ruby, ">=3.2.1, <3.3.0");
+      same("3.2.2", Syntax::Ruby, "=3.2.2");
+    }
+    #[cfg(feature = "pep440")] {
+      same("~=3.9", Syntax::Pep440, ">=3.9.0, <4.0.0");
+      same("==3.11.*", Syntax::Pep440, ">=3.11.0, <3.12.0");
+      same(">=3.9, <3.13", Syntax::Pep440, ">=3.9.0, <3.13.0");
+    }
+    #[cfg(feature = "npm")] {
+      same("18.x", Syntax::Npm, ">=18.0.0, <19.0.0");
+      same(">=18 <21", Syntax::Npm, ">=18.0.0, <21.0.0");
+      same("16 - 18.2.1", Syntax::Npm, ">=16.0.0, <=18.2.1");
+      same("16 - 18.2", Syntax::Npm, ">=16.0.0, <18.3.0");
+      same("16 - 18", Syntax::Npm, ">=16.0.0, <19.0.0");
+      same("16 - 18.x", Syntax::Npm, ">=16.0.0, <19.0.0");
+      same("16 - *", Syntax::Npm, ">=16.0.0");
+      same(">= 18", Syntax::Npm, ">=18.0.0");
+      same(">= 18 < 21", Syntax::Npm, ">=18.0.0, <21.0.0");
+    }
+    // what this build can't read says so
+    for (syntax, supported) in [(Syntax::Ruby, cfg!(feature = "ruby")), (Syntax::Pep440, cfg!(feature = "pep440")), (Syntax::Npm, cfg!(feature = "npm"))] {
+      if !supported {
+        assert_eq!(translate("1.0", syntax), Err(ParseError::Unsupported(syntax).to_string()));
+      }
+    }
+    assert!(translate(">=14 || >=16", Syntax::Npm).is_err());
+    let dir = std::env::temp_dir().join(format!("rvm-test-detect-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("DESCRIPTION"), description).unwrap();
+    std::fs::write(dir.join("package.json"), r#"{"engines": {"node": ">=14 || >=16"}}"#).unwrap();
+    let detected = detect(&dir);
+    assert_eq!(detected.iter().map(|d| d.tool.as_str()).collect::<Vec<_>>(), ["R", "node"]);
+    assert_eq!(detected[0].range, Some(">=4.1.0".parse::<Range>().unwrap()));
+    assert!(detected[1].range.is_none() && detected[1].error.is_some());
+    std::fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn new_project_ci_files() {
+    use crate::actions::ci_snippet::ci_file;
+    use crate::args::SnippetFormat;
+    let (path, workflow) = ci_file(SnippetFormat::GithubActions, &p("4.3.1"));
+    assert_eq!(path, ".github/workflows/r.yml");
+    let workflow: serde_yaml::Value = serde_yaml::from_str(&workflow).unwrap();
+    let steps = workflow["jobs"]["check"]["steps"].as_sequence().unwrap();
+    assert!(steps.iter().any(|step| step["run"].as_str() == Some("rvm install =4.3.1")));
+    assert_eq!(workflow["jobs"]["check"]["env"]["RVM_LOCKED"].as_str(), Some("1"));
+    let (path, gitlab) = ci_file(SnippetFormat::Gitlab, &p("4.3.1"));
+    assert_eq!(path, ".gitlab-ci.yml");
+    let gitlab: serde_yaml::Value = serde_yaml::from_str(&gitlab).unwrap();
+    assert!(gitlab["check"]["script"].as_sequence().is_some());
+    let (path, docker) = ci_file(SnippetFormat::Dockerfile, &p("4.3.1"));
+    assert_eq!(path, "Dockerfile");
+    assert!(docker.starts_with("FROM ") && docker.contains("rvm install =4.3.1"));
+  }
+
+  #[test]
+  fn dialect_features() {
+    use crate::error_code::ErrorCode;
+    use crate::parsing::version_parser::{RenderError, Syntax};
+    assert!(Syntax::Cargo.is_supported() && Syntax::Cargo.feature().is_none());
+    assert!(Version::supported_dialects().contains(&Syntax::Cargo));
+    #[cfg(all(feature = "npm", feature = "pep440", feature = "ruby"))]
+    assert_eq!(Version::supported_dialects(), Syntax::ALL.to_vec());
+    #[cfg(not(feature = "pep440"))] {
+      assert!(!Version::supported_dialects().contains(&Syntax::Pep440));
+      assert_eq!(r("=4.3.1").display_as(Syntax::Pep440), Err(RenderError::Unsupported(Syntax::Pep440)));
+    }
+    assert_eq!(Syntax::Pep440.feature(), Some("pep440"));
+    let unsupported = ParseError::Unsupported(Syntax::Pep440);
+    assert_eq!(unsupported.to_string(), "error in parsing range, PEP 440 syntax requires feature pep440");
+    assert_eq!(unsupported.code(), "E_UNSUPPORTED_SYNTAX");
+    assert_eq!(RenderError::Unsupported(Syntax::Npm).to_string(), "npm syntax requires feature npm");
+    assert_eq!(RenderError::Lossy(Syntax::Cargo, "holes".to_owned()).code(), "E_RENDER");
+  }
+
+  #[test]
+  fn env_flags() {
+    use crate::utils::env_flag;
+    // a name no other test sets, they run in parallel
+    let name = format!("RVM_TEST_FLAG_{}", std::process::id());
+    assert!(!env_flag(&name));
+    for off in ["", "0", "false", "FALSE", "f", "no", "No", "n", "off", "OFF"] {
+      std::env::set_var(&name, off);
+      assert!(!env_flag(&name), "{:?}", off);
+    }
+    for on in ["1", "true", "yes", "on", "y", "anything"] {
+      std::env::set_var(&name, on);
+      assert!(env_flag(&name), "{:?}", on);
+    }
+    std::env::remove_var(&name);
+  }
+
+  #[test]
+  fn signing_key_trust() {
+    use crate::error_code::ErrorCode;
+    use crate::index::{parse_index_document, IndexDocument};
+    use crate::trust::{fingerprint, payload, KnownKeys, Signed, Trust, TrustError};
+    let secret = "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60";
+    let versions = vec![crate::index::parse_index(r#"[{"version": "4.3.1", "url": "https://r.example.com/R-4.3.1.tar.gz"}]"#).unwrap().remove(0)];
+    let signed = Signed::sign(secret, payload(&serde_json::to_value(&versions).unwrap())).unwrap();
+    assert!(signed.key.starts_with("ed25519:d75a980182b10ab7"));
+    let document = IndexDocument { signing_key: Some(signed.key.clone()), signature: Some(signed.signature.clone()), ..IndexDocument::new(versions) };
+    // what's signed reads back the same from json and toml
+    for text in [serde_json::to_string_pretty(&document).unwrap(), toml::to_string_pretty(&document).unwrap()] {
+      let read = parse_index_document(&text).unwrap().signed().unwrap();
+      assert_eq!(read.payload, signed.payload);
+      assert!(read.verify().is_ok());
+    }
+    let tampered = serde_json::to_string(&document).unwrap().replace("R-4.3.1.tar.gz", "evil.tar.gz");
+    assert!(parse_index_document(&tampered).unwrap().signed().unwrap().verify().is_err());
+    assert!(Signed { key: "RWQf6LRCGA9i5".to_owned(), ..signed.clone() }.verify().is_err());
+    assert!(parse_index_document(r#"{"schema_version": 1, "signing_key": "ed25519:00", "versions": []}"#).unwrap().signed().is_none());
+    let key = signed.key;
+    let mut keys = KnownKeys::default();
+    assert_eq!(keys.check_unsigned("corp", false).unwrap(), Trust::Unsigned);
+    assert_eq!(keys.check("corp", &key, false).unwrap(), Trust::FirstUse);
+    assert_eq!(keys.registries["corp"].fingerprint, fingerprint(&key));
+    assert_eq!(keys.check("corp", &format!(" {} ", key), false).unwrap(), Trust::Known);
+    // each registry has its own
+    assert_eq!(keys.check("other", "ed25519:new", false).unwrap(), Trust::FirstUse);
+    let changed = keys.check("corp", "ed25519:new", false).unwrap_err();
+    assert!(matches!(&changed, TrustError::KeyChanged { registry, .. } if registry == "corp"));
+    assert_eq!(changed.code(), "E_KEY_CHANGED");
+    assert!(changed.to_string().contains("--trust-new-key"));
+    assert_eq!(keys.registries["corp"].fingerprint, fingerprint(&key));
+    // once signed, a listing without the signature fails too
+    let dropped = keys.check_unsigned("corp", false).unwrap_err();
+    assert!(matches!(&dropped, TrustError::Unsigned { registry, .. } if registry == "corp"));
+    assert_eq!(dropped.code(), "E_SIGNATURE");
+    assert_eq!(keys.check("corp", "ed25519:new", true).unwrap(), Trust::Replaced);
+    assert_eq!(keys.check("corp", "ed25519:new", false).unwrap(), Trust::Known);
+    assert_eq!(keys.check_unsigned("other", true).unwrap(), Trust::Dropped);
+    assert!(!keys.registries.contains_key("other"));
+    let json = serde_json::to_string(&keys).unwrap();
+    assert_eq!(serde_json::from_str::<KnownKeys>(&json).unwrap(), keys);
+  }
+
+  #[test]
+  fn range_round_trips() {
+    // every range made of these bounds, holes and extra versions prints as something that parses back to it,
+    // and is made again from its comparators
+    let versions = ["0.2.3", "1.0.0", "1.2.0", "1.2.3", "1.3.0", "2.0.0", "4.3.0-rc.1", "4.3.0", "4.3.1.1", "4.4.0", "5.0.0"];
+    let versions: Vec<Version> = versions.iter().map(|v| p(v)).collect();
+    let optional: Vec<Option<&Version>> = std::iter::once(None).chain(versions.iter().map(Some)).collect();
+    let mut checked = 0;
+    for min in &optional {
+      for max in optional.iter().filter(|max| min.zip(**max).map_or(true, |(min, max)| min < max)) {
+        for except in std::iter::once(vec![]).chain(versions.iter().map(|v| vec![v.clone()])) {
+          for include in [vec![], vec![versions[3].clone()], vec![versions[7].clone(), versions[9].clone()]] {
+            let range = Range { min: min.cloned(), max: max.cloned(), except: except.clone(), include, ..Range::default() };
+            let written = range.to_string();
+            assert_eq!(Range::parse(&written).as_ref(), Ok(&range), "{:?} printed as {}", range, written);
+            assert_eq!(Range::from_comparators(range.comparators()).as_ref(), Ok(&range), "{:?}", range);
+            checked += 1;
+          }
+        }
+      }
+    }
+    assert!(checked > 1000);
+    for written in ["^1.2.3", "~1.2.3", "^0.2.3", "~0.2", ">=1.2.3, <1.2.5", ">=4.3.0-rc.1, <4.4.0", "=4.3.1.1", "*"] {
+      let range = r(written);
+      assert_eq!(r(&range.to_string()), range, "{} printed as {}", written, range);
+      assert_eq!(Range::from_comparators(range.comparators()).unwrap(), range, "{}", written);
+    }
+  }
+
+  #[test]
+  fn verify_install() {
+    use crate::install::verify::{check, record, sums_path};
+    let version = p(&format!("0.0.0-verify.{}.0", std::process::id()));
+    let dir = std::env::temp_dir().join(format!("rvm-verify-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("bin")).unwrap();
+    std::fs::write(dir.join("bin/R"), "#!/bin/sh").unwrap();
+    std::fs::write(dir.join("COPYING"), "GPL").unwrap();
+    assert!(check(&version, &dir).unwrap().is_none());
+    record(&version, &dir).unwrap();
+    let intact = check(&version, &dir).unwrap().unwrap();
+    assert_eq!((intact.checked, intact.is_damaged()), (2, false));
+    std::fs::write(dir.join("bin/R"), "#!/bin/sx").unwrap();
+    std::fs::remove_file(dir.join("COPYING")).unwrap();
+    std::fs::write(dir.join("added"), "").unwrap();
+    let damaged = check(&version, &dir).unwrap().unwrap();
+    assert_eq!((damaged.modified, damaged.missing, damaged.added), (vec!["bin/R".to_owned()], vec!["COPYING".to_owned()], vec!["added".to_owned()]));
+    std::fs::remove_dir_all(&dir).unwrap();
+    std::fs::remove_file(sums_path(&version)).unwrap();
+  }
+
+  #[test]
+  fn verify_across_versions() {
+    use crate::cancel::CancellationToken;
+    use crate::install::NoReporter;
+    use crate::install::verify::{check_many, record_in};
+    use crate::layout::version_names;
+    let root = tempfile::tempdir().unwrap();
+    let meta = root.path().join(".meta");
+    let versions = [p("4.3.1"), p("4.2.3")];
+    for version in &versions {
+      let dir = root.path().join(version_names().name(version));
+      std::fs::create_dir_all(dir.join("bin")).unwrap();
+      for i in 0..20 {
+        std::fs::write(dir.join("bin").join(format!("f{}", i)), format!("{} {}", version, i)).unwrap();
+      }
+      record_in(&meta, version, &dir).unwrap();
+    }
+    // the same size, only hashing tells it changed
+    std::fs::write(root.path().join(version_names().name(&versions[1])).join("bin/f3"), format!("{} 4", versions[1])).unwrap();
+    let reports = check_many(root.path(), &versions, 3, &NoReporter, &CancellationToken::new());
+    let reports: Vec<_> = reports.into_iter().map(|r| r.unwrap().unwrap()).collect();
+    assert_eq!((reports[0].checked, reports[0].is_damaged()), (20, false));
+    assert_eq!(reports[1].modified, ["bin/f3"]);
+  }
+
+  #[test]
+  fn resolution_order() {
+    use crate::lockfile::FORMAT;
+    use crate::resolve::{explain_range, Origin, Source};
+    assert_eq!(Source::default_order(), vec![Source::Env, Source::Shell, Source::Project, Source::Lockfile, Source::Default, Source::Latest]);
+    let dir = std::env::temp_dir().join(format!("rvm-resolution-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let project = dir.join("Renv.yaml");
+    std::fs::write(&project, "rversion: ~4.3.0\ndependencies: {}\n").unwrap();
+    assert_eq!(explain_range(&dir).0.unwrap(), (r("~4.3.0"), Origin::Project(project.clone())));
+    // the project's range, pinned by its lockfile while they agree
+    let lock = ProjectLock { format: FORMAT, range: r("~4.3.0"), version: p("4.3.1"), url: None, sha256: None };
+    lock.save(&ProjectLock::path_for(&project)).unwrap();
+    let (range, steps) = explain_range(&dir);
+    assert_eq!(range.unwrap().1, Origin::Lockfile(ProjectLock::path_for(&project)));
+    assert!(steps.iter().any(|step| step.source == Source::Project && !step.used));
+    std::fs::write(&project, "rversion: ~4.4.0\ndependencies: {}\n").unwrap();
+    assert_eq!(explain_range(&dir).0.unwrap().1, Origin::Project(project.clone()));
+    std::fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn env_ranges() {
+    use crate::lockfile::FORMAT;
+    use crate::resolve::{env_range, Origin, ResolveError};
+    let dir = std::env::temp_dir().join(format!("rvm-env-range-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let project = dir.join("Renv.yaml");
+    std::fs::write(&project, "rversion: ~4.3.0\ndependencies: {}\n").unwrap();
+    // unlocked the range is used as is, locked only an exact version is
+    assert_eq!(env_range("~4.3.0".to_owned(), false, &dir).unwrap().unwrap(), (r("~4.3.0"), Origin::Env("~4.3.0".to_owned())));
+    assert_eq!(env_range("=4.3.1".to_owned(), true, &dir).unwrap().unwrap(), (r("=4.3.1"), Origin::Env("=4.3.1".to_owned())));
+    assert!(matches!(env_range("~4.3.0".to_owned(), true, &dir), Err(ResolveError::Drift(_))));
+    // with a lockfile the locked version is picked from the range, if it's in it
+    let lock = ProjectLock { format: FORMAT, range: r("~4.3.0"), version: p("4.3.1"), url: None, sha256: None };
+    lock.save(&ProjectLock::path_for(&project)).unwrap();
+    assert_eq!(env_range("~4.3.0".to_owned(), true, &dir).unwrap().unwrap(), (r("=4.3.1"), Origin::Lockfile(ProjectLock::path_for(&project))));
+    assert!(matches!(env_range("~4.2.0".to_owned(), true, &dir), Err(ResolveError::Drift(_))));
+    assert_eq!(env_range("~4.2.0".to_owned(), false, &dir).unwrap().unwrap().0, r("~4.2.0"));
+    std::fs::remove_dir_all(&dir).unwrap();
+  }
+
   //TODO test abput comparing versions
   //TODO test about ranging versions
 }
\ No newline at end of file
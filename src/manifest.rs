@@ -0,0 +1,166 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use crate::alias::{AliasError, Aliases};
+use crate::install::git::GitOrigin;
+use crate::install::installed_versions;
+use crate::install::meta::InstallMeta;
+use crate::parsing::version_parser::Version;
+use crate::plugin::{tool_versions, tools_root};
+
+// everything installed on a machine, so `rvm import env.toml` can set up the same on another one
+// bump FORMAT when a field changes meaning, adding fields doesn't need it
+pub const FORMAT: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+  pub format: u32,
+  /// what the "default" alias points to
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub default: Option<String>,
+  /// every other alias
+  #[serde(default)]
+  pub aliases: BTreeMap<String, String>,
+  /// versions of each plugin's tool
+  #[serde(default)]
+  pub tools: BTreeMap<String, Vec<Version>>,
+  #[serde(default)]
+  pub installs: Vec<ManifestInstall>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestInstall {
+  pub version: Version,
+  #[serde(default)]
+  pub pinned: bool,
+  /// built with `rvm install --git`, built the same way again
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub git: Option<GitOrigin>,
+  /// registered with `rvm install --path`, only reproducible where that path exists too
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub path: Option<PathBuf>,
+}
+
+#[derive(Error, Debug)]
+pub enum ManifestError {
+  #[error("error in reading manifest")]
+  Io(#[from] std::io::Error),
+  #[error("error in parsing manifest")]
+  De(#[from] toml::de::Error),
+  #[error("error in writing manifest")]
+  Ser(#[from] toml::ser::Error),
+  #[error(transparent)]
+  Aliases(#[from] AliasError),
+  #[error("manifest format {0} is newer than this rvm understands ({FORMAT}), update rvm")]
+  Format(u32),
+}
+
+impl Manifest {
+  /// What's on this machine now
+  pub fn current() -> Result<Self, ManifestError> {
+    let mut aliases = Aliases::load()?.aliases;
+    let default = aliases.remove("default");
+    let installs = installed_versions().into_iter().rev().map(|version| {
+      let meta = InstallMeta::load(&version);
+      ManifestInstall { pinned: meta.pinned, git: meta.git, path: meta.registered_from, version }
+    }).collect();
+    let tools = match tools_root().read_dir() {
+      Ok(entries) => entries.filter_map(|e| e.ok())
+        .map(|e| e.file_name().to_string_lossy().to_string())
+        .map(|name| { let versions = tool_versions(&name); (name, versions) })
+        .filter(|(_, versions)| !versions.is_empty())
+        .collect(),
+      Err(_) => BTreeMap::new(),
+    };
+    Ok(Self { format: FORMAT, default, aliases, tools, installs })
+  }
+
+  pub fn load(path: &Path) -> Result<Self, ManifestError> {
+    let manifest: Self = toml::from_str(&std::fs::read_to_string(path)?)?;
+    if manifest.format > FORMAT {
+      return Err(ManifestError::Format(manifest.format));
+    }
+    Ok(manifest)
+  }
+
+  pub fn to_toml(&self) -> Result<String, ManifestError> {
+    Ok(toml::to_string(self)?)
+  }
+}
+
+// what differs between two manifests, e.g. a developer's machine and a CI image's, keyed so that what's in both but
+// different shows up as changed: installs by version, aliases by name (the default one as "default"), tools by name
+// and version, a tool version is only ever added or removed
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "change", rename_all = "lowercase")]
+pub enum Difference<T> {
+  Added { key: String, new: T },
+  Removed { key: String, old: T },
+  Changed { key: String, old: T, new: T },
+}
+
+impl<T> Difference<T> {
+  pub fn key(&self) -> &str {
+    match self {
+      Difference::Added { key, .. } | Difference::Removed { key, .. } | Difference::Changed { key, .. } => key,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ManifestDiff {
+  pub installs: Vec<Difference<ManifestInstall>>,
+  pub aliases: Vec<Difference<String>>,
+  pub tools: Vec<Difference<Version>>,
+}
+
+impl ManifestDiff {
+  pub fn is_empty(&self) -> bool {
+    self.installs.is_empty() && self.aliases.is_empty() && self.tools.is_empty()
+  }
+}
+
+impl Manifest {
+  /// What changed from self to new
+  pub fn diff(&self, new: &Manifest) -> ManifestDiff {
+    let installs = |m: &Manifest| m.installs.iter().map(|i| (i.version.to_string(), i.clone())).collect();
+    let aliases = |m: &Manifest| m.aliases.clone().into_iter().chain(m.default.clone().map(|d| ("default".to_owned(), d))).collect();
+    let tools = |m: &Manifest| m.tools.iter()
+      .flat_map(|(tool, versions)| versions.iter().map(move |v| (format!("{} {}", tool, v), v.clone())))
+      .collect();
+    ManifestDiff {
+      installs: diff_maps(&installs(self), &installs(new)),
+      aliases: diff_maps(&aliases(self), &aliases(new)),
+      tools: diff_maps(&tools(self), &tools(new)),
+    }
+  }
+}
+
+// by key, removed and changed in the order of old's keys, then what's added
+fn diff_maps<T: Clone + PartialEq>(old: &BTreeMap<String, T>, new: &BTreeMap<String, T>) -> Vec<Difference<T>> {
+  let mut ret: Vec<Difference<T>> = old.iter().filter_map(|(key, was)| match new.get(key) {
+    None => Some(Difference::Removed { key: key.clone(), old: was.clone() }),
+    Some(now) if now != was => Some(Difference::Changed { key: key.clone(), old: was.clone(), new: now.clone() }),
+    Some(_) => None,
+  }).collect();
+  ret.extend(new.iter().filter(|(key, _)| !old.contains_key(*key)).map(|(key, now)| Difference::Added { key: key.clone(), new: now.clone() }));
+  ret
+}
+
+impl std::fmt::Display for ManifestInstall {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}", self.version)?;
+    if self.pinned {
+      write!(f, " pinned")?;
+    }
+    if let Some(git) = &self.git {
+      write!(f, " from {}@{}", git.url, git.reference)?;
+    }
+    match &self.path {
+      Some(path) => write!(f, " from {}", path.display()),
+      None => Ok(()),
+    }
+  }
+}
@@ -0,0 +1,165 @@
+// async versions of the VersionSource and Downloader apis, for GUIs or servers that can't block a thread
+// only compiled with the "async" feature, the cli itself stays blocking
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use lazy_static::lazy_static;
+use reqwest::header::{IF_MODIFIED_SINCE, IF_NONE_MATCH};
+use reqwest::StatusCode;
+use tokio::io::AsyncWriteExt;
+use crate::auth::WithAuth;
+use crate::config::{Config, Mirror};
+use crate::index::{answer_from_cache, is_offline, store_fetched, ArtifactMeta, CachePolicy, CranSource, Fetched, IndexCache, IndexError, Validators};
+use crate::cancel::CancellationToken;
+use crate::hooks::{self, Event};
+use crate::install::{before_download, finish, installer_args, installer_name, skip_installed, stage, InstallError, Reporter};
+use crate::local_utils::configured_proxy;
+use crate::policy::Policy;
+
+// boxed because async fn in traits needs a newer rust than rust-version
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+lazy_static!{
+  // async http client, same proxy settings as the blocking one
+  pub static ref ASYNC_CLIENT: reqwest::Client = {
+    let mut builder = reqwest::Client::builder();
+    if let Some(proxy) = configured_proxy(&Config::get()) {
+      builder = builder.proxy(proxy);
+    }
+    builder.build().unwrap_or_else(|err| panic!("Failed to build http client: {}", err))
+  };
+}
+
+pub trait AsyncVersionSource: Sync {
+  fn name(&self) -> &str;
+  fn fetch<'a>(&'a self, validators: &'a Validators) -> BoxFuture<'a, Result<Fetched, IndexError>>;
+}
+
+pub trait AsyncDownloader: Sync {
+  /// Downloads url (after mirror rewriting) to path, a cancelled download stops between chunks
+  fn download<'a>(&'a self, url: &'a str, path: &'a Path, cancel: &'a CancellationToken) -> BoxFuture<'a, Result<(), InstallError>>;
+}
+
+impl AsyncVersionSource for CranSource {
+  fn name(&self) -> &str {
+    "cran"
+  }
+
+  fn fetch<'a>(&'a self, validators: &'a Validators) -> BoxFuture<'a, Result<Fetched, IndexError>> {
+    Box::pin(async move {
+      let url = format!("{}/old/", self.base_url);
+      let mut request = ASYNC_CLIENT.get(&url).with_auth(&url);
+      if let Some(etag) = &validators.etag {
+        request = request.header(IF_NONE_MATCH, etag);
+      }
+      if let Some(last_modified) = &validators.last_modified {
+        request = request.header(IF_MODIFIED_SINCE, last_modified);
+      }
+      let response = request.send().await?;
+      let status = response.status();
+      let new_validators = Validators::from_headers(response.headers());
+      // only read the body when it's needed
+      let body = if status.is_success() { Some(response.text().await?) } else { None };
+      self.read_answer(status, new_validators, || Ok(body.unwrap_or_default()))
+    })
+  }
+}
+
+pub struct AsyncHttpDownloader;
+
+impl AsyncDownloader for AsyncHttpDownloader {
+  fn download<'a>(&'a self, url: &'a str, path: &'a Path, cancel: &'a CancellationToken) -> BoxFuture<'a, Result<(), InstallError>> {
+    Box::pin(async move {
+      cancel.check()?;
+      let url = Mirror::rewrite(&Config::get().mirrors, url);
+      if is_offline() {
+        return Err(InstallError::Offline(url));
+      }
+      Policy::get().check_url(&url)?;
+      let mut response = ASYNC_CLIENT.get(&url).with_auth(&url).send().await?;
+      if response.status() != StatusCode::OK {
+        return Err(InstallError::Status(url, response.status()));
+      }
+      let mut file = tokio::fs::File::create(path).await?;
+      while let Some(chunk) = response.chunk().await? {
+        cancel.check()?;
+        file.write_all(&chunk).await?;
+      }
+      file.flush().await?;
+      Ok(())
+    })
+  }
+}
+
+/// Same as index::remote_versions, the cache files are shared
+pub async fn remote_versions(source: &dyn AsyncVersionSource, policy: CachePolicy) -> Result<Vec<ArtifactMeta>, IndexError> {
+  let cache = IndexCache::load_named(source.name());
+  if let Some(answer) = answer_from_cache(source.name(), &cache, policy) {
+    return answer;
+  }
+  let validators = cache.as_ref().map(|c| c.validators.clone()).unwrap_or_default();
+  let fetched = source.fetch(&validators).await;
+  store_fetched(source.name(), cache, fetched)
+}
+
+/// Same as install::install with the prebuilt installer, without blocking while downloading or waiting for the
+/// installer. Unlike it this runs the install hooks too, there's no cli around it to run them: a failing pre_install
+/// hook stops it, a failing post_install one is only logged
+pub async fn install(meta: &ArtifactMeta, downloader: &dyn AsyncDownloader, reporter: &dyn Reporter, cancel: &CancellationToken) -> Result<PathBuf, InstallError> {
+  if let Some(dest) = skip_installed(meta, reporter) {
+    return Ok(dest);
+  }
+  // whatever failed once it was cancelled failed because of it, like install_many says
+  let result = install_missing(meta, downloader, reporter, cancel).await.map_err(|err| if cancel.is_cancelled() { InstallError::Cancelled } else { err });
+  if result.is_ok() {
+    if let Err(err) = hooks::run(Event::PostInstall, &meta.version) {
+      tracing::warn!(%err, "post install hook failed");
+    }
+  }
+  result
+}
+
+async fn install_missing(meta: &ArtifactMeta, downloader: &dyn AsyncDownloader, reporter: &dyn Reporter, cancel: &CancellationToken) -> Result<PathBuf, InstallError> {
+  hooks::run(Event::PreInstall, &meta.version)?;
+  before_download(meta, reporter, cancel)?;
+  let download = DownloadDir::create()?;
+  let installer = download.0.join(installer_name(&meta.version));
+  downloader.download(&meta.url, &installer, cancel).await?;
+  let (transaction, sha256) = stage(meta, &installer, reporter)?;
+  let mut child = tokio::process::Command::new(&installer).args(installer_args(transaction.staging())).kill_on_drop(true).spawn()?;
+  let status = loop {
+    if let Some(status) = child.try_wait()? {
+      break status;
+    }
+    if cancel.is_cancelled() {
+      let _ = child.kill().await;
+      return Err(InstallError::Cancelled);
+    }
+    tokio::time::sleep(Duration::from_millis(50)).await;
+  };
+  if !status.success() {
+    return Err(InstallError::Io(std::io::Error::new(std::io::ErrorKind::Other, format!("installer exited with {}", status))));
+  }
+  finish(meta, transaction, sha256, &installer)
+}
+
+// a dir of its own in the temp dir for one install's download, so installs of the same version at the same time
+// don't write over each other's installer, removed with whatever is in it when it's dropped
+struct DownloadDir(PathBuf);
+
+impl DownloadDir {
+  fn create() -> std::io::Result<Self> {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    let dir = std::env::temp_dir().join(format!("rvm-download-{}-{}", std::process::id(), NEXT.fetch_add(1, Ordering::Relaxed)));
+    std::fs::create_dir_all(&dir)?;
+    Ok(Self(dir))
+  }
+}
+
+impl Drop for DownloadDir {
+  fn drop(&mut self) {
+    let _ = std::fs::remove_dir_all(&self.0);
+  }
+}
@@ -0,0 +1,83 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use lazy_static::lazy_static;
+use crate::install::InstallError;
+use crate::parsing::version_parser::Version;
+use crate::resolve::Active;
+use crate::theme;
+
+// lifecycle events for code embedding rvm (an IDE, a build server), the in process counterpart of the shell hooks:
+// register an Observer once and it's told about every resolve, download, install and switch, from whatever thread did it.
+// observers only watch, they can't stop anything (pre_* hooks can), and they should return quickly.
+// rvm registers one itself that puts every event in the log at debug, `RVM_LOG=rvm::observer=debug` shows just those,
+// and one that prints the warnings a resolve came with
+
+/// Every method does nothing by default, implement the ones you care about
+pub trait Observer: Send + Sync {
+  /// A version was picked for a range, from a request or the project and alias files
+  fn on_resolve(&self, _active: &Active) {}
+  fn on_download_start(&self, _version: &Version, _url: &str) {}
+  /// path is where the verified download is
+  fn on_download_finish(&self, _version: &Version, _url: &str, _path: &Path) {}
+  /// For every install rvm tried, the install dir or why it failed
+  fn on_install_complete(&self, _version: &Version, _result: &Result<PathBuf, InstallError>) {}
+  /// The active version changed for the project (`rvm local`), globally (`rvm global`) or for a shell (`rvm shell`)
+  fn on_switch(&self, _from: Option<&Version>, _to: &Version) {}
+}
+
+lazy_static!{
+  static ref OBSERVERS: RwLock<Vec<Arc<dyn Observer>>> = RwLock::new(vec![]);
+}
+
+/// Adds observer, it stays registered until the process ends
+pub fn register(observer: impl Observer + 'static) {
+  OBSERVERS.write().unwrap_or_else(|e| e.into_inner()).push(Arc::new(observer));
+}
+
+/// Tells every observer, in the order they registered
+pub fn notify(event: impl Fn(&dyn Observer)) {
+  // cloned out so an observer that registers another doesn't deadlock
+  let observers: Vec<Arc<dyn Observer>> = OBSERVERS.read().unwrap_or_else(|e| e.into_inner()).clone();
+  for observer in &observers {
+    event(observer.as_ref());
+  }
+}
+
+/// The one the cli registers, each event as a debug log line
+pub struct LogObserver;
+
+impl Observer for LogObserver {
+  fn on_resolve(&self, active: &Active) {
+    tracing::debug!(version = %active.version, range = %active.range, origin = %active.origin, "resolved");
+  }
+
+  fn on_download_start(&self, version: &Version, url: &str) {
+    tracing::debug!(%version, %url, "download started");
+  }
+
+  fn on_download_finish(&self, version: &Version, url: &str, path: &Path) {
+    tracing::debug!(%version, %url, path = %path.display(), "download finished");
+  }
+
+  fn on_install_complete(&self, version: &Version, result: &Result<PathBuf, InstallError>) {
+    match result {
+      Ok(dest) => tracing::debug!(%version, dest = %dest.display(), "install complete"),
+      Err(err) => tracing::debug!(%version, error = %err, "install failed"),
+    }
+  }
+
+  fn on_switch(&self, from: Option<&Version>, to: &Version) {
+    tracing::debug!(from = from.map(|v| v.to_string()), %to, "switched");
+  }
+}
+
+/// The one the cli registers so resolving's warnings are on stderr, json output has them in the result too
+pub struct WarningObserver;
+
+impl Observer for WarningObserver {
+  fn on_resolve(&self, active: &Active) {
+    for warning in &active.warnings {
+      theme::warning(warning);
+    }
+  }
+}
@@ -0,0 +1,79 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use serde::Serialize;
+
+// --json output, every command wraps its data the same way so consumers can check what they got
+// bump JSON_VERSION when a field changes meaning or goes away, adding fields doesn't need it
+pub const JSON_VERSION: u32 = 1;
+
+#[derive(Serialize)]
+struct Envelope<'a, T: Serialize> {
+  version: u32,
+  command: &'a str,
+  data: &'a T,
+}
+
+// --json-lines, where every envelope is a line of its own
+static JSON_LINES: AtomicBool = AtomicBool::new(false);
+
+pub fn set_json_lines() {
+  JSON_LINES.store(true, Ordering::Relaxed);
+}
+
+pub fn print_json<T: Serialize>(command: &str, data: &T) {
+  let envelope = Envelope { version: JSON_VERSION, command, data };
+  let json = match JSON_LINES.load(Ordering::Relaxed) {
+    true => serde_json::to_string(&envelope),
+    false => serde_json::to_string_pretty(&envelope),
+  };
+  println!("{}", json.unwrap_or_else(|err| panic!("Failed to serialize output: {}", err)));
+}
+
+/// One item of a command's result with --json-lines, in the envelope --json has, printed as soon as it's known
+/// so consumers can act on it before the command is done
+pub fn print_json_line<T: Serialize>(command: &str, item: &T) {
+  let envelope = Envelope { version: JSON_VERSION, command, data: item };
+  println!("{}", serde_json::to_string(&envelope).unwrap_or_else(|err| panic!("Failed to serialize output: {}", err)));
+}
+
+// --porcelain output, one record per line with tab separated fields, "-" for an empty one
+// the fields of a command only get appended to, never reordered or removed, scripts can rely on them:
+//   list         version active(0|1) pinned(0|1) aliases(comma separated) size(bytes, with --size) last-used(YYYY-MM-DD, with --last-used)
+//   list-remote  version url eol support
+//   which        path version
+//   resolve      version range origin-kind origin-from | with --explain: source used(0|1) reason (candidates only in --json) | with a range: version (--minimal too)
+//   outdated     install version newest | project path range current newest
+//   doctor       severity message fix
+//                | with --mirrors: to from successes failures failing(in a row) latency-ms
+//   each         version exit-code seconds
+//   env          name value
+//   du           kind(version|cache|build|tools|saved|total) name bytes
+//   status       tool(R or a plugin) range version
+//   paths        name path
+//   verify       version status(ok|damaged|repaired|unrecorded) missing modified added
+//   check        version status(ok|broken|mismatch) reported-version failed-command
+//   gc           kind(staging|meta|artifact|shim) path bytes
+//   stats        kind(installs|runs|index|downloads) name(month, tool and version, or what's counted) count
+//   workspace    path range version installed(0|1) locked
+//   history      at(unix seconds) user kind(install|uninstall|local|alias) change
+//   lint         path line column kind(empty|inverted|except-outside|include-excepted) message
+//   diff         kind(install|alias|tool) change(added|removed|changed) key old new
+//   detect       file tool constraint range error
+// --json-lines has the same envelope as --json, compact and one per line: list-remote, each and workspace status print
+// one per version, run or project (what's in their --json data list), the other commands their whole data on one line
+pub fn print_porcelain(rows: &[Vec<String>]) {
+  for row in rows {
+    let fields: Vec<String> = row.iter()
+      .map(|f| if f.is_empty() { "-".to_owned() } else { f.replace(['\t', '\n'], " ") })
+      .collect();
+    println!("{}", fields.join("\t"));
+  }
+}
+
+/// A porcelain field that may be missing
+pub fn opt_field<T: std::fmt::Display>(value: &Option<T>) -> String {
+  value.as_ref().map(T::to_string).unwrap_or_default()
+}
+
+pub fn bool_field(value: bool) -> String {
+  if value { "1" } else { "0" }.to_owned()
+}
@@ -0,0 +1,165 @@
+use std::ops::Range as Span;
+use std::str::FromStr;
+use crate::parsing::version_parser::{Dialect, Op, ParseError, Range, Version};
+
+// a lossless view of a range for tools that edit constraint files (formatters, linters, editors)
+// every byte of the source is in exactly one token, so joining the texts gives the source back as it was written,
+// spaces, separators and the casing of pre-releases included. the meaning is still Range::parse's, next to it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RangeAst {
+  pub tokens: Vec<Token>,
+  /// what the source means, the error when it doesn't parse
+  pub range: Result<Range, ParseError>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+  pub kind: TokenKind,
+  /// byte offsets into the source
+  pub span: Span<usize>,
+  pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenKind {
+  Space,
+  /// , ; and the conjunctions of the dialect, && and and
+  Separator,
+  Op(Op),
+  Version(Version),
+  /// what's neither, or an op or version that doesn't parse
+  Invalid,
+}
+
+const OP_CHARS: &[char] = &['=', '!', '<', '>', '~', '^'];
+
+fn is_version_char(c: char) -> bool {
+  c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '+' | '_')
+}
+
+impl RangeAst {
+  pub fn parse(source: &str) -> Self {
+    Self::parse_in(source, Dialect::default())
+  }
+
+  /// Same as parse, a conjunction dialect doesn't allow is an invalid token
+  pub fn parse_in(source: &str, dialect: Dialect) -> Self {
+    let mut tokens = vec![];
+    let mut rest = source.char_indices().peekable();
+    while let Some((start, c)) = rest.next() {
+      let run = |pred: &dyn Fn(char) -> bool, rest: &mut std::iter::Peekable<std::str::CharIndices>| {
+        let mut end = start + c.len_utf8();
+        while let Some(&(i, next)) = rest.peek() {
+          if !pred(next) {
+            break;
+          }
+          end = i + next.len_utf8();
+          rest.next();
+        }
+        end
+      };
+      let (kind, end) = if c.is_whitespace() {
+        (TokenKind::Space, run(&|c| c.is_whitespace(), &mut rest))
+      } else if c == ',' || c == ';' {
+        (TokenKind::Separator, start + 1)
+      } else if c == '&' {
+        let end = run(&|c| c == '&', &mut rest);
+        (if dialect.ampersands && &source[start..end] == "&&" { TokenKind::Separator } else { TokenKind::Invalid }, end)
+      } else if OP_CHARS.contains(&c) {
+        let end = run(&|c| OP_CHARS.contains(&c), &mut rest);
+        (Op::from_str(&source[start..end]).map(TokenKind::Op).unwrap_or(TokenKind::Invalid), end)
+      } else if c == '"' {
+        // a quoted version is one token up to its closing quote, an escaped one doesn't close it
+        let mut end = source.len();
+        let mut escaped = false;
+        for (i, next) in rest.by_ref() {
+          match next {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => {
+              end = i + 1;
+              break;
+            },
+            _ => {},
+          }
+        }
+        (Version::parse(&source[start..end]).map(TokenKind::Version).unwrap_or(TokenKind::Invalid), end)
+      } else if is_version_char(c) {
+        let end = run(&is_version_char, &mut rest);
+        let text = &source[start..end];
+        match Version::parse(text) {
+          Ok(version) => (TokenKind::Version(version), end),
+          Err(_) if dialect.and && text.eq_ignore_ascii_case("and") => (TokenKind::Separator, end),
+          Err(_) => (TokenKind::Invalid, end),
+        }
+      } else {
+        (TokenKind::Invalid, start + c.len_utf8())
+      };
+      tokens.push(Token { kind, span: start..end, text: source[start..end].to_owned() });
+    }
+    Self { tokens, range: Range::parse_in(source, dialect) }
+  }
+
+  /// The source back, byte for byte
+  pub fn source(&self) -> String {
+    self.tokens.iter().map(|t| t.text.as_str()).collect()
+  }
+
+  /// The source without the tokens taken says are taken out, nor the separators and spaces that leaves doubled
+  /// or at either end: `>=4.1, <5, !=4.2.0` without `<5` is `>=4.1, !=4.2.0`
+  pub fn source_without(&self, taken: impl Fn(usize) -> bool) -> String {
+    let mut rest: Vec<&Token> = vec![];
+    for (_, token) in self.tokens.iter().enumerate().filter(|(i, _)| !taken(*i)) {
+      let last = rest.last().map(|t| &t.kind);
+      let last_word = rest.iter().rev().map(|t| &t.kind).find(|kind| **kind != TokenKind::Space);
+      let doubled = match token.kind {
+        TokenKind::Space => last.map_or(true, |kind| *kind == TokenKind::Space),
+        TokenKind::Separator => last_word.map_or(true, |kind| *kind == TokenKind::Separator),
+        _ => false,
+      };
+      if !doubled {
+        rest.push(token);
+      }
+    }
+    while rest.last().is_some_and(|t| matches!(t.kind, TokenKind::Space | TokenKind::Separator)) {
+      rest.pop();
+    }
+    rest.iter().map(|t| t.text.as_str()).collect()
+  }
+
+  /// Where the source goes wrong: the first invalid token, else an op nothing follows, else all of it
+  /// none when it parses
+  pub fn error_span(&self) -> Option<Span<usize>> {
+    if self.range.as_ref().err()? == &ParseError::MissingOperator {
+      return self.bare_version().map(|t| t.span.clone());
+    }
+    let meaningful: Vec<&Token> = self.tokens.iter().filter(|t| !matches!(t.kind, TokenKind::Space | TokenKind::Separator)).collect();
+    let invalid = meaningful.iter().copied().find(|t| t.kind == TokenKind::Invalid);
+    let dangling = meaningful.windows(2).find(|w| matches!(w[0].kind, TokenKind::Op(_)) && !matches!(w[1].kind, TokenKind::Version(_))).map(|w| w[0])
+      .or(meaningful.last().copied().filter(|t| matches!(t.kind, TokenKind::Op(_))));
+    Some(invalid.or(dangling).map(|t| t.span.clone()).unwrap_or(0..self.source().len()))
+  }
+
+  // the first version without an op that only spaces keep from another comparator
+  fn bare_version(&self) -> Option<&Token> {
+    let solid: Vec<&Token> = self.tokens.iter().filter(|t| t.kind != TokenKind::Space).collect();
+    let is_version = |t: Option<&&Token>| t.is_some_and(|t| matches!(t.kind, TokenKind::Version(_)));
+    let is_comparator = |t: Option<&&Token>| t.is_some_and(|t| matches!(t.kind, TokenKind::Version(_) | TokenKind::Op(_)));
+    (0..solid.len())
+      .find(|&i| {
+        let before = i.checked_sub(1).and_then(|b| solid.get(b));
+        is_version(solid.get(i)) && !before.is_some_and(|t| matches!(t.kind, TokenKind::Op(_)))
+          && (is_version(before) || is_comparator(solid.get(i + 1)))
+      })
+      .map(|i| solid[i])
+  }
+
+  /// The source with ^ under the error, for messages
+  pub fn point_at_error(&self) -> Option<String> {
+    let span = self.error_span()?;
+    let source = self.source();
+    let pad = source[..span.start].chars().count();
+    let width = source[span.clone()].chars().count().max(1);
+    Some(format!("{}\n{}{}", source, " ".repeat(pad), "^".repeat(width)))
+  }
+}
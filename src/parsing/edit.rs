@@ -0,0 +1,197 @@
+use std::cmp::Ordering;
+use std::ops::Range as Span;
+use crate::parsing::ast::{RangeAst, TokenKind};
+use crate::parsing::version_parser::{Comparator, Op, ParseError, Range, Version};
+
+// edits of a constraint for tools that change them in files (bots raising a floor, excluding a broken release): Range's
+// change what a range means, RangeAst's change its source as little as they can for the same meaning, the comparators
+// that aren't touched keep their text, order and separators, and new ones go at the end with the separator already
+// used. when the source can't be edited that way the result is the range written out, still meaning the same
+
+/// Which bound of a range
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+  /// >=, inclusive
+  Lower,
+  /// <, exclusive
+  Upper,
+}
+
+impl Range {
+  /// Sets side's bound to version, none takes it away
+  pub fn replace_bound(&mut self, side: Side, version: Option<Version>) {
+    match side {
+      Side::Lower => self.min = version,
+      Side::Upper => self.max = version,
+    }
+  }
+
+  /// Takes version out of the range, whether it wasn't already
+  pub fn add_except(&mut self, version: Version) -> bool {
+    if self.except.iter().any(|e| e.cmp(&version) == Ordering::Equal) {
+      return false;
+    }
+    self.except.push(version);
+    true
+  }
+
+  /// Stops taking version out, whether it was
+  pub fn remove_except(&mut self, version: &Version) -> bool {
+    let before = self.except.len();
+    self.except.retain(|e| e.cmp(version) != Ordering::Equal);
+    self.except.len() != before
+  }
+}
+
+// a comparator of the source and where it's written, the op's span is none for a bare version
+struct Located {
+  op: Op,
+  version: Version,
+  op_span: Option<Span<usize>>,
+  version_span: Span<usize>,
+}
+
+impl Located {
+  fn span(&self) -> Span<usize> {
+    self.op_span.as_ref().map_or(self.version_span.start, |s| s.start)..self.version_span.end
+  }
+}
+
+impl RangeAst {
+  /// The source with side's bound set to version (none takes it away), the comparators setting it rewritten in place
+  pub fn replace_bound(&self, side: Side, version: Option<&Version>) -> Result<Self, ParseError> {
+    let mut expected = self.range.clone()?;
+    expected.replace_bound(side, version.cloned());
+    let bound = match side {
+      Side::Lower => Op::Ge,
+      Side::Upper => Op::Lt,
+    };
+    let located = self.located();
+    let mut placed = version.is_none();
+    let mut edits = vec![];
+    for (at, comparator) in located.iter().enumerate() {
+      let expanded = Range::mixed_vec_to_stand_vec(vec![(comparator.op, comparator.version.clone())])?;
+      if !expanded.iter().any(|(op, _)| *op == bound) {
+        continue;
+      }
+      let source = self.source();
+      let edit = match version {
+        // >= 4.1 stays written with its op and spacing
+        Some(version) if !placed && comparator.op == bound => {
+          let op = &source[comparator.op_span.clone().unwrap_or(0..0)];
+          let between = &source[comparator.op_span.as_ref().map_or(comparator.version_span.start, |s| s.end)..comparator.version_span.start];
+          Some(format!("{}{}{}", op, between, version))
+        },
+        _ => {
+          let mut rest: Vec<String> = expanded.into_iter().filter(|(op, _)| *op != bound)
+            .map(|(op, version)| Comparator { op, version }.to_string()).collect();
+          if let Some(version) = version.filter(|_| !placed) {
+            let new = Comparator { op: bound, version: version.clone() }.to_string();
+            rest.insert(if side == Side::Lower { 0 } else { rest.len() }, new);
+          }
+          Some(rest.join(&self.separator())).filter(|text| !text.is_empty())
+        },
+      };
+      placed = true;
+      edits.push((at, edit));
+    }
+    let added = version.filter(|_| !placed).map(|version| Comparator { op: bound, version: version.clone() }.to_string());
+    self.edited(&located, edits, added, &expected)
+  }
+
+  /// The source with version taken out, added at the end when it isn't already
+  pub fn add_except(&self, version: &Version) -> Result<Self, ParseError> {
+    let mut expected = self.range.clone()?;
+    if !expected.add_except(version.clone()) {
+      return Ok(self.clone());
+    }
+    let added = Comparator { op: Op::Ne, version: version.clone() }.to_string();
+    self.edited(&self.located(), vec![], Some(added), &expected)
+  }
+
+  /// The source without the != of version, with their separators
+  pub fn remove_except(&self, version: &Version) -> Result<Self, ParseError> {
+    let mut expected = self.range.clone()?;
+    if !expected.remove_except(version) {
+      return Ok(self.clone());
+    }
+    let located = self.located();
+    let edits = located.iter().enumerate()
+      .filter(|(_, c)| c.op == Op::Ne && c.version.cmp(version) == Ordering::Equal)
+      .map(|(at, _)| (at, None))
+      .collect();
+    self.edited(&located, edits, None, &expected)
+  }
+
+  fn located(&self) -> Vec<Located> {
+    let mut located = vec![];
+    let mut op = None;
+    for token in &self.tokens {
+      match &token.kind {
+        TokenKind::Op(o) => op = Some((*o, token.span.clone())),
+        TokenKind::Version(version) => {
+          let (op, op_span) = op.take().map_or((Op::Eq, None), |(op, span)| (op, Some(span)));
+          located.push(Located { op, version: version.clone(), op_span, version_span: token.span.clone() });
+        },
+        _ => {},
+      }
+    }
+    located
+  }
+
+  // what's between the first two comparators, ", " when there aren't two
+  fn separator(&self) -> String {
+    let located = self.located();
+    match &located[..] {
+      [first, second, ..] => self.source()[first.span().end..second.span().start].to_owned(),
+      _ => ", ".to_owned(),
+    }
+  }
+
+  // the source with the comparators at edits replaced (none drops them with the separator before them) and added
+  // after the last one, the range written out when that doesn't mean expected
+  fn edited(&self, located: &[Located], edits: Vec<(usize, Option<String>)>, added: Option<String>, expected: &Range) -> Result<Self, ParseError> {
+    let source = self.source();
+    let separator = self.separator();
+    let Some((first, last)) = located.first().zip(located.last()) else {
+      return Ok(Self::parse(&added.unwrap_or_default()));
+    };
+    let mut text = source[..first.span().start].to_owned();
+    let mut any = false;
+    for (at, comparator) in located.iter().enumerate() {
+      let piece = match edits.iter().find(|(edit, _)| *edit == at) {
+        Some((_, edit)) => edit.clone(),
+        None => Some(source[comparator.span()].to_owned()),
+      };
+      let Some(piece) = piece else { continue };
+      if any {
+        text.push_str(&source[located[at - 1].span().end..comparator.span().start]);
+      }
+      text.push_str(&piece);
+      any = true;
+    }
+    if let Some(added) = added {
+      if any {
+        text.push_str(&separator);
+      }
+      text.push_str(&added);
+    }
+    text.push_str(&source[last.span().end..]);
+    let edited = Self::parse(&text);
+    if edited.range.as_ref().is_ok_and(|range| same(range, expected)) {
+      return Ok(edited);
+    }
+    let written: Vec<String> = expected.comparators().iter().map(Comparator::to_string).collect();
+    Ok(Self::parse(&written.join(", ")))
+  }
+}
+
+// whether a and b mean the same, excepts and includes in any order
+fn same(a: &Range, b: &Range) -> bool {
+  let sorted = |versions: &[Version]| {
+    let mut versions = versions.to_vec();
+    versions.sort();
+    versions
+  };
+  a.min == b.min && a.max == b.max && sorted(&a.except) == sorted(&b.except) && sorted(&a.include) == sorted(&b.include)
+}
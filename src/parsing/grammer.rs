@@ -1,4 +1,14 @@
-use crate::parsing::version_parser::{Version, Range, Op};
+use std::str::FromStr;
+use crate::parsing::version_parser::{Comparator, Dialect, Version, Range, Op};
+
+// a comparator as it was written, to tell 1.2 3.4 (a typo of something) from 1.2, 3.4 (a list)
+pub struct Written {
+  pub comparator: Comparator,
+  /// false for a bare version, which is =
+  pub explicit: bool,
+  /// only spaces between it and the next one
+  pub spaced: bool,
+}
 
 // Dependency is a simplified Package because it doesn't has all the info
 #[derive(Debug, PartialEq)]
@@ -11,26 +21,44 @@ pub struct Dependency {
 
 peg::parser!( pub grammar the_parser() for str {
   pub rule parse_version() -> Version
-    = " "* v:version() " "* ![_] {v} // ![_] means end of file
-
-  rule version() -> Version
-    = ['v' | 'V']? " "? m:main() e:extra()? a:afterV() {
-      Version::new_w_extra(
-        m.0,
-        m.1.unwrap_or(0),
-        m.2.unwrap_or(0),
+    = space()* v:version(Dialect::STRICT) space()* ![_] {v.0} // ![_] means end of file
+
+  // d is what may separate it from the next comparator, a lone version has nothing after it
+  // with whether only spaces separate it
+  rule version(d: Dialect) -> (Version, bool)
+    = quoted(d)
+    / ['v' | 'V']? " "? m:main() e:extra()? a:afterV(d) {
+      (Version::new_w_extra(
+        m.0.0,
+        m.1.map_or(0, |n| n.0),
+        m.2.map_or(0, |n| n.0),
         e,
         a.0,
         a.1
-      )
+      ).written_with([m.0.1, m.1.map_or(0, |n| n.1), m.2.map_or(0, |n| n.1)]), a.2)
+  }
+  // in double quotes the extra version, pre-release and build can have any character, for registries that allow them
+  // \ takes the next character as it is: a quote, a \, or a - or + that's part of an identifier instead of starting the next
+  // only in that order, so a pre-release ends at the first + that isn't escaped and a build at the closing quote
+  rule quoted(d: Dialect) -> (Version, bool)
+    = "\"" ['v' | 'V']? m:main() e:("." i:ident(&['-', '+']) {i})? p:("-" i:ident(&['+']) {i})? b:("+" i:ident(&[]) {i})? "\"" s:supOrEnd(d) {
+      (Version::new_w_extra(m.0.0, m.1.map_or(0, |n| n.0), m.2.map_or(0, |n| n.0), e, p, b)
+        .written_with([m.0.1, m.1.map_or(0, |n| n.1), m.2.map_or(0, |n| n.1)]), s)
   }
+  rule ident(stops: &'static [char]) -> String
+    = c:ident_char(stops)+ { c.into_iter().collect() }
+  rule ident_char(stops: &'static [char]) -> char
+    = "\\" c:[_] { c }
+    / c:[c if c != '"' && c != '\\' && !stops.contains(&c)] { c }
+
   // pre and build any order and existence
-  rule afterV() -> (Option<String>, Option<String>)
+  rule afterV(d: Dialect) -> (Option<String>, Option<String>, bool)
     // here end of file is kinda needed because if not it will accept afterV if the order is b p, cause "+window-alpha" will return (None, Some("window")) and come back without checking further
-    = p:pre()? b:build()? supOrEnd() { (p, b) }
-    / b:build() p:pre() supOrEnd() { (Some(p), Some(b)) }
-  rule num() -> u32
-    = n:$(['0'..='9']+) {? n.parse().or(Err("number")) } //n tenho a certeza do q {? rust} faz https://docs.rs/peg/latest/peg/#combining
+    = p:pre()? b:build()? s:supOrEnd(d) { (p, b, s) }
+    / b:build() p:pre() s:supOrEnd(d) { (Some(p), Some(b), s) }
+  // with how wide it was written if that was with leading zeros, 0 if not
+  rule num() -> (u32, u8)
+    = n:$(['0'..='9']+) {? n.parse().map(|v| (v, if n.len() > 1 && n.starts_with('0') { n.len().min(u8::MAX as usize) as u8 } else { 0 })).or(Err("number")) } //n tenho a certeza do q {? rust} faz https://docs.rs/peg/latest/peg/#combining
 
 
 
@@ -38,13 +66,19 @@ peg::parser!( pub grammar the_parser() for str {
     = n:$(['a'..='z' | 'A'..='Z' | '0'..='9' | '_' | '.']+) {? Ok(n.to_string())}
 
 
-  rule separator() -> ()
-    = n:$([' ' | ',' | ';']) {}
-  rule supOrEnd() -> ()
-    = separator()+ {} //* means 0 or more, + means 1 or more
-    / ![_] {}
+  rule space() -> ()
+    = [' ' | '\t'] {}
 
-  rule main() -> (u32, Option<u32>, Option<u32>)
+  rule separator(d: Dialect) -> ()
+    // and is a word of its own, so spaces before it are part of it (4.1and isn't a conjunction)
+    = space()+ ['a' | 'A'] ['n' | 'N'] ['d' | 'D'] !['a'..='z' | 'A'..='Z' | '0'..='9' | '_' | '.'] {? if d.and { Ok(()) } else { Err("separator") } }
+    / "&&" {? if d.ampersands { Ok(()) } else { Err("separator") } }
+    / n:$([' ' | '\t' | ',' | ';']) {}
+  rule supOrEnd(d: Dialect) -> bool
+    = s:$(separator(d)+) { s.trim().is_empty() } //* means 0 or more, + means 1 or more
+    / ![_] { false }
+
+  rule main() -> ((u32, u8), Option<(u32, u8)>, Option<(u32, u8)>)
     = M:num() "."? m:num()? "."? p:num()? { (M, m, p) }
 
   rule extra() -> String
@@ -56,14 +90,19 @@ peg::parser!( pub grammar the_parser() for str {
   rule pre() -> String
     = "-" c:chars() { c }
 
+  pub rule parse_comparators(d: Dialect) -> Vec<Written>
+    = space()* r:(range(d) ** "") space()* ![_] { r }
+
   pub rule parse_range() -> Range
-    = " "* r:(range() ** "") " "* ![_] { Range::from_ver_vec(r) }
+    = r:parse_comparators(Dialect::default()) {? Range::from_written(r).map_err(|_| "a range that doesn't overflow, with an operator on every version") }
 
-  rule range() -> (Op, Version)
-    = o:op() " "* v:version() " "* { (o,v) }
+  rule range(d: Dialect) -> Written
+    = o:$(op()?) space()* v:version(d) space()* {
+      Written { comparator: Comparator { op: Op::from_str(o).unwrap(), version: v.0 }, explicit: !o.is_empty(), spaced: v.1 }
+    }
 
-  rule op() -> Op
-    = o:$("==" / "!=" / "<=" / ">=" / "=" / "<" / ">" / "~" / "^" / " " / "") { Op::from_str(o).unwrap() }
+  rule op()
+    = "==" / "!=" / "<=" / ">=" / "=" / "<" / ">" / "~" / "^"
       // => and =< will fail, but that's ok
 
   pub rule parse_dependency() -> Dependency
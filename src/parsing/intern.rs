@@ -0,0 +1,103 @@
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+use crate::parsing::version_parser::{cmp_extra, cmp_pre_release, Version};
+
+// a registry with hundreds of thousands of versions repeats the same few labels (nightly.20240315, +win, .1)
+// over and over, a Version has a String of its own for each. interned versions share them instead,
+// so they're three numbers and three pointers, and cloning one doesn't allocate
+// only compiled with the "intern" feature, the cli's indexes are small enough as they are
+
+/// A version whose labels are shared with every other version from the same interner
+/// Eq, Ord and Display are Version's
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct InternedVersion {
+  major: u32,
+  minor: u32,
+  patch: u32,
+  extra_version: Option<Arc<str>>,
+  pre_release: Option<Arc<str>>,
+  build: Option<Arc<str>>,
+}
+
+impl InternedVersion {
+  pub fn parts(&self) -> (u32, u32, u32) {
+    (self.major, self.minor, self.patch)
+  }
+
+  /// The version back, with labels of its own
+  pub fn to_version(&self) -> Version {
+    Version::new_w_extra(self.major, self.minor, self.patch, self.extra_version.as_deref(), self.pre_release.as_deref(), self.build.as_deref())
+  }
+}
+
+impl Ord for InternedVersion {
+  fn cmp(&self, other: &Self) -> Ordering {
+    (self.major, self.minor, self.patch).cmp(&(other.major, other.minor, other.patch))
+      .then_with(|| cmp_extra(self.extra_version.as_deref(), other.extra_version.as_deref()))
+      .then_with(|| cmp_pre_release(self.pre_release.as_deref(), other.pre_release.as_deref()))
+      .then_with(|| self.build.cmp(&other.build))
+      .then_with(|| self.pre_release.cmp(&other.pre_release))
+      .then_with(|| self.extra_version.cmp(&other.extra_version))
+  }
+}
+
+impl PartialOrd for InternedVersion {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl Display for InternedVersion {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}", self.to_version())
+  }
+}
+
+/// Hands out InternedVersions, every label it has seen is stored once
+#[derive(Debug, Default)]
+pub struct VersionInterner {
+  labels: HashSet<Arc<str>>,
+}
+
+impl VersionInterner {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn intern(&mut self, version: &Version) -> InternedVersion {
+    let (major, minor, patch) = version.parts();
+    let (extra_version, pre_release, build) = version.labels();
+    InternedVersion {
+      major,
+      minor,
+      patch,
+      extra_version: extra_version.map(|label| self.label(label)),
+      pre_release: pre_release.map(|label| self.label(label)),
+      build: build.map(|label| self.label(label)),
+    }
+  }
+
+  pub fn intern_all<'a>(&mut self, versions: impl IntoIterator<Item = &'a Version>) -> Vec<InternedVersion> {
+    versions.into_iter().map(|version| self.intern(version)).collect()
+  }
+
+  /// How many different labels it holds
+  pub fn len(&self) -> usize {
+    self.labels.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.labels.is_empty()
+  }
+
+  fn label(&mut self, label: &str) -> Arc<str> {
+    if let Some(shared) = self.labels.get(label) {
+      return shared.clone();
+    }
+    let shared: Arc<str> = Arc::from(label);
+    self.labels.insert(shared.clone());
+    shared
+  }
+}
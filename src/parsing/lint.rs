@@ -0,0 +1,116 @@
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter};
+use std::ops::Range as Span;
+use serde::Serialize;
+use crate::parsing::ast::{RangeAst, TokenKind};
+use crate::parsing::version_parser::{Op, Range, Version};
+
+// constraints that parse but can't mean what they say: a range nothing matches, bounds the wrong way around,
+// comparators that have no effect. Range::lint finds them in what a range means, RangeAst places them in its source
+// so `rvm lint` can point at them like a compiler would
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum Lint {
+  /// no version at all is in the range
+  Empty,
+  /// the upper bound is at or under the lower one
+  Inverted { min: Version, max: Version },
+  /// an except outside the bounds, it takes out nothing
+  ExceptOutside { version: Version },
+  /// a version both included and excepted, the except wins
+  IncludeExcepted { version: Version },
+}
+
+impl Lint {
+  /// What kind it is, the kind field of its json
+  pub fn name(&self) -> &'static str {
+    match self {
+      Lint::Empty => "empty",
+      Lint::Inverted { .. } => "inverted",
+      Lint::ExceptOutside { .. } => "except-outside",
+      Lint::IncludeExcepted { .. } => "include-excepted",
+    }
+  }
+}
+
+impl Display for Lint {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Lint::Empty => write!(f, "no version can match this range"),
+      Lint::Inverted { min, max } => write!(f, "upper bound <{} is lower than lower bound >={}", max, min),
+      Lint::ExceptOutside { version } => write!(f, "!={} is outside the bounds, it excludes nothing", version),
+      Lint::IncludeExcepted { version } => write!(f, "={} contradicts !={}, it's excluded", version, version),
+    }
+  }
+}
+
+/// A lint and where it is in the source it was found in, in bytes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+  #[serde(flatten)]
+  pub lint: Lint,
+  pub span: Span<usize>,
+  pub message: String,
+}
+
+impl Range {
+  /// What in the range is suspicious, nothing for most
+  pub fn lint(&self) -> Vec<Lint> {
+    let mut ret = vec![];
+    let inverted = match (&self.min, &self.max) {
+      (Some(min), Some(max)) if max.cmp_release(min) != Ordering::Greater => Some(Lint::Inverted { min: min.clone(), max: max.clone() }),
+      _ => None,
+    };
+    // with only bounds and includes, the excepts are what's left to take out
+    let without = Range { except: vec![], ..self.clone() };
+    let excepted = |version: &Version| self.except.iter().any(|e| e.cmp(version) == Ordering::Equal);
+    let bounds_empty = inverted.is_some() || self.max.as_ref().is_some_and(|max| max.cmp_release(&Version::new(0, 0, 0)) != Ordering::Greater);
+    let bounded = self.min.is_some() || self.max.is_some();
+    if (bounded && bounds_empty || !bounded && !self.include.is_empty()) && self.include.iter().all(excepted) {
+      ret.push(Lint::Empty);
+    }
+    ret.extend(inverted);
+    ret.extend(self.except.iter()
+      .filter(|except| !without.contains_any(std::slice::from_ref(except)))
+      .map(|version| Lint::ExceptOutside { version: version.clone() }));
+    ret.extend(self.include.iter()
+      .filter(|include| excepted(include))
+      .map(|version| Lint::IncludeExcepted { version: version.clone() }));
+    ret
+  }
+}
+
+impl RangeAst {
+  /// The lints of what the source means, none when it doesn't parse
+  pub fn lint(&self) -> Vec<Diagnostic> {
+    self.range.as_ref().map(|range| self.locate(range.lint())).unwrap_or_default()
+  }
+
+  /// lints placed at the version they're about, the whole source when it isn't written as is (a ~ or ^ range's bound)
+  /// or when it's about all of it
+  pub fn locate(&self, lints: Vec<Lint>) -> Vec<Diagnostic> {
+    let whole = 0..self.source().len();
+    lints.into_iter().map(|lint| {
+      let at = match &lint {
+        Lint::Empty => None,
+        Lint::Inverted { max, .. } => self.version_span(max, Op::Lt),
+        Lint::ExceptOutside { version } => self.version_span(version, Op::Ne),
+        Lint::IncludeExcepted { version } => self.version_span(version, Op::Eq),
+      };
+      Diagnostic { message: lint.to_string(), lint, span: at.unwrap_or_else(|| whole.clone()) }
+    }).collect()
+  }
+
+  // version's token and the op right before it, the first one with op if there's one, else the first one
+  fn version_span(&self, version: &Version, op: Op) -> Option<Span<usize>> {
+    let op_before = |at: usize| self.tokens[..at].iter().rev().find(|t| t.kind != TokenKind::Space)
+      .and_then(|t| match t.kind { TokenKind::Op(op) => Some((op, t.span.start)), _ => None });
+    let versions: Vec<usize> = (0..self.tokens.len())
+      .filter(|&at| matches!(&self.tokens[at].kind, TokenKind::Version(v) if v == version))
+      .collect();
+    let at = versions.iter().copied().find(|&at| op_before(at).is_some_and(|(o, _)| o == op)).or(versions.first().copied())?;
+    let start = op_before(at).map_or(self.tokens[at].span.start, |(_, start)| start);
+    Some(start..self.tokens[at].span.end)
+  }
+}
@@ -1,3 +1,10 @@
 pub mod version_parser;
+pub mod request;
+pub mod ast;
+#[cfg(feature = "intern")] pub mod intern;
 pub mod yaml_ser;
-pub(crate) mod grammer;
\ No newline at end of file
+pub mod grammer;
+pub mod warning;
+pub mod lint;
+pub mod edit;
+pub mod operators;
\ No newline at end of file
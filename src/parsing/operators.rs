@@ -0,0 +1,91 @@
+use std::sync::{Arc, RwLock};
+use lazy_static::lazy_static;
+use thiserror::Error;
+use crate::parsing::version_parser::{Comparator, ParseError, Version};
+
+// operators other ecosystems write that the grammar doesn't know, ruby's ~>4.2 or pep 440's ~=4.2, registered by code
+// embedding rvm instead of added to the peg rules: before a range is parsed, every registered symbol starting a
+// comparator is replaced by the comparators its expansion gives for the version after it, so the grammar only ever
+// sees its own operators. where symbols overlap (~> and a ~>= registered too), the one with the higher precedence is
+// tried first and then the longer one, and any registered one before the built in ones
+
+/// Turns the version after a custom operator into comparators, with the version as it was written (4.2 isn't 4.2.0
+/// for every ecosystem)
+pub type Expansion = Arc<dyn Fn(&Version, &str) -> Vec<Comparator> + Send + Sync>;
+
+#[derive(Clone)]
+pub struct CustomOp {
+  pub symbol: String,
+  pub precedence: i32,
+  pub expand: Expansion,
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum OperatorError {
+  #[error("{0} is already an operator of the grammar")]
+  Builtin(String),
+  #[error("{0:?} can't be an operator, it has to be something other than spaces, separators, quotes or a number")]
+  InvalidSymbol(String),
+}
+
+const BUILTIN: [&str; 9] = ["==", "=", "!=", ">", "<", ">=", "<=", "~", "^"];
+
+lazy_static!{
+  // ordered the way they're tried
+  static ref OPS: RwLock<Vec<CustomOp>> = RwLock::new(vec![]);
+}
+
+/// Lets every range parsed after use symbol, `~>4.2` becomes what expand gives for 4.2. Registering a symbol again
+/// replaces it, it stays registered until the process ends
+pub fn register(symbol: &str, precedence: i32, expand: impl Fn(&Version, &str) -> Vec<Comparator> + Send + Sync + 'static) -> Result<(), OperatorError> {
+  if BUILTIN.contains(&symbol) {
+    return Err(OperatorError::Builtin(symbol.to_owned()));
+  }
+  if symbol.is_empty() || symbol.starts_with(|c: char| c.is_ascii_digit()) || symbol.contains(|c: char| c.is_whitespace() || is_boundary(c) || c == '"') {
+    return Err(OperatorError::InvalidSymbol(symbol.to_owned()));
+  }
+  let mut ops = OPS.write().unwrap_or_else(|e| e.into_inner());
+  ops.retain(|op| op.symbol != symbol);
+  ops.push(CustomOp { symbol: symbol.to_owned(), precedence, expand: Arc::new(expand) });
+  ops.sort_by(|a, b| b.precedence.cmp(&a.precedence).then(b.symbol.len().cmp(&a.symbol.len())));
+  Ok(())
+}
+
+/// The registered operators, in the order they're tried
+pub fn registered() -> Vec<CustomOp> {
+  OPS.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+fn is_boundary(c: char) -> bool {
+  matches!(c, ',' | ';' | '&')
+}
+
+/// range with the registered operators expanded, as it is when there's none in it
+pub(crate) fn expand(range: &str) -> Result<String, ParseError> {
+  let ops = OPS.read().unwrap_or_else(|e| e.into_inner());
+  if !ops.iter().any(|op| range.contains(op.symbol.as_str())) {
+    return Ok(range.to_owned());
+  }
+  let mut expanded = String::with_capacity(range.len());
+  let mut at = 0;
+  // a comparator starts the range or follows a space or separator
+  let mut starts = true;
+  while let Some(c) = range[at..].chars().next() {
+    let op = starts.then(|| ops.iter().find(|op| range[at..].starts_with(op.symbol.as_str()))).flatten();
+    let Some(op) = op else {
+      starts = c.is_whitespace() || is_boundary(c);
+      expanded.push(c);
+      at += c.len_utf8();
+      continue;
+    };
+    let rest = &range[at + op.symbol.len()..];
+    let written = rest.trim_start();
+    let end = written.find(|c: char| c.is_whitespace() || is_boundary(c)).unwrap_or(written.len());
+    let version = Version::parse(&written[..end]).map_err(|_| ParseError::InvalidRange)?;
+    let comparators = (op.expand)(&version, &written[..end]);
+    expanded.push_str(&comparators.iter().map(|c| c.to_string()).collect::<Vec<_>>().join(", "));
+    at += op.symbol.len() + (rest.len() - written.len()) + end;
+    starts = false;
+  }
+  Ok(expanded)
+}
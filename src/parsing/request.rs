@@ -0,0 +1,94 @@
+use std::fmt::{Display, Formatter};
+use crate::parsing::version_parser::{ParseError, Range, Version};
+
+// what can be asked for on the command line: a version, a range or a keyword for one that depends on what there is
+//   latest  the newest release (release means the same)
+//   stable  the newest release past its series' .0, for waiting on the first fixes
+//   lts     the newest release of the series before the newest, like R's oldrel (an "lts" alias wins over it)
+//   system  the R on PATH that rvm doesn't manage, it has no version here
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VersionRequest {
+  Exact(Version),
+  Range(Range),
+  Keyword(Keyword),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Keyword {
+  Latest,
+  Stable,
+  Lts,
+  System,
+}
+
+impl Keyword {
+  pub fn parse(name: &str) -> Option<Self> {
+    match name.trim().to_lowercase().as_str() {
+      "latest" | "release" => Some(Self::Latest),
+      "stable" => Some(Self::Stable),
+      "lts" => Some(Self::Lts),
+      "system" => Some(Self::System),
+      _ => None,
+    }
+  }
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      Self::Latest => "latest",
+      Self::Stable => "stable",
+      Self::Lts => "lts",
+      Self::System => "system",
+    }
+  }
+}
+
+impl VersionRequest {
+  /// A keyword, or what Range::from_request makes of it (a single version is Exact)
+  pub fn parse(request: &str) -> Result<Self, ParseError> {
+    if let Some(keyword) = Keyword::parse(request) {
+      return Ok(Self::Keyword(keyword));
+    }
+    let range = Range::from_request(request)?;
+    match range.include.as_slice() {
+      [version] if range.min.is_none() && range.max.is_none() && range.except.is_empty() => Ok(Self::Exact(version.clone())),
+      _ => Ok(Self::Range(range)),
+    }
+  }
+
+  /// The version it means among pool, none when nothing there fits (always for system)
+  pub fn resolve<'a>(&self, pool: impl IntoIterator<Item = &'a Version>) -> Option<&'a Version> {
+    let pool: Vec<&Version> = pool.into_iter().collect();
+    let releases = || pool.iter().copied().filter(|v| v.channel().is_none());
+    let series = |v: &Version| (v.parts().0, v.parts().1);
+    match self {
+      Self::Exact(version) => pool.into_iter().find(|v| *v == version),
+      Self::Range(range) => range.max_satisfying(pool),
+      Self::Keyword(Keyword::Latest) => releases().max(),
+      Self::Keyword(Keyword::Stable) => releases().filter(|v| v.parts().2 > 0).max(),
+      Self::Keyword(Keyword::Lts) => {
+        let newest = releases().max()?;
+        releases().filter(|v| series(v) < series(newest)).max()
+      },
+      Self::Keyword(Keyword::System) => None,
+    }
+  }
+
+  /// The range it stands for among pool, keywords become exactly their version
+  pub fn to_range<'a>(&self, pool: impl IntoIterator<Item = &'a Version>) -> Option<Range> {
+    match self {
+      Self::Range(range) => Some(range.clone()),
+      Self::Exact(version) => Some(Range::exactly(version.clone())),
+      Self::Keyword(_) => self.resolve(pool).map(|v| Range::exactly(v.clone())),
+    }
+  }
+}
+
+impl Display for VersionRequest {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Self::Exact(version) => write!(f, "{}", version),
+      Self::Range(range) => write!(f, "{}", range),
+      Self::Keyword(keyword) => write!(f, "{}", keyword.name()),
+    }
+  }
+}
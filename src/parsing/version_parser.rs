@@ -1,23 +1,58 @@
 use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
 use std::fmt::{Display, Formatter};
+use std::hash::{Hash, Hasher};
 use std::str::FromStr;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_with::{DeserializeFromStr, SerializeDisplay};
 use thiserror::Error;
-use crate::parsing::grammer::the_parser::{parse_version, parse_range};
+use crate::parsing::grammer::the_parser::{parse_comparators, parse_version};
+use crate::parsing::grammer::Written;
+use crate::parsing::ast::{RangeAst, TokenKind};
+use crate::parsing::operators;
 
 
-#[derive(Error, Debug, PartialEq, Eq)]
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
 pub enum ParseError {
   #[error("error in parsing version")]
   InvalidVersion,
   #[error("error in parsing range")]
   InvalidRange,
+  #[error("error in parsing range, a version is too big to go past")]
+  Overflow,
+  #[error("error in parsing version, it isn't valid UTF-8")]
+  InvalidUtf8,
+  #[error("error in parsing version or range, it's longer than the limits allow")]
+  TooLong,
+  #[error("error in parsing range, only a lone version can go without an operator (is 1.2 3.4 >=1.2 <3.4 or =1.2.3.4?)")]
+  MissingOperator,
+  #[error("error in parsing range, {0} isn't a version, alias or channel")]
+  UnknownName(String),
+  #[error("error in parsing range, {0} syntax requires feature {}", .0.feature().unwrap_or_default())]
+  Unsupported(Syntax),
 }
 
 
-#[derive(Debug, Clone, PartialEq, Eq, DeserializeFromStr, SerializeDisplay)]
+/// A version, R's (4.3.1) or any semver one, and a fourth extra part (4.3.1.1).
+/// Ordered by the release, then pre-releases before it, then the build, and Ord agrees with Eq and Hash:
+///
+/// ```
+/// use rvm_core::parsing::version_parser::Version;
+/// let v = |v: &str| Version::parse(v).unwrap();
+/// // numbers compare as numbers, an extra part comes after the release without one
+/// assert!(v("4.2.10") > v("4.2.9") && v("4.2.1.1") > v("4.2.1"));
+/// // pre-releases come before their release, identifiers one by one with numbers numerically
+/// assert!(v("4.3.0-rc.2") < v("4.3.0-rc.10") && v("4.3.0-rc.10") < v("4.3.0"));
+/// // builds only break ties
+/// assert!(v("4.3.0+a") < v("4.3.0+b") && v("4.3.0+b") < v("4.3.1"));
+/// // equal only when cmp says so, a leading zero still tells two pre-releases apart
+/// assert_ne!(v("4.3.0-01"), v("4.3.0-1"));
+/// assert_ne!(v("4.3.0-01").cmp(&v("4.3.0-1")), std::cmp::Ordering::Equal);
+/// assert_eq!(v("4.3.0-01").cmp_precedence(&v("4.3.0-1")), std::cmp::Ordering::Equal);
+/// ```
+#[derive(Debug, Clone, DeserializeFromStr, SerializeDisplay)]
 pub struct Version {
   major: u32,
   minor: u32,
@@ -25,19 +60,56 @@ pub struct Version {
   extra_version: Option<String>,
   pre_release: Option<String>,
   build: Option<String>,
+  // how wide major, minor and patch were written when that was with leading zeros (0 when not), 1.02.003 is [0, 2, 3]
+  // only VersionFormatter::keep_zeros looks at it, 1.02 is 1.2 for everything else
+  written_widths: [u8; 3],
+}
+
+impl PartialEq for Version {
+  // every field but written_widths, taken apart like in Ord so a new one has to be placed here too
+  fn eq(&self, other: &Self) -> bool {
+    let Version { major, minor, patch, extra_version, pre_release, build, written_widths: _ } = self;
+    (major, minor, patch, extra_version, pre_release, build)
+      == (&other.major, &other.minor, &other.patch, &other.extra_version, &other.pre_release, &other.build)
+  }
+}
+
+impl Eq for Version {}
+
+impl Hash for Version {
+  fn hash<H: Hasher>(&self, state: &mut H) {
+    let Version { major, minor, patch, extra_version, pre_release, build, written_widths: _ } = self;
+    (major, minor, patch, extra_version, pre_release, build).hash(state)
+  }
 }
 
 impl Version {
   pub fn parse<S:Into<String>>(version: S) -> Result<Self, ParseError> {
-    let version: Self = parse_version(&version.into()).map_err(|_| ParseError::InvalidVersion)?;
+    Self::parse_limited(&version.into(), Limits::default())
+  }
+  /// Same as parse, with other limits than the default ones
+  pub fn parse_limited(version: &str, limits: Limits) -> Result<Self, ParseError> {
+    limits.check(version)?;
+    let version: Self = parse_version(version).map_err(|_| ParseError::InvalidVersion)?;
     Ok(version)
   }
+  /// For file names and dir entries, one that isn't UTF-8 fails with InvalidUtf8 (no version is spelled like that)
+  pub fn parse_os(version: &OsStr) -> Result<Self, ParseError> {
+    Self::parse(version.to_str().ok_or(ParseError::InvalidUtf8)?)
+  }
+  /// The other ecosystems' constraint syntaxes this build was compiled with, see Syntax::feature
+  pub fn supported_dialects() -> Vec<Syntax> {
+    Syntax::ALL.into_iter().filter(|syntax| syntax.is_supported()).collect()
+  }
+  pub fn parse_bytes(version: &[u8]) -> Result<Self, ParseError> {
+    Self::parse(std::str::from_utf8(version).map_err(|_| ParseError::InvalidUtf8)?)
+  }
   // https://play.rust-lang.org/?version=stable&mode=debug&edition=2021&gist=d78be90c82a7b80c949f30b5befcd6c2
   pub fn new_w_extra<S:Into<String>>(
     major: u32,
     minor: u32,
     patch: u32,
-    //1.1.0.1.5 < 1.1.0.1.6, 1.1.0.1.5 > 1.1.0, 1.1.0.0.0 > 1.1.0
+    //1.1.0.1.5 < 1.1.0.1.6, 1.1.0.1.5 > 1.1.0, 1.1.0.0.0 > 1.1.0, 1.1.0.10 > 1.1.0.9 (identifiers compare like the pre-release's)
     extra_version: Option<S>,
     // 1.1.0-rc.1 < 1.1.0-rc.2, 1-a < 1-b, 1.1.0-rc.1 <= 1.1.0
     // # Pre-release-note
@@ -57,6 +129,7 @@ impl Version {
       extra_version: extra_version.map(|s| s.into()),
       pre_release: pre_release.map(|s| s.into()),
       build: build.map(|s| s.into()),
+      written_widths: [0; 3],
     }
   }
 
@@ -71,6 +144,7 @@ impl Version {
       extra_version: None,
       pre_release: None,
       build: None,
+      written_widths: [0; 3],
     }
   }
 
@@ -82,12 +156,26 @@ impl Version {
       extra_version: None,
       pre_release: None,
       build: None,
+      written_widths: [0; 3],
     }
   }
 
+  // the widths major, minor and patch were written with, from the grammar
+  pub(crate) fn written_with(mut self, widths: [u8; 3]) -> Self {
+    self.written_widths = widths;
+    self
+  }
+
+  /// (major, minor, patch), the fields are private so the builder methods can share their names
+  #[must_use]
+  pub fn parts(&self) -> (u32, u32, u32) {
+    (self.major, self.minor, self.patch)
+  }
+
   //could be a cool macro
   //maybe remove if not used anywhere
   /// These are useful to clone a version and change only one of the fields
+  #[must_use]
   pub fn with_major(&self, major: u32) -> Self {
     Self {
       major,
@@ -96,8 +184,10 @@ impl Version {
       extra_version: self.extra_version.clone(),
       pre_release: self.pre_release.clone(),
       build: self.build.clone(),
+      written_widths: self.written_widths,
     }
   }
+  #[must_use]
   pub fn with_minor(&self, minor: u32) -> Self {
     Self {
       major: self.major,
@@ -106,8 +196,10 @@ impl Version {
       extra_version: self.extra_version.clone(),
       pre_release: self.pre_release.clone(),
       build: self.build.clone(),
+      written_widths: self.written_widths,
     }
   }
+  #[must_use]
   pub fn with_patch(&self, patch: u32) -> Self {
     Self {
       major: self.major,
@@ -116,8 +208,10 @@ impl Version {
       extra_version: self.extra_version.clone(),
       pre_release: self.pre_release.clone(),
       build: self.build.clone(),
+      written_widths: self.written_widths,
     }
   }
+  #[must_use]
   pub fn with_extra_version(&self, extra_version: Option<impl Into<String>>) -> Self {
     Self {
       major: self.major,
@@ -126,11 +220,14 @@ impl Version {
       extra_version: extra_version.map(|s| s.into()),
       pre_release: self.pre_release.clone(),
       build: self.build.clone(),
+      written_widths: self.written_widths,
     }
   }
+  #[must_use]
   pub fn with_extra(&self, extra_version: Option<impl Into<String>>) -> Self {
     self.with_extra_version(extra_version)
   }
+  #[must_use]
   pub fn with_pre_release(&self, pre_release: Option<impl Into<String>>) -> Self {
     Self {
       major: self.major,
@@ -139,11 +236,14 @@ impl Version {
       extra_version: self.extra_version.clone(),
       pre_release: pre_release.map(|s| s.into()),
       build: self.build.clone(),
+      written_widths: self.written_widths,
     }
   }
+  #[must_use]
   pub fn with_pre(&self, pre_release: Option<impl Into<String>>) -> Self {
     self.with_pre_release(pre_release)
   }
+  #[must_use]
   pub fn with_build(&self, build: Option<impl Into<String>>) -> Self {
     Self {
       major: self.major,
@@ -152,6 +252,7 @@ impl Version {
       extra_version: self.extra_version.clone(),
       pre_release: self.pre_release.clone(),
       build: build.map(|s| s.into()),
+      written_widths: self.written_widths,
     }
   }
 
@@ -206,7 +307,7 @@ impl Version {
     self.major < other.major
       || self.minor < other.minor
       || self.patch < other.patch
-      || self.extra_version < other.extra_version
+      || cmp_extra(self.extra_version.as_deref(), other.extra_version.as_deref()) == Ordering::Less
       || self.pre_release < other.pre_release
   }
 }
@@ -217,57 +318,614 @@ impl FromStr for Version {
     Self::parse(s)
   }
 }
+
+// a range read from a string (project files, config, FromStr) can name versions, `>=lts` or `=default`. it's kept as
+// written and matches nothing until with_names gives the names their versions, which is resolving's job: parsing
+// doesn't do I/O, and a file that's read and written back still tracks what it names instead of what it was then
 impl FromStr for Range {
   type Err = ParseError;
 
   fn from_str(s: &str) -> Result<Self, Self::Err> {
-    Self::parse(s)
+    Self::parse(s).or_else(|err| Self::named(s).map_err(|_| err))
+  }
+}
+
+fn is_name(text: &str) -> bool {
+  text.starts_with(|c: char| c.is_ascii_alphabetic()) && Version::parse(text).is_err()
+}
+
+fn has_names(range: &str) -> bool {
+  RangeAst::parse(range).tokens.iter().any(|t| t.kind == TokenKind::Invalid && is_name(&t.text))
+}
+
+impl Version {
+  /// What's after the +, none when it has no build metadata
+  pub fn build_metadata(&self) -> Option<&str> {
+    self.build.as_deref()
+  }
+
+  /// What's after the -, none for a release
+  pub fn pre_release_tag(&self) -> Option<&str> {
+    self.pre_release.as_deref()
+  }
+
+  /// The pre-release's first identifier, "nightly" for 4.4.0-nightly.20240315, none for a release
+  pub fn channel(&self) -> Option<&str> {
+    self.pre_release.as_deref().map(|pre| pre.split('.').next().unwrap_or(pre))
+  }
+
+  /// The release a pre-release leads to, 2.0.0-rc.3 is 2.0.0, without its build too; a release stays as it is minus the build
+  #[must_use]
+  pub fn promote(&self) -> Self {
+    self.with_pre_release(None::<String>).with_build(None::<String>)
+  }
+
+  /// Whether this is the release pre_release was for: a release, pre_release a pre-release, and the same otherwise
+  /// 2.0.0 is the promotion of 2.0.0-rc.3 but not of 2.0.1-rc.1, builds don't count
+  #[must_use]
+  pub fn is_promotion_of(&self, pre_release: &Self) -> bool {
+    self.pre_release.is_none() && pre_release.pre_release.is_some() && self.cmp_release(pre_release) == Ordering::Equal
+  }
+
+  // the first release of the next patch, minor or major, carrying over u32::MAX, none past u32::MAX.u32::MAX.u32::MAX
+  fn next_patch(&self) -> Option<Self> {
+    match self.patch.checked_add(1) {
+      Some(patch) => Some(Self::new(self.major, self.minor, patch)),
+      None => self.next_minor(),
+    }
+  }
+  fn next_minor(&self) -> Option<Self> {
+    match self.minor.checked_add(1) {
+      Some(minor) => Some(Self::new(self.major, minor, 0)),
+      None => self.next_major(),
+    }
+  }
+  fn next_major(&self) -> Option<Self> {
+    self.major.checked_add(1).map(|major| Self::new(major, 0, 0))
+  }
+  // the first release past this one and what it stands for in a range, a version stands for the ones that only add
+  // identifiers to it: 1.2.3 for 1.2.3.x so next is 1.2.4, and 1.2.3.4 for 1.2.3.4.x so next is 1.2.3.5.
+  // an extra version ending in a word can't be counted up, that's the next patch
+  fn next_release(&self) -> Option<Self> {
+    let counted = self.extra_version.as_deref().and_then(|extra| {
+      let (head, last) = match extra.rsplit_once('.') {
+        Some((head, last)) => (Some(head), last),
+        None => (None, extra),
+      };
+      let next = last.parse::<u64>().ok()?.checked_add(1)?;
+      Some(head.map_or_else(|| next.to_string(), |head| format!("{}.{}", head, next)))
+    });
+    match counted {
+      Some(extra) => Some(Self::new_w_extra(self.major, self.minor, self.patch, Some(extra), None, None)),
+      None => self.next_patch(),
+    }
+  }
+
+  /// Ordering without pre-release and build, what ranges compare with (see version_parser.rs#Pre-release-note)
+  #[must_use]
+  pub fn cmp_release(&self, other: &Self) -> Ordering {
+    (self.major, self.minor, self.patch).cmp(&(other.major, other.minor, other.patch))
+      .then_with(|| cmp_extra(self.extra_version.as_deref(), other.extra_version.as_deref()))
+  }
+
+  /// Semver precedence, the release then the pre-release, builds don't count
+  #[must_use]
+  pub fn cmp_precedence(&self, other: &Self) -> Ordering {
+    self.cmp_release(other).then_with(|| cmp_pre_release(self.pre_release.as_deref(), other.pre_release.as_deref()))
+  }
+
+  /// The version as matching sees it: the pre-release lowercased when it's caseless, borrowed as it is when not
+  #[must_use]
+  pub fn matched_as(&self, matching: Matching) -> Cow<'_, Self> {
+    match &self.pre_release {
+      Some(pre) if matching.caseless_pre_release && pre.chars().any(|c| c.is_ascii_uppercase()) => {
+        Cow::Owned(self.with_pre_release(Some(pre.to_ascii_lowercase())))
+      },
+      _ => Cow::Borrowed(self),
+    }
+  }
+
+  /// Same as cmp, with matching's rules
+  #[must_use]
+  pub fn cmp_in(&self, other: &Self, matching: Matching) -> Ordering {
+    self.matched_as(matching).cmp(&other.matched_as(matching))
+  }
+
+  /// Same as cmp, with the component that decided it, e.g. "differs at minor: 2 < 5"
+  #[must_use]
+  pub fn explain_cmp(&self, other: &Self) -> CmpExplanation {
+    let explained = |component, detail: String| {
+      CmpExplanation { ordering: self.cmp(other), component: Some(component), detail }
+    };
+    for (component, a, b) in [("major", self.major, other.major), ("minor", self.minor, other.minor), ("patch", self.patch, other.patch)] {
+      if a != b {
+        return explained(component, format!("{} {} {}", a, symbol(a.cmp(&b)), b));
+      }
+    }
+    let order = cmp_extra(self.extra_version.as_deref(), other.extra_version.as_deref());
+    if order != Ordering::Equal {
+      return explained("extra_version", format!("{} {} {}", label(&self.extra_version), symbol(order), label(&other.extra_version)));
+    }
+    let order = cmp_pre_release(self.pre_release.as_deref(), other.pre_release.as_deref());
+    if order != Ordering::Equal {
+      return explained("pre_release", explain_pre_release(self.pre_release.as_deref().unwrap_or_default(), other.pre_release.as_deref().unwrap_or_default(), order));
+    }
+    if self.build != other.build {
+      let order = self.build.cmp(&other.build);
+      return explained("build", format!("{} {} {}, builds only break ties, ranges and semver precedence ignore them", label(&self.build), symbol(order), label(&other.build)));
+    }
+    CmpExplanation { ordering: self.cmp(other), component: None, detail: String::new() }
+  }
+
+  /// How many releases apart the two are, either way around. The parts after the first that differs count from 0
+  /// of the newer one: 4.1.3 and 4.3.1 are 2 minors and 1 patch apart, pre-releases and builds don't count
+  #[must_use]
+  pub fn distance(&self, other: &Self) -> Distance {
+    let (older, newer) = if self.cmp_release(other) == Ordering::Greater { (other, self) } else { (self, other) };
+    let majors = newer.major - older.major;
+    let minors = if majors == 0 { newer.minor - older.minor } else { newer.minor };
+    let patches = if majors == 0 && minors == 0 { newer.patch - older.patch } else { newer.patch };
+    Distance { majors, minors, patches }
+  }
+
+  /// (extra_version, pre_release, build), for what keeps versions outside this module
+  #[cfg(feature = "intern")]
+  pub(crate) fn labels(&self) -> (Option<&str>, Option<&str>, Option<&str>) {
+    (self.extra_version.as_deref(), self.pre_release.as_deref(), self.build.as_deref())
+  }
+
+  fn hash_release<H: Hasher>(&self, state: &mut H) {
+    (self.major, self.minor, self.patch).hash(state);
+    hash_identifiers(&self.extra_version, state);
+  }
+  fn hash_pre_release<H: Hasher>(&self, state: &mut H) {
+    hash_identifiers(&self.pre_release, state);
+  }
+}
+
+// numeric identifiers by value, so 01 and 1 hash the same like cmp_pre_release and cmp_extra have them equal
+fn hash_identifiers<H: Hasher>(label: &Option<String>, state: &mut H) {
+  label.is_some().hash(state);
+  for identifier in label.iter().flat_map(|label| label.split('.')) {
+    match identifier.parse::<u64>() {
+      Ok(number) => number.hash(state),
+      Err(_) => identifier.hash(state),
+    }
+  }
+}
+
+// which versions are the same depends on who asks, each of these picks one for sets and maps:
+//   StrictKey      every field, builds too, like Version's Ord but with 4.3.0-rc.01 the same as 4.3.0-rc.1
+//   PrecedenceKey  semver precedence, 4.2.1+a and 4.2.1+b are the same
+//   RangeKey       what range bounds see, 4.3.0-rc.1 is 4.3.0 too (see version_parser.rs#Pre-release-note)
+// Eq, Ord and Hash always agree
+macro_rules! version_key {
+  ($(#[$doc:meta])* $name:ident, |$a:ident, $b:ident| $cmp:expr, |$v:ident, $state:ident| $hash:expr) => {
+    $(#[$doc])*
+    #[derive(Debug, Clone)]
+    pub struct $name(pub Version);
+
+    impl Ord for $name {
+      fn cmp(&self, other: &Self) -> Ordering {
+        let ($a, $b) = (&self.0, &other.0);
+        $cmp
+      }
+    }
+    impl PartialOrd for $name {
+      fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+      }
+    }
+    impl PartialEq for $name {
+      fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+      }
+    }
+    impl Eq for $name {}
+    impl Hash for $name {
+      fn hash<H: Hasher>(&self, $state: &mut H) {
+        let $v = &self.0;
+        $hash
+      }
+    }
+    impl From<Version> for $name {
+      fn from(version: Version) -> Self {
+        Self(version)
+      }
+    }
+  };
+}
+
+version_key!(
+  /// Every field counts, builds too, pre-release numbers by their value
+  StrictKey,
+  |a, b| a.cmp_precedence(b).then_with(|| a.build.cmp(&b.build)),
+  |v, state| { v.hash_release(state); v.hash_pre_release(state); v.build.hash(state) }
+);
+version_key!(
+  /// Builds don't count
+  PrecedenceKey,
+  |a, b| a.cmp_precedence(b),
+  |v, state| { v.hash_release(state); v.hash_pre_release(state) }
+);
+version_key!(
+  /// Neither pre-releases nor builds count, a pre-release is its release
+  RangeKey,
+  |a, b| a.cmp_release(b),
+  |v, state| v.hash_release(state)
+);
+
+/// What Version::explain_cmp found, component is none when the versions are the same
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CmpExplanation {
+  pub ordering: Ordering,
+  /// major, minor, patch, extra_version, pre_release or build
+  pub component: Option<&'static str>,
+  pub detail: String,
+}
+
+impl Display for CmpExplanation {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self.component {
+      Some(component) => write!(f, "differs at {}: {}", component, self.detail),
+      None => write!(f, "equal in every component"),
+    }
+  }
+}
+
+/// What Version::distance found, in releases of each part
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Distance {
+  pub majors: u32,
+  pub minors: u32,
+  pub patches: u32,
+}
+
+impl Distance {
+  /// One number to sort or compare by, in minors: a major counts as 10 of them and a patch as a tenth
+  pub fn scalar(&self) -> f64 {
+    self.majors as f64 * 10.0 + self.minors as f64 + self.patches as f64 / 10.0
+  }
+
+  pub fn is_zero(&self) -> bool {
+    *self == Self::default()
+  }
+}
+
+impl Display for Distance {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    let parts: Vec<String> = [(self.majors, "major"), (self.minors, "minor"), (self.patches, "patch")].iter()
+      .filter(|(n, _)| *n > 0)
+      .map(|(n, part)| format!("{} {}{}", n, part, match (*n, *part) { (1, _) => "", (_, "patch") => "es", _ => "s" }))
+      .collect();
+    match parts.is_empty() {
+      true => write!(f, "the same release"),
+      false => write!(f, "{}", parts.join(", ")),
+    }
+  }
+}
+
+fn symbol(order: Ordering) -> &'static str {
+  match order {
+    Ordering::Less => "<",
+    Ordering::Equal => "=",
+    Ordering::Greater => ">",
+  }
+}
+
+fn label(part: &Option<String>) -> &str {
+  part.as_deref().unwrap_or("none")
+}
+
+// the same walk as cmp_pre_release, saying where it stopped, an empty one is a release
+fn explain_pre_release(a: &str, b: &str, order: Ordering) -> String {
+  if a.is_empty() || b.is_empty() {
+    let (a, b) = (if a.is_empty() { "release" } else { a }, if b.is_empty() { "release" } else { b });
+    return format!("{} {} {}, a release comes after its pre-releases", a, symbol(order), b);
+  }
+  let (ids_a, ids_b): (Vec<&str>, Vec<&str>) = (a.split('.').collect(), b.split('.').collect());
+  for (i, (x, y)) in ids_a.iter().zip(&ids_b).enumerate() {
+    let why = match (x.parse::<u64>(), y.parse::<u64>()) {
+      (Ok(n), Ok(m)) if n != m => "numbers compare by value",
+      (Ok(_), Err(_)) | (Err(_), Ok(_)) => "numbers come before words",
+      (Err(_), Err(_)) if x != y => "words compare as text",
+      _ => continue,
+    };
+    return format!("identifier {} of {} and {}: {} {} {}, {}", i + 1, a, b, x, symbol(order), y, why);
+  }
+  format!("{} {} {}, with the same identifiers first fewer of them comes first", a, symbol(order), b)
+}
+
+// semver's pre-release precedence: a release is newer than its pre-releases, identifiers are compared one by one,
+// numbers numerically (so the date of 4.4.0-nightly.20240315 orders chronologically) and before words, fewer is older
+pub(crate) fn cmp_pre_release(a: Option<&str>, b: Option<&str>) -> Ordering {
+  match (a, b) {
+    (None, None) => Ordering::Equal,
+    (None, Some(_)) => Ordering::Greater,
+    (Some(_), None) => Ordering::Less,
+    (Some(a), Some(b)) => {
+      let (mut a, mut b) = (a.split('.'), b.split('.'));
+      loop {
+        match (a.next(), b.next()) {
+          (None, None) => return Ordering::Equal,
+          (None, Some(_)) => return Ordering::Less,
+          (Some(_), None) => return Ordering::Greater,
+          (Some(x), Some(y)) => {
+            let order = match (x.parse::<u64>(), y.parse::<u64>()) {
+              (Ok(x), Ok(y)) => x.cmp(&y),
+              (Ok(_), Err(_)) => Ordering::Less,
+              (Err(_), Ok(_)) => Ordering::Greater,
+              (Err(_), Err(_)) => x.cmp(y),
+            };
+            if order != Ordering::Equal {
+              return order;
+            }
+          },
+        }
+      }
+    },
+  }
+}
+
+// the extra version's precedence: none is the release itself so it comes first, then the identifiers one by one like
+// a pre-release's, 4.3.1.10 after 4.3.1.9 and 4.3.1.2.1 after 4.3.1.2
+pub(crate) fn cmp_extra(a: Option<&str>, b: Option<&str>) -> Ordering {
+  match (a, b) {
+    (None, None) => Ordering::Equal,
+    (None, Some(_)) => Ordering::Less,
+    (Some(_), None) => Ordering::Greater,
+    (a, b) => cmp_pre_release(a, b),
   }
 }
 
 impl PartialOrd<Version> for Version {
   fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-    if self.major < other.major {
-      Some(Ordering::Less)
-    } else if self.major > other.major {
-      Some(Ordering::Greater)
-    } else if self.minor < other.minor {
-      Some(Ordering::Less)
-    } else if self.minor > other.minor {
-      Some(Ordering::Greater)
-    } else if self.patch < other.patch {
-      Some(Ordering::Less)
-    } else if self.patch > other.patch {
-      Some(Ordering::Greater)
-    } else if self.extra_version < other.extra_version { //TODO check if this works since it's optional
-      Some(Ordering::Less)
-    } else if self.extra_version > other.extra_version {
-      Some(Ordering::Greater)
-      // pre-release isn't checked because this is for implmenting ranges, see version_parser.rs#Pre-release-note
-    } else {
-      Some(Ordering::Equal)
-    }
+    Some(self.cmp(other))
   }
 }
+
 impl Ord for Version {
+  // every field counts so it agrees with Eq: pre-release then build break ties between equal releases,
+  // and last the pre-release and extra version as written, 4.3.0-01 has the precedence of 4.3.0-1 but isn't it
+  // taken apart without .. so a new field doesn't compile until it has its place here, and in SortKey and InternedVersion
   fn cmp(&self, other: &Self) -> Ordering {
-    self.partial_cmp(other).unwrap()
+    let Version { major: _, minor: _, patch: _, extra_version, pre_release, build, written_widths: _ } = self;
+    self.cmp_precedence(other)
+      .then_with(|| build.cmp(&other.build))
+      .then_with(|| pre_release.cmp(&other.pre_release))
+      .then_with(|| extra_version.cmp(&other.extra_version))
   }
 }
 
+// an identifier the grammar reads without quotes
+fn is_plain(identifier: &str) -> bool {
+  !identifier.is_empty() && identifier.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+// identifier with a \ before what would end it in quotes (see grammer.rs#quoted)
+fn escaped(identifier: &str, stops: &[char]) -> String {
+  identifier.chars().fold(String::new(), |mut s, c| {
+    if c == '"' || c == '\\' || stops.contains(&c) {
+      s.push('\\');
+    }
+    s.push(c);
+    s
+  })
+}
+
 impl Display for Version {
+  // in quotes when an identifier has a character the unquoted grammar doesn't, so it parses back the same
   fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    let identifiers = [&self.extra_version, &self.pre_release, &self.build];
+    let quoted = identifiers.iter().any(|i| i.as_deref().is_some_and(|i| !is_plain(i)));
     let mut s = format!("{}.{}.{}", self.major, self.minor, self.patch);
-    if let Some(extra_version) = &self.extra_version {
+    let mut push = |sep: char, identifier: &Option<String>, stops: &[char]| if let Some(identifier) = identifier {
+      s.push(sep);
+      s.push_str(&if quoted { escaped(identifier, stops) } else { identifier.clone() });
+    };
+    push('.', &self.extra_version, &['-', '+']);
+    push('-', &self.pre_release, &['+']);
+    push('+', &self.build, &[]);
+    if quoted {
+      write!(f, "\"{}\"", s)
+    } else {
+      write!(f, "{}", s)
+    }
+  }
+}
+
+/// Version's Ord as a plain value: pre-release identifiers split (and numbers parsed) once instead of on every comparison
+/// the derived ordering of the fields is the same as comparing the versions it's from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SortKey<'a> {
+  release: (u32, u32, u32),
+  extra_version: Option<Rest<'a>>,
+  /// after every pre-release of it
+  is_release: bool,
+  // the first two identifiers inline, R's are never longer (rc.1, nightly.20240315), what's left compared as text would be
+  pre_release: [Option<Identifier<'a>>; 2],
+  pre_release_rest: Option<Rest<'a>>,
+  build: Option<&'a str>,
+  /// the pre-release and extra version as written, Version's last tie breaks
+  pre_release_text: Option<&'a str>,
+  extra_version_text: Option<&'a str>,
+}
+
+// numbers before words, like cmp_pre_release
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Identifier<'a> {
+  Number(u64),
+  Word(&'a str),
+}
+
+impl<'a> Identifier<'a> {
+  fn parse(identifier: &'a str) -> Self {
+    identifier.parse().map(Identifier::Number).unwrap_or(Identifier::Word(identifier))
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Rest<'a>(&'a str);
+
+impl Ord for Rest<'_> {
+  fn cmp(&self, other: &Self) -> Ordering {
+    cmp_pre_release(Some(self.0), Some(other.0))
+  }
+}
+
+impl PartialOrd for Rest<'_> {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl Version {
+  #[must_use]
+  pub fn sort_key(&self) -> SortKey<'_> {
+    let mut parts = self.pre_release.as_deref().map(|pre| pre.splitn(3, '.'));
+    let mut next = || parts.as_mut().and_then(|p| p.next());
+    SortKey {
+      release: (self.major, self.minor, self.patch),
+      extra_version: self.extra_version.as_deref().map(Rest),
+      is_release: self.pre_release.is_none(),
+      pre_release: [next().map(Identifier::parse), next().map(Identifier::parse)],
+      pre_release_rest: next().map(Rest),
+      build: self.build.as_deref(),
+      pre_release_text: self.pre_release.as_deref(),
+      extra_version_text: self.extra_version.as_deref(),
+    }
+  }
+}
+
+/// Sorts like items.sort_by(|a, b| version(a).cmp(version(b))), stable too, with each key made once
+/// for big lists with many pre-releases of the same release (a nightly index), where comparing Versions splits them over and over
+/// benches/versions.rs has it about 3 times faster there, and slower than a plain sort on mostly releases
+pub fn sort_by_cached_precedence_key<T>(items: &mut [T], version: impl Fn(&T) -> &Version) {
+  let order: Vec<usize> = {
+    let mut keyed: Vec<(SortKey, usize)> = items.iter().enumerate().map(|(i, item)| (version(item).sort_key(), i)).collect();
+    keyed.sort_unstable(); // the index breaks ties, so it's stable anyway
+    keyed.into_iter().map(|(_, i)| i).collect()
+  };
+  // items[i] = old items[order[i]], one cycle of the permutation at a time
+  let mut done = vec![false; items.len()];
+  for start in 0..items.len() {
+    let mut i = start;
+    while !done[i] {
+      done[i] = true;
+      let from = order[i];
+      if from == start {
+        break;
+      }
+      items.swap(i, from);
+      i = from;
+    }
+  }
+}
+
+/// A key ordered the way range bounds see versions, by release first, so a BTreeSet of them can be scanned for a range
+/// At is a version, ordered like Version's Ord among the ones with its release (which is Version's order overall)
+/// Below is a bound, before every version with its release: Below(min)..Below(max) is what a range's min and max let in
+#[derive(Debug, Clone)]
+pub enum RangeBound {
+  Below(Version),
+  At(Version),
+}
+
+impl RangeBound {
+  pub fn version(&self) -> &Version {
+    match self {
+      RangeBound::Below(version) | RangeBound::At(version) => version,
+    }
+  }
+}
+
+impl Ord for RangeBound {
+  fn cmp(&self, other: &Self) -> Ordering {
+    self.version().cmp_release(other.version()).then_with(|| match (self, other) {
+      (RangeBound::Below(_), RangeBound::Below(_)) => Ordering::Equal,
+      (RangeBound::Below(_), RangeBound::At(_)) => Ordering::Less,
+      (RangeBound::At(_), RangeBound::Below(_)) => Ordering::Greater,
+      (RangeBound::At(a), RangeBound::At(b)) => a.cmp(b),
+    })
+  }
+}
+
+impl PartialOrd for RangeBound {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl PartialEq for RangeBound {
+  fn eq(&self, other: &Self) -> bool {
+    self.cmp(other) == Ordering::Equal
+  }
+}
+
+impl Eq for RangeBound {}
+
+/// items grouped by precedence, the ones whose versions only differ in build metadata (4.3.1, 4.3.1+patched) together
+/// the oldest class first, each in the order its items came in
+pub fn precedence_classes<T>(items: &[T], version: impl Fn(&T) -> &Version) -> Vec<Vec<&T>> {
+  let mut classes: BTreeMap<PrecedenceKey, Vec<&T>> = BTreeMap::new();
+  for item in items {
+    classes.entry(PrecedenceKey(version(item).clone())).or_default().push(item);
+  }
+  classes.into_values().collect()
+}
+
+/// Prints versions in other shapes than Display, for file names and log prefixes that have to sort as text
+/// e.g. padding to (2, 2, 3) with the prefix gives v03.01.002 for 3.1.2
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VersionFormatter {
+  widths: (usize, usize, usize),
+  v_prefix: bool,
+  hide_extra_version: bool,
+  hide_build: bool,
+  keep_zeros: bool,
+}
+
+impl VersionFormatter {
+  /// Formats like Display until changed
+  pub fn new() -> Self {
+    Self::default()
+  }
+  /// Zero-pads major, minor and patch to these widths, longer numbers are kept whole
+  pub fn pad(&mut self, major: usize, minor: usize, patch: usize) -> &mut Self {
+    self.widths = (major, minor, patch);
+    self
+  }
+  pub fn v_prefix(&mut self, v_prefix: bool) -> &mut Self {
+    self.v_prefix = v_prefix;
+    self
+  }
+  pub fn hide_extra_version(&mut self, hide: bool) -> &mut Self {
+    self.hide_extra_version = hide;
+    self
+  }
+  pub fn hide_build(&mut self, hide: bool) -> &mut Self {
+    self.hide_build = hide;
+    self
+  }
+  /// Writes major, minor and patch with the leading zeros they were parsed with, 1.02.003 stays that, padding still applies
+  pub fn keep_zeros(&mut self, keep: bool) -> &mut Self {
+    self.keep_zeros = keep;
+    self
+  }
+  pub fn format(&self, version: &Version) -> String {
+    let written = |i: usize| if self.keep_zeros { version.written_widths[i] as usize } else { 0 };
+    let (major, minor, patch) = (self.widths.0.max(written(0)), self.widths.1.max(written(1)), self.widths.2.max(written(2)));
+    let mut s = format!("{}{:0major$}.{:0minor$}.{:0patch$}", if self.v_prefix { "v" } else { "" }, version.major, version.minor, version.patch);
+    if let Some(extra_version) = version.extra_version.as_ref().filter(|_| !self.hide_extra_version) {
       s.push_str(&format!(".{}", extra_version));
     }
-    if let Some(pre_release) = &self.pre_release {
+    if let Some(pre_release) = &version.pre_release {
       s.push_str(&format!("-{}", pre_release));
     }
-    if let Some(build) = &self.build {
+    if let Some(build) = version.build.as_ref().filter(|_| !self.hide_build) {
       s.push_str(&format!("+{}", build));
     }
-    write!(f, "{}", s)
+    s
   }
 }
 
@@ -285,22 +943,28 @@ impl Default for Version {
       extra_version: None,
       pre_release: None,
       build: None,
+      written_widths: [0; 3],
     }
   }
 }
 
-#[derive(Debug, Default, PartialEq, Eq, DeserializeFromStr, SerializeDisplay)] //default should be equal to *
+#[derive(Debug, Clone, Default, PartialEq, Eq, DeserializeFromStr, SerializeDisplay)] //default should be equal to *
 // debug is needed for assert_eq
 pub struct Range { //TODO should implement exclusion ranges?
   pub min: Option<Version>, //inclusive
   pub max: Option<Version>, //exclusive, because it's hard to go back to the previous version
   pub except: Vec<Version>,
-  pub include: Vec<Version>
+  pub include: Vec<Version>,
+  /// the range as written when it names versions (>=lts), see Range::named, none for everything else
+  pub named: Option<String>,
 }
 
 impl Display for Range {
 
   fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    if let Some(named) = &self.named {
+      return write!(f, "{}", named);
+    }
     if self.is_any() {
       return write!(f, "*");
     }
@@ -312,28 +976,127 @@ impl Display for Range {
       return write!(f, "{}", tilde);
     }
 
-    let mut s = String::new();
-    if let Some(min) = &self.min {
-      s.push_str(&format!(">={},", min.to_string()));
-    }
-    if let Some(max) = &self.max {
-      s.push_str(&format!("<{},", max.to_string()));
-    }
-    for except in &self.except {
-      s.push_str(&format!("!={},", except.to_string()));
-    }
-    for include in &self.include {
-      s.push_str(&format!("={},", include.to_string()));
-    }
-    s.pop(); //remove the last comma
-    write!(f, "{}", s)
+    let comparators: Vec<String> = self.comparators().iter().map(|c| c.to_string()).collect();
+    write!(f, "{}", comparators.join(","))
   }
 }
 
 
 impl Range {
-  fn contains(&self, version: Version) -> bool {
-    todo!()
+  pub fn contains(&self, version: &Version) -> bool {
+    self.matcher()(version)
+  }
+  // contains with what doesn't depend on the version worked out once, for the batch checks
+  fn matcher(&self) -> impl Fn(&Version) -> bool + '_ {
+    // include is added on top of the bounds, except is taken out of both
+    // bounds compare releases only, so pre-releases and builds count as the release (see version_parser.rs#Pre-release-note)
+    // include and except are exact
+    let bounded = self.min.is_some() || self.max.is_some();
+    let anything = !bounded && self.include.is_empty();
+    move |version| {
+      let in_bounds = bounded
+        && self.min.as_ref().map_or(true, |min| version.cmp_release(min) != Ordering::Less)
+        && self.max.as_ref().map_or(true, |max| version.cmp_release(max) == Ordering::Less);
+      let included = self.include.iter().any(|v| v.cmp(version) == Ordering::Equal);
+      let matches = in_bounds || included || anything;
+      matches && !self.except.iter().any(|v| v.cmp(version) == Ordering::Equal)
+    }
+  }
+  /// Whether every one of versions is in the range, true for none
+  pub fn contains_all(&self, versions: &[Version]) -> bool {
+    versions.iter().all(self.matcher())
+  }
+  /// Whether any of versions is in the range, false for none
+  pub fn contains_any(&self, versions: &[Version]) -> bool {
+    versions.iter().any(self.matcher())
+  }
+  /// versions split into the ones in the range and the rest, both in the order they came in
+  pub fn partition<'a>(&self, versions: &'a [Version]) -> (Vec<&'a Version>, Vec<&'a Version>) {
+    let contains = self.matcher();
+    versions.iter().partition(|v| contains(v))
+  }
+  /// Same as contains, with matching's rules for the range's versions and version
+  pub fn contains_in(&self, version: &Version, matching: Matching) -> bool {
+    if matching == Matching::default() {
+      return self.contains(version);
+    }
+    let fold = |versions: &[Version]| versions.iter().map(|v| v.matched_as(matching).into_owned()).collect();
+    let range = Range {
+      min: self.min.as_ref().map(|v| v.matched_as(matching).into_owned()),
+      max: self.max.as_ref().map(|v| v.matched_as(matching).into_owned()),
+      except: fold(&self.except),
+      include: fold(&self.include),
+      named: self.named.clone(),
+    };
+    range.contains(&version.matched_as(matching))
+  }
+  /// Whether a version in the range is a pre-release, so asking for one makes pre-releases fair game
+  pub fn names_pre_release(&self) -> bool {
+    self.min.iter().chain(&self.max).chain(&self.include).any(|v| v.pre_release.is_some())
+  }
+  /// The smallest version the range allows, released or not, e.g. 4.2.2 for ">=4.2.1, !=4.2.1"
+  /// a pre-release only when the range names one, none when nothing fits
+  pub fn minimal_version(&self) -> Option<Version> {
+    let pre_release = self.names_pre_release();
+    let mut candidates: Vec<Version> = self.include.iter().filter(|v| pre_release || v.pre_release.is_none()).cloned().collect();
+    if self.min.is_some() || self.max.is_some() || self.include.is_empty() {
+      let min = self.min.clone().unwrap_or(Version::new(0, 0, 0));
+      let mut first = Some(if pre_release { min } else { min.with_pre_release(None::<String>).with_build(None::<String>) });
+      // step over the holes at the bottom, except is finite so this ends
+      while let Some(version) = first.as_ref().filter(|v| !self.contains(v) && self.except.contains(v)) {
+        first = version.next_release();
+      }
+      candidates.extend(first);
+    }
+    candidates.into_iter().filter(|v| self.contains(v)).min()
+  }
+  /// The versions except takes out from between the bounds, e.g. 4.2.1 in ">=4.2.0, <4.3.0, !=4.2.1"
+  /// excepting something the bounds already leave out isn't a hole
+  pub fn holes(&self) -> Vec<&Version> {
+    let bounds = Range { except: vec![], ..self.clone() };
+    self.except.iter().filter(|v| bounds.contains(v)).collect()
+  }
+  pub fn has_hole(&self) -> bool {
+    !self.holes().is_empty()
+  }
+  /// The newest version in versions that is in the range
+  pub fn max_satisfying<'a>(&self, versions: impl IntoIterator<Item = &'a Version>) -> Option<&'a Version> {
+    versions.into_iter().filter(|v| self.contains(v)).max()
+  }
+  /// The comparator that keeps version out of the range, none when it's in
+  /// a version below the range and excepted is out because of the bound
+  pub fn excluded_by(&self, version: &Version) -> Option<Comparator> {
+    if self.contains(version) {
+      return None;
+    }
+    let comparators = self.comparators();
+    comparators.iter()
+      .find(|c| match c.op {
+        Op::Ge => version.cmp_release(&c.version) == Ordering::Less,
+        Op::Lt => version.cmp_release(&c.version) != Ordering::Less,
+        Op::Ne => &c.version == version,
+        _ => false,
+      })
+      // unbounded, so only include could have let it in
+      .or(comparators.iter().find(|c| c.op == Op::Eq))
+      .cloned()
+  }
+  /// The range of only this version
+  pub fn exactly(version: Version) -> Self {
+    Self { include: vec![version], ..Self::default() }
+  }
+  /// Like parse, but a bare partial version means its whole series, so "4" is ^4 and "4.2" is ~4.2
+  /// This is what you'd expect from `rvm install 4.2`
+  pub fn from_request(request: &str) -> Result<Self, ParseError> {
+    let request = request.trim();
+    let request = request.strip_prefix(['v', 'V']).unwrap_or(request);
+    let components = request.split('.').collect::<Vec<_>>();
+    let is_partial = components.len() < 3 && components.iter().all(|c| !c.is_empty() && c.chars().all(|ch| ch.is_ascii_digit()));
+    match (is_partial, components.len()) {
+      (true, 1) => Self::parse(&format!("^{}", request)),
+      (true, _) => Self::parse(&format!("~{}", request)),
+      _ => Self::parse(request),
+    }
   }
   fn is_any(&self) -> bool { // is empty or is just >= 0.0.0
     (self.min.clone().is_none() || self.min.clone().unwrap() == Version::new(0, 0, 0))
@@ -349,11 +1112,11 @@ impl Range {
     todo!()
   }
   fn to_caret(&self) -> Option<String> {
-    //transforms range to caret range if appropriate
-    if self.min.is_some() && self.max.is_some() {
+    //transforms range to caret range if appropriate, it can't say what's excepted or included
+    if self.min.is_some() && self.max.is_some() && self.except.is_empty() && self.include.is_empty() {
       let min = self.min.as_ref().unwrap();
       let max= self.max.as_ref().unwrap();
-      if max.patch == 0 && max.minor == 0 && max.major == min.major + 1 {
+      if max.patch == 0 && max.minor == 0 && min.major.checked_add(1) == Some(max.major) {
         return Some(format!("^{}", min));
       }
     }
@@ -361,109 +1124,513 @@ impl Range {
   }
 
   fn to_tilde(&self) -> Option<String> {
-    //transforms range to tilde range if appropriate
-    if self.min.is_some() && self.max.is_some() {
+    //transforms range to tilde range if appropriate, it can't say what's excepted or included
+    if self.min.is_some() && self.max.is_some() && self.except.is_empty() && self.include.is_empty() {
       let min = self.min.clone().unwrap();
       let max = self.max.clone().unwrap();
-      if max.patch == 0 && max.minor == min.minor + 1 && max.major == min.major {
+      if max.patch == 0 && min.minor.checked_add(1) == Some(max.minor) && max.major == min.major {
         return Some(format!("~{}", min));
       }
     }
     return None
   }
-  fn separate_ops(ranges: Vec<(Op, Version)>) -> HashMap<Op, Vec<Version>> {
-    let mut map = HashMap::new();
+  fn separate_ops(ranges: Vec<(Op, Version)>) -> BTreeMap<Op, Vec<Version>> {
+    let mut map = BTreeMap::new();
     for (op, version) in ranges {
       map.entry(op).or_insert_with(Vec::new).push(version);
     }
     map
   }
 
-  pub fn from_ver_vec(ranges: Vec<(Op, Version)>) -> Self {
+  /// What the range is made of, in the order it's printed: >=min, <max, then every != and =
+  /// ~, ^, <= and > were already turned into these, so from_comparators(r.comparators()) is r again
+  pub fn comparators(&self) -> Vec<Comparator> {
+    let with = |op: Op| move |version: &Version| Comparator { op, version: version.clone() };
+    self.min.iter().map(with(Op::Ge))
+      .chain(self.max.iter().map(with(Op::Lt)))
+      .chain(self.except.iter().map(with(Op::Ne)))
+      .chain(self.include.iter().map(with(Op::Eq)))
+      .collect()
+  }
+
+  /// The range of a list of comparators, any op goes, like the ones parse reads
+  /// fails with Overflow when one can't be expanded, like >u32::MAX.u32::MAX.u32::MAX
+  pub fn from_comparators(comparators: impl IntoIterator<Item = Comparator>) -> Result<Self, ParseError> {
+    Self::from_ver_vec(comparators.into_iter().map(|c| (c.op, c.version)).collect())
+  }
+
+  /// The range every comparator holds in, their intersection: ">=1.0, >=2.0" starts at 2.0 and "<3.0, <2.5" ends before 2.5
+  pub fn from_ver_vec(ranges: Vec<(Op, Version)>) -> Result<Self, ParseError> {
     // Sort the ranges by version number
-    let mut ranges:Vec<(Op, Version)> = Self::sort_vec(ranges);
+    let mut ranges:Vec<(Op, Version)> = Self::sort_vec(ranges)?;
     // separate the ranges by operator
-    let mut map:HashMap<Op, Vec<Version>> = Self::separate_ops(ranges);
-    // atribute the ranges to the correct fields
-    let min:Option<Version> = (*map.get(&Op::Ge).unwrap_or(&vec![])).first().cloned();
-    let max:Option<Version> = (*map.get(&Op::Lt).unwrap_or(&vec![])).last().cloned();
+    let mut map:BTreeMap<Op, Vec<Version>> = Self::separate_ops(ranges);
+    // atribute the ranges to the correct fields, the tightest bounds win (the buckets are sorted so the last >= and the first <)
+    let min:Option<Version> = (*map.get(&Op::Ge).unwrap_or(&vec![])).last().cloned();
+    let max:Option<Version> = (*map.get(&Op::Lt).unwrap_or(&vec![])).first().cloned();
     let except = map.get(&Op::Ne).unwrap_or(&vec![]).clone();
     let include = map.get(&Op::Eq).unwrap_or(&vec![]).clone();
-    Range { //Note: this can return an invalid range, that's why we have is_valid
+    Ok(Range { //Note: this can return an invalid range, that's why we have is_valid
       min,
       max,
       except,
       include,
-    }
+      named: None,
+    })
   }
-  fn mixed_vec_to_stand_vec(ranges: Vec<(Op, Version)>) -> Vec<(Op, Version)> {
+  pub(crate) fn mixed_vec_to_stand_vec(ranges: Vec<(Op, Version)>) -> Result<Vec<(Op, Version)>, ParseError> {
     // Expand tilde, caret, le and gt ranges to simple lt and ge ranges
-    ranges.into_iter().flat_map(|(op, version)| {
+    let mut ret = vec![];
+    for (op, version) in ranges {
       match op {
-        Op::Tilde => Self::tilde_range_to_vec(version),
-        Op::Caret => Self::caret_range_to_vec(version),
-        Op::Le => Self::le_range_to_vec(version),
-        Op::Gt => Self::gt_range_to_vec(version),
-        _ => vec![(op, version)],
+        Op::Tilde => ret.extend(Self::tilde_range_to_vec(version)),
+        Op::Caret => ret.extend(Self::caret_range_to_vec(version)),
+        Op::Le => ret.extend(Self::le_range_to_vec(version)),
+        Op::Gt => ret.extend(Self::gt_range_to_vec(version)?),
+        _ => ret.push((op, version)),
       }
-    }).collect::<Vec<_>>()
+    }
+    Ok(ret)
   }
 
-  fn sort_vec(ranges: Vec<(Op, Version)>) -> Vec<(Op, Version)> {
+  fn sort_vec(ranges: Vec<(Op, Version)>) -> Result<Vec<(Op, Version)>, ParseError> {
     // Expand tilde, caret, le and gt ranges to simple lt and ge ranges, and sort them ranges by version number,
 
-    let mut ranges = Self::mixed_vec_to_stand_vec(ranges);
-    ranges.sort_by(|(_, a), (_, b)| a.cmp(&b));
-    ranges
+    let mut ranges = Self::mixed_vec_to_stand_vec(ranges)?;
+    ranges.sort_by(|(op_a, a), (op_b, b)| a.cmp(b).then(op_a.cmp(op_b)));
+    Ok(ranges)
   }
 
+  /// A range with any of the separators Dialect knows, e.g. ">=4.1 && <5" or ">=4.1, <5"
   pub fn parse(range: &str) -> Result<Self, ParseError> {
-    let range : Self = parse_range(range).map_err(|_| ParseError::InvalidRange)?;
-    Ok(range)
+    Self::parse_in(range, Dialect::default())
+  }
+  /// Same as parse, with only the conjunctions dialect allows
+  pub fn parse_in(range: &str, dialect: Dialect) -> Result<Self, ParseError> {
+    Self::parse_limited(range, dialect, Limits::default())
+  }
+  /// Same as parse_in, with other limits than the default ones
+  pub fn parse_limited(range: &str, dialect: Dialect, limits: Limits) -> Result<Self, ParseError> {
+    limits.check(range)?;
+    // what Display writes for any version
+    if range.trim() == "*" {
+      return Ok(Self::default());
+    }
+    let range = operators::expand(range)?;
+    let written = parse_comparators(&range, dialect).map_err(|_| ParseError::InvalidRange)?;
+    if written.len() > limits.max_components {
+      return Err(ParseError::TooLong);
+    }
+    Self::from_written(written)
+  }
+  /// Same as parse, with the names in range (the lts of >=lts, the default of =default) replaced by the version
+  /// resolve gives for them first, a name it doesn't know fails with UnknownName
+  pub fn parse_with(range: &str, resolve: impl Fn(&str) -> Option<Version>) -> Result<Self, ParseError> {
+    let mut expanded = String::with_capacity(range.len());
+    for token in RangeAst::parse(range).tokens {
+      match token.kind {
+        TokenKind::Invalid if is_name(&token.text) => {
+          let version = resolve(&token.text).ok_or(ParseError::UnknownName(token.text))?;
+          expanded.push_str(&version.to_string());
+        },
+        _ => expanded.push_str(&token.text),
+      }
+    }
+    Self::parse(&expanded)
+  }
+  /// A range naming versions (>=lts, =default, >=4.2, <latest) kept as written, it matches nothing until with_names
+  /// gives it its versions. fails when range names nothing or wouldn't parse whatever the names stood for
+  pub fn named(range: &str) -> Result<Self, ParseError> {
+    let range = range.trim();
+    if !has_names(range) {
+      return Err(ParseError::InvalidRange);
+    }
+    Self::parse_with(range, |_| Some(Version::new(0, 0, 0)))?;
+    Ok(Self { max: Some(Version::new(0, 0, 0)), named: Some(range.to_owned()), ..Self::default() })
+  }
+  /// Whether it names versions that with_names hasn't given it yet
+  pub fn is_named(&self) -> bool {
+    self.named.is_some()
+  }
+  /// The range with the versions resolve gives for its names, itself when it names none
+  pub fn with_names(&self, resolve: impl Fn(&str) -> Option<Version>) -> Result<Self, ParseError> {
+    match &self.named {
+      Some(named) => Self::parse_with(named, resolve),
+      None => Ok(self.clone()),
+    }
+  }
+  // comparators as the grammar read them, a bare version next to another one with only spaces between is a mistake,
+  // 1.2 3.4 could be >=1.2 <3.4 or 1.2.3.4, but 1.2, 3.4 is a list and >1.2 <3.4 is clear
+  pub(crate) fn from_written(written: Vec<Written>) -> Result<Self, ParseError> {
+    if written.windows(2).any(|w| w[0].spaced && !(w[0].explicit && w[1].explicit)) {
+      return Err(ParseError::MissingOperator);
+    }
+    Self::from_comparators(written.into_iter().map(|w| w.comparator))
   }
 
   fn tilde_range_to_vec(version: Version) -> Vec<(Op, Version)> {
     // ~1.2.3 -> >=1.2.3 <1.3.0
     // ~1.2 -> >=1.2.0 <1.3.0
     // ~1 -> >=1.0.0 <1.1.0, since 1 = 1.0.0
-    vec![
-      (Op::Ge, version.clone()),
-      (Op::Lt, Version::new(version.major, version.minor + 1, 0)),
-    ]
+    // ~1.2.3.4 -> >=1.2.3.4 <1.3.0, the extra version only moves the lower bound
+    // the bumps carry, ~1.u32::MAX is <2.0.0 and with nothing left to carry into there's no upper bound
+    let max = version.next_minor();
+    std::iter::once((Op::Ge, version)).chain(max.map(|max| (Op::Lt, max))).collect()
   }
   fn caret_range_to_vec(version: Version) -> Vec<(Op, Version)> {
     // ^1.2.3 -> >=1.2.3 <2.0.0
     // ^1.2 -> >=1.2.0 <2.0.0
     // ^1 -> >=1.0.0 <2.0.0, since 1 = 1.0.0
-    vec![
-      (Op::Ge, version.clone()),
-      (Op::Lt, Version::new(version.major + 1, 0, 0)),
-    ]
+    // ^1.2.3.4 -> >=1.2.3.4 <2.0.0
+    let max = version.next_major();
+    std::iter::once((Op::Ge, version)).chain(max.map(|max| (Op::Lt, max))).collect()
   }
   fn le_range_to_lt(version: Version) -> Vec<(Op, Version)> {
     // <=1.2.3 -> <1.2.4
     // <=1.2 -> <1.2.1
     // <=1 -> <1.0.1
-    vec![
-      (Op::Lt, Version::new(version.major, version.minor, version.patch + 1)),
-    ]
+    // <=1.2.3.4 -> <1.2.3.5, 1.2.3.4.1 is in and 1.2.3.5 isn't (see next_release)
+    version.next_release().map(|max| (Op::Lt, max)).into_iter().collect()
   }
 
   fn le_range_to_vec(version:Version) ->  Vec<(Op, Version)> {Self::le_range_to_lt(version)}
 
-  fn gt_range_to_ge(version: Version) -> Vec<(Op, Version)> {
+  fn gt_range_to_ge(version: Version) -> Result<Vec<(Op, Version)>, ParseError> {
     // >1.2.3 -> >=1.2.4
     // >1.2 -> >=1.2.1
     // >1 -> >=1.0.1
-    vec![
-      (Op::Ge, Version::new(version.major, version.minor, version.patch + 1)),
-    ]
+    // >1.2.3.4 -> >=1.2.3.5, and >1.2.3 leaves out every 1.2.3.x like <=1.2.3 lets them in
+    // nothing is bigger than u32::MAX.u32::MAX.u32::MAX, so that's an error (an empty vec would be everything)
+    let min = version.next_release().ok_or(ParseError::Overflow)?;
+    Ok(vec![(Op::Ge, min)])
   }
 
-  fn gt_range_to_vec(version:Version) ->  Vec<(Op, Version)> {Self::gt_range_to_ge(version)}
+  fn gt_range_to_vec(version:Version) ->  Result<Vec<(Op, Version)>, ParseError> {Self::gt_range_to_ge(version)}
+}
+
+/// What a set of ranges (e.g. every project of a workspace) allows together, see common_bounds
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CommonBounds {
+  /// the smallest version every range has, none when they have nothing in common
+  pub minimal: Option<Version>,
+  /// the lowest upper bound (exclusive), none when none of them has one
+  pub ceiling: Option<Version>,
+  /// the range with the highest lower bound, by index, and that bound, what to relax to go lower
+  pub floor_by: Option<(usize, Comparator)>,
+  /// the range with the lowest upper bound and that bound, what to relax to go higher
+  pub ceiling_by: Option<(usize, Comparator)>,
 }
 
-#[derive(PartialEq, Eq, Hash)]
+impl CommonBounds {
+  /// The newest of versions every one of ranges has
+  pub fn newest<'a>(ranges: &[Range], versions: impl IntoIterator<Item = &'a Version>) -> Option<&'a Version> {
+    versions.into_iter().filter(|v| ranges.iter().all(|r| r.contains(v))).max()
+  }
+}
+
+/// The floor and ceiling of ranges all holding at once, and which of them set each
+/// the first one wins a tie, no ranges is every version
+pub fn common_bounds(ranges: &[Range]) -> CommonBounds {
+  let contained = |v: &Version| ranges.iter().all(|r| r.contains(v));
+  // the smallest common version is where one range starts, one of them lets in exactly, right after a hole or 0.0.0
+  let candidates = ranges.iter()
+    .flat_map(|r| r.minimal_version().into_iter().chain(r.include.iter().cloned()).chain(r.except.iter().filter_map(Version::next_release)))
+    .chain(std::iter::once(Version::new(0, 0, 0)));
+  let minimal = candidates.filter(|v| contained(v)).min();
+  let bound = |pick: fn(&Range) -> Option<&Version>, op: Op, better: Ordering| {
+    ranges.iter().enumerate()
+      .filter_map(|(i, r)| pick(r).map(|v| (i, v)))
+      .fold(None, |best: Option<(usize, &Version)>, (i, v)| match best {
+        Some((_, b)) if v.cmp_release(b) != better => best,
+        _ => Some((i, v)),
+      })
+      .map(|(i, v)| (i, Comparator { op, version: v.clone() }))
+  };
+  let floor_by = bound(|r| r.min.as_ref(), Op::Ge, Ordering::Greater);
+  let ceiling_by = bound(|r| r.max.as_ref(), Op::Lt, Ordering::Less);
+  CommonBounds { minimal, ceiling: ceiling_by.as_ref().map(|(_, c)| c.version.clone()), floor_by, ceiling_by }
+}
+
+/// How much the parsers take before giving up with TooLong, so a hostile registry or file can't make them allocate without end
+/// the defaults are far past anything R or a constraint file has
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+  /// bytes of the whole input
+  pub max_input: usize,
+  /// comparators in a range, and dot, dash or plus separated parts of a version
+  pub max_components: usize,
+  /// bytes of one of those parts, e.g. nightly or 20240315
+  pub max_identifier: usize,
+}
+
+impl Default for Limits {
+  fn default() -> Self {
+    Self { max_input: 1024, max_components: 64, max_identifier: 128 }
+  }
+}
+
+impl Limits {
+  // a lexical pass before the grammar, so the peg rules only ever see bounded input
+  fn check(&self, source: &str) -> Result<(), ParseError> {
+    if source.len() > self.max_input {
+      return Err(ParseError::TooLong);
+    }
+    let words = source.split(|c: char| c.is_whitespace() || matches!(c, ',' | ';' | '&'));
+    for word in words {
+      let mut parts = 0;
+      for part in word.split(['.', '-', '+']) {
+        parts += 1;
+        if parts > self.max_components || part.len() > self.max_identifier {
+          return Err(ParseError::TooLong);
+        }
+      }
+    }
+    Ok(())
+  }
+}
+
+/// What can join comparators besides spaces, commas and semicolons, which always can
+/// both are on by default so constraints copied from other tools parse as they are
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Dialect {
+  /// >=4.1 && <5
+  pub ampersands: bool,
+  /// >=4.1 and <5, any case
+  pub and: bool,
+}
+
+impl Dialect {
+  /// Only what rvm writes itself
+  pub const STRICT: Self = Self { ampersands: false, and: false };
+}
+
+impl Default for Dialect {
+  fn default() -> Self {
+    Self { ampersands: true, and: true }
+  }
+}
+
+/// How strictly versions are compared and matched, semver's way by default
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Matching {
+  /// RC.1 is rc.1, for ecosystems that uppercase their tags
+  pub caseless_pre_release: bool,
+}
+
+/// Another ecosystem's constraint syntax, what Range::display_as writes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Syntax {
+  /// >=4.2.0 <4.2.1 || >4.2.1 <5.0.0, the only one with ||
+  Npm,
+  /// >=4.2.0, <5.0.0
+  Cargo,
+  /// >=4.2.0, <5.0.0, !=4.2.1
+  Pep440,
+  /// >= 4.2.0, < 5.0.0, != 4.2.1
+  Ruby,
+}
+
+impl Syntax {
+  pub const ALL: [Syntax; 4] = [Syntax::Npm, Syntax::Cargo, Syntax::Pep440, Syntax::Ruby];
+
+  /// The feature that compiles it in, none for Cargo's, which is close enough to rvm's own to always be there
+  pub fn feature(self) -> Option<&'static str> {
+    match self {
+      Syntax::Npm => Some("npm"),
+      Syntax::Cargo => None,
+      Syntax::Pep440 => Some("pep440"),
+      Syntax::Ruby => Some("ruby"),
+    }
+  }
+
+  /// Whether this build can read and write it
+  pub fn is_supported(self) -> bool {
+    match self {
+      Syntax::Npm => cfg!(feature = "npm"),
+      Syntax::Cargo => true,
+      Syntax::Pep440 => cfg!(feature = "pep440"),
+      Syntax::Ruby => cfg!(feature = "ruby"),
+    }
+  }
+}
+
+impl Display for Syntax {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    let name = match self {
+      Syntax::Npm => "npm",
+      Syntax::Cargo => "Cargo",
+      Syntax::Pep440 => "PEP 440",
+      Syntax::Ruby => "Ruby",
+    };
+    write!(f, "{}", name)
+  }
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum RenderError {
+  #[error("{0} can't say {1}")]
+  Lossy(Syntax, String),
+  #[error("{0} syntax requires feature {}", .0.feature().unwrap_or_default())]
+  Unsupported(Syntax),
+}
+
+// a piece of a range without holes: from (inclusive unless right after one) to (exclusive), either open
+struct Segment<'a> {
+  from: Option<(&'a Version, bool)>,
+  to: Option<&'a Version>,
+}
+
+impl Range {
+  /// The same constraint in syntax, failing when it can't say all of it (holes in Cargo, || outside npm,
+  /// 4 component versions in semver, pre-releases PEP 440 and Ruby don't have)
+  pub fn display_as(&self, syntax: Syntax) -> Result<String, RenderError> {
+    if !syntax.is_supported() {
+      return Err(RenderError::Unsupported(syntax));
+    }
+    let lossy = |what: String| RenderError::Lossy(syntax, what);
+    let bounded = self.min.is_some() || self.max.is_some();
+    let includes: Vec<&Version> = self.include.iter()
+      .filter(|v| !self.except.contains(v))
+      .filter(|v| !bounded || !Range { include: vec![], ..self.clone() }.contains(v))
+      .collect();
+    // bounds and except as pieces without holes, empty when only include lets anything in
+    let mut segments = vec![];
+    if bounded || self.include.is_empty() {
+      let mut from = self.min.as_ref().map(|min| (min, true));
+      for hole in self.holes() {
+        segments.push(Segment { from, to: Some(hole) });
+        from = Some((hole, false));
+      }
+      segments.push(Segment { from, to: self.max.as_ref() });
+    }
+    let render = |op: &str, version: &Version| -> Result<String, RenderError> {
+      let version = render_version(version, syntax).ok_or_else(|| lossy(format!("version {}", version)))?;
+      Ok(match syntax {
+        Syntax::Ruby => format!("{} {}", op, version),
+        _ => format!("{}{}", op, version),
+      })
+    };
+    let exact = match syntax {
+      Syntax::Pep440 => "==",
+      _ => "=",
+    };
+    if syntax == Syntax::Npm {
+      let mut pieces = vec![];
+      for segment in &segments {
+        let mut comparators = vec![];
+        if let Some((from, inclusive)) = segment.from {
+          comparators.push(render(if inclusive { ">=" } else { ">" }, from)?);
+        }
+        if let Some(to) = segment.to {
+          comparators.push(render("<", to)?);
+        }
+        pieces.push(if comparators.is_empty() { "*".to_owned() } else { comparators.join(" ") });
+      }
+      for version in &includes {
+        pieces.push(render(exact, version)?);
+      }
+      return Ok(pieces.join(" || "));
+    }
+    // the others only have one list of comparators that all hold
+    if segments.len() + includes.len() > 1 && !(syntax != Syntax::Cargo && includes.is_empty()) {
+      return Err(lossy(format!("{}, it would need ||", self)));
+    }
+    if let [version] = includes.as_slice() {
+      return render(exact, version);
+    }
+    let mut comparators = vec![];
+    if let Some(min) = &self.min {
+      comparators.push(render(">=", min)?);
+    }
+    if let Some(max) = &self.max {
+      comparators.push(render("<", max)?);
+    }
+    for hole in self.holes() {
+      comparators.push(render("!=", hole)?);
+    }
+    if comparators.is_empty() {
+      return Ok(match syntax {
+        Syntax::Cargo => "*".to_owned(),
+        _ => render(">=", &Version::new(0, 0, 0))?,
+      });
+    }
+    Ok(comparators.join(", "))
+  }
+}
+
+// version as syntax writes it, none when it can't
+fn render_version(version: &Version, syntax: Syntax) -> Option<String> {
+  let release = match &version.extra_version {
+    Some(extra) if matches!(syntax, Syntax::Pep440 | Syntax::Ruby) => format!("{}.{}.{}.{}", version.major, version.minor, version.patch, extra),
+    Some(_) => return None,
+    None => format!("{}.{}.{}", version.major, version.minor, version.patch),
+  };
+  match syntax {
+    Syntax::Npm | Syntax::Cargo => Some(version.to_string()),
+    _ if version.build.is_some() => None,
+    Syntax::Ruby => Some(match &version.pre_release {
+      // rubygems takes any letter as a pre-release
+      Some(pre) if pre.starts_with(|c: char| c.is_ascii_alphabetic()) => format!("{}.{}", release, pre),
+      Some(_) => return None,
+      None => release,
+    }),
+    Syntax::Pep440 => Some(match version.pre_release.as_deref() {
+      None => release,
+      Some(pre) => {
+        let (label, number) = pre.split_once('.').unwrap_or((pre, "0"));
+        let label = match label {
+          "alpha" | "a" => "a",
+          "beta" | "b" => "b",
+          "rc" | "c" => "rc",
+          _ => return None,
+        };
+        number.parse::<u64>().ok().map(|n| format!("{}{}{}", release, label, n))?
+      },
+    }),
+  }
+}
+
+/// One constraint of a range, e.g. >=4.2.0
+#[derive(Debug, Clone, PartialEq, Eq, SerializeDisplay)]
+pub struct Comparator {
+  pub op: Op,
+  pub version: Version,
+}
+
+impl Display for Comparator {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}{}", self.op, self.version)
+  }
+}
+
+impl Comparator {
+  /// Whether version satisfies this one constraint, see matches
+  pub fn matches(&self, version: &Version) -> bool {
+    matches(self.op, version, &self.version)
+  }
+}
+
+/// Whether candidate satisfies `<op><target>`, the way a range with only that comparator would contain it, for tools
+/// that only need one comparator: = and != are exact, the others compare releases, ~ stays under the next minor
+/// (R's pessimistic operator) and ^ under the next major
+pub fn matches(op: Op, candidate: &Version, target: &Version) -> bool {
+  let at_least = |min: &Version| candidate.cmp_release(min) != Ordering::Less;
+  // a bound past u32::MAX doesn't exist, nothing is under it
+  let under = |max: Option<Version>| max.map_or(true, |max| candidate.cmp_release(&max) == Ordering::Less);
+  match op {
+    Op::Eq => candidate.cmp(target) == Ordering::Equal,
+    Op::Ne => candidate.cmp(target) != Ordering::Equal,
+    Op::Ge => at_least(target),
+    Op::Lt => candidate.cmp_release(target) == Ordering::Less,
+    Op::Gt => target.next_release().is_some_and(|min| at_least(&min)),
+    Op::Le => under(target.next_release()),
+    Op::Tilde => at_least(target) && under(target.next_minor()),
+    Op::Caret => at_least(target) && under(target.next_major()),
+  }
+}
+
+// ordered as declared, only so ops can key a BTreeMap
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Op {
   Eq,    // ==
   Ne,    // !=
@@ -475,8 +1642,10 @@ pub enum Op {
   Caret  // ^
 }
 
-impl Op {
-  pub fn from_str(op: &str) -> Result<Self, ParseError> {
+impl FromStr for Op {
+  type Err = ParseError;
+
+  fn from_str(op: &str) -> Result<Self, Self::Err> {
     match op {
       "==" | "=" | "" => Ok(Self::Eq),
       "!=" => Ok(Self::Ne),
@@ -489,4 +1658,21 @@ impl Op {
       _ => Err(ParseError::InvalidRange)
     }
   }
-}
\ No newline at end of file
+}
+
+impl Display for Op {
+  // how Range prints them, = rather than ==
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    let op = match self {
+      Self::Eq => "=",
+      Self::Ne => "!=",
+      Self::Gt => ">",
+      Self::Lt => "<",
+      Self::Ge => ">=",
+      Self::Le => "<=",
+      Self::Tilde => "~",
+      Self::Caret => "^",
+    };
+    write!(f, "{}", op)
+  }
+}
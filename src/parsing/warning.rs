@@ -0,0 +1,101 @@
+use std::cell::RefCell;
+use std::fmt::{Display, Formatter};
+use serde::{Deserialize, Serialize};
+use crate::parsing::lint::Lint;
+use crate::parsing::version_parser::{ParseError, Range, Version};
+use crate::theme;
+use crate::utils::env_flag;
+
+// things that aren't wrong enough to fail on but likely aren't what was meant, found while parsing and resolving.
+// they come back next to what worked (Warned, Active's warnings) instead of being printed where they're found,
+// so the cli decides how to show them and code embedding rvm can show them its own way.
+// --deny-warnings (RVM_DENY_WARNINGS) makes resolving fail on them instead
+
+/// set by --deny-warnings (or by hand), resolving fails on any warning
+pub const DENY_WARNINGS_VAR: &str = "RVM_DENY_WARNINGS";
+
+/// Whether warnings are errors
+pub fn warnings_denied() -> bool {
+  env_flag(DENY_WARNINGS_VAR)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum Warning {
+  /// a bound with build metadata, bounds compare releases so it's as if it wasn't there
+  BuildIgnored { version: Version, range: String },
+  /// an except the range doesn't have in the first place
+  UnreachableExcept { version: Version, range: String },
+  /// a project's lockfile that's for another rversion, the project's range is used instead
+  StaleLock { message: String },
+  /// a project's rversion or an alias past the day it was to be looked at again
+  Expired { what: String, day: String },
+}
+
+impl Display for Warning {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Warning::BuildIgnored { version, range } =>
+        write!(f, "build metadata of {} is ignored in comparison, {} compares releases", version, range),
+      Warning::UnreachableExcept { version, range } =>
+        write!(f, "{} contains an unreachable except, {} isn't in it anyway", range, version),
+      Warning::StaleLock { message } => write!(f, "{}, run `rvm update`", message),
+      Warning::Expired { what, day } => write!(f, "{} expired on {}, re-evaluate this constraint", what, day),
+    }
+  }
+}
+
+/// A result and what was off about it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warned<T> {
+  pub value: T,
+  pub warnings: Vec<Warning>,
+}
+
+thread_local! {
+  static COLLECTING: RefCell<Option<Vec<Warning>>> = const { RefCell::new(None) };
+}
+
+/// Runs f, with what it reports coming back next to its result instead of being printed
+pub fn collect<T>(f: impl FnOnce() -> T) -> Warned<T> {
+  let outer = COLLECTING.with(|c| c.replace(Some(vec![])));
+  let value = f();
+  let warnings = COLLECTING.with(|c| c.replace(outer)).unwrap_or_default();
+  Warned { value, warnings }
+}
+
+/// Hands warning to the innermost collect, printed on stderr when nothing is collecting
+pub fn report(warning: Warning) {
+  tracing::debug!(%warning, "warning");
+  let unclaimed = COLLECTING.with(|c| match c.borrow_mut().as_mut() {
+    Some(collected) => {
+      collected.push(warning);
+      None
+    },
+    None => Some(warning),
+  });
+  if let Some(warning) = unclaimed {
+    theme::warning(warning);
+  }
+}
+
+impl Range {
+  /// What's likely not meant in the range, nothing for most
+  pub fn warnings(&self) -> Vec<Warning> {
+    let range = self.to_string();
+    let builds = self.min.iter().chain(&self.max)
+      .filter(|bound| bound.build_metadata().is_some())
+      .map(|version| Warning::BuildIgnored { version: version.clone(), range: range.clone() });
+    let unreachable = self.lint().into_iter().filter_map(|lint| match lint {
+      Lint::ExceptOutside { version } => Some(Warning::UnreachableExcept { version, range: range.clone() }),
+      _ => None,
+    });
+    builds.chain(unreachable).collect()
+  }
+
+  /// Same as parse, with the range's warnings
+  pub fn parse_warned(text: &str) -> Result<Warned<Range>, ParseError> {
+    let value = Range::parse(text)?;
+    Ok(Warned { warnings: value.warnings(), value })
+  }
+}
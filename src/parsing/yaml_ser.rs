@@ -1,6 +1,6 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs::File;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
@@ -20,6 +20,13 @@ pub fn read_yaml(path: PathBuf) -> Result<Env, YamlError> {
   Ok(env)
 }
 
+/// Whether the project file at path has `rversion: system`, the R on PATH that rvm doesn't manage instead of a range
+pub fn rversion_is_system(path: &Path) -> bool {
+  let Ok(file) = File::open(path) else { return false };
+  let value: Option<serde_yaml::Value> = serde_yaml::from_reader(file).ok();
+  value.as_ref().and_then(|v| v.get("rversion")).and_then(|r| r.as_str()).is_some_and(|r| r.trim() == "system")
+}
+
 #[derive(Error, Debug)]
 pub enum YamlError {
   #[error("error in creating/reading file")]
@@ -30,12 +37,28 @@ pub enum YamlError {
 
 #[derive(Serialize, Deserialize)]
 pub struct Env {
+  #[serde(skip_serializing_if = "Option::is_none")]
   pub name: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
   pub description: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
   pub r#type: Option<ProjectType>,
+  #[serde(skip_serializing_if = "Option::is_none")]
   pub version: Option<String>,
   pub rversion: Range,
-  pub dependencies: HashMap<String, Range>
+  /// the day rversion should be looked at again, YYYY-MM-DD, resolving it warns from then on
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub rversion_expires: Option<String>,
+  pub dependencies: HashMap<String, Range>,
+  /// ranges for plugin tools next to R, e.g. quarto: ^1.4
+  #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+  pub tools: BTreeMap<String, Range>,
+  /// ranges for single executables over rversion, e.g. Rscript: ~4.2 while the rest moves to 4.3
+  #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+  pub commands: BTreeMap<String, Range>,
+  /// variables set while the project's version is active, over the version's own, e.g. R_PROFILE_USER: "{project_dir}/.Rprofile"
+  #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+  pub env: BTreeMap<String, String>,
 }
 
 impl Default for Env {
@@ -47,7 +70,11 @@ impl Default for Env {
       r#type: Some(ProjectType::default()),
       version: Some("1.0.0".to_string()),
       rversion: Range::from_str(format!("^{}", latest).as_str()).unwrap(),
-      dependencies: HashMap::new()
+      rversion_expires: None,
+      dependencies: HashMap::new(),
+      tools: BTreeMap::new(),
+      commands: BTreeMap::new(),
+      env: BTreeMap::new(),
     }
   }
 }
@@ -0,0 +1,163 @@
+use std::fmt;
+use std::str::FromStr;
+use duct::cmd;
+use serde::{Deserialize, Serialize};
+use crate::config::Backend;
+use crate::index::ArtifactMeta;
+
+// which prebuilt artifacts can run here, as a triple like x86_64-linux-gnu, aarch64-macos or x86_64-windows
+// --platform overrides the detected one, e.g. to provision a musl docker image from a glibc host
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Arch {
+  X86_64,
+  Aarch64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Os {
+  Windows,
+  Macos,
+  Linux,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Libc {
+  Gnu,
+  Musl,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Platform {
+  pub arch: Arch,
+  pub os: Os,
+  /// only on linux, none means any libc
+  pub libc: Option<Libc>,
+}
+
+impl Platform {
+  /// The platform rvm is running on
+  pub fn detect() -> Self {
+    let os = match std::env::consts::OS {
+      "windows" => Os::Windows,
+      "macos" => Os::Macos,
+      _ => Os::Linux,
+    };
+    let arch = match std::env::consts::ARCH {
+      "aarch64" => Arch::Aarch64,
+      _ if os == Os::Macos && under_rosetta() => Arch::Aarch64,
+      _ => Arch::X86_64,
+    };
+    let libc = match os {
+      Os::Linux => Some(if is_musl() { Libc::Musl } else { Libc::Gnu }),
+      _ => None,
+    };
+    Self { arch, os, libc }
+  }
+
+  /// Whether an artifact built for other runs here
+  pub fn runs(&self, other: &Platform) -> bool {
+    self.arch == other.arch && self.os == other.os
+      && (self.libc.is_none() || other.libc.is_none() || self.libc == other.libc)
+  }
+}
+
+// an intel rvm on apple silicon would otherwise pick intel R builds
+fn under_rosetta() -> bool {
+  cmd!("sysctl", "-n", "sysctl.proc_translated")
+    .stderr_null()
+    .read()
+    .is_ok_and(|out| out.trim() == "1")
+}
+
+fn is_musl() -> bool {
+  // the musl dynamic loader is /lib/ld-musl-<arch>.so.1, glibc has none of those
+  let has_loader = std::fs::read_dir("/lib")
+    .map(|entries| entries.filter_map(|e| e.ok()).any(|e| e.file_name().to_string_lossy().starts_with("ld-musl-")))
+    .unwrap_or(false);
+  // ldd --version exits 1 on musl, so look at the output either way
+  has_loader || cmd!("ldd", "--version")
+    .stderr_to_stdout()
+    .unchecked()
+    .read()
+    .is_ok_and(|out| out.to_lowercase().contains("musl"))
+}
+
+impl fmt::Display for Platform {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let arch = match self.arch {
+      Arch::X86_64 => "x86_64",
+      Arch::Aarch64 => "aarch64",
+    };
+    let os = match self.os {
+      Os::Windows => "windows",
+      Os::Macos => "macos",
+      Os::Linux => "linux",
+    };
+    write!(f, "{}-{}", arch, os)?;
+    match self.libc {
+      Some(Libc::Gnu) => write!(f, "-gnu"),
+      Some(Libc::Musl) => write!(f, "-musl"),
+      None => Ok(()),
+    }
+  }
+}
+
+impl FromStr for Platform {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let parts: Vec<String> = s.trim().to_lowercase().split('-').map(str::to_owned).collect();
+    let (arch, os, libc) = match parts.as_slice() {
+      [arch, os] => (arch, os, None),
+      [arch, os, libc] => (arch, os, Some(libc)),
+      _ => return Err(format!("{} isn't a platform, expected <arch>-<os>[-<libc>] like x86_64-linux-gnu", s)),
+    };
+    let arch = match arch.as_str() {
+      "x86_64" | "amd64" | "x64" => Arch::X86_64,
+      "aarch64" | "arm64" => Arch::Aarch64,
+      other => return Err(format!("unknown arch {}", other)),
+    };
+    let os = match os.as_str() {
+      "windows" | "win" => Os::Windows,
+      "macos" | "darwin" => Os::Macos,
+      "linux" => Os::Linux,
+      other => return Err(format!("unknown os {}", other)),
+    };
+    let libc = match libc.map(String::as_str) {
+      None => None,
+      Some("gnu") | Some("glibc") => Some(Libc::Gnu),
+      Some("musl") => Some(Libc::Musl),
+      Some(other) => return Err(format!("unknown libc {}", other)),
+    };
+    if libc.is_some() && os != Os::Linux {
+      return Err(format!("a libc only makes sense on linux, not {}", s));
+    }
+    Ok(Self { arch, os, libc })
+  }
+}
+
+impl TryFrom<String> for Platform {
+  type Error = String;
+
+  fn try_from(value: String) -> Result<Self, Self::Error> {
+    value.parse()
+  }
+}
+
+impl From<Platform> for String {
+  fn from(value: Platform) -> Self {
+    value.to_string()
+  }
+}
+
+/// The entries that can be installed on platform with backend
+/// Sources build anywhere, prebuilt artifacts need a matching platform (or none, meaning any)
+pub fn installable(entries: Vec<ArtifactMeta>, backend: Backend, platform: &Platform) -> Vec<ArtifactMeta> {
+  if backend == Backend::Source {
+    return entries;
+  }
+  entries.into_iter()
+    .filter(|meta| meta.platform.as_ref().map_or(true, |p| platform.runs(p)))
+    .collect()
+}
@@ -0,0 +1,238 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use duct::cmd;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use crate::config::Config;
+use crate::index::{parse_index_document, ArtifactMeta, Fetched, IndexError, Validators, VersionSource};
+use crate::layout::{data_dir, with_layout};
+use crate::local_utils::{is_system, system_home};
+use crate::utils::share_tree;
+use crate::parsing::version_parser::Version;
+
+// other tools next to R (quarto, pandoc, ...) managed the same way: their own registry, their own layout, shims
+// a plugin is anything implementing Tool, the external ones are executables answering
+//   <exe> list-remote                  an index document (see index.rs) or a json array of {"version", "url"}
+//   <exe> install <version> <prefix>   puts the version in prefix, RVM_PLUGIN_URL has the url from list-remote
+//   <exe> bin-dirs                     dirs with executables relative to prefix, one per line (optional, "bin" otherwise)
+pub trait Tool: Sync {
+  fn name(&self) -> &str;
+  /// Where the versions come from, cached like CRAN's
+  fn source(&self) -> Box<dyn VersionSource + '_>;
+  /// Installs meta into prefix, which doesn't exist yet
+  fn install(&self, meta: &ArtifactMeta, prefix: &Path) -> Result<(), PluginError>;
+  /// Dirs under an install that go on PATH and get shims
+  fn bin_dirs(&self, prefix: &Path) -> Vec<PathBuf>;
+}
+
+#[derive(Error, Debug)]
+pub enum PluginError {
+  #[error("error in running the plugin")]
+  Io(#[from] std::io::Error),
+  #[error("plugin {0} failed, {1}")]
+  Failed(String, String),
+  #[error("error in reading what the plugin printed")]
+  Json(#[from] serde_json::Error),
+  #[error("no plugin named {0}, see `rvm plugin list`")]
+  NotFound(String),
+}
+
+// e.g. {name: quarto, command: /opt/rvm-quarto} in the config, for plugins not on PATH
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PluginConfig {
+  pub name: String,
+  pub command: PathBuf,
+}
+
+pub const EXECUTABLE_PREFIX: &str = "rvm-plugin-";
+
+pub struct ExternalPlugin {
+  pub name: String,
+  pub command: PathBuf,
+}
+
+impl ExternalPlugin {
+  fn run(&self, args: &[String]) -> Result<String, PluginError> {
+    let output = cmd(&self.command, args).stderr_to_stdout().stdout_capture().unchecked().run()?;
+    let out = String::from_utf8_lossy(&output.stdout).to_string();
+    if !output.status.success() {
+      return Err(PluginError::Failed(self.name.clone(), format!("{} {}: {}", self.command.display(), args.join(" "), out.trim())));
+    }
+    Ok(out)
+  }
+}
+
+impl Tool for ExternalPlugin {
+  fn name(&self) -> &str {
+    &self.name
+  }
+
+  fn source(&self) -> Box<dyn VersionSource + '_> {
+    Box::new(PluginSource { plugin: self, name: format!("plugin-{}", self.name) })
+  }
+
+  fn install(&self, meta: &ArtifactMeta, prefix: &Path) -> Result<(), PluginError> {
+    let output = with_layout(cmd!(&self.command, "install", meta.version.to_string(), prefix))
+      .env("RVM_PLUGIN_URL", &meta.url)
+      .stdout_to_stderr()
+      .unchecked()
+      .run()?;
+    if !output.status.success() {
+      return Err(PluginError::Failed(self.name.clone(), format!("install {} {}", meta.version, output.status)));
+    }
+    Ok(())
+  }
+
+  fn bin_dirs(&self, prefix: &Path) -> Vec<PathBuf> {
+    // bin-dirs is optional, a plugin that doesn't know it gets the usual layout
+    let dirs: Vec<PathBuf> = self.run(&["bin-dirs".to_owned()]).map(|out| {
+      out.lines().map(str::trim).filter(|l| !l.is_empty()).map(|l| prefix.join(l)).collect()
+    }).unwrap_or_default();
+    if dirs.is_empty() { vec![prefix.join("bin")] } else { dirs }
+  }
+}
+
+struct PluginSource<'a> {
+  plugin: &'a ExternalPlugin,
+  name: String,
+}
+
+impl VersionSource for PluginSource<'_> {
+  fn name(&self) -> &str {
+    &self.name
+  }
+
+  // plugins have no validators, every refresh is a full listing
+  fn fetch(&self, _validators: &Validators) -> Result<Fetched, IndexError> {
+    let out = self.plugin.run(&["list-remote".to_owned()])
+      .map_err(|err| IndexError::Io(std::io::Error::new(std::io::ErrorKind::Other, err.to_string())))?;
+    let document = parse_index_document(&out)?;
+    let signed = document.signed();
+    let mut entries = document.versions;
+    entries.sort_by(|a, b| b.version.cmp(&a.version));
+    Ok(Fetched::Modified(entries, Validators::default(), signed))
+  }
+}
+
+/// Every plugin rvm knows, the ones in the config first, then rvm-plugin-* executables on PATH
+pub fn plugins() -> Vec<Box<dyn Tool>> {
+  let mut found: BTreeMap<String, PathBuf> = BTreeMap::new();
+  if let Some(path) = std::env::var_os("PATH") {
+    for dir in std::env::split_paths(&path) {
+      let Ok(entries) = dir.read_dir() else { continue };
+      for entry in entries.filter_map(|e| e.ok()) {
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        let name = file_name.strip_suffix(".exe").unwrap_or(&file_name);
+        if let Some(name) = name.strip_prefix(EXECUTABLE_PREFIX) {
+          // first on PATH wins, like running it would
+          found.entry(name.to_owned()).or_insert_with(|| entry.path());
+        }
+      }
+    }
+  }
+  for plugin in Config::get().plugins {
+    found.insert(plugin.name, plugin.command);
+  }
+  found.into_iter()
+    .map(|(name, command)| Box::new(ExternalPlugin { name, command }) as Box<dyn Tool>)
+    .collect()
+}
+
+pub fn find_plugin(name: &str) -> Result<Box<dyn Tool>, PluginError> {
+  plugins().into_iter()
+    .find(|p| p.name() == name)
+    .ok_or_else(|| PluginError::NotFound(name.to_owned()))
+}
+
+// <data dir>\tools\<plugin>\<version>\, kept apart from R's versions dir
+pub fn tools_root() -> PathBuf {
+  data_dir().join("tools")
+}
+
+pub fn tool_dir(tool: &str) -> PathBuf {
+  tools_root().join(tool)
+}
+
+pub fn tool_install_dir(tool: &str, version: &Version) -> PathBuf {
+  tool_dir(tool).join(version.to_string())
+}
+
+/// Installed versions of tool, oldest first
+pub fn tool_versions(tool: &str) -> Vec<Version> {
+  let Ok(entries) = tool_dir(tool).read_dir() else { return vec![] };
+  let mut ret: Vec<Version> = entries.filter_map(|e| e.ok())
+    .filter(|e| e.path().is_dir())
+    .filter_map(|e| Version::parse_os(&e.file_name()).ok())
+    .collect();
+  ret.sort();
+  ret
+}
+
+/// Installs into a staging dir first so a failed plugin doesn't leave a half install behind
+pub fn install_tool(tool: &dyn Tool, meta: &ArtifactMeta) -> Result<PathBuf, PluginError> {
+  let dest = tool_install_dir(tool.name(), &meta.version);
+  let staging = tool_dir(tool.name()).join(format!(".staging-{}", meta.version));
+  if staging.exists() {
+    std::fs::remove_dir_all(&staging)?;
+  }
+  std::fs::create_dir_all(tool_dir(tool.name()))?;
+  if let Err(err) = tool.install(meta, &staging) {
+    let _ = std::fs::remove_dir_all(&staging);
+    return Err(err);
+  }
+  std::fs::rename(&staging, &dest)?;
+  if is_system() {
+    share_tree(&system_home(), &dest)?;
+  }
+  write_shims(tool)?;
+  Ok(dest)
+}
+
+pub fn uninstall_tool(tool: &dyn Tool, version: &Version) -> Result<(), PluginError> {
+  std::fs::remove_dir_all(tool_install_dir(tool.name(), version))?;
+  write_shims(tool)
+}
+
+// <data dir>\shims\ goes on PATH once, each shim runs `rvm plugin exec` so the version is picked when it runs
+pub fn shims_dir() -> PathBuf {
+  data_dir().join("shims")
+}
+
+/// Rewrites tool's shims after its installs changed, one per executable in any installed version
+pub fn write_shims(tool: &dyn Tool) -> Result<(), PluginError> {
+  let dir = shims_dir();
+  std::fs::create_dir_all(&dir)?;
+  let marker = format!("rvm plugin exec {} ", tool.name());
+  // drop the old ones first, an executable may be gone with the version that had it
+  for entry in dir.read_dir()?.filter_map(|e| e.ok()) {
+    if std::fs::read_to_string(entry.path()).is_ok_and(|s| s.contains(&marker)) {
+      std::fs::remove_file(entry.path())?;
+    }
+  }
+  for version in tool_versions(tool.name()) {
+    for bin in tool.bin_dirs(&tool_install_dir(tool.name(), &version)) {
+      let Ok(entries) = bin.read_dir() else { continue };
+      for entry in entries.filter_map(|e| e.ok()).filter(|e| e.path().is_file()) {
+        let name = entry.path().file_stem().unwrap_or_default().to_string_lossy().to_string();
+        write_shim(&dir, &name, &format!("{}-- {}", marker, name))?;
+      }
+    }
+  }
+  Ok(())
+}
+
+/// Writes a shim called name in dir that runs command with its arguments
+pub fn write_shim(dir: &Path, name: &str, command: &str) -> Result<(), std::io::Error> {
+  if cfg!(windows) {
+    std::fs::write(dir.join(format!("{}.cmd", name)), format!("@echo off\r\n{} %*\r\n", command))
+  } else {
+    let path = dir.join(name);
+    std::fs::write(&path, format!("#!/bin/sh\nexec {} \"$@\"\n", command))?;
+    #[cfg(unix)]
+    {
+      use std::os::unix::fs::PermissionsExt;
+      std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))?;
+    }
+    Ok(())
+  }
+}
@@ -0,0 +1,149 @@
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use crate::filter::CandidateFilter;
+use crate::index::ArtifactMeta;
+use crate::local_utils::system_home;
+use crate::parsing::version_parser::{Range, Version};
+use crate::utils::url_under;
+
+// what an admin allows on a machine, policy.yaml in the system home:
+//   allow: [">=4.2"]
+//   deny: ["=4.3.0"]
+//   allow_registries: ["https://r.example.com/"]
+//   contact: "#it-help"
+// unlike the machine config, nothing a user sets overrides it. resolving skips what it refuses (it's a candidate
+// filter, see filter.rs) and installing refuses it, and when that's why nothing matched the error says so instead of
+// "not found". a policy file that can't be read refuses everything rather than allowing everything.
+// registries are urls matched by scheme, host, port and then path, see url_under, not as text
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Policy {
+  /// versions have to be in one of these, any version when empty
+  pub allow: Vec<Range>,
+  /// versions in any of these are refused, whatever allow says
+  pub deny: Vec<Range>,
+  /// artifacts have to be downloaded from urls under one of these, any url when empty
+  pub allow_registries: Vec<String>,
+  /// urls under any of these are refused, whatever allow_registries says
+  pub deny_registries: Vec<String>,
+  /// who to ask for an exception, shown with every refusal
+  pub contact: Option<String>,
+  /// why the file couldn't be read
+  #[serde(skip)]
+  unreadable: Option<String>,
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum Violation {
+  #[error("R {version} is denied ({range})")]
+  DeniedVersion { version: Version, range: Box<Range> },
+  #[error("R {version} isn't allowed (only {allowed})")]
+  NotAllowed { version: Version, allowed: String },
+  #[error("{url} is from a denied registry ({prefix})")]
+  DeniedRegistry { url: String, prefix: String },
+  #[error("{url} isn't from an allowed registry")]
+  NotAllowedRegistry { url: String },
+  #[error("the policy can't be read ({error}), nothing is allowed")]
+  Unreadable { error: String },
+}
+
+/// A violation of the machine's policy, told apart from a version that doesn't exist
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("{violation} by the policy in {}{}", .path.display(), .contact.as_ref().map(|c| format!(", ask {} for an exception", c)).unwrap_or_default())]
+pub struct Denied {
+  pub violation: Box<Violation>,
+  pub path: PathBuf,
+  pub contact: Option<String>,
+}
+
+impl Policy {
+  pub fn path() -> PathBuf {
+    system_home().join("policy.yaml")
+  }
+
+  /// The machine's policy, allowing everything when there's no file
+  pub fn get() -> Self {
+    let path = Self::path();
+    let text = match std::fs::read_to_string(&path) {
+      Ok(text) => text,
+      Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Self::default(),
+      Err(err) => return Self::unreadable(err.to_string()),
+    };
+    match serde_yaml::from_str::<Option<Self>>(&text) {
+      Ok(policy) => policy.unwrap_or_default(),
+      Err(err) => Self::unreadable(err.to_string()),
+    }
+  }
+
+  fn unreadable(error: String) -> Self {
+    tracing::warn!(path = %Self::path().display(), %error, "can't read the policy, nothing is allowed");
+    Self { unreadable: Some(error), ..Self::default() }
+  }
+
+  /// Whether it allows everything, as with no file
+  pub fn is_open(&self) -> bool {
+    self == &Self::default()
+  }
+
+  /// Whether version, downloaded from url if it's given, can be installed and used
+  pub fn check(&self, version: &Version, url: Option<&str>) -> Result<(), Denied> {
+    self.violation(version, url).map_or(Ok(()), |violation| Err(self.denied(violation)))
+  }
+
+  /// Whether url can be downloaded from, whatever the version, for every url an install actually fetches (mirrors
+  /// included) since the version's own url was checked before rewriting
+  pub fn check_url(&self, url: &str) -> Result<(), Denied> {
+    self.registry_violation(url).map_or(Ok(()), |violation| Err(self.denied(violation)))
+  }
+
+  /// Why nothing in range was found, when it's the policy: the newest of candidates (versions and their url, if
+  /// there's one) it refuses, none when the newest in range is refused by something else or there's none
+  pub fn denied_match<'a>(&self, range: &Range, candidates: impl IntoIterator<Item = (&'a Version, Option<&'a str>)>) -> Option<Denied> {
+    let (version, url) = candidates.into_iter().filter(|(v, _)| range.contains(v)).max_by(|a, b| a.0.cmp(b.0))?;
+    self.violation(version, url).map(|violation| self.denied(violation))
+  }
+
+  fn denied(&self, violation: Violation) -> Denied {
+    Denied { violation: Box::new(violation), path: Self::path(), contact: self.contact.clone() }
+  }
+
+  fn violation(&self, version: &Version, url: Option<&str>) -> Option<Violation> {
+    if let Some(error) = &self.unreadable {
+      return Some(Violation::Unreadable { error: error.clone() });
+    }
+    if let Some(range) = self.deny.iter().find(|r| r.contains(version)) {
+      return Some(Violation::DeniedVersion { version: version.clone(), range: Box::new(range.clone()) });
+    }
+    if !self.allow.is_empty() && !self.allow.iter().any(|r| r.contains(version)) {
+      let allowed = self.allow.iter().map(|r| r.to_string()).collect::<Vec<_>>().join(" or ");
+      return Some(Violation::NotAllowed { version: version.clone(), allowed });
+    }
+    self.registry_violation(url?)
+  }
+
+  fn registry_violation(&self, url: &str) -> Option<Violation> {
+    if let Some(error) = &self.unreadable {
+      return Some(Violation::Unreadable { error: error.clone() });
+    }
+    if let Some(prefix) = self.deny_registries.iter().find(|p| url_under(url, p)) {
+      return Some(Violation::DeniedRegistry { url: url.to_owned(), prefix: prefix.clone() });
+    }
+    if !self.allow_registries.is_empty() && !self.allow_registries.iter().any(|p| url_under(url, p)) {
+      return Some(Violation::NotAllowedRegistry { url: url.to_owned() });
+    }
+    None
+  }
+}
+
+impl CandidateFilter for Policy {
+  fn name(&self) -> String {
+    "policy".to_owned()
+  }
+
+  fn allows(&self, version: &Version, meta: Option<&ArtifactMeta>) -> bool {
+    self.violation(version, meta.map(|m| m.url.as_str())).is_none()
+  }
+}
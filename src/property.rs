@@ -0,0 +1,190 @@
+use std::collections::hash_map::DefaultHasher;
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter};
+use std::hash::{Hash, Hasher};
+use crate::parsing::version_parser::{common_bounds, Comparator, Op, Range, Version};
+
+// the axioms the hand written comparisons have to keep, checked over generated values instead of the few examples a
+// test spells out: Ord agreeing with Eq, Hash and SortKey, the orderings being orders, parse reading back what Display
+// writes, and a range's contains agreeing with its comparators and with the intersection of two ranges.
+// generation is seeded and small (parts 0 to 2, a few pre-releases) so values collide and sit on each other's bounds,
+// where the inconsistencies are. the tests run it, a failure is the axiom and the values breaking it.
+// it only uses rvm_core's public api, and lives with the cli's tests so it isn't part of that api itself
+
+/// A seeded xorshift, the same values for the same seed on every machine
+#[derive(Debug, Clone)]
+pub struct Gen {
+  state: u64,
+}
+
+impl Gen {
+  pub fn new(seed: u64) -> Self {
+    Self { state: seed.max(1) }
+  }
+
+  /// A number under n
+  pub fn below(&mut self, n: u32) -> u32 {
+    self.state ^= self.state << 13;
+    self.state ^= self.state >> 7;
+    self.state ^= self.state << 17;
+    (self.state % u64::from(n.max(1))) as u32
+  }
+
+  /// True one time in n
+  pub fn one_in(&mut self, n: u32) -> bool {
+    self.below(n) == 0
+  }
+
+  pub fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+    &items[self.below(items.len() as u32) as usize]
+  }
+}
+
+/// What can be generated for the axioms
+pub trait Arbitrary: Sized {
+  fn arbitrary(gen: &mut Gen) -> Self;
+}
+
+impl Arbitrary for Version {
+  fn arbitrary(gen: &mut Gen) -> Self {
+    let mut part = || gen.below(3);
+    let (major, minor, patch) = (part(), part(), part());
+    let extra = gen.one_in(6).then(|| gen.pick(&["1", "2", "10", "2.1"]).to_string());
+    let pre = gen.one_in(3).then(|| gen.pick(&["alpha", "alpha.1", "beta", "rc.1", "rc.2", "rc.10", "1", "01", "RC.1"]).to_string());
+    let build = gen.one_in(6).then(|| gen.pick(&["b1", "b2", "20240315"]).to_string());
+    let version = Version::new_w_extra(major, minor, patch, extra, pre, build);
+    // written with a leading zero now and then, which only Display keeps
+    match gen.one_in(8) {
+      true => Version::parse(version.to_string().replacen('.', ".0", 1)).unwrap_or(version),
+      false => version,
+    }
+  }
+}
+
+impl Arbitrary for Op {
+  fn arbitrary(gen: &mut Gen) -> Self {
+    *gen.pick(&[Op::Eq, Op::Ne, Op::Gt, Op::Lt, Op::Ge, Op::Le, Op::Tilde, Op::Caret])
+  }
+}
+
+impl Arbitrary for Comparator {
+  fn arbitrary(gen: &mut Gen) -> Self {
+    Comparator { op: Op::arbitrary(gen), version: Version::arbitrary(gen) }
+  }
+}
+
+impl Arbitrary for Range {
+  // one to three comparators, any op
+  fn arbitrary(gen: &mut Gen) -> Self {
+    loop {
+      let count = gen.below(3) + 1;
+      let comparators: Vec<Comparator> = (0..count).map(|_| Comparator::arbitrary(gen)).collect();
+      if let Ok(range) = Range::from_comparators(comparators) {
+        return range;
+      }
+    }
+  }
+}
+
+/// An axiom that doesn't hold, and the values it doesn't hold for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Counterexample {
+  pub axiom: &'static str,
+  pub values: Vec<String>,
+}
+
+impl Display for Counterexample {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{} doesn't hold for {}", self.axiom, self.values.join(", "))
+  }
+}
+
+fn hash(version: &Version) -> u64 {
+  let mut hasher = DefaultHasher::new();
+  version.hash(&mut hasher);
+  hasher.finish()
+}
+
+type Order = fn(&Version, &Version) -> Ordering;
+
+// whether cmp orders a, b and c transitively
+fn transitive<T>(a: &T, b: &T, c: &T, cmp: impl Fn(&T, &T) -> Ordering) -> bool {
+  !(cmp(a, b) != Ordering::Greater && cmp(b, c) != Ordering::Greater) || cmp(a, c) != Ordering::Greater
+}
+
+/// Version's comparisons over cases triples of versions from seed
+pub fn version_axioms(seed: u64, cases: usize) -> Result<(), Counterexample> {
+  let mut gen = Gen::new(seed);
+  for _ in 0..cases {
+    let [a, b, c] = [(); 3].map(|_| Version::arbitrary(&mut gen));
+    let fail = |axiom, values: &[&Version]| Err(Counterexample { axiom, values: values.iter().map(|v| v.to_string()).collect() });
+    if (a == b) != (a.cmp(&b) == Ordering::Equal) {
+      return fail("eq agrees with cmp", &[&a, &b]);
+    }
+    if a.partial_cmp(&b) != Some(a.cmp(&b)) {
+      return fail("partial_cmp agrees with cmp", &[&a, &b]);
+    }
+    if a.cmp(&b) != b.cmp(&a).reverse() {
+      return fail("cmp is antisymmetric", &[&a, &b]);
+    }
+    if a == b && hash(&a) != hash(&b) {
+      return fail("equal versions hash the same", &[&a, &b]);
+    }
+    if a.sort_key().cmp(&b.sort_key()) != a.cmp(&b) {
+      return fail("sort_key orders like cmp", &[&a, &b]);
+    }
+    if a.cmp_release(&b) != b.cmp_release(&a).reverse() || a.cmp_precedence(&b) != b.cmp_precedence(&a).reverse() {
+      return fail("cmp_release and cmp_precedence are antisymmetric", &[&a, &b]);
+    }
+    let orders: [(&'static str, Order); 3] = [
+      ("cmp is transitive", Version::cmp),
+      ("cmp_release is transitive", Version::cmp_release),
+      ("cmp_precedence is transitive", Version::cmp_precedence),
+    ];
+    for (axiom, cmp) in orders {
+      let triples = [[&a, &b, &c], [&a, &c, &b], [&b, &a, &c], [&b, &c, &a], [&c, &a, &b], [&c, &b, &a]];
+      if let Some(triple) = triples.iter().find(|[x, y, z]| !transitive(*x, *y, *z, cmp)) {
+        return fail(axiom, triple);
+      }
+    }
+    if Version::parse(a.to_string()).as_ref() != Ok(&a) {
+      return fail("parse reads back to_string", &[&a]);
+    }
+  }
+  Ok(())
+}
+
+/// Range's contains over cases pairs of ranges and a version from seed
+pub fn range_axioms(seed: u64, cases: usize) -> Result<(), Counterexample> {
+  let mut gen = Gen::new(seed);
+  for _ in 0..cases {
+    let (a, b, version) = (Range::arbitrary(&mut gen), Range::arbitrary(&mut gen), Version::arbitrary(&mut gen));
+    let fail = |axiom, ranges: &[&Range]| {
+      let values = ranges.iter().map(|r| format!("{:?}", r.to_string())).chain(std::iter::once(version.to_string())).collect();
+      Err(Counterexample { axiom, values })
+    };
+    if Range::parse(&a.to_string()).map(|parsed| parsed.contains(&version)) != Ok(a.contains(&version)) {
+      return fail("parse reads back to_string", &[&a]);
+    }
+    // includes are let in on top of the bounds, so these only hold without them
+    if !a.include.is_empty() || !b.include.is_empty() {
+      continue;
+    }
+    if a.contains(&version) != a.comparators().iter().all(|c| c.matches(&version)) {
+      return fail("contains is every comparator matching", &[&a]);
+    }
+    let both = Range::from_comparators(a.comparators().into_iter().chain(b.comparators()));
+    if both.map(|both| both.contains(&version)) != Ok(a.contains(&version) && b.contains(&version)) {
+      return fail("the intersection contains what both do", &[&a, &b]);
+    }
+    let common = common_bounds(&[a.clone(), b.clone()]);
+    if common.minimal.as_ref().is_some_and(|minimal| !a.contains(minimal) || !b.contains(minimal)) {
+      return fail("the minimal common version is in both", &[&a, &b]);
+    }
+    // a pre-release counts as its release in a range, so it's only under the minimal one by release
+    if a.contains(&version) && b.contains(&version) && common.minimal.as_ref().map_or(true, |minimal| minimal.cmp_release(&version) == Ordering::Greater) {
+      return fail("nothing in both is under the minimal common version", &[&a, &b]);
+    }
+  }
+  Ok(())
+}
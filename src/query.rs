@@ -0,0 +1,138 @@
+use std::cmp::Ordering;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::parsing::ast::RangeAst;
+use crate::parsing::version_parser::{Range, Version};
+use crate::resolve::{active_version, Active, ResolveError};
+
+// `rvm query --stdin`: what an editor asks while a constraint file is being typed, answered by one rvm the plugin
+// keeps running instead of one started per keystroke. a json request per line in, a json answer per line out, in
+// the order they came and with the request's id (any json) back:
+//   {"id":1,"query":"parse","range":">=4.2, <4.x"}
+//   {"id":1,"answer":"error","message":"error in parsing range","span":[8,11]}
+//   {"id":2,"query":"satisfies","version":"4.3.1","range":"~4.3"}
+//   {"id":2,"answer":"satisfies","satisfies":true}
+// a line that isn't a request is answered with an error too, only the end of the input stops it
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "query", rename_all = "kebab-case")]
+pub enum Query {
+  /// a range the way a constraint file has it
+  Parse { range: String },
+  Compare { a: String, b: String },
+  Satisfies { version: String, range: String },
+  /// the newest installed version of range (aliases and keywords too), the active version in dir without one
+  Resolve {
+    #[serde(default)]
+    range: Option<String>,
+    #[serde(default)]
+    dir: Option<PathBuf>,
+  },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Request {
+  #[serde(default)]
+  pub id: Value,
+  #[serde(flatten)]
+  pub query: Query,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "answer", rename_all = "kebab-case")]
+pub enum Answer {
+  /// the range as rvm writes it, and the smallest version it allows
+  Parsed { range: Box<Range>, minimal: Option<Version> },
+  /// <, = or >
+  Compared { order: &'static str },
+  Satisfies { satisfies: bool },
+  Active(Box<Active>),
+  /// the system R is selected
+  System,
+  /// span is the bytes of the range that go wrong, for a range that doesn't parse
+  Error {
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    span: Option<[usize; 2]>,
+  },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Reply {
+  #[serde(skip_serializing_if = "Value::is_null")]
+  pub id: Value,
+  #[serde(flatten)]
+  pub answer: Answer,
+}
+
+/// The reply to one line, dir is where a resolve without one resolves
+pub fn reply(line: &str, dir: &Path) -> Reply {
+  match serde_json::from_str::<Request>(line) {
+    Ok(request) => Reply { id: request.id, answer: answer(&request.query, dir) },
+    Err(err) => {
+      // the id of a request with a query it doesn't know, so the editor still knows which one failed
+      let id = serde_json::from_str::<Value>(line).ok().and_then(|v| v.get("id").cloned()).unwrap_or_default();
+      Reply { id, answer: error(format!("not a request: {}", err)) }
+    },
+  }
+}
+
+pub fn answer(query: &Query, dir: &Path) -> Answer {
+  match query {
+    Query::Parse { range } => match parse_range(range) {
+      Ok(parsed) => Answer::Parsed { minimal: parsed.minimal_version(), range: Box::new(parsed) },
+      Err(answer) => answer,
+    },
+    Query::Compare { a, b } => match (parse_version(a), parse_version(b)) {
+      (Ok(a), Ok(b)) => Answer::Compared {
+        order: match a.cmp(&b) {
+          Ordering::Less => "<",
+          Ordering::Equal => "=",
+          Ordering::Greater => ">",
+        },
+      },
+      (Err(answer), _) | (_, Err(answer)) => answer,
+    },
+    Query::Satisfies { version, range } => match (parse_version(version), parse_range(range)) {
+      (Ok(version), Ok(range)) => Answer::Satisfies { satisfies: range.contains(&version) },
+      (Err(answer), _) | (_, Err(answer)) => answer,
+    },
+    Query::Resolve { range, dir: other } => match active_version(range.as_deref(), other.as_deref().unwrap_or(dir)) {
+      Ok(active) => Answer::Active(Box::new(active)),
+      Err(ResolveError::System) => Answer::System,
+      Err(err) => error(err.to_string()),
+    },
+  }
+}
+
+/// Replies to every line of input on output until input ends
+pub fn serve(input: impl BufRead, mut output: impl Write, dir: &Path) -> io::Result<()> {
+  for line in input.lines() {
+    let line = line?;
+    if line.trim().is_empty() {
+      continue;
+    }
+    let reply = serde_json::to_string(&reply(&line, dir)).map_err(io::Error::from)?;
+    writeln!(output, "{}", reply)?;
+    // the editor waits for each answer before it asks again
+    output.flush()?;
+  }
+  Ok(())
+}
+
+fn error(message: String) -> Answer {
+  Answer::Error { message, span: None }
+}
+
+fn parse_version(version: &str) -> Result<Version, Answer> {
+  Version::parse(version).map_err(|err| error(format!("{}: {}", err, version)))
+}
+
+fn parse_range(range: &str) -> Result<Range, Answer> {
+  range.parse::<Range>().map_err(|err| Answer::Error {
+    message: err.to_string(),
+    span: RangeAst::parse(range).error_span().map(|span| [span.start, span.end]),
+  })
+}
@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+use std::io::IsTerminal;
+use std::sync::Mutex;
+use std::time::Duration;
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use serde_json::json;
+use crate::args::Cli;
+use crate::install::{NoReporter, Progress, Reporter};
+use crate::output::JSON_VERSION;
+use crate::parsing::version_parser::Version;
+
+// the cli's frontends for install (and verify) progress: bars on a terminal, plain lines when piped, json lines with --json
+
+/// Bars on a terminal, the right one for the options otherwise
+/// porcelain gets nothing, progress lines on stdout would break the scripts reading it
+pub fn reporter_for(options: &Cli) -> Box<dyn Reporter> {
+  if options.json {
+    Box::new(JsonLinesReporter)
+  } else if options.porcelain {
+    Box::new(NoReporter)
+  } else if std::io::stderr().is_terminal() {
+    Box::new(TerminalReporter::new())
+  } else {
+    Box::new(LineReporter { verbose: options.verbose > 0 })
+  }
+}
+
+/// One line per step, what rvm printed before it had bars
+pub struct LineReporter {
+  pub verbose: bool,
+}
+
+impl Reporter for LineReporter {
+  fn report(&self, progress: Progress) {
+    match progress {
+      Progress::Downloading(v, url) => if self.verbose {
+        println!("[{}] downloading {}", v, url)
+      } else {
+        println!("[{}] downloading", v)
+      },
+      Progress::Installing(v) => println!("[{}] installing", v),
+      Progress::Building(v, step) => if self.verbose {
+        println!("[{}] {}", v, step)
+      },
+      Progress::Skipped(v) => println!("[{}] already installed", v),
+      Progress::Done(v, Ok(_)) => println!("[{}] done", v),
+      Progress::Done(v, Err(err)) => println!("[{}] failed: {}", v, err),
+      Progress::Downloaded(..) | Progress::Unpacking(..) | Progress::Verifying(..) => {},
+    }
+  }
+}
+
+/// A json object per event on stderr, stdout stays for the command's result
+pub struct JsonLinesReporter;
+
+impl Reporter for JsonLinesReporter {
+  fn report(&self, progress: Progress) {
+    let event = match progress {
+      Progress::Downloading(v, url) => json!({"event": "downloading", "version": v, "url": url}),
+      Progress::Downloaded(v, read, total) => json!({"event": "downloaded", "version": v, "bytes": read, "total": total}),
+      Progress::Unpacking(v, read, total) => json!({"event": "unpacking", "version": v, "bytes": read, "total": total}),
+      Progress::Installing(v) => json!({"event": "installing", "version": v}),
+      Progress::Building(v, step) => json!({"event": "building", "version": v, "step": step}),
+      Progress::Skipped(v) => json!({"event": "skipped", "version": v}),
+      Progress::Verifying(v, hashed, total) => json!({"event": "verifying", "version": v, "bytes": hashed, "total": total}),
+      Progress::Done(v, Ok(path)) => json!({"event": "done", "version": v, "path": path}),
+      Progress::Done(v, Err(err)) => json!({"event": "failed", "version": v, "error": err.to_string()}),
+    };
+    eprintln!("{}", json!({"version": JSON_VERSION, "progress": event}));
+  }
+}
+
+/// A bar per version being installed
+pub struct TerminalReporter {
+  multi: MultiProgress,
+  bars: Mutex<HashMap<Version, ProgressBar>>,
+}
+
+impl TerminalReporter {
+  pub fn new() -> Self {
+    Self { multi: MultiProgress::new(), bars: Mutex::new(HashMap::new()) }
+  }
+
+  fn bar(&self, version: &Version) -> ProgressBar {
+    let mut bars = self.bars.lock().unwrap();
+    bars.entry(version.clone()).or_insert_with(|| {
+      let bar = self.multi.add(ProgressBar::new_spinner());
+      bar.set_prefix(version.to_string());
+      bar.enable_steady_tick(Duration::from_millis(120));
+      bar
+    }).clone()
+  }
+
+  fn spinner(bar: &ProgressBar, message: String) {
+    bar.set_style(ProgressStyle::with_template("{spinner} [{prefix}] {msg}").unwrap());
+    bar.set_message(message);
+  }
+
+  fn bytes(bar: &ProgressBar, message: &'static str, read: u64, total: Option<u64>) {
+    match total {
+      Some(total) => {
+        bar.set_style(ProgressStyle::with_template("{spinner} [{prefix}] {msg} {bar:30} {bytes}/{total_bytes}").unwrap());
+        bar.set_length(total);
+      },
+      None => bar.set_style(ProgressStyle::with_template("{spinner} [{prefix}] {msg} {bytes}").unwrap()),
+    }
+    bar.set_message(message);
+    bar.set_position(read);
+  }
+}
+
+impl Default for TerminalReporter {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl Reporter for TerminalReporter {
+  fn report(&self, progress: Progress) {
+    match progress {
+      Progress::Downloading(v, _) => Self::spinner(&self.bar(v), "downloading".to_owned()),
+      Progress::Downloaded(v, read, total) => Self::bytes(&self.bar(v), "downloading", read, total),
+      Progress::Unpacking(v, read, total) => Self::bytes(&self.bar(v), "unpacking", read, Some(total)),
+      Progress::Installing(v) => Self::spinner(&self.bar(v), "installing".to_owned()),
+      Progress::Building(v, step) => Self::spinner(&self.bar(v), step.to_owned()),
+      Progress::Skipped(v) => Self::spinner(&self.bar(v), "already installed".to_owned()),
+      Progress::Verifying(v, hashed, total) if hashed < total => Self::bytes(&self.bar(v), "verifying", hashed, Some(total)),
+      Progress::Verifying(v, ..) => {
+        let bar = self.bar(v);
+        bar.set_style(ProgressStyle::with_template("  [{prefix}] {msg}").unwrap());
+        bar.finish_with_message("verified");
+      },
+      Progress::Done(v, result) => {
+        let bar = self.bar(v);
+        let message = match result {
+          Ok(_) => "done".to_owned(),
+          Err(err) => format!("failed: {}", err),
+        };
+        bar.set_style(ProgressStyle::with_template("  [{prefix}] {msg}").unwrap());
+        bar.finish_with_message(message);
+      },
+    }
+  }
+}
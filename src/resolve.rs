@@ -0,0 +1,733 @@
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use crate::alias::{expand_alias, Aliases};
+use crate::config::Config;
+use crate::filter;
+use crate::index::{released_range, remote_versions, supported_range, CachePolicy, CranSource, IndexError};
+use crate::install::installed_versions;
+use crate::install::meta::InstallMeta;
+use crate::observer;
+use crate::lockfile::{is_locked, LockfileError, ProjectLock, LOCK_FILE};
+use crate::parsing::ast::{RangeAst, TokenKind};
+use crate::parsing::request::{Keyword, VersionRequest};
+use crate::parsing::version_parser::{precedence_classes, Comparator, Op, ParseError, Range, Version};
+use crate::parsing::warning::{collect, report, warnings_denied, Warned, Warning};
+use crate::parsing::yaml_ser::{read_yaml, rversion_is_system, YamlError};
+use crate::plugin::tool_versions;
+use crate::policy::{Denied, Policy};
+use crate::utils::{env_var, is_day, today};
+
+pub const PROJECT_FILE: &str = "Renv.yaml";
+/// written by `rvm local --none`, its dir and everything under it have no project file, the cd hooks leave them alone
+pub const NONE_FILE: &str = ".rvm-none";
+/// the version `rvm shell` sets for one shell session, stronger than any project file
+pub const SHELL_VAR: &str = "RVM_SHELL_VERSION";
+/// a version or range for whatever runs with this environment, weaker than `rvm shell` by default
+/// with --locked a range means the version the project's lockfile has, which has to be in it
+pub const ENV_VAR: &str = "RVM_R_VERSION";
+
+// where the active version came from, so commands can say why
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "from", rename_all = "lowercase")]
+pub enum Origin {
+  /// given explicitly, e.g. `rvm exec 4.2 -- ...`
+  Request(String),
+  /// `rvm shell` in this session
+  Shell(String),
+  /// RVM_R_VERSION
+  Env(String),
+  /// rversion in a project file
+  Project(PathBuf),
+  /// the version a project's lockfile has for its rversion
+  Lockfile(PathBuf),
+  /// `commands` in a project file or the config mapping the executable to a version
+  Command(String, PathBuf),
+  /// the "default" alias
+  Default,
+  /// nothing said anything, so the newest installed
+  Latest,
+}
+
+impl Display for Origin {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Origin::Request(request) => write!(f, "requested {}", request),
+      Origin::Shell(request) => write!(f, "rvm shell {}", request),
+      Origin::Env(request) => write!(f, "{}={}", ENV_VAR, request),
+      Origin::Project(path) => write!(f, "set by {}", path.display()),
+      Origin::Lockfile(path) => write!(f, "locked by {}", path.display()),
+      Origin::Command(command, path) => write!(f, "commands.{} in {}", command, path.display()),
+      Origin::Default => write!(f, "default alias"),
+      Origin::Latest => write!(f, "newest installed"),
+    }
+  }
+}
+
+impl Origin {
+  /// The project file that picked the version, through its rversion or its lockfile
+  pub fn project_file(&self) -> Option<PathBuf> {
+    match self {
+      Origin::Project(path) => Some(path.clone()),
+      Origin::Lockfile(path) => Some(path.with_file_name(PROJECT_FILE)),
+      _ => None,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Active {
+  pub version: Version,
+  pub range: Range,
+  pub origin: Origin,
+  /// what was off about the range or where it came from, see warning.rs
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub warnings: Vec<Warning>,
+}
+
+#[derive(Error, Debug)]
+pub enum ResolveError {
+  #[error("error in parsing version or range")]
+  Parse(#[from] ParseError),
+  #[error("error in reading project file {0}")]
+  Project(PathBuf, #[source] YamlError),
+  #[error("no installed version matches {0} ({1}), try `rvm install`")]
+  NotInstalled(String, Origin),
+  #[error("no R version is installed, try `rvm install latest`")]
+  NothingInstalled,
+  #[error("error in reading lockfile {0}")]
+  Lockfile(PathBuf, #[source] LockfileError),
+  #[error("{0}, run `rvm update`")]
+  Drift(String),
+  #[error("nothing in the resolution order of the config set a version")]
+  Unresolved,
+  #[error("no {1} version is {0}")]
+  NoKeywordMatch(String, &'static str),
+  #[error("error in reading the index for `supported`")]
+  Index(#[from] IndexError),
+  #[error("{0} takes a day, e.g. {0}:2024-01-01")]
+  BadDay(String),
+  #[error("{0} needs the days versions came out and the index has none, use a registry whose index has them")]
+  Undated(String),
+  #[error("the system R (the one on PATH that rvm doesn't manage) is selected, it has no version here")]
+  System,
+  #[error("no {PROJECT_FILE} in {} or above and no_project in the config is error, try `rvm local`", .0.display())]
+  NoProject(PathBuf),
+  #[error("{} warning(s) with --deny-warnings: {}", .0.len(), .0.iter().map(|w| w.to_string()).collect::<Vec<_>>().join("; "))]
+  Warnings(Vec<Warning>),
+  #[error(transparent)]
+  Denied(#[from] Denied),
+}
+
+/// The nearest project file from dir upwards, none from a dir `rvm local --none` opted out up
+pub fn find_project_file(dir: &Path) -> Option<PathBuf> {
+  dir.ancestors().take_while(|d| !d.join(NONE_FILE).is_file()).map(|d| d.join(PROJECT_FILE)).find(|p| p.is_file())
+}
+
+/// The `rvm local --none` marker dir is under, none when a project file is nearer
+pub fn none_marker(dir: &Path) -> Option<PathBuf> {
+  for d in dir.ancestors() {
+    if d.join(NONE_FILE).is_file() {
+      return Some(d.join(NONE_FILE));
+    }
+    if d.join(PROJECT_FILE).is_file() {
+      return None;
+    }
+  }
+  None
+}
+
+/// Every project file under root, skipping hidden dirs and project envs
+pub fn find_project_files(root: &Path) -> Vec<PathBuf> {
+  let mut ret = vec![];
+  let mut stack = vec![root.to_path_buf()];
+  while let Some(dir) = stack.pop() {
+    let Ok(entries) = dir.read_dir() else { continue };
+    for entry in entries.filter_map(|e| e.ok()) {
+      let path = entry.path();
+      let name = entry.file_name().to_string_lossy().to_string();
+      if path.is_dir() && !path.is_symlink() {
+        if !name.starts_with('.') && name != "env" && name != "renv" && name != "node_modules" {
+          stack.push(path);
+        }
+      } else if name == PROJECT_FILE {
+        ret.push(path);
+      }
+    }
+  }
+  ret.sort();
+  ret
+}
+
+// the places a version can come from, tried in the order of `resolution` in the config
+// an explicit request (e.g. `rvm exec 4.2`) always wins and isn't part of it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Source {
+  /// `rvm shell` in this session
+  Shell,
+  /// RVM_R_VERSION, for CI and scripts that don't want to touch any file
+  Env,
+  /// the project's rvm.lock, while it matches Renv.yaml
+  Lockfile,
+  /// rversion in the nearest Renv.yaml
+  Project,
+  /// the "default" alias, what `rvm global` sets
+  Default,
+  /// the newest installed
+  Latest,
+}
+
+impl Source {
+  /// The order used when the config doesn't say: RVM_R_VERSION, `rvm shell`, the project file, its lockfile, the
+  /// default alias and the newest installed
+  pub fn default_order() -> Vec<Source> {
+    vec![Source::Env, Source::Shell, Source::Project, Source::Lockfile, Source::Default, Source::Latest]
+  }
+}
+
+/// What resolving falls back to in a dir without a project file (or opted out by `rvm local --none`), `no_project` in the config
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum NoProject {
+  /// the rest of the resolution order, the default alias (`rvm global`) then the newest installed
+  #[default]
+  Default,
+  /// the newest installed, the default alias is passed over
+  Latest,
+  /// fail, nothing runs with a version no project picked
+  Error,
+}
+
+/// Which of versions that only differ in build metadata (4.3.1, 4.3.1+patched) resolving picks, `tie_break` in the config
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TieBreak {
+  /// the one without a build, else the latest build
+  PreferNoBuild,
+  /// the build that sorts last, a plain release before any build
+  #[default]
+  PreferLatestBuild,
+  /// the one that's installed, else the latest build
+  PreferInstalled,
+}
+
+impl TieBreak {
+  /// The newest of versions in range, the policy deciding between ones with the same precedence
+  pub fn newest<'a>(self, range: &Range, versions: impl IntoIterator<Item = &'a Version>, installed: &[Version]) -> Option<&'a Version> {
+    let candidates: Vec<&Version> = versions.into_iter().filter(|v| range.contains(v)).collect();
+    let top = *candidates.iter().max_by(|a, b| a.cmp_precedence(b))?;
+    self.pick(candidates.into_iter().filter(|v| v.cmp_precedence(top) == std::cmp::Ordering::Equal), installed)
+  }
+
+  /// The one of ties (versions with the same precedence) the policy prefers
+  pub fn pick<'a>(self, ties: impl IntoIterator<Item = &'a Version>, installed: &[Version]) -> Option<&'a Version> {
+    let ties = ties.into_iter();
+    // Version's Ord already sorts by build last, the key only puts what the policy prefers first
+    match self {
+      TieBreak::PreferLatestBuild => ties.max(),
+      TieBreak::PreferNoBuild => ties.max_by(|a, b| (a.build_metadata().is_none(), a).cmp(&(b.build_metadata().is_none(), b))),
+      TieBreak::PreferInstalled => ties.max_by(|a, b| (installed.contains(a), a).cmp(&(installed.contains(b), b))),
+    }
+  }
+
+  /// items with one per precedence class left, the one the policy picks, for listings with several builds of a release
+  /// in the order they came in
+  pub fn dedup<'a, T>(self, items: &'a [T], version: impl Fn(&T) -> &Version, installed: &[Version]) -> Vec<&'a T> {
+    let picked: Vec<&Version> = precedence_classes(items, &version).into_iter()
+      .filter_map(|class| self.pick(class.into_iter().map(&version), installed))
+      .collect();
+    items.iter().filter(|item| picked.iter().any(|p| std::ptr::eq(*p, version(item)))).collect()
+  }
+}
+
+/// What one source said while resolving, for `rvm resolve --explain`
+#[derive(Debug, Clone, Serialize)]
+pub struct Step {
+  pub source: Source,
+  pub used: bool,
+  pub reason: String,
+}
+
+/// What the active range is and why, going through the sources in the configured order
+pub fn active_range(dir: &Path) -> Result<(Range, Origin), ResolveError> {
+  explain_range(dir).0
+}
+
+/// Same as active_range, with what every source consulted said
+#[tracing::instrument]
+pub fn explain_range(dir: &Path) -> (Result<(Range, Origin), ResolveError>, Vec<Step>) {
+  let mut steps = vec![];
+  for source in Config::get().resolution {
+    match try_source(source, dir) {
+      Ok(Ok((range, origin))) => {
+        let range = match with_names(range) {
+          Ok(range) => range,
+          Err(err) => {
+            steps.push(Step { source, used: false, reason: err.to_string() });
+            return (Err(err), steps);
+          },
+        };
+        tracing::debug!(?source, %range, "using");
+        steps.push(Step { source, used: true, reason: format!("{} ({})", range, origin) });
+        return (Ok((range, origin)), steps);
+      },
+      Ok(Err(reason)) => steps.push(Step { source, used: false, reason }),
+      Err(err) => {
+        steps.push(Step { source, used: false, reason: err.to_string() });
+        return (Err(err), steps);
+      },
+    }
+  }
+  (Err(ResolveError::Unresolved), steps)
+}
+
+// Ok(Err(reason)) when the source has nothing to say, Err when what it has is broken
+fn try_source(source: Source, dir: &Path) -> Result<Result<(Range, Origin), String>, ResolveError> {
+  // the fallbacks are what no_project says they are outside projects
+  if matches!(source, Source::Default | Source::Latest) && find_project_file(dir).is_none() {
+    match Config::get().no_project {
+      NoProject::Error => return Err(ResolveError::NoProject(dir.to_path_buf())),
+      NoProject::Latest if source == Source::Default => return Ok(Err(format!("no {} here or above and no_project is latest", PROJECT_FILE))),
+      _ => {},
+    }
+  }
+  match source {
+    Source::Shell => Ok(match env_var(SHELL_VAR).filter(|r| !r.trim().is_empty()) {
+      Some(request) => Ok((request_range(&request)?, Origin::Shell(request))),
+      None => Err(format!("{} isn't set", SHELL_VAR)),
+    }),
+    Source::Env => match env_var(ENV_VAR).filter(|r| !r.trim().is_empty()) {
+      Some(request) => env_range(request, is_locked(), dir),
+      None => Ok(Err(format!("{} isn't set", ENV_VAR))),
+    },
+    Source::Lockfile => {
+      let Some(project) = find_project_file(dir) else { return Ok(Err(format!("no {} here or above", PROJECT_FILE))) };
+      if rversion_is_system(&project) {
+        return Err(ResolveError::System);
+      }
+      let env = read_yaml(project.clone()).map_err(|err| ResolveError::Project(project.clone(), err))?;
+      let expires = env.rversion_expires.clone();
+      let locked = locked_range(env.rversion.clone(), project.clone())?;
+      if locked.is_ok() {
+        report_expired(&env.rversion, expires, &project);
+      }
+      Ok(locked)
+    },
+    Source::Project => Ok(match find_project_file(dir) {
+      Some(project) if rversion_is_system(&project) => return Err(ResolveError::System),
+      Some(project) => {
+        let env = read_yaml(project.clone()).map_err(|err| ResolveError::Project(project.clone(), err))?;
+        // the project picks the range, its lockfile (when it's next and agrees, or --locked insists) the version in it
+        let lock = ProjectLock::path_for(&project);
+        if lockfile_follows() {
+          if ProjectLock::load(&lock).ok().flatten().is_some_and(|l| l.range == env.rversion) {
+            return Ok(Err(format!("{} locks its {}", lock.display(), env.rversion)));
+          }
+          if is_locked() {
+            return Ok(Err(format!("--locked, {} decides", lock.display())));
+          }
+        }
+        report_expired(&env.rversion, env.rversion_expires, &project);
+        Ok((env.rversion, Origin::Project(project)))
+      },
+      None => Err(format!("no {} here or above", PROJECT_FILE)),
+    }),
+    Source::Default => {
+      let aliases = Aliases::load().unwrap_or_default();
+      Ok(match aliases.get("default") {
+        Some(default) => {
+          aliases.report_expired("default");
+          Ok((request_range(default)?, Origin::Default))
+        },
+        None => Err("no default alias".to_owned()),
+      })
+    },
+    Source::Latest => Ok(Ok((Range::default(), Origin::Latest))),
+  }
+}
+
+// whether the lockfile comes right after the project file in the resolution order
+fn lockfile_follows() -> bool {
+  Config::get().resolution.windows(2).any(|pair| pair == [Source::Project, Source::Lockfile])
+}
+
+/// RVM_R_VERSION's range. under --locked the newest installed in a range isn't the same on every machine, so unless
+/// it's a single version it's the one the project's lockfile has, which has to be in it
+pub fn env_range(request: String, locked: bool, dir: &Path) -> Result<Result<(Range, Origin), String>, ResolveError> {
+  let range = request_range(&request)?;
+  if !locked || range.include.len() == 1 && range.min.is_none() && range.max.is_none() && range.except.is_empty() {
+    return Ok(Ok((range, Origin::Env(request))));
+  }
+  let Some(project) = find_project_file(dir) else {
+    return Err(ResolveError::Drift(format!("{}={} is a range and there's no {} here or above to pick from it", ENV_VAR, request, LOCK_FILE)));
+  };
+  let path = ProjectLock::path_for(&project);
+  match ProjectLock::load(&path).map_err(|err| ResolveError::Lockfile(path.clone(), err))? {
+    Some(lock) if range.contains(&lock.version) => Ok(Ok((Range::parse(&format!("={}", lock.version))?, Origin::Lockfile(path)))),
+    Some(lock) => Err(ResolveError::Drift(format!("{} locks {}, which isn't in {}={}", path.display(), lock.version, ENV_VAR, request))),
+    None => Err(ResolveError::Drift(format!("{}={} is a range and {} has no {} to pick from it", ENV_VAR, request, project.display(), LOCK_FILE))),
+  }
+}
+
+// a project's rversion past the day it was to be looked at again
+fn report_expired(range: &Range, expires: Option<String>, project: &Path) {
+  if let Some(day) = expires.filter(|day| day.as_str() <= today().as_str()) {
+    report(Warning::Expired { what: format!("rversion {} of {}", range, project.display()), day });
+  }
+}
+
+// the exact version the project's lockfile has, if it's there and still for the project's range
+fn locked_range(range: Range, project: PathBuf) -> Result<Result<(Range, Origin), String>, ResolveError> {
+  let path = ProjectLock::path_for(&project);
+  let lock = ProjectLock::load(&path).map_err(|err| ResolveError::Lockfile(path.clone(), err))?;
+  match lock {
+    Some(lock) if lock.range == range => {
+      let installed = InstallMeta::load(&lock.version).sha256;
+      if let (true, Some(locked), Some(installed)) = (is_locked(), &lock.sha256, &installed) {
+        if !locked.eq_ignore_ascii_case(installed) {
+          return Err(ResolveError::Drift(format!("R {} is installed from an artifact with sha256 {}, {} has {}", lock.version, installed, path.display(), locked)));
+        }
+      }
+      Ok(Ok((Range::parse(&format!("={}", lock.version))?, Origin::Lockfile(path))))
+    },
+    Some(lock) => {
+      let message = format!("{} locks {} for {}, but {} asks for {}", path.display(), lock.version, lock.range, project.display(), range);
+      if is_locked() {
+        return Err(ResolveError::Drift(message));
+      }
+      report(Warning::StaleLock { message: message.clone() });
+      Ok(Err(format!("stale, {}", message)))
+    },
+    None if is_locked() => Err(ResolveError::Drift(format!("{} has no {}", project.display(), LOCK_FILE))),
+    None => Ok(Err(format!("no {} next to {}", LOCK_FILE, project.display()))),
+  }
+}
+
+/// The lockfile of project (a Renv.yaml), failing when it's missing or stale the way --locked does,
+/// for reproducing the project's R somewhere else
+pub fn project_lock(project: &Path) -> Result<ProjectLock, ResolveError> {
+  let range = read_yaml(project.to_path_buf()).map_err(|err| ResolveError::Project(project.to_path_buf(), err))?.rversion;
+  let path = ProjectLock::path_for(project);
+  match ProjectLock::load(&path).map_err(|err| ResolveError::Lockfile(path.clone(), err))? {
+    Some(lock) if lock.range == range => Ok(lock),
+    Some(lock) => Err(ResolveError::Drift(format!("{} locks {} for {}, but {} asks for {}", path.display(), lock.version, lock.range, project.display(), range))),
+    None => Err(ResolveError::Drift(format!("{} has no {}", project.display(), LOCK_FILE))),
+  }
+}
+
+/// Resolves a range against the installed versions
+pub fn resolve_installed(range: Range, origin: Origin) -> Result<Active, ResolveError> {
+  resolve_warned(range, origin, vec![])
+}
+
+// warnings are the ones found before getting the range, the range's own are added
+fn resolve_warned(range: Range, origin: Origin, mut warnings: Vec<Warning>) -> Result<Active, ResolveError> {
+  let range = with_names(range)?;
+  warnings.extend(range.warnings());
+  if warnings_denied() && !warnings.is_empty() {
+    return Err(ResolveError::Warnings(warnings));
+  }
+  let installed = installed_versions();
+  tracing::debug!(%range, %origin, installed = installed.len(), "resolving");
+  if installed.is_empty() {
+    return Err(ResolveError::NothingInstalled);
+  }
+  match Config::get().tie_break.newest(&range, &filter::installed_candidates(&installed), &installed) {
+    Some(version) => {
+      let active = Active { version: version.clone(), range, origin, warnings };
+      observer::notify(|o| o.on_resolve(&active));
+      Ok(active)
+    },
+    None => Err(no_match(range, origin, &installed)),
+  }
+}
+
+// nothing installed matched, because of the policy or because there's none
+fn no_match(range: Range, origin: Origin, installed: &[Version]) -> ResolveError {
+  match Policy::get().denied_match(&range, installed.iter().map(|v| (v, None))) {
+    Some(denied) => ResolveError::Denied(denied),
+    None => ResolveError::NotInstalled(range.to_string(), origin),
+  }
+}
+
+/// What became of an installed version while resolving
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "verdict", content = "by", rename_all = "lowercase")]
+pub enum Verdict {
+  /// the newest that matches
+  Selected,
+  /// matches, but a newer one does too
+  Older,
+  /// out of the range because of this comparator
+  Excluded(Comparator),
+  /// in the range, but a candidate filter refuses it, the filter's name
+  Filtered(String),
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Candidate {
+  pub version: Version,
+  #[serde(flatten)]
+  pub verdict: Verdict,
+}
+
+/// Everything that went into picking a version, for `rvm resolve --explain`
+#[derive(Debug, Clone, Serialize)]
+pub struct Trace {
+  /// the sources consulted, empty for a request
+  pub steps: Vec<Step>,
+  /// none when the sources failed, error says why
+  pub range: Option<Range>,
+  pub origin: Option<Origin>,
+  /// every installed version, newest first
+  pub candidates: Vec<Candidate>,
+  pub selected: Option<Version>,
+  pub error: Option<String>,
+}
+
+/// Like active_version, but says what each source and each installed version had to do with the result
+pub fn explain(request: Option<&str>, dir: &Path) -> Trace {
+  let (found, steps) = match request {
+    Some(request) => (request_range(request).map(|range| (range, Origin::Request(request.to_owned()))), vec![]),
+    None => explain_range(dir),
+  };
+  let (range, origin) = match found {
+    Ok(found) => found,
+    Err(err) => return Trace { steps, range: None, origin: None, candidates: vec![], selected: None, error: Some(err.to_string()) },
+  };
+  let installed = installed_versions();
+  let filters = filter::active();
+  let selected = Config::get().tie_break.newest(&range, &filter::installed_candidates(&installed), &installed).cloned();
+  let candidates = installed.iter().rev()
+    .map(|version| Candidate {
+      version: version.clone(),
+      verdict: match (range.excluded_by(version), filter::refused_by(&filters, version, None)) {
+        (Some(comparator), _) => Verdict::Excluded(comparator),
+        (None, Some(name)) => Verdict::Filtered(name),
+        (None, None) if selected.as_ref() == Some(version) => Verdict::Selected,
+        (None, None) => Verdict::Older,
+      },
+    })
+    .collect();
+  let error = match (&selected, installed.is_empty()) {
+    (Some(_), _) => None,
+    (None, true) => Some(ResolveError::NothingInstalled.to_string()),
+    (None, false) => Some(no_match(range.clone(), origin.clone(), &installed).to_string()),
+  };
+  Trace { steps, range: Some(range), origin: Some(origin), candidates, selected, error }
+}
+
+/// The range a request (alias, keyword, version or range) stands for, keywords picked among pool
+/// kind names the pool in errors, "installed" or "remote"
+pub fn request_range_in<'a>(request: &str, pool: impl IntoIterator<Item = &'a Version>, kind: &'static str) -> Result<Range, ResolveError> {
+  if let Some((rest, window)) = without_release_window(request)? {
+    let entries = remote_versions(&CranSource::configured(), CachePolicy::Default)?;
+    // an index without any day would keep everything, as if there was no window
+    if entries.iter().all(|m| m.released.is_none()) {
+      return Err(ResolveError::Undated(window[0].name().to_owned()));
+    }
+    let released = released_range(&entries, |day| window.iter().all(|bound| bound.keeps(day)));
+    let rest = if rest.is_empty() { Range::default() } else { request_range_in(&rest, pool, kind)? };
+    return Ok(Range::from_comparators(rest.comparators().into_iter().chain(released.comparators()))?);
+  }
+  if let Some(rest) = without_supported(request) {
+    let entries = remote_versions(&CranSource::configured(), CachePolicy::Default)?;
+    let Some(supported) = supported_range(&entries, &today()) else {
+      return Err(ResolveError::NoKeywordMatch("supported".to_owned(), "released"));
+    };
+    if rest.is_empty() {
+      return Ok(supported);
+    }
+    let rest = request_range_in(&rest, pool, kind)?;
+    return Ok(Range::from_comparators(rest.comparators().into_iter().chain(supported.comparators()))?);
+  }
+  let pool: Vec<&Version> = pool.into_iter().collect();
+  let request = expand_alias(request);
+  let parsed = match VersionRequest::parse(&request) {
+    Ok(parsed) => parsed,
+    // names inside a range, >=lts or =default
+    Err(err) => return Range::parse_with(&request, |name| name_version_in(name, pool.iter().copied()))
+      .map_err(|named| match named {
+        ParseError::UnknownName(_) => named.into(),
+        _ => err.into(),
+      }),
+  };
+  match parsed.to_range(pool) {
+    Some(range) => Ok(range),
+    None if parsed == VersionRequest::Keyword(Keyword::System) => Err(ResolveError::System),
+    None => Err(ResolveError::NoKeywordMatch(parsed.to_string(), kind)),
+  }
+}
+
+/// Whether request (or the alias it is) selects the system R, `rvm use system`
+pub fn selects_system(request: &str) -> bool {
+  VersionRequest::parse(&expand_alias(request)).is_ok_and(|parsed| parsed == VersionRequest::Keyword(Keyword::System))
+}
+
+// the request without its `supported` or `>=supported` comparator, none when it has neither
+// the rest is read as usual, so `>=supported, <5` works
+fn without_supported(request: &str) -> Option<String> {
+  let ast = RangeAst::parse(request);
+  let at = ast.tokens.iter().position(|t| t.kind == TokenKind::Invalid && t.text == "supported")?;
+  let solid: Vec<usize> = (0..at).filter(|&i| ast.tokens[i].kind != TokenKind::Space).collect();
+  let from = match solid.last() {
+    Some(&op) if ast.tokens[op].kind == TokenKind::Op(Op::Ge) => op,
+    Some(&op) if matches!(ast.tokens[op].kind, TokenKind::Op(_)) => return None,
+    _ => at,
+  };
+  Some(ast.source_without(|i| (from..=at).contains(&i)))
+}
+
+// `released-before:<day>` or `released-after:<day>` in a request, both exclusive
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ReleaseBound {
+  Before(String),
+  After(String),
+}
+
+impl ReleaseBound {
+  fn name(&self) -> &'static str {
+    match self {
+      ReleaseBound::Before(_) => "released-before",
+      ReleaseBound::After(_) => "released-after",
+    }
+  }
+
+  fn keeps(&self, day: &str) -> bool {
+    match self {
+      ReleaseBound::Before(bound) => day < bound.as_str(),
+      ReleaseBound::After(bound) => day > bound.as_str(),
+    }
+  }
+}
+
+// the request without its released-before and released-after comparators and what they were, none when it has neither
+// for reproducing what could be installed back then, `>=4.1, released-before:2024-01-01`
+fn without_release_window(request: &str) -> Result<Option<(String, Vec<ReleaseBound>)>, ResolveError> {
+  let ast = RangeAst::parse(request);
+  let mut bounds = vec![];
+  let mut taken = vec![];
+  for (at, token) in ast.tokens.iter().enumerate() {
+    let bound: fn(String) -> ReleaseBound = match token.text.as_str() {
+      "released-before" if token.kind == TokenKind::Invalid => ReleaseBound::Before,
+      "released-after" if token.kind == TokenKind::Invalid => ReleaseBound::After,
+      _ => continue,
+    };
+    let day = match (ast.tokens.get(at + 1), ast.tokens.get(at + 2)) {
+      (Some(colon), Some(day)) if colon.text == ":" && is_day(&day.text) => day.text.clone(),
+      _ => return Err(ResolveError::BadDay(token.text.clone())),
+    };
+    bounds.push(bound(day));
+    taken.extend([at, at + 1, at + 2]);
+  }
+  if bounds.is_empty() {
+    return Ok(None);
+  }
+  Ok(Some((ast.source_without(|i| taken.contains(&i)), bounds)))
+}
+
+/// The version a name inside a range stands for among pool: an alias's or a keyword's (lts, latest...) newest match
+pub fn name_version_in<'a>(name: &str, pool: impl IntoIterator<Item = &'a Version>) -> Option<Version> {
+  let target = expand_alias(name);
+  let parsed = VersionRequest::parse(&target).ok()?;
+  let pool: Vec<&Version> = pool.into_iter().collect();
+  parsed.to_range(pool.iter().copied())?.max_satisfying(pool).cloned()
+}
+
+/// name_version_in the installed versions
+pub fn name_version(name: &str) -> Option<Version> {
+  name_version_in(name, &installed_versions())
+}
+
+/// range with the versions its names (>=lts in a project file, see Range::named) stand for among the installed ones,
+/// range itself when it names none. the file keeps the name, it's only looked up here
+pub fn with_names(range: Range) -> Result<Range, ResolveError> {
+  if !range.is_named() {
+    return Ok(range);
+  }
+  let installed = installed_versions();
+  Ok(range.with_names(|name| name_version_in(name, &installed))?)
+}
+
+/// request_range_in the installed versions
+pub fn request_range(request: &str) -> Result<Range, ResolveError> {
+  request_range_in(request, &installed_versions(), "installed")
+}
+
+/// The version commands run with in dir, or the requested one if there's a request (aliases and keywords allowed)
+pub fn active_version(request: Option<&str>, dir: &Path) -> Result<Active, ResolveError> {
+  let Warned { value, warnings } = collect(|| match request {
+    Some(request) => Ok((request_range(request)?, Origin::Request(request.to_owned()))),
+    None => active_range(dir),
+  });
+  let (range, origin) = value?;
+  resolve_warned(range, origin, warnings)
+}
+
+/// The name commands map an executable by, R for R.exe and /opt/R/bin/R
+pub fn command_name(command: &str) -> String {
+  let path = Path::new(command);
+  let name = path.file_name().unwrap_or(path.as_os_str()).to_string_lossy();
+  name.strip_suffix(".exe").unwrap_or(&name).to_owned()
+}
+
+/// The range command is mapped to, by the nearest project file's `commands` or else the config's, none if neither maps it
+pub fn command_range(command: &str, dir: &Path) -> Result<Option<(Range, Origin)>, ResolveError> {
+  let name = command_name(command);
+  // a project on the system R opts out of rvm, its commands too
+  if let Some(project) = find_project_file(dir).filter(|project| !rversion_is_system(project)) {
+    let env = read_yaml(project.clone()).map_err(|err| ResolveError::Project(project.clone(), err))?;
+    if let Some(range) = env.commands.get(&name) {
+      return Ok(Some((range.clone(), Origin::Command(name, project))));
+    }
+  }
+  match Config::get().commands.get(&name) {
+    Some(request) => Ok(Some((request_range(request)?, Origin::Command(name, Config::path())))),
+    None => Ok(None),
+  }
+}
+
+/// The version command runs with in dir: the one it's mapped to or the active one
+pub fn command_version(command: &str, dir: &Path) -> Result<Active, ResolveError> {
+  match command_range(command, dir)? {
+    Some((range, origin)) => resolve_installed(range, origin),
+    None => active_version(None, dir),
+  }
+}
+
+/// Every executable the project file around dir and the config map to a version
+pub fn mapped_commands(dir: &Path) -> Vec<String> {
+  let mut ret: Vec<String> = Config::get().commands.into_keys().collect();
+  if let Some(env) = find_project_file(dir).and_then(|project| read_yaml(project).ok()) {
+    ret.extend(env.commands.into_keys());
+  }
+  ret.sort();
+  ret.dedup();
+  ret
+}
+
+/// A plugin tool's range in a project file and what it resolves to
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolActive {
+  pub tool: String,
+  pub range: Range,
+  /// none when no installed version matches
+  pub version: Option<Version>,
+  pub project: PathBuf,
+}
+
+/// The tools of the nearest project file from dir, each resolved on its own against its installs
+pub fn project_tools(dir: &Path) -> Result<Vec<ToolActive>, ResolveError> {
+  let Some(project) = find_project_file(dir) else { return Ok(vec![]) };
+  let env = read_yaml(project.clone()).map_err(|err| ResolveError::Project(project.clone(), err))?;
+  Ok(env.tools.into_iter()
+    .map(|(tool, range)| ToolActive {
+      version: range.max_satisfying(&tool_versions(&tool)).cloned(),
+      tool,
+      range,
+      project: project.clone(),
+    })
+    .collect())
+}
@@ -0,0 +1,193 @@
+use std::ffi::OsStr;
+use std::path::Path;
+use std::str::FromStr;
+use clap::ValueEnum;
+
+// rvm can't change the environment of the shell that runs it, so commands that need to print
+// code for the shell to eval, in whichever syntax that shell understands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Shell {
+  Bash,
+  Zsh,
+  Fish,
+  Powershell,
+  Cmd,
+}
+
+impl Shell {
+  /// The shell rvm is probably running under, from $SHELL (powershell on windows without it)
+  pub fn detect() -> Self {
+    let from_env = std::env::var_os("SHELL").and_then(|shell| {
+      let name = Path::new(&shell).file_stem()?.to_string_lossy().to_lowercase();
+      match name.as_str() {
+        "zsh" => Some(Shell::Zsh),
+        "fish" => Some(Shell::Fish),
+        "bash" | "sh" | "dash" | "ksh" => Some(Shell::Bash),
+        "pwsh" | "powershell" => Some(Shell::Powershell),
+        _ => None,
+      }
+    });
+    from_env.unwrap_or(if cfg!(windows) { Shell::Powershell } else { Shell::Bash })
+  }
+
+  /// A statement setting name to value
+  pub fn set_var(&self, name: &str, value: &str) -> String {
+    match self {
+      Shell::Bash | Shell::Zsh => format!("export {}='{}'", name, value.replace('\'', r"'\''")),
+      Shell::Fish => format!("set -gx {} '{}'", name, value.replace('\\', r"\\").replace('\'', r"\'")),
+      Shell::Powershell => format!("$env:{} = '{}'", name, value.replace('\'', "''")),
+      Shell::Cmd => format!("set \"{}={}\"", name, value),
+    }
+  }
+
+  /// A statement removing name from the environment
+  pub fn unset_var(&self, name: &str) -> String {
+    match self {
+      Shell::Bash | Shell::Zsh => format!("unset {}", name),
+      Shell::Fish => format!("set -e {}", name),
+      Shell::Powershell => format!("Remove-Item Env:{} -ErrorAction SilentlyContinue", name),
+      Shell::Cmd => format!("set {}=", name),
+    }
+  }
+
+  /// A statement per variable, for what activate::env_for gives
+  pub fn set_vars<K: AsRef<str>, V: AsRef<OsStr>>(&self, vars: &[(K, V)]) -> String {
+    vars.iter()
+      .map(|(name, value)| self.set_var(name.as_ref(), &value.as_ref().to_string_lossy()))
+      .collect::<Vec<_>>()
+      .join("\n")
+  }
+
+  /// Code that runs `rvm hook-env` whenever the current dir changes, none if the shell has no way to
+  /// bash, zsh and powershell don't run it when the dir's hook cache entry says nothing would change (see hook_cache.rs)
+  pub fn cd_hook(&self) -> Option<String> {
+    let hook = match self {
+      Shell::Bash => return Some(format!("{}\n{}", SH_UNCHANGED, r#"_rvm_hook() {
+  if [ "$PWD" != "${_RVM_LAST_PWD:-}" ]; then
+    _RVM_LAST_PWD="$PWD"
+    _rvm_unchanged || eval "$(command rvm hook-env --shell bash)"
+  fi
+}
+case ";${PROMPT_COMMAND:-};" in
+  *";_rvm_hook;"*) ;;
+  *) PROMPT_COMMAND="_rvm_hook${PROMPT_COMMAND:+;$PROMPT_COMMAND}" ;;
+esac"#)),
+      Shell::Zsh => return Some(format!("{}\n{}", SH_UNCHANGED, r#"_rvm_hook() {
+  _rvm_unchanged || eval "$(command rvm hook-env --shell zsh)"
+}
+autoload -U add-zsh-hook
+add-zsh-hook chpwd _rvm_hook
+_rvm_hook"#)),
+      Shell::Fish => r#"function _rvm_hook --on-variable PWD
+  command rvm hook-env --shell fish | source
+end
+_rvm_hook"#,
+      Shell::Powershell => r#"$global:_RvmLastPwd = $null
+$global:_RvmPrompt = $function:prompt
+function global:_RvmUnchanged {
+  if (-not $env:RVM_HOOK_CACHE -or $env:RVM_SHELL_VERSION -or $env:RVM_R_VERSION -or $env:RVM_LOCKED -or $env:RVM_SYSTEM -or $env:RVM_OFFLINE) { return $false }
+  $entry = Join-Path $env:RVM_HOOK_CACHE ($PWD.Path -replace '[\\/:]', '%')
+  if (-not (Test-Path -LiteralPath $entry)) { return $false }
+  $written = (Get-Item -LiteralPath $entry).LastWriteTimeUtc
+  $lines = @(Get-Content -LiteralPath $entry)
+  foreach ($dependency in $lines | Select-Object -Skip 2) {
+    if ((Test-Path -LiteralPath $dependency) -and (Get-Item -LiteralPath $dependency).LastWriteTimeUtc -ge $written) { return $false }
+  }
+  return $lines[0] -eq $env:RVM_HOOK_KEY
+}
+function global:prompt {
+  if ($PWD.Path -ne $global:_RvmLastPwd) {
+    $global:_RvmLastPwd = $PWD.Path
+    if (-not (_RvmUnchanged)) {
+      rvm hook-env --shell powershell | Out-String | Invoke-Expression
+    }
+  }
+  & $global:_RvmPrompt
+}"#,
+      Shell::Cmd => return None,
+    };
+    Some(hook.to_owned())
+  }
+
+  /// Code that completes rvm's commands, and their versions through `rvm complete`, none if the shell can't
+  /// installed versions come from the versions dir and remote ones from the cached index, completing never waits on the network
+  pub fn completion(&self) -> Option<String> {
+    let script = match self {
+      Shell::Bash => r#"_rvm_complete() {
+  local IFS=$'\n'
+  COMPREPLY=($(command rvm complete -- "${COMP_WORDS[@]:1:COMP_CWORD}" 2>/dev/null))
+}
+complete -o default -F _rvm_complete rvm"#,
+      Shell::Zsh => r#"_rvm_complete() {
+  local -a candidates
+  candidates=("${(@f)$(command rvm complete -- "${(@)words[2,CURRENT]}" 2>/dev/null)}")
+  compadd -a candidates
+}
+compdef _rvm_complete rvm"#,
+      Shell::Fish => r#"complete -c rvm -f -a '(command rvm complete -- (commandline -opc)[2..-1] (commandline -ct) 2>/dev/null)'"#,
+      Shell::Powershell => r#"Register-ArgumentCompleter -Native -CommandName rvm -ScriptBlock {
+  param($wordToComplete, $commandAst, $cursorPosition)
+  $words = @($commandAst.CommandElements | Select-Object -Skip 1 | ForEach-Object { $_.ToString() })
+  if ($wordToComplete -eq '') { $words += '""' }
+  rvm complete -- @words 2>$null | ForEach-Object {
+    [System.Management.Automation.CompletionResult]::new($_, $_, 'ParameterValue', $_)
+  }
+}"#,
+      Shell::Cmd => return None,
+    };
+    Some(script.to_owned())
+  }
+
+  /// How the user makes the shell run what rvm prints
+  pub fn eval_hint(&self, command: &str) -> String {
+    match self {
+      Shell::Bash | Shell::Zsh => format!("eval \"$({})\"", command),
+      Shell::Fish => format!("{} | source", command),
+      Shell::Powershell => format!("{} | Invoke-Expression", command),
+      Shell::Cmd => format!("for /f \"delims=\" %i in ('{}') do %i", command),
+    }
+  }
+}
+
+// bash and zsh: whether the hook cache entry of $PWD is fresh and its key is what's set already, without running
+// anything, [ -nt ] compares modification times (and is true for a dependency that isn't there)
+const SH_UNCHANGED: &str = r#"_rvm_unchanged() {
+  [ -n "${RVM_HOOK_CACHE:-}" ] && [ -z "${RVM_SHELL_VERSION:-}${RVM_R_VERSION:-}${RVM_LOCKED:-}${RVM_SYSTEM:-}${RVM_OFFLINE:-}" ] || return 1
+  local entry="$RVM_HOOK_CACHE/${PWD//\//%}" key dependency
+  [ -f "$entry" ] || return 1
+  {
+    read -r key && read -r dependency || return 1
+    while read -r dependency; do
+      [ "$entry" -nt "$dependency" ] || return 1
+    done
+  } < "$entry"
+  [ "$key" = "${RVM_HOOK_KEY:-}" ]
+}"#;
+
+// what `rvm init <hook>` can print: a shell's cd hook or direnv's layout function
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hook {
+  Shell(Shell),
+  Direnv,
+}
+
+impl FromStr for Hook {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    if s.eq_ignore_ascii_case("direnv") {
+      return Ok(Hook::Direnv);
+    }
+    Shell::from_str(s, true)
+      .map(Hook::Shell)
+      .map_err(|_| format!("{} isn't a shell (bash, zsh, fish, powershell, cmd) or direnv", s))
+  }
+}
+
+/// For ~/.config/direnv/direnvrc, gives .envrc files a `use rvm [version]`
+pub fn direnv_layout() -> &'static str {
+  r#"use_rvm() {
+  watch_file Renv.yaml
+  eval "$(rvm direnv export "$@")"
+}"#
+}
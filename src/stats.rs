@@ -0,0 +1,109 @@
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+use crate::config::Config;
+use crate::layout::state_dir;
+use crate::parsing::version_parser::Version;
+
+// usage counters for `rvm stats`, kept only with `stats: true` in the config and never sent anywhere:
+//   runs       how often each version ran through rvm (exec, each, try) and each tool version through its shims
+//   index      how the remote index was answered: from the cache, revalidated with the registry (304) or fetched again
+//   downloads  artifacts downloaded whole or patched from a kept older one
+// in <state dir>/stats.json, changed with an flock on it held so two rvm at once don't lose counts.
+// counting never fails a command, a file that can't be written only loses that count
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Stats {
+  /// unix seconds of the first count
+  pub since: Option<u64>,
+  /// per tool (r for R itself), per version
+  pub runs: BTreeMap<String, BTreeMap<Version, u64>>,
+  pub index: IndexCounts,
+  pub downloads: DownloadCounts,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IndexCounts {
+  pub cached: u64,
+  pub revalidated: u64,
+  pub fetched: u64,
+}
+
+impl IndexCounts {
+  /// The share of index lookups that didn't download it, none before any
+  pub fn hit_rate(&self) -> Option<f64> {
+    let total = self.cached + self.revalidated + self.fetched;
+    (total > 0).then(|| (self.cached + self.revalidated) as f64 / total as f64)
+  }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DownloadCounts {
+  pub full: u64,
+  pub delta: u64,
+}
+
+impl Stats {
+  pub fn path() -> PathBuf {
+    state_dir().join("stats.json")
+  }
+
+  /// What's been counted, nothing when there's no (readable) file
+  pub fn load() -> Self {
+    std::fs::read(Self::path()).ok().and_then(|bytes| serde_json::from_slice(&bytes).ok()).unwrap_or_default()
+  }
+
+  /// The other way around to runs, most used first, with the tool
+  pub fn most_used(&self) -> Vec<(&str, &Version, u64)> {
+    let mut ret: Vec<(&str, &Version, u64)> = self.runs.iter()
+      .flat_map(|(tool, versions)| versions.iter().map(move |(version, &count)| (tool.as_str(), version, count)))
+      .collect();
+    ret.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| b.1.cmp(a.1)));
+    ret
+  }
+}
+
+/// Whether the config asks for counting
+pub fn enabled() -> bool {
+  Config::get().stats
+}
+
+/// Counts with change when counting is on
+pub fn count(change: impl FnOnce(&mut Stats)) {
+  if !enabled() {
+    return;
+  }
+  if let Err(err) = update(change) {
+    tracing::debug!(error = %err, "can't count usage");
+  }
+}
+
+/// One more run of tool's version
+pub fn count_run(tool: &str, version: &Version) {
+  count(|stats| *stats.runs.entry(tool.to_owned()).or_default().entry(version.clone()).or_default() += 1);
+}
+
+fn update(change: impl FnOnce(&mut Stats)) -> std::io::Result<()> {
+  std::fs::create_dir_all(state_dir())?;
+  let mut file: File = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(Stats::path())?;
+  file.lock_exclusive()?;
+  let mut text = String::new();
+  file.read_to_string(&mut text)?;
+  let mut stats: Stats = serde_json::from_str(&text).unwrap_or_default();
+  if stats.since.is_none() {
+    stats.since = SystemTime::now().duration_since(UNIX_EPOCH).ok().map(|d| d.as_secs());
+  }
+  change(&mut stats);
+  file.set_len(0)?;
+  file.seek(SeekFrom::Start(0))?;
+  file.write_all(&serde_json::to_vec_pretty(&stats).map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?)?;
+  file.flush()?;
+  FileExt::unlock(&file)
+}
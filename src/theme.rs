@@ -0,0 +1,101 @@
+use std::fmt::Display;
+use std::sync::OnceLock;
+use console::{Style, StyledObject};
+use serde::{Deserialize, Serialize};
+use crate::config::Config;
+
+// the few styles human output uses, so every command colors the same things the same way
+// --json and --porcelain never go through here
+
+/// --color or `color` in the config, auto colors terminals unless NO_COLOR is set
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorChoice {
+  #[default]
+  Auto,
+  Always,
+  Never,
+}
+
+// `theme` in the config, each a dotted console style like "green.bold", "" for plain
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ThemeConfig {
+  /// something got done, e.g. "R 4.3.1 installed"
+  pub success: String,
+  pub warn: String,
+  pub error: String,
+  /// version numbers that matter in a line, like the active one in `rvm list`
+  pub version: String,
+}
+
+impl Default for ThemeConfig {
+  fn default() -> Self {
+    Self { success: "green".to_owned(), warn: "yellow".to_owned(), error: "red.bold".to_owned(), version: "cyan".to_owned() }
+  }
+}
+
+struct Theme {
+  success: Style,
+  warn: Style,
+  error: Style,
+  version: Style,
+}
+
+impl From<&ThemeConfig> for Theme {
+  fn from(config: &ThemeConfig) -> Self {
+    let style = |s: &str| Style::from_dotted_str(s);
+    Self { success: style(&config.success), warn: style(&config.warn), error: style(&config.error), version: style(&config.version) }
+  }
+}
+
+static THEME: OnceLock<Theme> = OnceLock::new();
+
+fn theme() -> &'static Theme {
+  THEME.get_or_init(|| Theme::from(&ThemeConfig::default()))
+}
+
+/// Decides whether to color and with which styles, once before any output
+pub fn init(choice: Option<ColorChoice>) {
+  let config = Config::get();
+  let enabled = match choice.unwrap_or(config.color) {
+    ColorChoice::Always => Some(true),
+    ColorChoice::Never => Some(false),
+    // https://no-color.org, any value that isn't empty
+    ColorChoice::Auto if std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()) => Some(false),
+    // console checks for a terminal (and CLICOLOR) on each stream
+    ColorChoice::Auto => None,
+  };
+  if let Some(enabled) = enabled {
+    console::set_colors_enabled(enabled);
+    console::set_colors_enabled_stderr(enabled);
+  }
+  let _ = THEME.set(Theme::from(&config.theme));
+}
+
+pub fn success<D: Display>(value: D) -> StyledObject<D> {
+  theme().success.apply_to(value)
+}
+
+pub fn version<D: Display>(value: D) -> StyledObject<D> {
+  theme().version.apply_to(value)
+}
+
+/// For stdout, like a problem in `rvm doctor`, stderr ones go through failure
+pub fn error<D: Display>(value: D) -> StyledObject<D> {
+  theme().error.apply_to(value)
+}
+
+pub fn warn<D: Display>(value: D) -> StyledObject<D> {
+  theme().warn.apply_to(value)
+}
+
+/// Something that failed, on stderr
+pub fn failure<D: Display>(value: D) -> StyledObject<D> {
+  theme().error.clone().for_stderr().apply_to(value)
+}
+
+/// Prints "Warning: message" on stderr
+pub fn warning(message: impl Display) {
+  eprintln!("{} {}", theme().warn.clone().for_stderr().apply_to("Warning:"), message);
+}
@@ -0,0 +1,177 @@
+use std::collections::HashMap;
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use lazy_static::lazy_static;
+use reqwest::blocking::{RequestBuilder, Response};
+use reqwest::header::RETRY_AFTER;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use crate::config::Config;
+use crate::utils::parse_http_date;
+
+// keeps rvm polite with registries when it fetches a lot at once (install --jobs, upgrade --each-minor, a big prune and reinstall):
+// at most `connections` requests to a host at the same time, no more than `per_second` started each second,
+// and a 429 or 503 with Retry-After waits that long (up to `max_wait`) before trying again, every thread waiting with it.
+// a host that doesn't answer in time is tried again too. the timeouts and retries can be set per registry, an internal
+// mirror failing fast while a far away public registry gets patience:
+//   requests: {read_timeout: 120, registries: [{prefix: "https://r.corp.example/", connect_timeout: 2, retries: 0}]}
+// only the blocking client goes through here, the async one is for embedders doing their own scheduling
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RequestLimits {
+  /// requests to one host at the same time, 0 is no limit
+  pub connections: usize,
+  /// requests started per second to one host, 0 is no limit
+  pub per_second: f64,
+  /// how many times to try again when a host says to slow down
+  pub retries: u32,
+  /// the longest Retry-After to wait for, in seconds, a longer one fails right away
+  pub max_wait: u64,
+  /// seconds to wait for a connection, none for the system's own limit
+  pub connect_timeout: Option<u64>,
+  /// seconds to wait for the answer, and then for each read of its body, none for reqwest's 30. it's how long the host
+  /// may go quiet, not how long the whole download may take
+  pub read_timeout: Option<u64>,
+  /// the timeouts and retries of the urls starting with a prefix, first match wins
+  pub registries: Vec<RegistryLimits>,
+}
+
+impl Default for RequestLimits {
+  fn default() -> Self {
+    Self { connections: 4, per_second: 0.0, retries: 3, max_wait: 60, connect_timeout: None, read_timeout: None, registries: vec![] }
+  }
+}
+
+/// What a registry (or artifact host) gets instead of the defaults, what isn't set is the default
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RegistryLimits {
+  pub prefix: String,
+  pub connect_timeout: Option<u64>,
+  pub read_timeout: Option<u64>,
+  pub retries: Option<u32>,
+}
+
+/// The timeouts and retries a request gets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Network {
+  pub connect_timeout: Option<Duration>,
+  pub read_timeout: Option<Duration>,
+  pub retries: u32,
+}
+
+impl RequestLimits {
+  /// What applies to a url no registry setting matches
+  pub fn defaults(&self) -> Network {
+    Network {
+      connect_timeout: self.connect_timeout.map(Duration::from_secs),
+      read_timeout: self.read_timeout.map(Duration::from_secs),
+      retries: self.retries,
+    }
+  }
+
+  /// What applies to url, its registry's settings over the defaults
+  pub fn for_url(&self, url: &str) -> Network {
+    let registry = self.registries.iter().find(|r| url.starts_with(r.prefix.as_str()));
+    let seconds = |own: Option<u64>, default: Option<u64>| own.or(default).map(Duration::from_secs);
+    Network {
+      connect_timeout: seconds(registry.and_then(|r| r.connect_timeout), self.connect_timeout),
+      read_timeout: seconds(registry.and_then(|r| r.read_timeout), self.read_timeout),
+      retries: registry.and_then(|r| r.retries).unwrap_or(self.retries),
+    }
+  }
+}
+
+#[derive(Default)]
+struct Hosts {
+  in_flight: HashMap<String, usize>,
+  /// when the next request to a host may start
+  next_start: HashMap<String, Instant>,
+}
+
+lazy_static!{
+  static ref HOSTS: Mutex<Hosts> = Mutex::new(Hosts::default());
+  static ref FREED: Condvar = Condvar::new();
+}
+
+/// A request's place in its host's connections, given back when dropped
+/// keep it while reading the body, a download is a connection until it's done
+pub struct Permit {
+  host: String,
+}
+
+impl Drop for Permit {
+  fn drop(&mut self) {
+    let mut hosts = HOSTS.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(n) = hosts.in_flight.get_mut(&self.host) {
+      *n = n.saturating_sub(1);
+    }
+    FREED.notify_all();
+  }
+}
+
+fn acquire(host: &str, limits: &RequestLimits) -> Permit {
+  let mut hosts = HOSTS.lock().unwrap_or_else(|e| e.into_inner());
+  while limits.connections > 0 && hosts.in_flight.get(host).copied().unwrap_or(0) >= limits.connections {
+    hosts = FREED.wait(hosts).unwrap_or_else(|e| e.into_inner());
+  }
+  *hosts.in_flight.entry(host.to_owned()).or_default() += 1;
+  // take the next start slot while holding the lock, then wait for it without
+  let now = Instant::now();
+  let start = hosts.next_start.get(host).copied().filter(|s| *s > now).unwrap_or(now);
+  if limits.per_second > 0.0 {
+    hosts.next_start.insert(host.to_owned(), start + Duration::from_secs_f64(1.0 / limits.per_second));
+  }
+  drop(hosts);
+  std::thread::sleep(start.saturating_duration_since(now));
+  Permit { host: host.to_owned() }
+}
+
+// no request to host starts before until
+fn hold_off(host: &str, until: Instant) {
+  let mut hosts = HOSTS.lock().unwrap_or_else(|e| e.into_inner());
+  let next = hosts.next_start.entry(host.to_owned()).or_insert(until);
+  *next = (*next).max(until);
+}
+
+/// How long a Retry-After asks to wait, in seconds or as a date
+pub fn retry_after(value: &str, now: SystemTime) -> Option<Duration> {
+  if let Ok(seconds) = value.trim().parse::<u64>() {
+    return Some(Duration::from_secs(seconds));
+  }
+  Some(parse_http_date(value)?.duration_since(now).unwrap_or(Duration::ZERO))
+}
+
+/// Sends the request build makes for url within the config's limits, again when the host answers 429 or 503 with a Retry-After
+/// or doesn't answer in time. build is called once per try, requests can't be cloned when they have a body,
+/// and gets its client from client_for so the connect and read timeouts are url's
+pub fn send(url: &str, build: impl Fn() -> RequestBuilder) -> reqwest::Result<(Permit, Response)> {
+  let limits = Config::get().requests;
+  let network = limits.for_url(url);
+  let host = reqwest::Url::parse(url).ok().and_then(|u| u.host_str().map(str::to_owned)).unwrap_or_default();
+  let mut tries = 0;
+  loop {
+    let permit = acquire(&host, &limits);
+    let response = match build().send() {
+      Ok(response) => response,
+      Err(err) if (err.is_timeout() || err.is_connect()) && tries < network.retries => {
+        tries += 1;
+        drop(permit);
+        tracing::warn!(%host, error = %err, tries, retries = network.retries, "host didn't answer, trying again");
+        std::thread::sleep(Duration::from_secs(tries.into()));
+        continue;
+      },
+      Err(err) => return Err(err),
+    };
+    let slow_down = matches!(response.status(), StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE);
+    let wait = response.headers().get(RETRY_AFTER).and_then(|v| v.to_str().ok()).and_then(|v| retry_after(v, SystemTime::now()));
+    match wait {
+      Some(wait) if slow_down && tries < network.retries && wait <= Duration::from_secs(limits.max_wait) => {
+        tries += 1;
+        tracing::warn!(%host, status = %response.status(), ?wait, tries, retries = network.retries, "host asked to slow down, trying again");
+        hold_off(&host, Instant::now() + wait);
+      },
+      _ => return Ok((permit, response)),
+    }
+  }
+}
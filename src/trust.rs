@@ -0,0 +1,220 @@
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use crate::layout::state_dir;
+use crate::utils::env_flag;
+
+// trust on first use for registries' signing keys: an index document can be signed (`rvm index generate
+// --signing-key`), it then has the registry's ed25519 public key (`signing_key`) and the signature of its versions
+// with it (`signature`). every listing, whatever source it comes from, is checked here before it's cached: the
+// signature has to verify against the document's key, and the first key that verified for a registry is kept in
+// <state dir>/known-keys.json. from then on a document with another key fails the listing, and so does one without
+// a signature (or a changelog, which can't have one), so a mirror or registry that was taken over can neither swap
+// the key nor drop it without the user noticing. a registry that rotated (or dropped) its key on purpose is trusted
+// again with --trust-new-key, which replaces (or forgets) the kept one.
+// what's signed is the document's versions as compact json with sorted keys and without nulls (payload), so the json
+// and toml forms of an index sign the same. only the key's fingerprint is kept, the file is changed with an flock on
+// it like mirror-health.json
+
+/// set by --trust-new-key (or by hand), a registry's changed signing key replaces the known one instead of failing
+pub const TRUST_NEW_KEY_VAR: &str = "RVM_TRUST_NEW_KEY";
+
+/// Whether a changed key is trusted
+pub fn new_key_trusted() -> bool {
+  env_flag(TRUST_NEW_KEY_VAR)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KnownKey {
+  /// sha256:<hex> of the key, see fingerprint
+  pub fingerprint: String,
+  /// unix seconds it was first trusted (or trusted again with --trust-new-key)
+  pub since: u64,
+}
+
+/// The key each registry was first seen with, by the registry's name
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KnownKeys {
+  pub registries: BTreeMap<String, KnownKey>,
+}
+
+/// What checking a listing found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trust {
+  /// neither the listing nor the registry has a key
+  Unsigned,
+  /// the listing has no key but the registry had one, forgotten with --trust-new-key
+  Dropped,
+  /// the registry had no key yet, this one is kept
+  FirstUse,
+  /// the same key as before
+  Known,
+  /// another key than before, trusted with --trust-new-key
+  Replaced,
+}
+
+#[derive(Error, Debug)]
+pub enum TrustError {
+  #[error("error in reading/writing the known signing keys")]
+  Io(#[from] io::Error),
+  #[error("the signing key of {registry} changed to {seen}, {known} is the one trusted since it was first seen. \
+    if the registry rotated its key, pass --trust-new-key to trust the new one")]
+  KeyChanged { registry: String, known: String, seen: String },
+  #[error("{registry} signed its index with {known} since it was first seen, but this listing isn't signed. \
+    if the registry stopped signing, pass --trust-new-key to forget its key")]
+  Unsigned { registry: String, known: String },
+  #[error("the index of {registry} isn't signed by the key it has, {reason}")]
+  BadSignature { registry: String, reason: String },
+}
+
+/// A signed listing, the key and signature as the document has them and the bytes they sign
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signed {
+  /// ed25519:<hex> of the public key
+  pub key: String,
+  /// hex of the signature
+  pub signature: String,
+  /// see payload
+  pub payload: Vec<u8>,
+}
+
+impl Signed {
+  /// Signs payload with the key whose secret is secret, hex of its 32 bytes like `openssl rand -hex 32` makes
+  pub fn sign(secret: &str, payload: Vec<u8>) -> Result<Self, String> {
+    let secret: [u8; 32] = from_hex(secret.trim()).and_then(|b| b.try_into().ok()).ok_or("the secret key isn't 32 bytes of hex")?;
+    let key = SigningKey::from_bytes(&secret);
+    Ok(Self { key: format!("ed25519:{}", to_hex(key.verifying_key().as_bytes())), signature: to_hex(&key.sign(&payload).to_bytes()), payload })
+  }
+
+  /// Whether signature is payload's with key, why not when it isn't
+  pub fn verify(&self) -> Result<(), String> {
+    let key: [u8; 32] = self.key.trim().strip_prefix("ed25519:").and_then(from_hex).and_then(|b| b.try_into().ok())
+      .ok_or("the key isn't ed25519:<hex of 32 bytes>")?;
+    let key = VerifyingKey::from_bytes(&key).map_err(|err| err.to_string())?;
+    let signature: [u8; 64] = from_hex(self.signature.trim()).and_then(|b| b.try_into().ok()).ok_or("the signature isn't 64 bytes of hex")?;
+    key.verify(&self.payload, &Signature::from_bytes(&signature)).map_err(|_| "the signature doesn't match".to_owned())
+  }
+}
+
+/// What's signed of versions, compact json with sorted keys and without the nulls in objects
+pub fn payload(versions: &serde_json::Value) -> Vec<u8> {
+  fn canonical(value: &serde_json::Value) -> serde_json::Value {
+    match value {
+      serde_json::Value::Object(map) => {
+        let sorted: BTreeMap<&String, serde_json::Value> = map.iter().filter(|(_, v)| !v.is_null()).map(|(k, v)| (k, canonical(v))).collect();
+        serde_json::Value::Object(sorted.into_iter().map(|(k, v)| (k.clone(), v)).collect())
+      },
+      serde_json::Value::Array(items) => serde_json::Value::Array(items.iter().map(canonical).collect()),
+      other => other.clone(),
+    }
+  }
+  serde_json::to_vec(&canonical(versions)).unwrap_or_default()
+}
+
+impl KnownKeys {
+  pub fn path() -> PathBuf {
+    state_dir().join("known-keys.json")
+  }
+
+  /// What's trusted, nothing when there's no (readable) file
+  pub fn load() -> Self {
+    std::fs::read(Self::path()).ok().and_then(|bytes| serde_json::from_slice(&bytes).ok()).unwrap_or_default()
+  }
+
+  /// Checks key against the one known for registry, keeping it when there's none (or when accept_new and it changed)
+  pub fn check(&mut self, registry: &str, key: &str, accept_new: bool) -> Result<Trust, TrustError> {
+    let seen = fingerprint(key);
+    let trust = match self.registries.get(registry) {
+      None => Trust::FirstUse,
+      Some(known) if known.fingerprint == seen => return Ok(Trust::Known),
+      Some(_) if accept_new => Trust::Replaced,
+      Some(known) => return Err(TrustError::KeyChanged { registry: registry.to_owned(), known: known.fingerprint.clone(), seen }),
+    };
+    self.registries.insert(registry.to_owned(), KnownKey { fingerprint: seen, since: now() });
+    Ok(trust)
+  }
+
+  /// Checks a listing without a key from registry, failing when it had one (forgotten when accept_new)
+  pub fn check_unsigned(&mut self, registry: &str, accept_new: bool) -> Result<Trust, TrustError> {
+    match self.registries.get(registry) {
+      None => Ok(Trust::Unsigned),
+      Some(_) if accept_new => {
+        self.registries.remove(registry);
+        Ok(Trust::Dropped)
+      },
+      Some(known) => Err(TrustError::Unsigned { registry: registry.to_owned(), known: known.fingerprint.clone() }),
+    }
+  }
+}
+
+/// How a key is told apart from another, sha256:<hex> of it without the whitespace around it
+pub fn fingerprint(key: &str) -> String {
+  use sha2::{Digest, Sha256};
+  format!("sha256:{}", to_hex(&Sha256::digest(key.trim().as_bytes())))
+}
+
+/// Checks a listing registry served before it's used, see above. signed is none for one without a signature
+pub fn check(registry: &str, signed: Option<&Signed>) -> Result<Trust, TrustError> {
+  if let Some(signed) = signed {
+    signed.verify().map_err(|reason| TrustError::BadSignature { registry: registry.to_owned(), reason })?;
+  }
+  // only read, the file is only written (and locked) when there's something to keep
+  let known = KnownKeys::load().registries.contains_key(registry);
+  if signed.is_none() && !known {
+    return Ok(Trust::Unsigned);
+  }
+  let accept_new = new_key_trusted();
+  let mut result = Ok(Trust::Known);
+  update(|keys| result = match signed {
+    Some(signed) => keys.check(registry, &signed.key, accept_new),
+    None => keys.check_unsigned(registry, accept_new),
+  })?;
+  let key = signed.map(|s| s.key.as_str()).unwrap_or_default();
+  match &result {
+    Ok(Trust::FirstUse) => tracing::info!(registry, fingerprint = %fingerprint(key), "trusting the registry's signing key from now on"),
+    Ok(Trust::Replaced) => tracing::warn!(registry, fingerprint = %fingerprint(key), "the registry's signing key changed, trusting the new one"),
+    Ok(Trust::Dropped) => tracing::warn!(registry, "the registry stopped signing its index, forgetting its key"),
+    _ => {},
+  }
+  result
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+  bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(text: &str) -> Option<Vec<u8>> {
+  if text.len() % 2 != 0 || !text.is_ascii() {
+    return None;
+  }
+  (0..text.len()).step_by(2).map(|i| u8::from_str_radix(&text[i..i + 2], 16).ok()).collect()
+}
+
+fn update(change: impl FnOnce(&mut KnownKeys)) -> io::Result<()> {
+  std::fs::create_dir_all(state_dir())?;
+  let mut file: File = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(KnownKeys::path())?;
+  file.lock_exclusive()?;
+  let mut text = String::new();
+  file.read_to_string(&mut text)?;
+  let mut keys: KnownKeys = serde_json::from_str(&text).unwrap_or_default();
+  let before = keys.clone();
+  change(&mut keys);
+  if keys != before {
+    file.set_len(0)?;
+    file.seek(SeekFrom::Start(0))?;
+    file.write_all(&serde_json::to_vec_pretty(&keys).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?)?;
+    file.flush()?;
+  }
+  FileExt::unlock(&file)
+}
+
+fn now() -> u64 {
+  SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
@@ -1,13 +1,17 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::ffi::OsString;
 use std::fs::File;
 use std::io;
 use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use path_clean::PathClean;
 use reqwest::blocking::Response;
 use thiserror::Error;
 
 macro_rules! request {
   ($typof:ident, $url:expr) => {{ //2 to make an isolated scope
-    let response = CLIENT.$typof($url).send().unwrap(); //TODO maybe change unwrap here
+    let response = CLIENT.$typof($url).with_auth($url).send().unwrap(); //TODO maybe change unwrap here
     if response.status() != 200{
       return Err(response.status());
     }
@@ -15,11 +19,11 @@ macro_rules! request {
   }};
 }
 // https://georgik.rocks/how-to-download-binary-file-in-rust-by-reqwest/
-pub fn response_to_file_path(folder: PathBuf, name: String, response: Response) -> Result<PathBuf, ResponseToFileError> {
+pub fn response_to_file_path(folder: PathBuf, name: String, mut response: Response) -> Result<PathBuf, ResponseToFileError> {
   let filepath = folder.join(name);
   let mut file = File::create(&filepath)?;
-  let mut content = io::Cursor::new(response.bytes()?);
-  io::copy(&mut content, &mut file)?;
+  // read by parts, the client's timeout is then for each of them and not for all of the body
+  io::copy(&mut response, &mut file)?;
   Ok(filepath)
 }
 
@@ -52,4 +56,238 @@ impl ToAbsolute for Path {
       self.clean()
     }
   }
+}
+
+/// Wraps a reader, calling on_read with the total bytes read so far after every read
+pub struct ProgressReader<R, F> {
+  inner: R,
+  read: u64,
+  on_read: F,
+}
+
+impl<R, F: Fn(u64)> ProgressReader<R, F> {
+  pub fn new(inner: R, on_read: F) -> Self {
+    Self { inner, read: 0, on_read }
+  }
+}
+
+impl<R: io::Read, F: Fn(u64)> io::Read for ProgressReader<R, F> {
+  fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    let n = self.inner.read(buf)?;
+    if n > 0 {
+      self.read += n as u64;
+      (self.on_read)(self.read);
+    }
+    Ok(n)
+  }
+}
+
+/// Total size in bytes of the files under path (not following links)
+pub fn dir_size(path: &Path) -> u64 {
+  let Ok(metadata) = std::fs::symlink_metadata(path) else { return 0 };
+  if !metadata.is_dir() {
+    return metadata.len();
+  }
+  path.read_dir()
+    .map(|entries| entries.filter_map(|e| e.ok()).map(|e| dir_size(&e.path())).sum())
+    .unwrap_or(0)
+}
+
+/// Copies the tree under from into to, creating to (symlinks are copied as the files they point to)
+pub fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+  std::fs::create_dir_all(to)?;
+  for entry in from.read_dir()? {
+    let entry = entry?;
+    let dest = to.join(entry.file_name());
+    if entry.path().is_dir() {
+      copy_dir(&entry.path(), &dest)?;
+    } else {
+      std::fs::copy(entry.path(), dest)?;
+    }
+  }
+  Ok(())
+}
+
+/// Makes the tree under path readable by everyone (and its dirs and executables usable), for system installs
+/// the dirs between root and path too, they were created with the same umask
+/// sudo often runs with a umask that would leave them to root, on windows ProgramData's ACLs already do it
+pub fn share_tree(root: &Path, path: &Path) -> io::Result<()> {
+  for dir in path.ancestors().skip(1).take_while(|d| d.starts_with(root)) {
+    share(dir, false)?;
+  }
+  share(path, true)
+}
+
+fn share(path: &Path, recursive: bool) -> io::Result<()> {
+  #[cfg(unix)]
+  {
+    use std::os::unix::fs::PermissionsExt;
+    let metadata = std::fs::symlink_metadata(path)?;
+    if metadata.file_type().is_symlink() {
+      return Ok(());
+    }
+    let mode = metadata.permissions().mode();
+    // r for group and others, x too where the owner has it
+    let shared = mode | 0o044 | if mode & 0o100 != 0 { 0o011 } else { 0 };
+    if shared != mode {
+      std::fs::set_permissions(path, std::fs::Permissions::from_mode(shared))?;
+    }
+    if recursive && metadata.is_dir() {
+      for entry in path.read_dir()? {
+        share(&entry?.path(), true)?;
+      }
+    }
+  }
+  #[cfg(not(unix))]
+  let _ = (path, recursive);
+  Ok(())
+}
+
+/// Whether url is under prefix, another url: the same scheme, host and port, and a path that's prefix's or goes on
+/// from it after a /. so https://r.example.com/ doesn't take in https://r.example.com.evil.test/ or
+/// https://r.example.com@evil.test/, and https://r.example.com/old doesn't take in https://r.example.com/older/.
+/// none of them is when either can't be parsed
+pub fn url_under(url: &str, prefix: &str) -> bool {
+  let (Ok(url), Ok(prefix)) = (reqwest::Url::parse(url), reqwest::Url::parse(prefix)) else {
+    return false;
+  };
+  let base = prefix.path().trim_end_matches('/');
+  url.scheme() == prefix.scheme()
+    && url.host() == prefix.host()
+    && url.port_or_known_default() == prefix.port_or_known_default()
+    && url.path().strip_prefix(base).is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+thread_local!{
+  // the RVM_* variables of the client this thread answers for, see with_rvm_vars
+  static RVM_VARS: RefCell<Option<BTreeMap<String, String>>> = const { RefCell::new(None) };
+}
+
+/// The environment variable name, an RVM_* one from with_rvm_vars' instead of the process' while that runs on this
+/// thread. Everything rvm's own variables change is read through this
+pub fn env_var_os(name: &str) -> Option<OsString> {
+  if name.starts_with("RVM_") {
+    if let Some(value) = RVM_VARS.with(|vars| vars.borrow().as_ref().map(|vars| vars.get(name).map(OsString::from))) {
+      return value;
+    }
+  }
+  std::env::var_os(name)
+}
+
+/// env_var_os as a String, none when it isn't unicode
+pub fn env_var(name: &str) -> Option<String> {
+  env_var_os(name).and_then(|value| value.into_string().ok())
+}
+
+/// f with vars as every RVM_* variable there is, on this thread only: the daemon answers for a shell with the
+/// shell's variables without changing the process environment its other threads read
+pub fn with_rvm_vars<T>(vars: &BTreeMap<String, String>, f: impl FnOnce() -> T) -> T {
+  struct Restore(Option<BTreeMap<String, String>>);
+  impl Drop for Restore {
+    fn drop(&mut self) {
+      let before = self.0.take();
+      RVM_VARS.with(|vars| *vars.borrow_mut() = before);
+    }
+  }
+  let _restore = Restore(RVM_VARS.with(|own| own.replace(Some(vars.clone()))));
+  f()
+}
+
+/// Whether the boolean variable name is on, read the way clap's FalseyValueParser reads the flags bound to the same
+/// variables: unset, empty, 0, false, f, no, n and off (in any case) are off, anything else is on
+pub fn env_flag(name: &str) -> bool {
+  env_var(name).is_some_and(|v| !matches!(v.to_ascii_lowercase().as_str(), "" | "0" | "false" | "f" | "no" | "n" | "off"))
+}
+
+/// Hex sha256 of a file's contents
+pub fn sha256_file(path: &Path) -> io::Result<String> {
+  use sha2::{Digest, Sha256};
+  let mut hasher = Sha256::new();
+  io::copy(&mut std::fs::File::open(path)?, &mut hasher)?;
+  Ok(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+pub fn format_bytes(bytes: u64) -> String {
+  const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+  let mut size = bytes as f64;
+  let mut unit = 0;
+  while size >= 1024.0 && unit < UNITS.len() - 1 {
+    size /= 1024.0;
+    unit += 1;
+  }
+  if unit == 0 { format!("{} B", bytes) } else { format!("{:.1} {}", size, UNITS[unit]) }
+}
+
+/// Today in UTC as YYYY-MM-DD, the way indexes write dates
+pub fn today() -> String {
+  date_of(SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0))
+}
+
+/// Whether text is a day written YYYY-MM-DD, days written that way compare as text
+pub fn is_day(text: &str) -> bool {
+  text.len() == 10 && text.char_indices().all(|(i, c)| if i == 4 || i == 7 { c == '-' } else { c.is_ascii_digit() })
+}
+
+/// Parses a day written YYYY-MM-DD, or a duration from now (90d, 2w) as the day it ends on
+pub fn parse_day(s: &str) -> Result<String, String> {
+  let s = s.trim();
+  if is_day(s) {
+    return Ok(s.to_owned());
+  }
+  let duration = parse_duration(s).map_err(|_| format!("invalid day {} (use YYYY-MM-DD or a duration like 90d)", s))?;
+  let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+  now.checked_add(duration.as_secs()).map(date_of).ok_or_else(|| format!("invalid day {}, it's too far ahead", s))
+}
+
+/// The UTC day of unix seconds as YYYY-MM-DD
+pub fn date_of(secs: u64) -> String {
+  let days = (secs / 86400) as i64;
+  // days since 1970-01-01 to a civil date, http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+  let z = days + 719468;
+  let era = z.div_euclid(146097);
+  let doe = z.rem_euclid(146097);
+  let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+  let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+  let mp = (5 * doy + 2) / 153;
+  let day = doy - (153 * mp + 2) / 5 + 1;
+  let month = if mp < 10 { mp + 3 } else { mp - 9 };
+  let year = yoe + era * 400 + i64::from(month <= 2);
+  format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Parses the dates of http headers, Sun, 06 Nov 1994 08:49:37 GMT
+pub fn parse_http_date(date: &str) -> Option<SystemTime> {
+  const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+  let parts: Vec<&str> = date.split_whitespace().collect();
+  let [_, day, month, year, time, "GMT"] = parts.as_slice() else { return None };
+  let day: i64 = day.parse().ok()?;
+  let month = MONTHS.iter().position(|m| m == month)? as i64 + 1;
+  let year: i64 = year.parse().ok()?;
+  let time: Vec<u64> = time.split(':').map(|n| n.parse().ok()).collect::<Option<_>>()?;
+  let [hours, minutes, seconds] = time.as_slice() else { return None };
+  // the other way of today's, http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+  let y = if month <= 2 { year - 1 } else { year };
+  let era = y.div_euclid(400);
+  let yoe = y.rem_euclid(400);
+  let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+  let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+  let days = u64::try_from(era * 146097 + doe - 719468).ok()?;
+  Some(UNIX_EPOCH + Duration::from_secs(days * 86400 + hours * 3600 + minutes * 60 + seconds))
+}
+
+/// Parses durations like 90d, 12h, 2w or 30m (a bare number is seconds)
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+  let s = s.trim();
+  let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+  let (number, unit) = s.split_at(split);
+  let number: u64 = number.parse().map_err(|_| format!("invalid duration {}", s))?;
+  let seconds = match unit {
+    "" | "s" => 1,
+    "m" => 60,
+    "h" => 60 * 60,
+    "d" => 60 * 60 * 24,
+    "w" => 60 * 60 * 24 * 7,
+    _ => return Err(format!("invalid duration unit {} (use s, m, h, d or w)", unit)),
+  };
+  number.checked_mul(seconds).map(Duration::from_secs).ok_or_else(|| format!("invalid duration {}, it's too long", s))
 }
\ No newline at end of file